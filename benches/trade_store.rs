@@ -0,0 +1,143 @@
+//! Shadow settle-tick queries against a full `TradeStore`: the per-token time index against the
+//! previous linear scan over every retained trade. Each iteration answers `window_stats` plus one
+//! `volume_at_or_better_price` per leg for every pending signal, as one 50 ms settle tick does.
+//!
+//! `cargo bench --bench trade_store`; `RAZOR_BENCH_PENDING=<n>` changes the pending signal count.
+
+use std::hint::black_box;
+
+use criterion::{criterion_group, criterion_main, Criterion, Throughput};
+use razor::clock::SimClock;
+use razor::trade_store::TradeStore;
+use razor::types::{MarketId, Price, Qty, TokenId, TradeTick};
+
+const MARKETS: u64 = 200;
+const TRADES: u64 = 50_000;
+const SPAN_MS: u64 = 60_000;
+const START_MS: u64 = 1_700_000_000_000;
+const WINDOW_MS: u64 = 1_000;
+
+fn trades() -> Vec<TradeTick> {
+    (0..TRADES)
+        .map(|i| {
+            let ts = START_MS + i * SPAN_MS / TRADES;
+            TradeTick {
+                ts_ms: ts,
+                ingest_ts_ms: ts,
+                exchange_ts_ms: Some(ts),
+                market_id: format!("m{}", i % MARKETS).into(),
+                token_id: format!("m{}-{}", i % MARKETS, (i / MARKETS) % 2).into(),
+                price: Price::from_f64(0.40 + (i % 20) as f64 / 100.0).unwrap(),
+                size: Qty::from_f64(1.0 + (i % 7) as f64).unwrap(),
+                trade_id: format!("t{i}"),
+                side: None,
+            }
+        })
+        .collect()
+}
+
+/// `(market, legs, window start)` per pending signal.
+fn pending() -> Vec<(MarketId, [TokenId; 2], u64)> {
+    let n: u64 = std::env::var("RAZOR_BENCH_PENDING")
+        .ok()
+        .and_then(|v| v.parse().ok())
+        .unwrap_or(2_000);
+    (0..n)
+        .map(|i| {
+            let m = i % MARKETS;
+            (
+                format!("m{m}").into(),
+                [format!("m{m}-0").into(), format!("m{m}-1").into()],
+                START_MS + (i * 7_919) % (SPAN_MS - WINDOW_MS),
+            )
+        })
+        .collect()
+}
+
+/// The pre-index store queries: filter every retained trade per call.
+mod linear_scan {
+    use razor::types::{MarketId, Price, Qty, TokenId, TradeTick};
+
+    pub fn volume_at_or_better_price(
+        trades: &[TradeTick],
+        market_id: &MarketId,
+        token_id: &TokenId,
+        start_ms: u64,
+        end_ms: u64,
+        price_limit: Price,
+    ) -> Qty {
+        trades
+            .iter()
+            .filter(|t| &t.market_id == market_id && &t.token_id == token_id)
+            .filter(|t| t.ingest_ts_ms >= start_ms && t.ingest_ts_ms <= end_ms)
+            .filter(|t| t.price <= price_limit)
+            .map(|t| t.size)
+            .sum()
+    }
+
+    pub fn trades_in_window(
+        trades: &[TradeTick],
+        market_id: &MarketId,
+        start_ms: u64,
+        end_ms: u64,
+    ) -> usize {
+        let mut ts: Vec<u64> = trades
+            .iter()
+            .filter(|t| &t.market_id == market_id)
+            .map(|t| t.ingest_ts_ms)
+            .filter(|ts| *ts >= start_ms && *ts <= end_ms)
+            .collect();
+        ts.sort_unstable();
+        ts.len()
+    }
+}
+
+fn settle_tick(c: &mut Criterion) {
+    let trades = trades();
+    let pending = pending();
+    let limit = Price::from_f64(0.5).unwrap();
+
+    let clock = SimClock::starting_at_ms(START_MS + SPAN_MS);
+    let mut store = TradeStore::new_with_cap(2 * SPAN_MS, usize::MAX).with_clock(clock);
+    for t in &trades {
+        let _ = store.push(t.clone());
+    }
+
+    let mut group = c.benchmark_group("trade_store_settle_tick");
+    group.throughput(Throughput::Elements(pending.len() as u64));
+    group.sample_size(10);
+    group.bench_function("linear_scan", |b| {
+        b.iter(|| {
+            let mut acc = 0.0;
+            for (m, legs, start) in &pending {
+                let end = start + WINDOW_MS;
+                acc += linear_scan::trades_in_window(&trades, m, *start, end) as f64;
+                for tok in legs {
+                    acc +=
+                        linear_scan::volume_at_or_better_price(&trades, m, tok, *start, end, limit)
+                            .to_f64();
+                }
+            }
+            black_box(acc)
+        })
+    });
+    group.bench_function("time_index", |b| {
+        b.iter(|| {
+            let mut acc = 0.0;
+            for (m, legs, start) in &pending {
+                let end = start + WINDOW_MS;
+                acc += store.window_stats(m, *start, end).trades_in_window as f64;
+                for tok in legs {
+                    acc += store
+                        .volume_at_or_better_price(m, tok, *start, end, limit)
+                        .to_f64();
+                }
+            }
+            black_box(acc)
+        })
+    });
+    group.finish();
+}
+
+criterion_group!(benches, settle_tick);
+criterion_main!(benches);
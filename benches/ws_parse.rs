@@ -0,0 +1,127 @@
+//! WS frame decoding throughput on recorded `raw_ws.jsonl` data: the typed `ws_msg` decoder
+//! against the previous `serde_json::Value` walk. Both reduce every frame to the same top-of-book
+//! numbers the feed derives from it.
+//!
+//! `cargo bench --bench ws_parse` uses `tests/fixtures/raw_ws_small/raw_ws.jsonl`; set
+//! `RAZOR_BENCH_RAW_WS=<run_dir>/raw_ws.jsonl` to bench a real capture.
+
+use std::hint::black_box;
+
+use criterion::{criterion_group, criterion_main, Criterion, Throughput};
+use razor::ws_msg::{self, ask_depth3_usdc, best_level, PriceSide, WsEvent};
+
+const FIXTURE: &str = concat!(
+    env!("CARGO_MANIFEST_DIR"),
+    "/tests/fixtures/raw_ws_small/raw_ws.jsonl"
+);
+
+fn load_frames() -> Vec<String> {
+    let path = std::env::var("RAZOR_BENCH_RAW_WS").unwrap_or_else(|_| FIXTURE.to_string());
+    let text = std::fs::read_to_string(&path).unwrap_or_else(|e| panic!("read {path}: {e}"));
+    text.lines()
+        .filter(|l| !l.trim().is_empty())
+        .map(str::to_string)
+        .collect()
+}
+
+fn typed(frame: &str) -> f64 {
+    let mut acc = 0.0;
+    let _ = ws_msg::for_each_event(frame, |ev| {
+        match ev {
+            WsEvent::Book(book) => {
+                acc += best_level(&book.bids, PriceSide::Bid).map_or(0.0, |l| l.0);
+                acc += best_level(&book.asks, PriceSide::Ask).map_or(1.0, |l| l.0);
+                acc += ask_depth3_usdc(&book.asks);
+            }
+            WsEvent::PriceChange(pc) => {
+                for ch in &pc.price_changes {
+                    acc += ch.best_bid.unwrap_or(0.0) + ch.best_ask.unwrap_or(0.0);
+                }
+            }
+            WsEvent::LastTrade(_) | WsEvent::Other => {}
+        }
+        Ok::<_, ()>(())
+    });
+    acc
+}
+
+/// The pre-`ws_msg` decoder: a full `Value` tree per frame, fields looked up by key.
+mod value_walk {
+    use serde_json::Value;
+
+    fn parse_f64(v: Option<&Value>) -> Option<f64> {
+        let v = v?;
+        if let Some(s) = v.as_str() {
+            return s.parse::<f64>().ok();
+        }
+        v.as_f64()
+    }
+
+    fn best(levels: &[Value], bid: bool) -> Option<f64> {
+        levels
+            .iter()
+            .filter_map(|l| parse_f64(l.get("price")).filter(|p| p.is_finite() && *p > 0.0))
+            .reduce(|a, b| if (b > a) == bid { b } else { a })
+    }
+
+    fn depth3(levels: &[Value]) -> f64 {
+        let mut lv: Vec<(f64, f64)> = levels
+            .iter()
+            .filter_map(|l| Some((parse_f64(l.get("price"))?, parse_f64(l.get("size"))?)))
+            .filter(|(p, s)| p.is_finite() && *p > 0.0 && s.is_finite() && *s > 0.0)
+            .collect();
+        lv.sort_by(|a, b| a.0.total_cmp(&b.0));
+        lv.iter().take(3).map(|(p, s)| p * s).sum()
+    }
+
+    fn event(obj: &serde_json::Map<String, Value>) -> f64 {
+        let levels = |k: &str| obj.get(k).and_then(Value::as_array).map_or(&[][..], |v| v);
+        match obj.get("event_type").and_then(Value::as_str) {
+            Some("book") => {
+                let asks = levels("asks");
+                best(levels("bids"), true).unwrap_or(0.0)
+                    + best(asks, false).unwrap_or(1.0)
+                    + depth3(asks)
+            }
+            Some("price_change") => levels("price_changes")
+                .iter()
+                .map(|ch| {
+                    parse_f64(ch.get("best_bid")).unwrap_or(0.0)
+                        + parse_f64(ch.get("best_ask")).unwrap_or(0.0)
+                })
+                .sum(),
+            _ => 0.0,
+        }
+    }
+
+    pub fn frame(txt: &str) -> f64 {
+        match serde_json::from_str::<Value>(txt) {
+            Ok(Value::Array(items)) => items.iter().filter_map(Value::as_object).map(event).sum(),
+            Ok(Value::Object(obj)) => event(&obj),
+            _ => 0.0,
+        }
+    }
+}
+
+fn ws_parse(c: &mut Criterion) {
+    let frames = load_frames();
+    let bytes: usize = frames.iter().map(String::len).sum();
+
+    let mut group = c.benchmark_group("ws_parse");
+    group.throughput(Throughput::Bytes(bytes as u64));
+    group.bench_function("value_walk", |b| {
+        b.iter(|| {
+            frames
+                .iter()
+                .map(|f| value_walk::frame(black_box(f)))
+                .sum::<f64>()
+        })
+    });
+    group.bench_function("typed", |b| {
+        b.iter(|| frames.iter().map(|f| typed(black_box(f))).sum::<f64>())
+    });
+    group.finish();
+}
+
+criterion_group!(benches, ws_parse);
+criterion_main!(benches);
@@ -0,0 +1,171 @@
+//! Frozen Spec shadow accounting for one signal: matched sets merge at `1 - FEE_MERGE`, and the
+//! unmatched remainder of each leg is dumped at its signal-time best bid less the assumed
+//! slippage. Live shadow, replay and the sweeps all settle through [`settle_set`], so embedding
+//! tools get the same numbers as `shadow_log.csv`. The rates in the `FEE_POLY` / `FEE_MERGE`
+//! slots come from the fee model in force for the row ([`FeeRates::FROZEN`] by default).
+
+use serde::{Deserialize, Serialize};
+
+use crate::fees::FeeRates;
+
+/// One leg as seen by the settlement: its limit, the best bid at signal time (the dump price
+/// before slippage) and the market volume traded at or better than the limit inside the window.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SetLeg {
+    pub p_limit: f64,
+    pub best_bid: f64,
+    pub v_mkt: f64,
+}
+
+/// Per-signal result; field names match the `shadow_log.csv` columns.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SetSettlement {
+    /// Filled quantity per leg, in input order.
+    pub q_fill: Vec<f64>,
+    pub q_set: f64,
+    pub cost_set: f64,
+    pub proceeds_set: f64,
+    pub pnl_set: f64,
+    pub pnl_left_total: f64,
+    pub total_pnl: f64,
+    pub q_fill_avg: f64,
+    pub set_ratio: f64,
+}
+
+/// Settles one signal of `legs` with `q_req` requested per leg.
+///
+/// `q_fill = min(q_req, v_mkt * fill_share)` per leg and `q_set` is the smallest fill. A missing
+/// (non-positive or non-finite) bid dumps at 0. Inputs are otherwise used as given: an invalid
+/// limit yields a NaN cost, which callers flag through their own reason codes.
+#[allow(dead_code)]
+pub fn settle_set(
+    q_req: f64,
+    legs: &[SetLeg],
+    fill_share: f64,
+    dump_slippage_assumed: f64,
+    fees: FeeRates,
+) -> SetSettlement {
+    settle_set_per_leg(
+        q_req,
+        legs,
+        &vec![fill_share; legs.len()],
+        dump_slippage_assumed,
+        fees,
+    )
+}
+
+/// [`settle_set`] with one fill share per leg (`fill_shares[i]` for `legs[i]`), for per-leg
+/// buckets; the formula is otherwise the same. A leg without a share fills nothing.
+pub fn settle_set_per_leg(
+    q_req: f64,
+    legs: &[SetLeg],
+    fill_shares: &[f64],
+    dump_slippage_assumed: f64,
+    fees: FeeRates,
+) -> SetSettlement {
+    let q_fill: Vec<f64> = legs
+        .iter()
+        .enumerate()
+        .map(|(i, l)| (l.v_mkt * fill_shares.get(i).copied().unwrap_or(0.0)).min(q_req))
+        .collect();
+
+    let q_set = q_fill
+        .iter()
+        .copied()
+        .fold(f64::INFINITY, f64::min)
+        .min(q_req);
+    let q_set = if q_set.is_finite() { q_set } else { 0.0 };
+
+    let cost_set_per_unit: f64 = legs.iter().map(|l| fees.taker.apply_cost(l.p_limit)).sum();
+    let cost_set = q_set * cost_set_per_unit;
+    let proceeds_set = q_set * fees.merge.apply_proceeds(1.0);
+    let pnl_set = proceeds_set - cost_set;
+
+    let mut pnl_left_total: f64 = 0.0;
+    for (leg, q) in legs.iter().zip(&q_fill) {
+        let q_left = q - q_set;
+        if q_left <= 0.0 {
+            continue;
+        }
+        let exit_price = leg.best_bid.max(0.0) * (1.0 - dump_slippage_assumed);
+        let proceeds_left_per_unit = fees.taker.apply_proceeds(exit_price);
+        let cost_left_per_unit = fees.taker.apply_cost(leg.p_limit);
+        pnl_left_total += q_left * (proceeds_left_per_unit - cost_left_per_unit);
+    }
+
+    let total_pnl = pnl_set + pnl_left_total;
+    let q_fill_avg = if legs.is_empty() {
+        0.0
+    } else {
+        q_fill.iter().sum::<f64>() / (legs.len() as f64)
+    };
+    let set_ratio = if q_fill_avg > 0.0 {
+        q_set / q_fill_avg
+    } else {
+        0.0
+    };
+
+    SetSettlement {
+        q_fill,
+        q_set,
+        cost_set,
+        proceeds_set,
+        pnl_set,
+        pnl_left_total,
+        total_pnl,
+        q_fill_avg,
+        set_ratio,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use assert_approx_eq::assert_approx_eq;
+
+    #[test]
+    fn settles_matched_sets_and_dumps_the_leftover_leg() {
+        let legs = [
+            SetLeg {
+                p_limit: 0.5,
+                best_bid: 0.48,
+                v_mkt: 100.0,
+            },
+            SetLeg {
+                p_limit: 0.45,
+                best_bid: 0.0,
+                v_mkt: 40.0,
+            },
+        ];
+        let s = settle_set(20.0, &legs, 0.25, 0.05, FeeRates::FROZEN);
+
+        assert_eq!(s.q_fill, vec![20.0, 10.0]);
+        assert_approx_eq!(s.q_set, 10.0);
+        assert_approx_eq!(s.cost_set, 10.0 * (0.5 * 1.02 + 0.45 * 1.02));
+        assert_approx_eq!(s.proceeds_set, 10.0 * 0.999);
+        let pnl_left = 10.0 * (0.48 * 0.95 * 0.98 - 0.5 * 1.02);
+        assert_approx_eq!(s.pnl_left_total, pnl_left);
+        assert_approx_eq!(s.total_pnl, s.pnl_set + pnl_left);
+        assert_approx_eq!(s.q_fill_avg, 15.0);
+        assert_approx_eq!(s.set_ratio, 10.0 / 15.0);
+
+        // Equal per-leg shares are the single-share settlement; a liquid second leg fills more.
+        assert_eq!(
+            settle_set_per_leg(20.0, &legs, &[0.25, 0.25], 0.05, FeeRates::FROZEN),
+            s
+        );
+        let per_leg = settle_set_per_leg(20.0, &legs, &[0.25, 0.5], 0.05, FeeRates::FROZEN);
+        assert_eq!(per_leg.q_fill, vec![20.0, 20.0]);
+        assert_approx_eq!(per_leg.set_ratio, 1.0);
+
+        // Another fee model only changes the rates in the fee slots.
+        let cheap = FeeRates {
+            taker: crate::types::Bps::new(100),
+            merge: crate::types::Bps::ZERO,
+        };
+        let c = settle_set(20.0, &legs, 0.25, 0.05, cheap);
+        assert_eq!(c.q_fill, s.q_fill);
+        assert_approx_eq!(c.cost_set, 10.0 * (0.5 * 1.01 + 0.45 * 1.01));
+        assert_approx_eq!(c.proceeds_set, 10.0);
+    }
+}
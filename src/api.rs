@@ -0,0 +1,535 @@
+use std::collections::{HashMap, VecDeque};
+use std::sync::{Arc, Mutex, RwLock};
+
+use axum::extract::ws::{Message, WebSocket, WebSocketUpgrade};
+use axum::extract::{Query, State};
+use axum::response::Response;
+use axum::routing::get;
+use axum::{Json, Router};
+use serde::{Deserialize, Serialize};
+use tokio::net::TcpListener;
+use tokio::sync::{broadcast, watch};
+
+use crate::config::Config;
+use crate::health::HealthCounters;
+use crate::reasons::format_notes;
+use crate::types::{now_ms, MarketId, MarketSnapshot, Price, Qty, Signal, SignalId, TokenId};
+
+/// Shared, read-mostly view of a running process for the HTTP API. Producers only touch it when
+/// the API is enabled (`Option<Arc<RunState>>`), so the hot paths stay unchanged otherwise.
+pub struct RunState {
+    run_id: String,
+    mode: String,
+    start_ts_ms: u64,
+    /// The run's config with `RAZOR__*` overrides and SIGHUP reloads applied.
+    config: watch::Receiver<Config>,
+    recent_cap: usize,
+    snapshots: RwLock<HashMap<MarketId, SnapshotView>>,
+    recent_signals: Mutex<VecDeque<SignalView>>,
+    pending_shadow: RwLock<Vec<PendingView>>,
+    events: broadcast::Sender<ApiEvent>,
+}
+
+/// Bump when the `/ws` event shape changes incompatibly.
+pub const API_EVENT_SCHEMA_VERSION: u32 = 1;
+const API_EVENT_BUFFER: usize = 1_024;
+
+/// One `/ws` message: `{"v":1,"ts_ms":...,"type":"signal"|"settlement"|"lagged",...}`.
+#[derive(Debug, Clone, Serialize)]
+pub struct ApiEvent {
+    pub v: u32,
+    pub ts_ms: u64,
+    #[serde(flatten)]
+    pub kind: ApiEventKind,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ApiEventKind {
+    Signal(SignalView),
+    Settlement(SettlementView),
+    /// The subscriber fell behind and `skipped` events were dropped for it.
+    Lagged {
+        skipped: u64,
+    },
+}
+
+impl ApiEvent {
+    fn new(kind: ApiEventKind) -> Self {
+        Self {
+            v: API_EVENT_SCHEMA_VERSION,
+            ts_ms: now_ms(),
+            kind,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct LegView {
+    pub token_id: TokenId,
+    pub best_bid: Price,
+    pub best_ask: Price,
+    pub ask_depth3_usdc: f64,
+    pub ts_recv_us: u64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct SnapshotView {
+    pub market_id: MarketId,
+    pub legs: Vec<LegView>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct SignalView {
+    pub signal_id: SignalId,
+    pub signal_ts_ms: u64,
+    pub market_id: MarketId,
+    pub strategy: &'static str,
+    pub bucket: &'static str,
+    pub q_req: Qty,
+    pub raw_edge_bps: i32,
+    pub expected_net_bps: i32,
+    pub notes: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct SettlementView {
+    pub signal_id: SignalId,
+    pub market_id: MarketId,
+    pub signal_ts_ms: u64,
+    pub q_set: f64,
+    pub total_pnl: f64,
+    pub set_ratio: f64,
+    pub notes: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct PendingView {
+    pub signal_id: SignalId,
+    pub market_id: MarketId,
+    pub signal_ts_ms: u64,
+    pub settle_after_ms: u64,
+}
+
+impl From<&MarketSnapshot> for SnapshotView {
+    fn from(s: &MarketSnapshot) -> Self {
+        Self {
+            market_id: s.market_id.clone(),
+            legs: s
+                .legs
+                .iter()
+                .map(|l| LegView {
+                    token_id: l.token_id.clone(),
+                    best_bid: l.best_bid,
+                    best_ask: l.best_ask,
+                    ask_depth3_usdc: l.ask_depth3_usdc,
+                    ts_recv_us: l.ts_recv_us,
+                })
+                .collect(),
+        }
+    }
+}
+
+impl From<&Signal> for SignalView {
+    fn from(s: &Signal) -> Self {
+        Self {
+            signal_id: s.signal_id,
+            signal_ts_ms: s.signal_ts_ms,
+            market_id: s.market_id.clone(),
+            strategy: s.strategy.as_str(),
+            bucket: s.bucket.as_str(),
+            q_req: s.q_req,
+            raw_edge_bps: s.raw_edge_bps.raw(),
+            expected_net_bps: s.expected_net_bps.raw(),
+            notes: format_notes(&s.reasons),
+        }
+    }
+}
+
+impl RunState {
+    pub fn new(
+        run_id: String,
+        mode: String,
+        start_ts_ms: u64,
+        config: watch::Receiver<Config>,
+        recent_cap: usize,
+    ) -> Self {
+        Self {
+            run_id,
+            mode,
+            start_ts_ms,
+            config,
+            recent_cap: recent_cap.max(1),
+            snapshots: RwLock::new(HashMap::new()),
+            recent_signals: Mutex::new(VecDeque::new()),
+            pending_shadow: RwLock::new(Vec::new()),
+            events: broadcast::channel(API_EVENT_BUFFER).0,
+        }
+    }
+
+    fn publish(&self, kind: ApiEventKind) {
+        // No subscribers is the common case; `send` only errors then.
+        let _ = self.events.send(ApiEvent::new(kind));
+    }
+
+    pub fn record_settlement(&self, settlement: SettlementView) {
+        self.publish(ApiEventKind::Settlement(settlement));
+    }
+
+    pub fn record_snapshot(&self, snap: &MarketSnapshot) {
+        let mut map = self.snapshots.write().unwrap_or_else(|e| e.into_inner());
+        map.insert(snap.market_id.clone(), SnapshotView::from(snap));
+    }
+
+    pub fn record_signal(&self, sig: &Signal) {
+        let mut q = self
+            .recent_signals
+            .lock()
+            .unwrap_or_else(|e| e.into_inner());
+        let view = SignalView::from(sig);
+        q.push_back(view.clone());
+        while q.len() > self.recent_cap {
+            q.pop_front();
+        }
+        drop(q);
+        self.publish(ApiEventKind::Signal(view));
+    }
+
+    pub fn set_pending_shadow(&self, pending: &[Signal], cfg: &Config) {
+        let views = pending
+            .iter()
+            .map(|s| PendingView {
+                signal_id: s.signal_id,
+                market_id: s.market_id.clone(),
+                signal_ts_ms: s.signal_ts_ms,
+                settle_after_ms: s.signal_ts_ms + cfg.market(&s.market_id).window_end_ms,
+            })
+            .collect();
+        *self
+            .pending_shadow
+            .write()
+            .unwrap_or_else(|e| e.into_inner()) = views;
+    }
+
+    fn snapshots(&self) -> Vec<SnapshotView> {
+        let map = self.snapshots.read().unwrap_or_else(|e| e.into_inner());
+        let mut out: Vec<SnapshotView> = map.values().cloned().collect();
+        out.sort_by(|a, b| a.market_id.cmp(&b.market_id));
+        out
+    }
+
+    /// Newest first.
+    fn recent_signals(&self, limit: usize) -> Vec<SignalView> {
+        let q = self
+            .recent_signals
+            .lock()
+            .unwrap_or_else(|e| e.into_inner());
+        q.iter().rev().take(limit).cloned().collect()
+    }
+
+    /// The live config as JSON, with credentials redacted like the run-dir snapshot.
+    fn config_view(&self) -> serde_json::Value {
+        let cfg = self.config.borrow().clone();
+        toml::Value::try_from(&cfg)
+            .ok()
+            .and_then(|mut doc| {
+                crate::config::redact_secrets(&mut doc);
+                serde_json::to_value(doc).ok()
+            })
+            .unwrap_or(serde_json::Value::Null)
+    }
+
+    fn pending_shadow(&self) -> Vec<PendingView> {
+        self.pending_shadow
+            .read()
+            .unwrap_or_else(|e| e.into_inner())
+            .clone()
+    }
+}
+
+#[derive(Clone)]
+struct ApiCtx {
+    state: Arc<RunState>,
+    health: Arc<HealthCounters>,
+    shutdown: watch::Receiver<bool>,
+}
+
+#[derive(Debug, Deserialize)]
+struct LimitQuery {
+    limit: Option<usize>,
+}
+
+pub fn router(
+    state: Arc<RunState>,
+    health: Arc<HealthCounters>,
+    shutdown: watch::Receiver<bool>,
+) -> Router {
+    Router::new()
+        .route("/", get(index))
+        .route("/health", get(health_handler))
+        .route("/snapshots", get(snapshots_handler))
+        .route("/signals", get(signals_handler))
+        .route("/shadow/pending", get(pending_handler))
+        .route("/config", get(config_handler))
+        .route("/ws", get(ws_handler))
+        .with_state(ApiCtx {
+            state,
+            health,
+            shutdown,
+        })
+}
+
+/// Serves the API until shutdown. Also mirrors `snap_rx` into `RunState` so `/snapshots` keeps
+/// the latest book per market (the watch channel itself only holds the most recent one).
+pub async fn serve(
+    listener: TcpListener,
+    state: Arc<RunState>,
+    health: Arc<HealthCounters>,
+    mut snap_rx: watch::Receiver<Option<MarketSnapshot>>,
+    mut shutdown: watch::Receiver<bool>,
+) -> anyhow::Result<()> {
+    let ingest_state = state.clone();
+    let mut ingest_shutdown = shutdown.clone();
+    let ingest = async move {
+        loop {
+            tokio::select! {
+                _ = ingest_shutdown.changed() => {
+                    if *ingest_shutdown.borrow() { break; }
+                }
+                res = snap_rx.changed() => {
+                    if res.is_err() { break; }
+                    if let Some(s) = snap_rx.borrow_and_update().as_ref() {
+                        ingest_state.record_snapshot(s);
+                    }
+                }
+            }
+        }
+    };
+
+    let app = router(state, health, shutdown.clone());
+    let server = axum::serve(listener, app).with_graceful_shutdown(async move {
+        while !*shutdown.borrow() {
+            if shutdown.changed().await.is_err() {
+                break;
+            }
+        }
+    });
+
+    let (res, ()) = tokio::join!(server, ingest);
+    res?;
+    Ok(())
+}
+
+async fn index(State(ctx): State<ApiCtx>) -> Json<serde_json::Value> {
+    Json(serde_json::json!({
+        "run_id": ctx.state.run_id,
+        "mode": ctx.state.mode,
+        "start_ts_ms": ctx.state.start_ts_ms,
+        "uptime_ms": now_ms().saturating_sub(ctx.state.start_ts_ms),
+        "endpoints": ["/health", "/snapshots", "/signals?limit=N", "/shadow/pending", "/config", "/ws"],
+        "ws_schema_version": API_EVENT_SCHEMA_VERSION,
+    }))
+}
+
+async fn health_handler(State(ctx): State<ApiCtx>) -> Json<serde_json::Value> {
+    let markets: Vec<serde_json::Value> = ctx
+        .health
+        .market_freshness()
+        .into_iter()
+        .map(|m| {
+            serde_json::json!({
+                "market_id": m.market_id,
+                "last_tick_ingest_ms": m.last_tick_ingest_ms,
+                "last_trade_ingest_ms": m.last_trade_ingest_ms,
+            })
+        })
+        .collect();
+    Json(serde_json::json!({
+        "counters": ctx.health.snapshot(),
+        "markets": markets,
+    }))
+}
+
+async fn snapshots_handler(State(ctx): State<ApiCtx>) -> Json<Vec<SnapshotView>> {
+    Json(ctx.state.snapshots())
+}
+
+async fn signals_handler(
+    State(ctx): State<ApiCtx>,
+    Query(q): Query<LimitQuery>,
+) -> Json<Vec<SignalView>> {
+    let limit = q.limit.unwrap_or(50).min(ctx.state.recent_cap);
+    Json(ctx.state.recent_signals(limit))
+}
+
+async fn pending_handler(State(ctx): State<ApiCtx>) -> Json<Vec<PendingView>> {
+    Json(ctx.state.pending_shadow())
+}
+
+async fn config_handler(State(ctx): State<ApiCtx>) -> Json<serde_json::Value> {
+    Json(ctx.state.config_view())
+}
+
+async fn ws_handler(ws: WebSocketUpgrade, State(ctx): State<ApiCtx>) -> Response {
+    let rx = ctx.state.events.subscribe();
+    ws.on_upgrade(move |socket| push_events(socket, rx, ctx.shutdown))
+}
+
+/// Streams `ApiEvent`s to one client until it disconnects or the run shuts down (graceful
+/// shutdown waits for open connections, so the socket must close itself).
+async fn push_events(
+    mut socket: WebSocket,
+    mut rx: broadcast::Receiver<ApiEvent>,
+    mut shutdown: watch::Receiver<bool>,
+) {
+    loop {
+        let ev = tokio::select! {
+            _ = shutdown.changed() => {
+                if *shutdown.borrow() { break; }
+                continue;
+            }
+            incoming = socket.recv() => {
+                match incoming {
+                    Some(Ok(Message::Close(_))) | None | Some(Err(_)) => return,
+                    Some(Ok(_)) => continue,
+                }
+            }
+            ev = rx.recv() => match ev {
+                Ok(ev) => ev,
+                Err(broadcast::error::RecvError::Lagged(skipped)) => {
+                    ApiEvent::new(ApiEventKind::Lagged { skipped })
+                }
+                Err(broadcast::error::RecvError::Closed) => break,
+            },
+        };
+        let Ok(txt) = serde_json::to_string(&ev) else {
+            continue;
+        };
+        if socket.send(Message::Text(txt.into())).await.is_err() {
+            return;
+        }
+    }
+    let _ = socket.send(Message::Close(None)).await;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::LegSnapshot;
+
+    async fn get_json(app: Router, uri: &str) -> serde_json::Value {
+        use tower::ServiceExt as _;
+        let req = axum::http::Request::get(uri)
+            .body(axum::body::Body::empty())
+            .unwrap();
+        let res = app.oneshot(req).await.unwrap();
+        assert_eq!(res.status(), axum::http::StatusCode::OK, "{uri}");
+        let body = axum::body::to_bytes(res.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        serde_json::from_slice(&body).unwrap()
+    }
+
+    #[tokio::test]
+    async fn routes_serve_the_run_state_without_secrets() {
+        let raw = include_str!("../config.example.toml").replacen(
+            "ws_base = \"wss://",
+            "ws_base = \"wss://user:hunter2@",
+            1,
+        );
+        let (cfg, _, _) = crate::config::parse_with_env_overrides(&raw).unwrap();
+        let (_cfg_tx, cfg_rx) = watch::channel(cfg);
+        let state = Arc::new(RunState::new(
+            "run_api".into(),
+            "shadow".into(),
+            1,
+            cfg_rx,
+            10,
+        ));
+        state.record_snapshot(&MarketSnapshot {
+            market_id: "m1".into(),
+            legs: vec![LegSnapshot {
+                token_id: "t0".into(),
+                best_ask: Price::from_f64(0.48).unwrap(),
+                best_bid: Price::from_f64(0.47).unwrap(),
+                best_ask_size_best: Qty::ZERO,
+                best_bid_size_best: Qty::ZERO,
+                ask_depth3_usdc: 250.0,
+                asks: Default::default(),
+                ts_recv_us: 7,
+            }],
+        });
+        let health = Arc::new(HealthCounters::default());
+        health.track_markets(["m1"]);
+        let (_shutdown_tx, shutdown_rx) = watch::channel(false);
+        let app = router(state, health, shutdown_rx);
+
+        let index = get_json(app.clone(), "/").await;
+        assert_eq!(index["run_id"], "run_api");
+        assert_eq!(index["ws_schema_version"], API_EVENT_SCHEMA_VERSION);
+
+        let health = get_json(app.clone(), "/health").await;
+        assert!(health["counters"].is_object());
+        assert_eq!(health["markets"][0]["market_id"], "m1");
+
+        let snaps = get_json(app.clone(), "/snapshots").await;
+        assert_eq!(snaps[0]["market_id"], "m1");
+        assert_eq!(snaps[0]["legs"][0]["token_id"], "t0");
+
+        assert_eq!(
+            get_json(app.clone(), "/signals?limit=5").await,
+            serde_json::json!([])
+        );
+        assert_eq!(
+            get_json(app.clone(), "/shadow/pending").await,
+            serde_json::json!([])
+        );
+
+        let config = get_json(app, "/config").await;
+        assert_eq!(config["polymarket"]["ws_base"], "<redacted>");
+        assert!(!config.to_string().contains("hunter2"), "{config}");
+    }
+
+    #[test]
+    fn ws_events_are_flat_and_versioned() {
+        let ev = ApiEvent::new(ApiEventKind::Settlement(SettlementView {
+            signal_id: SignalId(7),
+            market_id: "m".into(),
+            signal_ts_ms: 1,
+            q_set: 2.0,
+            total_pnl: 0.5,
+            set_ratio: 1.0,
+            notes: String::new(),
+        }));
+        let v = serde_json::to_value(&ev).expect("serialize");
+        assert_eq!(v["v"], API_EVENT_SCHEMA_VERSION);
+        assert_eq!(v["type"], "settlement");
+        assert_eq!(v["signal_id"], 7);
+
+        let lagged = serde_json::to_value(ApiEvent::new(ApiEventKind::Lagged { skipped: 3 }))
+            .expect("serialize");
+        assert_eq!(lagged["type"], "lagged");
+        assert_eq!(lagged["skipped"], 3);
+    }
+
+    #[test]
+    fn config_view_follows_reloads_and_redacts_credentials() {
+        let raw = include_str!("../config.example.toml").replacen(
+            "ws_base = \"wss://",
+            "ws_base = \"wss://user:hunter2@",
+            1,
+        );
+        let (cfg, _, _) = crate::config::parse_with_env_overrides(&raw).unwrap();
+        let (tx, rx) = watch::channel(cfg.clone());
+        let state = RunState::new("r".into(), "shadow".into(), 0, rx, 10);
+
+        let view = state.config_view();
+        assert_eq!(view["polymarket"]["ws_base"], "<redacted>");
+        assert!(!view.to_string().contains("hunter2"));
+
+        let mut reloaded = cfg;
+        reloaded.brain.risk_premium_bps = 123;
+        tx.send_replace(reloaded);
+        assert_eq!(state.config_view()["brain"]["risk_premium_bps"], 123);
+    }
+}
@@ -0,0 +1,300 @@
+//! Process-wide request accounting for the Polymarket APIs: every request is counted per
+//! endpoint and UTC hour (status class, 429s, transport errors, busiest 10s window) and closed
+//! hours are written to `api_usage.jsonl`, so a run can show it stayed under the documented
+//! limits and a 429 storm can be traced after the fact.
+
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, OnceLock};
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+use tokio::sync::watch;
+
+use crate::recorder::JsonlAppender;
+use crate::types::now_ms;
+
+const HOUR_MS: u64 = 3_600_000;
+/// Polymarket documents its limits per 10 seconds.
+const PEAK_WINDOW_MS: u64 = 10_000;
+/// How often closed hours are drained to disk.
+const DRAIN_INTERVAL: Duration = Duration::from_secs(60);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum ApiEndpoint {
+    /// `gamma-api` market metadata.
+    Gamma,
+    /// `data-api` `/trades`.
+    DataApiTrades,
+    /// CLOB REST (auth, tick size, fee rate, orders).
+    #[cfg_attr(not(feature = "live"), allow(dead_code))]
+    Clob,
+    /// Polygon JSON-RPC (`[reconcile]` balance reads).
+    PolygonRpc,
+}
+
+impl ApiEndpoint {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            ApiEndpoint::Gamma => "gamma",
+            ApiEndpoint::DataApiTrades => "data_api_trades",
+            ApiEndpoint::Clob => "clob",
+            ApiEndpoint::PolygonRpc => "polygon_rpc",
+        }
+    }
+}
+
+/// One line of `api_usage.jsonl`: one endpoint over one UTC hour.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct UsageLine {
+    pub hour_start_ms: u64,
+    pub endpoint: String,
+    pub requests: u64,
+    /// 2xx/3xx responses.
+    pub ok: u64,
+    pub http_429: u64,
+    /// 4xx other than 429.
+    pub http_4xx: u64,
+    pub http_5xx: u64,
+    /// No response (connect/timeout/TLS errors).
+    pub transport_errors: u64,
+    /// Most requests within one aligned 10s window of the hour.
+    pub peak_10s: u64,
+    /// Written at shutdown before the hour ended.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub partial: bool,
+}
+
+#[derive(Debug, Default)]
+struct EndpointHour {
+    line: UsageLine,
+    window_start_ms: u64,
+    window_count: u64,
+}
+
+#[derive(Debug, Default)]
+struct Usage {
+    hour_start_ms: u64,
+    current: BTreeMap<ApiEndpoint, EndpointHour>,
+    closed: Vec<UsageLine>,
+}
+
+impl Usage {
+    /// `status` is the HTTP status, `None` when the request got no response.
+    fn record(&mut self, ts_ms: u64, endpoint: ApiEndpoint, status: Option<u16>) {
+        let hour = ts_ms - ts_ms % HOUR_MS;
+        if hour != self.hour_start_ms {
+            self.close_hour(false);
+            self.hour_start_ms = hour;
+        }
+        let e = self
+            .current
+            .entry(endpoint)
+            .or_insert_with(|| EndpointHour {
+                line: UsageLine {
+                    hour_start_ms: hour,
+                    endpoint: endpoint.as_str().to_string(),
+                    ..UsageLine::default()
+                },
+                ..EndpointHour::default()
+            });
+        let window = ts_ms - ts_ms % PEAK_WINDOW_MS;
+        if window != e.window_start_ms {
+            e.window_start_ms = window;
+            e.window_count = 0;
+        }
+        e.window_count += 1;
+        let l = &mut e.line;
+        l.peak_10s = l.peak_10s.max(e.window_count);
+        l.requests += 1;
+        match status {
+            None => l.transport_errors += 1,
+            Some(429) => l.http_429 += 1,
+            Some(s) if s >= 500 => l.http_5xx += 1,
+            Some(s) if s >= 400 => l.http_4xx += 1,
+            Some(_) => l.ok += 1,
+        }
+    }
+
+    fn close_hour(&mut self, partial: bool) {
+        for (_, e) in std::mem::take(&mut self.current) {
+            self.closed.push(UsageLine { partial, ..e.line });
+        }
+    }
+}
+
+fn usage() -> &'static Mutex<Usage> {
+    static USAGE: OnceLock<Mutex<Usage>> = OnceLock::new();
+    USAGE.get_or_init(|| Mutex::new(Usage::default()))
+}
+
+/// Counts one request to `endpoint`.
+pub fn record(endpoint: ApiEndpoint, status: Option<u16>) {
+    let mut u = usage().lock().unwrap_or_else(|e| e.into_inner());
+    u.record(now_ms(), endpoint, status);
+}
+
+/// Counts the outcome of `client...send().await` and hands it back unchanged.
+pub fn track(
+    endpoint: ApiEndpoint,
+    res: reqwest::Result<reqwest::Response>,
+) -> reqwest::Result<reqwest::Response> {
+    let status = match &res {
+        Ok(resp) => Some(resp.status().as_u16()),
+        Err(e) => e.status().map(|s| s.as_u16()),
+    };
+    record(endpoint, status);
+    res
+}
+
+/// Hours that have ended since the last call; with `flush` the current hour too (`partial`).
+fn take_lines(flush: bool) -> Vec<UsageLine> {
+    let mut u = usage().lock().unwrap_or_else(|e| e.into_inner());
+    if flush {
+        u.close_hour(true);
+        u.hour_start_ms = 0;
+    }
+    std::mem::take(&mut u.closed)
+}
+
+/// Drains closed hours into `api_usage.jsonl` every minute, and the running hour at shutdown.
+pub async fn run_api_usage_writer(
+    path: PathBuf,
+    mut shutdown: watch::Receiver<bool>,
+) -> anyhow::Result<()> {
+    let mut out = JsonlAppender::open(&path)?;
+    let mut interval = tokio::time::interval(DRAIN_INTERVAL);
+    interval.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+    loop {
+        tokio::select! {
+            _ = shutdown.changed() => {
+                if *shutdown.borrow() { break; }
+            }
+            _ = interval.tick() => {}
+        }
+        if *shutdown.borrow() {
+            break;
+        }
+        let lines = take_lines(false);
+        for line in &lines {
+            out.write_line(&serde_json::to_string(line)?)?;
+        }
+        if !lines.is_empty() {
+            out.flush_and_sync()?;
+        }
+    }
+    for line in take_lines(true) {
+        out.write_line(&serde_json::to_string(&line)?)?;
+    }
+    out.flush_and_sync()?;
+    Ok(())
+}
+
+/// Run totals per endpoint (report.json `api_usage`).
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct EndpointTotals {
+    pub requests: u64,
+    pub http_429: u64,
+    pub http_4xx: u64,
+    pub http_5xx: u64,
+    pub transport_errors: u64,
+    /// Busiest hour and busiest 10s window of the run.
+    pub peak_hour: u64,
+    pub peak_10s: u64,
+}
+
+/// Sums `api_usage.jsonl` per endpoint; `None` when the file is missing or empty.
+pub fn summarize_file(path: &Path) -> Option<BTreeMap<String, EndpointTotals>> {
+    let raw = std::fs::read_to_string(path).ok()?;
+    let lines = raw
+        .lines()
+        .filter_map(|l| serde_json::from_str::<UsageLine>(l).ok());
+    let totals = summarize(lines);
+    (!totals.is_empty()).then_some(totals)
+}
+
+fn summarize(lines: impl IntoIterator<Item = UsageLine>) -> BTreeMap<String, EndpointTotals> {
+    // A resumed run can write the same hour twice (partial at the crash, again later).
+    let mut hours: BTreeMap<(String, u64), u64> = BTreeMap::new();
+    let mut out: BTreeMap<String, EndpointTotals> = BTreeMap::new();
+    for l in lines {
+        *hours
+            .entry((l.endpoint.clone(), l.hour_start_ms))
+            .or_default() += l.requests;
+        let t = out.entry(l.endpoint).or_default();
+        t.requests += l.requests;
+        t.http_429 += l.http_429;
+        t.http_4xx += l.http_4xx;
+        t.http_5xx += l.http_5xx;
+        t.transport_errors += l.transport_errors;
+        t.peak_10s = t.peak_10s.max(l.peak_10s);
+    }
+    for ((endpoint, _), n) in hours {
+        if let Some(t) = out.get_mut(&endpoint) {
+            t.peak_hour = t.peak_hour.max(n);
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn counts_per_endpoint_hour_and_10s_window() {
+        let mut u = Usage::default();
+        let h = 5 * HOUR_MS;
+        for i in 0..3 {
+            u.record(h + i, ApiEndpoint::DataApiTrades, Some(200));
+        }
+        u.record(h + 20_000, ApiEndpoint::DataApiTrades, Some(429));
+        u.record(h + 20_001, ApiEndpoint::DataApiTrades, None);
+        u.record(h + 30_000, ApiEndpoint::Gamma, Some(503));
+        u.record(h + 30_001, ApiEndpoint::Gamma, Some(404));
+        assert!(u.closed.is_empty());
+
+        u.record(h + HOUR_MS, ApiEndpoint::Gamma, Some(200));
+        assert_eq!(u.closed.len(), 2);
+        let trades = &u.closed[1];
+        assert_eq!(trades.endpoint, "data_api_trades");
+        assert_eq!(
+            (
+                trades.requests,
+                trades.ok,
+                trades.http_429,
+                trades.transport_errors
+            ),
+            (5, 3, 1, 1)
+        );
+        assert_eq!(trades.peak_10s, 3);
+        let gamma = &u.closed[0];
+        assert_eq!(
+            (gamma.http_5xx, gamma.http_4xx, gamma.hour_start_ms),
+            (1, 1, h)
+        );
+
+        u.close_hour(true);
+        assert!(u.closed[2].partial && u.closed[2].requests == 1);
+
+        let totals = summarize(u.closed.clone());
+        assert_eq!(totals["gamma"].requests, 3);
+        assert_eq!(totals["gamma"].peak_hour, 2);
+        assert_eq!(totals["data_api_trades"].http_429, 1);
+    }
+
+    #[test]
+    fn usage_line_round_trips() {
+        let line = UsageLine {
+            hour_start_ms: HOUR_MS,
+            endpoint: "clob".to_string(),
+            requests: 2,
+            ok: 2,
+            peak_10s: 2,
+            ..UsageLine::default()
+        };
+        let json = serde_json::to_string(&line).unwrap();
+        assert!(!json.contains("partial"), "{json}");
+        assert_eq!(serde_json::from_str::<UsageLine>(&json).unwrap(), line);
+    }
+}
@@ -0,0 +1,299 @@
+//! Compressed run archives: `data/run_<id>/` packed into `data/run_<id>.tar.zst` (entries are
+//! prefixed with the run dir name) plus one line in `data/archive_index.jsonl`.
+//!
+//! Offline tooling calls [`resolve_run_dir`] on its input, so an archived run can still be
+//! passed by its old dir path and is extracted once into a temp cache.
+
+use std::collections::BTreeMap;
+use std::io::{Read as _, Write as _};
+use std::path::{Path, PathBuf};
+
+use anyhow::Context as _;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest as _, Sha256};
+
+use crate::schema::FILE_ARCHIVE_INDEX;
+use crate::types::now_ms;
+
+pub const ARCHIVE_SUFFIX: &str = ".tar.zst";
+
+/// Written next to an extracted copy; it is reused while the archive's size and mtime match.
+const EXTRACTED_STAMP: &str = ".razor_extracted";
+
+/// One `archive_index.jsonl` line: the stub left behind for a run dir that was archived.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ArchiveIndexEntry {
+    pub run_id: String,
+    /// File name of the archive, relative to the index.
+    pub archive: String,
+    pub archived_ts_ms: u64,
+    pub files: u64,
+    pub bytes_raw: u64,
+    pub bytes_archive: u64,
+    pub archive_sha256: String,
+}
+
+/// `data/run_<id>` -> `data/run_<id>.tar.zst`.
+pub fn archive_path_for(run_dir: &Path) -> PathBuf {
+    let name = run_dir
+        .file_name()
+        .map(|n| n.to_string_lossy().to_string())
+        .unwrap_or_default();
+    run_dir.with_file_name(format!("{name}{ARCHIVE_SUFFIX}"))
+}
+
+fn run_name_of_archive(archive: &Path) -> Option<String> {
+    let name = archive.file_name()?.to_string_lossy().to_string();
+    name.strip_suffix(ARCHIVE_SUFFIX).map(str::to_string)
+}
+
+/// Packs every regular file under `run_dir` into its archive (written via a temp file and
+/// renamed), reads the archive back and checks each `expected` path/SHA-256 against it, then
+/// appends the index line. `run_dir` itself is left for the caller to remove.
+pub fn pack_run_dir(
+    run_dir: &Path,
+    level: i32,
+    expected_sha256: &BTreeMap<String, String>,
+) -> anyhow::Result<ArchiveIndexEntry> {
+    let run_name = run_dir
+        .file_name()
+        .map(|n| n.to_string_lossy().to_string())
+        .with_context(|| format!("not a run dir: {}", run_dir.display()))?;
+    let archive = archive_path_for(run_dir);
+    anyhow::ensure!(
+        !archive.exists(),
+        "archive already exists: {}",
+        archive.display()
+    );
+    let tmp = archive.with_file_name(format!("{run_name}{ARCHIVE_SUFFIX}.tmp"));
+
+    let (files, bytes_raw) = {
+        let f = std::fs::File::create(&tmp).with_context(|| format!("create {}", tmp.display()))?;
+        let enc = zstd::Encoder::new(f, level).context("zstd encoder")?;
+        let mut tar = tar::Builder::new(enc);
+        tar.follow_symlinks(false);
+        let (mut files, mut bytes_raw) = (0u64, 0u64);
+        for rel in list_files(run_dir)? {
+            let path = run_dir.join(&rel);
+            bytes_raw += std::fs::metadata(&path)?.len();
+            tar.append_path_with_name(&path, format!("{run_name}/{rel}"))
+                .with_context(|| format!("pack {}", path.display()))?;
+            files += 1;
+        }
+        let f = tar.into_inner()?.finish().context("zstd finish")?;
+        f.sync_all()
+            .with_context(|| format!("sync {}", tmp.display()))?;
+        (files, bytes_raw)
+    };
+
+    let got = archive_digests(&tmp)?;
+    for (rel, want) in expected_sha256 {
+        match got.get(&format!("{run_name}/{rel}")) {
+            Some(sha) if sha == want => {}
+            Some(_) => {
+                let _ = std::fs::remove_file(&tmp);
+                anyhow::bail!("archived {rel} does not match its manifest checksum");
+            }
+            None => {
+                let _ = std::fs::remove_file(&tmp);
+                anyhow::bail!("archive is missing {rel}");
+            }
+        }
+    }
+    std::fs::rename(&tmp, &archive)
+        .with_context(|| format!("rename {} -> {}", tmp.display(), archive.display()))?;
+
+    let entry = ArchiveIndexEntry {
+        run_id: run_name.clone(),
+        archive: format!("{run_name}{ARCHIVE_SUFFIX}"),
+        archived_ts_ms: now_ms(),
+        files,
+        bytes_raw,
+        bytes_archive: std::fs::metadata(&archive)?.len(),
+        archive_sha256: sha256_file(&archive)?,
+    };
+    let index = archive.with_file_name(FILE_ARCHIVE_INDEX);
+    let mut f = std::fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(&index)
+        .with_context(|| format!("open {}", index.display()))?;
+    writeln!(f, "{}", serde_json::to_string(&entry)?)?;
+    f.sync_all()
+        .with_context(|| format!("sync {}", index.display()))?;
+    Ok(entry)
+}
+
+/// Entries of `data_dir/archive_index.jsonl`, oldest first; unparsable lines are skipped.
+#[allow(dead_code)]
+pub fn read_archive_index(data_dir: &Path) -> Vec<ArchiveIndexEntry> {
+    std::fs::read_to_string(data_dir.join(FILE_ARCHIVE_INDEX))
+        .unwrap_or_default()
+        .lines()
+        .filter_map(|l| serde_json::from_str(l).ok())
+        .collect()
+}
+
+/// Returns a directory holding the run at `path`: `path` itself when it is a dir, otherwise the
+/// extracted copy of `path` (a `.tar.zst`) or of `<path>.tar.zst`.
+pub fn resolve_run_dir(path: &Path) -> anyhow::Result<PathBuf> {
+    if path.is_dir() {
+        return Ok(path.to_path_buf());
+    }
+    let archive = if run_name_of_archive(path).is_some() {
+        path.to_path_buf()
+    } else {
+        archive_path_for(path)
+    };
+    if !archive.is_file() {
+        // Not archived either: let the caller report the missing dir as before.
+        return Ok(path.to_path_buf());
+    }
+    let cache = std::env::temp_dir().join("razor_archive_cache");
+    extract_archive(&archive, &cache)
+}
+
+/// Extracts `archive` under `cache_dir` (reusing an up-to-date earlier extraction) and returns
+/// the run dir inside it.
+pub fn extract_archive(archive: &Path, cache_dir: &Path) -> anyhow::Result<PathBuf> {
+    let run_name = run_name_of_archive(archive)
+        .with_context(|| format!("not a {ARCHIVE_SUFFIX} file: {}", archive.display()))?;
+    let meta = std::fs::metadata(archive).with_context(|| format!("stat {}", archive.display()))?;
+    let mtime_ms = meta
+        .modified()
+        .ok()
+        .and_then(|t| t.duration_since(std::time::UNIX_EPOCH).ok())
+        .map(|d| d.as_millis())
+        .unwrap_or(0);
+    let stamp = format!("{} {mtime_ms}\n", meta.len());
+
+    let out = cache_dir.join(&run_name);
+    if std::fs::read_to_string(out.join(EXTRACTED_STAMP)).is_ok_and(|s| s == stamp) {
+        return Ok(out);
+    }
+
+    let staging = cache_dir.join(format!(".{run_name}.{}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&staging);
+    std::fs::create_dir_all(&staging).with_context(|| format!("create {}", staging.display()))?;
+    let f = std::fs::File::open(archive).with_context(|| format!("open {}", archive.display()))?;
+    let dec = zstd::Decoder::new(f).context("zstd decoder")?;
+    tar::Archive::new(dec)
+        .unpack(&staging)
+        .with_context(|| format!("extract {}", archive.display()))?;
+    let extracted = staging.join(&run_name);
+    anyhow::ensure!(
+        extracted.is_dir(),
+        "{} does not contain {run_name}/",
+        archive.display()
+    );
+    std::fs::write(extracted.join(EXTRACTED_STAMP), &stamp)?;
+    let _ = std::fs::remove_dir_all(&out);
+    std::fs::rename(&extracted, &out)
+        .with_context(|| format!("rename {} -> {}", extracted.display(), out.display()))?;
+    let _ = std::fs::remove_dir_all(&staging);
+    Ok(out)
+}
+
+/// SHA-256 of every file entry in `archive`, keyed by its path inside the archive.
+fn archive_digests(archive: &Path) -> anyhow::Result<BTreeMap<String, String>> {
+    let f = std::fs::File::open(archive).with_context(|| format!("open {}", archive.display()))?;
+    let mut tar = tar::Archive::new(zstd::Decoder::new(f).context("zstd decoder")?);
+    let mut out = BTreeMap::new();
+    let mut buf = vec![0u8; 1 << 20];
+    for entry in tar.entries()? {
+        let mut entry = entry?;
+        if !entry.header().entry_type().is_file() {
+            continue;
+        }
+        let path = entry.path()?.to_string_lossy().replace('\\', "/");
+        let mut hasher = Sha256::new();
+        loop {
+            let n = entry.read(&mut buf)?;
+            if n == 0 {
+                break;
+            }
+            hasher.update(&buf[..n]);
+        }
+        out.insert(path, hex::encode(hasher.finalize()));
+    }
+    Ok(out)
+}
+
+fn sha256_file(path: &Path) -> anyhow::Result<String> {
+    let mut f = std::fs::File::open(path).with_context(|| format!("open {}", path.display()))?;
+    let mut hasher = Sha256::new();
+    std::io::copy(&mut f, &mut hasher).with_context(|| format!("read {}", path.display()))?;
+    Ok(hex::encode(hasher.finalize()))
+}
+
+/// Run-relative, `/`-separated paths of the regular files under `dir`, sorted.
+fn list_files(dir: &Path) -> anyhow::Result<Vec<String>> {
+    fn walk(dir: &Path, rel: &str, out: &mut Vec<String>) -> anyhow::Result<()> {
+        for entry in std::fs::read_dir(dir).with_context(|| format!("read {}", dir.display()))? {
+            let entry = entry?;
+            let name = entry.file_name().to_string_lossy().to_string();
+            let rel_path = if rel.is_empty() {
+                name
+            } else {
+                format!("{rel}/{name}")
+            };
+            let ft = entry.file_type()?;
+            if ft.is_dir() {
+                walk(&entry.path(), &rel_path, out)?;
+            } else if ft.is_file() {
+                out.push(rel_path);
+            }
+        }
+        Ok(())
+    }
+    let mut out = Vec::new();
+    walk(dir, "", &mut out)?;
+    out.sort();
+    Ok(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn packs_checks_and_transparently_extracts_a_run() {
+        let data = std::env::temp_dir().join(format!("razor_archive_{}", now_ms()));
+        let run = data.join("run_20260101_000000_000001");
+        std::fs::create_dir_all(run.join("2026-01-01/00")).unwrap();
+        std::fs::write(run.join("shadow_log.csv"), "a,b\n1,2\n").unwrap();
+        std::fs::write(run.join("2026-01-01/00/ticks.csv"), "t\n1\n").unwrap();
+
+        let bad: BTreeMap<String, String> =
+            [("shadow_log.csv".to_string(), "00".repeat(32))].into();
+        assert!(pack_run_dir(&run, 3, &bad).is_err());
+        assert!(!archive_path_for(&run).exists());
+
+        let good: BTreeMap<String, String> = [(
+            "shadow_log.csv".to_string(),
+            sha256_file(&run.join("shadow_log.csv")).unwrap(),
+        )]
+        .into();
+        let entry = pack_run_dir(&run, 3, &good).unwrap();
+        assert_eq!(entry.files, 2);
+        assert_eq!(read_archive_index(&data), vec![entry]);
+        std::fs::remove_dir_all(&run).unwrap();
+
+        let cache = data.join("cache");
+        let out = extract_archive(&archive_path_for(&run), &cache).unwrap();
+        assert_eq!(out, cache.join("run_20260101_000000_000001"));
+        assert_eq!(
+            std::fs::read_to_string(out.join("2026-01-01/00/ticks.csv")).unwrap(),
+            "t\n1\n"
+        );
+        std::fs::write(out.join("shadow_log.csv"), "stale").unwrap();
+        extract_archive(&archive_path_for(&run), &cache).unwrap();
+        assert_eq!(
+            std::fs::read_to_string(out.join("shadow_log.csv")).unwrap(),
+            "stale",
+            "an up-to-date extraction is reused"
+        );
+
+        let _ = std::fs::remove_dir_all(&data);
+    }
+}
@@ -0,0 +1,334 @@
+//! Bounded single-consumer channel whose behaviour when full is chosen per channel in
+//! `[backpressure.<channel>]`: drop the new item, evict the oldest queued item, or wait up to
+//! `block_timeout_ms` for room and drop then. Every channel counts what it dropped and remembers
+//! when (merged into spans), so health.jsonl shows the realized loss and shadow can flag the
+//! windows it hit.
+
+use std::collections::VecDeque;
+use std::sync::atomic::{AtomicBool, AtomicU64, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use schemars::JsonSchema;
+use serde::Serialize;
+use tokio::sync::Notify;
+
+use crate::config::{BackpressurePolicy, ChannelPolicy};
+use crate::types::now_ms;
+
+/// Drops closer together than this share one span in the drop log.
+const DROP_SPAN_MERGE_MS: u64 = 1_000;
+/// Spans kept per channel; the oldest go first.
+const MAX_DROP_SPANS: usize = 4_096;
+
+/// Counters and drop log of one channel, shared with health.
+#[derive(Debug)]
+pub struct ChannelStats {
+    name: &'static str,
+    policy: ChannelPolicy,
+    capacity: usize,
+    len: AtomicUsize,
+    sent: AtomicU64,
+    dropped: AtomicU64,
+    drop_spans: Mutex<VecDeque<(u64, u64)>>,
+}
+
+impl ChannelStats {
+    fn record_drop(&self, ts_ms: u64) {
+        self.dropped.fetch_add(1, Ordering::Relaxed);
+        let mut spans = self.drop_spans.lock().unwrap_or_else(|e| e.into_inner());
+        match spans.back_mut() {
+            Some(last) if ts_ms <= last.1 + DROP_SPAN_MERGE_MS => last.1 = last.1.max(ts_ms),
+            _ => {
+                if spans.len() == MAX_DROP_SPANS {
+                    spans.pop_front();
+                }
+                spans.push_back((ts_ms, ts_ms));
+            }
+        }
+    }
+
+    /// Whether an item was dropped within `[start_ms, end_ms]` (unix ms).
+    pub fn dropped_between(&self, start_ms: u64, end_ms: u64) -> bool {
+        let spans = self.drop_spans.lock().unwrap_or_else(|e| e.into_inner());
+        spans
+            .iter()
+            .rev()
+            .take_while(|(_, last)| *last + DROP_SPAN_MERGE_MS >= start_ms)
+            .any(|&(first, last)| first <= end_ms && last >= start_ms)
+    }
+
+    pub fn dropped(&self) -> u64 {
+        self.dropped.load(Ordering::Relaxed)
+    }
+
+    pub fn snapshot(&self) -> ChannelSnapshot {
+        ChannelSnapshot {
+            name: self.name,
+            policy: self.policy.policy.as_str(),
+            capacity: self.capacity,
+            len: self.len.load(Ordering::Relaxed),
+            sent: self.sent.load(Ordering::Relaxed),
+            dropped: self.dropped(),
+        }
+    }
+}
+
+/// One channel in the health heartbeat.
+#[derive(Debug, Clone, Serialize, JsonSchema)]
+pub struct ChannelSnapshot {
+    pub name: &'static str,
+    pub policy: &'static str,
+    pub capacity: usize,
+    /// Items queued at the heartbeat.
+    pub len: usize,
+    pub sent: u64,
+    pub dropped: u64,
+}
+
+/// What `send` did with the item.
+#[derive(Debug)]
+pub enum SendOutcome<T> {
+    Queued,
+    /// The channel was full; this item (the new one, or the evicted oldest) was dropped.
+    Dropped(T),
+}
+
+impl<T> SendOutcome<T> {
+    pub fn is_queued(&self) -> bool {
+        matches!(self, SendOutcome::Queued)
+    }
+}
+
+/// The receiver is gone.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Closed;
+
+struct Shared<T> {
+    stats: Arc<ChannelStats>,
+    queue: Mutex<VecDeque<T>>,
+    item_ready: Notify,
+    space_ready: Notify,
+    senders: AtomicUsize,
+    receiver_alive: AtomicBool,
+}
+
+pub fn channel<T>(
+    name: &'static str,
+    capacity: usize,
+    policy: ChannelPolicy,
+) -> (Sender<T>, Receiver<T>) {
+    let capacity = capacity.max(1);
+    let shared = Arc::new(Shared {
+        stats: Arc::new(ChannelStats {
+            name,
+            policy,
+            capacity,
+            len: AtomicUsize::new(0),
+            sent: AtomicU64::new(0),
+            dropped: AtomicU64::new(0),
+            drop_spans: Mutex::new(VecDeque::new()),
+        }),
+        queue: Mutex::new(VecDeque::with_capacity(capacity.min(1_024))),
+        item_ready: Notify::new(),
+        space_ready: Notify::new(),
+        senders: AtomicUsize::new(1),
+        receiver_alive: AtomicBool::new(true),
+    });
+    (
+        Sender {
+            shared: shared.clone(),
+        },
+        Receiver { shared },
+    )
+}
+
+pub struct Sender<T> {
+    shared: Arc<Shared<T>>,
+}
+
+impl<T> Clone for Sender<T> {
+    fn clone(&self) -> Self {
+        self.shared.senders.fetch_add(1, Ordering::AcqRel);
+        Self {
+            shared: self.shared.clone(),
+        }
+    }
+}
+
+impl<T> Drop for Sender<T> {
+    fn drop(&mut self) {
+        if self.shared.senders.fetch_sub(1, Ordering::AcqRel) == 1 {
+            self.shared.item_ready.notify_one();
+        }
+    }
+}
+
+impl<T> Sender<T> {
+    pub fn stats(&self) -> Arc<ChannelStats> {
+        self.shared.stats.clone()
+    }
+
+    /// Queues `value`, applying the channel's policy when it is full. Only
+    /// `block_with_timeout` ever waits.
+    pub async fn send(&self, value: T) -> Result<SendOutcome<T>, Closed> {
+        let stats = &self.shared.stats;
+        let deadline =
+            tokio::time::Instant::now() + Duration::from_millis(stats.policy.block_timeout_ms);
+        loop {
+            let notified = self.shared.space_ready.notified();
+            tokio::pin!(notified);
+            notified.as_mut().enable();
+            {
+                let mut q = self.shared.queue.lock().unwrap_or_else(|e| e.into_inner());
+                if !self.shared.receiver_alive.load(Ordering::Acquire) {
+                    return Err(Closed);
+                }
+                if q.len() < stats.capacity {
+                    return Ok(self.push(&mut q, value, None));
+                }
+                match stats.policy.policy {
+                    BackpressurePolicy::DropNewest => {
+                        stats.record_drop(now_ms());
+                        return Ok(SendOutcome::Dropped(value));
+                    }
+                    BackpressurePolicy::DropOldest => {
+                        let oldest = q.pop_front();
+                        return Ok(self.push(&mut q, value, oldest));
+                    }
+                    BackpressurePolicy::BlockWithTimeout => {}
+                }
+            }
+            if tokio::time::timeout_at(deadline, notified).await.is_err() {
+                // One last look: the receiver may have made room right at the deadline.
+                let mut q = self.shared.queue.lock().unwrap_or_else(|e| e.into_inner());
+                if q.len() < stats.capacity {
+                    return Ok(self.push(&mut q, value, None));
+                }
+                stats.record_drop(now_ms());
+                return Ok(SendOutcome::Dropped(value));
+            }
+        }
+    }
+
+    fn push(&self, q: &mut VecDeque<T>, value: T, evicted: Option<T>) -> SendOutcome<T> {
+        let stats = &self.shared.stats;
+        q.push_back(value);
+        stats.len.store(q.len(), Ordering::Relaxed);
+        stats.sent.fetch_add(1, Ordering::Relaxed);
+        self.shared.item_ready.notify_one();
+        match evicted {
+            Some(old) => {
+                stats.record_drop(now_ms());
+                SendOutcome::Dropped(old)
+            }
+            None => SendOutcome::Queued,
+        }
+    }
+}
+
+pub struct Receiver<T> {
+    shared: Arc<Shared<T>>,
+}
+
+impl<T> Drop for Receiver<T> {
+    fn drop(&mut self) {
+        self.shared.receiver_alive.store(false, Ordering::Release);
+        self.shared.space_ready.notify_waiters();
+    }
+}
+
+impl<T> Receiver<T> {
+    pub fn stats(&self) -> Arc<ChannelStats> {
+        self.shared.stats.clone()
+    }
+
+    /// The next item; `None` once every sender is gone and the queue is drained.
+    pub async fn recv(&mut self) -> Option<T> {
+        loop {
+            let notified = self.shared.item_ready.notified();
+            tokio::pin!(notified);
+            notified.as_mut().enable();
+            {
+                let mut q = self.shared.queue.lock().unwrap_or_else(|e| e.into_inner());
+                if let Some(v) = q.pop_front() {
+                    self.shared.stats.len.store(q.len(), Ordering::Relaxed);
+                    self.shared.space_ready.notify_one();
+                    return Some(v);
+                }
+                if self.shared.senders.load(Ordering::Acquire) == 0 {
+                    return None;
+                }
+            }
+            notified.await;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn policy(policy: BackpressurePolicy, block_timeout_ms: u64) -> ChannelPolicy {
+        ChannelPolicy {
+            policy,
+            block_timeout_ms,
+        }
+    }
+
+    #[tokio::test]
+    async fn drop_newest_and_drop_oldest_keep_different_items() {
+        let (tx, mut rx) = channel::<u32>("t", 2, policy(BackpressurePolicy::DropNewest, 0));
+        for i in 0..3 {
+            tx.send(i).await.unwrap();
+        }
+        assert_eq!((rx.recv().await, rx.recv().await), (Some(0), Some(1)));
+        assert_eq!(rx.stats().dropped(), 1);
+
+        let (tx, mut rx) = channel::<u32>("t", 2, policy(BackpressurePolicy::DropOldest, 0));
+        for i in 0..3 {
+            tx.send(i).await.unwrap();
+        }
+        assert!(matches!(tx.send(3).await, Ok(SendOutcome::Dropped(1))));
+        drop(tx);
+        assert_eq!(rx.recv().await, Some(2));
+        assert_eq!(rx.recv().await, Some(3));
+        assert_eq!(rx.recv().await, None);
+        let snap = rx.stats().snapshot();
+        assert_eq!((snap.sent, snap.dropped, snap.len), (4, 2, 0));
+    }
+
+    #[tokio::test]
+    async fn block_with_timeout_waits_for_room_then_drops() {
+        let (tx, mut rx) = channel::<u32>("t", 1, policy(BackpressurePolicy::BlockWithTimeout, 20));
+        tx.send(0).await.unwrap();
+        assert!(matches!(tx.send(1).await, Ok(SendOutcome::Dropped(1))));
+
+        let waiting = tokio::spawn(async move { tx.send(2).await.map(|o| o.is_queued()) });
+        assert_eq!(rx.recv().await, Some(0));
+        assert_eq!(waiting.await.unwrap(), Ok(true));
+        assert_eq!(rx.recv().await, Some(2));
+        assert_eq!(rx.stats().dropped(), 1);
+    }
+
+    #[tokio::test]
+    async fn send_after_receiver_drop_is_closed() {
+        let (tx, rx) = channel::<u32>("t", 1, ChannelPolicy::default());
+        drop(rx);
+        assert!(matches!(tx.send(0).await, Err(Closed)));
+    }
+
+    #[test]
+    fn drop_spans_merge_and_answer_window_queries() {
+        let (tx, _rx) = channel::<u32>("t", 1, ChannelPolicy::default());
+        let stats = tx.stats();
+        for ts in [10_000, 10_500, 11_400, 20_000] {
+            stats.record_drop(ts);
+        }
+        assert_eq!(stats.drop_spans.lock().unwrap().len(), 2);
+        assert!(stats.dropped_between(11_000, 12_000));
+        assert!(!stats.dropped_between(12_000, 19_999));
+        assert!(stats.dropped_between(19_000, 25_000));
+        assert!(!stats.dropped_between(0, 9_999));
+    }
+}
@@ -1,16 +1,22 @@
 use std::collections::HashMap;
 use std::sync::Arc;
 
-use tokio::sync::{mpsc, watch};
-use tracing::{debug, info, warn};
+use tokio::sync::{watch, Mutex};
+use tokio::task::JoinSet;
+use tracing::{debug, info, warn, Instrument as _};
 
-use crate::buckets::classify_bucket;
+use crate::backpressure::{self, SendOutcome};
+use crate::buckets::{BucketClassifier, BucketDecision, TradeArrivals};
+use crate::clock::SharedClock;
 use crate::config::Config;
+use crate::fees::{FeeRates, FeeRegistry};
 use crate::health::HealthCounters;
 use crate::reasons::ShadowNoteReason;
+use crate::signal_trace::trace_signal;
+use crate::strategy::{self, CompleteSetStrategy, SignalDraft, StrategyContext};
 use crate::types::{
-    now_ms, now_us, Bps, Bucket, BucketMetrics, Leg, MarketDef, MarketSnapshot, Side, Signal,
-    Strategy,
+    Bps, Bucket, BucketMetrics, Leg, LegSnapshot, MarketDef, MarketId, MarketSnapshot, Qty, Signal,
+    SignalId, Strategy, TokenId,
 };
 
 #[derive(Clone, Copy, Debug)]
@@ -38,104 +44,151 @@ struct EvalMetrics {
     risk_premium_bps: Bps,
     expected_net_bps: Bps,
     bucket_metrics: BucketMetrics,
-    worst_leg_token_id: String,
+    worst_leg_token_id: TokenId,
     reasons: Vec<ShadowNoteReason>,
+    /// Unsized: `qty` is set once brain has capped `q_req`.
+    legs: Vec<Leg>,
 }
 
-pub async fn run(
-    cfg: Config,
+/// What every market's state machine shares: the output channel, the run-wide signal id
+/// counter and the counters/inputs that are already keyed by market.
+struct BrainShared {
     run_id: String,
-    markets: Vec<MarketDef>,
-    mut snap_rx: watch::Receiver<Option<MarketSnapshot>>,
-    signal_tx: mpsc::Sender<Signal>,
+    signal_tx: backpressure::Sender<Signal>,
     health: Arc<HealthCounters>,
-    mut shutdown: watch::Receiver<bool>,
-) -> anyhow::Result<()> {
-    let mut next_signal_id: u64 = 1;
-    let mut last_by_key: HashMap<(String, Strategy, i32), LastSignalState> = HashMap::new();
-    let cooldown_ms = cfg.brain.signal_cooldown_ms;
-    let min_net_edge = Bps::new(cfg.brain.min_net_edge_bps);
-    let mut last_prune_ms: u64 = 0;
-    const DEDUP_PRUNE_EVERY_MS: u64 = 60_000;
-    const DEDUP_TTL_MS: u64 = 60 * 60_000;
-
-    let mut supported: HashMap<String, usize> = HashMap::new();
-    for m in markets {
-        supported.insert(m.market_id, m.token_ids.len());
+    trade_arrivals: Arc<TradeArrivals>,
+    /// Held from id assignment until the signal is queued, so ids reach shadow in increasing
+    /// order while evaluation itself stays concurrent.
+    next_signal_id: Mutex<SignalId>,
+    fees: FeeRegistry,
+    clock: SharedClock,
+}
+
+/// Signal generation state of one market. Each market evaluates on its own task with its own
+/// cooldown keys, staleness threshold and bucket classifier history, so a busy market neither
+/// delays another's evaluation nor suppresses its signals.
+struct MarketBrain {
+    market_id: MarketId,
+    leg_count: usize,
+    cooldown_ms: u64,
+    max_snapshot_staleness_ms: u64,
+    min_net_edge: Bps,
+    last_by_key: HashMap<(Strategy, i32), LastSignalState>,
+    last_prune_ms: u64,
+    stale_skipped: u64,
+    classifier: BucketClassifier,
+    strategy: Box<dyn strategy::Strategy>,
+}
+
+const DEDUP_PRUNE_EVERY_MS: u64 = 60_000;
+const DEDUP_TTL_MS: u64 = 60 * 60_000;
+
+impl MarketBrain {
+    fn new(cfg: &Config, market: &MarketDef) -> Self {
+        let mut brain = Self {
+            market_id: market.market_id.clone(),
+            leg_count: market.token_ids.len(),
+            cooldown_ms: 0,
+            max_snapshot_staleness_ms: 0,
+            min_net_edge: Bps::ZERO,
+            last_by_key: HashMap::new(),
+            last_prune_ms: 0,
+            stale_skipped: 0,
+            classifier: BucketClassifier::new(&cfg.bucket_classifier)
+                .with_depth_levels(cfg.buckets.depth_levels),
+            // `Config::validate` rejects unknown names.
+            strategy: strategy::from_name(&cfg.brain.strategy)
+                .unwrap_or_else(|| Box::new(CompleteSetStrategy)),
+        };
+        brain.apply_config(cfg);
+        brain
     }
 
-    loop {
-        tokio::select! {
-            _ = shutdown.changed() => {
-                if *shutdown.borrow() { break; }
-            }
-            res = snap_rx.changed() => {
-                res?;
-            }
+    /// Thresholds that `reload` may change; the market's overrides win over `[brain]`.
+    fn apply_config(&mut self, cfg: &Config) {
+        let params = cfg.market(&self.market_id);
+        self.cooldown_ms = params.signal_cooldown_ms;
+        self.max_snapshot_staleness_ms = params.max_snapshot_staleness_ms;
+        self.min_net_edge = Bps::new(cfg.brain.min_net_edge_bps);
+    }
+
+    fn prune(&mut self, now_ms: u64) {
+        if now_ms.saturating_sub(self.last_prune_ms) < DEDUP_PRUNE_EVERY_MS {
+            return;
         }
-        if *shutdown.borrow() {
-            break;
+        self.last_prune_ms = now_ms;
+        let cutoff = now_ms.saturating_sub(DEDUP_TTL_MS);
+        let before = self.last_by_key.len();
+        self.last_by_key.retain(|_, v| v.ts_ms >= cutoff);
+        let after = self.last_by_key.len();
+        if after < before {
+            debug!(
+                market_id = %self.market_id,
+                pruned = before - after,
+                remaining = after,
+                ttl_ms = DEDUP_TTL_MS,
+                "brain dedup state pruned"
+            );
         }
-        let Some(snap) = snap_rx.borrow().clone() else {
-            continue;
-        };
+    }
 
-        let Some(&leg_count) = supported.get(&snap.market_id) else {
-            continue;
-        };
-        if snap.legs.len() != leg_count {
-            continue;
+    /// Runs one snapshot through the filters; the signal to emit, if any. Its `signal_id` is
+    /// assigned when it is sent.
+    fn evaluate(
+        &mut self,
+        cfg: &Config,
+        shared: &BrainShared,
+        snap: &MarketSnapshot,
+    ) -> Option<(Signal, EvalMetrics)> {
+        if snap.legs.len() != self.leg_count {
+            return None;
         }
 
         let max_recv_us = snap.legs.iter().map(|l| l.ts_recv_us).max().unwrap_or(0);
         if max_recv_us > 0 {
-            let lag_ms = now_us().saturating_sub(max_recv_us) / 1000;
-            if lag_ms > cfg.brain.max_snapshot_staleness_ms {
-                health.inc_snapshots_stale_skipped(1);
+            let lag_ms = shared.clock.now_us().saturating_sub(max_recv_us) / 1000;
+            if lag_ms > self.max_snapshot_staleness_ms {
+                self.stale_skipped += 1;
+                shared.health.inc_snapshots_stale_skipped(1);
                 debug!(
                     market_id = %snap.market_id,
                     lag_ms,
-                    threshold_ms = cfg.brain.max_snapshot_staleness_ms,
+                    threshold_ms = self.max_snapshot_staleness_ms,
+                    stale_skipped = self.stale_skipped,
                     "skip: snapshot stale"
                 );
-                continue;
-            }
-        }
-
-        let signal_ts_ms = now_ms();
-        if signal_ts_ms.saturating_sub(last_prune_ms) >= DEDUP_PRUNE_EVERY_MS {
-            last_prune_ms = signal_ts_ms;
-            let cutoff = signal_ts_ms.saturating_sub(DEDUP_TTL_MS);
-            let before = last_by_key.len();
-            last_by_key.retain(|_, v| v.ts_ms >= cutoff);
-            let after = last_by_key.len();
-            if after < before {
-                debug!(
-                    pruned = before - after,
-                    remaining = after,
-                    ttl_ms = DEDUP_TTL_MS,
-                    "brain dedup state pruned"
-                );
+                return None;
             }
         }
 
-        let metrics = match eval_snapshot(&cfg, &snap) {
-            Ok(v) => v,
-            Err(e) => {
-                warn!(market_id = %snap.market_id, error = %e, "skip snapshot");
-                continue;
-            }
+        let signal_ts_ms = shared.clock.now_ms();
+        self.prune(signal_ts_ms);
+
+        let trades_per_min = shared
+            .trade_arrivals
+            .rate_per_min(&snap.market_id, signal_ts_ms);
+        let decision = self.classifier.classify(snap, signal_ts_ms, trades_per_min);
+        let fee_rates = shared.fees.resolve(signal_ts_ms).rates;
+        let Some(mut metrics) =
+            eval_snapshot(self.strategy.as_ref(), cfg, snap, decision, fee_rates)
+        else {
+            debug!(
+                market_id = %snap.market_id,
+                strategy = self.strategy.name(),
+                "skip: nothing to price"
+            );
+            return None;
         };
 
         let rounded_cost_bps = (metrics.raw_cost_bps.raw() / 2) * 2;
-        let key = (snap.market_id.clone(), metrics.strategy, rounded_cost_bps);
+        let key = (metrics.strategy, rounded_cost_bps);
 
         if let Err(reason) = should_emit(
             signal_ts_ms,
             metrics.expected_net_bps,
-            min_net_edge,
-            cooldown_ms,
-            last_by_key.get(&key),
+            self.min_net_edge,
+            self.cooldown_ms,
+            self.last_by_key.get(&key),
             rounded_cost_bps,
         ) {
             match reason {
@@ -143,7 +196,7 @@ pub async fn run(
                     debug!(
                         market_id = %snap.market_id,
                         expected_net_bps = metrics.expected_net_bps.raw(),
-                        min_net_edge_bps = min_net_edge.raw(),
+                        min_net_edge_bps = self.min_net_edge.raw(),
                         "skip: below min net edge"
                     );
                 }
@@ -151,7 +204,7 @@ pub async fn run(
                     remaining_ms,
                     key_cost_bps,
                 } => {
-                    health.inc_signals_suppressed(1);
+                    shared.health.inc_signals_suppressed(1);
                     debug!(
                         market_id = %snap.market_id,
                         remaining_ms,
@@ -161,31 +214,30 @@ pub async fn run(
                     );
                 }
             }
-            continue;
+            return None;
         };
 
-        let q_req = cfg.brain.q_req;
-        let legs: Vec<Leg> = snap
-            .legs
-            .iter()
-            .enumerate()
-            .map(|(idx, l)| Leg {
-                leg_index: idx,
-                token_id: l.token_id.clone(),
-                side: Side::Buy,
-                limit_price: l.best_ask,
-                qty: q_req,
-                best_bid_at_signal: l.best_bid,
-                best_ask_at_signal: l.best_ask,
-            })
-            .collect();
-
-        let signal_id = next_signal_id;
-        next_signal_id += 1;
+        let q_req = depth_capped_q_req(
+            cfg.market(&snap.market_id).q_req,
+            &snap.legs,
+            cfg.brain.depth_slippage_bps,
+        );
+        if !q_req.is_positive() {
+            debug!(
+                market_id = %snap.market_id,
+                depth_slippage_bps = cfg.brain.depth_slippage_bps,
+                "skip: no depth within slippage"
+            );
+            return None;
+        }
+        let mut legs = std::mem::take(&mut metrics.legs);
+        for leg in &mut legs {
+            leg.qty = q_req;
+        }
 
         let signal = Signal {
-            run_id: run_id.clone(),
-            signal_id,
+            run_id: shared.run_id.clone(),
+            signal_id: SignalId::default(),
             signal_ts_ms,
             market_id: snap.market_id.clone(),
             strategy: metrics.strategy,
@@ -201,33 +253,171 @@ pub async fn run(
             legs,
         };
 
-        last_by_key.insert(
+        self.last_by_key.insert(
             key,
             LastSignalState {
                 ts_ms: signal_ts_ms,
                 _expected_net_bps: metrics.expected_net_bps,
             },
         );
+        Some((signal, metrics))
+    }
+}
+
+/// Fans the shared snapshot channel out to one [`MarketBrain`] task per market. Each task sees
+/// only the latest snapshot of its own market, so a snapshot of one market is never
+/// overwritten by another's before it was evaluated.
+#[allow(clippy::too_many_arguments)]
+pub async fn run(
+    cfg: Config,
+    run_id: String,
+    markets: Vec<MarketDef>,
+    mut snap_rx: watch::Receiver<Option<MarketSnapshot>>,
+    signal_tx: backpressure::Sender<Signal>,
+    health: Arc<HealthCounters>,
+    trade_arrivals: Arc<TradeArrivals>,
+    first_signal_id: SignalId,
+    clock: SharedClock,
+    reload: watch::Receiver<Config>,
+    mut shutdown: watch::Receiver<bool>,
+) -> anyhow::Result<()> {
+    let shared = Arc::new(BrainShared {
+        run_id,
+        signal_tx,
+        health,
+        trade_arrivals,
+        next_signal_id: Mutex::new(first_signal_id),
+        fees: FeeRegistry::from_config(&cfg.fees),
+        clock,
+    });
+    info!(
+        classifier = cfg.bucket_classifier.version.as_str(),
+        depth_levels = cfg.buckets.depth_levels,
+        markets = markets.len(),
+        "bucket classifier"
+    );
+
+    let mut routes: HashMap<MarketId, watch::Sender<Option<MarketSnapshot>>> = HashMap::new();
+    let mut tasks = JoinSet::new();
+    for m in &markets {
+        let (tx, rx) = watch::channel(None);
+        routes.insert(m.market_id.clone(), tx);
+        tasks.spawn(
+            run_market(
+                MarketBrain::new(&cfg, m),
+                cfg.clone(),
+                Arc::clone(&shared),
+                rx,
+                reload.clone(),
+                shutdown.clone(),
+            )
+            .in_current_span(),
+        );
+    }
 
-        match signal_tx.try_send(signal) {
-            Ok(()) => {
-                health.inc_signals_emitted(1);
+    loop {
+        tokio::select! {
+            _ = shutdown.changed() => {
+                if *shutdown.borrow() { break; }
+            }
+            Some(joined) = tasks.join_next() => {
+                joined??;
+            }
+            res = snap_rx.changed() => {
+                res?;
+                let Some(snap) = snap_rx.borrow_and_update().clone() else {
+                    continue;
+                };
+                if let Some(route) = routes.get(&snap.market_id) {
+                    route.send_replace(Some(snap));
+                }
+            }
+        }
+    }
+
+    drop(routes);
+    while let Some(joined) = tasks.join_next().await {
+        joined??;
+    }
+    Ok(())
+}
+
+async fn run_market(
+    mut brain: MarketBrain,
+    mut cfg: Config,
+    shared: Arc<BrainShared>,
+    mut snap_rx: watch::Receiver<Option<MarketSnapshot>>,
+    mut reload: watch::Receiver<Config>,
+    mut shutdown: watch::Receiver<bool>,
+) -> anyhow::Result<()> {
+    debug!(
+        market_id = %brain.market_id,
+        classifier = brain.classifier.version().as_str(),
+        strategy = brain.strategy.name(),
+        cooldown_ms = brain.cooldown_ms,
+        max_snapshot_staleness_ms = brain.max_snapshot_staleness_ms,
+        "market brain started"
+    );
+    loop {
+        tokio::select! {
+            _ = shutdown.changed() => {
+                if *shutdown.borrow() { break; }
+            }
+            // Only reloadable keys differ (see `reload::RELOADABLE_KEYS`).
+            Ok(()) = reload.changed() => {
+                cfg = reload.borrow().clone();
+                brain.apply_config(&cfg);
+                continue;
+            }
+            res = snap_rx.changed() => {
+                // The dispatcher dropped the route: it is shutting down.
+                if res.is_err() { break; }
+            }
+        }
+        if *shutdown.borrow() {
+            break;
+        }
+        let Some(snap) = snap_rx.borrow_and_update().clone() else {
+            continue;
+        };
+        let Some((mut signal, metrics)) = brain.evaluate(&cfg, &shared, &snap) else {
+            continue;
+        };
+        let mut next_signal_id = shared.next_signal_id.lock().await;
+        let signal_id = *next_signal_id;
+        *next_signal_id = signal_id.next();
+        signal.signal_id = signal_id;
+        let q_req = signal.q_req;
+
+        let sent = shared.signal_tx.send(signal).await;
+        drop(next_signal_id);
+        match sent {
+            Ok(SendOutcome::Queued) => {
+                shared.health.inc_signals_emitted(1);
                 info!(
-                    signal_id,
+                    signal_id = signal_id.get(),
                     market_id = %snap.market_id,
                     bucket = %metrics.bucket.as_str(),
                     strategy = %metrics.strategy.as_str(),
                     worst_leg_token_id = %metrics.worst_leg_token_id,
                     raw_cost_bps = metrics.raw_cost_bps.raw(),
                     expected_net_bps = metrics.expected_net_bps.raw(),
-                    q_req,
+                    q_req = q_req.to_f64(),
                     "signal"
                 );
+                trace_signal!(
+                    signal_id,
+                    "brain_emit",
+                    market_id = %snap.market_id,
+                    expected_net_bps = metrics.expected_net_bps.raw(),
+                    q_req = q_req.to_f64()
+                );
             }
-            Err(tokio::sync::mpsc::error::TrySendError::Full(s)) => {
-                health.inc_signals_dropped(1);
+            Ok(SendOutcome::Dropped(s)) => {
+                shared.health.inc_signals_dropped(1);
+                trace_signal!(s.signal_id, "brain_channel_full_dropped");
                 warn!(
-                    signal_id = s.signal_id,
+                    signal_id = s.signal_id.get(),
                     market_id = %s.market_id,
                     bucket = %s.bucket.as_str(),
                     raw_cost_bps = s.raw_cost_bps.raw(),
@@ -238,7 +428,7 @@ pub async fn run(
                     "signal channel full; dropped"
                 );
             }
-            Err(tokio::sync::mpsc::error::TrySendError::Closed(_)) => {
+            Err(backpressure::Closed) => {
                 return Err(anyhow::anyhow!("signal receiver dropped"));
             }
         }
@@ -247,35 +437,37 @@ pub async fn run(
     Ok(())
 }
 
-fn eval_snapshot(cfg: &Config, snap: &MarketSnapshot) -> anyhow::Result<EvalMetrics> {
-    let strategy = match snap.legs.len() {
-        2 => Strategy::Binary,
-        3 => Strategy::Triangle,
-        n => anyhow::bail!("unsupported legs: {n}"),
+/// The strategy's draft merged with the bucket decision; `None` when the strategy has nothing
+/// to price.
+fn eval_snapshot(
+    strategy: &dyn strategy::Strategy,
+    cfg: &Config,
+    snap: &MarketSnapshot,
+    decision: BucketDecision,
+    fees: FeeRates,
+) -> Option<EvalMetrics> {
+    let ctx = StrategyContext {
+        fees,
+        risk_premium_bps: Bps::new(cfg.brain.risk_premium_bps),
     };
+    let SignalDraft {
+        strategy,
+        raw_cost_bps,
+        raw_edge_bps,
+        hard_fees_bps,
+        risk_premium_bps,
+        expected_net_bps,
+        legs,
+    } = strategy.evaluate(snap, &ctx)?;
 
-    let crate::buckets::BucketDecision {
+    let BucketDecision {
         bucket,
         worst_leg_token_id,
         metrics: bucket_metrics,
         reasons,
-    } = classify_bucket(snap);
-
-    let sum_ask: f64 = snap.legs.iter().map(|l| l.best_ask).sum();
-    if !sum_ask.is_finite() || sum_ask < 0.0 {
-        anyhow::bail!("invalid sum_ask={sum_ask}");
-    }
-
-    // Cost/gating conversion uses ceil to avoid overstating edge near thresholds.
-    let raw_cost_bps = Bps::from_price_cost(sum_ask);
-    let raw_edge_bps = Bps::ONE_HUNDRED_PERCENT - raw_cost_bps;
-
-    let hard_fees_bps = Bps::FEE_POLY + Bps::FEE_MERGE;
-    let risk_premium_bps = Bps::new(cfg.brain.risk_premium_bps);
-
-    let expected_net_bps = raw_edge_bps - hard_fees_bps - risk_premium_bps;
+    } = decision;
 
-    Ok(EvalMetrics {
+    Some(EvalMetrics {
         strategy,
         bucket,
         raw_cost_bps,
@@ -286,9 +478,23 @@ fn eval_snapshot(cfg: &Config, snap: &MarketSnapshot) -> anyhow::Result<EvalMetr
         bucket_metrics,
         worst_leg_token_id,
         reasons,
+        legs,
     })
 }
 
+/// `q_req` capped at the smallest quantity any leg's L2 book offers within `slippage_bps` above
+/// its best ask; unchanged when `slippage_bps` is 0 or for legs without a book.
+fn depth_capped_q_req(q_req: Qty, legs: &[LegSnapshot], slippage_bps: u32) -> Qty {
+    if slippage_bps == 0 {
+        return q_req;
+    }
+    let slippage = Bps::new(i32::try_from(slippage_bps).unwrap_or(i32::MAX));
+    legs.iter()
+        .filter(|l| !l.asks.is_empty())
+        .map(|l| l.asks.qty_at_or_below(l.best_ask.offset_bps(slippage)))
+        .fold(q_req, Qty::min)
+}
+
 fn should_emit(
     now_ms: u64,
     expected_net_bps: Bps,
@@ -319,11 +525,14 @@ fn should_emit(
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::buckets::{classify_bucket_at, FROZEN_DEPTH_LEVELS};
     use crate::config::{
-        BrainConfig, BucketConfig, CalibrationConfig, Config, LiveConfig, MarketSelectConfig,
-        PolymarketConfig, ReportConfig, RunConfig, ShadowConfig, SimConfig,
+        ApiConfig, BrainConfig, BucketConfig, CalibrationConfig, Config, DiskConfig, EmailConfig,
+        EscalationConfig, HealthConfig, LiveConfig, MarketSelectConfig, PolymarketConfig,
+        RecorderConfig, ReportConfig, RiskConfig, RunConfig, ShadowConfig, SimConfig, UploadConfig,
     };
-    use crate::types::LegSnapshot;
+    use crate::order_book::AskLadder;
+    use crate::types::Price;
 
     #[test]
     fn test_bps_from_price_rounding() {
@@ -339,14 +548,21 @@ mod tests {
                 market_ids: vec![],
                 snapshot_log_interval_ms: 1_000,
                 raw_ws_rotate_keep: 0,
+                raw_ws_rotate_interval_ms: 0,
+                raw_ws_max_total_mb: 0,
+                partition_hourly: false,
+                shutdown_deadline_ms: 10_000,
+                schedule: Default::default(),
+                labels: Default::default(),
             },
             schema_version: crate::schema::SCHEMA_VERSION.to_string(),
             brain: BrainConfig {
                 risk_premium_bps: 80,
                 min_net_edge_bps: 10,
-                q_req: 10.0,
+                q_req: Qty::from_f64(10.0).unwrap(),
                 signal_cooldown_ms: 0,
                 max_snapshot_staleness_ms: 500,
+                ..BrainConfig::default()
             },
             buckets: BucketConfig::default(),
             shadow: ShadowConfig::default(),
@@ -355,33 +571,60 @@ mod tests {
             live: LiveConfig::default(),
             calibration: CalibrationConfig::default(),
             sim: SimConfig::default(),
+            health: HealthConfig::default(),
+            disk: DiskConfig::default(),
+            risk: RiskConfig::default(),
+            api: ApiConfig::default(),
+            email: EmailConfig::default(),
+            escalation: EscalationConfig::default(),
+            upload: UploadConfig::default(),
+            recorder: RecorderConfig::default(),
+            backpressure: Default::default(),
+            bucket_classifier: Default::default(),
+            conflation: Default::default(),
+            demo: Default::default(),
+            reconcile: Default::default(),
+            fees: Default::default(),
+            gas: Default::default(),
+            metrics: Default::default(),
+            markets: Default::default(),
+            secrets: Default::default(),
         };
 
         let snap = MarketSnapshot {
-            market_id: "0xdeadbeef".to_string(),
+            market_id: "0xdeadbeef".into(),
             legs: vec![
                 LegSnapshot {
-                    token_id: "a".to_string(),
-                    best_ask: 0.48,
-                    best_bid: 0.4796,
-                    best_ask_size_best: 0.0,
-                    best_bid_size_best: 0.0,
+                    token_id: "a".into(),
+                    best_ask: Price::from_f64(0.48).unwrap(),
+                    best_bid: Price::from_f64(0.4796).unwrap(),
+                    best_ask_size_best: Qty::ZERO,
+                    best_bid_size_best: Qty::ZERO,
                     ask_depth3_usdc: 1000.0,
+                    asks: Default::default(),
                     ts_recv_us: 1,
                 },
                 LegSnapshot {
-                    token_id: "b".to_string(),
-                    best_ask: 0.49,
-                    best_bid: 0.4896,
-                    best_ask_size_best: 0.0,
-                    best_bid_size_best: 0.0,
+                    token_id: "b".into(),
+                    best_ask: Price::from_f64(0.49).unwrap(),
+                    best_bid: Price::from_f64(0.4896).unwrap(),
+                    best_ask_size_best: Qty::ZERO,
+                    best_bid_size_best: Qty::ZERO,
                     ask_depth3_usdc: 1000.0,
+                    asks: Default::default(),
                     ts_recv_us: 2,
                 },
             ],
         };
 
-        let metrics = eval_snapshot(&cfg, &snap).expect("eval");
+        let metrics = eval_snapshot(
+            &CompleteSetStrategy,
+            &cfg,
+            &snap,
+            classify_bucket_at(&snap, FROZEN_DEPTH_LEVELS),
+            FeeRates::FROZEN,
+        )
+        .expect("eval");
         assert_eq!(metrics.strategy, Strategy::Binary);
         assert_eq!(metrics.bucket, Bucket::Liquid);
         assert_eq!(metrics.raw_cost_bps.raw(), 9700);
@@ -393,6 +636,39 @@ mod tests {
         assert_eq!(metrics.bucket_metrics.worst_leg_index, 0);
     }
 
+    #[test]
+    fn depth_cap_sizes_q_req_to_the_thinnest_book_within_slippage() {
+        let px = |x: f64| Price::from_f64(x).unwrap();
+        let qty = |x: f64| Qty::from_f64(x).unwrap();
+        let leg = |best_ask: f64, asks: Vec<(Price, Qty)>| LegSnapshot {
+            token_id: "t".into(),
+            best_ask: px(best_ask),
+            best_bid: px(best_ask - 0.01),
+            best_ask_size_best: Qty::ZERO,
+            best_bid_size_best: Qty::ZERO,
+            ask_depth3_usdc: 1000.0,
+            asks: AskLadder::from_levels(asks),
+            ts_recv_us: 1,
+        };
+        let legs = vec![
+            leg(
+                0.50,
+                vec![
+                    (px(0.50), qty(4.0)),
+                    (px(0.505), qty(3.0)),
+                    (px(0.60), qty(50.0)),
+                ],
+            ),
+            leg(0.40, vec![(px(0.40), qty(100.0))]),
+            // No book: does not cap.
+            leg(0.10, Vec::new()),
+        ];
+        assert_eq!(depth_capped_q_req(qty(10.0), &legs, 0), qty(10.0));
+        // 100 bps above 0.50 reaches 0.505 but not 0.60.
+        assert_eq!(depth_capped_q_req(qty(10.0), &legs, 100), qty(7.0));
+        assert_eq!(depth_capped_q_req(qty(5.0), &legs, 100), qty(5.0));
+    }
+
     #[test]
     fn test_filter_min_net_edge() {
         let now_ms = 1_000;
@@ -440,14 +716,21 @@ mod tests {
                 market_ids: vec![],
                 snapshot_log_interval_ms: 1_000,
                 raw_ws_rotate_keep: 0,
+                raw_ws_rotate_interval_ms: 0,
+                raw_ws_max_total_mb: 0,
+                partition_hourly: false,
+                shutdown_deadline_ms: 10_000,
+                schedule: Default::default(),
+                labels: Default::default(),
             },
             schema_version: crate::schema::SCHEMA_VERSION.to_string(),
             brain: BrainConfig {
                 risk_premium_bps: 80,
                 min_net_edge_bps: 10,
-                q_req: 10.0,
+                q_req: Qty::from_f64(10.0).unwrap(),
                 signal_cooldown_ms: 0,
                 max_snapshot_staleness_ms: 500,
+                ..BrainConfig::default()
             },
             buckets: BucketConfig::default(),
             shadow: ShadowConfig::default(),
@@ -456,34 +739,131 @@ mod tests {
             live: LiveConfig::default(),
             calibration: CalibrationConfig::default(),
             sim: SimConfig::default(),
+            health: HealthConfig::default(),
+            disk: DiskConfig::default(),
+            risk: RiskConfig::default(),
+            api: ApiConfig::default(),
+            email: EmailConfig::default(),
+            escalation: EscalationConfig::default(),
+            upload: UploadConfig::default(),
+            recorder: RecorderConfig::default(),
+            backpressure: Default::default(),
+            bucket_classifier: Default::default(),
+            conflation: Default::default(),
+            demo: Default::default(),
+            reconcile: Default::default(),
+            fees: Default::default(),
+            gas: Default::default(),
+            metrics: Default::default(),
+            markets: Default::default(),
+            secrets: Default::default(),
         };
 
         let snap = MarketSnapshot {
-            market_id: "m".to_string(),
+            market_id: "m".into(),
             legs: vec![
                 LegSnapshot {
-                    token_id: "a".to_string(),
-                    best_ask: 0.6,
-                    best_bid: 0.5992,
-                    best_ask_size_best: 0.0,
-                    best_bid_size_best: 0.0,
+                    token_id: "a".into(),
+                    best_ask: Price::from_f64(0.6).unwrap(),
+                    best_bid: Price::from_f64(0.5992).unwrap(),
+                    best_ask_size_best: Qty::ZERO,
+                    best_bid_size_best: Qty::ZERO,
                     ask_depth3_usdc: 1_000.0,
+                    asks: Default::default(),
                     ts_recv_us: 0,
                 },
                 LegSnapshot {
-                    token_id: "b".to_string(),
-                    best_ask: 0.6,
-                    best_bid: 0.5992,
-                    best_ask_size_best: 0.0,
-                    best_bid_size_best: 0.0,
+                    token_id: "b".into(),
+                    best_ask: Price::from_f64(0.6).unwrap(),
+                    best_bid: Price::from_f64(0.5992).unwrap(),
+                    best_ask_size_best: Qty::ZERO,
+                    best_bid_size_best: Qty::ZERO,
                     ask_depth3_usdc: 1_000.0,
+                    asks: Default::default(),
                     ts_recv_us: 0,
                 },
             ],
         };
 
-        let metrics = eval_snapshot(&cfg, &snap).expect("eval");
+        let metrics = eval_snapshot(
+            &CompleteSetStrategy,
+            &cfg,
+            &snap,
+            classify_bucket_at(&snap, FROZEN_DEPTH_LEVELS),
+            FeeRates::FROZEN,
+        )
+        .expect("eval");
         assert_eq!(metrics.bucket, Bucket::Liquid);
         assert!(metrics.expected_net_bps <= Bps::ZERO);
     }
+
+    #[test]
+    fn cooldown_is_tracked_per_market() {
+        let cfg: Config = toml::from_str(
+            "[run]\nmarket_ids = [\"m1\", \"m2\", \"m3\"]\n\
+             [brain]\nsignal_cooldown_ms = 60000\nmin_net_edge_bps = 0\n\
+             [markets.\"m3\"]\nsignal_cooldown_ms = 0\n",
+        )
+        .unwrap();
+        let (signal_tx, _signal_rx) =
+            backpressure::channel::<Signal>("signal", 8, Default::default());
+        let clock = crate::clock::SimClock::starting_at_ms(1_700_000_000_000);
+        let shared = BrainShared {
+            run_id: "run_test".to_string(),
+            signal_tx,
+            health: Arc::new(HealthCounters::default()),
+            trade_arrivals: Arc::new(TradeArrivals::new(60_000)),
+            next_signal_id: Mutex::new(SignalId(1)),
+            fees: FeeRegistry::from_config(&cfg.fees),
+            clock: clock.clone(),
+        };
+        let leg = |token_id: &str, ask: f64| LegSnapshot {
+            token_id: token_id.into(),
+            best_ask: Price::from_f64(ask).unwrap(),
+            best_bid: Price::from_f64(ask - 0.0004).unwrap(),
+            best_ask_size_best: Qty::ZERO,
+            best_bid_size_best: Qty::ZERO,
+            ask_depth3_usdc: 1000.0,
+            asks: Default::default(),
+            ts_recv_us: 0,
+        };
+        let market = |id: &str| MarketDef {
+            market_id: id.into(),
+            token_ids: vec!["a".into(), "b".into()],
+        };
+        let snap = |id: &str| MarketSnapshot {
+            market_id: id.into(),
+            legs: vec![leg("a", 0.45), leg("b", 0.45)],
+        };
+
+        let mut m1 = MarketBrain::new(&cfg, &market("m1"));
+        let mut m2 = MarketBrain::new(&cfg, &market("m2"));
+        let mut m3 = MarketBrain::new(&cfg, &market("m3"));
+        assert!(m1.evaluate(&cfg, &shared, &snap("m1")).is_some());
+        clock.advance_ms(10);
+        assert!(
+            m1.evaluate(&cfg, &shared, &snap("m1")).is_none(),
+            "m1 cooling down"
+        );
+        assert!(
+            m2.evaluate(&cfg, &shared, &snap("m2")).is_some(),
+            "m1's cooldown does not suppress m2"
+        );
+        assert!(m3.evaluate(&cfg, &shared, &snap("m3")).is_some());
+        assert!(
+            m3.evaluate(&cfg, &shared, &snap("m3")).is_some(),
+            "m3 overrides the cooldown to 0"
+        );
+        // A snapshot of a market with another leg count is not this brain's.
+        assert!(m1
+            .evaluate(
+                &cfg,
+                &shared,
+                &MarketSnapshot {
+                    market_id: "m1".into(),
+                    legs: vec![leg("a", 0.3)],
+                }
+            )
+            .is_none());
+    }
 }
@@ -3,12 +3,21 @@ use std::path::{Path, PathBuf};
 
 use anyhow::Context as _;
 
-use crate::buckets::{classify_bucket, fill_share_p25};
-use crate::config::Config;
+use crate::accounting::{settle_set_per_leg, SetLeg};
+use crate::buckets::{fill_share_p25, trade_rate_per_min, BucketClassifier};
+use crate::config::{Config, VolumeMode};
+use crate::fees::FeeRegistry;
+use crate::recorder::csv_input_paths;
 use crate::schema::{
     FILE_RUN_CONFIG, FILE_SNAPSHOTS, FILE_TRADES, SNAPSHOTS_HEADER, TRADES_HEADER,
 };
-use crate::types::{Bps, LegSnapshot, MarketSnapshot, Signal, SignalLeg, Strategy, TradeTick};
+use crate::schema_adapt::{FrozenCsvReader, SchemaMode};
+use crate::strategy::{self, CompleteSetStrategy, SignalDraft, StrategyContext};
+use crate::trade_store::side_matches;
+use crate::types::{
+    Bps, LegSnapshot, MarketId, MarketSnapshot, Price, Qty, Side, Signal, SignalId, SignalLeg,
+    Strategy, TokenId, TradeTick,
+};
 
 pub const FILE_BRAIN_SWEEP_SCORES: &str = "brain_sweep_scores.csv";
 pub const FILE_BEST_BRAIN_PATCH: &str = "best_brain_patch.toml";
@@ -66,8 +75,9 @@ struct TimedSnapshot {
 #[derive(Debug, Clone, Copy)]
 struct TradeLite {
     ts_ms: u64,
-    price: f64,
-    size: f64,
+    price: Price,
+    size: Qty,
+    side: Option<Side>,
 }
 
 pub fn run_brain_sweep(run_dir: &Path, out_dir: &Path) -> anyhow::Result<BrainSweepResult> {
@@ -82,7 +92,9 @@ pub fn run_brain_sweep(run_dir: &Path, out_dir: &Path) -> anyhow::Result<BrainSw
         .unwrap_or_else(|_| "unknown".to_string());
 
     let snapshots = read_snapshots_csv(&run_dir.join(FILE_SNAPSHOTS)).context("read snapshots")?;
-    let trades_by_key = read_trades_by_key(&run_dir.join(FILE_TRADES)).context("read trades")?;
+    let trades_by_key =
+        read_trades_by_key(&csv_input_paths(run_dir, FILE_TRADES)).context("read trades")?;
+    let arrivals = trade_arrivals_by_market(&trades_by_key);
 
     let mut rows: Vec<BrainSweepScoreRow> = Vec::new();
 
@@ -94,7 +106,7 @@ pub fn run_brain_sweep(run_dir: &Path, out_dir: &Path) -> anyhow::Result<BrainSw
                 cfg.brain.risk_premium_bps = risk_premium_bps;
                 cfg.brain.signal_cooldown_ms = signal_cooldown_ms;
 
-                let signals = generate_signals(&cfg, "brain_sweep", &snapshots);
+                let signals = generate_signals(&cfg, "brain_sweep", &snapshots, &arrivals);
                 let score = score_signals(
                     &cfg,
                     &base_run_id,
@@ -219,7 +231,7 @@ fn score_signals(
     risk_premium_bps: i32,
     signal_cooldown_ms: u64,
     signals: &[Signal],
-    trades_by_key: &HashMap<(String, String), Vec<TradeLite>>,
+    trades_by_key: &HashMap<(MarketId, TokenId), Vec<TradeLite>>,
 ) -> BrainSweepScoreRow {
     let mut total_pnl_sum: f64 = 0.0;
     let mut set_ratio_sum: f64 = 0.0;
@@ -229,8 +241,9 @@ fn score_signals(
     let mut ok: u64 = 0;
     let mut bad: u64 = 0;
 
+    let fees = FeeRegistry::from_config(&cfg.fees);
     for s in signals {
-        match settle_one(cfg, s, trades_by_key) {
+        match settle_one(cfg, &fees, s, trades_by_key) {
             Some((total_pnl, set_ratio)) => {
                 ok += 1;
                 total_pnl_sum += total_pnl;
@@ -283,101 +296,95 @@ fn score_signals(
 
 fn settle_one(
     cfg: &Config,
+    fees: &FeeRegistry,
     s: &Signal,
-    trades_by_key: &HashMap<(String, String), Vec<TradeLite>>,
+    trades_by_key: &HashMap<(MarketId, TokenId), Vec<TradeLite>>,
 ) -> Option<(f64, f64)> {
     let legs_n = s.legs.len();
     if !(2..=3).contains(&legs_n) {
         return None;
     }
-    if !s.q_req.is_finite() || s.q_req <= 0.0 {
+    if !s.q_req.is_positive() {
         return None;
     }
 
     let mut legs = s.legs.clone();
     legs.sort_by_key(|l| l.leg_index);
 
-    let window_start_ms = s.signal_ts_ms + cfg.shadow.window_start_ms;
-    let window_end_ms = s.signal_ts_ms + cfg.shadow.window_end_ms;
+    let market = cfg.market(&s.market_id);
+    let window_start_ms = s.signal_ts_ms + market.window_start_ms;
+    let window_end_ms = s.signal_ts_ms + market.window_end_ms;
     if window_start_ms > window_end_ms {
         return None;
     }
 
-    let fill_share_used = fill_share_p25(s.bucket, &cfg.buckets);
-    if !fill_share_used.is_finite() || fill_share_used < 0.0 {
+    let leg_fill_shares: Vec<f64> = legs
+        .iter()
+        .map(|l| fill_share_p25(s.leg_bucket(l.leg_index), &market.buckets))
+        .collect();
+    if leg_fill_shares.iter().any(|f| !f.is_finite() || *f < 0.0) {
         return None;
     }
 
-    let mut v_mkt: [f64; 3] = [0.0, 0.0, 0.0];
-    let mut q_fill: [f64; 3] = [0.0, 0.0, 0.0];
+    let mut v_mkt = [Qty::ZERO; 3];
     for (i, leg) in legs.iter().take(3).enumerate() {
-        if !leg.limit_price.is_finite() || leg.limit_price <= 0.0 {
+        if !leg.limit_price.is_positive() {
             return None;
         }
         let key = (s.market_id.clone(), leg.token_id.clone());
         if let Some(trades) = trades_by_key.get(&key) {
-            v_mkt[i] =
-                volume_at_or_better_price(trades, window_start_ms, window_end_ms, leg.limit_price);
+            let aggressor = match cfg.shadow.volume_mode {
+                VolumeMode::All => None,
+                VolumeMode::Sided => Some(leg.side.opposite()),
+            };
+            v_mkt[i] = volume_at_or_better_price(
+                trades,
+                window_start_ms,
+                window_end_ms,
+                leg.limit_price,
+                aggressor,
+            );
         }
-        q_fill[i] = (v_mkt[i] * fill_share_used).min(s.q_req);
     }
 
-    let q_set = q_fill[..legs_n]
+    let set_legs: Vec<SetLeg> = legs
         .iter()
-        .copied()
-        .fold(f64::INFINITY, f64::min)
-        .min(s.q_req);
-    let q_set = if q_set.is_finite() { q_set } else { 0.0 };
-
-    let mut cost_set_per_unit: f64 = 0.0;
-    for leg in legs.iter().take(legs_n) {
-        cost_set_per_unit += Bps::FEE_POLY.apply_cost(leg.limit_price);
-    }
-    let cost_set = q_set * cost_set_per_unit;
-    let proceeds_set = q_set * Bps::FEE_MERGE.apply_proceeds(1.0);
-    let pnl_set = proceeds_set - cost_set;
-
-    let dump_slippage_assumed = crate::schema::DUMP_SLIPPAGE_ASSUMED;
-    let mut pnl_left_total: f64 = 0.0;
-    for (i, leg) in legs.iter().take(legs_n).enumerate() {
-        let q_left = q_fill[i] - q_set;
-        if q_left <= 0.0 {
-            continue;
-        }
-        let exit_price = leg.best_bid_at_signal.max(0.0) * (1.0 - dump_slippage_assumed);
-        let proceeds_left_per_unit = Bps::FEE_POLY.apply_proceeds(exit_price);
-        let cost_left_per_unit = Bps::FEE_POLY.apply_cost(leg.limit_price);
-        pnl_left_total += q_left * (proceeds_left_per_unit - cost_left_per_unit);
-    }
-
-    let total_pnl = pnl_set + pnl_left_total;
-    let q_fill_avg = q_fill[..legs_n].iter().sum::<f64>() / (legs_n as f64);
-    let set_ratio = if q_fill_avg > 0.0 {
-        q_set / q_fill_avg
-    } else {
-        0.0
-    };
-
-    Some((total_pnl, set_ratio))
+        .zip(&v_mkt)
+        .map(|(l, &v)| SetLeg {
+            p_limit: l.limit_price.to_f64(),
+            best_bid: l.best_bid_at_signal.to_f64(),
+            v_mkt: v.to_f64(),
+        })
+        .collect();
+    let settled = settle_set_per_leg(
+        s.q_req.to_f64(),
+        &set_legs,
+        &leg_fill_shares,
+        crate::schema::DUMP_SLIPPAGE_ASSUMED,
+        fees.resolve(s.signal_ts_ms).rates,
+    );
+
+    Some((settled.total_pnl, settled.set_ratio))
 }
 
 fn volume_at_or_better_price(
     trades: &[TradeLite],
     start_ms: u64,
     end_ms: u64,
-    price_limit: f64,
-) -> f64 {
-    if start_ms > end_ms || !price_limit.is_finite() {
-        return 0.0;
+    price_limit: Price,
+    aggressor: Option<Side>,
+) -> Qty {
+    if start_ms > end_ms {
+        return Qty::ZERO;
     }
 
     let start_idx = lower_bound(trades, start_ms);
-    let mut vol: f64 = 0.0;
+    let mut vol = Qty::ZERO;
     for t in &trades[start_idx..] {
         if t.ts_ms > end_ms {
             break;
         }
-        if t.price <= price_limit {
+        if t.price <= price_limit && side_matches(t.side, aggressor) {
             vol += t.size;
         }
     }
@@ -398,36 +405,52 @@ fn lower_bound(trades: &[TradeLite], ts_ms: u64) -> usize {
     lo
 }
 
-fn generate_signals(cfg: &Config, run_id: &str, snapshots: &[TimedSnapshot]) -> Vec<Signal> {
+fn generate_signals(
+    cfg: &Config,
+    run_id: &str,
+    snapshots: &[TimedSnapshot],
+    arrivals: &HashMap<MarketId, Vec<u64>>,
+) -> Vec<Signal> {
     let mut out: Vec<Signal> = Vec::new();
-    let mut next_signal_id: u64 = 1;
-    let mut last_by_key: HashMap<(String, Strategy, i32), u64> = HashMap::new();
+    let mut next_signal_id = SignalId(1);
+    let mut last_by_key: HashMap<(MarketId, Strategy, i32), u64> = HashMap::new();
 
     let cooldown_ms = cfg.brain.signal_cooldown_ms;
     let min_net_edge = Bps::new(cfg.brain.min_net_edge_bps);
+    let mut classifier =
+        BucketClassifier::new(&cfg.bucket_classifier).with_depth_levels(cfg.buckets.depth_levels);
+    let window_ms = cfg.bucket_classifier.v2.window_ms;
+    let fees = FeeRegistry::from_config(&cfg.fees);
+    let strategy_impl =
+        strategy::from_name(&cfg.brain.strategy).unwrap_or_else(|| Box::new(CompleteSetStrategy));
 
     for s in snapshots {
         let snap = &s.snapshot;
-        let strategy = match snap.legs.len() {
-            2 => Strategy::Binary,
-            3 => Strategy::Triangle,
-            _ => continue,
-        };
-
-        let decision = classify_bucket(snap);
-
-        let sum_ask: f64 = snap.legs.iter().map(|l| l.best_ask).sum();
-        if !sum_ask.is_finite() || sum_ask < 0.0 {
+        if !(2..=3).contains(&snap.legs.len()) {
             continue;
         }
 
-        // Cost/gating conversion uses ceil to avoid overstating edge.
-        let raw_cost_bps = Bps::from_price_cost(sum_ask);
-        let raw_edge_bps = Bps::ONE_HUNDRED_PERCENT - raw_cost_bps;
+        let trades_per_min = arrivals
+            .get(&snap.market_id)
+            .map_or(0.0, |ts| trade_rate_per_min(ts, s.ts_ms, window_ms));
+        let decision = classifier.classify(snap, s.ts_ms, trades_per_min);
 
-        let hard_fees_bps = Bps::FEE_POLY + Bps::FEE_MERGE;
-        let risk_premium_bps = Bps::new(cfg.brain.risk_premium_bps);
-        let expected_net_bps = raw_edge_bps - hard_fees_bps - risk_premium_bps;
+        let ctx = StrategyContext {
+            fees: fees.resolve(s.ts_ms).rates,
+            risk_premium_bps: Bps::new(cfg.brain.risk_premium_bps),
+        };
+        let Some(SignalDraft {
+            strategy,
+            raw_cost_bps,
+            raw_edge_bps,
+            hard_fees_bps,
+            risk_premium_bps,
+            expected_net_bps,
+            legs: draft_legs,
+        }) = strategy_impl.evaluate(snap, &ctx)
+        else {
+            continue;
+        };
 
         if expected_net_bps < min_net_edge {
             continue;
@@ -442,20 +465,10 @@ fn generate_signals(cfg: &Config, run_id: &str, snapshots: &[TimedSnapshot]) ->
             }
         }
 
-        let q_req = cfg.brain.q_req;
-        let legs: Vec<SignalLeg> = snap
-            .legs
-            .iter()
-            .enumerate()
-            .map(|(idx, l)| SignalLeg {
-                leg_index: idx,
-                token_id: l.token_id.clone(),
-                side: crate::types::Side::Buy,
-                limit_price: l.best_ask,
-                qty: q_req,
-                best_bid_at_signal: l.best_bid,
-                best_ask_at_signal: l.best_ask,
-            })
+        let q_req = cfg.market(&snap.market_id).q_req;
+        let legs: Vec<SignalLeg> = draft_legs
+            .into_iter()
+            .map(|l| SignalLeg { qty: q_req, ..l })
             .collect();
 
         out.push(Signal {
@@ -477,31 +490,25 @@ fn generate_signals(cfg: &Config, run_id: &str, snapshots: &[TimedSnapshot]) ->
         });
 
         last_by_key.insert(key, s.ts_ms);
-        next_signal_id += 1;
+        next_signal_id = next_signal_id.next();
     }
 
     out
 }
 
 fn read_snapshots_csv(path: &Path) -> anyhow::Result<Vec<TimedSnapshot>> {
-    let mut rdr = csv::ReaderBuilder::new()
-        .flexible(true)
-        .trim(csv::Trim::All)
-        .from_path(path)
-        .with_context(|| format!("open {}", path.display()))?;
-    let header = rdr
-        .headers()
-        .with_context(|| format!("read header {}", path.display()))?
-        .clone();
-    if header.iter().map(|s| s.trim()).collect::<Vec<_>>() != SNAPSHOTS_HEADER {
-        anyhow::bail!("snapshots.csv header mismatch (expected frozen SNAPSHOTS_HEADER)");
-    }
+    let mut rdr = FrozenCsvReader::open(
+        path,
+        FILE_SNAPSHOTS,
+        &SNAPSHOTS_HEADER,
+        SchemaMode::from_env(),
+    )?;
 
     let mut out: Vec<TimedSnapshot> = Vec::new();
     for record in rdr.records() {
         let record = record?;
         let ts_ms = record.get(0).and_then(parse_u64).context("ts_ms")?;
-        let market_id = record.get(1).unwrap_or("").trim().to_string();
+        let market_id = MarketId::from(record.get(1).unwrap_or("").trim());
         let legs_n = record.get(2).and_then(parse_u64).context("legs_n")? as usize;
         if !(2..=3).contains(&legs_n) {
             continue;
@@ -510,20 +517,29 @@ fn read_snapshots_csv(path: &Path) -> anyhow::Result<Vec<TimedSnapshot>> {
         let mut legs: Vec<LegSnapshot> = Vec::with_capacity(legs_n);
         for i in 0..legs_n {
             let base = 3 + i * 4;
-            let token_id = record.get(base).unwrap_or("").trim().to_string();
+            let token_id = TokenId::from(record.get(base).unwrap_or("").trim());
             if token_id.is_empty() {
                 continue;
             }
-            let best_bid = record.get(base + 1).and_then(parse_f64).unwrap_or(0.0);
-            let best_ask = record.get(base + 2).and_then(parse_f64).unwrap_or(1.0);
+            let best_bid = record
+                .get(base + 1)
+                .and_then(parse_f64)
+                .and_then(Price::from_f64)
+                .unwrap_or(Price::MISSING_BID);
+            let best_ask = record
+                .get(base + 2)
+                .and_then(parse_f64)
+                .and_then(Price::from_f64)
+                .unwrap_or(Price::MISSING_ASK);
             let depth3 = record.get(base + 3).and_then(parse_f64).unwrap_or(f64::NAN);
             legs.push(LegSnapshot {
                 token_id,
                 best_bid,
                 best_ask,
-                best_ask_size_best: 0.0,
-                best_bid_size_best: 0.0,
+                best_ask_size_best: Qty::ZERO,
+                best_bid_size_best: Qty::ZERO,
                 ask_depth3_usdc: depth3,
+                asks: Default::default(),
                 ts_recv_us: ts_ms * 1000,
             });
         }
@@ -540,36 +556,51 @@ fn read_snapshots_csv(path: &Path) -> anyhow::Result<Vec<TimedSnapshot>> {
     Ok(out)
 }
 
-fn read_trades_by_key(path: &Path) -> anyhow::Result<HashMap<(String, String), Vec<TradeLite>>> {
-    let mut rdr = csv::ReaderBuilder::new()
-        .flexible(true)
-        .trim(csv::Trim::All)
-        .from_path(path)
-        .with_context(|| format!("open {}", path.display()))?;
-    let header = rdr
-        .headers()
-        .with_context(|| format!("read header {}", path.display()))?
-        .clone();
-    if header.iter().map(|s| s.trim()).collect::<Vec<_>>() != TRADES_HEADER {
-        anyhow::bail!("trades.csv header mismatch (expected frozen TRADES_HEADER)");
+/// Sorted trade arrival times per market, the v2 classifier's trade-rate input.
+fn trade_arrivals_by_market(
+    trades_by_key: &HashMap<(MarketId, TokenId), Vec<TradeLite>>,
+) -> HashMap<MarketId, Vec<u64>> {
+    let mut out: HashMap<MarketId, Vec<u64>> = HashMap::new();
+    for ((market_id, _), trades) in trades_by_key {
+        out.entry(market_id.clone())
+            .or_default()
+            .extend(trades.iter().map(|t| t.ts_ms));
+    }
+    for ts in out.values_mut() {
+        ts.sort_unstable();
     }
+    out
+}
 
-    let mut out: HashMap<(String, String), Vec<TradeLite>> = HashMap::new();
-    for record in rdr.records() {
-        let record = record?;
-        let tick = parse_trade_tick(&record)?;
-        let ts_ms = if tick.ingest_ts_ms > 0 {
-            tick.ingest_ts_ms
-        } else {
-            tick.ts_ms
-        };
-        out.entry((tick.market_id, tick.token_id))
-            .or_default()
-            .push(TradeLite {
-                ts_ms,
-                price: tick.price,
-                size: tick.size,
-            });
+/// Reads every `trades.csv` of a run: the flat file and/or its hourly partitions.
+fn read_trades_by_key(
+    paths: &[PathBuf],
+) -> anyhow::Result<HashMap<(MarketId, TokenId), Vec<TradeLite>>> {
+    if paths.is_empty() {
+        anyhow::bail!("no trades.csv found (flat or partitioned)");
+    }
+    let mut out: HashMap<(MarketId, TokenId), Vec<TradeLite>> = HashMap::new();
+    for path in paths {
+        let mut rdr =
+            FrozenCsvReader::open(path, FILE_TRADES, &TRADES_HEADER, SchemaMode::from_env())?;
+
+        for record in rdr.records() {
+            let record = record?;
+            let tick = parse_trade_tick(&record)?;
+            let ts_ms = if tick.ingest_ts_ms > 0 {
+                tick.ingest_ts_ms
+            } else {
+                tick.ts_ms
+            };
+            out.entry((tick.market_id, tick.token_id))
+                .or_default()
+                .push(TradeLite {
+                    ts_ms,
+                    price: tick.price,
+                    size: tick.size,
+                    side: tick.side,
+                });
+        }
     }
     for v in out.values_mut() {
         v.sort_by_key(|t| t.ts_ms);
@@ -579,13 +610,22 @@ fn read_trades_by_key(path: &Path) -> anyhow::Result<HashMap<(String, String), V
 
 fn parse_trade_tick(record: &csv::StringRecord) -> anyhow::Result<TradeTick> {
     let ts_ms = record.get(0).and_then(parse_u64).context("ts_ms")?;
-    let market_id = record.get(1).unwrap_or("").trim().to_string();
-    let token_id = record.get(2).unwrap_or("").trim().to_string();
-    let price = record.get(3).and_then(parse_f64).context("price")?;
-    let size = record.get(4).and_then(parse_f64).context("size")?;
+    let market_id = MarketId::from(record.get(1).unwrap_or("").trim());
+    let token_id = TokenId::from(record.get(2).unwrap_or("").trim());
+    let price = record
+        .get(3)
+        .and_then(parse_f64)
+        .and_then(Price::from_f64)
+        .context("price")?;
+    let size = record
+        .get(4)
+        .and_then(parse_f64)
+        .and_then(Qty::from_f64)
+        .context("size")?;
     let trade_id = record.get(5).unwrap_or("").trim().to_string();
     let ingest_ts_ms = record.get(6).and_then(parse_u64).unwrap_or(ts_ms);
     let exchange_ts_ms = record.get(7).and_then(parse_u64);
+    let side = record.get(8).and_then(Side::parse);
 
     Ok(TradeTick {
         ts_ms,
@@ -596,6 +636,7 @@ fn parse_trade_tick(record: &csv::StringRecord) -> anyhow::Result<TradeTick> {
         price,
         size,
         trade_id,
+        side,
     })
 }
 
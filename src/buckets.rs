@@ -1,9 +1,20 @@
-use crate::config::BucketConfig;
+use std::collections::{HashMap, VecDeque};
+use std::path::Path;
+use std::sync::Mutex;
+
+use crate::config::{BucketClassifierConfig, BucketClassifierV2Config, BucketConfig};
 use crate::reasons::ShadowNoteReason;
-use crate::types::{Bps, Bucket, BucketMetrics, MarketSnapshot};
+use crate::schema::FILE_RUN_CONFIG;
+use crate::types::{
+    Bps, Bucket, BucketClassifierVersion, BucketMetrics, LegSnapshot, MarketId, MarketSnapshot,
+    Price, TokenId,
+};
 
 const INVALID_SPREAD_BPS: Bps = Bps(i32::MAX);
 const MAX_DEPTH3_USDC: f64 = 10_000_000.0;
+/// `buckets.depth_levels` of the Frozen Spec: the `ask_depth3_usdc` the feed computes from
+/// `book` frames.
+pub const FROZEN_DEPTH_LEVELS: usize = 3;
 
 pub fn fill_share_p25(bucket: Bucket, cfg: &BucketConfig) -> f64 {
     match bucket {
@@ -15,87 +26,362 @@ pub fn fill_share_p25(bucket: Bucket, cfg: &BucketConfig) -> f64 {
 #[derive(Debug, Clone)]
 pub struct BucketDecision {
     pub bucket: Bucket,
-    pub worst_leg_token_id: String,
+    pub worst_leg_token_id: TokenId,
     pub metrics: BucketMetrics,
     pub reasons: Vec<ShadowNoteReason>,
 }
 
-pub fn classify_bucket(snapshot: &MarketSnapshot) -> BucketDecision {
+/// The worst-leg rule with leg depth measured over the `depth_levels` cheapest asks (see
+/// [`leg_depth_usdc`]). The frozen top-3 measure is always reported in `worst_depth3_usdc` /
+/// `is_depth3_degraded`; the depth the bucket was decided on is `worst_depth_usdc`.
+pub fn classify_bucket_at(snapshot: &MarketSnapshot, depth_levels: usize) -> BucketDecision {
     if snapshot.legs.is_empty() {
         return BucketDecision {
             bucket: Bucket::Thin,
-            worst_leg_token_id: String::new(),
+            worst_leg_token_id: TokenId::default(),
             metrics: BucketMetrics {
                 worst_leg_index: 0,
                 worst_spread_bps: i32::MAX,
                 worst_depth3_usdc: f64::NAN,
                 is_depth3_degraded: true,
+                depth_levels,
+                worst_depth_usdc: f64::NAN,
+                classifier: BucketClassifierVersion::V1,
+                leg_buckets: Vec::new(),
             },
             reasons: vec![ShadowNoteReason::BucketThinNan],
         };
     }
 
-    let mut is_depth3_degraded = false;
-    let mut depth_unit_suspect = false;
-    let mut worst_leg_index = 0usize;
-    let mut worst_depth = f64::INFINITY;
-
-    for (idx, leg) in snapshot.legs.iter().enumerate() {
-        let d = depth_sanitize(leg.ask_depth3_usdc);
-        if !leg.ask_depth3_usdc.is_finite()
-            || leg.ask_depth3_usdc <= 0.0
-            || leg.ask_depth3_usdc > MAX_DEPTH3_USDC
-        {
-            is_depth3_degraded = true;
-            if leg.ask_depth3_usdc.is_finite() && leg.ask_depth3_usdc > MAX_DEPTH3_USDC {
-                depth_unit_suspect = true;
-            }
-        }
-        if d < worst_depth {
-            worst_depth = d;
-            worst_leg_index = idx;
-        }
-    }
+    let worst_depth = worst_leg_depth(snapshot, depth_levels);
+    let frozen = if depth_levels == FROZEN_DEPTH_LEVELS {
+        worst_depth
+    } else {
+        worst_leg_depth(snapshot, FROZEN_DEPTH_LEVELS)
+    };
 
+    let worst_leg_index = worst_depth.leg_index;
+    let is_degraded = worst_depth.is_degraded;
     let worst = &snapshot.legs[worst_leg_index];
     let spread = spread_bps(worst.best_bid, worst.best_ask).raw();
-    let worst_depth3 = if is_depth3_degraded {
-        f64::NAN
-    } else {
-        worst_depth
-    };
 
-    let bucket = if !is_depth3_degraded && spread < 20 && worst_depth3 > 500.0 {
+    let bucket = if !is_degraded && spread < 20 && worst_depth.depth_usdc > 500.0 {
         Bucket::Liquid
     } else {
         Bucket::Thin
     };
 
     let mut reasons: Vec<ShadowNoteReason> = Vec::new();
-    if depth_unit_suspect {
+    if worst_depth.unit_suspect {
         reasons.push(ShadowNoteReason::DepthUnitSuspect);
     }
-    if bucket == Bucket::Thin && (is_depth3_degraded || spread == INVALID_SPREAD_BPS.raw()) {
+    if bucket == Bucket::Thin && (is_degraded || spread == INVALID_SPREAD_BPS.raw()) {
         reasons.push(ShadowNoteReason::BucketThinNan);
     }
 
     BucketDecision {
         bucket,
-        worst_leg_token_id: if is_depth3_degraded || spread == INVALID_SPREAD_BPS.raw() {
-            String::new()
+        worst_leg_token_id: if is_degraded || spread == INVALID_SPREAD_BPS.raw() {
+            TokenId::default()
         } else {
             worst.token_id.clone()
         },
         metrics: BucketMetrics {
             worst_leg_index,
             worst_spread_bps: spread,
-            worst_depth3_usdc: worst_depth3,
-            is_depth3_degraded,
+            worst_depth3_usdc: frozen.depth_usdc,
+            is_depth3_degraded: frozen.is_degraded,
+            depth_levels,
+            worst_depth_usdc: worst_depth.depth_usdc,
+            classifier: BucketClassifierVersion::V1,
+            leg_buckets: Vec::new(),
         },
         reasons,
     }
 }
 
+/// The thinnest leg of a snapshot over some number of ask levels.
+#[derive(Clone, Copy)]
+struct WorstLegDepth {
+    leg_index: usize,
+    /// NaN when any leg's depth is degraded.
+    depth_usdc: f64,
+    is_degraded: bool,
+    unit_suspect: bool,
+}
+
+fn worst_leg_depth(snapshot: &MarketSnapshot, depth_levels: usize) -> WorstLegDepth {
+    let mut is_degraded = false;
+    let mut unit_suspect = false;
+    let mut leg_index = 0usize;
+    let mut worst_depth = f64::INFINITY;
+
+    for (idx, leg) in snapshot.legs.iter().enumerate() {
+        let depth = leg_depth_usdc(leg, depth_levels);
+        let d = depth_sanitize(depth);
+        if !depth.is_finite() || depth <= 0.0 || depth > MAX_DEPTH3_USDC {
+            is_degraded = true;
+            if depth.is_finite() && depth > MAX_DEPTH3_USDC {
+                unit_suspect = true;
+            }
+        }
+        if d < worst_depth {
+            worst_depth = d;
+            leg_index = idx;
+        }
+    }
+
+    WorstLegDepth {
+        leg_index,
+        depth_usdc: if is_degraded { f64::NAN } else { worst_depth },
+        is_degraded,
+        unit_suspect,
+    }
+}
+
+/// The worst-leg thresholds applied to one leg on its own; a degraded depth or an invalid
+/// spread is thin.
+pub fn classify_leg(leg: &LegSnapshot, depth_levels: usize) -> Bucket {
+    let depth = leg_depth_usdc(leg, depth_levels);
+    let depth_ok = depth.is_finite() && depth > 0.0 && depth <= MAX_DEPTH3_USDC;
+    let spread = spread_bps(leg.best_bid, leg.best_ask).raw();
+    if depth_ok && spread < 20 && depth > 500.0 {
+        Bucket::Liquid
+    } else {
+        Bucket::Thin
+    }
+}
+
+/// USDC offered over the leg's `depth_levels` cheapest asks. The frozen 3 is the feed's
+/// `ask_depth3_usdc`; any other count walks the L2 ladder, and a leg without one (snapshots.csv
+/// replay, demo feed) is NaN, i.e. degraded.
+pub fn leg_depth_usdc(leg: &LegSnapshot, depth_levels: usize) -> f64 {
+    if depth_levels == FROZEN_DEPTH_LEVELS {
+        leg.ask_depth3_usdc
+    } else if leg.asks.is_empty() {
+        f64::NAN
+    } else {
+        leg.asks.depth_usdc(depth_levels)
+    }
+}
+
+/// `buckets.depth_levels` of the run in `dir` from its `config.toml` snapshot; the frozen 3
+/// when absent.
+pub fn depth_levels_for_run_dir(dir: &Path) -> usize {
+    std::fs::read_to_string(dir.join(FILE_RUN_CONFIG))
+        .ok()
+        .and_then(|raw| toml::from_str::<toml::Table>(&raw).ok())
+        .and_then(|t| t.get("buckets").cloned())
+        .and_then(|v| v.try_into::<BucketConfig>().ok())
+        .map_or(FROZEN_DEPTH_LEVELS, |b| b.depth_levels)
+}
+
+/// Recent trade arrival times per market, the trade-rate input of the v2 classifier. The
+/// trades poller records into it and the brain reads it.
+#[derive(Debug)]
+pub struct TradeArrivals {
+    window_ms: u64,
+    by_market: Mutex<HashMap<MarketId, VecDeque<u64>>>,
+}
+
+impl TradeArrivals {
+    pub fn new(window_ms: u64) -> Self {
+        Self {
+            window_ms: window_ms.max(1),
+            by_market: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Records a trade ingested at `ts_ms`; arrivals must come in ingest order.
+    pub fn record(&self, market_id: &MarketId, ts_ms: u64) {
+        let mut by_market = self.by_market.lock().unwrap_or_else(|e| e.into_inner());
+        let q = by_market.entry(market_id.clone()).or_default();
+        q.push_back(ts_ms);
+        while q.front().is_some_and(|&t| t + self.window_ms <= ts_ms) {
+            q.pop_front();
+        }
+    }
+
+    /// Trades per minute of `market_id` over the window ending at `now_ms`.
+    pub fn rate_per_min(&self, market_id: &MarketId, now_ms: u64) -> f64 {
+        let by_market = self.by_market.lock().unwrap_or_else(|e| e.into_inner());
+        let n = by_market.get(market_id).map_or(0, |q| {
+            q.iter()
+                .filter(|&&t| t <= now_ms && t + self.window_ms > now_ms)
+                .count()
+        });
+        per_min(n, self.window_ms)
+    }
+}
+
+/// Trades per minute over the window ending at `now_ms`, from one market's sorted arrival
+/// times (the offline counterpart of [`TradeArrivals::rate_per_min`]).
+#[allow(dead_code)]
+pub fn trade_rate_per_min(sorted_ts_ms: &[u64], now_ms: u64, window_ms: u64) -> f64 {
+    let window_ms = window_ms.max(1);
+    let lo = sorted_ts_ms.partition_point(|&t| t + window_ms <= now_ms);
+    let hi = sorted_ts_ms.partition_point(|&t| t <= now_ms);
+    per_min(hi.saturating_sub(lo), window_ms)
+}
+
+fn per_min(n: usize, window_ms: u64) -> f64 {
+    n as f64 * 60_000.0 / window_ms as f64
+}
+
+/// The v2 inputs of one snapshot.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct V2Features {
+    pub trades_per_min: f64,
+    /// `spread_percentile` of the worst-leg spread over the window.
+    pub spread_bps: f64,
+    pub depth3_usdc: f64,
+    /// Coefficient of variation of the worst-leg depth over the window.
+    pub depth_cv: f64,
+}
+
+impl V2Features {
+    /// Weighted mean of the per-feature scores, in [0, 1].
+    pub fn score(&self, cfg: &BucketClassifierV2Config) -> f64 {
+        let w = &cfg.weights;
+        let total = w.sum();
+        if total <= 0.0 {
+            return 0.0;
+        }
+        let weighted = w.trade_rate * at_least(self.trades_per_min, cfg.min_trades_per_min)
+            + w.spread * at_most(self.spread_bps, cfg.max_spread_bps)
+            + w.depth * at_least(self.depth3_usdc, cfg.min_depth3_usdc)
+            + w.depth_stability * at_most(self.depth_cv, cfg.max_depth_cv);
+        weighted / total
+    }
+}
+
+fn at_least(v: f64, min: f64) -> f64 {
+    if !v.is_finite() {
+        return 0.0;
+    }
+    (v / min).clamp(0.0, 1.0)
+}
+
+fn at_most(v: f64, max: f64) -> f64 {
+    if !v.is_finite() {
+        0.0
+    } else if v <= max {
+        1.0
+    } else {
+        max / v
+    }
+}
+
+/// The classifier selected by `[bucket_classifier] version`. v2 keeps a rolling window of each
+/// market's worst-leg spread and depth, so one instance should see every snapshot of a market
+/// in time order.
+pub struct BucketClassifier {
+    cfg: BucketClassifierConfig,
+    depth_levels: usize,
+    /// `(ts_ms, worst_spread_bps, worst_depth_usdc)` per market, oldest first.
+    history: HashMap<MarketId, VecDeque<(u64, i32, f64)>>,
+}
+
+impl BucketClassifier {
+    pub fn new(cfg: &BucketClassifierConfig) -> Self {
+        Self {
+            cfg: cfg.clone(),
+            depth_levels: FROZEN_DEPTH_LEVELS,
+            history: HashMap::new(),
+        }
+    }
+
+    /// Measures leg depth over `buckets.depth_levels` asks instead of the frozen 3.
+    pub fn with_depth_levels(mut self, depth_levels: usize) -> Self {
+        self.depth_levels = depth_levels;
+        self
+    }
+
+    pub fn version(&self) -> BucketClassifierVersion {
+        self.cfg.version
+    }
+
+    /// Classifies `snap` taken at `now_ms`; `trades_per_min` is the market's trade arrival rate
+    /// over `v2.window_ms` (unused by v1). A snapshot v1 already rejects as degraded stays thin.
+    /// With `per_leg`, each leg is also bucketed on its own by [`classify_leg`].
+    pub fn classify(
+        &mut self,
+        snap: &MarketSnapshot,
+        now_ms: u64,
+        trades_per_min: f64,
+    ) -> BucketDecision {
+        let mut d = classify_bucket_at(snap, self.depth_levels);
+        if self.cfg.per_leg {
+            d.metrics.leg_buckets = snap
+                .legs
+                .iter()
+                .map(|l| classify_leg(l, self.depth_levels))
+                .collect();
+        }
+        if self.cfg.version == BucketClassifierVersion::V1 {
+            return d;
+        }
+        d.metrics.classifier = BucketClassifierVersion::V2;
+        if d.metrics.worst_depth_usdc.is_nan()
+            || d.metrics.worst_spread_bps == INVALID_SPREAD_BPS.raw()
+        {
+            return d;
+        }
+
+        let v2 = &self.cfg.v2;
+        let hist = self.history.entry(snap.market_id.clone()).or_default();
+        hist.push_back((
+            now_ms,
+            d.metrics.worst_spread_bps,
+            d.metrics.worst_depth_usdc,
+        ));
+        while hist
+            .front()
+            .is_some_and(|&(t, _, _)| t + v2.window_ms <= now_ms)
+        {
+            hist.pop_front();
+        }
+
+        let features = V2Features {
+            trades_per_min,
+            spread_bps: spread_percentile(hist.iter().map(|h| h.1), v2.spread_percentile),
+            depth3_usdc: d.metrics.worst_depth_usdc,
+            depth_cv: coefficient_of_variation(hist.iter().map(|h| h.2)),
+        };
+        d.bucket = if features.score(v2) >= v2.liquid_min_score {
+            Bucket::Liquid
+        } else {
+            Bucket::Thin
+        };
+        d
+    }
+}
+
+/// Same index rule as the market_select quantiles: `floor((n - 1) * q)` of the sorted values.
+fn spread_percentile(spreads: impl Iterator<Item = i32>, q: f64) -> f64 {
+    let mut v: Vec<i32> = spreads.collect();
+    if v.is_empty() {
+        return f64::NAN;
+    }
+    v.sort_unstable();
+    let idx = ((v.len() - 1) as f64 * q.clamp(0.0, 1.0)).floor() as usize;
+    f64::from(v[idx])
+}
+
+fn coefficient_of_variation(values: impl Iterator<Item = f64>) -> f64 {
+    let v: Vec<f64> = values.collect();
+    if v.is_empty() {
+        return f64::NAN;
+    }
+    let n = v.len() as f64;
+    let mean = v.iter().sum::<f64>() / n;
+    if mean <= 0.0 {
+        return f64::NAN;
+    }
+    let var = v.iter().map(|x| (x - mean).powi(2)).sum::<f64>() / n;
+    var.sqrt() / mean
+}
+
 fn depth_sanitize(depth3_usdc: f64) -> f64 {
     if !depth3_usdc.is_finite() || !(0.0..=MAX_DEPTH3_USDC).contains(&depth3_usdc) {
         f64::INFINITY
@@ -104,21 +390,15 @@ fn depth_sanitize(depth3_usdc: f64) -> f64 {
     }
 }
 
-fn spread_bps(best_bid: f64, best_ask: f64) -> Bps {
-    if !best_bid.is_finite() || !best_ask.is_finite() {
-        return INVALID_SPREAD_BPS;
-    }
-    if best_bid <= 0.0 || best_ask <= 0.0 {
+fn spread_bps(best_bid: Price, best_ask: Price) -> Bps {
+    if !best_bid.is_positive() || !best_ask.is_positive() {
         return INVALID_SPREAD_BPS;
     }
     if best_ask < best_bid {
         return INVALID_SPREAD_BPS;
     }
-    let mid = (best_ask + best_bid) / 2.0;
-    if !mid.is_finite() || mid <= 0.0 {
-        return INVALID_SPREAD_BPS;
-    }
-    let ratio = (best_ask - best_bid) / mid;
+    let mid = (best_ask + best_bid).to_f64() / 2.0;
+    let ratio = (best_ask - best_bid).to_f64() / mid;
     if !ratio.is_finite() || ratio < 0.0 {
         return INVALID_SPREAD_BPS;
     }
@@ -128,34 +408,36 @@ fn spread_bps(best_bid: f64, best_ask: f64) -> Bps {
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::types::{LegSnapshot, MarketSnapshot};
+    use crate::types::Qty;
 
     #[test]
     fn bucket_thin_when_worst_depth_is_low() {
         let snap = MarketSnapshot {
-            market_id: "m".to_string(),
+            market_id: "m".into(),
             legs: vec![
                 LegSnapshot {
-                    token_id: "a".to_string(),
-                    best_bid: 0.4991,
-                    best_ask: 0.5,
-                    best_ask_size_best: 0.0,
-                    best_bid_size_best: 0.0,
+                    token_id: "a".into(),
+                    best_bid: Price::from_f64(0.4991).unwrap(),
+                    best_ask: Price::from_f64(0.5).unwrap(),
+                    best_ask_size_best: Qty::ZERO,
+                    best_bid_size_best: Qty::ZERO,
                     ask_depth3_usdc: 400.0,
+                    asks: Default::default(),
                     ts_recv_us: 0,
                 },
                 LegSnapshot {
-                    token_id: "b".to_string(),
-                    best_bid: 0.4995,
-                    best_ask: 0.5,
-                    best_ask_size_best: 0.0,
-                    best_bid_size_best: 0.0,
+                    token_id: "b".into(),
+                    best_bid: Price::from_f64(0.4995).unwrap(),
+                    best_ask: Price::from_f64(0.5).unwrap(),
+                    best_ask_size_best: Qty::ZERO,
+                    best_bid_size_best: Qty::ZERO,
                     ask_depth3_usdc: 10_000.0,
+                    asks: Default::default(),
                     ts_recv_us: 0,
                 },
             ],
         };
-        let d = classify_bucket(&snap);
+        let d = classify_bucket_at(&snap, FROZEN_DEPTH_LEVELS);
         assert_eq!(d.bucket, Bucket::Thin);
         assert_eq!(d.metrics.worst_leg_index, 0);
     }
@@ -163,31 +445,160 @@ mod tests {
     #[test]
     fn bucket_liquid_when_worst_leg_is_tight_and_deep() {
         let snap = MarketSnapshot {
-            market_id: "m".to_string(),
+            market_id: "m".into(),
             legs: vec![
                 // worst depth = 600 (>500), spread ~= 18.0 bps (<20)
                 LegSnapshot {
-                    token_id: "a".to_string(),
-                    best_bid: 0.4991,
-                    best_ask: 0.5,
-                    best_ask_size_best: 0.0,
-                    best_bid_size_best: 0.0,
+                    token_id: "a".into(),
+                    best_bid: Price::from_f64(0.4991).unwrap(),
+                    best_ask: Price::from_f64(0.5).unwrap(),
+                    best_ask_size_best: Qty::ZERO,
+                    best_bid_size_best: Qty::ZERO,
                     ask_depth3_usdc: 600.0,
+                    asks: Default::default(),
                     ts_recv_us: 0,
                 },
                 LegSnapshot {
-                    token_id: "b".to_string(),
-                    best_bid: 0.4995,
-                    best_ask: 0.5,
-                    best_ask_size_best: 0.0,
-                    best_bid_size_best: 0.0,
+                    token_id: "b".into(),
+                    best_bid: Price::from_f64(0.4995).unwrap(),
+                    best_ask: Price::from_f64(0.5).unwrap(),
+                    best_ask_size_best: Qty::ZERO,
+                    best_bid_size_best: Qty::ZERO,
                     ask_depth3_usdc: 10_000.0,
+                    asks: Default::default(),
                     ts_recv_us: 0,
                 },
             ],
         };
-        let d = classify_bucket(&snap);
+        let d = classify_bucket_at(&snap, FROZEN_DEPTH_LEVELS);
         assert_eq!(d.bucket, Bucket::Liquid);
         assert_eq!(d.metrics.worst_leg_index, 0);
     }
+
+    fn tight_snap(depth_a: f64) -> MarketSnapshot {
+        let leg = |token_id: &str, depth: f64| LegSnapshot {
+            token_id: token_id.into(),
+            best_bid: Price::from_f64(0.4995).unwrap(),
+            best_ask: Price::from_f64(0.5).unwrap(),
+            best_ask_size_best: Qty::ZERO,
+            best_bid_size_best: Qty::ZERO,
+            ask_depth3_usdc: depth,
+            asks: Default::default(),
+            ts_recv_us: 0,
+        };
+        MarketSnapshot {
+            market_id: "m".into(),
+            legs: vec![leg("a", depth_a), leg("b", 1_000_000.0)],
+        }
+    }
+
+    #[test]
+    fn v2_needs_trade_flow_and_stable_depth() {
+        let cfg = BucketClassifierConfig {
+            version: BucketClassifierVersion::V2,
+            ..Default::default()
+        };
+        // v1 calls every one of these liquid; v2 also weighs trade flow and depth swings.
+        let mut c = BucketClassifier::new(&cfg);
+        let d = c.classify(&tight_snap(1_000.0), 1_000, 0.0);
+        assert_eq!(d.metrics.classifier, BucketClassifierVersion::V2);
+        assert_eq!(d.bucket, Bucket::Thin, "no trades: score 0.75 < 0.9");
+        assert_eq!(
+            c.classify(&tight_snap(1_000.0), 2_000, 3.0).bucket,
+            Bucket::Liquid
+        );
+        assert_eq!(
+            c.classify(&tight_snap(9_000.0), 3_000, 3.0).bucket,
+            Bucket::Thin,
+            "depth swinging 1k..9k: cv ~1.0 > max_depth_cv"
+        );
+        // The swing leaves the window and the market is stable again.
+        let later = 3_000 + cfg.v2.window_ms;
+        assert_eq!(
+            c.classify(&tight_snap(9_000.0), later, 3.0).bucket,
+            Bucket::Liquid
+        );
+
+        let mut v1 = BucketClassifier::new(&BucketClassifierConfig::default());
+        let d = v1.classify(&tight_snap(1_000.0), 1_000, 0.0);
+        assert_eq!(
+            (d.bucket, d.metrics.classifier),
+            (Bucket::Liquid, BucketClassifierVersion::V1)
+        );
+    }
+
+    #[test]
+    fn per_leg_buckets_each_leg_on_its_own() {
+        let mut snap = tight_snap(400.0);
+        snap.legs[1].ask_depth3_usdc = 800.0;
+        let legacy =
+            BucketClassifier::new(&BucketClassifierConfig::default()).classify(&snap, 0, 0.0);
+        assert_eq!(legacy.bucket, Bucket::Thin);
+        assert!(legacy.metrics.leg_buckets.is_empty());
+
+        let cfg = BucketClassifierConfig {
+            per_leg: true,
+            ..Default::default()
+        };
+        let d = BucketClassifier::new(&cfg).classify(&snap, 0, 0.0);
+        assert_eq!(d.bucket, Bucket::Thin, "the market bucket stays worst-leg");
+        assert_eq!(d.metrics.leg_buckets, vec![Bucket::Thin, Bucket::Liquid]);
+    }
+
+    #[test]
+    fn trade_rate_counts_the_window_ending_now() {
+        let arrivals = TradeArrivals::new(60_000);
+        let m: MarketId = "m".into();
+        for ts in [1_000, 30_000, 61_000, 62_000] {
+            arrivals.record(&m, ts);
+        }
+        // 1_000 fell out of (2_000, 62_000].
+        assert_eq!(arrivals.rate_per_min(&m, 62_000), 3.0);
+        assert_eq!(arrivals.rate_per_min(&"x".into(), 62_000), 0.0);
+        assert_eq!(
+            trade_rate_per_min(&[1_000, 30_000, 61_000, 62_000], 62_000, 60_000),
+            3.0
+        );
+        // Trades after `now_ms` are not seen yet.
+        assert_eq!(trade_rate_per_min(&[1_000, 30_000], 10_000, 120_000), 0.5);
+    }
+
+    #[test]
+    fn depth_levels_other_than_three_read_the_l2_ladder() {
+        use crate::order_book::AskLadder;
+
+        // Leg a: ~400 USDC in its top 3 asks, ~694 over its top 5; leg b is deep either way.
+        let mut snap = tight_snap(400.0);
+        snap.legs[0].asks = AskLadder::from_levels(
+            [0.50, 0.51, 0.52, 0.53, 0.54]
+                .iter()
+                .zip([300.0, 200.0, 280.0, 300.0, 255.0])
+                .map(|(p, q)| (Price::from_f64(*p).unwrap(), Qty::from_f64(q).unwrap()))
+                .collect(),
+        );
+        snap.legs[1].asks = AskLadder::from_levels(vec![(
+            Price::from_f64(0.5).unwrap(),
+            Qty::from_f64(100_000.0).unwrap(),
+        )]);
+        let frozen = BucketClassifier::new(&BucketClassifierConfig::default());
+        assert_eq!(
+            frozen.with_depth_levels(3).classify(&snap, 0, 0.0).bucket,
+            Bucket::Thin
+        );
+
+        let mut deep =
+            BucketClassifier::new(&BucketClassifierConfig::default()).with_depth_levels(5);
+        let d = deep.classify(&snap, 0, 0.0);
+        assert_eq!(d.bucket, Bucket::Liquid);
+        assert!((d.metrics.worst_depth_usdc - 694.3).abs() < 1e-9);
+        // The frozen column keeps its top-3 meaning.
+        assert_eq!(d.metrics.depth_levels, 5);
+        assert_eq!(d.metrics.worst_depth3_usdc, 400.0);
+
+        // Without a ladder (snapshots.csv replay) a deeper count has no depth: degraded, thin.
+        let d = deep.classify(&tight_snap(1_000.0), 0, 0.0);
+        assert_eq!(d.bucket, Bucket::Thin);
+        assert!(d.metrics.worst_depth_usdc.is_nan());
+        assert!(!d.metrics.is_depth3_degraded);
+    }
 }
@@ -1,20 +1,23 @@
+use std::collections::{BTreeMap, VecDeque};
 use std::path::{Path, PathBuf};
+use std::time::Duration;
 
 use anyhow::Context as _;
 use tokio::sync::{mpsc, watch};
-use tracing::{info, warn};
+use tracing::{debug, info, warn};
 
-use crate::config::Config;
+use crate::config::{AutoTuneConfig, BucketConfig, Config};
+use crate::health::HealthLine;
 use crate::recorder::CsvAppender;
-use crate::schema::CALIBRATION_LOG_HEADER;
-use crate::types::{now_ms, Bucket, Side};
+use crate::schema::{CALIBRATION_LOG_HEADER, FILE_CALIBRATION_PATCH};
+use crate::types::{now_ms, Bucket, MarketId, Side, TokenId};
 
 #[derive(Debug, Clone)]
 pub struct CalibrationEvent {
     pub ts_ms: u64,
     pub bucket: Bucket,
-    pub market_id: String,
-    pub token_id: String,
+    pub market_id: MarketId,
+    pub token_id: TokenId,
     pub side: Side,
     pub req_qty: f64,
     pub filled_qty: f64,
@@ -29,6 +32,8 @@ pub async fn run(
     mut rx: mpsc::Receiver<CalibrationEvent>,
     calibration_log_path: PathBuf,
     suggest_dir: PathBuf,
+    fill_share_tx: watch::Sender<BucketConfig>,
+    health_tx: mpsc::Sender<HealthLine>,
     mut shutdown: watch::Receiver<bool>,
 ) -> anyhow::Result<()> {
     let mut out = CsvAppender::open(calibration_log_path, &CALIBRATION_LOG_HEADER)
@@ -50,12 +55,58 @@ pub async fn run(
 
     let min_n = cfg.calibration.min_samples_per_bucket.max(1);
 
+    let mut rolling = RollingCalibration::default();
+    let mut tuner = cfg
+        .calibration
+        .auto_tune
+        .enabled
+        .then(|| AutoTuner::new(cfg.calibration.auto_tune.clone(), cfg.buckets.clone()));
+    let patch_path = suggest_dir.join(FILE_CALIBRATION_PATCH);
+    let mut patch_tick =
+        tokio::time::interval(Duration::from_millis(cfg.calibration.patch_interval_ms));
+    patch_tick.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+
     loop {
         let ev = tokio::select! {
             _ = shutdown.changed() => {
                 if *shutdown.borrow() { break; }
                 continue;
             }
+            _ = patch_tick.tick() => {
+                if rolling.dirty {
+                    let now = now_ms();
+                    write_patch(&mut rolling, &cfg, &patch_path, now)?;
+                    if let Some(tuner) = tuner.as_mut() {
+                        for bucket in [Bucket::Liquid, Bucket::Thin] {
+                            let Some(est) = rolling.bucket(bucket).estimate(min_n) else {
+                                continue;
+                            };
+                            let Some(adj) = tuner.propose(now, bucket, &est) else {
+                                continue;
+                            };
+                            info!(
+                                bucket = bucket.as_str(),
+                                from = adj.from,
+                                to = adj.to,
+                                target = adj.target,
+                                samples = est.samples,
+                                "calibration auto-tune: fill share adjusted"
+                            );
+                            let _ = health_tx.try_send(HealthLine::FillShareTuned {
+                                ts_ms: now,
+                                bucket: bucket.as_str().to_string(),
+                                from: adj.from,
+                                to: adj.to,
+                                target: adj.target,
+                                samples: est.samples,
+                                confidence: est.confidence.as_str().to_string(),
+                            });
+                            fill_share_tx.send_replace(tuner.current.clone());
+                        }
+                    }
+                }
+                continue;
+            }
             maybe = rx.recv() => maybe,
         };
         let Some(ev) = ev else {
@@ -65,8 +116,8 @@ pub async fn run(
         out.write_record([
             ev.ts_ms.to_string(),
             ev.bucket.as_str().to_string(),
-            ev.market_id.clone(),
-            ev.token_id.clone(),
+            ev.market_id.to_string(),
+            ev.token_id.to_string(),
             ev.side.as_str().to_string(),
             ev.req_qty.to_string(),
             ev.filled_qty.to_string(),
@@ -77,6 +128,7 @@ pub async fn run(
         ])?;
 
         if let Some(sample) = real_share_sample(ev.req_qty, ev.filled_qty) {
+            rolling.record(ev.ts_ms, ev.bucket, &ev.token_id, sample);
             match ev.bucket {
                 Bucket::Liquid => samples_liquid.push(sample),
                 Bucket::Thin => samples_thin.push(sample),
@@ -126,10 +178,286 @@ pub async fn run(
         last_written_thin = thin_n;
     }
 
+    if rolling.dirty {
+        write_patch(&mut rolling, &cfg, &patch_path, now_ms())?;
+    }
     out.flush_and_sync()?;
     Ok(())
 }
 
+/// A p25 estimate is `high` confidence once its 95% interval is at most this wide.
+const HIGH_CONFIDENCE_MAX_CI_WIDTH: f64 = 0.10;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Confidence {
+    /// Fewer than `calibration.min_samples_per_bucket` samples; not applied in the patch.
+    Low,
+    Medium,
+    High,
+}
+
+impl Confidence {
+    fn as_str(self) -> &'static str {
+        match self {
+            Confidence::Low => "low",
+            Confidence::Medium => "medium",
+            Confidence::High => "high",
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+struct FillShareEstimate {
+    samples: usize,
+    p25: f64,
+    mean: f64,
+    /// Distribution-free 95% interval of the p25 (order statistics around rank n/4).
+    ci_low: f64,
+    ci_high: f64,
+    confidence: Confidence,
+}
+
+/// Fill-share samples inside the rolling window, oldest first.
+#[derive(Debug, Default)]
+struct RollingSamples {
+    samples: VecDeque<(u64, f64)>,
+}
+
+impl RollingSamples {
+    fn push(&mut self, ts_ms: u64, sample: f64) {
+        self.samples.push_back((ts_ms, sample));
+    }
+
+    fn prune(&mut self, cutoff_ms: u64) {
+        while self.samples.front().is_some_and(|(ts, _)| *ts < cutoff_ms) {
+            self.samples.pop_front();
+        }
+    }
+
+    fn estimate(&self, min_n: usize) -> Option<FillShareEstimate> {
+        let n = self.samples.len();
+        if n == 0 {
+            return None;
+        }
+        let mut v: Vec<f64> = self.samples.iter().map(|(_, s)| *s).collect();
+        v.sort_by(|a, b| a.partial_cmp(b).unwrap_or(std::cmp::Ordering::Equal));
+        let rank = n as f64 * 0.25;
+        let half = 1.96 * (n as f64 * 0.25 * 0.75).sqrt();
+        let ci_low = v[((rank - half).floor().max(0.0) as usize).min(n - 1)];
+        let ci_high = v[((rank + half).ceil() as usize).min(n - 1)];
+        let confidence = if n < min_n {
+            Confidence::Low
+        } else if ci_high - ci_low > HIGH_CONFIDENCE_MAX_CI_WIDTH {
+            Confidence::Medium
+        } else {
+            Confidence::High
+        };
+        Some(FillShareEstimate {
+            samples: n,
+            p25: p25(&v),
+            mean: v.iter().sum::<f64>() / n as f64,
+            ci_low,
+            ci_high,
+            confidence,
+        })
+    }
+}
+
+/// Rolling per-bucket and per-token fill shares behind `calibration_patch.toml`.
+#[derive(Debug, Default)]
+struct RollingCalibration {
+    liquid: RollingSamples,
+    thin: RollingSamples,
+    tokens: BTreeMap<String, (Bucket, RollingSamples)>,
+    /// A sample arrived since the last patch.
+    dirty: bool,
+}
+
+impl RollingCalibration {
+    fn record(&mut self, ts_ms: u64, bucket: Bucket, token_id: &TokenId, sample: f64) {
+        match bucket {
+            Bucket::Liquid => self.liquid.push(ts_ms, sample),
+            Bucket::Thin => self.thin.push(ts_ms, sample),
+        }
+        let token = self
+            .tokens
+            .entry(token_id.to_string())
+            .or_insert_with(|| (bucket, RollingSamples::default()));
+        token.0 = bucket;
+        token.1.push(ts_ms, sample);
+        self.dirty = true;
+    }
+
+    fn bucket(&self, bucket: Bucket) -> &RollingSamples {
+        match bucket {
+            Bucket::Liquid => &self.liquid,
+            Bucket::Thin => &self.thin,
+        }
+    }
+
+    fn prune(&mut self, cutoff_ms: u64) {
+        self.liquid.prune(cutoff_ms);
+        self.thin.prune(cutoff_ms);
+        self.tokens.retain(|_, (_, s)| {
+            s.prune(cutoff_ms);
+            !s.samples.is_empty()
+        });
+    }
+
+    /// `[buckets]` carries only the estimates that are not `low` confidence, so the file can be
+    /// layered over a config as is (`config validate --patch`); `[calibration_patch]` is the
+    /// summary behind them.
+    fn render_patch(&self, generated_at_ms: u64, window_ms: u64, min_n: usize) -> String {
+        let liquid = self.liquid.estimate(min_n);
+        let thin = self.thin.estimate(min_n);
+        let mut out = String::new();
+        out.push_str(&format!(
+            "# Rolling fill_share calibration over the last {window_ms} ms; only estimates with\n\
+             # at least {min_n} samples are applied under [buckets].\n"
+        ));
+        let applied: Vec<(&str, &FillShareEstimate)> = [
+            ("fill_share_liquid_p25", liquid.as_ref()),
+            ("fill_share_thin_p25", thin.as_ref()),
+        ]
+        .into_iter()
+        .filter_map(|(k, e)| {
+            e.filter(|e| e.confidence != Confidence::Low)
+                .map(|e| (k, e))
+        })
+        .collect();
+        if !applied.is_empty() {
+            out.push_str("[buckets]\n");
+            for (key, e) in applied {
+                out.push_str(&format!("{key} = {:.6}\n", e.p25));
+            }
+            out.push('\n');
+        }
+        out.push_str("[calibration_patch]\n");
+        out.push_str(&format!("generated_at_ms = {generated_at_ms}\n"));
+        out.push_str(&format!("window_ms = {window_ms}\n"));
+        out.push_str(&format!("min_samples = {min_n}\n"));
+        for (name, e) in [("liquid", &liquid), ("thin", &thin)] {
+            if let Some(e) = e {
+                out.push_str(&format!("\n[calibration_patch.buckets.{name}]\n"));
+                push_estimate(&mut out, e);
+            }
+        }
+        for (token_id, (bucket, samples)) in &self.tokens {
+            if let Some(e) = samples.estimate(min_n) {
+                out.push_str(&format!("\n[calibration_patch.tokens.\"{token_id}\"]\n"));
+                out.push_str(&format!("bucket = \"{}\"\n", bucket.as_str()));
+                push_estimate(&mut out, &e);
+            }
+        }
+        out
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct FillShareAdjustment {
+    from: f64,
+    to: f64,
+    /// The estimate the step moved towards, before bounds and budget.
+    target: f64,
+}
+
+/// Moves the running bucket fill shares towards the rolling p25, at most
+/// `max_step_per_hour` per bucket within any trailing hour and never outside the bounds.
+#[derive(Debug)]
+struct AutoTuner {
+    cfg: AutoTuneConfig,
+    current: BucketConfig,
+    /// Applied steps `(ts_ms, bucket, |delta|)` of the last hour.
+    steps: VecDeque<(u64, Bucket, f64)>,
+}
+
+impl AutoTuner {
+    const HOUR_MS: u64 = 3_600_000;
+    /// Smaller moves are not worth an adjustment line.
+    const MIN_STEP: f64 = 1e-4;
+
+    fn new(cfg: AutoTuneConfig, current: BucketConfig) -> Self {
+        Self {
+            cfg,
+            current,
+            steps: VecDeque::new(),
+        }
+    }
+
+    fn propose(
+        &mut self,
+        now_ms: u64,
+        bucket: Bucket,
+        est: &FillShareEstimate,
+    ) -> Option<FillShareAdjustment> {
+        if est.confidence == Confidence::Low {
+            return None;
+        }
+        while self
+            .steps
+            .front()
+            .is_some_and(|(ts, _, _)| ts + Self::HOUR_MS <= now_ms)
+        {
+            self.steps.pop_front();
+        }
+        let used: f64 = self
+            .steps
+            .iter()
+            .filter(|(_, b, _)| *b == bucket)
+            .map(|(_, _, d)| d)
+            .sum();
+        let budget = (self.cfg.max_step_per_hour - used).max(0.0);
+        let (min, max) = self.cfg.bounds(bucket);
+        let slot = match bucket {
+            Bucket::Liquid => &mut self.current.fill_share_liquid_p25,
+            Bucket::Thin => &mut self.current.fill_share_thin_p25,
+        };
+        let from = *slot;
+        let to = (from + (est.p25 - from).clamp(-budget, budget)).clamp(min, max);
+        if (to - from).abs() < Self::MIN_STEP {
+            return None;
+        }
+        *slot = to;
+        self.steps.push_back((now_ms, bucket, (to - from).abs()));
+        Some(FillShareAdjustment {
+            from,
+            to,
+            target: est.p25,
+        })
+    }
+}
+
+fn push_estimate(out: &mut String, e: &FillShareEstimate) {
+    out.push_str(&format!("samples = {}\n", e.samples));
+    out.push_str(&format!("p25 = {:.6}\n", e.p25));
+    out.push_str(&format!("mean = {:.6}\n", e.mean));
+    out.push_str(&format!("ci_low = {:.6}\n", e.ci_low));
+    out.push_str(&format!("ci_high = {:.6}\n", e.ci_high));
+    out.push_str(&format!("confidence = \"{}\"\n", e.confidence.as_str()));
+}
+
+/// Prunes the window and replaces `calibration_patch.toml` (temp file + rename).
+fn write_patch(
+    rolling: &mut RollingCalibration,
+    cfg: &Config,
+    path: &Path,
+    now_ms: u64,
+) -> anyhow::Result<()> {
+    let window_ms = cfg.calibration.rolling_window_ms;
+    rolling.prune(now_ms.saturating_sub(window_ms));
+    let body = rolling.render_patch(
+        now_ms,
+        window_ms,
+        cfg.calibration.min_samples_per_bucket.max(1),
+    );
+    let tmp = path.with_extension("toml.tmp");
+    std::fs::write(&tmp, body.as_bytes()).with_context(|| format!("write {}", tmp.display()))?;
+    std::fs::rename(&tmp, path).with_context(|| format!("rename {}", path.display()))?;
+    rolling.dirty = false;
+    debug!(path = %path.display(), "calibration patch written");
+    Ok(())
+}
+
 fn real_share_sample(req_qty: f64, filled_qty: f64) -> Option<f64> {
     if !req_qty.is_finite() || !filled_qty.is_finite() {
         return None;
@@ -197,6 +525,87 @@ mod tests {
         assert_eq!(p25(&samples), 0.09);
     }
 
+    #[test]
+    fn rolling_patch_applies_only_confident_buckets() {
+        let mut r = RollingCalibration::default();
+        let token = TokenId::from("123");
+        for i in 0..40u64 {
+            r.record(1_000 + i, Bucket::Liquid, &token, 0.5);
+        }
+        r.record(1_000, Bucket::Thin, &TokenId::from("456"), 0.1);
+        r.record(9_000, Bucket::Thin, &TokenId::from("456"), 0.2);
+
+        let text = r.render_patch(10_000, 60_000, 30);
+        let doc: toml::Table = text.parse().expect("valid toml");
+        assert_eq!(
+            doc["buckets"]["fill_share_liquid_p25"].as_float(),
+            Some(0.5)
+        );
+        assert!(
+            doc["buckets"].get("fill_share_thin_p25").is_none(),
+            "{text}"
+        );
+        let summary = &doc["calibration_patch"];
+        assert_eq!(
+            summary["buckets"]["liquid"]["confidence"].as_str(),
+            Some("high")
+        );
+        assert_eq!(
+            summary["buckets"]["thin"]["confidence"].as_str(),
+            Some("low")
+        );
+        assert_eq!(summary["tokens"]["123"]["samples"].as_integer(), Some(40));
+
+        // Everything before 5s falls out of the window.
+        r.prune(5_000);
+        assert!(r.liquid.samples.is_empty() && !r.tokens.contains_key("123"));
+        assert_eq!(r.thin.estimate(30).unwrap().samples, 1);
+    }
+
+    #[test]
+    fn auto_tune_respects_hourly_budget_and_bounds() {
+        let cfg = AutoTuneConfig {
+            enabled: true,
+            liquid_min: 0.10,
+            liquid_max: 0.40,
+            max_step_per_hour: 0.05,
+            ..AutoTuneConfig::default()
+        };
+        let mut tuner = AutoTuner::new(cfg, BucketConfig::default());
+        let est = |p25: f64, confidence: Confidence| FillShareEstimate {
+            samples: 50,
+            p25,
+            mean: p25,
+            ci_low: p25,
+            ci_high: p25,
+            confidence,
+        };
+        let h = AutoTuner::HOUR_MS;
+
+        assert!(tuner
+            .propose(0, Bucket::Liquid, &est(0.9, Confidence::Low))
+            .is_none());
+        let a = tuner
+            .propose(0, Bucket::Liquid, &est(0.9, Confidence::High))
+            .unwrap();
+        assert!((a.from - 0.30).abs() < 1e-12 && (a.to - 0.35).abs() < 1e-12);
+        // Budget for the hour is spent.
+        assert!(tuner
+            .propose(h / 2, Bucket::Liquid, &est(0.9, Confidence::High))
+            .is_none());
+        // Next hour: capped by liquid_max.
+        let a = tuner
+            .propose(h + 1, Bucket::Liquid, &est(0.9, Confidence::High))
+            .unwrap();
+        assert!((a.to - 0.40).abs() < 1e-12);
+        // Thin has its own budget.
+        let a = tuner
+            .propose(h + 1, Bucket::Thin, &est(0.08, Confidence::Medium))
+            .unwrap();
+        assert!((a.to - 0.08).abs() < 1e-12);
+        assert!((tuner.current.fill_share_thin_p25 - 0.08).abs() < 1e-12);
+    }
+
     #[test]
     fn real_share_sample_is_clamped_and_validated() {
         assert_eq!(real_share_sample(10.0, 3.0).unwrap(), 0.3);
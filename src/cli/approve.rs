@@ -0,0 +1,47 @@
+use std::path::PathBuf;
+
+use anyhow::Context as _;
+
+use crate::run_meta::RunMeta;
+use crate::types::now_ms;
+
+/// `razor approve`: manually approve a GO run's config for the live gateway.
+#[derive(Debug, clap::Args)]
+pub struct ApproveArgs {
+    /// Run whose `report.json` verdict is GO.
+    #[arg(long, default_value = "data/run_latest")]
+    run_dir: PathBuf,
+    /// Who approves (recorded as `manual:<by>`; default `$USER`).
+    #[arg(long)]
+    by: Option<String>,
+}
+
+pub fn run(args: ApproveArgs) -> anyhow::Result<()> {
+    let run_dir = crate::archive::resolve_run_dir(&args.run_dir)?;
+    anyhow::ensure!(
+        crate::go_live::report_is_go(&run_dir)?,
+        "{}: report verdict is not GO; nothing to approve",
+        run_dir.display()
+    );
+    let run_id = RunMeta::read_from_dir(&run_dir)
+        .map(|m| m.run_id)
+        .context("read run_meta.json")?;
+    let by = args
+        .by
+        .or_else(|| std::env::var("USER").ok())
+        .unwrap_or_else(|| "unknown".to_string());
+    let token = crate::go_live::issue_for_run_dir(
+        &run_dir,
+        &run_id,
+        &format!("manual:{by}"),
+        true,
+        now_ms(),
+    )?
+    .context("approval key not set (live.approval_key_env)")?;
+
+    println!("run_id={}", token.run_id);
+    println!("config_sha256={}", token.config_sha256);
+    println!("approved_by={}", token.approved_by);
+    println!("expires_ts_ms={}", token.expires_ts_ms);
+    Ok(())
+}
@@ -0,0 +1,53 @@
+use std::path::PathBuf;
+
+use anyhow::Context as _;
+
+/// `razor brain-sweep`: replays a run across a grid of brain thresholds.
+#[derive(Debug, clap::Args)]
+pub struct BrainSweepArgs {
+    /// Input run directory (expects snapshots.csv, trades.csv, config.toml).
+    #[arg(long)]
+    run_dir: PathBuf,
+
+    /// Output directory (default: <run_dir>/brain_sweep).
+    #[arg(long)]
+    out_dir: Option<PathBuf>,
+}
+
+pub fn run(args: BrainSweepArgs) -> anyhow::Result<()> {
+    let run_dir = crate::archive::resolve_run_dir(&args.run_dir)?;
+    let out_dir = args.out_dir.unwrap_or_else(|| run_dir.join("brain_sweep"));
+
+    let res = crate::brain_sweep::run_brain_sweep(&run_dir, &out_dir)
+        .with_context(|| format!("brain sweep {}", run_dir.display()))?;
+
+    println!("base_run_id={}", res.base_run_id);
+    println!("out_dir={}", res.out_dir.display());
+    println!(
+        "scores_csv={}",
+        res.out_dir
+            .join(crate::brain_sweep::FILE_BRAIN_SWEEP_SCORES)
+            .display()
+    );
+    println!(
+        "best_patch={}",
+        res.out_dir
+            .join(crate::brain_sweep::FILE_BEST_BRAIN_PATCH)
+            .display()
+    );
+    if let Some(best) = res.best {
+        println!(
+            "best: min_net_edge_bps={} risk_premium_bps={} signal_cooldown_ms={} total_pnl_sum={:.6} legging_rate={:.6} signals_ok={}",
+            best.min_net_edge_bps,
+            best.risk_premium_bps,
+            best.signal_cooldown_ms,
+            best.total_pnl_sum,
+            best.legging_rate,
+            best.signals_ok,
+        );
+    } else {
+        println!("best: <none> (insufficient signals in sweep grid)");
+    }
+
+    Ok(())
+}
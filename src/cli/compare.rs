@@ -0,0 +1,84 @@
+use std::path::{Path, PathBuf};
+
+use anyhow::Context as _;
+use tracing::info;
+
+/// `razor compare`: side-by-side summary of several run dirs.
+#[derive(clap::Args, Debug)]
+pub struct CompareArgs {
+    /// Root data directory that contains `run_*` folders.
+    #[arg(long, default_value = "data")]
+    data_dir: PathBuf,
+
+    /// Explicit run directories (comma-separated). If omitted, scans `data_dir` for `run_*`.
+    #[arg(long, value_delimiter = ',')]
+    runs: Vec<PathBuf>,
+
+    /// Only compare runs tagged `key=value` (repeatable; all must match).
+    #[arg(long = "tag", value_name = "KEY=VALUE", value_parser = crate::run_meta::parse_tag)]
+    tags: Vec<(String, String)>,
+
+    /// Output directory (default: data/run_compare/<run_id>/).
+    #[arg(long)]
+    out_dir: Option<PathBuf>,
+}
+
+fn default_out_dir(data_dir: &Path) -> PathBuf {
+    let id = format!("rcmp_{}", crate::types::now_ms());
+    data_dir.join("run_compare").join(id)
+}
+
+pub fn run(args: CompareArgs) -> anyhow::Result<()> {
+    let run_dirs = if args.runs.is_empty() {
+        crate::run_compare::discover_run_dirs(&args.data_dir)?
+    } else {
+        let mut v = args
+            .runs
+            .iter()
+            .map(|r| crate::archive::resolve_run_dir(r))
+            .collect::<anyhow::Result<Vec<_>>>()?;
+        v.sort();
+        v
+    };
+
+    if run_dirs.is_empty() {
+        anyhow::bail!("no run dirs found (use --runs or ensure data/run_* exists)");
+    }
+
+    let out_dir = args
+        .out_dir
+        .unwrap_or_else(|| default_out_dir(&args.data_dir));
+    std::fs::create_dir_all(&out_dir).with_context(|| format!("create {}", out_dir.display()))?;
+
+    let mut summaries: Vec<crate::run_compare::RunSummary> = Vec::new();
+    for dir in run_dirs {
+        match crate::run_compare::summarize_run_dir(&dir) {
+            Ok(s) => summaries.push(s),
+            Err(e) => {
+                tracing::warn!(run_dir = %dir.display(), error = %e, "skip run_dir");
+            }
+        }
+    }
+
+    let mut summaries = crate::run_compare::filter_by_tags(summaries, &args.tags);
+    if summaries.is_empty() {
+        anyhow::bail!("no usable runs after filtering");
+    }
+
+    summaries.sort_by(|a, b| a.run_id.cmp(&b.run_id));
+    for m in crate::run_compare::provenance_mismatches(&summaries) {
+        tracing::warn!("{}", m.replace('`', ""));
+    }
+
+    let csv_path = crate::run_compare::write_runs_summary_csv(&out_dir, &summaries)?;
+    let md_path = crate::run_compare::write_runs_summary_md(&out_dir, &summaries)?;
+
+    info!(
+        out_dir = %out_dir.display(),
+        runs = summaries.len(),
+        csv = %csv_path.display(),
+        md = %md_path.display(),
+        "run_compare done"
+    );
+    Ok(())
+}
@@ -0,0 +1,595 @@
+//! `razor config validate`: parses and validates a config (file or profile layers), optionally
+//! with tool-written patch files layered on top, without starting a run. Every error and warning
+//! points at the file, line and column that set the offending key.
+
+use std::ops::Range;
+use std::path::PathBuf;
+
+use anyhow::Context as _;
+use toml_edit::{ImDocument, TableLike};
+
+use crate::config::{apply_env_overrides, merge_toml, Config, ConfigSource, EnvOverride};
+use crate::units::fmt_ms;
+
+#[derive(clap::Args, Debug)]
+pub struct ValidateArgs {
+    /// Patch written by a tool (`best_patch.toml`, `best_brain_patch.toml`, `suggest.toml`,
+    /// `calibration_suggest.toml`, `calibration_patch.toml`) to layer over the config before validating; repeatable, later
+    /// patches win.
+    #[arg(long = "patch", value_name = "FILE")]
+    pub patches: Vec<PathBuf>,
+}
+
+/// Patch sections that summarize the tool run rather than set config keys.
+const PATCH_SUMMARY_SECTIONS: [&str; 3] =
+    ["shadow_sweep_best", "shadow_sweep", "calibration_patch"];
+
+/// Old key names still accepted through serde aliases (`recorder` and `recorder.files.*`).
+const LEGACY_KEY_NAMES: [(&str, &str); 4] = [
+    ("commit_max_latency_ms", "flush_interval_ms"),
+    ("commit_fsync", "fsync_policy"),
+    ("max_latency_ms", "flush_interval_ms"),
+    ("fsync", "fsync_policy"),
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Severity {
+    Error,
+    Warning,
+}
+
+#[derive(Debug, Clone)]
+pub struct Finding {
+    pub severity: Severity,
+    /// Dotted config key the finding is about, when there is one.
+    pub key: Option<String>,
+    pub message: String,
+    /// `--> file:line:col` and the source line; empty when the key was left at its default.
+    pub location: String,
+}
+
+impl std::fmt::Display for Finding {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let level = match self.severity {
+            Severity::Error => "error",
+            Severity::Warning => "warning",
+        };
+        match &self.key {
+            Some(key) => write!(f, "{level}: {key}: {}", self.message)?,
+            None => write!(f, "{level}: {}", self.message)?,
+        }
+        if !self.location.is_empty() {
+            write!(f, "\n{}", self.location)?;
+        }
+        Ok(())
+    }
+}
+
+/// One TOML text in merge order: the config (or each profile layer), then the patches.
+pub struct Source {
+    pub label: String,
+    pub text: String,
+    pub patch: bool,
+}
+
+impl Source {
+    fn read(path: &std::path::Path, patch: bool) -> anyhow::Result<Self> {
+        Ok(Self {
+            label: path.display().to_string(),
+            text: std::fs::read_to_string(path)
+                .with_context(|| format!("read {}", path.display()))?,
+            patch,
+        })
+    }
+}
+
+struct Parsed<'a> {
+    source: &'a Source,
+    doc: ImDocument<&'a str>,
+    /// Config key -> key in this file, for patch keys that are translated on merge.
+    renamed: Vec<(String, String)>,
+}
+
+fn finding(severity: Severity, key: Option<&str>, message: impl Into<String>) -> Finding {
+    Finding {
+        severity,
+        key: key.map(str::to_string),
+        message: message.into(),
+        location: String::new(),
+    }
+}
+
+/// Span from the key to the end of its value.
+fn key_span(doc: &ImDocument<&str>, key: &str) -> Option<Range<usize>> {
+    let mut table: &dyn TableLike = doc.as_table();
+    let mut parts = key.split('.').peekable();
+    while let Some(part) = parts.next() {
+        let (k, item) = table.get_key_value(part)?;
+        if parts.peek().is_none() {
+            return match (k.span(), item.span()) {
+                (Some(k), Some(v)) => Some(k.start..v.end),
+                (k, v) => k.or(v),
+            };
+        }
+        table = item.as_table_like()?;
+    }
+    None
+}
+
+/// rustc-style location: `--> file:line:col`, then the line with the span underlined.
+fn render_location(label: &str, text: &str, span: Range<usize>) -> String {
+    let line_start = text[..span.start].rfind('\n').map_or(0, |i| i + 1);
+    let line_end = text[span.start..]
+        .find('\n')
+        .map_or(text.len(), |i| span.start + i);
+    let line_no = text[..span.start].matches('\n').count() + 1;
+    let line = text[line_start..line_end].trim_end_matches('\r');
+    let col = text[line_start..span.start].chars().count();
+    let width = text[span.start..span.end.min(line_end)]
+        .chars()
+        .count()
+        .max(1);
+    let gutter = " ".repeat(line_no.to_string().len());
+    format!(
+        "{gutter}--> {label}:{line_no}:{}\n{gutter} |\n{line_no} | {line}\n{gutter} | {}{}",
+        col + 1,
+        " ".repeat(col),
+        "^".repeat(width)
+    )
+}
+
+/// Where `key` got its value: the env override, else the last file that sets it.
+fn locate(parsed: &[Parsed<'_>], overrides: &[EnvOverride], key: &str) -> String {
+    if let Some(o) = overrides.iter().rev().find(|o| o.key == key) {
+        return format!(" --> env {}", o.var);
+    }
+    for p in parsed.iter().rev() {
+        let in_file = p
+            .renamed
+            .iter()
+            .find(|(cfg_key, _)| cfg_key == key)
+            .map_or(key, |(_, file_key)| file_key.as_str());
+        if let Some(span) = key_span(&p.doc, in_file) {
+            return render_location(&p.source.label, &p.source.text, span);
+        }
+    }
+    String::new()
+}
+
+fn leaves(prefix: &str, v: &toml::Value, out: &mut Vec<String>) {
+    match v {
+        toml::Value::Table(t) => {
+            for (k, child) in t {
+                let key = if prefix.is_empty() {
+                    k.clone()
+                } else {
+                    format!("{prefix}.{k}")
+                };
+                leaves(&key, child, out);
+            }
+        }
+        _ => out.push(prefix.to_string()),
+    }
+}
+
+/// Drops the tool-summary sections of a patch and maps `calibration_suggest` onto the bucket
+/// fill shares it suggests.
+fn patch_layer(
+    label: &str,
+    mut layer: toml::Value,
+    renamed: &mut Vec<(String, String)>,
+    findings: &mut Vec<(Finding, Option<String>)>,
+) -> toml::Value {
+    let Some(table) = layer.as_table_mut() else {
+        return layer;
+    };
+    for (section, body) in table.iter_mut() {
+        if let Some(body) = body.as_table_mut() {
+            if body.remove("insufficient_data") == Some(toml::Value::Boolean(true)) {
+                findings.push((
+                    finding(
+                        Severity::Warning,
+                        None,
+                        format!("{label}: written with insufficient data; review before applying"),
+                    ),
+                    Some(format!("{section}.insufficient_data")),
+                ));
+            }
+        }
+    }
+    for section in PATCH_SUMMARY_SECTIONS {
+        table.remove(section);
+    }
+    if let Some(suggest) = table.remove("calibration_suggest") {
+        for side in ["liquid", "thin"] {
+            let Some(s) = suggest.get(side) else {
+                continue;
+            };
+            if s.get("samples").and_then(toml::Value::as_integer) == Some(0) {
+                findings.push((
+                    finding(
+                        Severity::Warning,
+                        None,
+                        format!("{label}: no {side} samples behind the suggested p25"),
+                    ),
+                    Some(format!("calibration_suggest.{side}.samples")),
+                ));
+            }
+            if let Some(p25) = s.get("p25") {
+                let key = format!("fill_share_{side}_p25");
+                let buckets = table
+                    .entry("buckets")
+                    .or_insert_with(|| toml::Value::Table(toml::Table::new()));
+                if let Some(buckets) = buckets.as_table_mut() {
+                    buckets.insert(key.clone(), p25.clone());
+                    renamed.push((
+                        format!("buckets.{key}"),
+                        format!("calibration_suggest.{side}.p25"),
+                    ));
+                }
+            }
+        }
+    }
+    layer
+}
+
+/// Cross-field checks on a parsed config: the `validate` rules that have a key to point at, plus
+/// settings that are legal but probably not intended.
+fn lint(cfg: &Config) -> Vec<(Severity, &'static str, String)> {
+    let mut out = Vec::new();
+    let shadow = &cfg.shadow;
+    if shadow.window_end_ms <= shadow.window_start_ms {
+        out.push((
+            Severity::Error,
+            "shadow.window_end_ms",
+            format!(
+                "{} must be > shadow.window_start_ms ({})",
+                fmt_ms(shadow.window_end_ms),
+                fmt_ms(shadow.window_start_ms)
+            ),
+        ));
+    }
+    if shadow.trade_retention_ms < shadow.window_end_ms {
+        out.push((
+            Severity::Error,
+            "shadow.trade_retention_ms",
+            format!(
+                "{} must be >= shadow.window_end_ms ({})",
+                fmt_ms(shadow.trade_retention_ms),
+                fmt_ms(shadow.window_end_ms)
+            ),
+        ));
+    }
+    let shares = [
+        (
+            "buckets.fill_share_liquid_p25",
+            cfg.buckets.fill_share_liquid_p25,
+            "buckets.fill_share_thin_p25",
+            cfg.buckets.fill_share_thin_p25,
+        ),
+        (
+            "sim.sim_fill_share_liquid",
+            cfg.sim.sim_fill_share_liquid,
+            "sim.sim_fill_share_thin",
+            cfg.sim.sim_fill_share_thin,
+        ),
+    ];
+    for (liquid_key, liquid, thin_key, thin) in shares {
+        let mut in_range = true;
+        for (key, v) in [(liquid_key, liquid), (thin_key, thin)] {
+            if !v.is_finite() || !(0.0..=1.0).contains(&v) {
+                in_range = false;
+                out.push((Severity::Error, key, format!("{v} is outside [0, 1]")));
+            } else if v == 0.0 {
+                out.push((
+                    Severity::Warning,
+                    key,
+                    "0 assumes nothing ever fills".to_string(),
+                ));
+            }
+        }
+        if in_range && liquid < thin {
+            out.push((
+                Severity::Warning,
+                liquid_key,
+                format!("{liquid} is below {thin_key} ({thin})"),
+            ));
+        }
+    }
+    if cfg.run.market_ids.is_empty() {
+        out.push((
+            Severity::Warning,
+            "run.market_ids",
+            "empty: the run records nothing".to_string(),
+        ));
+    }
+    if let Ok(Some(stop_ms)) = cfg.run.schedule.stop_ms(crate::types::now_ms()) {
+        if !cfg.run.schedule.stop_at.is_empty() && stop_ms <= crate::types::now_ms() {
+            out.push((
+                Severity::Warning,
+                "run.schedule.stop_at",
+                "already passed: a new run refuses to start".to_string(),
+            ));
+        }
+    }
+    if cfg.live.enabled && !cfg!(feature = "live") {
+        out.push((
+            Severity::Warning,
+            "live.enabled",
+            "true, but this build has no `live` feature: live_sim refuses to start".to_string(),
+        ));
+    } else if cfg.live.enabled {
+        out.push((
+            Severity::Warning,
+            "live.enabled",
+            "true: the live gateway also needs `--mode live`, `run --live` and RAZOR_LIVE_CONFIRM=<config token>".to_string(),
+        ));
+    }
+    out
+}
+
+/// The window and fill-share checks for each `[markets."<id>"]` block, pointed at the override
+/// that breaks them.
+fn lint_markets(cfg: &Config) -> Vec<(Severity, String, String)> {
+    let mut out = Vec::new();
+    for (id, o) in &cfg.markets {
+        let m = cfg.market(id);
+        if m.window_end_ms <= m.window_start_ms {
+            let field = if o.window_end_ms.is_some() {
+                "window_end_ms"
+            } else {
+                "window_start_ms"
+            };
+            out.push((
+                Severity::Error,
+                format!("markets.{id}.{field}"),
+                format!(
+                    "market window_end_ms {} must be > window_start_ms {}",
+                    fmt_ms(m.window_end_ms),
+                    fmt_ms(m.window_start_ms)
+                ),
+            ));
+        } else if cfg.shadow.trade_retention_ms < m.window_end_ms {
+            out.push((
+                Severity::Error,
+                format!("markets.{id}.window_end_ms"),
+                format!(
+                    "{} exceeds shadow.trade_retention_ms ({})",
+                    fmt_ms(m.window_end_ms),
+                    fmt_ms(cfg.shadow.trade_retention_ms)
+                ),
+            ));
+        }
+        let shares = [
+            ("fill_share_liquid_p25", o.fill_share_liquid_p25),
+            ("fill_share_thin_p25", o.fill_share_thin_p25),
+        ];
+        for (field, v) in shares {
+            if let Some(v) = v.filter(|v| !v.is_finite() || !(0.0..=1.0).contains(v)) {
+                out.push((
+                    Severity::Error,
+                    format!("markets.{id}.{field}"),
+                    format!("{v} is outside [0, 1]"),
+                ));
+            }
+        }
+        if let Some(q) = o.q_req.filter(|q| !q.is_positive()) {
+            out.push((
+                Severity::Error,
+                format!("markets.{id}.q_req"),
+                format!("{q} must be > 0"),
+            ));
+        }
+    }
+    out
+}
+
+/// Every problem with `sources` merged in order under the `RAZOR__*` entries of `env`.
+pub fn check(sources: &[Source], env: impl IntoIterator<Item = (String, String)>) -> Vec<Finding> {
+    let mut findings = Vec::new();
+    let mut parsed = Vec::with_capacity(sources.len());
+    for source in sources {
+        match ImDocument::parse(source.text.as_str()) {
+            Ok(doc) => parsed.push(Parsed {
+                source,
+                doc,
+                renamed: Vec::new(),
+            }),
+            Err(e) => findings.push(finding(
+                Severity::Error,
+                None,
+                format!("{}: {}", source.label, e.to_string().trim_end()),
+            )),
+        }
+    }
+    if !findings.is_empty() {
+        return findings;
+    }
+
+    // (finding, key to locate when it differs from `finding.key`)
+    let mut located: Vec<(Finding, Option<String>)> = Vec::new();
+    let mut merged = toml::Value::Table(toml::Table::new());
+    for p in &mut parsed {
+        let layer: toml::Value = match toml::from_str(&p.source.text) {
+            Ok(v) => v,
+            Err(e) => {
+                return vec![finding(
+                    Severity::Error,
+                    None,
+                    format!("{}: {}", p.source.label, e.to_string().trim_end()),
+                )]
+            }
+        };
+        let layer = if p.source.patch {
+            patch_layer(&p.source.label, layer, &mut p.renamed, &mut located)
+        } else {
+            layer
+        };
+        merge_toml(&mut merged, layer);
+    }
+    let overrides = match apply_env_overrides(&mut merged, env) {
+        Ok(o) => o,
+        Err(e) => return vec![finding(Severity::Error, None, format!("{e:#}"))],
+    };
+
+    let cfg: Config = match merged.clone().try_into() {
+        Ok(cfg) => cfg,
+        Err(e) => {
+            // Errors from a `toml::Value` end in "in `section.key`" instead of carrying a span.
+            let msg = e.to_string();
+            let (message, key) = match msg.trim_end().rsplit_once("\nin `") {
+                Some((m, k)) => (m.to_string(), k.strip_suffix('`').map(str::to_string)),
+                None => (msg.trim_end().to_string(), None),
+            };
+            let mut f = finding(Severity::Error, key.as_deref(), message);
+            if let Some(key) = &key {
+                f.location = locate(&parsed, &overrides, key);
+            }
+            return vec![f];
+        }
+    };
+
+    let mut known = Vec::new();
+    match toml::Value::try_from(&cfg) {
+        Ok(v) => leaves("", &v, &mut known),
+        Err(e) => return vec![finding(Severity::Error, None, format!("{e:#}"))],
+    }
+    let mut set = Vec::new();
+    leaves("", &merged, &mut set);
+    for key in set.iter().filter(|k| !known.contains(k)) {
+        let (parent, leaf) = key.rsplit_once('.').unwrap_or(("", key.as_str()));
+        let message = match LEGACY_KEY_NAMES.iter().find(|(old, _)| *old == leaf) {
+            Some((_, new)) => format!("old name of `{parent}.{new}`; still accepted"),
+            None => "not a config key; ignored".to_string(),
+        };
+        located.push((finding(Severity::Warning, Some(key), message), None));
+    }
+
+    let lints: Vec<(Severity, String, String)> = lint(&cfg)
+        .into_iter()
+        .map(|(s, k, m)| (s, k.to_string(), m))
+        .chain(lint_markets(&cfg))
+        .collect();
+    let lint_error = lints.iter().any(|(s, _, _)| *s == Severity::Error);
+    for (severity, key, message) in lints {
+        located.push((finding(severity, Some(&key), message), None));
+    }
+    if !lint_error {
+        if let Err(e) = cfg.validate() {
+            let msg = e.to_string();
+            let key = known
+                .iter()
+                .filter(|k| msg.contains(k.as_str()))
+                .max_by_key(|k| k.len());
+            located.push((finding(Severity::Error, key.map(String::as_str), msg), None));
+        }
+    }
+
+    for (mut f, at) in located {
+        if let Some(key) = at.as_ref().or(f.key.as_ref()) {
+            f.location = locate(&parsed, &overrides, key);
+        }
+        findings.push(f);
+    }
+    findings.sort_by_key(|f| f.severity != Severity::Error);
+    findings
+}
+
+/// Prints every finding; fails when any is an error.
+pub fn run(source: &ConfigSource, args: ValidateArgs) -> anyhow::Result<()> {
+    let mut sources = Vec::new();
+    for path in source.layers() {
+        sources.push(Source::read(&path, false)?);
+    }
+    for path in &args.patches {
+        sources.push(Source::read(path, true)?);
+    }
+    let findings = check(&sources, std::env::vars());
+    for f in &findings {
+        println!("{f}\n");
+    }
+    let errors = findings
+        .iter()
+        .filter(|f| f.severity == Severity::Error)
+        .count();
+    let warnings = findings.len() - errors;
+    let checked = sources
+        .iter()
+        .map(|s| s.label.as_str())
+        .collect::<Vec<_>>()
+        .join(" + ");
+    if errors > 0 {
+        anyhow::bail!("{checked}: {errors} error(s), {warnings} warning(s)");
+    }
+    println!("{checked}: ok, {warnings} warning(s)");
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn source(label: &str, text: &str, patch: bool) -> Source {
+        Source {
+            label: label.to_string(),
+            text: text.to_string(),
+            patch,
+        }
+    }
+
+    #[test]
+    fn points_at_the_file_line_and_key_behind_each_problem() {
+        let base = source(
+            "config.toml",
+            "[run]\nmarket_ids = [\"1\"]\n\n[shadow]\nwindow_start_ms = \"1s\"\nwindow_end_ms = 900\n\
+             trade_retention_ms = \"1m\"\n\n[recorder]\ncommit_fsync = true\nflush_every = 3\n",
+            false,
+        );
+        let findings = check(&[base], Vec::new());
+        let window = &findings[0];
+        assert_eq!(window.severity, Severity::Error);
+        assert_eq!(window.key.as_deref(), Some("shadow.window_end_ms"));
+        assert_eq!(
+            window.message,
+            "900ms must be > shadow.window_start_ms (1s)"
+        );
+        assert!(window.location.contains("--> config.toml:6:1"), "{window}");
+        assert!(window.location.contains("^^^^^^^^^^^^^^^^^^^"), "{window}");
+        let keys: Vec<_> = findings.iter().filter_map(|f| f.key.as_deref()).collect();
+        assert!(keys.contains(&"recorder.commit_fsync"), "{keys:?}");
+        assert!(keys.contains(&"recorder.flush_every"), "{keys:?}");
+
+        // A calibration suggestion is checked as the bucket share it would set.
+        let ok = source("config.toml", "[run]\nmarket_ids = [\"1\"]\n", false);
+        let suggest = source(
+            "calibration_suggest.toml",
+            "[calibration_suggest]\ngenerated_at_ms = 1\n\n[calibration_suggest.liquid]\n\
+             samples = 40\np25 = 1.5\n",
+            true,
+        );
+        let findings = check(&[ok, suggest], Vec::new());
+        assert_eq!(findings.len(), 1, "{findings:?}");
+        assert_eq!(
+            findings[0].key.as_deref(),
+            Some("buckets.fill_share_liquid_p25")
+        );
+        assert!(findings[0]
+            .location
+            .contains("--> calibration_suggest.toml:6:1"));
+
+        // Type errors and env overrides are located too.
+        let bad = source("config.toml", "[run]\nmarket_ids = [\"1\"]\n", false);
+        let findings = check(
+            &[bad],
+            vec![(
+                "RAZOR__SHADOW__WINDOW_END_MS".to_string(),
+                "soon".to_string(),
+            )],
+        );
+        assert_eq!(findings[0].key.as_deref(), Some("shadow.window_end_ms"));
+        assert_eq!(
+            findings[0].location,
+            " --> env RAZOR__SHADOW__WINDOW_END_MS"
+        );
+    }
+}
@@ -0,0 +1,78 @@
+use std::path::PathBuf;
+
+use tracing::info;
+
+use crate::config::ConfigSource;
+use crate::types::{normalize_ts_ms, parse_rfc3339_to_unix_secs};
+
+/// `razor fetch-history`: download a market's past trades and price history into a run dir
+/// (read-only).
+#[derive(clap::Args, Debug)]
+pub struct FetchHistoryArgs {
+    /// Gamma market id (as in `run.market_ids`).
+    #[arg(long)]
+    market: String,
+
+    /// Start of the window: unix timestamp (s/ms) or RFC 3339.
+    #[arg(long, value_parser = parse_ts_ms)]
+    from: u64,
+
+    /// End of the window: unix timestamp (s/ms) or RFC 3339.
+    #[arg(long, value_parser = parse_ts_ms)]
+    to: u64,
+
+    /// Output directory. Default: `<data_dir>/history/<run_id>/`.
+    #[arg(long)]
+    out_dir: Option<PathBuf>,
+
+    /// data-api `/trades` page size.
+    #[arg(long, default_value_t = 500)]
+    page_size: usize,
+
+    /// Stop paging after this many pages (the dataset is then marked truncated).
+    #[arg(long, default_value_t = 200)]
+    max_pages: usize,
+
+    /// `/prices-history` resolution in minutes (one snapshot per point).
+    #[arg(long, default_value_t = 1)]
+    fidelity_min: u32,
+}
+
+fn parse_ts_ms(s: &str) -> Result<u64, String> {
+    if let Ok(n) = s.trim().parse::<u64>() {
+        return Ok(normalize_ts_ms(n));
+    }
+    parse_rfc3339_to_unix_secs(s.trim())
+        .map(|secs| secs * 1000)
+        .ok_or_else(|| format!("expected a unix timestamp or RFC 3339, got {s:?}"))
+}
+
+/// Downloads with the `[polymarket]` endpoints of `config`.
+pub async fn run(config: &ConfigSource, args: FetchHistoryArgs) -> anyhow::Result<()> {
+    let cfg = super::load_config(config)?;
+
+    let opts = crate::history::FetchHistoryOptions {
+        market_id: args.market,
+        from_ms: args.from,
+        to_ms: args.to,
+        out_dir: args.out_dir,
+        page_size: args.page_size,
+        max_pages: args.max_pages,
+        fidelity_min: args.fidelity_min,
+    };
+    info!(
+        config = %config.describe(),
+        market = %opts.market_id,
+        from_ms = opts.from_ms,
+        to_ms = opts.to_ms,
+        "fetch_history start"
+    );
+
+    let s = crate::history::run(&cfg, opts).await?;
+    println!("run_id={}", s.run_id);
+    println!(
+        "trades={} pages={} truncated={} snapshots={} book_source={}",
+        s.trades_written, s.trade_pages, s.trades_truncated, s.snapshots_written, s.book_source
+    );
+    Ok(())
+}
@@ -0,0 +1,62 @@
+//! The offline `razor` subcommands (`select`, `fetch-history`, `replay`, `sweep`, `brain-sweep`,
+//! `split`, `report`, `approve`, `compare`, `xval`, `config validate`): one `*Args` struct and `run` per
+//! tool, shared by the main binary and the old per-tool binaries, which stay as thin aliases for
+//! one release.
+
+use anyhow::Context as _;
+use tracing_subscriber::EnvFilter;
+
+use crate::config::{parse_with_env_overrides, Config, ConfigSource};
+
+pub mod approve;
+pub mod brain_sweep;
+pub mod compare;
+pub mod config_validate;
+pub mod fetch_history;
+pub mod replay;
+pub mod report;
+pub mod select;
+pub mod split;
+pub mod sweep;
+pub mod xval;
+
+#[derive(clap::ValueEnum, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum LogFormat {
+    #[default]
+    Text,
+    Json,
+}
+
+/// Global tracing subscriber; `RUST_LOG` overrides the default `info` filter.
+pub fn init_tracing(format: LogFormat) {
+    let filter = EnvFilter::try_from_default_env().unwrap_or_else(|_| EnvFilter::new("info"));
+    match format {
+        LogFormat::Text => tracing_subscriber::fmt().with_env_filter(filter).init(),
+        // Event fields (market_id, signal_id, ...) are flattened to the top level; the enclosing
+        // `run` span contributes run_id under `span`.
+        LogFormat::Json => tracing_subscriber::fmt()
+            .json()
+            .flatten_event(true)
+            .with_current_span(true)
+            .with_span_list(false)
+            .with_env_filter(filter)
+            .init(),
+    }
+}
+
+/// Reads a config (file or profile), layers the `RAZOR__*` environment overrides over it and
+/// validates it.
+pub fn load_config(source: &ConfigSource) -> anyhow::Result<Config> {
+    let raw = source.read()?;
+    let (cfg, _, overrides) = parse_with_env_overrides(&raw).context("parse config")?;
+    cfg.validate().context("validate config")?;
+    for o in &overrides {
+        tracing::info!(var = %o.var, "config override: {}", o.display_redacted());
+    }
+    Ok(cfg)
+}
+
+/// Logged by the old per-tool binaries.
+pub fn warn_deprecated_alias(old: &str, subcommand: &str) {
+    tracing::warn!("`{old}` is deprecated and will be removed; use `razor {subcommand}`");
+}
@@ -0,0 +1,99 @@
+use std::path::PathBuf;
+
+use anyhow::Context as _;
+
+/// Where `razor replay` takes market snapshots from.
+#[derive(Debug, Clone, Copy, Default, clap::ValueEnum)]
+pub enum SourceArg {
+    /// The sampled snapshots.csv.
+    #[default]
+    Snapshots,
+    /// raw_ws.jsonl re-parsed by the WS feed, timed by the frames' exchange timestamps.
+    RawWs,
+}
+
+/// `razor replay`: rebuilds a run's shadow log offline from its snapshots and trades.
+#[derive(Debug, clap::Args)]
+pub struct ReplayArgs {
+    /// Input run directory (expects snapshots.csv or raw_ws.jsonl, trades.csv, config.toml).
+    #[arg(long)]
+    run_dir: PathBuf,
+
+    /// Output directory (default: <run_dir>/replay).
+    #[arg(long)]
+    out_dir: Option<PathBuf>,
+
+    /// Override replay run_id written into replay outputs (default: replay_<run_id>).
+    #[arg(long)]
+    replay_run_id: Option<String>,
+
+    /// Snapshot source.
+    #[arg(long, value_enum, default_value_t = SourceArg::Snapshots)]
+    source: SourceArg,
+
+    /// Virtual clock speed: 0 = as fast as possible, 1 = realtime, 10 = ten times realtime.
+    #[arg(long, default_value_t = 0.0)]
+    speed: f64,
+}
+
+/// `rebuild_raw_ws` ([`crate::feed::replay_raw_ws`] from `razor replay`) is needed for
+/// `--source raw-ws`; the deprecated `razor_replay` alias passes `None`.
+pub fn run(
+    args: ReplayArgs,
+    rebuild_raw_ws: Option<crate::replay::RawWsRebuild>,
+) -> anyhow::Result<()> {
+    let run_dir = crate::archive::resolve_run_dir(&args.run_dir)?;
+    let out_dir = args.out_dir.unwrap_or_else(|| run_dir.join("replay"));
+
+    let replay_run_id = match args.replay_run_id {
+        Some(v) => v,
+        None => {
+            let orig = crate::run_meta::RunMeta::read_from_dir(&run_dir)
+                .map(|m| m.run_id)
+                .unwrap_or_else(|_| "unknown".to_string());
+            format!("replay_{orig}")
+        }
+    };
+
+    let res = crate::replay::run_replay(
+        &run_dir,
+        crate::replay::ReplayOptions {
+            out_dir: out_dir.clone(),
+            replay_run_id: replay_run_id.clone(),
+            source: match args.source {
+                SourceArg::Snapshots => crate::replay::ReplaySource::SnapshotsCsv,
+                SourceArg::RawWs => crate::replay::ReplaySource::RawWs(
+                    rebuild_raw_ws.context("--source raw-ws needs `razor replay`")?,
+                ),
+            },
+            speed: args.speed,
+        },
+    )
+    .with_context(|| format!("replay {}", run_dir.display()))?;
+
+    println!("replay_run_id={}", res.replay_run_id);
+    println!("snapshots={}", res.snapshots);
+    println!("signals={}", res.signals);
+    println!("shadow_rows={}", res.shadow_rows);
+    println!("out_dir={}", res.out_dir.display());
+    println!(
+        "shadow_csv={}",
+        res.out_dir
+            .join(crate::replay::FILE_REPLAY_SHADOW_LOG)
+            .display()
+    );
+    println!(
+        "report_json={}",
+        res.out_dir
+            .join(crate::replay::FILE_REPLAY_REPORT_JSON)
+            .display()
+    );
+    println!(
+        "report_md={}",
+        res.out_dir
+            .join(crate::replay::FILE_REPLAY_REPORT_MD)
+            .display()
+    );
+
+    Ok(())
+}
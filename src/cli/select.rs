@@ -0,0 +1,73 @@
+use std::path::PathBuf;
+
+use clap::ValueEnum;
+use tracing::info;
+
+use crate::config::ConfigSource;
+
+/// `razor select`: Phase 1 market selector (read-only).
+#[derive(clap::Args, Debug)]
+pub struct SelectArgs {
+    /// Probe duration per market (seconds). Default: 3600.
+    #[arg(long)]
+    probe_seconds: Option<u64>,
+
+    /// Gamma candidate pool limit. Default: 200.
+    #[arg(long)]
+    pool_limit: Option<usize>,
+
+    /// Prefer a single strategy to control variables (binary/triangle) or allow any.
+    #[arg(long, value_enum)]
+    prefer_strategy: Option<PreferStrategyArg>,
+
+    /// Output directory. Default: `<data_dir>/market_select/<run_id>/`.
+    #[arg(long)]
+    out_dir: Option<PathBuf>,
+}
+
+#[derive(Clone, Copy, Debug, ValueEnum)]
+pub enum PreferStrategyArg {
+    Binary,
+    Triangle,
+    Any,
+}
+
+impl From<PreferStrategyArg> for crate::market_select::PreferStrategy {
+    fn from(v: PreferStrategyArg) -> Self {
+        match v {
+            PreferStrategyArg::Binary => crate::market_select::PreferStrategy::Binary,
+            PreferStrategyArg::Triangle => crate::market_select::PreferStrategy::Triangle,
+            PreferStrategyArg::Any => crate::market_select::PreferStrategy::Any,
+        }
+    }
+}
+
+/// Selects markets with the `[market_select]` settings of `config`, overridden by `args`.
+pub async fn run(config: &ConfigSource, args: SelectArgs) -> anyhow::Result<()> {
+    let cfg = super::load_config(config)?;
+
+    let opts = crate::market_select::MarketSelectOptions {
+        probe_seconds: args
+            .probe_seconds
+            .unwrap_or(cfg.market_select.probe_seconds),
+        pool_limit: args.pool_limit.unwrap_or(cfg.market_select.pool_limit),
+        prefer_strategy: args.prefer_strategy.map(Into::into).unwrap_or_else(|| {
+            cfg.market_select
+                .prefer_strategy
+                .parse::<crate::market_select::PreferStrategy>()
+                .unwrap()
+        }),
+        out_dir: args.out_dir,
+    };
+
+    info!(
+        config = %config.describe(),
+        probe_seconds = opts.probe_seconds,
+        pool_limit = opts.pool_limit,
+        prefer_strategy = %opts.prefer_strategy.as_str(),
+        "market_select start"
+    );
+
+    crate::market_select::run(&cfg, opts).await?;
+    Ok(())
+}
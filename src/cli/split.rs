@@ -0,0 +1,44 @@
+use std::path::PathBuf;
+
+use anyhow::Context as _;
+
+/// `razor split`: per-day scores and walk-forward folds of a run's shadow log.
+#[derive(Debug, clap::Args)]
+pub struct SplitArgs {
+    /// Input run directory (expects shadow_log.csv and run_meta.json).
+    #[arg(long)]
+    run_dir: PathBuf,
+
+    /// Output directory (default: <run_dir>/walk_forward).
+    #[arg(long)]
+    out_dir: Option<PathBuf>,
+
+    /// Set ratio threshold used for legging_rate statistics.
+    #[arg(long, default_value = "0.85")]
+    set_ratio_threshold: f64,
+}
+
+pub fn run(args: SplitArgs) -> anyhow::Result<()> {
+    let run_dir = crate::archive::resolve_run_dir(&args.run_dir)?;
+    let out_dir = args.out_dir.unwrap_or_else(|| run_dir.join("walk_forward"));
+
+    let res = crate::dataset_split::run_dataset_split(&run_dir, &out_dir, args.set_ratio_threshold)
+        .with_context(|| format!("dataset_split {}", run_dir.display()))?;
+
+    println!("run_id={}", res.run_id);
+    println!("out_dir={}", res.out_dir.display());
+    println!(
+        "daily_scores_csv={}",
+        res.out_dir
+            .join(crate::dataset_split::FILE_DAILY_SCORES)
+            .display()
+    );
+    println!(
+        "walk_forward_json={}",
+        res.out_dir
+            .join(crate::dataset_split::FILE_WALK_FORWARD_JSON)
+            .display()
+    );
+    println!("days={}", res.days.len());
+    Ok(())
+}
@@ -0,0 +1,64 @@
+use std::path::PathBuf;
+
+use anyhow::Context as _;
+
+/// `razor xval`: shadow fill predictions against the SIM/LIVE fills of the same signals.
+#[derive(Debug, clap::Args)]
+pub struct XvalArgs {
+    /// Input run directory (expects shadow_log.csv, trade_log.csv and run_meta.json).
+    #[arg(long)]
+    run_dir: PathBuf,
+
+    /// Output directory (default: <run_dir>/fill_xval).
+    #[arg(long)]
+    out_dir: Option<PathBuf>,
+
+    /// Legs a bucket needs before its bias gets a verdict.
+    #[arg(long, default_value_t = 30)]
+    min_samples: u64,
+}
+
+pub fn run(args: XvalArgs) -> anyhow::Result<()> {
+    let run_dir = crate::archive::resolve_run_dir(&args.run_dir)?;
+    let out_dir = args.out_dir.unwrap_or_else(|| run_dir.join("fill_xval"));
+
+    let res = crate::fill_xval::run_fill_xval(&run_dir, &out_dir, args.min_samples)
+        .with_context(|| format!("fill_xval {}", run_dir.display()))?;
+    let r = &res.report;
+
+    println!("run_id={}", r.run_id);
+    println!("out_dir={}", res.out_dir.display());
+    println!(
+        "shadow_legs={} execution_legs={} joined_legs={} unmatched_execution_legs={}",
+        r.shadow_legs, r.execution_legs, r.joined_legs, r.unmatched_execution_legs
+    );
+    println!();
+    println!(
+        "| bucket | legs | predicted | realized | bias [95% CI] | MAE | err p10/p50/p90 | fill_share used | implied p25 (n) | verdict |"
+    );
+    println!("|---|---:|---:|---:|---|---:|---|---:|---|---|");
+    for b in &r.buckets {
+        let implied = b
+            .implied_fill_share_p25
+            .map_or_else(|| "-".to_string(), |v| format!("{v:.4}"));
+        println!(
+            "| {} | {} | {:.4} | {:.4} | {:+.4} [{:+.4}, {:+.4}] | {:.4} | {:+.3}/{:+.3}/{:+.3} | {:.4} | {} ({}) | {} |",
+            b.bucket,
+            b.samples,
+            b.predicted_fill_ratio_avg,
+            b.realized_fill_ratio_avg,
+            b.bias,
+            b.bias_ci95_low,
+            b.bias_ci95_high,
+            b.mae,
+            b.error_p10,
+            b.error_p50,
+            b.error_p90,
+            b.fill_share_used,
+            implied,
+            b.implied_samples,
+            b.verdict
+        );
+    }
+    Ok(())
+}
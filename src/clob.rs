@@ -8,6 +8,7 @@ use k256::ecdsa::SigningKey;
 use serde::Deserialize;
 use sha2::Sha256;
 
+use crate::api_usage::{self, ApiEndpoint};
 use crate::config::Config;
 use crate::eth;
 
@@ -44,9 +45,7 @@ impl ClobSigner {
             !env_name.is_empty(),
             "live.private_key_env must not be empty"
         );
-        let pk = std::env::var(env_name).with_context(|| {
-            format!("missing private key env var: {env_name} (set it for live.enabled=true)")
-        })?;
+        let pk = crate::secrets::require(env_name, "private key (needed for live.enabled=true)")?;
         let pk32 = eth::parse_hex_32(&pk).context("parse private key")?;
         let signing_key =
             SigningKey::from_bytes((&pk32).into()).context("invalid secp256k1 private key")?;
@@ -214,11 +213,13 @@ pub async fn create_or_derive_api_creds(
     let l1 = create_level1_headers(signer, nonce).context("create level1 headers")?;
 
     let create_url = format!("{base}/auth/api-key");
-    let create_res = http
-        .post(&create_url)
-        .headers(map_to_headermap(&l1)?)
-        .send()
-        .await;
+    let create_res = api_usage::track(
+        ApiEndpoint::Clob,
+        http.post(&create_url)
+            .headers(map_to_headermap(&l1)?)
+            .send()
+            .await,
+    );
 
     if let Ok(resp) = create_res {
         if resp.status().is_success() {
@@ -233,12 +234,14 @@ pub async fn create_or_derive_api_creds(
 
     // Fallback: derive existing creds for this (address, nonce).
     let derive_url = format!("{base}/auth/derive-api-key");
-    let resp = http
-        .get(&derive_url)
-        .headers(map_to_headermap(&l1)?)
-        .send()
-        .await
-        .context("derive api creds")?;
+    let resp = api_usage::track(
+        ApiEndpoint::Clob,
+        http.get(&derive_url)
+            .headers(map_to_headermap(&l1)?)
+            .send()
+            .await,
+    )
+    .context("derive api creds")?;
     if !resp.status().is_success() {
         let status = resp.status();
         let body = resp.text().await.unwrap_or_default();
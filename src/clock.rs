@@ -0,0 +1,84 @@
+//! Time source for the pipeline. brain, shadow, sniper and the TradeStore read "now" through a
+//! [`Clock`] instead of the system time directly, so cooldowns, settle windows and retention can
+//! be driven deterministically by a [`SimClock`] (unit tests, replay).
+
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+use crate::types::{now_ms, now_us};
+
+pub trait Clock: Send + Sync + std::fmt::Debug {
+    /// Unix time in microseconds.
+    fn now_us(&self) -> u64;
+
+    /// Unix time in milliseconds.
+    fn now_ms(&self) -> u64 {
+        self.now_us() / 1000
+    }
+}
+
+/// Shared handle passed to tasks; cloning is cheap.
+pub type SharedClock = Arc<dyn Clock>;
+
+/// Wall clock (`SystemTime::now`).
+#[derive(Debug, Default, Clone, Copy)]
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now_us(&self) -> u64 {
+        now_us()
+    }
+
+    fn now_ms(&self) -> u64 {
+        now_ms()
+    }
+}
+
+/// The wall clock as a [`SharedClock`].
+pub fn system() -> SharedClock {
+    Arc::new(SystemClock)
+}
+
+/// Sleeps until the wall clock reaches `target_ms` (unix ms). Re-reads the wall clock at least
+/// once a minute, so NTP steps and host suspend during a long wait don't shift the wake-up.
+pub async fn sleep_until_wall_ms(target_ms: u64) {
+    loop {
+        let now = now_ms();
+        if now >= target_ms {
+            return;
+        }
+        tokio::time::sleep(Duration::from_millis((target_ms - now).min(60_000))).await;
+    }
+}
+
+/// Clock that only moves when told to.
+#[allow(dead_code)]
+#[derive(Debug, Default)]
+pub struct SimClock {
+    now_us: AtomicU64,
+}
+
+#[allow(dead_code)]
+impl SimClock {
+    pub fn starting_at_ms(ms: u64) -> Arc<Self> {
+        Arc::new(Self {
+            now_us: AtomicU64::new(ms.saturating_mul(1000)),
+        })
+    }
+
+    pub fn set_ms(&self, ms: u64) {
+        self.now_us.store(ms.saturating_mul(1000), Ordering::SeqCst);
+    }
+
+    pub fn advance_ms(&self, ms: u64) {
+        self.now_us
+            .fetch_add(ms.saturating_mul(1000), Ordering::SeqCst);
+    }
+}
+
+impl Clock for SimClock {
+    fn now_us(&self) -> u64 {
+        self.now_us.load(Ordering::SeqCst)
+    }
+}
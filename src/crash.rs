@@ -0,0 +1,191 @@
+use std::future::Future;
+use std::panic::AssertUnwindSafe;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, OnceLock};
+
+use anyhow::Context as _;
+use futures_util::FutureExt as _;
+use serde::{Deserialize, Serialize};
+use tokio::sync::Notify;
+use tokio::task::JoinHandle;
+use tracing::Instrument as _;
+
+use crate::errors::ErrorCode;
+use crate::health::HealthCounters;
+use crate::run_meta::RunMeta;
+use crate::schema::FILE_CRASH_REPORT_JSON;
+use crate::types::now_ms;
+
+/// `run_meta.abnormal_termination` value set by the panic hook.
+pub const ABNORMAL_PANIC: &str = "panic";
+
+struct CrashContext {
+    run_dir: PathBuf,
+    health: Arc<HealthCounters>,
+}
+
+static CONTEXT: OnceLock<CrashContext> = OnceLock::new();
+
+/// Name of the first supervised task that panicked; `TASK_PANIC` wakes the run loop.
+static FIRST_PANICKED_TASK: OnceLock<&'static str> = OnceLock::new();
+static TASK_PANIC: Notify = Notify::const_new();
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct CrashReport {
+    pub ts_ms: u64,
+    pub run_id: String,
+    pub thread: String,
+    /// Always `PANIC` today; run-ending task errors carry theirs in health.jsonl `task_error`.
+    #[serde(default)]
+    pub code: ErrorCode,
+    pub message: String,
+    pub location: Option<String>,
+    pub backtrace: String,
+    /// Supervised tasks that panicked, in order (filled in by `spawn_supervised`).
+    #[serde(default)]
+    pub tasks: Vec<String>,
+    pub health: Option<serde_json::Value>,
+}
+
+/// Chains a panic hook that writes `crash_report.json` (message, backtrace, health snapshot) into
+/// the run dir and flags `run_meta.json` before the default hook runs. First panic wins.
+pub fn install_panic_hook(run_dir: PathBuf, run_id: String, health: Arc<HealthCounters>) {
+    if CONTEXT.set(CrashContext { run_dir, health }).is_err() {
+        return;
+    }
+
+    let prev = std::panic::take_hook();
+    std::panic::set_hook(Box::new(move |info| {
+        if let Some(ctx) = CONTEXT.get() {
+            let report = CrashReport {
+                ts_ms: now_ms(),
+                run_id: run_id.clone(),
+                thread: std::thread::current()
+                    .name()
+                    .unwrap_or("<unnamed>")
+                    .to_string(),
+                code: ErrorCode::Panic,
+                message: panic_message(info.payload()),
+                location: info.location().map(|l| l.to_string()),
+                backtrace: std::backtrace::Backtrace::force_capture().to_string(),
+                tasks: Vec::new(),
+                health: serde_json::to_value(ctx.health.snapshot()).ok(),
+            };
+            // Best-effort: we're already unwinding; never panic again from here.
+            let _ = write_crash_report(&ctx.run_dir, &report, false);
+            let _ = mark_run_meta_abnormal(&ctx.run_dir, ABNORMAL_PANIC);
+        }
+        prev(info);
+    }));
+}
+
+/// `tokio::spawn` in the caller's span, under supervision: a panic is recorded against `task` in
+/// `crash_report.json` (the panic hook has already written the message and backtrace) and wakes
+/// [`task_panicked`], then resumes so the `JoinHandle` still reports it.
+pub fn spawn_supervised<F>(task: &'static str, fut: F) -> JoinHandle<F::Output>
+where
+    F: Future + Send + 'static,
+    F::Output: Send + 'static,
+{
+    tokio::spawn(
+        async move {
+            match AssertUnwindSafe(fut).catch_unwind().await {
+                Ok(out) => out,
+                Err(payload) => {
+                    note_task_panic(task);
+                    std::panic::resume_unwind(payload)
+                }
+            }
+        }
+        .in_current_span(),
+    )
+}
+
+/// Resolves with the task name once a supervised task has panicked.
+pub async fn task_panicked() -> &'static str {
+    loop {
+        if let Some(task) = FIRST_PANICKED_TASK.get() {
+            return task;
+        }
+        TASK_PANIC.notified().await;
+    }
+}
+
+/// The first supervised task that panicked, if any.
+pub fn panicked_task() -> Option<&'static str> {
+    FIRST_PANICKED_TASK.get().copied()
+}
+
+fn note_task_panic(task: &'static str) {
+    let _ = FIRST_PANICKED_TASK.set(task);
+    TASK_PANIC.notify_one();
+    tracing::error!(task, "task panicked");
+
+    let Some(ctx) = CONTEXT.get() else {
+        return;
+    };
+    let path = ctx.run_dir.join(FILE_CRASH_REPORT_JSON);
+    let Ok(raw) = std::fs::read(&path) else {
+        return;
+    };
+    let Ok(mut report) = serde_json::from_slice::<CrashReport>(&raw) else {
+        return;
+    };
+    report.tasks.push(task.to_string());
+    let _ = write_crash_report(&ctx.run_dir, &report, true);
+}
+
+fn write_crash_report(run_dir: &Path, report: &CrashReport, overwrite: bool) -> anyhow::Result<()> {
+    let path = run_dir.join(FILE_CRASH_REPORT_JSON);
+    if !overwrite && path.exists() {
+        return Ok(());
+    }
+    let json = serde_json::to_vec_pretty(report).context("serialize crash_report.json")?;
+    std::fs::write(&path, json).with_context(|| format!("write {}", path.display()))
+}
+
+fn mark_run_meta_abnormal(run_dir: &Path, reason: &str) -> anyhow::Result<()> {
+    let mut meta = RunMeta::read_from_dir(run_dir)?;
+    if meta.abnormal_termination.is_none() {
+        meta.abnormal_termination = Some(reason.to_string());
+        meta.write_to_dir(run_dir)?;
+    }
+    Ok(())
+}
+
+fn panic_message(payload: &(dyn std::any::Any + Send)) -> String {
+    if let Some(s) = payload.downcast_ref::<&str>() {
+        (*s).to_string()
+    } else if let Some(s) = payload.downcast_ref::<String>() {
+        s.clone()
+    } else {
+        "<non-string panic payload>".to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn panic_message_handles_str_and_string_payloads() {
+        assert_eq!(panic_message(&"boom"), "boom");
+        assert_eq!(panic_message(&String::from("bang")), "bang");
+        assert_eq!(panic_message(&42u32), "<non-string panic payload>");
+    }
+
+    #[tokio::test]
+    async fn supervised_panic_wakes_the_run_and_still_reaches_the_join_handle() {
+        let ok = spawn_supervised("quiet", async { 7 });
+        assert_eq!(ok.await.unwrap(), 7);
+        assert_eq!(panicked_task(), None);
+
+        let h = spawn_supervised("ingest", async { panic!("boom") });
+        let task = tokio::time::timeout(std::time::Duration::from_secs(5), task_panicked())
+            .await
+            .expect("task_panicked resolves");
+        assert_eq!(task, "ingest");
+        assert!(h.await.unwrap_err().is_panic());
+        assert_eq!(panicked_task(), Some("ingest"));
+    }
+}
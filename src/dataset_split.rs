@@ -4,8 +4,10 @@ use std::path::{Path, PathBuf};
 use anyhow::Context as _;
 use serde::Serialize;
 
+use crate::fees::{FeeRates, FeeRegistry};
 use crate::schema::{FILE_SHADOW_LOG, SCHEMA_VERSION, SHADOW_HEADER};
-use crate::shadow_sweep::{recompute_ledger_row, RecomputeLeg};
+use crate::schema_adapt::{FrozenCsvReader, SchemaMode};
+use crate::shadow_sweep::{recompute_ledger_row_per_leg, RecomputeLeg};
 
 pub const FILE_DAILY_SCORES: &str = "daily_scores.csv";
 pub const FILE_WALK_FORWARD_JSON: &str = "walk_forward.json";
@@ -82,9 +84,12 @@ pub struct WalkForwardGrid {
 #[derive(Debug, Clone)]
 struct Row {
     day_start_ms: u64,
-    bucket: BucketKey,
     q_req: f64,
     legs: Vec<RecomputeLeg>,
+    /// Per leg: `legN_bucket` when written (`per_leg` on), else the row's bucket.
+    leg_buckets: Vec<BucketKey>,
+    /// Rates of the row's `fee_model`.
+    fees: FeeRates,
     total_pnl_logged: f64,
     set_ratio_logged: f64,
 }
@@ -374,15 +379,20 @@ fn compute_metrics_recomputed(
     let mut legging_miss = 0u64;
 
     for r in rows {
-        let fill_share_used = match r.bucket {
-            BucketKey::Liquid => params.fill_share_liquid,
-            BucketKey::Thin => params.fill_share_thin,
-        };
-        let (total_pnl, set_ratio) = recompute_ledger_row(
+        let fill_shares: Vec<f64> = r
+            .leg_buckets
+            .iter()
+            .map(|b| match b {
+                BucketKey::Liquid => params.fill_share_liquid,
+                BucketKey::Thin => params.fill_share_thin,
+            })
+            .collect();
+        let (total_pnl, set_ratio) = recompute_ledger_row_per_leg(
             r.q_req,
             &r.legs,
-            fill_share_used,
+            &fill_shares,
             params.dump_slippage_assumed,
+            r.fees,
         );
         sum_pnl += total_pnl;
         pnls.push(total_pnl);
@@ -425,20 +435,13 @@ fn concat_days(by_day: &BTreeMap<u64, Vec<Row>>, days: &[u64]) -> Vec<Row> {
 }
 
 fn parse_rows(shadow_log_path: &Path, run_id: &str) -> anyhow::Result<Vec<Row>> {
-    let mut rdr = csv::ReaderBuilder::new()
-        .flexible(true)
-        .trim(csv::Trim::All)
-        .from_path(shadow_log_path)
-        .with_context(|| format!("open {}", shadow_log_path.display()))?;
-
-    let header = rdr
-        .headers()
-        .with_context(|| format!("read header {}", shadow_log_path.display()))?
-        .clone();
-
-    if header.iter().map(|s| s.trim()).collect::<Vec<_>>() != SHADOW_HEADER {
-        anyhow::bail!("shadow_log.csv header mismatch (expected frozen SHADOW_HEADER)");
-    }
+    let mut rdr = FrozenCsvReader::open(
+        shadow_log_path,
+        FILE_SHADOW_LOG,
+        &SHADOW_HEADER,
+        SchemaMode::from_env(),
+    )?;
+    let header = rdr.header().clone();
 
     let idx_run_id = idx(&header, "run_id")?;
     let idx_schema = idx(&header, "schema_version")?;
@@ -448,6 +451,8 @@ fn parse_rows(shadow_log_path: &Path, run_id: &str) -> anyhow::Result<Vec<Row>>
     let idx_q_req = idx(&header, "q_req")?;
     let idx_total_pnl = idx(&header, "total_pnl")?;
     let idx_set_ratio = idx(&header, "set_ratio")?;
+    let idx_fee_model = idx(&header, "fee_model")?;
+    let fee_registry = FeeRegistry::for_run_dir(shadow_log_path.parent().unwrap_or(Path::new(".")));
 
     let leg0 = LegIdxs::new(0)?;
     let leg1 = LegIdxs::new(1)?;
@@ -501,8 +506,13 @@ fn parse_rows(shadow_log_path: &Path, run_id: &str) -> anyhow::Result<Vec<Row>>
             .get(idx_set_ratio)
             .and_then(parse_f64)
             .context("set_ratio")?;
+        let fee_model = record.get(idx_fee_model).unwrap_or("");
+        let fees = fee_registry
+            .rates_for_row(fee_model)
+            .with_context(|| format!("unknown fee_model {fee_model:?}"))?;
 
         let mut legs: Vec<RecomputeLeg> = Vec::with_capacity(legs_n);
+        let mut leg_buckets: Vec<BucketKey> = Vec::with_capacity(legs_n);
         for (i, idxs) in [leg0, leg1, leg2].into_iter().enumerate() {
             if i >= legs_n {
                 break;
@@ -521,6 +531,12 @@ fn parse_rows(shadow_log_path: &Path, run_id: &str) -> anyhow::Result<Vec<Row>>
                 best_bid,
                 v_mkt,
             });
+            leg_buckets.push(
+                record
+                    .get(idxs.bucket)
+                    .and_then(BucketKey::parse)
+                    .unwrap_or(bucket),
+            );
         }
         if legs.len() != legs_n {
             continue;
@@ -528,9 +544,10 @@ fn parse_rows(shadow_log_path: &Path, run_id: &str) -> anyhow::Result<Vec<Row>>
 
         out.push(Row {
             day_start_ms,
-            bucket,
             q_req,
             legs,
+            leg_buckets,
+            fees,
             total_pnl_logged,
             set_ratio_logged,
         });
@@ -544,6 +561,7 @@ struct LegIdxs {
     p_limit: usize,
     best_bid: usize,
     v_mkt: usize,
+    bucket: usize,
 }
 
 impl LegIdxs {
@@ -561,6 +579,10 @@ impl LegIdxs {
                 .iter()
                 .position(|h| h.eq_ignore_ascii_case(&format!("leg{i}_v_mkt")))
                 .context("leg v_mkt idx")?,
+            bucket: SHADOW_HEADER
+                .iter()
+                .position(|h| h.eq_ignore_ascii_case(&format!("leg{i}_bucket")))
+                .context("leg bucket idx")?,
         })
     }
 }
@@ -633,6 +655,13 @@ mod tests {
             notes_enum_version: "v1".to_string(),
             trade_poll_taker_only: None,
             sim_stress: crate::run_meta::SimStressProfile::default(),
+            abnormal_termination: None,
+            resumed_ts_unix_ms: Vec::new(),
+            provenance: crate::run_meta::Provenance::default(),
+            recorder_durability: None,
+            secret_fingerprints: Default::default(),
+            tags: Default::default(),
+            live_interlocks: Vec::new(),
         }
         .write_to_dir(&tmp)?;
 
@@ -0,0 +1,496 @@
+//! Synthetic feed behind `razor demo`: the `[demo]` markets' books and trades are generated
+//! in-process and go through the same channels, CSVs and health counters as the WS feed and the
+//! trades poller, so brain, shadow and the live_sim sniper run unchanged without network access.
+//!
+//! [`DemoSim`] is a pure, seeded state machine stepped once per `tick_interval_ms`; only the
+//! timestamps stamped on its output come from the wall clock.
+
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::Duration;
+
+use tokio::sync::{mpsc, watch};
+use tracing::{info, warn};
+
+use crate::backpressure::{self, SendOutcome};
+use crate::buckets::TradeArrivals;
+use crate::config::{Config, DemoConfig};
+use crate::errors::{ErrorContext as _, RazorError};
+use crate::feed::open_run_csv;
+use crate::health::HealthCounters;
+use crate::recorder::{TICKS_HEADER, TRADES_HEADER};
+use crate::rows::{TickRow, TradeRow};
+use crate::types::{
+    now_us, trade_dedup_key, LegSnapshot, MarketDef, MarketSnapshot, Price, Qty, Side, TokenId,
+    TradeTick,
+};
+
+/// Synthetic trades queued between the book task and the trades task.
+const DEMO_TRADE_QUEUE: usize = 4_096;
+const MIN_PRICE: f64 = 0.001;
+const MAX_PRICE: f64 = 0.999;
+
+/// `demo-b<i>` (2 legs) then `demo-t<i>` (3 legs); tokens are `<market_id>-<leg>`.
+pub fn markets(cfg: &DemoConfig) -> Vec<MarketDef> {
+    let binary = (0..cfg.binary_markets).map(|i| (format!("demo-b{i}"), 2));
+    let triangle = (0..cfg.triangle_markets).map(|i| (format!("demo-t{i}"), 3));
+    binary
+        .chain(triangle)
+        .map(|(market_id, legs)| MarketDef {
+            token_ids: (0..legs)
+                .map(|leg| TokenId::from(format!("{market_id}-{leg}")))
+                .collect(),
+            market_id: market_id.into(),
+        })
+        .collect()
+}
+
+/// SplitMix64: tiny, seedable and identical on every platform.
+#[derive(Debug, Clone)]
+struct Rng(u64);
+
+impl Rng {
+    fn next_u64(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        z ^ (z >> 31)
+    }
+
+    /// Uniform in [0, 1).
+    fn unit(&mut self) -> f64 {
+        (self.next_u64() >> 11) as f64 / (1u64 << 53) as f64
+    }
+
+    fn chance(&mut self, p: f64) -> bool {
+        self.unit() < p
+    }
+
+    /// Standard normal (Box-Muller).
+    fn normal(&mut self) -> f64 {
+        let u1 = (1.0 - self.unit()).max(f64::MIN_POSITIVE);
+        let u2 = self.unit();
+        (-2.0 * u1.ln()).sqrt() * (std::f64::consts::TAU * u2).cos()
+    }
+
+    /// Poisson(lambda) by inversion; `lambda` is per tick, so it stays small.
+    fn poisson(&mut self, lambda: f64) -> usize {
+        let limit = (-lambda).exp();
+        let mut n = 0;
+        let mut p = self.unit();
+        while p > limit && n < 1_000 {
+            n += 1;
+            p *= self.unit();
+        }
+        n
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) struct DemoQuote {
+    pub bid: f64,
+    pub ask: f64,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) enum DemoEvent {
+    /// New top of book for every leg of `market` (index into [`markets`]).
+    Book { market: usize, legs: Vec<DemoQuote> },
+    Trade {
+        market: usize,
+        leg: usize,
+        price: f64,
+        size: f64,
+        side: Side,
+    },
+}
+
+#[derive(Debug, Clone)]
+struct SimMarket {
+    logits: Vec<f64>,
+    /// Long-run logits the prices revert to.
+    anchor: Vec<f64>,
+    quotes: Vec<DemoQuote>,
+    arb_until_ms: u64,
+    burst_until_ms: u64,
+    outage_until_ms: u64,
+}
+
+pub(crate) struct DemoSim {
+    cfg: DemoConfig,
+    markets: Vec<SimMarket>,
+    rng: Rng,
+    /// Simulated time since start.
+    t_ms: u64,
+}
+
+impl DemoSim {
+    pub(crate) fn new(cfg: &DemoConfig, markets: &[MarketDef]) -> Self {
+        let mut rng = Rng(cfg.seed);
+        let markets = markets
+            .iter()
+            .map(|m| {
+                let anchor: Vec<f64> = (0..m.token_ids.len()).map(|_| rng.normal() * 0.5).collect();
+                SimMarket {
+                    logits: anchor.clone(),
+                    anchor,
+                    quotes: Vec::new(),
+                    arb_until_ms: 0,
+                    burst_until_ms: 0,
+                    outage_until_ms: 0,
+                }
+            })
+            .collect();
+        Self {
+            cfg: cfg.clone(),
+            markets,
+            rng,
+            t_ms: 0,
+        }
+    }
+
+    /// Advances one `tick_interval_ms` and returns the tick's events, books before trades.
+    pub(crate) fn step(&mut self) -> Vec<DemoEvent> {
+        let cfg = &self.cfg;
+        let dt_ms = cfg.tick_interval_ms as f64;
+        let per_tick = |per_ms: f64| (per_ms * dt_ms).min(1.0);
+        let p_arb = per_tick(cfg.arb_windows_per_min / 60_000.0);
+        let p_burst = per_tick(cfg.bursts_per_min / 60_000.0);
+        let p_outage = per_tick(cfg.outages_per_hour / 3_600_000.0);
+        let half_spread = f64::from(cfg.half_spread_bps) / 10_000.0;
+        let arb_edge = f64::from(cfg.arb_edge_bps) / 10_000.0;
+
+        self.t_ms += cfg.tick_interval_ms;
+        let now = self.t_ms;
+        let rng = &mut self.rng;
+        let mut events = Vec::new();
+        for (idx, m) in self.markets.iter_mut().enumerate() {
+            // Prices keep moving through an outage, so the book jumps when it comes back.
+            for (x, anchor) in m.logits.iter_mut().zip(&m.anchor) {
+                *x += cfg.mean_reversion * (anchor - *x) + cfg.volatility * rng.normal();
+            }
+            if now < m.outage_until_ms {
+                continue;
+            }
+            if rng.chance(p_outage) {
+                m.outage_until_ms = now + cfg.outage_ms;
+                continue;
+            }
+            if now >= m.arb_until_ms && rng.chance(p_arb) {
+                m.arb_until_ms = now + cfg.arb_window_ms;
+            }
+            if now >= m.burst_until_ms && rng.chance(p_burst) {
+                m.burst_until_ms = now + cfg.burst_ms;
+            }
+
+            let fair = softmax(&m.logits);
+            let legs_n = fair.len() as f64;
+            m.quotes = fair
+                .iter()
+                .map(|p| {
+                    let ask = if now < m.arb_until_ms {
+                        // Asks sum to `1 - arb_edge` across the legs.
+                        p - arb_edge / legs_n
+                    } else {
+                        p + half_spread
+                    };
+                    let ask = ask.clamp(MIN_PRICE, MAX_PRICE);
+                    DemoQuote {
+                        bid: (ask - 2.0 * half_spread).clamp(MIN_PRICE, ask),
+                        ask,
+                    }
+                })
+                .collect();
+            events.push(DemoEvent::Book {
+                market: idx,
+                legs: m.quotes.clone(),
+            });
+
+            let mut rate_per_ms = cfg.trades_per_sec / 1_000.0;
+            if now < m.burst_until_ms {
+                rate_per_ms *= cfg.burst_multiplier;
+            }
+            for _ in 0..rng.poisson(rate_per_ms * dt_ms) {
+                let leg = (rng.next_u64() % m.quotes.len() as u64) as usize;
+                let side = if rng.chance(0.5) {
+                    Side::Buy
+                } else {
+                    Side::Sell
+                };
+                let q = m.quotes[leg];
+                let size = (5.0 * -(1.0 - rng.unit()).ln()).max(1.0);
+                events.push(DemoEvent::Trade {
+                    market: idx,
+                    leg,
+                    price: if side == Side::Buy { q.ask } else { q.bid },
+                    size: (size * 100.0).round() / 100.0,
+                    side,
+                });
+            }
+        }
+        events
+    }
+}
+
+fn softmax(logits: &[f64]) -> Vec<f64> {
+    let max = logits.iter().copied().fold(f64::NEG_INFINITY, f64::max);
+    let exp: Vec<f64> = logits.iter().map(|x| (x - max).exp()).collect();
+    let sum: f64 = exp.iter().sum();
+    exp.into_iter().map(|e| e / sum).collect()
+}
+
+/// The WS-feed side of the demo: steps [`DemoSim`], writes ticks.csv, publishes snapshots and
+/// hands trades to [`run_demo_trades`].
+#[allow(clippy::too_many_arguments)]
+pub async fn run_demo_books(
+    cfg: Config,
+    markets: Vec<MarketDef>,
+    snap_tx: watch::Sender<Option<MarketSnapshot>>,
+    demo_trade_tx: mpsc::Sender<TradeTick>,
+    ticks_path: PathBuf,
+    health: Arc<HealthCounters>,
+    mut shutdown: watch::Receiver<bool>,
+) -> anyhow::Result<()> {
+    let mut ticks = open_run_csv(&cfg, &ticks_path, &TICKS_HEADER).io_context("open ticks.csv")?;
+    let mut sim = DemoSim::new(&cfg.demo, &markets);
+    let mut seq: u64 = 0;
+    info!(
+        seed = cfg.demo.seed,
+        markets = markets.len(),
+        tick_interval_ms = cfg.demo.tick_interval_ms,
+        "demo feed started (synthetic books and trades)"
+    );
+
+    let mut interval = tokio::time::interval(Duration::from_millis(cfg.demo.tick_interval_ms));
+    interval.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+    loop {
+        tokio::select! {
+            _ = shutdown.changed() => {
+                if *shutdown.borrow() { break; }
+            }
+            _ = interval.tick() => {}
+        }
+        if *shutdown.borrow() {
+            break;
+        }
+
+        for ev in sim.step() {
+            let ts_recv_us = now_us();
+            let ts_ms = ts_recv_us / 1000;
+            match ev {
+                DemoEvent::Book { market, legs } => {
+                    let m = &markets[market];
+                    let depth3 = cfg.demo.depth3_usdc;
+                    for (token_id, q) in m.token_ids.iter().zip(&legs) {
+                        ticks
+                            .write_row(&TickRow {
+                                ts_recv_us,
+                                market_id: m.market_id.to_string(),
+                                token_id: token_id.to_string(),
+                                best_bid: q.bid,
+                                best_ask: q.ask,
+                                ask_depth3_usdc: depth3,
+                            })
+                            .io_context("write ticks.csv")?;
+                        health.inc_ticks_processed(1);
+                    }
+                    health.set_last_tick_ingest_ms(ts_ms);
+                    health.set_market_last_tick_ingest_ms(&m.market_id, ts_ms);
+
+                    let snap = MarketSnapshot {
+                        market_id: m.market_id.clone(),
+                        legs: m
+                            .token_ids
+                            .iter()
+                            .zip(&legs)
+                            .map(|(token_id, q)| LegSnapshot {
+                                token_id: token_id.clone(),
+                                best_ask: Price::from_f64(q.ask).unwrap_or(Price::MISSING_ASK),
+                                best_bid: Price::from_f64(q.bid).unwrap_or(Price::MISSING_BID),
+                                best_ask_size_best: Qty::from_f64(depth3 / 3.0 / q.ask)
+                                    .unwrap_or(Qty::ZERO),
+                                best_bid_size_best: Qty::from_f64(depth3 / 3.0 / q.ask)
+                                    .unwrap_or(Qty::ZERO),
+                                ask_depth3_usdc: depth3,
+                                asks: Default::default(),
+                                ts_recv_us,
+                            })
+                            .collect(),
+                    };
+                    health.inc_snapshots_published(1);
+                    let _ = snap_tx.send(Some(snap));
+                }
+                DemoEvent::Trade {
+                    market,
+                    leg,
+                    price,
+                    size,
+                    side,
+                } => {
+                    let (Some(px), Some(qty)) = (Price::from_f64(price), Qty::from_f64(size))
+                    else {
+                        continue;
+                    };
+                    let m = &markets[market];
+                    seq += 1;
+                    let token_id = m.token_ids[leg].clone();
+                    let trade_id = trade_dedup_key(
+                        &m.market_id,
+                        &token_id,
+                        ts_ms,
+                        price,
+                        size,
+                        &format!("demo-{seq}"),
+                    );
+                    let tick = TradeTick {
+                        ts_ms,
+                        ingest_ts_ms: ts_ms,
+                        exchange_ts_ms: Some(ts_ms),
+                        market_id: m.market_id.clone(),
+                        token_id,
+                        price: px,
+                        size: qty,
+                        trade_id,
+                        side: Some(side),
+                    };
+                    if demo_trade_tx.try_send(tick).is_err() {
+                        health.inc_trades_dropped(1);
+                    }
+                }
+            }
+        }
+    }
+
+    ticks.flush_and_sync().io_context("flush ticks.csv")?;
+    Ok(())
+}
+
+/// The trades-poller side of the demo: records the generated trades and forwards them to shadow.
+pub async fn run_demo_trades(
+    cfg: Config,
+    mut demo_trade_rx: mpsc::Receiver<TradeTick>,
+    trade_tx: backpressure::Sender<TradeTick>,
+    trades_path: PathBuf,
+    health: Arc<HealthCounters>,
+    trade_arrivals: Arc<TradeArrivals>,
+    mut shutdown: watch::Receiver<bool>,
+) -> anyhow::Result<()> {
+    let mut trades =
+        open_run_csv(&cfg, &trades_path, &TRADES_HEADER).io_context("open trades.csv")?;
+    let mut dropped_trades: u64 = 0;
+
+    loop {
+        let tick = tokio::select! {
+            _ = shutdown.changed() => {
+                if *shutdown.borrow() { break; }
+                continue;
+            }
+            tick = demo_trade_rx.recv() => match tick {
+                Some(t) => t,
+                None => break,
+            },
+        };
+
+        trades
+            .write_row(&TradeRow::from(&tick))
+            .io_context("write trades.csv")?;
+        health.inc_trades_written(1);
+        health.set_last_trade_ingest_ms(tick.ingest_ts_ms);
+        health.set_market_last_trade_ingest_ms(&tick.market_id, tick.ingest_ts_ms);
+        trade_arrivals.record(&tick.market_id, tick.ingest_ts_ms);
+
+        match trade_tx.send(tick).await {
+            Ok(SendOutcome::Queued) => {}
+            Ok(SendOutcome::Dropped(_)) => {
+                health.inc_trades_dropped(1);
+                dropped_trades += 1;
+                if dropped_trades.is_power_of_two() {
+                    warn!(dropped_trades, "trade channel full; dropping demo trades");
+                }
+            }
+            Err(backpressure::Closed) => {
+                return Err(RazorError::ChannelClosed("trade").into());
+            }
+        }
+    }
+
+    trades.flush_and_sync().io_context("flush trades.csv")?;
+    Ok(())
+}
+
+/// Channel between [`run_demo_books`] and [`run_demo_trades`].
+pub fn trade_queue() -> (mpsc::Sender<TradeTick>, mpsc::Receiver<TradeTick>) {
+    mpsc::channel(DEMO_TRADE_QUEUE)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn run(cfg: &DemoConfig, ticks: usize) -> Vec<DemoEvent> {
+        let mut sim = DemoSim::new(cfg, &markets(cfg));
+        (0..ticks).flat_map(|_| sim.step()).collect()
+    }
+
+    #[test]
+    fn same_seed_same_sequence() {
+        let cfg = DemoConfig::default();
+        assert_eq!(run(&cfg, 500), run(&cfg, 500));
+
+        let other = DemoConfig {
+            seed: 2,
+            ..DemoConfig::default()
+        };
+        assert_ne!(run(&cfg, 500), run(&other, 500));
+    }
+
+    #[test]
+    fn arb_windows_outages_and_bursts_show_up() {
+        let cfg = DemoConfig {
+            arb_windows_per_min: 6.0,
+            outages_per_hour: 60.0,
+            bursts_per_min: 6.0,
+            ..DemoConfig::default()
+        };
+        let ticks = 4 * 60 * 10; // 10 simulated minutes at 250ms.
+        let events = run(&cfg, ticks);
+
+        let sums: Vec<f64> = events
+            .iter()
+            .filter_map(|e| match e {
+                DemoEvent::Book { legs, .. } => Some(legs.iter().map(|q| q.ask).sum()),
+                _ => None,
+            })
+            .collect();
+        // Outside windows the asks sum above 1; inside them to 1 - arb_edge_bps.
+        assert!(sums.iter().any(|s| *s > 1.0));
+        assert!(sums.iter().any(|s| (*s - 0.96).abs() < 1e-9));
+        // Outages: fewer books than ticks x markets.
+        assert!(sums.len() < ticks * 2);
+
+        let trades = events
+            .iter()
+            .filter(|e| matches!(e, DemoEvent::Trade { .. }))
+            .count();
+        // 2 markets x 2/s x 600s without bursts; bursts add on top.
+        assert!(trades > 2_400, "trades={trades}");
+        for e in &events {
+            if let DemoEvent::Book { legs, .. } = e {
+                assert!(legs.iter().all(|q| q.bid <= q.ask && q.ask < 1.0));
+            }
+        }
+    }
+
+    #[test]
+    fn markets_follow_config_counts() {
+        let ms = markets(&DemoConfig {
+            binary_markets: 2,
+            triangle_markets: 1,
+            ..DemoConfig::default()
+        });
+        let legs: Vec<usize> = ms.iter().map(|m| m.token_ids.len()).collect();
+        assert_eq!(legs, vec![2, 2, 3]);
+        assert_eq!(ms[2].token_ids[1].as_str(), "demo-t0-1");
+    }
+}
@@ -0,0 +1,207 @@
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::Duration;
+
+use tokio::sync::{mpsc, watch};
+use tracing::{error, info, warn};
+
+use crate::config::DiskConfig;
+use crate::fs_compat::available_bytes;
+use crate::graceful_shutdown;
+use crate::health::{HealthCounters, HealthLine};
+use crate::types::now_ms;
+
+const MIB: u64 = 1024 * 1024;
+
+/// Output degradation steps, ordered from "all good" to "stop the run".
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum DiskLevel {
+    Ok = 0,
+    RawWsOff = 1,
+    TicksDegraded = 2,
+    Shutdown = 3,
+}
+
+impl DiskLevel {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            DiskLevel::Ok => "ok",
+            DiskLevel::RawWsOff => "raw_ws_off",
+            DiskLevel::TicksDegraded => "ticks_degraded",
+            DiskLevel::Shutdown => "shutdown",
+        }
+    }
+
+    pub fn from_u64(v: u64) -> Self {
+        match v {
+            0 => DiskLevel::Ok,
+            1 => DiskLevel::RawWsOff,
+            2 => DiskLevel::TicksDegraded,
+            _ => DiskLevel::Shutdown,
+        }
+    }
+}
+
+pub fn level_for_free_bytes(cfg: &DiskConfig, free_bytes: u64) -> DiskLevel {
+    let below = |mb: u64| mb > 0 && free_bytes < mb.saturating_mul(MIB);
+    if below(cfg.shutdown_free_mb) {
+        DiskLevel::Shutdown
+    } else if below(cfg.ticks_degraded_free_mb) {
+        DiskLevel::TicksDegraded
+    } else if below(cfg.raw_ws_off_free_mb) {
+        DiskLevel::RawWsOff
+    } else {
+        DiskLevel::Ok
+    }
+}
+
+/// Periodically checks free space under `data_dir` and publishes the degradation level through
+/// `HealthCounters`. Once the shutdown threshold is crossed it records a `disk_shutdown` line and
+/// requests a graceful shutdown through `stop_tx` (report + flush) instead of failing mid-write.
+pub async fn run(
+    cfg: DiskConfig,
+    data_dir: PathBuf,
+    health: Arc<HealthCounters>,
+    health_tx: mpsc::Sender<HealthLine>,
+    stop_tx: watch::Sender<bool>,
+    mut shutdown: watch::Receiver<bool>,
+) -> anyhow::Result<()> {
+    let mut interval = tokio::time::interval(Duration::from_millis(cfg.check_interval_ms));
+    interval.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+    let mut warned_unavailable = false;
+
+    loop {
+        tokio::select! {
+            _ = shutdown.changed() => {
+                if *shutdown.borrow() { break; }
+            }
+            _ = interval.tick() => {}
+        }
+        if *shutdown.borrow() {
+            break;
+        }
+
+        let Some(free_bytes) = available_bytes(&data_dir) else {
+            if !warned_unavailable {
+                warned_unavailable = true;
+                warn!(data_dir = %data_dir.display(), "free disk space unavailable; disk guard idle");
+            }
+            continue;
+        };
+
+        let prev = health.disk_level();
+        let level = level_for_free_bytes(&cfg, free_bytes);
+        health.set_disk_status(level, free_bytes);
+        if level == prev {
+            continue;
+        }
+
+        let _ = health_tx.try_send(HealthLine::DiskLevelChanged {
+            ts_ms: now_ms(),
+            free_bytes,
+            from: prev.as_str().to_string(),
+            to: level.as_str().to_string(),
+        });
+
+        match level {
+            DiskLevel::Ok => info!(free_bytes, "disk space recovered; full output resumed"),
+            DiskLevel::RawWsOff => {
+                warn!(free_bytes, "disk space low; raw_ws.jsonl capture paused")
+            }
+            DiskLevel::TicksDegraded => {
+                warn!(
+                    free_bytes,
+                    "disk space low; raw_ws.jsonl paused, ticks.csv throttled"
+                )
+            }
+            DiskLevel::Shutdown => {
+                error!(free_bytes, "disk space critical; shutting down");
+                let _ = health_tx.try_send(HealthLine::DiskShutdown {
+                    ts_ms: now_ms(),
+                    free_bytes,
+                    threshold_mb: cfg.shutdown_free_mb,
+                });
+                graceful_shutdown::request(&stop_tx);
+                break;
+            }
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn levels_follow_thresholds_and_zero_disables() {
+        let cfg = DiskConfig {
+            check_interval_ms: 1_000,
+            raw_ws_off_free_mb: 300,
+            ticks_degraded_free_mb: 200,
+            shutdown_free_mb: 100,
+        };
+        assert_eq!(level_for_free_bytes(&cfg, 400 * MIB), DiskLevel::Ok);
+        assert_eq!(level_for_free_bytes(&cfg, 250 * MIB), DiskLevel::RawWsOff);
+        assert_eq!(
+            level_for_free_bytes(&cfg, 150 * MIB),
+            DiskLevel::TicksDegraded
+        );
+        assert_eq!(level_for_free_bytes(&cfg, 50 * MIB), DiskLevel::Shutdown);
+
+        let off = DiskConfig {
+            check_interval_ms: 1_000,
+            raw_ws_off_free_mb: 0,
+            ticks_degraded_free_mb: 0,
+            shutdown_free_mb: 0,
+        };
+        assert_eq!(level_for_free_bytes(&off, 0), DiskLevel::Ok);
+    }
+
+    #[tokio::test]
+    async fn shutdown_level_requests_a_graceful_stop() {
+        let data_dir = std::env::temp_dir();
+        if available_bytes(&data_dir).is_none() {
+            return;
+        }
+        let cfg = DiskConfig {
+            check_interval_ms: 10,
+            raw_ws_off_free_mb: 0,
+            ticks_degraded_free_mb: 0,
+            shutdown_free_mb: u64::MAX / MIB,
+        };
+        let health = Arc::new(HealthCounters::default());
+        let (health_tx, mut health_rx) = mpsc::channel(8);
+        let (stop_tx, shutdown_rx) = graceful_shutdown::channel();
+
+        run(
+            cfg,
+            data_dir,
+            health.clone(),
+            health_tx,
+            stop_tx.clone(),
+            shutdown_rx.clone(),
+        )
+        .await
+        .expect("a disk shutdown is a graceful stop, not an error");
+
+        assert!(*shutdown_rx.borrow());
+        assert_eq!(health.disk_level(), DiskLevel::Shutdown);
+        assert!(matches!(
+            health_rx.recv().await,
+            Some(HealthLine::DiskLevelChanged { ref to, .. }) if to == "shutdown"
+        ));
+        assert!(matches!(
+            health_rx.recv().await,
+            Some(HealthLine::DiskShutdown { threshold_mb, .. }) if threshold_mb == u64::MAX / MIB
+        ));
+    }
+
+    #[test]
+    fn available_bytes_reports_temp_dir() {
+        if cfg!(any(unix, windows)) {
+            assert!(available_bytes(&std::env::temp_dir()).is_some());
+        }
+    }
+}
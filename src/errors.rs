@@ -0,0 +1,241 @@
+//! Error taxonomy for the run tasks. Failures in feed/shadow/sniper/execution carry a stable
+//! [`ErrorCode`]; health.jsonl (`task_error`) and crash_report.json record the code next to the
+//! message, so failures can be grouped across runs without parsing free text.
+
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+pub type BoxError = Box<dyn std::error::Error + Send + Sync + 'static>;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum ErrorCode {
+    /// WS handshake failed or timed out.
+    WsConnect,
+    /// An established WS failed: send/read error, close frame or end of stream.
+    WsStream,
+    /// The WS was torn down because the feed went stale.
+    FeedStale,
+    /// An HTTP request failed (Gamma, Data-API trades, CLOB endpoints).
+    Http,
+    /// A response or message could not be decoded.
+    Decode,
+    /// The other end of an internal channel was dropped.
+    ChannelClosed,
+    /// A run output could not be opened, written or flushed.
+    Io,
+    /// The execution gateway refused to build or place an order.
+    OrderRejected,
+    /// Any other execution gateway failure.
+    Exec,
+    /// A task panicked.
+    Panic,
+    /// A `[risk]` kill-switch condition stopped the run.
+    RiskHardstop,
+    /// The error did not come from this taxonomy.
+    #[default]
+    Internal,
+}
+
+impl ErrorCode {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            ErrorCode::WsConnect => "WS_CONNECT",
+            ErrorCode::WsStream => "WS_STREAM",
+            ErrorCode::FeedStale => "FEED_STALE",
+            ErrorCode::Http => "HTTP",
+            ErrorCode::Decode => "DECODE",
+            ErrorCode::ChannelClosed => "CHANNEL_CLOSED",
+            ErrorCode::Io => "IO",
+            ErrorCode::OrderRejected => "ORDER_REJECTED",
+            ErrorCode::Exec => "EXEC",
+            ErrorCode::Panic => "PANIC",
+            ErrorCode::RiskHardstop => "RISK_HARDSTOP",
+            ErrorCode::Internal => "INTERNAL",
+        }
+    }
+}
+
+impl std::fmt::Display for ErrorCode {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum RazorError {
+    #[error("connect ws {url}")]
+    WsConnect {
+        url: String,
+        #[source]
+        source: BoxError,
+    },
+    #[error("{what}")]
+    WsStream {
+        what: String,
+        #[source]
+        source: Option<BoxError>,
+    },
+    #[error("forced reconnect (feed stale)")]
+    FeedStale,
+    #[error("{what}")]
+    Http {
+        what: String,
+        #[source]
+        source: BoxError,
+    },
+    #[error("decode {what}")]
+    Decode {
+        what: String,
+        #[source]
+        source: BoxError,
+    },
+    #[error("{0} channel closed")]
+    ChannelClosed(&'static str),
+    #[error("{what}")]
+    Io {
+        what: String,
+        #[source]
+        source: BoxError,
+    },
+    #[error("order rejected: {0}")]
+    OrderRejected(String),
+    #[error("{what}")]
+    Exec {
+        what: String,
+        #[source]
+        source: BoxError,
+    },
+    #[error("task panicked")]
+    Panic(#[source] BoxError),
+    #[error("risk hardstop: {0}")]
+    RiskHardstop(String),
+}
+
+impl RazorError {
+    pub fn code(&self) -> ErrorCode {
+        match self {
+            RazorError::WsConnect { .. } => ErrorCode::WsConnect,
+            RazorError::WsStream { .. } => ErrorCode::WsStream,
+            RazorError::FeedStale => ErrorCode::FeedStale,
+            RazorError::Http { .. } => ErrorCode::Http,
+            RazorError::Decode { .. } => ErrorCode::Decode,
+            RazorError::ChannelClosed(_) => ErrorCode::ChannelClosed,
+            RazorError::Io { .. } => ErrorCode::Io,
+            RazorError::OrderRejected(_) => ErrorCode::OrderRejected,
+            RazorError::Exec { .. } => ErrorCode::Exec,
+            RazorError::Panic(_) => ErrorCode::Panic,
+            RazorError::RiskHardstop(_) => ErrorCode::RiskHardstop,
+        }
+    }
+
+    pub fn ws_stream(what: impl Into<String>) -> Self {
+        RazorError::WsStream {
+            what: what.into(),
+            source: None,
+        }
+    }
+}
+
+/// The code of the [`RazorError`] in `err`'s chain; [`ErrorCode::Internal`] if there is none.
+pub fn code_of(err: &anyhow::Error) -> ErrorCode {
+    err.chain()
+        .find_map(|e| e.downcast_ref::<RazorError>())
+        .map_or(ErrorCode::Internal, RazorError::code)
+}
+
+/// An `anyhow` error from a lower layer as a [`RazorError`]: kept (with its code) when it already
+/// is one, otherwise wrapped by `wrap`.
+pub fn into_razor(err: anyhow::Error, wrap: impl FnOnce(BoxError) -> RazorError) -> RazorError {
+    match err.downcast::<RazorError>() {
+        Ok(tagged) => tagged,
+        Err(err) => wrap(err.into()),
+    }
+}
+
+/// `anyhow::Context`-style constructors that tag the error with its [`ErrorCode`].
+#[allow(dead_code)]
+pub trait ErrorContext<T> {
+    fn ws_context(self, what: impl Into<String>) -> Result<T, RazorError>;
+    fn http_context(self, what: impl Into<String>) -> Result<T, RazorError>;
+    fn decode_context(self, what: impl Into<String>) -> Result<T, RazorError>;
+    fn io_context(self, what: impl Into<String>) -> Result<T, RazorError>;
+    fn exec_context(self, what: impl Into<String>) -> Result<T, RazorError>;
+}
+
+impl<T, E: Into<BoxError>> ErrorContext<T> for Result<T, E> {
+    fn ws_context(self, what: impl Into<String>) -> Result<T, RazorError> {
+        self.map_err(|e| RazorError::WsStream {
+            what: what.into(),
+            source: Some(e.into()),
+        })
+    }
+
+    fn http_context(self, what: impl Into<String>) -> Result<T, RazorError> {
+        self.map_err(|e| RazorError::Http {
+            what: what.into(),
+            source: e.into(),
+        })
+    }
+
+    fn decode_context(self, what: impl Into<String>) -> Result<T, RazorError> {
+        self.map_err(|e| RazorError::Decode {
+            what: what.into(),
+            source: e.into(),
+        })
+    }
+
+    fn io_context(self, what: impl Into<String>) -> Result<T, RazorError> {
+        self.map_err(|e| RazorError::Io {
+            what: what.into(),
+            source: e.into(),
+        })
+    }
+
+    fn exec_context(self, what: impl Into<String>) -> Result<T, RazorError> {
+        self.map_err(|e| RazorError::Exec {
+            what: what.into(),
+            source: e.into(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use anyhow::Context as _;
+
+    use super::*;
+
+    #[test]
+    fn code_survives_anyhow_context_and_serializes_stably() {
+        let err: anyhow::Result<()> = Err(std::io::Error::other("disk full"))
+            .io_context("flush ticks.csv")
+            .context("ws task failed");
+        let err = err.unwrap_err();
+        assert_eq!(code_of(&err), ErrorCode::Io);
+        assert_eq!(
+            format!("{err:#}"),
+            "ws task failed: flush ticks.csv: disk full"
+        );
+
+        assert_eq!(code_of(&anyhow::anyhow!("plain")), ErrorCode::Internal);
+        let exec = |source| RazorError::Exec {
+            what: "exec error".to_string(),
+            source,
+        };
+        let http = anyhow::Error::from(RazorError::Http {
+            what: "GET /tick-size".to_string(),
+            source: "timed out".into(),
+        });
+        assert_eq!(into_razor(http, exec).code(), ErrorCode::Http);
+        assert_eq!(
+            into_razor(anyhow::anyhow!("sim"), exec).code(),
+            ErrorCode::Exec
+        );
+        assert_eq!(
+            serde_json::to_value(ErrorCode::ChannelClosed).unwrap(),
+            "CHANNEL_CLOSED"
+        );
+        assert_eq!(ErrorCode::OrderRejected.to_string(), "ORDER_REJECTED");
+    }
+}
@@ -0,0 +1,184 @@
+use std::time::Duration;
+
+use anyhow::Context as _;
+use serde_json::json;
+use tracing::{info, warn};
+
+use crate::config::{Config, EscalationProvider};
+use crate::types::{MarketId, SignalId};
+
+const PAGERDUTY_ENQUEUE_URL: &str = "https://events.pagerduty.com/v2/enqueue";
+const OPSGENIE_ALERTS_URL: &str = "https://api.opsgenie.com/v2/alerts";
+const ESCALATION_HTTP_TIMEOUT_MS: u64 = 10_000;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Severity {
+    Critical,
+    Error,
+}
+
+impl Severity {
+    /// PagerDuty `payload.severity`.
+    fn pagerduty(self) -> &'static str {
+        match self {
+            Severity::Critical => "critical",
+            Severity::Error => "error",
+        }
+    }
+
+    /// Opsgenie `priority`.
+    fn opsgenie(self) -> &'static str {
+        match self {
+            Severity::Critical => "P1",
+            Severity::Error => "P2",
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct EscalationEvent {
+    /// Stable event class; together with run_id it forms the dedup key.
+    pub kind: &'static str,
+    pub severity: Severity,
+    pub summary: String,
+    pub details: serde_json::Value,
+}
+
+impl EscalationEvent {
+    /// Sniper HARDSTOP. A failed flatten leaves inventory on the book and pages as critical.
+    pub fn hardstop(signal_id: SignalId, market_id: &MarketId, reason: &str) -> Self {
+        let (kind, severity) = if reason.starts_with("flatten_failed") {
+            ("flatten_failed", Severity::Critical)
+        } else {
+            ("hardstop", Severity::Error)
+        };
+        Self {
+            kind,
+            severity,
+            summary: format!("razor sniper HARDSTOP ({kind}) market={market_id}: {reason}"),
+            details: json!({
+                "signal_id": signal_id,
+                "market_id": market_id,
+                "reason": reason,
+            }),
+        }
+    }
+}
+
+/// One incident per run and event class: repeats within a run update the same page.
+pub fn dedup_key(run_id: &str, kind: &str) -> String {
+    format!("razor:{run_id}:{kind}")
+}
+
+fn pagerduty_body(routing_key: &str, run_id: &str, ev: &EscalationEvent) -> serde_json::Value {
+    json!({
+        "routing_key": routing_key,
+        "event_action": "trigger",
+        "dedup_key": dedup_key(run_id, ev.kind),
+        "payload": {
+            "summary": ev.summary,
+            "source": format!("razor/{run_id}"),
+            "severity": ev.severity.pagerduty(),
+            "component": "sniper",
+            "class": ev.kind,
+            "custom_details": ev.details,
+        },
+    })
+}
+
+fn opsgenie_body(run_id: &str, ev: &EscalationEvent) -> serde_json::Value {
+    json!({
+        "message": ev.summary,
+        "alias": dedup_key(run_id, ev.kind),
+        "priority": ev.severity.opsgenie(),
+        "source": format!("razor/{run_id}"),
+        "tags": ["razor", ev.kind],
+        "details": ev.details,
+    })
+}
+
+#[derive(Clone)]
+pub struct Escalator {
+    http: reqwest::Client,
+    provider: EscalationProvider,
+    key: String,
+    run_id: String,
+}
+
+impl Escalator {
+    /// `None` when escalation is disabled (or `live_only` and this is not a live run). A missing
+    /// key env var fails startup rather than silently never paging.
+    pub fn from_config(cfg: &Config, run_id: &str) -> anyhow::Result<Option<Self>> {
+        let esc = &cfg.escalation;
+        if !esc.enabled || (esc.live_only && !cfg.live.enabled) {
+            return Ok(None);
+        }
+        let env_name = esc.routing_key_env.trim();
+        let key = crate::secrets::require(env_name, "escalation key")?;
+        let http = reqwest::Client::builder()
+            .user_agent(concat!("razor/", env!("CARGO_PKG_VERSION")))
+            .timeout(Duration::from_millis(ESCALATION_HTTP_TIMEOUT_MS))
+            .build()
+            .context("build escalation http client")?;
+        info!(provider = ?esc.provider, "escalation enabled");
+        Ok(Some(Self {
+            http,
+            provider: esc.provider,
+            key,
+            run_id: run_id.to_string(),
+        }))
+    }
+
+    /// Fires the page in the background; the caller (the OMS loop) never waits on the network.
+    pub fn escalate(&self, ev: EscalationEvent) {
+        let this = self.clone();
+        crate::crash::spawn_supervised("escalation", async move {
+            match this.send(&ev).await {
+                Ok(()) => info!(kind = ev.kind, "escalation sent"),
+                Err(e) => warn!(kind = ev.kind, error = %e, "escalation failed"),
+            }
+        });
+    }
+
+    async fn send(&self, ev: &EscalationEvent) -> anyhow::Result<()> {
+        let req = match self.provider {
+            EscalationProvider::Pagerduty => self
+                .http
+                .post(PAGERDUTY_ENQUEUE_URL)
+                .json(&pagerduty_body(&self.key, &self.run_id, ev)),
+            EscalationProvider::Opsgenie => self
+                .http
+                .post(OPSGENIE_ALERTS_URL)
+                .header("Authorization", format!("GenieKey {}", self.key))
+                .json(&opsgenie_body(&self.run_id, ev)),
+        };
+        req.send()
+            .await
+            .context("escalation request")?
+            .error_for_status()
+            .context("escalation response")?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn flatten_failed_pages_critical_with_run_scoped_dedup_key() {
+        let ev = EscalationEvent::hardstop(SignalId(7), &"m1".into(), "flatten_failed:no_snapshot");
+        assert_eq!(ev.kind, "flatten_failed");
+        assert_eq!(ev.severity, Severity::Critical);
+
+        let pd = pagerduty_body("k", "run_1", &ev);
+        assert_eq!(pd["dedup_key"], "razor:run_1:flatten_failed");
+        assert_eq!(pd["payload"]["severity"], "critical");
+
+        let other = EscalationEvent::hardstop(SignalId(8), &"m1".into(), "place_ioc: timeout");
+        assert_eq!(other.kind, "hardstop");
+        let og = opsgenie_body("run_1", &other);
+        assert_eq!(og["alias"], "razor:run_1:hardstop");
+        assert_eq!(og["priority"], "P2");
+    }
+}
@@ -2,12 +2,21 @@ use std::sync::atomic::{AtomicU64, Ordering};
 use std::sync::Arc;
 use std::time::Duration;
 
+#[cfg(feature = "live")]
 use anyhow::Context as _;
 
+#[cfg(feature = "live")]
+use crate::api_usage::{self, ApiEndpoint};
+#[cfg(feature = "live")]
 use crate::clob::{self, ApiCreds, ClobSigner};
+#[cfg(feature = "live")]
 use crate::clob_order::{self, OrderType};
 use crate::config::Config;
-use crate::types::{now_ms, Bucket, FillReport, FillStatus, MarketSnapshot, Side};
+#[cfg(feature = "live")]
+use crate::errors::{ErrorContext as _, RazorError};
+use crate::types::{
+    now_ms, Bucket, FillReport, FillStatus, MarketSnapshot, Price, Qty, Side, TokenId,
+};
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum ExecKind {
@@ -28,14 +37,14 @@ impl ExecKind {
 
 #[derive(Debug, Clone, Copy)]
 pub struct TopOfBook {
-    pub best_ask: f64,
-    pub best_ask_size_best: f64,
-    pub best_bid: f64,
-    pub best_bid_size_best: f64,
+    pub best_ask: Price,
+    pub best_ask_size_best: Qty,
+    pub best_bid: Price,
+    pub best_bid_size_best: Qty,
 }
 
-pub fn top_of_book(snap: &MarketSnapshot, token_id: &str) -> Option<TopOfBook> {
-    let leg = snap.legs.iter().find(|l| l.token_id == token_id)?;
+pub fn top_of_book(snap: &MarketSnapshot, token_id: &TokenId) -> Option<TopOfBook> {
+    let leg = snap.legs.iter().find(|l| &l.token_id == token_id)?;
     Some(TopOfBook {
         best_ask: leg.best_ask,
         best_ask_size_best: leg.best_ask_size_best,
@@ -57,16 +66,17 @@ pub struct ExecResult {
 pub struct PlaceIocRequest<'a> {
     pub kind: ExecKind,
     pub bucket: Bucket,
-    pub token_id: &'a str,
+    pub token_id: &'a TokenId,
     pub side: Side,
-    pub limit_price: f64,
-    pub req_qty: f64,
+    pub limit_price: Price,
+    pub req_qty: Qty,
     pub top: TopOfBook,
 }
 
 #[derive(Debug, Clone)]
 pub enum ExecutionGateway {
     Sim(SimGateway),
+    #[cfg(feature = "live")]
     Live(Arc<LiveGateway>),
 }
 
@@ -90,6 +100,7 @@ impl ExecutionGateway {
         })
     }
 
+    #[cfg(feature = "live")]
     pub async fn new_live(cfg: &Config) -> anyhow::Result<Self> {
         let signer = ClobSigner::from_env(cfg).context("load live signer")?;
         let http = reqwest::Client::builder()
@@ -99,7 +110,7 @@ impl ExecutionGateway {
             ))
             .timeout(Duration::from_millis(cfg.polymarket.http_timeout_ms))
             .build()
-            .context("build clob http client")?;
+            .http_context("build clob http client")?;
 
         let creds: ApiCreds = clob::create_or_derive_api_creds(cfg, &signer, &http)
             .await
@@ -115,14 +126,34 @@ impl ExecutionGateway {
         })))
     }
 
+    /// Built without the `live` feature: there is no live gateway to construct.
+    #[cfg(not(feature = "live"))]
+    pub async fn new_live(_cfg: &Config) -> anyhow::Result<Self> {
+        anyhow::bail!(
+            "live.enabled=true but this binary was built without the `live` feature \
+             (rebuild with `--features live`)"
+        )
+    }
+
+    /// API creds of the live gateway (for the user channel); `None` for SIM.
+    #[cfg(feature = "live")]
+    pub fn live_creds(&self) -> Option<ApiCreds> {
+        match self {
+            ExecutionGateway::Sim(_) => None,
+            ExecutionGateway::Live(g) => Some(g.creds.clone()),
+        }
+    }
+
     pub async fn place_ioc(&self, req: PlaceIocRequest<'_>) -> anyhow::Result<ExecResult> {
         match self {
             ExecutionGateway::Sim(g) => g.place_ioc(req).await,
+            #[cfg(feature = "live")]
             ExecutionGateway::Live(g) => g.place_ioc(req).await,
         }
     }
 }
 
+#[cfg(feature = "live")]
 #[derive(Debug)]
 pub struct LiveGateway {
     base: String,
@@ -133,6 +164,7 @@ pub struct LiveGateway {
     seq: AtomicU64,
 }
 
+#[cfg(feature = "live")]
 impl LiveGateway {
     async fn place_ioc(&self, req: PlaceIocRequest<'_>) -> anyhow::Result<ExecResult> {
         // NOTE: Safety gate. We compute the exact signed request (and HMAC headers) but only send
@@ -157,40 +189,30 @@ impl LiveGateway {
         }
 
         let tick_url = format!("{base}/tick-size?token_id={token_id}");
-        let min_tick_size = self
-            .http
-            .get(&tick_url)
-            .send()
-            .await
-            .context("GET /tick-size")?
-            .json::<TickSizeResp>()
-            .await
-            .context("decode /tick-size")?
-            .minimum_tick_size;
+        let min_tick_size =
+            api_usage::track(ApiEndpoint::Clob, self.http.get(&tick_url).send().await)
+                .http_context("GET /tick-size")?
+                .json::<TickSizeResp>()
+                .await
+                .decode_context("/tick-size")?
+                .minimum_tick_size;
 
         let neg_url = format!("{base}/neg-risk?token_id={token_id}");
-        let neg_risk = self
-            .http
-            .get(&neg_url)
-            .send()
-            .await
-            .context("GET /neg-risk")?
+        let neg_risk = api_usage::track(ApiEndpoint::Clob, self.http.get(&neg_url).send().await)
+            .http_context("GET /neg-risk")?
             .json::<NegRiskResp>()
             .await
-            .context("decode /neg-risk")?
+            .decode_context("/neg-risk")?
             .neg_risk;
 
         let fee_url = format!("{base}/fee-rate?token_id={token_id}");
-        let fee_rate_bps = self
-            .http
-            .get(&fee_url)
-            .send()
-            .await
-            .context("GET /fee-rate")?
-            .json::<FeeRateResp>()
-            .await
-            .context("decode /fee-rate")?
-            .base_fee;
+        let fee_rate_bps =
+            api_usage::track(ApiEndpoint::Clob, self.http.get(&fee_url).send().await)
+                .http_context("GET /fee-rate")?
+                .json::<FeeRateResp>()
+                .await
+                .decode_context("/fee-rate")?
+                .base_fee;
 
         let exchange_addr =
             exchange_address(self.signer.chain_id(), neg_risk).context("exchange_address")?;
@@ -204,16 +226,18 @@ impl LiveGateway {
             clob_order::BuildOrderParams {
                 chain_id: self.signer.chain_id(),
                 exchange_address: exchange_addr,
-                token_id: ethereum_types::U256::from_dec_str(token_id).context("parse token_id")?,
+                token_id: ethereum_types::U256::from_dec_str(token_id).map_err(|e| {
+                    RazorError::OrderRejected(format!("token_id {token_id}: {e:?}"))
+                })?,
                 side: req.side,
-                limit_price: req.limit_price,
-                qty: req.req_qty,
+                limit_price: req.limit_price.to_f64(),
+                qty: req.req_qty.to_f64(),
                 min_tick_size,
                 fee_rate_bps,
                 salt,
             },
         )
-        .context("build signed order")?;
+        .map_err(|e| RazorError::OrderRejected(format!("build signed order: {e:#}")))?;
 
         let body = clob_order::PostOrderBody {
             order: signed.to_order_json(),
@@ -250,8 +274,8 @@ impl LiveGateway {
         Ok(ExecResult {
             fill: FillReport {
                 requested_qty: req.req_qty,
-                filled_qty: 0.0,
-                avg_price: 0.0,
+                filled_qty: Qty::ZERO,
+                avg_price: Price::ZERO,
                 status: FillStatus::None,
                 order_id: format!("LIVE_DRY_{salt}"),
                 latency_ms: 0,
@@ -264,6 +288,7 @@ impl LiveGateway {
     }
 }
 
+#[cfg(feature = "live")]
 fn exchange_address(chain_id: u64, neg_risk: bool) -> anyhow::Result<&'static str> {
     match (chain_id, neg_risk) {
         (137, false) => Ok("0x4bFb41d5B3570DeFd03C39a9A4D8dE6Bd8B8982E"),
@@ -295,7 +320,7 @@ impl SimGateway {
             .saturating_add(1);
 
         let latency_spike_ms_applied = if self.latency_spike_ms > 0
-            && (self.latency_spike_every == 0 || seq % self.latency_spike_every == 0)
+            && (self.latency_spike_every == 0 || seq.is_multiple_of(self.latency_spike_every))
         {
             self.latency_spike_ms
         } else {
@@ -305,10 +330,10 @@ impl SimGateway {
         let book_dropped = should_drop_book(self.drop_book_pct, seq, req.token_id);
         let top = if book_dropped {
             TopOfBook {
-                best_ask: 0.0,
-                best_ask_size_best: 0.0,
-                best_bid: 0.0,
-                best_bid_size_best: 0.0,
+                best_ask: Price::ZERO,
+                best_ask_size_best: Qty::ZERO,
+                best_bid: Price::ZERO,
+                best_bid_size_best: Qty::ZERO,
             }
         } else {
             req.top
@@ -329,7 +354,7 @@ impl SimGateway {
 
         let (filled_qty, status, avg_price) =
             if self.force_chase_fail && req.kind == ExecKind::Chase {
-                (0.0, FillStatus::None, 0.0)
+                (Qty::ZERO, FillStatus::None, Price::ZERO)
             } else {
                 sim_fill(
                     req.side,
@@ -381,56 +406,43 @@ fn sim_fill_share(bucket: Bucket, liquid: f64, thin: f64) -> f64 {
 #[allow(clippy::too_many_arguments)]
 fn sim_fill(
     side: Side,
-    limit_price: f64,
-    req_qty: f64,
-    best_ask: f64,
-    best_ask_size_best: f64,
-    best_bid: f64,
-    best_bid_size_best: f64,
+    limit_price: Price,
+    req_qty: Qty,
+    best_ask: Price,
+    best_ask_size_best: Qty,
+    best_bid: Price,
+    best_bid_size_best: Qty,
     sim_fill_share_used: f64,
-) -> (f64, FillStatus, f64) {
-    if !limit_price.is_finite() || !req_qty.is_finite() || req_qty <= 0.0 {
-        return (0.0, FillStatus::None, 0.0);
+) -> (Qty, FillStatus, Price) {
+    let none = (Qty::ZERO, FillStatus::None, Price::ZERO);
+    if !req_qty.is_positive() {
+        return none;
     }
 
-    match side {
+    let book_size = match side {
         Side::Buy => {
-            if !best_ask.is_finite() || best_ask <= 0.0 {
-                return (0.0, FillStatus::None, 0.0);
-            }
-            if limit_price + 1e-12 < best_ask {
-                return (0.0, FillStatus::None, 0.0);
+            if !best_ask.is_positive() || limit_price < best_ask {
+                return none;
             }
-            let cap = (best_ask_size_best.max(0.0)) * sim_fill_share_used;
-            let filled = req_qty.min(cap).max(0.0);
-            let status = if filled <= 0.0 {
-                FillStatus::None
-            } else if filled + 1e-9 >= req_qty {
-                FillStatus::Full
-            } else {
-                FillStatus::Partial
-            };
-            (filled, status, limit_price)
+            best_ask_size_best
         }
         Side::Sell => {
-            if !best_bid.is_finite() || best_bid <= 0.0 {
-                return (0.0, FillStatus::None, 0.0);
-            }
-            if limit_price - 1e-12 > best_bid {
-                return (0.0, FillStatus::None, 0.0);
+            if !best_bid.is_positive() || limit_price > best_bid {
+                return none;
             }
-            let cap = (best_bid_size_best.max(0.0)) * sim_fill_share_used;
-            let filled = req_qty.min(cap).max(0.0);
-            let status = if filled <= 0.0 {
-                FillStatus::None
-            } else if filled + 1e-9 >= req_qty {
-                FillStatus::Full
-            } else {
-                FillStatus::Partial
-            };
-            (filled, status, limit_price)
+            best_bid_size_best
         }
-    }
+    };
+    let cap = book_size.max(Qty::ZERO).scale_down(sim_fill_share_used);
+    let filled = req_qty.min(cap).max(Qty::ZERO);
+    let status = if !filled.is_positive() {
+        FillStatus::None
+    } else if filled >= req_qty {
+        FillStatus::Full
+    } else {
+        FillStatus::Partial
+    };
+    (filled, status, limit_price)
 }
 
 fn should_drop_book(drop_book_pct: f64, seq: u64, token_id: &str) -> bool {
@@ -463,6 +475,7 @@ fn env_u64(name: &str) -> Option<u64> {
     raw.trim().parse::<u64>().ok()
 }
 
+#[cfg(feature = "live")]
 fn env_flag(name: &str) -> bool {
     std::env::var(name)
         .ok()
@@ -490,49 +503,97 @@ mod tests {
     #[test]
     fn sim_fill_buy_is_deterministic() {
         // limit < best_ask => none
-        let (filled, status, avg_px) =
-            sim_fill(Side::Buy, 0.49, 10.0, 0.50, 100.0, 0.49, 100.0, 0.10);
-        assert_eq!(filled, 0.0);
+        let (filled, status, avg_px) = sim_fill(
+            Side::Buy,
+            Price::from_f64(0.49).unwrap(),
+            Qty::from_f64(10.0).unwrap(),
+            Price::from_f64(0.50).unwrap(),
+            Qty::from_f64(100.0).unwrap(),
+            Price::from_f64(0.49).unwrap(),
+            Qty::from_f64(100.0).unwrap(),
+            0.10,
+        );
+        assert_eq!(filled, Qty::ZERO);
         assert_eq!(status, FillStatus::None);
-        assert_eq!(avg_px, 0.0);
+        assert_eq!(avg_px, Price::ZERO);
 
         // limit >= best_ask, cap >= req => full
-        let (filled, status, avg_px) =
-            sim_fill(Side::Buy, 0.50, 10.0, 0.50, 200.0, 0.49, 200.0, 0.10);
-        assert_eq!(filled, 10.0);
+        let (filled, status, avg_px) = sim_fill(
+            Side::Buy,
+            Price::from_f64(0.50).unwrap(),
+            Qty::from_f64(10.0).unwrap(),
+            Price::from_f64(0.50).unwrap(),
+            Qty::from_f64(200.0).unwrap(),
+            Price::from_f64(0.49).unwrap(),
+            Qty::from_f64(200.0).unwrap(),
+            0.10,
+        );
+        assert_eq!(filled, Qty::from_f64(10.0).unwrap());
         assert_eq!(status, FillStatus::Full);
-        assert_eq!(avg_px, 0.50);
+        assert_eq!(avg_px, Price::from_f64(0.50).unwrap());
 
         // cap < req => partial
-        let (filled, status, avg_px) =
-            sim_fill(Side::Buy, 0.50, 10.0, 0.50, 50.0, 0.49, 50.0, 0.10);
-        assert_eq!(filled, 5.0);
+        let (filled, status, avg_px) = sim_fill(
+            Side::Buy,
+            Price::from_f64(0.50).unwrap(),
+            Qty::from_f64(10.0).unwrap(),
+            Price::from_f64(0.50).unwrap(),
+            Qty::from_f64(50.0).unwrap(),
+            Price::from_f64(0.49).unwrap(),
+            Qty::from_f64(50.0).unwrap(),
+            0.10,
+        );
+        assert_eq!(filled, Qty::from_f64(5.0).unwrap());
         assert_eq!(status, FillStatus::Partial);
-        assert_eq!(avg_px, 0.50);
+        assert_eq!(avg_px, Price::from_f64(0.50).unwrap());
     }
 
     #[test]
     fn sim_fill_sell_is_deterministic() {
         // limit > best_bid => none
-        let (filled, status, avg_px) =
-            sim_fill(Side::Sell, 0.51, 10.0, 0.52, 100.0, 0.50, 100.0, 0.10);
-        assert_eq!(filled, 0.0);
+        let (filled, status, avg_px) = sim_fill(
+            Side::Sell,
+            Price::from_f64(0.51).unwrap(),
+            Qty::from_f64(10.0).unwrap(),
+            Price::from_f64(0.52).unwrap(),
+            Qty::from_f64(100.0).unwrap(),
+            Price::from_f64(0.50).unwrap(),
+            Qty::from_f64(100.0).unwrap(),
+            0.10,
+        );
+        assert_eq!(filled, Qty::ZERO);
         assert_eq!(status, FillStatus::None);
-        assert_eq!(avg_px, 0.0);
+        assert_eq!(avg_px, Price::ZERO);
 
         // limit <= best_bid, cap >= req => full
-        let (filled, status, avg_px) =
-            sim_fill(Side::Sell, 0.50, 10.0, 0.52, 200.0, 0.50, 200.0, 0.10);
-        assert_eq!(filled, 10.0);
+        let (filled, status, avg_px) = sim_fill(
+            Side::Sell,
+            Price::from_f64(0.50).unwrap(),
+            Qty::from_f64(10.0).unwrap(),
+            Price::from_f64(0.52).unwrap(),
+            Qty::from_f64(200.0).unwrap(),
+            Price::from_f64(0.50).unwrap(),
+            Qty::from_f64(200.0).unwrap(),
+            0.10,
+        );
+        assert_eq!(filled, Qty::from_f64(10.0).unwrap());
         assert_eq!(status, FillStatus::Full);
-        assert_eq!(avg_px, 0.50);
+        assert_eq!(avg_px, Price::from_f64(0.50).unwrap());
 
         // cap < req => partial
-        let (filled, status, avg_px) =
-            sim_fill(Side::Sell, 0.50, 10.0, 0.52, 50.0, 0.50, 50.0, 0.10);
-        assert_eq!(filled, 5.0);
+        let (filled, status, avg_px) = sim_fill(
+            Side::Sell,
+            Price::from_f64(0.50).unwrap(),
+            Qty::from_f64(10.0).unwrap(),
+            Price::from_f64(0.52).unwrap(),
+            Qty::from_f64(50.0).unwrap(),
+            Price::from_f64(0.50).unwrap(),
+            Qty::from_f64(50.0).unwrap(),
+            0.10,
+        );
+        assert_eq!(filled, Qty::from_f64(5.0).unwrap());
         assert_eq!(status, FillStatus::Partial);
-        assert_eq!(avg_px, 0.50);
+        assert_eq!(avg_px, Price::from_f64(0.50).unwrap());
     }
 
     #[tokio::test]
@@ -553,22 +614,22 @@ mod tests {
             .place_ioc(PlaceIocRequest {
                 kind: ExecKind::FireLeg1,
                 bucket: Bucket::Liquid,
-                token_id: "T",
+                token_id: &"T".into(),
                 side: Side::Buy,
-                limit_price: 0.50,
-                req_qty: 10.0,
+                limit_price: Price::from_f64(0.50).unwrap(),
+                req_qty: Qty::from_f64(10.0).unwrap(),
                 top: TopOfBook {
-                    best_ask: 0.50,
-                    best_ask_size_best: 100.0,
-                    best_bid: 0.49,
-                    best_bid_size_best: 100.0,
+                    best_ask: Price::from_f64(0.50).unwrap(),
+                    best_ask_size_best: Qty::from_f64(100.0).unwrap(),
+                    best_bid: Price::from_f64(0.49).unwrap(),
+                    best_bid_size_best: Qty::from_f64(100.0).unwrap(),
                 },
             })
             .await?;
 
         assert!(res.book_dropped);
         assert_eq!(res.fill.status, FillStatus::None);
-        assert_eq!(res.fill.filled_qty, 0.0);
+        assert_eq!(res.fill.filled_qty, Qty::ZERO);
         Ok(())
     }
 
@@ -590,15 +651,15 @@ mod tests {
             .place_ioc(PlaceIocRequest {
                 kind: ExecKind::FireLeg1,
                 bucket: Bucket::Liquid,
-                token_id: "T",
+                token_id: &"T".into(),
                 side: Side::Buy,
-                limit_price: 0.50,
-                req_qty: 10.0,
+                limit_price: Price::from_f64(0.50).unwrap(),
+                req_qty: Qty::from_f64(10.0).unwrap(),
                 top: TopOfBook {
-                    best_ask: 0.50,
-                    best_ask_size_best: 100.0,
-                    best_bid: 0.49,
-                    best_bid_size_best: 100.0,
+                    best_ask: Price::from_f64(0.50).unwrap(),
+                    best_ask_size_best: Qty::from_f64(100.0).unwrap(),
+                    best_bid: Price::from_f64(0.49).unwrap(),
+                    best_bid_size_best: Qty::from_f64(100.0).unwrap(),
                 },
             })
             .await?;
@@ -0,0 +1,155 @@
+use std::fmt::Write as _;
+use std::path::{Path, PathBuf};
+
+use anyhow::Context as _;
+
+use crate::recorder::{csv_input_paths, TICKS_HEADER};
+use crate::schema::{
+    column_kind, ColumnKind, FILE_SHADOW_LOG, FILE_SNAPSHOTS, FILE_TICKS, FILE_TRADES,
+    FILE_TRADE_LOG, SHADOW_HEADER, SNAPSHOTS_HEADER, TRADES_HEADER, TRADE_LOG_HEADER,
+};
+
+/// Name of the generated script, written into the run dir next to the CSVs it loads.
+pub const DUCKDB_SQL_FILE: &str = "export_duckdb.sql";
+
+/// (table, csv file, frozen header) for every CSV the export loads.
+const TABLES: [(&str, &str, &[&str]); 5] = [
+    ("ticks", FILE_TICKS, &TICKS_HEADER),
+    ("trades", FILE_TRADES, &TRADES_HEADER),
+    ("snapshots", FILE_SNAPSHOTS, &SNAPSHOTS_HEADER),
+    ("shadow", FILE_SHADOW_LOG, &SHADOW_HEADER),
+    ("trade_log", FILE_TRADE_LOG, &TRADE_LOG_HEADER),
+];
+
+/// DuckDB column type for a frozen CSV column.
+fn duck_type(col: &str) -> &'static str {
+    match column_kind(col) {
+        ColumnKind::Text => "VARCHAR",
+        ColumnKind::Integer => "BIGINT",
+        ColumnKind::Float => "DOUBLE",
+    }
+}
+
+fn sql_str(s: &str) -> String {
+    format!("'{}'", s.replace('\'', "''"))
+}
+
+/// Builds the DuckDB script for the CSVs present in `run_dir`: one typed table per CSV plus
+/// `signals`, `shadow_exec` (shadow rows with their trade_log summary) and `trade_log_shadow`
+/// (every OMS action with its shadow outcome) views.
+pub fn render_duckdb_sql(run_dir: &Path) -> String {
+    let mut sql = String::from("-- Generated by `razor export duckdb`.\n");
+    let mut present = Vec::new();
+    for (table, file, header) in TABLES {
+        // Flat file plus any hourly partitions (`run.partition_hourly`).
+        let paths = csv_input_paths(run_dir, file);
+        if paths.is_empty() {
+            continue;
+        }
+        present.push(table);
+        let files = paths
+            .iter()
+            .map(|p| sql_str(&p.display().to_string()))
+            .collect::<Vec<_>>()
+            .join(", ");
+        let columns = header
+            .iter()
+            .map(|c| format!("{}: {}", sql_str(c), sql_str(duck_type(c))))
+            .collect::<Vec<_>>()
+            .join(", ");
+        let _ = writeln!(
+            sql,
+            "CREATE OR REPLACE TABLE {table} AS SELECT * FROM read_csv([{files}], header = true, columns = {{{columns}}});"
+        );
+    }
+
+    if present.contains(&"shadow") {
+        sql.push_str(
+            "CREATE OR REPLACE VIEW signals AS SELECT signal_id, signal_ts_unix_ms, market_id, \
+             strategy, bucket, legs_n, q_req, q_set, total_pnl, set_ratio, notes FROM shadow;\n",
+        );
+        if present.contains(&"trade_log") {
+            sql.push_str(
+                "CREATE OR REPLACE VIEW shadow_exec AS SELECT s.*, t.actions, t.fill_qty_sum, \
+                 t.action_seq FROM shadow s LEFT JOIN (SELECT signal_id, count(*) AS actions, \
+                 sum(fill_qty) AS fill_qty_sum, string_agg(action, '>' ORDER BY ts_ms) AS \
+                 action_seq FROM trade_log GROUP BY signal_id) t USING (signal_id);\n",
+            );
+            sql.push_str(
+                "CREATE OR REPLACE VIEW trade_log_shadow AS SELECT t.*, s.q_set, s.total_pnl, \
+                 s.set_ratio, s.notes AS shadow_notes FROM trade_log t LEFT JOIN shadow s \
+                 USING (signal_id);\n",
+            );
+        }
+    }
+    sql
+}
+
+/// `razor export duckdb`: writes `export_duckdb.sql` into the run dir and, unless `sql_only`,
+/// loads it into `out` with the `duckdb` CLI (no database engine is linked into razor).
+pub fn export_duckdb(run_dir: &Path, out: Option<PathBuf>, sql_only: bool) -> anyhow::Result<()> {
+    anyhow::ensure!(run_dir.is_dir(), "run dir not found: {}", run_dir.display());
+    // Absolute paths so the script works from any cwd.
+    let run_dir = run_dir
+        .canonicalize()
+        .with_context(|| format!("resolve {}", run_dir.display()))?;
+    let sql_path = run_dir.join(DUCKDB_SQL_FILE);
+    std::fs::write(&sql_path, render_duckdb_sql(&run_dir))
+        .with_context(|| format!("write {}", sql_path.display()))?;
+    println!("sql={}", sql_path.display());
+    if sql_only {
+        return Ok(());
+    }
+
+    let out = out.unwrap_or_else(|| run_dir.join("run.duckdb"));
+    let status = std::process::Command::new("duckdb")
+        .arg(&out)
+        .arg("-c")
+        .arg(format!(".read {}", sql_path.display()))
+        .status();
+    match status {
+        Ok(s) if s.success() => {
+            println!("duckdb={}", out.display());
+            Ok(())
+        }
+        Ok(s) => anyhow::bail!("duckdb exited with {s}"),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => anyhow::bail!(
+            "duckdb CLI not found on PATH; load the script manually: duckdb {} -c \".read {}\"",
+            out.display(),
+            sql_path.display()
+        ),
+        Err(e) => Err(e).context("run duckdb"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::now_ms;
+
+    #[test]
+    fn sql_types_columns_and_only_joins_present_tables() {
+        assert_eq!(duck_type("leg2_token_id"), "VARCHAR");
+        assert_eq!(duck_type("signal_ts_unix_ms"), "BIGINT");
+        assert_eq!(duck_type("ts_recv_us"), "BIGINT");
+        assert_eq!(duck_type("total_pnl"), "DOUBLE");
+
+        let dir = std::env::temp_dir().join(format!("razor_export_{}", now_ms()));
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(dir.join(FILE_SHADOW_LOG), SHADOW_HEADER.join(",")).unwrap();
+        let sql = render_duckdb_sql(&dir);
+        assert!(sql.contains("CREATE OR REPLACE TABLE shadow"));
+        assert!(sql.contains("'signal_id': 'BIGINT'"));
+        assert!(sql.contains("VIEW signals"));
+        assert!(!sql.contains("trade_log"));
+
+        let hour = dir.join("2026-01-02").join("03");
+        std::fs::create_dir_all(&hour).unwrap();
+        std::fs::write(hour.join(FILE_TRADES), TRADES_HEADER.join(",")).unwrap();
+        assert!(render_duckdb_sql(&dir).contains("2026-01-02/03/trades.csv'], header"));
+
+        std::fs::write(dir.join(FILE_TRADE_LOG), TRADE_LOG_HEADER.join(",")).unwrap();
+        assert!(render_duckdb_sql(&dir).contains("VIEW shadow_exec"));
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
@@ -1,22 +1,37 @@
 use std::collections::{HashMap, HashSet};
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use std::time::Duration;
 
 use anyhow::Context as _;
 use futures_util::{SinkExt as _, StreamExt as _};
 use serde::Deserialize;
-use std::sync::Arc;
-use tokio::sync::{mpsc, watch};
+use std::sync::{Arc, Mutex};
+use tokio::sync::{mpsc, watch, Notify};
 use tokio_tungstenite::tungstenite::Message;
 use tracing::{error, info, warn};
 
-use crate::config::Config;
+use crate::api_usage::{self, ApiEndpoint};
+use crate::backpressure::{self, SendOutcome};
+use crate::buckets::TradeArrivals;
+use crate::config::{Config, ConflationConfig};
+use crate::disk_guard::DiskLevel;
+use crate::errors::{code_of, BoxError, ErrorContext as _, RazorError};
 use crate::health::{HealthCounters, HealthLine};
-use crate::json_util::parse_f64;
-use crate::recorder::{CsvAppender, JsonlAppender, TICKS_HEADER, TRADES_HEADER};
-use crate::types::{now_ms, now_us, LegSnapshot, MarketDef, MarketSnapshot, TradeTick};
+use crate::log_throttle::warn_throttled;
+use crate::order_book::OrderBook;
+use crate::recorder::{CsvAppender, JsonlAppender, RotationPolicy, TICKS_HEADER, TRADES_HEADER};
+use crate::rows::{TickRow, TradeRow};
+use crate::types::{
+    normalize_ts_ms, now_ms, now_us, trade_dedup_key, LegSnapshot, MarketDef, MarketId,
+    MarketSnapshot, Price, Qty, Side, TokenId, TradeTick,
+};
+use crate::ws_msg::{
+    self, ask_depth3_usdc, best_level, BookEvent, LastTradeEvent, PriceChangeEvent, PriceSide,
+    WsEvent,
+};
 
 const RAW_WS_ROTATE_BYTES: u64 = 512 * 1024 * 1024;
+const DEGRADED_TICK_LOG_EVERY_MS: u64 = 10_000;
 
 #[derive(Debug, Deserialize)]
 struct GammaMarket {
@@ -34,7 +49,7 @@ pub async fn fetch_markets(cfg: &Config) -> anyhow::Result<Vec<MarketDef>> {
         ))
         .timeout(Duration::from_millis(cfg.polymarket.http_timeout_ms))
         .build()
-        .context("build http client")?;
+        .http_context("build http client")?;
 
     let mut out = Vec::with_capacity(cfg.run.market_ids.len());
     for id in &cfg.run.market_ids {
@@ -42,19 +57,18 @@ pub async fn fetch_markets(cfg: &Config) -> anyhow::Result<Vec<MarketDef>> {
             "{}/markets",
             cfg.polymarket.gamma_base.trim_end_matches('/')
         );
-        let resp = client
-            .get(url)
-            .query(&[("id", id)])
-            .send()
-            .await
-            .with_context(|| format!("gamma markets?id={id}"))?;
-        let markets: Vec<GammaMarket> = resp.json().await.context("decode gamma market")?;
+        let resp = api_usage::track(
+            ApiEndpoint::Gamma,
+            client.get(url).query(&[("id", id)]).send().await,
+        )
+        .http_context(format!("gamma markets?id={id}"))?;
+        let markets: Vec<GammaMarket> = resp.json().await.decode_context("gamma market")?;
         let Some(m) = markets.into_iter().next() else {
             return Err(anyhow::anyhow!("gamma market id {id} not found"));
         };
 
-        let token_ids: Vec<String> = serde_json::from_str(&m.clob_token_ids)
-            .with_context(|| format!("parse clobTokenIds for gamma market {id}"))?;
+        let token_ids: Vec<TokenId> = serde_json::from_str(&m.clob_token_ids)
+            .decode_context(format!("clobTokenIds for gamma market {id}"))?;
 
         if token_ids.len() != 2 && token_ids.len() != 3 {
             warn!(
@@ -66,7 +80,7 @@ pub async fn fetch_markets(cfg: &Config) -> anyhow::Result<Vec<MarketDef>> {
         }
 
         out.push(MarketDef {
-            market_id: m.condition_id,
+            market_id: m.condition_id.into(),
             token_ids,
         });
     }
@@ -80,47 +94,202 @@ pub async fn fetch_markets(cfg: &Config) -> anyhow::Result<Vec<MarketDef>> {
 }
 
 struct LegState {
-    token_id: String,
+    token_id: TokenId,
     best_ask: f64,
     best_ask_size_best: f64,
     best_bid: f64,
     best_bid_size_best: f64,
     ask_depth3_usdc: f64,
+    /// L2 book behind the snapshot's ask ladder.
+    book: OrderBook,
     ts_recv_us: u64,
     last_tick_log_ms: u64,
     ready: bool,
 }
 
 struct MarketState {
-    market_id: String,
+    market_id: MarketId,
     legs: Vec<LegState>,
+    publish: PublishState,
 }
 
-pub async fn run_market_ws(
-    cfg: Config,
-    markets: Vec<MarketDef>,
-    snap_tx: watch::Sender<Option<MarketSnapshot>>,
-    ticks_path: PathBuf,
-    raw_ws_path: PathBuf,
-    health: Arc<HealthCounters>,
-    shutdown: watch::Receiver<bool>,
-) -> anyhow::Result<()> {
-    let mut ticks = CsvAppender::open(ticks_path, &TICKS_HEADER).context("open ticks.csv")?;
-    let mut raw = JsonlAppender::open_with_rotation(
-        raw_ws_path,
-        Some(RAW_WS_ROTATE_BYTES),
-        Some(cfg.run.raw_ws_rotate_keep),
-    )
-    .context("open raw_ws.jsonl")?;
+/// What brain last saw of a market, for `[conflation]`.
+#[derive(Debug, Default)]
+struct PublishState {
+    /// `None` until the first snapshot goes out.
+    last_publish_ms: Option<u64>,
+    /// `(best_bid, best_ask)` per leg in the last published snapshot.
+    last_quotes: Vec<(f64, f64)>,
+    /// An update was held back by `min_interval_ms` and still has to be flushed.
+    pending: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Conflation {
+    Publish,
+    /// Hold until `min_interval_ms` has elapsed (flushed by the WS loop).
+    Defer,
+    /// Below `min_change_bps`: brain keeps the last published snapshot.
+    Skip,
+}
 
-    let mut token_to_market: HashMap<String, (String, usize)> = HashMap::new();
-    let mut market_states: HashMap<String, MarketState> = HashMap::new();
-    let mut subscribe_tokens: Vec<String> = Vec::new();
+fn conflation_decision(
+    cfg: &ConflationConfig,
+    st: &PublishState,
+    legs: &[LegState],
+    now_ms: u64,
+) -> Conflation {
+    let Some(last_ms) = st.last_publish_ms else {
+        return Conflation::Publish;
+    };
+    if cfg.min_change_bps > 0 && !quotes_moved(&st.last_quotes, legs, cfg.min_change_bps) {
+        return Conflation::Skip;
+    }
+    if cfg.min_interval_ms > 0 && now_ms.saturating_sub(last_ms) < cfg.min_interval_ms {
+        return Conflation::Defer;
+    }
+    Conflation::Publish
+}
+
+/// True when any leg's best bid or ask moved by `min_bps` or more, or gained/lost a side.
+fn quotes_moved(last: &[(f64, f64)], legs: &[LegState], min_bps: u32) -> bool {
+    if last.len() != legs.len() {
+        return true;
+    }
+    let moved = |old: f64, new: f64| {
+        if old <= 0.0 || new <= 0.0 {
+            return (old > 0.0) != (new > 0.0);
+        }
+        (new - old).abs() / old * 10_000.0 >= f64::from(min_bps)
+    };
+    last.iter()
+        .zip(legs)
+        .any(|(&(bid, ask), l)| moved(bid, l.best_bid) || moved(ask, l.best_ask))
+}
+
+/// Where published snapshots go: brain's watch channel, or every one of them in order with its
+/// publish time (raw_ws replay).
+enum SnapshotSink<'a> {
+    Watch(&'a watch::Sender<Option<MarketSnapshot>>),
+    #[allow(dead_code)]
+    Collect(&'a Mutex<Vec<(u64, MarketSnapshot)>>),
+}
+
+/// Hands market snapshots to brain, conflated per `[conflation]`.
+struct SnapshotPublisher<'a> {
+    sink: SnapshotSink<'a>,
+    cfg: ConflationConfig,
+}
+
+impl SnapshotPublisher<'_> {
+    /// Publishes `state` now, later (deferred) or not at all, once every leg is ready.
+    fn offer(&self, state: &mut MarketState, health: &HealthCounters, now_ms: u64) {
+        if !state.legs.iter().all(|l| l.ready) {
+            return;
+        }
+        match conflation_decision(&self.cfg, &state.publish, &state.legs, now_ms) {
+            Conflation::Publish => self.publish(state, health, now_ms),
+            Conflation::Defer => {
+                state.publish.pending = true;
+                health.inc_snapshots_conflated(1);
+            }
+            Conflation::Skip => health.inc_snapshots_conflated(1),
+        }
+    }
+
+    /// Publishes every deferred market whose interval has elapsed.
+    fn flush_due(
+        &self,
+        states: &mut HashMap<MarketId, MarketState>,
+        health: &HealthCounters,
+        now_ms: u64,
+    ) {
+        for state in states.values_mut() {
+            let due = state.publish.pending
+                && state
+                    .publish
+                    .last_publish_ms
+                    .is_none_or(|t| now_ms.saturating_sub(t) >= self.cfg.min_interval_ms);
+            if due {
+                self.publish(state, health, now_ms);
+            }
+        }
+    }
+
+    /// How often the WS loop checks for deferred snapshots.
+    fn flush_period(&self) -> Duration {
+        match self.cfg.min_interval_ms {
+            0 => Duration::from_secs(3_600),
+            ms => Duration::from_millis((ms / 2).max(1)),
+        }
+    }
+
+    fn publish(&self, state: &mut MarketState, health: &HealthCounters, now_ms: u64) {
+        let snap = MarketSnapshot {
+            market_id: state.market_id.clone(),
+            legs: state
+                .legs
+                .iter()
+                .map(|l| LegSnapshot {
+                    token_id: l.token_id.clone(),
+                    best_ask: Price::from_f64(l.best_ask).unwrap_or(Price::MISSING_ASK),
+                    best_bid: Price::from_f64(l.best_bid).unwrap_or(Price::MISSING_BID),
+                    best_ask_size_best: Qty::from_f64(l.best_ask_size_best).unwrap_or(Qty::ZERO),
+                    best_bid_size_best: Qty::from_f64(l.best_bid_size_best).unwrap_or(Qty::ZERO),
+                    ask_depth3_usdc: l.ask_depth3_usdc,
+                    asks: l.book.ask_ladder(),
+                    ts_recv_us: l.ts_recv_us,
+                })
+                .collect(),
+        };
+        state.publish = PublishState {
+            last_publish_ms: Some(now_ms),
+            last_quotes: state
+                .legs
+                .iter()
+                .map(|l| (l.best_bid, l.best_ask))
+                .collect(),
+            pending: false,
+        };
+        health.inc_snapshots_published(1);
+        match &self.sink {
+            SnapshotSink::Watch(tx) => {
+                let _ = tx.send(Some(snap));
+            }
+            SnapshotSink::Collect(out) => out
+                .lock()
+                .unwrap_or_else(|e| e.into_inner())
+                .push((now_ms, snap)),
+        }
+    }
+}
+
+/// Opens a high-volume run CSV flat or hour-partitioned per `run.partition_hourly`.
+pub(crate) fn open_run_csv(
+    cfg: &Config,
+    path: &Path,
+    header: &[&str],
+) -> anyhow::Result<CsvAppender> {
+    if cfg.run.partition_hourly {
+        CsvAppender::open_partitioned(path, header)
+    } else {
+        CsvAppender::open(path, header)
+    }
+}
+
+/// Token -> (market, leg index) routing and the empty per-market book state of `markets`.
+fn market_states(
+    markets: Vec<MarketDef>,
+) -> (
+    HashMap<TokenId, (MarketId, usize)>,
+    HashMap<MarketId, MarketState>,
+) {
+    let mut token_to_market: HashMap<TokenId, (MarketId, usize)> = HashMap::new();
+    let mut market_states: HashMap<MarketId, MarketState> = HashMap::new();
 
     for m in markets {
         for (idx, token) in m.token_ids.iter().enumerate() {
             token_to_market.insert(token.clone(), (m.market_id.clone(), idx));
-            subscribe_tokens.push(token.clone());
         }
 
         let legs = m
@@ -133,6 +302,7 @@ pub async fn run_market_ws(
                 best_bid: 0.0,
                 best_bid_size_best: 0.0,
                 ask_depth3_usdc: 0.0,
+                book: OrderBook::default(),
                 ts_recv_us: 0,
                 last_tick_log_ms: 0,
                 ready: false,
@@ -144,14 +314,62 @@ pub async fn run_market_ws(
             MarketState {
                 market_id: m.market_id,
                 legs,
+                publish: PublishState::default(),
             },
         );
     }
+    (token_to_market, market_states)
+}
 
+#[allow(clippy::too_many_arguments)]
+pub async fn run_market_ws(
+    cfg: Config,
+    markets: Vec<MarketDef>,
+    snap_tx: watch::Sender<Option<MarketSnapshot>>,
+    ticks_path: PathBuf,
+    raw_ws_path: PathBuf,
+    health: Arc<HealthCounters>,
+    health_tx: mpsc::Sender<HealthLine>,
+    reconnect: Arc<Notify>,
+    ws_trades: Option<mpsc::Sender<IncomingTrade>>,
+    shutdown: watch::Receiver<bool>,
+) -> anyhow::Result<()> {
+    let mut ticks = open_run_csv(&cfg, &ticks_path, &TICKS_HEADER).io_context("open ticks.csv")?;
+    let mut raw = JsonlAppender::open_with_policy(
+        raw_ws_path,
+        RotationPolicy {
+            max_bytes: Some(RAW_WS_ROTATE_BYTES),
+            max_age_ms: Some(cfg.run.raw_ws_rotate_interval_ms),
+            keep_files: Some(cfg.run.raw_ws_rotate_keep),
+            max_total_bytes: Some(cfg.run.raw_ws_max_total_mb.saturating_mul(1024 * 1024)),
+        },
+    )
+    .io_context("open raw_ws.jsonl")?;
+    let error_tx = health_tx.clone();
+    raw.set_on_rotate(move |r| {
+        let name = |p: &Path| {
+            p.file_name()
+                .map(|s| s.to_string_lossy().to_string())
+                .unwrap_or_default()
+        };
+        let _ = health_tx.try_send(HealthLine::RawWsRotated {
+            ts_ms: r.ts_ms,
+            reason: r.reason.to_string(),
+            segment: name(&r.segment),
+            segment_bytes: r.segment_bytes,
+            removed: r.removed.iter().map(|p| name(p)).collect(),
+        });
+    });
+
+    let (token_to_market, mut market_states) = market_states(markets);
+    let mut subscribe_tokens: Vec<TokenId> = token_to_market.keys().cloned().collect();
     subscribe_tokens.sort();
-    subscribe_tokens.dedup();
 
     let ws_url = format!("{}/ws/market", cfg.polymarket.ws_base.trim_end_matches('/'));
+    let publisher = SnapshotPublisher {
+        sink: SnapshotSink::Watch(&snap_tx),
+        cfg: cfg.conflation.clone(),
+    };
 
     let mut backoff = Duration::from_secs(1);
     loop {
@@ -165,8 +383,10 @@ pub async fn run_market_ws(
             &mut market_states,
             &mut ticks,
             &mut raw,
-            &snap_tx,
+            &publisher,
+            ws_trades.as_ref(),
             &health,
+            &reconnect,
             Duration::from_millis(cfg.polymarket.ws_connect_timeout_ms),
             Duration::from_millis(cfg.polymarket.ws_write_timeout_ms),
             shutdown.clone(),
@@ -177,28 +397,39 @@ pub async fn run_market_ws(
                 backoff = Duration::from_secs(1);
             }
             Err(e) => {
-                error!(error = %e, "ws error; reconnecting");
+                let code = code_of(&e);
+                error!(%code, error = %e, "ws error; reconnecting");
+                health.inc_ws_reconnects(1);
+                let _ = error_tx.try_send(HealthLine::TaskError {
+                    ts_ms: now_ms(),
+                    task: "ws".to_string(),
+                    code,
+                    error: format!("{e:#}"),
+                    fatal: false,
+                });
                 tokio::time::sleep(backoff).await;
                 backoff = (backoff * 2).min(Duration::from_secs(60));
             }
         }
     }
 
-    ticks.flush_and_sync().context("flush ticks.csv")?;
-    raw.flush_and_sync().context("flush raw_ws.jsonl")?;
+    ticks.flush_and_sync().io_context("flush ticks.csv")?;
+    raw.flush_and_sync().io_context("flush raw_ws.jsonl")?;
     Ok(())
 }
 
 #[allow(clippy::too_many_arguments)]
 async fn ws_run_once(
     ws_url: &str,
-    subscribe_tokens: &[String],
-    token_to_market: &HashMap<String, (String, usize)>,
-    market_states: &mut HashMap<String, MarketState>,
+    subscribe_tokens: &[TokenId],
+    token_to_market: &HashMap<TokenId, (MarketId, usize)>,
+    market_states: &mut HashMap<MarketId, MarketState>,
     ticks: &mut CsvAppender,
     raw: &mut JsonlAppender,
-    snap_tx: &watch::Sender<Option<MarketSnapshot>>,
+    publisher: &SnapshotPublisher<'_>,
+    ws_trades: Option<&mpsc::Sender<IncomingTrade>>,
     health: &HealthCounters,
+    reconnect: &Notify,
     ws_connect_timeout: Duration,
     ws_write_timeout: Duration,
     mut shutdown: watch::Receiver<bool>,
@@ -207,11 +438,15 @@ async fn ws_run_once(
     if *shutdown.borrow() {
         return Ok(());
     }
+    let connect_err = |source: BoxError| RazorError::WsConnect {
+        url: ws_url.to_string(),
+        source,
+    };
     let (ws, _) =
         tokio::time::timeout(ws_connect_timeout, tokio_tungstenite::connect_async(ws_url))
             .await
-            .context("ws connect timeout")?
-            .context("connect ws")?;
+            .map_err(|e| connect_err(e.into()))?
+            .map_err(|e| connect_err(e.into()))?;
 
     let (mut sink, mut stream) = ws.split();
 
@@ -230,6 +465,8 @@ async fn ws_run_once(
 
     let mut ping = tokio::time::interval(Duration::from_secs(10));
     ping.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+    let mut conflation_flush = tokio::time::interval(publisher.flush_period());
+    conflation_flush.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Skip);
 
     loop {
         tokio::select! {
@@ -238,6 +475,12 @@ async fn ws_run_once(
                     return Ok(());
                 }
             }
+            _ = reconnect.notified() => {
+                return Err(RazorError::FeedStale.into());
+            }
+            _ = conflation_flush.tick() => {
+                publisher.flush_due(market_states, health, now_ms());
+            }
             _ = ping.tick() => {
                 ws_send(&mut sink, Message::Text("PING".to_string().into()), ws_write_timeout)
                     .await
@@ -245,20 +488,20 @@ async fn ws_run_once(
             }
             msg = stream.next() => {
                 let Some(msg) = msg else {
-                    return Err(anyhow::anyhow!("ws stream ended"));
+                    return Err(RazorError::ws_stream("ws stream ended").into());
                 };
-                let msg = msg.context("ws read")?;
+                let msg = msg.ws_context("ws read")?;
                 match msg {
                     Message::Text(txt) => {
-                        handle_ws_text(&txt, token_to_market, market_states, ticks, raw, snap_tx, health).await?;
+                        handle_ws_text(&txt, now_us(), token_to_market, market_states, ticks, Some(&mut *raw), publisher, ws_trades, health)?;
                     }
                     Message::Binary(bin) => {
                         let txt = String::from_utf8_lossy(&bin);
-                        handle_ws_text(&txt, token_to_market, market_states, ticks, raw, snap_tx, health).await?;
+                        handle_ws_text(&txt, now_us(), token_to_market, market_states, ticks, Some(&mut *raw), publisher, ws_trades, health)?;
                     }
                     Message::Ping(_) | Message::Pong(_) => {}
                     Message::Close(frame) => {
-                        return Err(anyhow::anyhow!("ws close: {frame:?}"));
+                        return Err(RazorError::ws_stream(format!("ws close: {frame:?}")).into());
                     }
                     Message::Frame(_) => {}
                 }
@@ -267,95 +510,116 @@ async fn ws_run_once(
     }
 }
 
-async fn ws_send<S>(sink: &mut S, msg: Message, timeout: Duration) -> anyhow::Result<()>
+async fn ws_send<S>(sink: &mut S, msg: Message, timeout: Duration) -> Result<(), RazorError>
 where
     S: futures_util::Sink<Message> + Unpin,
     S::Error: std::error::Error + Send + Sync + 'static,
 {
     tokio::time::timeout(timeout, sink.send(msg))
         .await
-        .context("ws send timeout")?
-        .context("ws send error")?;
-    Ok(())
+        .ws_context("ws send timeout")?
+        .ws_context("ws send error")
 }
 
-async fn handle_ws_text(
+/// Decodes one frame received at `ts_recv_us` into ticks and snapshots, queueing its fills on
+/// `ws_trades` when set. `raw` is `None` when the frame is itself being replayed from
+/// `raw_ws.jsonl`.
+#[allow(clippy::too_many_arguments)]
+fn handle_ws_text(
     txt: &str,
-    token_to_market: &HashMap<String, (String, usize)>,
-    market_states: &mut HashMap<String, MarketState>,
+    ts_recv_us: u64,
+    token_to_market: &HashMap<TokenId, (MarketId, usize)>,
+    market_states: &mut HashMap<MarketId, MarketState>,
     ticks: &mut CsvAppender,
-    raw: &mut JsonlAppender,
-    snap_tx: &watch::Sender<Option<MarketSnapshot>>,
+    raw: Option<&mut JsonlAppender>,
+    publisher: &SnapshotPublisher<'_>,
+    ws_trades: Option<&mpsc::Sender<IncomingTrade>>,
     health: &HealthCounters,
 ) -> anyhow::Result<()> {
     if txt == "PONG" {
         return Ok(());
     }
 
-    if let Err(e) = raw.write_line(txt) {
-        warn!(error = %e, "raw ws write failed");
-    }
-
-    let v: serde_json::Value = match serde_json::from_str(txt) {
-        Ok(v) => v,
-        Err(e) => {
-            warn!(error = %e, "ws non-json message");
-            return Ok(());
+    if let Some(raw) = raw.filter(|_| health.disk_level() < DiskLevel::RawWsOff) {
+        if let Err(e) = raw.write_line(txt) {
+            warn_throttled!(error = %e, "raw ws write failed");
         }
-    };
+    }
 
-    match v {
-        serde_json::Value::Array(items) => {
-            for item in items {
-                if let serde_json::Value::Object(obj) = item {
-                    handle_ws_obj(obj, token_to_market, market_states, ticks, snap_tx, health)?;
-                }
+    let skipped = ws_msg::for_each_event(txt, |ev| match ev {
+        WsEvent::Book(book) => handle_ws_book(
+            book,
+            ts_recv_us,
+            token_to_market,
+            market_states,
+            ticks,
+            publisher,
+            health,
+        ),
+        WsEvent::PriceChange(pc) => handle_ws_price_change(
+            pc,
+            ts_recv_us,
+            token_to_market,
+            market_states,
+            ticks,
+            publisher,
+            health,
+        ),
+        WsEvent::LastTrade(trade) => {
+            if let Some(tx) = ws_trades {
+                queue_ws_trade(trade, token_to_market, tx, health);
             }
+            Ok(())
         }
-        serde_json::Value::Object(obj) => {
-            handle_ws_obj(obj, token_to_market, market_states, ticks, snap_tx, health)?;
-        }
-        _ => {}
+        WsEvent::Other => Ok(()),
+    })?;
+    for e in skipped {
+        warn_throttled!(error = %e, "ws message not decodable; skipped");
     }
 
     Ok(())
 }
 
-fn handle_ws_obj(
-    obj: serde_json::Map<String, serde_json::Value>,
-    token_to_market: &HashMap<String, (String, usize)>,
-    market_states: &mut HashMap<String, MarketState>,
-    ticks: &mut CsvAppender,
-    snap_tx: &watch::Sender<Option<MarketSnapshot>>,
+/// Hands a `last_trade_price` fill to the trades task. A full queue drops the fill here; the
+/// poller picks it up on its next round.
+fn queue_ws_trade(
+    trade: LastTradeEvent<'_>,
+    token_to_market: &HashMap<TokenId, (MarketId, usize)>,
+    tx: &mpsc::Sender<IncomingTrade>,
     health: &HealthCounters,
-) -> anyhow::Result<()> {
-    let Some(event_type) = obj.get("event_type").and_then(|v| v.as_str()) else {
-        return Ok(());
+) {
+    let Some((market_id, _)) = token_to_market.get(trade.asset_id.as_ref()) else {
+        return;
     };
-
-    match event_type {
-        "book" => handle_ws_book(obj, token_to_market, market_states, ticks, snap_tx, health)?,
-        "price_change" => {
-            handle_ws_price_change(obj, token_to_market, market_states, ticks, snap_tx, health)?
-        }
-        _ => {}
+    let (Some(price), Some(size), Some(timestamp)) = (trade.price, trade.size, trade.timestamp)
+    else {
+        health.inc_trades_invalid(1);
+        return;
+    };
+    let t = IncomingTrade {
+        market_id: market_id.clone(),
+        token_id: trade.asset_id.as_ref().into(),
+        price,
+        size,
+        timestamp,
+        transaction_hash: trade.transaction_hash.into_owned(),
+        side: Side::parse(&trade.side),
+    };
+    if let Err(mpsc::error::TrySendError::Full(_)) = tx.try_send(t) {
+        warn_throttled!(%market_id, "ws trade queue full; leaving the fill to the poller");
     }
-
-    Ok(())
 }
 
 fn handle_ws_book(
-    obj: serde_json::Map<String, serde_json::Value>,
-    token_to_market: &HashMap<String, (String, usize)>,
-    market_states: &mut HashMap<String, MarketState>,
+    book: BookEvent<'_>,
+    ts_recv_us: u64,
+    token_to_market: &HashMap<TokenId, (MarketId, usize)>,
+    market_states: &mut HashMap<MarketId, MarketState>,
     ticks: &mut CsvAppender,
-    snap_tx: &watch::Sender<Option<MarketSnapshot>>,
+    publisher: &SnapshotPublisher<'_>,
     health: &HealthCounters,
 ) -> anyhow::Result<()> {
-    let Some(token_id) = obj.get("asset_id").and_then(|v| v.as_str()) else {
-        return Ok(());
-    };
-
+    let token_id = book.asset_id.as_ref();
     let Some((mapped_market_id, idx)) = token_to_market.get(token_id) else {
         return Ok(());
     };
@@ -363,9 +627,9 @@ fn handle_ws_book(
 
     // Some WS messages include a `market` field; it can be inconsistent with our gamma-derived
     // condition_id mapping. Token->market mapping is the Phase 1 authority.
-    if let Some(msg_market_id) = obj.get("market").and_then(|v| v.as_str()) {
+    if let Some(msg_market_id) = book.market.as_deref() {
         if msg_market_id != market_id {
-            warn!(
+            warn_throttled!(
                 token_id,
                 msg_market_id,
                 mapped_market_id = market_id,
@@ -374,16 +638,7 @@ fn handle_ws_book(
         }
     }
 
-    let bids: &[serde_json::Value] = obj
-        .get("bids")
-        .and_then(|v| v.as_array())
-        .map(|v| v.as_slice())
-        .unwrap_or(&[]);
-    let asks: &[serde_json::Value] = obj
-        .get("asks")
-        .and_then(|v| v.as_array())
-        .map(|v| v.as_slice())
-        .unwrap_or(&[]);
+    let (bids, asks) = (book.bids.as_slice(), book.asks.as_slice());
 
     // Phase 1 hardening:
     // - Some markets can publish one-sided books (bids=[] or asks=[]). We still want to
@@ -396,17 +651,32 @@ fn handle_ws_book(
     // Depth uses top-3 asks; when asks are missing, this is 0 => bucket degrades to Thin.
     let ask_depth3_usdc = ask_depth3_usdc(asks);
 
-    let ts_recv_us = now_us();
-    ticks.write_record([
-        ts_recv_us.to_string(),
-        market_id.to_string(),
-        token_id.to_string(),
-        best_bid.to_string(),
-        best_ask.to_string(),
-        ask_depth3_usdc.to_string(),
-    ])?;
+    let tick_ms = ts_recv_us / 1000;
+
+    // Disk guard: when free space is low, keep at most one book tick per leg every
+    // `DEGRADED_TICK_LOG_EVERY_MS` so ticks.csv stops being the dominant writer.
+    let last_tick_log_ms = market_states
+        .get(market_id)
+        .and_then(|st| st.legs.get(*idx))
+        .map(|l| l.last_tick_log_ms)
+        .unwrap_or(0);
+    let log_tick = health.disk_level() < DiskLevel::TicksDegraded
+        || tick_ms.saturating_sub(last_tick_log_ms) >= DEGRADED_TICK_LOG_EVERY_MS;
+    if log_tick {
+        ticks
+            .write_row(&TickRow {
+                ts_recv_us,
+                market_id: market_id.to_string(),
+                token_id: token_id.to_string(),
+                best_bid,
+                best_ask,
+                ask_depth3_usdc,
+            })
+            .io_context("write ticks.csv")?;
+    }
     health.inc_ticks_processed(1);
-    health.set_last_tick_ingest_ms(ts_recv_us / 1000);
+    health.set_last_tick_ingest_ms(tick_ms);
+    health.set_market_last_tick_ingest_ms(market_id, tick_ms);
 
     let Some(state) = market_states.get_mut(market_id) else {
         return Ok(());
@@ -422,31 +692,28 @@ fn handle_ws_book(
     leg.best_bid_size_best = best_bid_size_best;
     leg.best_ask_size_best = best_ask_size_best;
     leg.ask_depth3_usdc = ask_depth3_usdc;
+    leg.book.apply_snapshot(bids, asks);
     leg.ts_recv_us = ts_recv_us;
-    leg.last_tick_log_ms = ts_recv_us / 1000;
+    if log_tick {
+        leg.last_tick_log_ms = tick_ms;
+    }
     leg.ready = leg.best_ask.is_finite() && leg.best_ask > 0.0;
 
-    maybe_publish_snapshot(state, snap_tx);
+    publisher.offer(state, health, tick_ms);
     Ok(())
 }
 
 fn handle_ws_price_change(
-    obj: serde_json::Map<String, serde_json::Value>,
-    token_to_market: &HashMap<String, (String, usize)>,
-    market_states: &mut HashMap<String, MarketState>,
+    pc: PriceChangeEvent<'_>,
+    ts_recv_us: u64,
+    token_to_market: &HashMap<TokenId, (MarketId, usize)>,
+    market_states: &mut HashMap<MarketId, MarketState>,
     ticks: &mut CsvAppender,
-    snap_tx: &watch::Sender<Option<MarketSnapshot>>,
+    publisher: &SnapshotPublisher<'_>,
     health: &HealthCounters,
 ) -> anyhow::Result<()> {
-    let Some(changes) = obj.get("price_changes").and_then(|v| v.as_array()) else {
-        return Ok(());
-    };
-
-    for ch in changes {
-        let Some(ch) = ch.as_object() else { continue };
-        let Some(token_id) = ch.get("asset_id").and_then(|v| v.as_str()) else {
-            continue;
-        };
+    for ch in &pc.price_changes {
+        let token_id = ch.asset_id.as_ref();
         let Some((market_id, idx)) = token_to_market.get(token_id) else {
             continue;
         };
@@ -457,8 +724,8 @@ fn handle_ws_price_change(
             continue;
         }
 
-        let best_bid = parse_f64(ch.get("best_bid")).unwrap_or(0.0);
-        let best_ask = parse_f64(ch.get("best_ask")).unwrap_or(0.0);
+        let best_bid = ch.best_bid.unwrap_or(0.0);
+        let best_ask = ch.best_ask.unwrap_or(0.0);
 
         let leg = &mut state.legs[*idx];
         // Best bid: 0 means missing.
@@ -475,7 +742,14 @@ fn handle_ws_price_change(
         };
         leg.best_bid_size_best = 0.0;
         leg.best_ask_size_best = 0.0;
-        leg.ts_recv_us = now_us();
+        if let (Some(side), Some(price), Some(size)) = (
+            Side::parse(&ch.side),
+            ch.price.and_then(Price::from_f64),
+            ch.size.and_then(Qty::from_f64),
+        ) {
+            leg.book.apply_change(side, price, size);
+        }
+        leg.ts_recv_us = ts_recv_us;
         leg.ready = leg.best_ask.is_finite() && leg.best_ask > 0.0;
 
         // Observability hardening:
@@ -484,134 +758,498 @@ fn handle_ws_price_change(
         //
         // Rate-limit per leg to ~1Hz to avoid turning price_change into an unbounded tick log.
         let tick_ms = leg.ts_recv_us / 1000;
-        if tick_ms.saturating_sub(leg.last_tick_log_ms) >= 1_000 {
-            ticks.write_record([
-                leg.ts_recv_us.to_string(),
-                market_id.to_string(),
-                token_id.to_string(),
-                leg.best_bid.to_string(),
-                leg.best_ask.to_string(),
-                leg.ask_depth3_usdc.to_string(),
-            ])?;
+        let log_every_ms = if health.disk_level() < DiskLevel::TicksDegraded {
+            1_000
+        } else {
+            DEGRADED_TICK_LOG_EVERY_MS
+        };
+        if tick_ms.saturating_sub(leg.last_tick_log_ms) >= log_every_ms {
+            ticks
+                .write_row(&TickRow {
+                    ts_recv_us: leg.ts_recv_us,
+                    market_id: market_id.to_string(),
+                    token_id: token_id.to_string(),
+                    best_bid: leg.best_bid,
+                    best_ask: leg.best_ask,
+                    ask_depth3_usdc: leg.ask_depth3_usdc,
+                })
+                .io_context("write ticks.csv")?;
             leg.last_tick_log_ms = tick_ms;
             health.inc_ticks_processed(1);
             health.set_last_tick_ingest_ms(tick_ms);
         }
+        health.set_market_last_tick_ingest_ms(market_id, tick_ms);
 
-        maybe_publish_snapshot(state, snap_tx);
+        publisher.offer(state, health, tick_ms);
     }
 
     Ok(())
 }
 
-fn maybe_publish_snapshot(state: &MarketState, snap_tx: &watch::Sender<Option<MarketSnapshot>>) {
-    if !state.legs.iter().all(|l| l.ready) {
-        return;
-    }
-    let snap = MarketSnapshot {
-        market_id: state.market_id.clone(),
-        legs: state
-            .legs
-            .iter()
-            .map(|l| LegSnapshot {
-                token_id: l.token_id.clone(),
-                best_ask: l.best_ask,
-                best_bid: l.best_bid,
-                best_ask_size_best: l.best_ask_size_best,
-                best_bid_size_best: l.best_bid_size_best,
-                ask_depth3_usdc: l.ask_depth3_usdc,
-                ts_recv_us: l.ts_recv_us,
-            })
-            .collect(),
+/// [`crate::replay::RawWsRebuild`] of the feed: every recorded frame goes through
+/// [`handle_ws_text`] as if just received, on a clock taken from the frames' exchange
+/// `timestamp`s (held monotonic; frames without one keep the last value). `[conflation]`
+/// applies on that clock, so the snapshots are the ones brain would have been offered.
+#[allow(dead_code)]
+pub fn replay_raw_ws(
+    cfg: &Config,
+    markets: Vec<MarketDef>,
+    raw_paths: &[PathBuf],
+    ticks_path: &Path,
+) -> anyhow::Result<Vec<(u64, MarketSnapshot)>> {
+    let mut ticks = CsvAppender::open(ticks_path, &TICKS_HEADER).io_context("open replay ticks")?;
+    let (token_to_market, mut market_states) = market_states(markets);
+    let collected = Mutex::new(Vec::new());
+    let publisher = SnapshotPublisher {
+        sink: SnapshotSink::Collect(&collected),
+        cfg: cfg.conflation.clone(),
     };
-    let _ = snap_tx.send(Some(snap));
+    let health = HealthCounters::default();
+
+    let mut clock_ms = 0u64;
+    let mut frames = 0u64;
+    for path in raw_paths {
+        let f = std::fs::File::open(path).with_context(|| format!("open {}", path.display()))?;
+        for line in std::io::BufRead::lines(std::io::BufReader::new(f)) {
+            let line = line.with_context(|| format!("read {}", path.display()))?;
+            let txt = line.trim();
+            if txt.is_empty() {
+                continue;
+            }
+            if let Some(ts) = ws_msg::frame_ts_ms(txt) {
+                clock_ms = clock_ms.max(ts);
+            }
+            publisher.flush_due(&mut market_states, &health, clock_ms);
+            handle_ws_text(
+                txt,
+                clock_ms * 1000,
+                &token_to_market,
+                &mut market_states,
+                &mut ticks,
+                None,
+                &publisher,
+                None,
+                &health,
+            )?;
+            frames += 1;
+        }
+    }
+    publisher.flush_due(
+        &mut market_states,
+        &health,
+        clock_ms.saturating_add(cfg.conflation.min_interval_ms),
+    );
+    ticks.flush_and_sync().io_context("flush replay ticks")?;
+
+    let h = health.snapshot();
+    info!(
+        frames,
+        ticks = h.ticks_processed,
+        snapshots = h.snapshots_published,
+        conflated = h.snapshots_conflated,
+        "raw_ws replayed"
+    );
+    Ok(collected.into_inner().unwrap_or_else(|e| e.into_inner()))
 }
 
-#[derive(Clone, Copy)]
-enum PriceSide {
-    Bid,
-    Ask,
+#[derive(Debug, Deserialize)]
+pub(crate) struct DataApiTrade {
+    #[serde(rename = "asset")]
+    asset_id: TokenId,
+    #[serde(rename = "conditionId")]
+    market_id: MarketId,
+    size: f64,
+    price: f64,
+    timestamp: u64,
+    #[serde(rename = "transactionHash")]
+    transaction_hash: String,
+    /// Taker side, `BUY` / `SELL`.
+    #[serde(default)]
+    side: Option<String>,
 }
 
-fn best_level(levels: &[serde_json::Value], side: PriceSide) -> Option<(f64, f64)> {
-    let mut best: Option<(f64, f64)> = None;
-    for lvl in levels {
-        let Some(px) = parse_f64(lvl.get("price")).filter(|v| v.is_finite() && *v > 0.0) else {
-            continue;
-        };
+/// Which path a fill came in on.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+enum TradeSource {
+    Poll,
+    Ws,
+    /// data-api history fetched on startup by [`backfill_trades`].
+    Backfill,
+}
 
-        let sz = parse_f64(lvl.get("size"))
-            .filter(|s| s.is_finite() && *s > 0.0)
-            .unwrap_or(0.0);
-
-        best = match (best, side) {
-            (None, _) => Some((px, sz)),
-            (Some((cur_px, cur_sz)), PriceSide::Bid) => {
-                if px > cur_px {
-                    Some((px, sz))
-                } else {
-                    Some((cur_px, cur_sz))
+impl TradeSource {
+    fn as_str(self) -> &'static str {
+        match self {
+            TradeSource::Poll => "data-api",
+            TradeSource::Ws => "ws",
+            TradeSource::Backfill => "backfill",
+        }
+    }
+}
+
+/// A fill from either trade source, before validation and dedup.
+#[derive(Debug, Clone)]
+pub struct IncomingTrade {
+    market_id: MarketId,
+    token_id: TokenId,
+    price: f64,
+    size: f64,
+    /// Exchange time as sent (s or ms); normalized on ingest.
+    timestamp: u64,
+    transaction_hash: String,
+    side: Option<Side>,
+}
+
+impl From<DataApiTrade> for IncomingTrade {
+    fn from(t: DataApiTrade) -> Self {
+        Self {
+            market_id: t.market_id,
+            token_id: t.asset_id,
+            price: t.price,
+            size: t.size,
+            timestamp: t.timestamp,
+            transaction_hash: t.transaction_hash,
+            side: t.side.as_deref().and_then(Side::parse),
+        }
+    }
+}
+
+/// Fills [`run_market_ws`] can queue for [`run_trades_poller`] before the WS side starts
+/// dropping them (the poller backfills those).
+const WS_TRADE_QUEUE: usize = 4096;
+
+/// Channel from [`run_market_ws`] to [`run_trades_poller`] for `shadow.trade_feed = "ws"`.
+pub fn ws_trade_queue() -> (mpsc::Sender<IncomingTrade>, mpsc::Receiver<IncomingTrade>) {
+    mpsc::channel(WS_TRADE_QUEUE)
+}
+
+/// Pairs up the same fill arriving from both sources. Their trade ids differ (the WS stamps ms,
+/// data-api seconds, and the WS may omit the tx hash), so fills match on token + second +
+/// price + size, one for one: N identical fills from one side cancel at most N from the other.
+#[derive(Debug, Default)]
+struct CrossSourceMatch {
+    unmatched: HashMap<(TradeSource, String), std::collections::VecDeque<u64>>,
+    last_sweep_ms: u64,
+}
+
+impl CrossSourceMatch {
+    /// `None` for a non-finite price or size, which never matches.
+    fn key(t: &IncomingTrade) -> Option<String> {
+        Some(format!(
+            "{}:{}:{}:{}:{}",
+            t.market_id,
+            t.token_id,
+            normalize_ts_ms(t.timestamp) / 1000,
+            Price::from_f64(t.price)?.micros(),
+            Qty::from_f64(t.size)?.micros()
+        ))
+    }
+
+    /// True when the other source already delivered this fill (that copy is consumed);
+    /// otherwise remembers it until `retention_ms` has passed.
+    fn is_echo(
+        &mut self,
+        source: TradeSource,
+        t: &IncomingTrade,
+        now_ms: u64,
+        retention_ms: u64,
+    ) -> bool {
+        let cutoff = now_ms.saturating_sub(retention_ms);
+        if now_ms.saturating_sub(self.last_sweep_ms) >= 1_000 {
+            self.last_sweep_ms = now_ms;
+            self.unmatched.retain(|_, seen| {
+                while seen.front().is_some_and(|ts| *ts < cutoff) {
+                    seen.pop_front();
                 }
+                !seen.is_empty()
+            });
+        }
+
+        let Some(key) = Self::key(t) else {
+            return false;
+        };
+        let other = match source {
+            TradeSource::Poll | TradeSource::Backfill => TradeSource::Ws,
+            TradeSource::Ws => TradeSource::Poll,
+        };
+        if let Some(seen) = self.unmatched.get_mut(&(other, key.clone())) {
+            while seen.front().is_some_and(|ts| *ts < cutoff) {
+                seen.pop_front();
             }
-            (Some((cur_px, cur_sz)), PriceSide::Ask) => {
-                if px < cur_px {
-                    Some((px, sz))
-                } else {
-                    Some((cur_px, cur_sz))
-                }
+            if seen.pop_front().is_some() {
+                return true;
             }
-        };
+        }
+        self.unmatched
+            .entry((source, key))
+            .or_default()
+            .push_back(now_ms);
+        false
     }
-    best
 }
 
-fn ask_depth3_usdc(levels: &[serde_json::Value]) -> f64 {
-    let mut best = [(f64::INFINITY, 0.0f64); 3];
-    for lvl in levels {
-        let Some(px) = parse_f64(lvl.get("price")).filter(|v| v.is_finite() && *v > 0.0) else {
-            continue;
+/// The trades task's write side, shared by the poller and the WS trade stream: validation,
+/// dedup, trades.csv and the shadow trade channel.
+struct TradeIngest<'a> {
+    cfg: &'a Config,
+    // Keep token allow-lists per market_id. Using a union set here can silently accept a
+    // token from another configured market when polling per-market, which would pollute
+    // shadow accounting.
+    tokens_by_market: HashMap<MarketId, HashSet<TokenId>>,
+    trades: CsvAppender,
+    trade_tx: &'a backpressure::Sender<TradeTick>,
+    health: &'a HealthCounters,
+    trade_arrivals: &'a TradeArrivals,
+    recent_ids: HashSet<String>,
+    recent_queue: std::collections::VecDeque<(u64, String)>,
+    collisions: Option<JsonlAppender>,
+    /// Set when both sources feed trades.csv (`shadow.trade_feed = "ws"`).
+    cross: Option<CrossSourceMatch>,
+    last_drop_log_ms: u64,
+    dropped_trades: u64,
+}
+
+impl TradeIngest<'_> {
+    /// Returns whether the fill was new and written.
+    async fn ingest(&mut self, t: IncomingTrade, source: TradeSource) -> anyhow::Result<bool> {
+        let cfg = self.cfg;
+        let health = self.health;
+        if !t.price.is_finite()
+            || !t.size.is_finite()
+            || t.price < 0.0
+            || t.size <= 0.0
+            || t.price > 1.0
+        {
+            health.inc_trades_invalid(1);
+            return Ok(false);
+        }
+
+        if t.token_id.trim().is_empty() {
+            warn_throttled!(
+                market_id = %t.market_id,
+                source = source.as_str(),
+                "trade missing token_id/asset; skipping tick to avoid shadow pollution"
+            );
+            return Ok(false);
+        }
+        let Some(tokens_for_market) = self.tokens_by_market.get(&t.market_id) else {
+            return Ok(false);
         };
-        let Some(sz) = parse_f64(lvl.get("size")).filter(|v| v.is_finite() && *v > 0.0) else {
-            continue;
+        if !tokens_for_market.contains(&t.token_id) {
+            warn_throttled!(
+                market_id = %t.market_id,
+                token_id = %t.token_id,
+                source = source.as_str(),
+                "trade token_id not in configured market token set; skipping"
+            );
+            return Ok(false);
+        }
+
+        let (Some(price), Some(size)) = (Price::from_f64(t.price), Qty::from_f64(t.size)) else {
+            warn_throttled!(
+                market_id = %t.market_id,
+                token_id = %t.token_id,
+                source = source.as_str(),
+                price = t.price,
+                size = t.size,
+                "trade price/size not a finite number; skipping"
+            );
+            return Ok(false);
         };
 
-        if px >= best[2].0 {
-            continue;
+        let trade_ts_ms = normalize_ts_ms(t.timestamp);
+        let trade_id = trade_dedup_key(
+            &t.market_id,
+            &t.token_id,
+            trade_ts_ms,
+            t.price,
+            t.size,
+            &t.transaction_hash,
+        );
+
+        let now = now_ms();
+        let expired = expire_recent_ids(
+            now,
+            cfg.shadow.trade_retention_ms,
+            &mut self.recent_queue,
+            &mut self.recent_ids,
+        );
+        health.inc_dedup_expired(expired as u64);
+
+        if self.recent_ids.contains(&trade_id) {
+            let weak_key = trade_id.starts_with("weak:");
+            health.record_trade_duplicate(weak_key);
+            if let Some(out) = self.collisions.as_mut() {
+                let line = serde_json::json!({
+                    "ts_ms": now,
+                    "market_id": t.market_id,
+                    "token_id": t.token_id,
+                    "key_kind": if weak_key { "weak" } else { "tx" },
+                    "key": trade_id,
+                    "exchange_ts_ms": trade_ts_ms,
+                    "price": t.price,
+                    "size": t.size,
+                    "transaction_hash": t.transaction_hash,
+                });
+                if let Err(e) = out.write_line(&line.to_string()) {
+                    warn_throttled!(error = %e, "dedup_collisions.jsonl write failed");
+                }
+            }
+            return Ok(false);
         }
+        if let Some(cross) = self
+            .cross
+            .as_mut()
+            .filter(|_| source != TradeSource::Backfill)
+        {
+            if cross.is_echo(source, &t, now, cfg.shadow.trade_retention_ms) {
+                return Ok(false);
+            }
+            match source {
+                TradeSource::Ws => health.inc_trades_from_ws(1),
+                _ => health.inc_trades_gap_filled(1),
+            }
+        }
+        self.recent_ids.insert(trade_id.clone());
+        self.recent_queue.push_back((now, trade_id.clone()));
+        health.set_dedup_recent_ids(self.recent_ids.len());
+
+        // Phase 1 uses local ingest time as the canonical timestamp domain for shadow windows.
+        // Backfilled fills were never received live; like fetch-history they take exchange time.
+        let ingest_ts_ms = match source {
+            TradeSource::Backfill => trade_ts_ms,
+            TradeSource::Poll | TradeSource::Ws => now,
+        };
+        let ts_ms = ingest_ts_ms;
+        let tick = TradeTick {
+            ts_ms,
+            ingest_ts_ms,
+            exchange_ts_ms: Some(trade_ts_ms),
+            market_id: t.market_id,
+            token_id: t.token_id,
+            price,
+            size,
+            trade_id,
+            side: t.side,
+        };
 
-        best[2] = (px, sz);
-        best.sort_by(|a, b| a.0.partial_cmp(&b.0).unwrap_or(std::cmp::Ordering::Equal));
+        self.trades
+            .write_row(&TradeRow::from(&tick))
+            .io_context("write trades.csv")?;
+        health.inc_trades_written(1);
+        health.set_last_trade_ingest_ms(tick.ingest_ts_ms);
+        health.set_market_last_trade_ingest_ms(&tick.market_id, tick.ingest_ts_ms);
+        self.trade_arrivals
+            .record(&tick.market_id, tick.ingest_ts_ms);
+
+        match self.trade_tx.send(tick).await {
+            Ok(SendOutcome::Queued) => {}
+            Ok(SendOutcome::Dropped(_)) => {
+                health.inc_trades_dropped(1);
+                self.dropped_trades = self.dropped_trades.saturating_add(1);
+                if now.saturating_sub(self.last_drop_log_ms) >= 10_000 {
+                    self.last_drop_log_ms = now;
+                    warn!(
+                        dropped_trades = self.dropped_trades,
+                        policy = cfg.backpressure.trade.policy.as_str(),
+                        "trade channel full; dropping trades (shadow windows flagged TRADE_CHANNEL_DROP)"
+                    );
+                }
+            }
+            Err(backpressure::Closed) => {
+                return Err(RazorError::ChannelClosed("trade").into());
+            }
+        }
+        Ok(true)
     }
+}
 
-    best.iter()
-        .filter(|(px, _)| px.is_finite())
-        .map(|(px, sz)| px * sz)
-        .sum()
+/// Pages data-api `/trades` for `market_id` newest first (by `offset`, `trade_poll_limit` per
+/// page) until a page reaches back to `since_ms`, comes back short, or `max_pages` pages were
+/// read. Returns the fills at or after `since_ms`, oldest first, not yet validated or deduped.
+pub async fn backfill_trades(
+    cfg: &Config,
+    client: &reqwest::Client,
+    market_id: &MarketId,
+    since_ms: u64,
+    max_pages: usize,
+) -> anyhow::Result<Vec<IncomingTrade>> {
+    let url = format!(
+        "{}/trades",
+        cfg.polymarket.data_api_base.trim_end_matches('/')
+    );
+    let page_size = cfg.shadow.trade_poll_limit;
+    let mut out = Vec::new();
+    for page_no in 0..max_pages {
+        let offset = page_no * page_size;
+        let resp = api_usage::track(
+            ApiEndpoint::DataApiTrades,
+            client
+                .get(&url)
+                .query(&[
+                    ("limit", page_size.to_string()),
+                    ("offset", offset.to_string()),
+                    ("takerOnly", cfg.shadow.trade_poll_taker_only.to_string()),
+                    ("market", market_id.to_string()),
+                ])
+                .send()
+                .await,
+        )
+        .http_context("data-api trades backfill")?;
+        let page: Vec<DataApiTrade> = resp.json().await.http_context("decode data-api trades")?;
+        let page_len = page.len();
+        let oldest = take_backfill_page(page, market_id, since_ms, &mut out);
+        if page_len < page_size || oldest.is_none_or(|ts| ts < since_ms) {
+            break;
+        }
+    }
+    out.sort_by_key(|t| normalize_ts_ms(t.timestamp));
+    Ok(out)
 }
 
-#[derive(Debug, Deserialize)]
-struct DataApiTrade {
-    #[serde(rename = "asset")]
-    asset_id: String,
-    #[serde(rename = "conditionId")]
-    market_id: String,
-    size: f64,
-    price: f64,
-    timestamp: u64,
-    #[serde(rename = "transactionHash")]
-    transaction_hash: String,
+/// Keeps `market_id`'s fills at or after `since_ms`; returns the page's oldest exchange time.
+fn take_backfill_page(
+    page: Vec<DataApiTrade>,
+    market_id: &MarketId,
+    since_ms: u64,
+    out: &mut Vec<IncomingTrade>,
+) -> Option<u64> {
+    let mut oldest: Option<u64> = None;
+    for t in page {
+        let ts_ms = normalize_ts_ms(t.timestamp);
+        oldest = Some(oldest.map_or(ts_ms, |o| o.min(ts_ms)));
+        if t.market_id == *market_id && ts_ms >= since_ms {
+            out.push(t.into());
+        }
+    }
+    oldest
 }
 
+/// Next fill from the WS trade stream; never resolves without one.
+async fn recv_ws_trade(rx: &mut Option<mpsc::Receiver<IncomingTrade>>) -> Option<IncomingTrade> {
+    match rx {
+        Some(rx) => rx.recv().await,
+        None => std::future::pending().await,
+    }
+}
+
+/// Writes trades.csv and feeds shadow. Polls data-api `/trades` per market; with `ws_trades`
+/// (`shadow.trade_feed = "ws"`) the market channel's fills come first and the poll only
+/// backfills what the WS missed.
+#[allow(clippy::too_many_arguments)]
 pub async fn run_trades_poller(
     cfg: Config,
     markets: Vec<MarketDef>,
-    trade_tx: mpsc::Sender<TradeTick>,
+    trade_tx: backpressure::Sender<TradeTick>,
     trades_path: PathBuf,
     health: Arc<HealthCounters>,
     health_tx: mpsc::Sender<HealthLine>,
+    trade_arrivals: Arc<TradeArrivals>,
+    dedup_seed: std::collections::VecDeque<(u64, String)>,
+    mut ws_trades: Option<mpsc::Receiver<IncomingTrade>>,
     mut shutdown: watch::Receiver<bool>,
 ) -> anyhow::Result<()> {
-    let mut trades = CsvAppender::open(trades_path, &TRADES_HEADER).context("open trades.csv")?;
+    let trades = open_run_csv(&cfg, &trades_path, &TRADES_HEADER).io_context("open trades.csv")?;
 
     let client = reqwest::Client::builder()
         .user_agent(concat!("razor/", env!("CARGO_PKG_VERSION")))
@@ -620,16 +1258,13 @@ pub async fn run_trades_poller(
         ))
         .timeout(Duration::from_millis(cfg.polymarket.http_timeout_ms))
         .build()
-        .context("build http client")?;
+        .http_context("build http client")?;
 
-    // Keep token allow-lists per market_id. Using a union set here can silently accept a
-    // token from another configured market when polling per-market, which would pollute
-    // shadow accounting.
-    let mut tokens_by_market: HashMap<String, HashSet<String>> = HashMap::new();
-    let mut market_ids: Vec<String> = Vec::with_capacity(markets.len());
+    let mut tokens_by_market: HashMap<MarketId, HashSet<TokenId>> = HashMap::new();
+    let mut market_ids: Vec<MarketId> = Vec::with_capacity(markets.len());
     for m in markets {
         market_ids.push(m.market_id.clone());
-        let token_set: HashSet<String> = m
+        let token_set: HashSet<TokenId> = m
             .token_ids
             .into_iter()
             .filter(|t| !t.trim().is_empty())
@@ -642,14 +1277,70 @@ pub async fn run_trades_poller(
         cfg.polymarket.data_api_base.trim_end_matches('/')
     );
 
-    let mut recent_ids: HashSet<String> = HashSet::new();
-    let mut recent_queue: std::collections::VecDeque<(u64, String)> =
-        std::collections::VecDeque::new();
-    let mut last_drop_log_ms: u64 = 0;
-    let mut dropped_trades: u64 = 0;
+    let collisions = if cfg.shadow.trade_dedup_dump_collisions {
+        let path = trades_path.with_file_name(crate::schema::FILE_DEDUP_COLLISIONS_JSONL);
+        Some(JsonlAppender::open(&path).io_context("open dedup_collisions.jsonl")?)
+    } else {
+        None
+    };
+    let mut ingest = TradeIngest {
+        cfg: &cfg,
+        tokens_by_market,
+        trades,
+        trade_tx: &trade_tx,
+        health: health.as_ref(),
+        trade_arrivals: trade_arrivals.as_ref(),
+        // Seeded with the trades a resumed run already recorded, so they aren't written twice.
+        recent_ids: dedup_seed.iter().map(|(_, id)| id.clone()).collect(),
+        recent_queue: dedup_seed,
+        collisions,
+        cross: ws_trades.is_some().then(CrossSourceMatch::default),
+        last_drop_log_ms: 0,
+        dropped_trades: 0,
+    };
 
-    let mut interval =
-        tokio::time::interval(Duration::from_millis(cfg.shadow.trade_poll_interval_ms));
+    let mut poll_adapt = TradePollAdaptor::new(
+        cfg.shadow.trade_poll_interval_ms,
+        cfg.shadow.trade_poll_min_interval_ms,
+        cfg.shadow.trade_poll_limit_alert_count,
+        cfg.shadow.trade_poll_limit_alert_window_ms,
+    );
+    health.set_trade_poll_interval_ms(poll_adapt.interval_ms());
+    if cfg.shadow.trade_backfill_max_pages > 0 {
+        let since_ms = now_ms().saturating_sub(cfg.shadow.trade_retention_ms);
+        for market_id in &market_ids {
+            let fills = match backfill_trades(
+                &cfg,
+                &client,
+                market_id,
+                since_ms,
+                cfg.shadow.trade_backfill_max_pages,
+            )
+            .await
+            {
+                Ok(v) => v,
+                Err(e) => {
+                    warn!(%market_id, error = %e, "trade backfill failed; starting from live trades");
+                    continue;
+                }
+            };
+            let fetched = fills.len();
+            let mut written = 0usize;
+            for t in fills {
+                if ingest.ingest(t, TradeSource::Backfill).await? {
+                    written += 1;
+                }
+            }
+            info!(%market_id, since_ms, fetched, written, "trade backfill done");
+        }
+    }
+
+    info!(
+        trade_feed = cfg.shadow.trade_feed.as_str(),
+        ws = ingest.cross.is_some(),
+        "trades task started"
+    );
+    let mut interval = tokio::time::interval(Duration::from_millis(poll_adapt.interval_ms()));
     interval.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
 
     loop {
@@ -659,30 +1350,45 @@ pub async fn run_trades_poller(
                     break;
                 }
             }
+            maybe = recv_ws_trade(&mut ws_trades) => {
+                match maybe {
+                    Some(t) => {
+                        ingest.ingest(t, TradeSource::Ws).await?;
+                    }
+                    None => {
+                        warn!("ws trade stream closed; polling only");
+                        ws_trades = None;
+                    }
+                }
+                continue;
+            }
             _ = interval.tick() => {}
         }
         if *shutdown.borrow() {
             break;
         }
 
+        let mut hit_limit_this_round = false;
         for market_id in &market_ids {
             if *shutdown.borrow() {
                 break;
             }
 
-            let resp = match client
-                .get(&url)
-                .query(&[
-                    ("limit", cfg.shadow.trade_poll_limit.to_string()),
-                    ("takerOnly", cfg.shadow.trade_poll_taker_only.to_string()),
-                    ("market", market_id.to_string()),
-                ])
-                .send()
-                .await
-            {
+            let resp = match api_usage::track(
+                ApiEndpoint::DataApiTrades,
+                client
+                    .get(&url)
+                    .query(&[
+                        ("limit", cfg.shadow.trade_poll_limit.to_string()),
+                        ("takerOnly", cfg.shadow.trade_poll_taker_only.to_string()),
+                        ("market", market_id.to_string()),
+                    ])
+                    .send()
+                    .await,
+            ) {
                 Ok(r) => r,
                 Err(e) => {
-                    warn!(market_id = %market_id, error = %e, "data-api trades request failed");
+                    warn_throttled!(market_id = %market_id, error = %e, "data-api trades request failed");
                     continue;
                 }
             };
@@ -690,7 +1396,7 @@ pub async fn run_trades_poller(
             let list: Vec<DataApiTrade> = match resp.json().await {
                 Ok(v) => v,
                 Err(e) => {
-                    warn!(market_id = %market_id, error = %e, "data-api trades decode failed");
+                    warn_throttled!(market_id = %market_id, error = %e, "data-api trades decode failed");
                     continue;
                 }
             };
@@ -698,6 +1404,7 @@ pub async fn run_trades_poller(
             let returned_count = list.len();
             if returned_count >= cfg.shadow.trade_poll_limit {
                 health.inc_trade_poll_hit_limit(1);
+                hit_limit_this_round = true;
                 let mut earliest = u64::MAX;
                 let mut latest = 0u64;
                 for t in &list {
@@ -705,7 +1412,7 @@ pub async fn run_trades_poller(
                     earliest = earliest.min(ts_ms);
                     latest = latest.max(ts_ms);
                 }
-                warn!(
+                warn_throttled!(
                     market_id = %market_id,
                     returned_count,
                     limit = cfg.shadow.trade_poll_limit,
@@ -724,167 +1431,59 @@ pub async fn run_trades_poller(
                     .map_err(|_| ());
             }
 
-            let tokens_for_market = match tokens_by_market.get(market_id) {
-                Some(v) => v,
-                None => continue,
-            };
-
             for t in list {
                 if t.market_id != *market_id {
                     continue;
                 }
-                if !t.price.is_finite()
-                    || !t.size.is_finite()
-                    || t.price < 0.0
-                    || t.size <= 0.0
-                    || t.price > 1.0
-                {
-                    health.inc_trades_invalid(1);
-                    continue;
-                }
-
-                if t.asset_id.trim().is_empty() {
-                    warn!(
-                        market_id = %t.market_id,
-                        "data-api trade missing token_id/asset; skipping tick to avoid shadow pollution"
-                    );
-                    continue;
-                }
-                if !tokens_for_market.contains(&t.asset_id) {
-                    warn!(
-                        market_id = %t.market_id,
-                        token_id = %t.asset_id,
-                        "data-api trade token_id not in configured market token set; skipping"
-                    );
-                    continue;
-                }
+                ingest.ingest(t.into(), TradeSource::Poll).await?;
+            }
+        }
 
-                let trade_ts_ms = normalize_ts_ms(t.timestamp);
-                let trade_id = dedup_key(
-                    &t.market_id,
-                    &t.asset_id,
-                    trade_ts_ms,
-                    t.price,
-                    t.size,
-                    &t.transaction_hash,
+        let now = now_ms();
+        let adjusted = if hit_limit_this_round {
+            poll_adapt.on_limit_hit(now)
+        } else {
+            poll_adapt.on_quiet_round(now)
+        };
+        if let Some(change) = adjusted {
+            if change.to_ms < change.from_ms {
+                warn!(
+                    from_ms = change.from_ms,
+                    to_ms = change.to_ms,
+                    hits_in_window = change.hits_in_window,
+                    "trade poll limit hit repeatedly; shortening poll interval"
                 );
-
-                let now = now_ms();
-                expire_recent_ids(
-                    now,
-                    cfg.shadow.trade_retention_ms,
-                    &mut recent_queue,
-                    &mut recent_ids,
+            } else {
+                info!(
+                    from_ms = change.from_ms,
+                    to_ms = change.to_ms,
+                    "trade poll limit quiet; relaxing poll interval"
                 );
-
-                if recent_ids.contains(&trade_id) {
-                    health.inc_trades_duplicated(1);
-                    continue;
-                }
-                recent_ids.insert(trade_id.clone());
-                recent_queue.push_back((now, trade_id.clone()));
-
-                // Phase 1 uses local ingest time as the canonical timestamp domain for shadow windows.
-                let ingest_ts_ms = now;
-                let ts_ms = ingest_ts_ms;
-                let tick = TradeTick {
-                    ts_ms,
-                    ingest_ts_ms,
-                    exchange_ts_ms: Some(trade_ts_ms),
-                    market_id: t.market_id.clone(),
-                    token_id: t.asset_id.clone(),
-                    price: t.price,
-                    size: t.size,
-                    trade_id: trade_id.clone(),
-                };
-
-                trades.write_record([
-                    tick.ts_ms.to_string(),
-                    tick.market_id.clone(),
-                    tick.token_id.clone(),
-                    tick.price.to_string(),
-                    tick.size.to_string(),
-                    tick.trade_id.clone(),
-                    tick.ingest_ts_ms.to_string(),
-                    tick.exchange_ts_ms
-                        .map(|v| v.to_string())
-                        .unwrap_or_default(),
-                ])?;
-                health.inc_trades_written(1);
-                health.set_last_trade_ingest_ms(tick.ingest_ts_ms);
-
-                match trade_tx.try_send(tick) {
-                    Ok(()) => {}
-                    Err(tokio::sync::mpsc::error::TrySendError::Full(_)) => {
-                        health.inc_trades_dropped(1);
-                        dropped_trades = dropped_trades.saturating_add(1);
-                        if now.saturating_sub(last_drop_log_ms) >= 10_000 {
-                            last_drop_log_ms = now;
-                            warn!(
-                                dropped_trades,
-                                "trade channel full; dropping trades (Phase1 allows drop)"
-                            );
-                        }
-                    }
-                    Err(tokio::sync::mpsc::error::TrySendError::Closed(_)) => {
-                        return Err(anyhow::anyhow!("trade receiver dropped"));
-                    }
-                }
             }
+            health.set_trade_poll_interval_ms(change.to_ms);
+            let _ = health_tx.try_send(HealthLine::TradePollIntervalChanged {
+                ts_ms: now,
+                from_ms: change.from_ms,
+                to_ms: change.to_ms,
+                hits_in_window: change.hits_in_window,
+            });
+            interval = tokio::time::interval_at(
+                tokio::time::Instant::now() + Duration::from_millis(change.to_ms),
+                Duration::from_millis(change.to_ms),
+            );
+            interval.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
         }
     }
 
-    trades.flush_and_sync().context("flush trades.csv")?;
-    Ok(())
-}
-
-fn normalize_ts_ms(ts: u64) -> u64 {
-    // Normalize unix timestamps to milliseconds.
-    //
-    // Supported input units:
-    // - seconds:      ~1.7e9  (10 digits)
-    // - milliseconds: ~1.7e12 (13 digits)
-    // - microseconds: ~1.7e15 (16 digits)
-    // - nanoseconds:  ~1.7e18 (19 digits)
-    //
-    // Note: Phase 1 uses local ingest time (`ingest_ts_ms`) as the canonical timestamp domain for
-    // shadow windows; this normalization is used for diagnostics + exchange_ts_ms only.
-    match ts {
-        // seconds
-        0..=99_999_999_999 => ts.saturating_mul(1_000),
-        // milliseconds
-        100_000_000_000..=99_999_999_999_999 => ts,
-        // microseconds
-        100_000_000_000_000..=99_999_999_999_999_999 => ts / 1_000,
-        // nanoseconds (and anything larger) -> ms
-        _ => ts / 1_000_000,
-    }
-}
-
-fn dedup_key(
-    market_id: &str,
-    token_id: &str,
-    ts_ms: u64,
-    price: f64,
-    size: f64,
-    transaction_hash: &str,
-) -> String {
-    let tx = transaction_hash.trim();
-    if !tx.is_empty() {
-        // Do not use bare `transactionHash` as a unique trade ID: a single tx can contain
-        // multiple fills. Include token+ts+price+size to avoid false de-duplication.
-        format!(
-            "tx:{market_id}:{token_id}:{ts_ms}:{tx}:{:016x}:{:016x}",
-            price.to_bits(),
-            size.to_bits()
-        )
-    } else {
-        format!(
-            "weak:{market_id}:{token_id}:{ts_ms}:{:016x}:{:016x}",
-            price.to_bits(),
-            size.to_bits()
-        )
+    ingest
+        .trades
+        .flush_and_sync()
+        .io_context("flush trades.csv")?;
+    if let Some(out) = ingest.collisions.as_mut() {
+        out.flush_and_sync()
+            .io_context("flush dedup_collisions.jsonl")?;
     }
+    Ok(())
 }
 
 fn expire_recent_ids(
@@ -892,73 +1491,132 @@ fn expire_recent_ids(
     retention_ms: u64,
     q: &mut std::collections::VecDeque<(u64, String)>,
     set: &mut HashSet<String>,
-) {
+) -> usize {
     if retention_ms == 0 {
+        let n = set.len();
         q.clear();
         set.clear();
-        return;
+        return n;
     }
 
     let cutoff = now_ms.saturating_sub(retention_ms);
+    let mut expired = 0;
     while q.front().is_some_and(|(ts, _)| *ts < cutoff) {
         if let Some((_, id)) = q.pop_front() {
-            set.remove(&id);
+            if set.remove(&id) {
+                expired += 1;
+            }
         }
     }
+    expired
 }
 
-#[cfg(test)]
-mod tests {
-    use super::*;
-    use assert_approx_eq::assert_approx_eq;
-    use serde_json::json;
-    use tokio::sync::watch;
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct PollIntervalChange {
+    from_ms: u64,
+    to_ms: u64,
+    hits_in_window: usize,
+}
 
-    #[test]
-    fn normalize_ts_ms_handles_s_ms_us_ns() {
-        // seconds -> ms
-        assert_eq!(normalize_ts_ms(1_700_000_000), 1_700_000_000_000);
-        // ms -> ms
-        assert_eq!(normalize_ts_ms(1_700_000_000_000), 1_700_000_000_000);
-        // us -> ms
-        assert_eq!(normalize_ts_ms(1_700_000_000_000_000), 1_700_000_000_000);
-        // ns -> ms
-        assert_eq!(
-            normalize_ts_ms(1_700_000_000_000_000_000),
-            1_700_000_000_000
-        );
+/// Adaptive trade poll interval: after `alert_count` limit hits within `window_ms` the interval
+/// is halved (not below `min_ms`); after a full window without hits it doubles back toward the
+/// configured base.
+struct TradePollAdaptor {
+    base_ms: u64,
+    min_ms: u64,
+    alert_count: usize,
+    window_ms: u64,
+    current_ms: u64,
+    hits: std::collections::VecDeque<u64>,
+    last_change_ms: u64,
+    alerted_at_floor: bool,
+}
+
+impl TradePollAdaptor {
+    fn new(base_ms: u64, min_ms: u64, alert_count: usize, window_ms: u64) -> Self {
+        Self {
+            base_ms,
+            min_ms: min_ms.min(base_ms),
+            alert_count,
+            window_ms,
+            current_ms: base_ms,
+            hits: std::collections::VecDeque::new(),
+            last_change_ms: 0,
+            alerted_at_floor: false,
+        }
     }
 
-    #[test]
-    fn ws_book_level_parses_numeric_and_string_fields() {
-        let bids = vec![
-            json!({"price": 0.49, "size": 1.0}),
-            json!({"price": "0.5", "size": "2"}),
-        ];
-        let (px, sz) = best_level(&bids, PriceSide::Bid).expect("best bid");
-        assert_approx_eq!(px, 0.5);
-        assert_approx_eq!(sz, 2.0);
-
-        let asks = vec![
-            json!({"price": 0.6, "size": 1.0}),
-            json!({"price": "0.55", "size": "2"}),
-        ];
-        let (px, sz) = best_level(&asks, PriceSide::Ask).expect("best ask");
-        assert_approx_eq!(px, 0.55);
-        assert_approx_eq!(sz, 2.0);
+    fn interval_ms(&self) -> u64 {
+        self.current_ms
     }
 
-    #[test]
-    fn ws_book_depth3_parses_numeric_and_sums_top3() {
-        let asks = vec![
-            json!({"price": 0.6, "size": 10.0}),    // 6
-            json!({"price": "0.55", "size": 20.0}), // 11
-            json!({"price": 0.50, "size": "30"}),   // 15
-            json!({"price": 0.65, "size": 40.0}),   // excluded (higher price)
-        ];
-        let d = ask_depth3_usdc(&asks);
-        assert_approx_eq!(d, 32.0);
+    fn on_limit_hit(&mut self, now_ms: u64) -> Option<PollIntervalChange> {
+        if self.alert_count == 0 {
+            return None;
+        }
+        self.hits.push_back(now_ms);
+        while self
+            .hits
+            .front()
+            .is_some_and(|t| now_ms.saturating_sub(*t) > self.window_ms)
+        {
+            self.hits.pop_front();
+        }
+        if self.hits.len() < self.alert_count {
+            return None;
+        }
+
+        let hits_in_window = self.hits.len();
+        self.hits.clear();
+        self.last_change_ms = now_ms;
+        let to_ms = (self.current_ms / 2).max(self.min_ms);
+        if to_ms == self.current_ms {
+            if !self.alerted_at_floor {
+                self.alerted_at_floor = true;
+                error!(
+                    interval_ms = self.current_ms,
+                    hits_in_window,
+                    "trade poll limit still hit at minimum interval; trades are likely missing"
+                );
+            }
+            return None;
+        }
+        let change = PollIntervalChange {
+            from_ms: self.current_ms,
+            to_ms,
+            hits_in_window,
+        };
+        self.current_ms = to_ms;
+        Some(change)
+    }
+
+    fn on_quiet_round(&mut self, now_ms: u64) -> Option<PollIntervalChange> {
+        if self.current_ms >= self.base_ms
+            || now_ms.saturating_sub(self.last_change_ms) < self.window_ms
+            || self
+                .hits
+                .back()
+                .is_some_and(|t| now_ms.saturating_sub(*t) < self.window_ms)
+        {
+            return None;
+        }
+        self.last_change_ms = now_ms;
+        self.alerted_at_floor = false;
+        let to_ms = self.current_ms.saturating_mul(2).min(self.base_ms);
+        let change = PollIntervalChange {
+            from_ms: self.current_ms,
+            to_ms,
+            hits_in_window: 0,
+        };
+        self.current_ms = to_ms;
+        Some(change)
     }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::sync::watch;
 
     #[test]
     fn ws_book_market_id_uses_token_mapping_when_mismatched() {
@@ -969,47 +1627,51 @@ mod tests {
         ));
         let mut ticks = CsvAppender::open(&tmp, &TICKS_HEADER).expect("open ticks csv");
 
-        let mut token_to_market: HashMap<String, (String, usize)> = HashMap::new();
-        token_to_market.insert("t1".to_string(), ("m1".to_string(), 0));
+        let mut token_to_market: HashMap<TokenId, (MarketId, usize)> = HashMap::new();
+        token_to_market.insert("t1".into(), ("m1".into(), 0));
 
-        let mut market_states: HashMap<String, MarketState> = HashMap::new();
+        let mut market_states: HashMap<MarketId, MarketState> = HashMap::new();
         market_states.insert(
-            "m1".to_string(),
+            "m1".into(),
             MarketState {
-                market_id: "m1".to_string(),
+                market_id: "m1".into(),
                 legs: vec![LegState {
-                    token_id: "t1".to_string(),
+                    token_id: "t1".into(),
                     best_ask: 0.0,
                     best_ask_size_best: 0.0,
                     best_bid: 0.0,
                     best_bid_size_best: 0.0,
                     ask_depth3_usdc: 0.0,
+                    book: OrderBook::default(),
                     ts_recv_us: 0,
                     last_tick_log_ms: 0,
                     ready: false,
                 }],
+                publish: PublishState::default(),
             },
         );
 
         let (snap_tx, snap_rx) = watch::channel::<Option<MarketSnapshot>>(None);
+        let publisher = SnapshotPublisher {
+            sink: SnapshotSink::Watch(&snap_tx),
+            cfg: ConflationConfig::default(),
+        };
         let health = HealthCounters::default();
 
-        let v = json!({
-            "event_type": "book",
-            // Mismatched market id from WS.
-            "market": "mX",
-            "asset_id": "t1",
-            "bids": [{"price": 0.49, "size": 1.0}],
-            "asks": [{"price": 0.50, "size": 2.0}],
-        });
-        let obj = v.as_object().expect("obj").clone();
+        // Mismatched market id from WS.
+        let book: BookEvent = serde_json::from_str(
+            r#"{"market": "mX", "asset_id": "t1",
+                "bids": [{"price": 0.49, "size": 1.0}], "asks": [{"price": 0.50, "size": 2.0}]}"#,
+        )
+        .expect("book");
 
         handle_ws_book(
-            obj,
+            book,
+            now_us(),
             &token_to_market,
             &mut market_states,
             &mut ticks,
-            &snap_tx,
+            &publisher,
             &health,
         )
         .expect("handle_ws_book");
@@ -1020,6 +1682,35 @@ mod tests {
         assert_eq!(snap.market_id, "m1");
         assert_eq!(snap.legs.len(), 1);
         assert_eq!(snap.legs[0].token_id, "t1");
+        assert_eq!(
+            snap.legs[0].asks.levels(),
+            [(Price::from_f64(0.50).unwrap(), Qty::from_f64(2.0).unwrap())]
+        );
+
+        // A price_change delta adds a deeper ask level to the L2 book.
+        let pc: PriceChangeEvent = serde_json::from_str(
+            r#"{"price_changes": [{"asset_id": "t1", "price": "0.52", "size": "3", "side": "SELL",
+                "best_bid": "0.49", "best_ask": "0.50"}]}"#,
+        )
+        .expect("price_change");
+        handle_ws_price_change(
+            pc,
+            now_us(),
+            &token_to_market,
+            &mut market_states,
+            &mut ticks,
+            &publisher,
+            &health,
+        )
+        .expect("handle_ws_price_change");
+        let snap = snap_rx.borrow().clone().expect("snapshot published");
+        assert_eq!(snap.legs[0].asks.levels().len(), 2);
+        assert_eq!(
+            snap.legs[0]
+                .asks
+                .qty_at_or_below(Price::from_f64(0.52).unwrap()),
+            Qty::from_f64(5.0).unwrap()
+        );
 
         // Tick row must also use mapped market_id (m1), not ws field (mX).
         let text = std::fs::read_to_string(&tmp).expect("read ticks");
@@ -1030,4 +1721,227 @@ mod tests {
         assert_eq!(cols[1], "m1");
         assert_eq!(cols[2], "t1");
     }
+
+    #[test]
+    fn raw_ws_replay_rebuilds_snapshots_on_the_frame_clock() {
+        let fixture = std::path::PathBuf::from("tests/fixtures/raw_ws_small/raw_ws.jsonl");
+        let first = std::fs::read_to_string(&fixture).expect("fixture");
+        let first: Vec<serde_json::Value> =
+            serde_json::from_str(first.lines().next().unwrap()).expect("first frame");
+        let market = MarketDef {
+            market_id: first[0]["market"].as_str().unwrap().into(),
+            token_ids: first
+                .iter()
+                .map(|e| e["asset_id"].as_str().unwrap().into())
+                .collect(),
+        };
+        let cfg: Config =
+            toml::from_str(include_str!("../tests/fixtures/replay_small/config.toml")).unwrap();
+        let ticks = std::env::temp_dir().join(format!(
+            "razor_replay_ticks_{}_{}.csv",
+            std::process::id(),
+            crate::types::now_ms()
+        ));
+
+        let snaps = replay_raw_ws(&cfg, vec![market], &[fixture], &ticks).expect("replay");
+        assert!(!snaps.is_empty());
+        assert!(snaps[0].0 >= 1_760_000_000_000);
+        assert!(snaps.windows(2).all(|w| w[0].0 <= w[1].0));
+        for (ts_ms, snap) in &snaps {
+            assert_eq!(snap.legs.len(), 2);
+            assert!(snap.legs.iter().all(|l| l.ts_recv_us / 1000 <= *ts_ms));
+        }
+        let rows = std::fs::read_to_string(&ticks)
+            .expect("ticks")
+            .lines()
+            .count();
+        assert!(rows > 1);
+        let _ = std::fs::remove_file(&ticks);
+    }
+
+    #[test]
+    fn conflation_defers_within_interval_and_skips_small_moves() {
+        let leg = |bid: f64, ask: f64| LegState {
+            token_id: "t1".into(),
+            best_ask: ask,
+            best_ask_size_best: 0.0,
+            best_bid: bid,
+            best_bid_size_best: 0.0,
+            ask_depth3_usdc: 0.0,
+            book: OrderBook::default(),
+            ts_recv_us: 0,
+            last_tick_log_ms: 0,
+            ready: true,
+        };
+        let mut state = MarketState {
+            market_id: "m1".into(),
+            legs: vec![leg(0.40, 0.50)],
+            publish: PublishState::default(),
+        };
+        let (snap_tx, snap_rx) = watch::channel::<Option<MarketSnapshot>>(None);
+        let publisher = SnapshotPublisher {
+            sink: SnapshotSink::Watch(&snap_tx),
+            cfg: ConflationConfig {
+                min_interval_ms: 100,
+                min_change_bps: 50,
+            },
+        };
+        let health = HealthCounters::default();
+
+        publisher.offer(&mut state, &health, 1_000);
+        assert!(snap_rx.has_changed().unwrap());
+
+        // 0.50 -> 0.501 is 20 bps: below the threshold, never flushed.
+        state.legs[0] = leg(0.40, 0.501);
+        publisher.offer(&mut state, &health, 1_050);
+        assert!(!state.publish.pending);
+
+        // 0.50 -> 0.49 is 200 bps but inside the interval: deferred, then flushed.
+        state.legs[0] = leg(0.40, 0.49);
+        publisher.offer(&mut state, &health, 1_060);
+        assert!(state.publish.pending);
+
+        let mut states = HashMap::from([(state.market_id.clone(), state)]);
+        publisher.flush_due(&mut states, &health, 1_080);
+        assert!(states["m1"].publish.pending);
+        publisher.flush_due(&mut states, &health, 1_100);
+        assert!(!states["m1"].publish.pending);
+        let snap = snap_rx.borrow().clone().expect("flushed snapshot");
+        assert_eq!(snap.legs[0].best_ask, Price::from_f64(0.49).unwrap());
+
+        let h = health.snapshot();
+        assert_eq!((h.snapshots_published, h.snapshots_conflated), (2, 2));
+    }
+
+    #[test]
+    fn trade_poll_adaptor_halves_to_floor_and_relaxes_back() {
+        let mut a = TradePollAdaptor::new(1_000, 300, 2, 10_000);
+        assert_eq!(a.on_limit_hit(0), None);
+        let c = a.on_limit_hit(1_000).expect("halve");
+        assert_eq!((c.from_ms, c.to_ms, c.hits_in_window), (1_000, 500, 2));
+
+        a.on_limit_hit(2_000);
+        assert_eq!(a.on_limit_hit(3_000).map(|c| c.to_ms), Some(300));
+        // At the floor: alert only, no further change.
+        a.on_limit_hit(4_000);
+        assert_eq!(a.on_limit_hit(5_000), None);
+        assert_eq!(a.interval_ms(), 300);
+
+        // Quiet for a full window: double back toward base, bounded.
+        assert_eq!(a.on_quiet_round(6_000), None);
+        assert_eq!(a.on_quiet_round(16_000).map(|c| c.to_ms), Some(600));
+        assert_eq!(a.on_quiet_round(26_000).map(|c| c.to_ms), Some(1_000));
+        assert_eq!(a.on_quiet_round(36_000), None);
+    }
+
+    #[test]
+    fn backfill_page_keeps_the_market_fills_inside_retention() {
+        let page: Vec<DataApiTrade> = serde_json::from_str(
+            r#"[
+                {"asset":"t1","conditionId":"m","size":5,"price":0.48,"timestamp":1760000010,"transactionHash":"0x3","side":"SELL"},
+                {"asset":"t1","conditionId":"other","size":5,"price":0.48,"timestamp":1760000009,"transactionHash":"0x2"},
+                {"asset":"t2","conditionId":"m","size":7,"price":0.51,"timestamp":1760000005,"transactionHash":"0x1"}
+            ]"#,
+        )
+        .unwrap();
+        let mut out = Vec::new();
+        let oldest = take_backfill_page(page, &"m".into(), 1_760_000_008_000, &mut out);
+        // The page reached past `since_ms`, so paging stops here.
+        assert_eq!(oldest, Some(1_760_000_005_000));
+        assert_eq!(out.len(), 1);
+        assert_eq!(out[0].transaction_hash, "0x3");
+        assert_eq!(out[0].side, Some(Side::Sell));
+    }
+
+    #[test]
+    fn cross_source_match_pairs_ws_and_poll_copies_one_for_one() {
+        let fill = |timestamp, transaction_hash: &str| IncomingTrade {
+            market_id: "m".into(),
+            token_id: "t".into(),
+            price: 0.48,
+            size: 30.0,
+            timestamp,
+            transaction_hash: transaction_hash.to_string(),
+            side: Some(Side::Sell),
+        };
+        let mut m = CrossSourceMatch::default();
+        let retention = 60_000;
+
+        // Two identical fills on the WS (ms, no tx hash); data-api reports them in seconds.
+        assert!(!m.is_echo(
+            TradeSource::Ws,
+            &fill(1_760_000_000_123, ""),
+            1_000,
+            retention
+        ));
+        assert!(!m.is_echo(
+            TradeSource::Ws,
+            &fill(1_760_000_000_456, ""),
+            1_000,
+            retention
+        ));
+        assert!(m.is_echo(
+            TradeSource::Poll,
+            &fill(1_760_000_000, "0xa"),
+            2_000,
+            retention
+        ));
+        assert!(m.is_echo(
+            TradeSource::Poll,
+            &fill(1_760_000_000, "0xb"),
+            2_000,
+            retention
+        ));
+        // A third copy from the poller is a fill the WS missed.
+        assert!(!m.is_echo(
+            TradeSource::Poll,
+            &fill(1_760_000_000, "0xc"),
+            2_000,
+            retention
+        ));
+        // ... and the WS delivering it late does not write it twice.
+        assert!(m.is_echo(
+            TradeSource::Ws,
+            &fill(1_760_000_000_900, ""),
+            3_000,
+            retention
+        ));
+
+        // Past retention nothing is left to pair with.
+        assert!(!m.is_echo(
+            TradeSource::Ws,
+            &fill(1_760_000_001_000, ""),
+            4_000,
+            retention
+        ));
+        assert!(!m.is_echo(
+            TradeSource::Poll,
+            &fill(1_760_000_001, ""),
+            70_000,
+            retention
+        ));
+    }
+
+    #[test]
+    fn expire_recent_ids_reports_expired_count() {
+        let mut q = std::collections::VecDeque::new();
+        let mut set = HashSet::new();
+        for (ts, id) in [(100, "a"), (200, "b"), (300, "c")] {
+            q.push_back((ts, id.to_string()));
+            set.insert(id.to_string());
+        }
+        assert_eq!(expire_recent_ids(1_250, 1_000, &mut q, &mut set), 2);
+        assert_eq!(set.len(), 1);
+        assert_eq!(expire_recent_ids(1_250, 0, &mut q, &mut set), 1);
+        assert!(set.is_empty() && q.is_empty());
+    }
+
+    #[test]
+    fn trade_poll_adaptor_disabled_when_count_zero() {
+        let mut a = TradePollAdaptor::new(1_000, 250, 0, 10_000);
+        for t in 0..10 {
+            assert_eq!(a.on_limit_hit(t), None);
+        }
+        assert_eq!(a.interval_ms(), 1_000);
+    }
 }
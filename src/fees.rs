@@ -0,0 +1,189 @@
+//! Fee model registry: the venue fee schedules the frozen accounting charges, keyed by venue and
+//! effective date. Shadow, replay and the sweeps resolve the model in force at each row's
+//! timestamp and record its id, so a run replayed after a fee change still settles old rows at
+//! the old rates. The built-in model is the Frozen Spec `FEE_POLY` / `FEE_MERGE`.
+
+use std::path::Path;
+
+use crate::config::FeesConfig;
+use crate::schema::FILE_RUN_CONFIG;
+use crate::types::{parse_rfc3339_to_unix_secs, Bps};
+
+pub const VENUE_POLYMARKET: &str = "polymarket";
+/// The Frozen Spec schedule; rows written before fee models were recorded settled with it.
+pub const BUILTIN_FEE_MODEL_ID: &str = "polymarket-v1";
+
+/// The two rates the frozen formula uses: `taker` in the `FEE_POLY` slot
+/// (`apply_cost` / `apply_proceeds` per leg) and `merge` in the `FEE_MERGE` slot.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FeeRates {
+    pub taker: Bps,
+    pub merge: Bps,
+}
+
+impl FeeRates {
+    pub const FROZEN: FeeRates = FeeRates {
+        taker: Bps::FEE_POLY,
+        merge: Bps::FEE_MERGE,
+    };
+
+    /// Fees a set pays on top of its ask cost, for the brain's net-edge gate.
+    pub fn hard_fees_bps(self) -> Bps {
+        self.taker + self.merge
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FeeModel {
+    pub id: String,
+    pub venue: String,
+    pub effective_from_ms: u64,
+    pub rates: FeeRates,
+}
+
+#[derive(Debug, Clone)]
+pub struct FeeRegistry {
+    models: Vec<FeeModel>,
+    venue: String,
+}
+
+impl FeeRegistry {
+    /// Built-in models followed by `[[fees.models]]`; a configured id replaces the built-in one.
+    pub fn from_config(cfg: &FeesConfig) -> Self {
+        let mut models = vec![FeeModel {
+            id: BUILTIN_FEE_MODEL_ID.to_string(),
+            venue: VENUE_POLYMARKET.to_string(),
+            effective_from_ms: 0,
+            rates: FeeRates::FROZEN,
+        }];
+        for m in &cfg.models {
+            // `Config::validate` rejects unparseable dates; skip rather than guess if unvalidated.
+            let Some(secs) = parse_rfc3339_to_unix_secs(m.effective_from.trim()) else {
+                continue;
+            };
+            models.retain(|b| b.id != m.id);
+            models.push(FeeModel {
+                id: m.id.clone(),
+                venue: m.venue.clone(),
+                effective_from_ms: secs * 1000,
+                rates: FeeRates {
+                    taker: Bps::new(m.taker_bps),
+                    merge: Bps::new(m.merge_bps),
+                },
+            });
+        }
+        Self {
+            models,
+            venue: cfg.venue.clone(),
+        }
+    }
+
+    /// The configured venue's model in force at `ts_ms` (latest `effective_from_ms <= ts_ms`);
+    /// the built-in model when the venue has none yet.
+    pub fn resolve(&self, ts_ms: u64) -> &FeeModel {
+        self.models
+            .iter()
+            .filter(|m| m.venue == self.venue && m.effective_from_ms <= ts_ms)
+            .max_by_key(|m| m.effective_from_ms)
+            .or_else(|| self.by_id(BUILTIN_FEE_MODEL_ID))
+            .unwrap_or(&self.models[0])
+    }
+
+    /// The model a recorded row names, e.g. to recompute a `shadow_log.csv` row at its own rates.
+    pub fn by_id(&self, id: &str) -> Option<&FeeModel> {
+        self.models.iter().find(|m| m.id == id)
+    }
+
+    /// Rates for a row's `fee_model` cell: empty (read without the column) is the built-in
+    /// model, an id this registry does not know is `None`.
+    pub fn rates_for_row(&self, fee_model: &str) -> Option<FeeRates> {
+        let id = fee_model.trim();
+        let id = if id.is_empty() {
+            BUILTIN_FEE_MODEL_ID
+        } else {
+            id
+        };
+        self.by_id(id).map(|m| m.rates)
+    }
+
+    /// The registry of the run in `dir`, from the `[fees]` of its `config.toml` snapshot; the
+    /// built-in registry when there is no snapshot or it has no usable `[fees]`.
+    pub fn for_run_dir(dir: &Path) -> Self {
+        std::fs::read_to_string(dir.join(FILE_RUN_CONFIG))
+            .ok()
+            .and_then(|raw| toml::from_str::<toml::Table>(&raw).ok())
+            .and_then(|t| t.get("fees").cloned())
+            .and_then(|v| v.try_into::<FeesConfig>().ok())
+            .map(|cfg| Self::from_config(&cfg))
+            .unwrap_or_default()
+    }
+}
+
+impl Default for FeeRegistry {
+    fn default() -> Self {
+        Self::from_config(&FeesConfig::default())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::FeeModelConfig;
+
+    fn model(id: &str, venue: &str, from: &str, taker: i32) -> FeeModelConfig {
+        FeeModelConfig {
+            id: id.to_string(),
+            venue: venue.to_string(),
+            effective_from: from.to_string(),
+            taker_bps: taker,
+            merge_bps: 10,
+        }
+    }
+
+    #[test]
+    fn resolves_the_model_in_force_at_each_timestamp() {
+        let cfg = FeesConfig {
+            venue: VENUE_POLYMARKET.to_string(),
+            models: vec![
+                model("pm-2026-11", VENUE_POLYMARKET, "2026-11-01T00:00:00Z", 150),
+                model("other-2020", "other", "2020-01-01T00:00:00Z", 50),
+            ],
+        };
+        let reg = FeeRegistry::from_config(&cfg);
+        let cutover_ms = 1_793_491_200_000;
+
+        let before = reg.resolve(cutover_ms - 1);
+        assert_eq!(before.id, BUILTIN_FEE_MODEL_ID);
+        assert_eq!(before.rates, FeeRates::FROZEN);
+        assert_eq!(before.rates.hard_fees_bps().raw(), 210);
+
+        let after = reg.resolve(cutover_ms);
+        assert_eq!(after.id, "pm-2026-11");
+        assert_eq!(after.rates.taker.raw(), 150);
+        assert_eq!(reg.by_id("other-2020").unwrap().rates.taker.raw(), 50);
+        assert!(reg.by_id("nope").is_none());
+        assert_eq!(reg.rates_for_row(""), Some(FeeRates::FROZEN));
+        assert_eq!(reg.rates_for_row("nope"), None);
+    }
+
+    #[test]
+    fn configured_id_replaces_builtin_and_unknown_venue_falls_back() {
+        let cfg = FeesConfig {
+            venue: VENUE_POLYMARKET.to_string(),
+            models: vec![model(
+                BUILTIN_FEE_MODEL_ID,
+                VENUE_POLYMARKET,
+                "1970-01-01T00:00:00Z",
+                0,
+            )],
+        };
+        let reg = FeeRegistry::from_config(&cfg);
+        assert_eq!(reg.resolve(5).rates.taker, Bps::ZERO);
+
+        let other = FeeRegistry::from_config(&FeesConfig {
+            venue: "other".to_string(),
+            models: Vec::new(),
+        });
+        assert_eq!(other.resolve(5).id, BUILTIN_FEE_MODEL_ID);
+    }
+}
@@ -0,0 +1,485 @@
+//! Cross-validation of the shadow fill model against SIM/LIVE execution: joins every sniper
+//! entry leg in `trade_log.csv` (FIRE_LEG1 plus its CHASE attempts) with the shadow ledger row
+//! of the same `(signal_id, token_id)` and compares the fill ratio shadow predicted
+//! (`q_fill / q_req`) with the one execution realized (`fill_qty / req_qty`), per bucket.
+//!
+//! The per-bucket bias and its 95% interval say whether `fill_share_p25` is too optimistic
+//! (shadow predicts more than execution gets) or too pessimistic; the implied share
+//! `fill_qty / v_mkt` of legs execution did not fill completely is what the p25 would have to be.
+
+use std::collections::{BTreeMap, HashMap};
+use std::path::{Path, PathBuf};
+
+use anyhow::Context as _;
+use serde::Serialize;
+
+use crate::market_select::metrics::quantile_f64;
+use crate::recorder::csv_input_paths;
+use crate::rows::{ShadowRow, TradeLogRow};
+use crate::schema::{
+    FILE_SHADOW_LOG, FILE_TRADE_LOG, SCHEMA_VERSION, SHADOW_HEADER, TRADE_LOG_HEADER,
+};
+use crate::schema_adapt::{FrozenCsvReader, SchemaMode};
+
+pub const FILE_FILL_XVAL_JSON: &str = "fill_xval.json";
+pub const FILE_FILL_XVAL_LEGS: &str = "fill_xval_legs.csv";
+
+pub const FILL_XVAL_LEGS_HEADER: [&str; 12] = [
+    "signal_id",
+    "market_id",
+    "token_id",
+    "bucket",
+    "phase",
+    "v_mkt",
+    "fill_share_used",
+    "predicted_fill_ratio",
+    "req_qty",
+    "fill_qty",
+    "realized_fill_ratio",
+    "error",
+];
+
+/// trade_log actions that buy into the set; FLATTEN only unwinds and says nothing about fills.
+const ENTRY_ACTIONS: [&str; 2] = ["FIRE_LEG1", "CHASE"];
+
+/// One shadow leg with the execution outcome of the same signal and token.
+#[derive(Debug, Clone, PartialEq)]
+pub struct JoinedLeg {
+    pub signal_id: u64,
+    pub market_id: String,
+    pub token_id: String,
+    /// The leg's bucket (`legN_bucket`, else the row's bucket).
+    pub bucket: String,
+    pub phase: String,
+    pub v_mkt: f64,
+    pub fill_share_used: f64,
+    pub predicted_fill_ratio: f64,
+    /// Requested by the first entry attempt of the leg.
+    pub req_qty: f64,
+    /// Summed over every entry attempt of the leg.
+    pub fill_qty: f64,
+    pub realized_fill_ratio: f64,
+}
+
+impl JoinedLeg {
+    /// Predicted minus realized fill ratio: positive when shadow is optimistic.
+    pub fn error(&self) -> f64 {
+        self.predicted_fill_ratio - self.realized_fill_ratio
+    }
+
+    /// Execution filled everything it asked for, so the realized ratio only bounds the market
+    /// from below.
+    pub fn censored(&self) -> bool {
+        self.fill_qty >= self.req_qty
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct BucketXval {
+    pub bucket: String,
+    pub samples: u64,
+    pub predicted_fill_ratio_avg: f64,
+    pub realized_fill_ratio_avg: f64,
+    /// Mean of predicted − realized.
+    pub bias: f64,
+    pub bias_ci95_low: f64,
+    pub bias_ci95_high: f64,
+    pub mae: f64,
+    pub rmse: f64,
+    pub error_p10: f64,
+    pub error_p25: f64,
+    pub error_p50: f64,
+    pub error_p75: f64,
+    pub error_p90: f64,
+    /// Median `fill_share` shadow settled these legs with.
+    pub fill_share_used: f64,
+    /// p25 of `fill_qty / v_mkt` over legs execution did not fill completely.
+    pub implied_fill_share_p25: Option<f64>,
+    pub implied_samples: u64,
+    pub censored_samples: u64,
+    /// `insufficient_samples`, `shadow_optimistic`, `shadow_pessimistic` or `consistent`.
+    pub verdict: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct FillXvalReport {
+    pub version: String,
+    pub run_id: String,
+    pub shadow_legs: u64,
+    pub execution_legs: u64,
+    pub joined_legs: u64,
+    /// Execution legs without a shadow row (signal not settled, or a different run).
+    pub unmatched_execution_legs: u64,
+    pub min_samples: u64,
+    pub buckets: Vec<BucketXval>,
+}
+
+#[derive(Debug, Clone)]
+pub struct FillXvalResult {
+    pub out_dir: PathBuf,
+    pub report: FillXvalReport,
+}
+
+pub fn run_fill_xval(
+    run_dir: &Path,
+    out_dir: &Path,
+    min_samples: u64,
+) -> anyhow::Result<FillXvalResult> {
+    std::fs::create_dir_all(out_dir).with_context(|| format!("create {}", out_dir.display()))?;
+
+    let run_id = crate::run_meta::RunMeta::read_from_dir(run_dir)
+        .map(|m| m.run_id)
+        .unwrap_or_else(|_| "unknown".to_string());
+
+    let mut shadow: Vec<ShadowRow> = Vec::new();
+    for path in csv_input_paths(run_dir, FILE_SHADOW_LOG) {
+        let mut rdr = FrozenCsvReader::open(
+            &path,
+            FILE_SHADOW_LOG,
+            &SHADOW_HEADER,
+            SchemaMode::from_env(),
+        )?;
+        for row in rdr.deserialize::<ShadowRow>() {
+            let row = row?;
+            if row.run_id == run_id && row.schema_version.eq_ignore_ascii_case(SCHEMA_VERSION) {
+                shadow.push(row);
+            }
+        }
+    }
+
+    let mut trade_log: Vec<TradeLogRow> = Vec::new();
+    for path in csv_input_paths(run_dir, FILE_TRADE_LOG) {
+        let mut rdr = FrozenCsvReader::open(
+            &path,
+            FILE_TRADE_LOG,
+            &TRADE_LOG_HEADER,
+            SchemaMode::from_env(),
+        )?;
+        for row in rdr.deserialize::<TradeLogRow>() {
+            trade_log.push(row?);
+        }
+    }
+    if trade_log.is_empty() {
+        anyhow::bail!(
+            "no {FILE_TRADE_LOG} rows in {} (cross-validation needs a live_sim run)",
+            run_dir.display()
+        );
+    }
+
+    let join = join_legs(&shadow, &trade_log);
+    let report = FillXvalReport {
+        version: "v1".to_string(),
+        run_id,
+        shadow_legs: join.shadow_legs,
+        execution_legs: join.execution_legs,
+        joined_legs: join.legs.len() as u64,
+        unmatched_execution_legs: join.unmatched_execution_legs,
+        min_samples,
+        buckets: summarize(&join.legs, min_samples),
+    };
+
+    write_legs_csv(out_dir, &join.legs).context("write fill_xval_legs.csv")?;
+    let path = out_dir.join(FILE_FILL_XVAL_JSON);
+    std::fs::write(&path, serde_json::to_vec_pretty(&report)?)
+        .with_context(|| format!("write {}", path.display()))?;
+
+    Ok(FillXvalResult {
+        out_dir: out_dir.to_path_buf(),
+        report,
+    })
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct LegJoin {
+    pub legs: Vec<JoinedLeg>,
+    pub shadow_legs: u64,
+    pub execution_legs: u64,
+    pub unmatched_execution_legs: u64,
+}
+
+struct ExecutionLeg {
+    phase: String,
+    req_qty: f64,
+    fill_qty: f64,
+}
+
+/// Joins shadow legs and execution entry legs on `(signal_id, token_id)`. Shadow rows without a
+/// positive `q_req` and execution legs without a positive first request are skipped.
+pub fn join_legs(shadow: &[ShadowRow], trade_log: &[TradeLogRow]) -> LegJoin {
+    let mut exec: HashMap<(u64, String), ExecutionLeg> = HashMap::new();
+    for r in trade_log {
+        if !ENTRY_ACTIONS.contains(&r.action.as_str()) || r.token_id.is_empty() {
+            continue;
+        }
+        let leg = exec
+            .entry((r.signal_id, r.token_id.clone()))
+            .or_insert_with(|| ExecutionLeg {
+                phase: r.phase.clone(),
+                req_qty: r.req_qty,
+                fill_qty: 0.0,
+            });
+        if r.fill_qty.is_finite() && r.fill_qty > 0.0 {
+            leg.fill_qty += r.fill_qty;
+        }
+    }
+    exec.retain(|_, l| l.req_qty.is_finite() && l.req_qty > 0.0);
+
+    let mut out = LegJoin {
+        execution_legs: exec.len() as u64,
+        ..LegJoin::default()
+    };
+    for row in shadow {
+        if !row.q_req.is_finite() || row.q_req <= 0.0 {
+            continue;
+        }
+        for i in 0..usize::from(row.legs_n).min(3) {
+            let leg = row.leg(i);
+            if leg.token_id.is_empty() {
+                continue;
+            }
+            out.shadow_legs += 1;
+            let Some(e) = exec.remove(&(row.signal_id, leg.token_id.clone())) else {
+                continue;
+            };
+            let bucket = if leg.bucket.is_empty() {
+                row.bucket.clone()
+            } else {
+                leg.bucket
+            };
+            let fill_share_used = if leg.fill_share > 0.0 {
+                leg.fill_share
+            } else {
+                row.fill_share_p25_used
+            };
+            out.legs.push(JoinedLeg {
+                signal_id: row.signal_id,
+                market_id: row.market_id.clone(),
+                token_id: leg.token_id,
+                bucket,
+                phase: e.phase,
+                v_mkt: leg.v_mkt,
+                fill_share_used,
+                predicted_fill_ratio: (leg.q_fill / row.q_req).clamp(0.0, 1.0),
+                req_qty: e.req_qty,
+                fill_qty: e.fill_qty,
+                realized_fill_ratio: (e.fill_qty / e.req_qty).clamp(0.0, 1.0),
+            });
+        }
+    }
+    out.unmatched_execution_legs = exec.len() as u64;
+    out.legs
+        .sort_by(|a, b| (a.signal_id, &a.token_id).cmp(&(b.signal_id, &b.token_id)));
+    out
+}
+
+/// Per-bucket error statistics, buckets in name order.
+pub fn summarize(legs: &[JoinedLeg], min_samples: u64) -> Vec<BucketXval> {
+    let mut by_bucket: BTreeMap<&str, Vec<&JoinedLeg>> = BTreeMap::new();
+    for l in legs {
+        by_bucket.entry(l.bucket.as_str()).or_default().push(l);
+    }
+
+    by_bucket
+        .into_iter()
+        .map(|(bucket, legs)| {
+            let n = legs.len() as f64;
+            let errors: Vec<f64> = legs.iter().map(|l| l.error()).collect();
+            let bias = errors.iter().sum::<f64>() / n;
+            let var = if legs.len() > 1 {
+                errors.iter().map(|e| (e - bias).powi(2)).sum::<f64>() / (n - 1.0)
+            } else {
+                0.0
+            };
+            let half_width = 1.96 * (var / n).sqrt();
+            let (bias_ci95_low, bias_ci95_high) = (bias - half_width, bias + half_width);
+
+            let implied: Vec<f64> = legs
+                .iter()
+                .filter(|l| !l.censored() && l.v_mkt > 0.0)
+                .map(|l| l.fill_qty / l.v_mkt)
+                .collect();
+            let shares: Vec<f64> = legs.iter().map(|l| l.fill_share_used).collect();
+            let q = |v: &[f64], p: f64| quantile_f64(v, p).unwrap_or(f64::NAN);
+
+            let verdict = if (legs.len() as u64) < min_samples {
+                "insufficient_samples"
+            } else if bias_ci95_low > 0.0 {
+                "shadow_optimistic"
+            } else if bias_ci95_high < 0.0 {
+                "shadow_pessimistic"
+            } else {
+                "consistent"
+            };
+
+            BucketXval {
+                bucket: bucket.to_string(),
+                samples: legs.len() as u64,
+                predicted_fill_ratio_avg: legs.iter().map(|l| l.predicted_fill_ratio).sum::<f64>()
+                    / n,
+                realized_fill_ratio_avg: legs.iter().map(|l| l.realized_fill_ratio).sum::<f64>()
+                    / n,
+                bias,
+                bias_ci95_low,
+                bias_ci95_high,
+                mae: errors.iter().map(|e| e.abs()).sum::<f64>() / n,
+                rmse: (errors.iter().map(|e| e * e).sum::<f64>() / n).sqrt(),
+                error_p10: q(&errors, 0.10),
+                error_p25: q(&errors, 0.25),
+                error_p50: q(&errors, 0.50),
+                error_p75: q(&errors, 0.75),
+                error_p90: q(&errors, 0.90),
+                fill_share_used: q(&shares, 0.50),
+                implied_fill_share_p25: quantile_f64(&implied, 0.25),
+                implied_samples: implied.len() as u64,
+                censored_samples: legs.iter().filter(|l| l.censored()).count() as u64,
+                verdict: verdict.to_string(),
+            }
+        })
+        .collect()
+}
+
+fn write_legs_csv(out_dir: &Path, legs: &[JoinedLeg]) -> anyhow::Result<()> {
+    let path = out_dir.join(FILE_FILL_XVAL_LEGS);
+    let mut wtr = csv::WriterBuilder::new()
+        .has_headers(false)
+        .from_path(&path)
+        .with_context(|| format!("open {}", path.display()))?;
+    wtr.write_record(FILL_XVAL_LEGS_HEADER)
+        .context("write header")?;
+    for l in legs {
+        wtr.write_record([
+            l.signal_id.to_string(),
+            l.market_id.clone(),
+            l.token_id.clone(),
+            l.bucket.clone(),
+            l.phase.clone(),
+            fmt_f64(l.v_mkt),
+            fmt_f64(l.fill_share_used),
+            fmt_f64(l.predicted_fill_ratio),
+            fmt_f64(l.req_qty),
+            fmt_f64(l.fill_qty),
+            fmt_f64(l.realized_fill_ratio),
+            fmt_f64(l.error()),
+        ])
+        .context("write row")?;
+    }
+    wtr.flush().context("flush")?;
+    Ok(())
+}
+
+fn fmt_f64(v: f64) -> String {
+    if !v.is_finite() {
+        return "NaN".to_string();
+    }
+    format!("{v:.6}")
+}
+
+#[cfg(test)]
+mod tests {
+    use assert_approx_eq::assert_approx_eq;
+
+    use super::*;
+    use crate::rows::ShadowLegCols;
+
+    fn shadow_row(signal_id: u64, legs: &[(&str, f64, f64)]) -> ShadowRow {
+        let mut row = ShadowRow {
+            signal_id,
+            bucket: "liquid".to_string(),
+            q_req: 10.0,
+            legs_n: legs.len() as u8,
+            fill_share_p25_used: 0.3,
+            ..ShadowRow::default()
+        };
+        for (i, (token_id, v_mkt, q_fill)) in legs.iter().enumerate() {
+            row.set_leg(
+                i,
+                ShadowLegCols {
+                    token_id: token_id.to_string(),
+                    v_mkt: *v_mkt,
+                    q_fill: *q_fill,
+                    ..ShadowLegCols::default()
+                },
+            );
+        }
+        row
+    }
+
+    fn exec_row(signal_id: u64, action: &str, token_id: &str, req: f64, fill: f64) -> TradeLogRow {
+        TradeLogRow {
+            ts_ms: 0,
+            signal_id,
+            market_id: "m".to_string(),
+            strategy: "binary".to_string(),
+            bucket: "liquid".to_string(),
+            phase: "SIM".to_string(),
+            action: action.to_string(),
+            leg_index: 0,
+            token_id: token_id.to_string(),
+            side: "BUY".to_string(),
+            limit_price: 0.5,
+            req_qty: req,
+            fill_qty: fill,
+            fill_status: "partial".to_string(),
+            expected_net_bps: 0,
+            notes: String::new(),
+        }
+    }
+
+    #[test]
+    fn joins_entry_attempts_per_leg_and_skips_flatten() {
+        let shadow = vec![shadow_row(1, &[("A", 40.0, 10.0), ("B", 20.0, 6.0)])];
+        let log = vec![
+            exec_row(1, "FIRE_LEG1", "A", 10.0, 4.0),
+            exec_row(1, "CHASE", "B", 4.0, 1.0),
+            exec_row(1, "CHASE", "B", 3.0, 1.0),
+            exec_row(1, "FLATTEN", "A", 2.0, 2.0),
+            exec_row(9, "FIRE_LEG1", "Z", 10.0, 10.0),
+        ];
+        let join = join_legs(&shadow, &log);
+        assert_eq!(
+            (join.shadow_legs, join.execution_legs, join.legs.len()),
+            (2, 3, 2)
+        );
+        assert_eq!(join.unmatched_execution_legs, 1);
+
+        let a = &join.legs[0];
+        assert_eq!(a.bucket, "liquid");
+        assert_approx_eq!(a.fill_share_used, 0.3, 1e-12);
+        assert_approx_eq!(a.error(), 1.0 - 0.4, 1e-12);
+        let b = &join.legs[1];
+        assert_approx_eq!(b.realized_fill_ratio, 2.0 / 4.0, 1e-12);
+        assert_approx_eq!(b.predicted_fill_ratio, 0.6, 1e-12);
+    }
+
+    #[test]
+    fn verdict_follows_the_bias_interval() {
+        let leg = |predicted: f64, realized: f64| JoinedLeg {
+            signal_id: 0,
+            market_id: "m".to_string(),
+            token_id: "A".to_string(),
+            bucket: "thin".to_string(),
+            phase: "SIM".to_string(),
+            v_mkt: 100.0,
+            fill_share_used: 0.1,
+            predicted_fill_ratio: predicted,
+            req_qty: 10.0,
+            fill_qty: realized * 10.0,
+            realized_fill_ratio: realized,
+        };
+        let optimistic: Vec<JoinedLeg> = (0..40)
+            .map(|i| leg(0.8, 0.3 + (i % 5) as f64 / 100.0))
+            .collect();
+        let s = &summarize(&optimistic, 30)[0];
+        assert_eq!(s.verdict, "shadow_optimistic");
+        assert!(s.bias_ci95_low > 0.0);
+        assert_eq!(s.censored_samples, 0);
+        // 40 legs filled 3.0..3.4 of v_mkt 100.
+        assert_approx_eq!(s.implied_fill_share_p25.unwrap(), 0.031, 1e-12);
+
+        let mixed: Vec<JoinedLeg> = (0..40)
+            .map(|i| leg(0.5, if i % 2 == 0 { 0.4 } else { 0.6 }))
+            .collect();
+        assert_eq!(summarize(&mixed, 30)[0].verdict, "consistent");
+        assert_eq!(summarize(&mixed, 50)[0].verdict, "insufficient_samples");
+    }
+}
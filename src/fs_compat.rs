@@ -0,0 +1,165 @@
+//! Filesystem operations whose behaviour differs between Unix and Windows collectors: file
+//! aliases, free disk space and path length. Each function picks the closest equivalent per
+//! platform and says which one it used, so a Windows run doesn't silently degrade.
+
+use std::path::Path;
+
+/// Legacy Win32 `MAX_PATH` (including the terminating NUL). Razor itself is not bound by it (std
+/// adds the `\\?\` prefix), but Explorer, `mklink`, Excel and the duckdb CLI are unless
+/// `LongPathsEnabled` is set.
+pub const WINDOWS_MAX_PATH: usize = 260;
+
+/// Longest path razor writes below a run dir (hourly partitions, replay/migrate outputs, rotated
+/// raw_ws segments), with headroom.
+pub const RUN_DIR_MAX_REL_PATH: usize = 64;
+
+/// How [`alias_file`] made `dst` available.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FileAlias {
+    Symlink,
+    HardLink,
+    Copy,
+}
+
+impl FileAlias {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            FileAlias::Symlink => "symlink",
+            FileAlias::HardLink => "hard_link",
+            FileAlias::Copy => "copy",
+        }
+    }
+}
+
+/// Makes `dst` (in the same directory as `src`) show `src`'s contents: a relative symlink, else a
+/// hard link (Windows without Developer Mode, filesystems without symlinks), else a copy. An
+/// existing `dst` file is replaced.
+pub fn alias_file(src: &Path, dst: &Path) -> std::io::Result<FileAlias> {
+    if std::fs::symlink_metadata(dst).is_ok() {
+        std::fs::remove_file(dst)?;
+    }
+    let target = src.file_name().map(Path::new).unwrap_or(src);
+    #[cfg(unix)]
+    if std::os::unix::fs::symlink(target, dst).is_ok() {
+        return Ok(FileAlias::Symlink);
+    }
+    #[cfg(windows)]
+    if std::os::windows::fs::symlink_file(target, dst).is_ok() {
+        return Ok(FileAlias::Symlink);
+    }
+    #[cfg(not(any(unix, windows)))]
+    let _ = target;
+    if std::fs::hard_link(src, dst).is_ok() {
+        return Ok(FileAlias::HardLink);
+    }
+    std::fs::copy(src, dst)?;
+    Ok(FileAlias::Copy)
+}
+
+/// Bytes available to this process on the filesystem holding `path`, or `None` when the
+/// platform query fails (the disk guard then stays at `ok`).
+#[cfg(unix)]
+#[allow(clippy::unnecessary_cast)]
+pub fn available_bytes(path: &Path) -> Option<u64> {
+    use std::ffi::CString;
+    use std::os::unix::ffi::OsStrExt as _;
+
+    let c_path = CString::new(path.as_os_str().as_bytes()).ok()?;
+    // SAFETY: `statvfs` is plain-old-data; zeroed is a valid initial value and libc fills it in.
+    let mut st: libc::statvfs = unsafe { std::mem::zeroed() };
+    // SAFETY: `c_path` is a valid NUL-terminated string and `st` is a valid out pointer.
+    let rc = unsafe { libc::statvfs(c_path.as_ptr(), &mut st) };
+    if rc != 0 {
+        return None;
+    }
+    Some((st.f_bavail as u64).saturating_mul(st.f_frsize as u64))
+}
+
+#[cfg(windows)]
+pub fn available_bytes(path: &Path) -> Option<u64> {
+    use std::os::windows::ffi::OsStrExt as _;
+
+    #[link(name = "kernel32")]
+    extern "system" {
+        fn GetDiskFreeSpaceExW(
+            directory: *const u16,
+            free_bytes_available_to_caller: *mut u64,
+            total_bytes: *mut u64,
+            total_free_bytes: *mut u64,
+        ) -> i32;
+    }
+
+    let wide: Vec<u16> = path
+        .as_os_str()
+        .encode_wide()
+        .chain(std::iter::once(0))
+        .collect();
+    let mut free = 0u64;
+    // SAFETY: `wide` is NUL-terminated UTF-16; the two unused out pointers may be null.
+    let ok = unsafe {
+        GetDiskFreeSpaceExW(
+            wide.as_ptr(),
+            &mut free,
+            std::ptr::null_mut(),
+            std::ptr::null_mut(),
+        )
+    };
+    (ok != 0).then_some(free)
+}
+
+#[cfg(not(any(unix, windows)))]
+pub fn available_bytes(path: &Path) -> Option<u64> {
+    let _ = path;
+    None
+}
+
+/// Whether files up to [`RUN_DIR_MAX_REL_PATH`] below `dir` can exceed [`WINDOWS_MAX_PATH`].
+pub fn exceeds_windows_max_path(dir: &Path) -> bool {
+    let len = std::path::absolute(dir)
+        .map(|p| p.as_os_str().len())
+        .unwrap_or_else(|_| dir.as_os_str().len());
+    len + 1 + RUN_DIR_MAX_REL_PATH >= WINDOWS_MAX_PATH
+}
+
+/// On Windows, warns when `run_dir` is deep enough that some of its files pass `MAX_PATH`.
+pub fn warn_if_long_path(run_dir: &Path) {
+    if cfg!(windows) && exceeds_windows_max_path(run_dir) {
+        tracing::warn!(
+            run_dir = %run_dir.display(),
+            "run dir path is long: some files will exceed the {WINDOWS_MAX_PATH}-char MAX_PATH \
+             that Explorer/mklink/duckdb still enforce; use a shorter run.data_dir or enable \
+             LongPathsEnabled"
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn alias_file_replaces_dst_and_reads_through() {
+        let dir = std::env::temp_dir().join(format!("razor_fs_compat_{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let src = dir.join("replay_shadow_log.csv");
+        let dst = dir.join("shadow_log.csv");
+        std::fs::write(&src, "a,b\n1,2\n").unwrap();
+        std::fs::write(&dst, "stale").unwrap();
+
+        let how = alias_file(&src, &dst).unwrap();
+        assert_eq!(std::fs::read_to_string(&dst).unwrap(), "a,b\n1,2\n");
+        if cfg!(unix) {
+            assert_eq!(how, FileAlias::Symlink);
+        }
+        alias_file(&src, &dst).unwrap();
+        assert_eq!(std::fs::read_to_string(&dst).unwrap(), "a,b\n1,2\n");
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn long_run_dirs_are_flagged() {
+        assert!(!exceeds_windows_max_path(Path::new("data/run_1")));
+        let deep = Path::new("data").join("x".repeat(WINDOWS_MAX_PATH));
+        assert!(exceeds_windows_max_path(&deep));
+    }
+}
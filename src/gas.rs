@@ -0,0 +1,217 @@
+//! Gas model for the on-chain transactions that close out sniper positions: `mergePositions`
+//! for a completed set and `redeemPositions` for whatever is still held at resolution. CLOB
+//! fills themselves are gasless. The sniper stamps the merge cost on its `MERGE` row; the
+//! report charges merges (recorded or static) and redeems (static) in the execution PnL.
+
+use std::path::Path;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+use anyhow::Context as _;
+use tokio::sync::watch;
+use tracing::{info, warn};
+
+use crate::api_usage::{self, ApiEndpoint};
+use crate::config::GasConfig;
+use crate::schema::FILE_RUN_CONFIG;
+
+const GWEI_PER_NATIVE: f64 = 1e9;
+const WEI_PER_GWEI: f64 = 1e9;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GasSource {
+    Static,
+    Oracle,
+}
+
+impl GasSource {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            GasSource::Static => "static",
+            GasSource::Oracle => "oracle",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct GasQuote {
+    pub gas_price_gwei: f64,
+    pub source: GasSource,
+}
+
+impl GasQuote {
+    pub fn fixed(cfg: &GasConfig) -> Self {
+        Self {
+            gas_price_gwei: cfg.gas_price_gwei,
+            source: GasSource::Static,
+        }
+    }
+}
+
+/// USDC cost of a transaction using `units` gas at `quote`.
+pub fn tx_cost_usdc(cfg: &GasConfig, units: u64, quote: GasQuote) -> f64 {
+    units as f64 * quote.gas_price_gwei / GWEI_PER_NATIVE * cfg.native_usd
+}
+
+pub fn merge_cost_usdc(cfg: &GasConfig, quote: GasQuote) -> f64 {
+    tx_cost_usdc(cfg, cfg.merge_gas_units, quote)
+}
+
+pub fn redeem_cost_usdc(cfg: &GasConfig, quote: GasQuote) -> f64 {
+    tx_cost_usdc(cfg, cfg.redeem_gas_units, quote)
+}
+
+/// `notes` of the sniper's `MERGE` row; [`parse_gas_usdc`] reads the cost back.
+pub fn merge_notes(q_set: f64, cost_usdc: f64, quote: GasQuote) -> String {
+    format!(
+        "merge_sets={q_set}|gas_usdc={cost_usdc:.6}|gas_price_gwei={:.3}|gas_source={}",
+        quote.gas_price_gwei,
+        quote.source.as_str()
+    )
+}
+
+pub fn parse_gas_usdc(notes: &str) -> Option<f64> {
+    notes
+        .split('|')
+        .find_map(|kv| kv.strip_prefix("gas_usdc="))
+        .and_then(|v| v.trim().parse::<f64>().ok())
+        .filter(|v| v.is_finite() && *v >= 0.0)
+}
+
+/// `[gas]` of the run in `dir` from its `config.toml` snapshot; defaults when absent.
+pub fn config_for_run_dir(dir: &Path) -> GasConfig {
+    std::fs::read_to_string(dir.join(FILE_RUN_CONFIG))
+        .ok()
+        .and_then(|raw| toml::from_str::<toml::Table>(&raw).ok())
+        .and_then(|t| t.get("gas").cloned())
+        .and_then(|v| v.try_into::<GasConfig>().ok())
+        .unwrap_or_default()
+}
+
+/// Latest oracle gas price shared with the sniper; empty until the first successful poll.
+#[derive(Debug, Clone, Default)]
+pub struct GasPriceFeed(Arc<AtomicU64>);
+
+impl GasPriceFeed {
+    pub fn set(&self, gwei: f64) {
+        self.0.store(gwei.to_bits(), Ordering::Relaxed);
+    }
+
+    /// The oracle reading if there is one, else the static `gas_price_gwei`.
+    pub fn quote(&self, cfg: &GasConfig) -> GasQuote {
+        match self.0.load(Ordering::Relaxed) {
+            0 => GasQuote::fixed(cfg),
+            bits => GasQuote {
+                gas_price_gwei: f64::from_bits(bits),
+                source: GasSource::Oracle,
+            },
+        }
+    }
+}
+
+fn parse_wei_hex(s: &str) -> anyhow::Result<f64> {
+    let hex = s.trim().trim_start_matches("0x");
+    let wei = u128::from_str_radix(if hex.is_empty() { "0" } else { hex }, 16)
+        .with_context(|| format!("bad gas price {s:?}"))?;
+    Ok(wei as f64 / WEI_PER_GWEI)
+}
+
+async fn eth_gas_price(http: &reqwest::Client, url: &str) -> anyhow::Result<f64> {
+    let body = serde_json::json!({
+        "jsonrpc": "2.0",
+        "id": 1,
+        "method": "eth_gasPrice",
+        "params": [],
+    });
+    let resp = api_usage::track(
+        ApiEndpoint::PolygonRpc,
+        http.post(url).json(&body).send().await,
+    )
+    .context("polygon rpc request")?
+    .error_for_status()
+    .context("polygon rpc status")?;
+    let v: serde_json::Value = resp.json().await.context("polygon rpc body")?;
+    if let Some(err) = v.get("error") {
+        anyhow::bail!("polygon rpc error: {err}");
+    }
+    let result = v
+        .get("result")
+        .and_then(|r| r.as_str())
+        .context("polygon rpc: missing result")?;
+    parse_wei_hex(result)
+}
+
+/// Polls `eth_gasPrice` into `feed` until shutdown; returns at once unless `oracle_enabled`.
+/// Failed polls keep the previous reading.
+pub async fn run_oracle(
+    cfg: GasConfig,
+    http_connect_timeout_ms: u64,
+    http_timeout_ms: u64,
+    feed: GasPriceFeed,
+    mut shutdown: watch::Receiver<bool>,
+) -> anyhow::Result<()> {
+    if !cfg.oracle_enabled {
+        return Ok(());
+    }
+    let http = reqwest::Client::builder()
+        .user_agent(concat!("razor/", env!("CARGO_PKG_VERSION")))
+        .connect_timeout(Duration::from_millis(http_connect_timeout_ms))
+        .timeout(Duration::from_millis(http_timeout_ms))
+        .build()
+        .context("build gas oracle http client")?;
+    info!(interval_ms = cfg.oracle_interval_ms, "gas oracle started");
+
+    let mut tick = tokio::time::interval(Duration::from_millis(cfg.oracle_interval_ms));
+    tick.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+    loop {
+        tokio::select! {
+            _ = tick.tick() => {}
+            _ = shutdown.changed() => {
+                if *shutdown.borrow() { break; }
+                continue;
+            }
+        }
+        match eth_gas_price(&http, &cfg.rpc_url).await {
+            Ok(gwei) if gwei > 0.0 => feed.set(gwei),
+            Ok(_) => warn!("gas oracle returned 0; keeping previous price"),
+            Err(e) => warn!(error = %format!("{e:#}"), "gas oracle poll failed"),
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn costs_merge_and_redeem_and_round_trips_notes() {
+        let cfg = GasConfig::default();
+        let quote = GasQuote::fixed(&cfg);
+        // 200k gas * 50 gwei = 0.01 POL at 0.40 USDC.
+        let merge = merge_cost_usdc(&cfg, quote);
+        assert!((merge - 0.004).abs() < 1e-12, "{merge}");
+        assert!((redeem_cost_usdc(&cfg, quote) - 0.003).abs() < 1e-12);
+
+        let notes = merge_notes(2.0, merge, quote);
+        assert_eq!(
+            notes,
+            "merge_sets=2|gas_usdc=0.004000|gas_price_gwei=50.000|gas_source=static"
+        );
+        assert_eq!(parse_gas_usdc(&notes), Some(0.004));
+        assert_eq!(parse_gas_usdc("attempt=1|order_id=x"), None);
+    }
+
+    #[test]
+    fn feed_prefers_oracle_reading_over_static_price() {
+        let cfg = GasConfig::default();
+        let feed = GasPriceFeed::default();
+        assert_eq!(feed.quote(&cfg), GasQuote::fixed(&cfg));
+
+        feed.set(parse_wei_hex("0x1bf08eb000").unwrap());
+        let q = feed.quote(&cfg);
+        assert_eq!(q.source, GasSource::Oracle);
+        assert!((q.gas_price_gwei - 120.0).abs() < 1e-9);
+    }
+}
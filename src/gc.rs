@@ -0,0 +1,368 @@
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
+
+use anyhow::Context as _;
+
+use crate::archive::pack_run_dir;
+use crate::manifest::verify_run_dir;
+use crate::run_context::{latest_run_dir, point_run_latest, read_latest_json, RUN_LATEST_LINK};
+use crate::schema::{
+    FILE_LATEST_JSON, FILE_MANIFEST_JSON, FILE_RAW_WS_JSONL, FILE_REPORT_HTML, FILE_REPORT_JSON,
+    FILE_REPORT_MD, FILE_RUN_CONFIG, FILE_RUN_META_JSON, FILE_RUN_README, FILE_SCHEMA_VERSION,
+    FILE_SHADOW_LOG,
+};
+use crate::types::now_ms;
+
+/// Left in a pruned run dir; lists what gc removed so the skeleton isn't mistaken for a
+/// truncated run.
+pub const FILE_GC_PRUNED: &str = "PRUNED";
+
+/// Files a pruned run keeps: the report, the shadow ledger and what is needed to identify it.
+const ALWAYS_KEEP: [&str; 12] = [
+    FILE_RUN_README,
+    FILE_REPORT_JSON,
+    FILE_REPORT_MD,
+    FILE_REPORT_HTML,
+    FILE_SHADOW_LOG,
+    "shadow_log.csv.meta.json",
+    FILE_RUN_CONFIG,
+    FILE_RUN_META_JSON,
+    FILE_SCHEMA_VERSION,
+    FILE_MANIFEST_JSON,
+    "LATEST",
+    FILE_GC_PRUNED,
+];
+
+#[derive(Debug, Clone, Copy)]
+pub struct GcPolicy {
+    /// Newest runs left untouched (`0` keeps all).
+    pub keep_runs: usize,
+    /// Delete `raw_ws.jsonl` (and rotated segments) last modified longer ago (`0` disables).
+    pub raw_ws_max_age_days: u64,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GcAction {
+    pub path: PathBuf,
+    pub bytes: u64,
+    pub reason: &'static str,
+}
+
+/// `run_*` dirs under `data_dir`, oldest first (`runs_index.jsonl` start time, else the run id's).
+fn list_runs(data_dir: &Path) -> anyhow::Result<Vec<PathBuf>> {
+    let mut runs = Vec::new();
+    for entry in
+        std::fs::read_dir(data_dir).with_context(|| format!("read {}", data_dir.display()))?
+    {
+        let entry = entry?;
+        let name = entry.file_name().to_string_lossy().to_string();
+        if name.starts_with("run_") && name != RUN_LATEST_LINK && entry.file_type()?.is_dir() {
+            runs.push(entry.path());
+        }
+    }
+    crate::run_index::sort_run_dirs(data_dir, &mut runs);
+    Ok(runs)
+}
+
+fn latest_run_name(data_dir: &Path) -> Option<String> {
+    let target = latest_run_dir(data_dir)?;
+    Some(target.file_name()?.to_string_lossy().to_string())
+}
+
+fn disk_usage(path: &Path) -> u64 {
+    let Ok(meta) = std::fs::symlink_metadata(path) else {
+        return 0;
+    };
+    if !meta.is_dir() {
+        return meta.len();
+    }
+    std::fs::read_dir(path)
+        .into_iter()
+        .flatten()
+        .flatten()
+        .map(|e| disk_usage(&e.path()))
+        .sum()
+}
+
+fn is_raw_ws(name: &str) -> bool {
+    name == FILE_RAW_WS_JSONL || name.starts_with(&format!("{FILE_RAW_WS_JSONL}.rotated_"))
+}
+
+/// What `policy` would remove under `data_dir`. The run `run_latest` points at is never touched
+/// (it may still be recording).
+pub fn plan_gc(
+    data_dir: &Path,
+    policy: GcPolicy,
+    now: SystemTime,
+) -> anyhow::Result<Vec<GcAction>> {
+    let runs = list_runs(data_dir)?;
+    let latest = latest_run_name(data_dir);
+    let prune_before = if policy.keep_runs == 0 {
+        0
+    } else {
+        runs.len().saturating_sub(policy.keep_runs)
+    };
+    let raw_ws_cutoff = (policy.raw_ws_max_age_days > 0)
+        .then(|| now.checked_sub(Duration::from_secs(policy.raw_ws_max_age_days * 86_400)))
+        .flatten();
+
+    let mut actions = Vec::new();
+    for (i, run) in runs.iter().enumerate() {
+        let name = run.file_name().map(|n| n.to_string_lossy().to_string());
+        if name.is_some() && name == latest {
+            continue;
+        }
+        let prune = i < prune_before;
+        let mut entries: Vec<_> = std::fs::read_dir(run)
+            .with_context(|| format!("read {}", run.display()))?
+            .collect::<Result<_, _>>()?;
+        entries.sort_by_key(|e| e.file_name());
+        for entry in entries {
+            let file_name = entry.file_name().to_string_lossy().to_string();
+            let path = entry.path();
+            if prune {
+                if !ALWAYS_KEEP.contains(&file_name.as_str()) {
+                    actions.push(GcAction {
+                        bytes: disk_usage(&path),
+                        path,
+                        reason: "run_pruned",
+                    });
+                }
+                continue;
+            }
+            let Some(cutoff) = raw_ws_cutoff else {
+                continue;
+            };
+            if !is_raw_ws(&file_name) {
+                continue;
+            }
+            let meta = entry.metadata()?;
+            if meta.modified().is_ok_and(|m| m < cutoff) {
+                actions.push(GcAction {
+                    path,
+                    bytes: meta.len(),
+                    reason: "raw_ws_expired",
+                });
+            }
+        }
+    }
+    Ok(actions)
+}
+
+/// `razor gc`: applies `policy` (or only prints it with `dry_run`). Pruned runs get a `PRUNED`
+/// marker listing the removed entries, and a dangling `run_latest` is repointed at the newest
+/// remaining run.
+pub fn run_gc(data_dir: &Path, policy: GcPolicy, dry_run: bool) -> anyhow::Result<()> {
+    anyhow::ensure!(
+        data_dir.is_dir(),
+        "data dir not found: {}",
+        data_dir.display()
+    );
+    let actions = plan_gc(data_dir, policy, SystemTime::now())?;
+    let total_bytes: u64 = actions.iter().map(|a| a.bytes).sum();
+    for a in &actions {
+        let verb = if dry_run { "would remove" } else { "remove" };
+        println!(
+            "{verb} {} ({} bytes, {})",
+            a.path.display(),
+            a.bytes,
+            a.reason
+        );
+    }
+    println!(
+        "gc: entries={} bytes={total_bytes} dry_run={dry_run}",
+        actions.len()
+    );
+    if dry_run {
+        return Ok(());
+    }
+
+    let mut pruned_runs: Vec<(&Path, Vec<String>)> = Vec::new();
+    for a in &actions {
+        let meta = std::fs::symlink_metadata(&a.path)?;
+        if meta.is_dir() {
+            std::fs::remove_dir_all(&a.path)
+        } else {
+            std::fs::remove_file(&a.path)
+        }
+        .with_context(|| format!("remove {}", a.path.display()))?;
+
+        if a.reason == "run_pruned" {
+            let (Some(run), Some(name)) = (a.path.parent(), a.path.file_name()) else {
+                continue;
+            };
+            let name = name.to_string_lossy().to_string();
+            match pruned_runs.iter_mut().find(|(r, _)| *r == run) {
+                Some((_, names)) => names.push(name),
+                None => pruned_runs.push((run, vec![name])),
+            }
+        }
+    }
+    for (run, names) in pruned_runs {
+        let marker = run.join(FILE_GC_PRUNED);
+        let mut body = format!("pruned_ts_ms={}\n", now_ms());
+        for n in names {
+            body.push_str(&n);
+            body.push('\n');
+        }
+        std::fs::write(&marker, body).with_context(|| format!("write {}", marker.display()))?;
+    }
+
+    repoint_dangling_run_latest(data_dir)
+}
+
+/// `razor archive <run_dir>`: checks the run against its manifest, packs it into
+/// `<run_dir>.tar.zst` (re-verifying every manifest checksum inside the archive), records it in
+/// `archive_index.jsonl` and removes the dir. A run still being recorded is refused.
+pub fn run_archive(run_dir: &Path, level: i32) -> anyhow::Result<()> {
+    anyhow::ensure!(run_dir.is_dir(), "run dir not found: {}", run_dir.display());
+    let data_dir = run_dir
+        .parent()
+        .filter(|p| !p.as_os_str().is_empty())
+        .unwrap_or(Path::new("."));
+    let name = run_dir.file_name().map(|n| n.to_string_lossy().to_string());
+    if name.is_some() && name == latest_run_name(data_dir) {
+        let complete = read_latest_json(data_dir).is_some_and(|p| p.complete);
+        anyhow::ensure!(
+            complete,
+            "{} is the latest run and not marked complete; refusing to archive",
+            run_dir.display()
+        );
+    }
+
+    let (manifest, issues) = verify_run_dir(run_dir)
+        .with_context(|| format!("{} has no usable manifest", run_dir.display()))?;
+    for issue in &issues {
+        println!("{issue}");
+    }
+    anyhow::ensure!(
+        issues.is_empty(),
+        "{} integrity issue(s) in {}; not archiving",
+        issues.len(),
+        run_dir.display()
+    );
+    let expected: BTreeMap<String, String> = manifest
+        .artifacts
+        .into_iter()
+        .filter(|a| run_dir.join(&a.path).is_file())
+        .map(|a| (a.path, a.sha256))
+        .collect();
+
+    let entry = pack_run_dir(run_dir, level, &expected)?;
+    std::fs::remove_dir_all(run_dir).with_context(|| format!("remove {}", run_dir.display()))?;
+    println!(
+        "archive: run_id={} files={} bytes_raw={} bytes_archive={} -> {}",
+        entry.run_id,
+        entry.files,
+        entry.bytes_raw,
+        entry.bytes_archive,
+        data_dir.join(&entry.archive).display()
+    );
+    repoint_dangling_run_latest(data_dir)
+}
+
+/// Repoints `run_latest` / `latest.json` at the newest run when the run they name was removed.
+fn repoint_dangling_run_latest(data_dir: &Path) -> anyhow::Result<()> {
+    let link = data_dir.join(RUN_LATEST_LINK);
+    let link_dangling = std::fs::symlink_metadata(&link).is_ok() && !link.exists();
+    let pointer_dangling =
+        read_latest_json(data_dir).is_some_and(|p| !data_dir.join(p.run_dir).is_dir());
+    if !(link_dangling || pointer_dangling) {
+        return Ok(());
+    }
+    match list_runs(data_dir)?.pop() {
+        Some(newest) => {
+            point_run_latest(data_dir, &newest)
+                .with_context(|| format!("repoint {}", link.display()))?;
+            println!(
+                "run_latest -> {}",
+                newest.strip_prefix(data_dir).unwrap_or(&newest).display()
+            );
+        }
+        None => {
+            // A dangling link is a file on Unix and a directory entry on Windows.
+            let _ = std::fs::remove_file(&link).or_else(|_| std::fs::remove_dir(&link));
+            let _ = std::fs::remove_file(data_dir.join(FILE_LATEST_JSON));
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn prunes_old_runs_to_skeleton_and_expires_raw_ws() {
+        let data = std::env::temp_dir().join(format!("razor_gc_{}", now_ms()));
+        for run in ["run_20260101_000000_000001", "run_20260102_000000_000002"] {
+            let dir = data.join(run);
+            std::fs::create_dir_all(dir.join("2026-01-01/00")).unwrap();
+            for f in [
+                FILE_REPORT_JSON,
+                FILE_SHADOW_LOG,
+                FILE_RAW_WS_JSONL,
+                "ticks.csv",
+            ] {
+                std::fs::write(dir.join(f), "x").unwrap();
+            }
+        }
+        let old = SystemTime::now() - Duration::from_secs(10 * 86_400);
+        std::fs::File::options()
+            .write(true)
+            .open(
+                data.join("run_20260102_000000_000002")
+                    .join(FILE_RAW_WS_JSONL),
+            )
+            .unwrap()
+            .set_modified(old)
+            .unwrap();
+
+        let policy = GcPolicy {
+            keep_runs: 1,
+            raw_ws_max_age_days: 7,
+        };
+        let actions = plan_gc(&data, policy, SystemTime::now()).unwrap();
+        let got: Vec<(String, &str)> = actions
+            .iter()
+            .map(|a| {
+                let rel = a.path.strip_prefix(&data).unwrap();
+                (rel.display().to_string(), a.reason)
+            })
+            .collect();
+        assert_eq!(
+            got,
+            vec![
+                (
+                    "run_20260101_000000_000001/2026-01-01".to_string(),
+                    "run_pruned"
+                ),
+                (
+                    "run_20260101_000000_000001/raw_ws.jsonl".to_string(),
+                    "run_pruned"
+                ),
+                (
+                    "run_20260101_000000_000001/ticks.csv".to_string(),
+                    "run_pruned"
+                ),
+                (
+                    "run_20260102_000000_000002/raw_ws.jsonl".to_string(),
+                    "raw_ws_expired"
+                ),
+            ]
+        );
+
+        run_gc(&data, policy, false).unwrap();
+        let pruned = data.join("run_20260101_000000_000001");
+        assert!(pruned.join(FILE_SHADOW_LOG).exists());
+        assert!(!pruned.join("ticks.csv").exists());
+        assert!(std::fs::read_to_string(pruned.join(FILE_GC_PRUNED))
+            .unwrap()
+            .contains("ticks.csv\n"));
+        assert!(plan_gc(&data, policy, SystemTime::now())
+            .unwrap()
+            .is_empty());
+        let _ = std::fs::remove_dir_all(&data);
+    }
+}
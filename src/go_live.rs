@@ -0,0 +1,226 @@
+//! Go-live approval: a GO verdict (end-of-run report, `razor report`) or a manual `razor approve`
+//! writes `<data_dir>/go_live_token.json`, HMAC-signed with the `live.approval_key_env` secret,
+//! naming the approved config hash and an expiry. The live gateway's `go_live_token` interlock
+//! refuses to start unless a valid token matches the current config.
+
+use std::path::Path;
+
+use anyhow::Context as _;
+use hmac::Mac as _;
+use serde::{Deserialize, Serialize};
+use sha2::Sha256;
+
+use crate::config::Config;
+use crate::schema::{FILE_GO_LIVE_TOKEN, FILE_REPORT_JSON, FILE_RUN_CONFIG};
+
+type HmacSha256 = hmac::Hmac<Sha256>;
+
+pub const TOKEN_VERSION: &str = "v1";
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct GoLiveToken {
+    pub version: String,
+    /// The run whose GO verdict is approved.
+    pub run_id: String,
+    /// [`approval_config_sha256`] of that run's config.
+    pub config_sha256: String,
+    /// `report` (end of run), `day14` (`razor report`) or `manual:<who>` (`razor approve`).
+    pub approved_by: String,
+    pub manual: bool,
+    pub issued_ts_ms: u64,
+    pub expires_ts_ms: u64,
+    /// Hex HMAC-SHA256 over the token's JSON with this field empty.
+    pub signature: String,
+}
+
+/// Hash of `cfg` as a live run would use it: the effective config hash with `live.enabled`
+/// cleared, so the GO run (SIM) and the live run it approves hash alike.
+pub fn approval_config_sha256(cfg: &Config) -> anyhow::Result<String> {
+    let mut cfg = cfg.clone();
+    cfg.live.enabled = false;
+    crate::run_meta::config_sha256(&cfg)
+}
+
+fn sign(token: &GoLiveToken, key: &str) -> anyhow::Result<String> {
+    let mut unsigned = token.clone();
+    unsigned.signature.clear();
+    let mut mac = HmacSha256::new_from_slice(key.as_bytes()).context("approval key")?;
+    mac.update(&serde_json::to_vec(&unsigned)?);
+    Ok(hex::encode(mac.finalize().into_bytes()))
+}
+
+/// Signs and writes a token approving `cfg` on the strength of `run_id`'s GO verdict.
+pub fn issue(
+    data_dir: &Path,
+    cfg: &Config,
+    run_id: &str,
+    approved_by: &str,
+    manual: bool,
+    key: &str,
+    now_ms: u64,
+) -> anyhow::Result<GoLiveToken> {
+    let mut token = GoLiveToken {
+        version: TOKEN_VERSION.to_string(),
+        run_id: run_id.to_string(),
+        config_sha256: approval_config_sha256(cfg)?,
+        approved_by: approved_by.to_string(),
+        manual,
+        issued_ts_ms: now_ms,
+        expires_ts_ms: now_ms.saturating_add(cfg.live.approval_ttl_ms),
+        signature: String::new(),
+    };
+    token.signature = sign(&token, key)?;
+    let path = data_dir.join(FILE_GO_LIVE_TOKEN);
+    let tmp = data_dir.join(format!("{FILE_GO_LIVE_TOKEN}.tmp"));
+    std::fs::write(&tmp, serde_json::to_vec_pretty(&token)?)
+        .with_context(|| format!("write {}", tmp.display()))?;
+    std::fs::rename(&tmp, &path).with_context(|| format!("rename {}", path.display()))?;
+    Ok(token)
+}
+
+/// The token in `data_dir` if it is signed with `key`, unexpired at `now_ms`, approves `cfg`
+/// and (with `live.require_manual_approval`) was written by `razor approve`; else why not.
+pub fn check(
+    data_dir: &Path,
+    cfg: &Config,
+    key: Option<&str>,
+    now_ms: u64,
+) -> Result<GoLiveToken, String> {
+    let key = key.ok_or_else(|| format!("{} unset", cfg.live.approval_key_env.trim()))?;
+    let raw = std::fs::read(data_dir.join(FILE_GO_LIVE_TOKEN))
+        .map_err(|_| format!("no {FILE_GO_LIVE_TOKEN} in {}", data_dir.display()))?;
+    let token: GoLiveToken = serde_json::from_slice(&raw)
+        .map_err(|e| format!("unreadable {FILE_GO_LIVE_TOKEN}: {e}"))?;
+    if token.version != TOKEN_VERSION {
+        return Err(format!("unknown token version {}", token.version));
+    }
+    if sign(&token, key).map_err(|e| e.to_string())? != token.signature {
+        return Err("bad signature".to_string());
+    }
+    if now_ms >= token.expires_ts_ms {
+        return Err(format!("expired at {}", token.expires_ts_ms));
+    }
+    let want = approval_config_sha256(cfg).map_err(|e| e.to_string())?;
+    if token.config_sha256 != want {
+        return Err(format!(
+            "approves config {} from {}, not this config {}",
+            short(&token.config_sha256),
+            token.run_id,
+            short(&want)
+        ));
+    }
+    if cfg.live.require_manual_approval && !token.manual {
+        return Err(format!(
+            "live.require_manual_approval: token is from {}, run `razor approve`",
+            token.approved_by
+        ));
+    }
+    Ok(token)
+}
+
+fn short(sha: &str) -> &str {
+    &sha[..sha.len().min(12)]
+}
+
+/// `go` of the run dir's `report.json`.
+#[allow(dead_code)]
+pub fn report_is_go(run_dir: &Path) -> anyhow::Result<bool> {
+    let path = run_dir.join(FILE_REPORT_JSON);
+    let raw = std::fs::read(&path).with_context(|| format!("read {}", path.display()))?;
+    let v: serde_json::Value =
+        serde_json::from_slice(&raw).with_context(|| format!("parse {}", path.display()))?;
+    Ok(v.pointer("/verdict/go").and_then(|g| g.as_bool()) == Some(true))
+}
+
+/// The run dir's `config.toml` snapshot, parsed.
+#[allow(dead_code)]
+pub fn run_config(run_dir: &Path) -> anyhow::Result<Config> {
+    let path = run_dir.join(FILE_RUN_CONFIG);
+    let raw = std::fs::read_to_string(&path).with_context(|| format!("read {}", path.display()))?;
+    toml::from_str(&raw).with_context(|| format!("parse {}", path.display()))
+}
+
+/// Issues a token for the GO run in `run_dir` (into its data dir) with the key its config
+/// names. `Ok(None)` when no key is set.
+#[allow(dead_code)]
+pub fn issue_for_run_dir(
+    run_dir: &Path,
+    run_id: &str,
+    approved_by: &str,
+    manual: bool,
+    now_ms: u64,
+) -> anyhow::Result<Option<GoLiveToken>> {
+    let cfg = run_config(run_dir)?;
+    crate::secrets::init(&cfg.secrets)?;
+    let Some(key) = crate::secrets::get(&cfg.live.approval_key_env) else {
+        return Ok(None);
+    };
+    let data_dir = run_dir.parent().unwrap_or(Path::new("."));
+    issue(data_dir, &cfg, run_id, approved_by, manual, &key, now_ms).map(Some)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn cfg() -> Config {
+        toml::from_str("[run]\nmarket_ids = [\"1\"]\n").unwrap()
+    }
+
+    #[test]
+    fn token_approves_only_its_config_until_it_expires() {
+        let dir = std::env::temp_dir().join(format!("razor_go_live_{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        let base = cfg();
+        let t = issue(&dir, &base, "run_a", "report", false, "k1", 1_000).unwrap();
+
+        // The live run differs only in live.enabled.
+        let mut live = base.clone();
+        live.live.enabled = true;
+        assert_eq!(check(&dir, &live, Some("k1"), 2_000), Ok(t.clone()));
+
+        assert_eq!(
+            check(&dir, &live, Some("k2"), 2_000),
+            Err("bad signature".to_string())
+        );
+        assert!(check(&dir, &live, None, 2_000)
+            .unwrap_err()
+            .contains("unset"));
+        let expiry = 1_000 + base.live.approval_ttl_ms;
+        assert!(check(&dir, &live, Some("k1"), expiry)
+            .unwrap_err()
+            .contains("expired"));
+
+        let mut other = live.clone();
+        other.brain.min_net_edge_bps += 1;
+        assert!(check(&dir, &other, Some("k1"), 2_000)
+            .unwrap_err()
+            .contains("not this config"));
+
+        // Tampering with the expiry breaks the signature.
+        let mut forged = t;
+        forged.expires_ts_ms = u64::MAX;
+        std::fs::write(
+            dir.join(FILE_GO_LIVE_TOKEN),
+            serde_json::to_vec(&forged).unwrap(),
+        )
+        .unwrap();
+        assert_eq!(
+            check(&dir, &live, Some("k1"), 2_000),
+            Err("bad signature".to_string())
+        );
+
+        let mut manual_only = base.clone();
+        manual_only.live.require_manual_approval = true;
+        issue(&dir, &manual_only, "run_a", "report", false, "k1", 1_000).unwrap();
+        live = manual_only.clone();
+        live.live.enabled = true;
+        assert!(check(&dir, &live, Some("k1"), 2_000)
+            .unwrap_err()
+            .contains("razor approve"));
+        issue(&dir, &manual_only, "run_a", "manual:ops", true, "k1", 1_000).unwrap();
+        assert!(check(&dir, &live, Some("k1"), 2_000).is_ok());
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
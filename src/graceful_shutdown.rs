@@ -7,3 +7,57 @@ pub fn channel() -> (watch::Sender<bool>, watch::Receiver<bool>) {
 pub fn request(tx: &watch::Sender<bool>) {
     let _ = tx.send(true);
 }
+
+/// Resolves on the first external stop request and names it: ctrl-c everywhere, SIGTERM (systemd,
+/// `docker stop`) and SIGQUIT on Unix, console close / logoff / shutdown / ctrl-break on Windows.
+pub async fn stop_signal() -> &'static str {
+    platform_stop_signal().await
+}
+
+#[cfg(unix)]
+async fn platform_stop_signal() -> &'static str {
+    use tokio::signal::unix::{signal, SignalKind};
+
+    let (Ok(mut term), Ok(mut quit)) =
+        (signal(SignalKind::terminate()), signal(SignalKind::quit()))
+    else {
+        tracing::warn!(
+            "install SIGTERM/SIGQUIT handlers failed; only ctrl-c shuts down gracefully"
+        );
+        let _ = tokio::signal::ctrl_c().await;
+        return "ctrl_c";
+    };
+    tokio::select! {
+        _ = tokio::signal::ctrl_c() => "ctrl_c",
+        _ = term.recv() => "sigterm",
+        _ = quit.recv() => "sigquit",
+    }
+}
+
+#[cfg(windows)]
+async fn platform_stop_signal() -> &'static str {
+    use tokio::signal::windows::{ctrl_break, ctrl_close, ctrl_logoff, ctrl_shutdown};
+
+    // Windows kills the process a few seconds after a close/logoff/shutdown event, so these only
+    // buy time for the flush and the report; `run.shutdown_deadline_ms` should stay short.
+    let (Ok(mut close), Ok(mut logoff), Ok(mut shutdown), Ok(mut brk)) =
+        (ctrl_close(), ctrl_logoff(), ctrl_shutdown(), ctrl_break())
+    else {
+        tracing::warn!("install console event handlers failed; only ctrl-c shuts down gracefully");
+        let _ = tokio::signal::ctrl_c().await;
+        return "ctrl_c";
+    };
+    tokio::select! {
+        _ = tokio::signal::ctrl_c() => "ctrl_c",
+        _ = close.recv() => "ctrl_close",
+        _ = logoff.recv() => "ctrl_logoff",
+        _ = shutdown.recv() => "ctrl_shutdown",
+        _ = brk.recv() => "ctrl_break",
+    }
+}
+
+#[cfg(not(any(unix, windows)))]
+async fn platform_stop_signal() -> &'static str {
+    let _ = tokio::signal::ctrl_c().await;
+    "ctrl_c"
+}
@@ -1,14 +1,22 @@
-use std::path::PathBuf;
+use std::collections::{HashMap, HashSet};
+use std::hash::Hash;
+use std::path::{Path, PathBuf};
 use std::sync::atomic::{AtomicU64, Ordering};
-use std::sync::Arc;
+use std::sync::{Arc, RwLock};
 use std::time::Duration;
 
+use schemars::JsonSchema;
 use serde::Serialize;
 use tokio::sync::{mpsc, watch};
 use tokio::task::JoinHandle;
 use tracing::warn;
 
-use crate::recorder::JsonlAppender;
+use crate::backpressure::{ChannelSnapshot, ChannelStats};
+use crate::config::Config;
+use crate::disk_guard::DiskLevel;
+use crate::errors::ErrorCode;
+use crate::recorder::{FileIoSnapshot, JsonlAppender};
+use crate::report::{SettleLatencySummary, SETTLE_DELAY_BUCKETS_MS};
 use crate::types::now_ms;
 
 #[derive(Default)]
@@ -17,18 +25,53 @@ pub struct HealthCounters {
     trades_written: AtomicU64,
     trades_dropped: AtomicU64,
     trades_duplicated: AtomicU64,
+    trades_dup_tx_key: AtomicU64,
+    trades_dup_weak_key: AtomicU64,
+    dedup_recent_ids: AtomicU64,
+    dedup_expired: AtomicU64,
     trades_invalid: AtomicU64,
+    trades_from_ws: AtomicU64,
+    trades_gap_filled: AtomicU64,
     trade_poll_hit_limit: AtomicU64,
+    trade_poll_interval_ms: AtomicU64,
     signals_emitted: AtomicU64,
     signals_suppressed: AtomicU64,
     signals_dropped: AtomicU64,
     snapshots_stale_skipped: AtomicU64,
+    snapshots_published: AtomicU64,
+    snapshots_conflated: AtomicU64,
     shadow_processed: AtomicU64,
+    shadow_losing_streak: AtomicU64,
+    ws_reconnects: AtomicU64,
     trade_store_size: AtomicU64,
     trade_store_evicted: AtomicU64,
     last_tick_ingest_ms: AtomicU64,
     last_trade_ingest_ms: AtomicU64,
     last_shadow_write_ms: AtomicU64,
+    reconcile_breaks: AtomicU64,
+    disk_level: AtomicU64,
+    disk_free_bytes: AtomicU64,
+    markets: RwLock<HashMap<String, MarketFreshness>>,
+    channels: RwLock<Vec<Arc<ChannelStats>>>,
+    settle_hist: [AtomicU64; SETTLE_DELAY_BUCKETS_MS.len() + 1],
+    settle_count: AtomicU64,
+    settle_sum_ms: AtomicU64,
+    settle_max_ms: AtomicU64,
+    settle_window_partial: AtomicU64,
+}
+
+#[derive(Default)]
+struct MarketFreshness {
+    last_tick_ingest_ms: AtomicU64,
+    last_trade_ingest_ms: AtomicU64,
+}
+
+/// Point-in-time per-market freshness (0 = nothing seen yet).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MarketFreshnessSnapshot {
+    pub market_id: String,
+    pub last_tick_ingest_ms: u64,
+    pub last_trade_ingest_ms: u64,
 }
 
 impl HealthCounters {
@@ -44,18 +87,44 @@ impl HealthCounters {
         self.trades_dropped.fetch_add(n, Ordering::Relaxed);
     }
 
-    pub fn inc_trades_duplicated(&self, n: u64) {
-        self.trades_duplicated.fetch_add(n, Ordering::Relaxed);
+    /// A polled trade matched a recent dedup key; `weak_key` when the trade had no tx hash.
+    pub fn record_trade_duplicate(&self, weak_key: bool) {
+        self.trades_duplicated.fetch_add(1, Ordering::Relaxed);
+        if weak_key {
+            self.trades_dup_weak_key.fetch_add(1, Ordering::Relaxed);
+        } else {
+            self.trades_dup_tx_key.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+
+    pub fn set_dedup_recent_ids(&self, size: usize) {
+        self.dedup_recent_ids.store(size as u64, Ordering::Relaxed);
+    }
+
+    pub fn inc_dedup_expired(&self, n: u64) {
+        self.dedup_expired.fetch_add(n, Ordering::Relaxed);
     }
 
     pub fn inc_trades_invalid(&self, n: u64) {
         self.trades_invalid.fetch_add(n, Ordering::Relaxed);
     }
 
+    pub fn inc_trades_from_ws(&self, n: u64) {
+        self.trades_from_ws.fetch_add(n, Ordering::Relaxed);
+    }
+
+    pub fn inc_trades_gap_filled(&self, n: u64) {
+        self.trades_gap_filled.fetch_add(n, Ordering::Relaxed);
+    }
+
     pub fn inc_trade_poll_hit_limit(&self, n: u64) {
         self.trade_poll_hit_limit.fetch_add(n, Ordering::Relaxed);
     }
 
+    pub fn set_trade_poll_interval_ms(&self, ms: u64) {
+        self.trade_poll_interval_ms.store(ms, Ordering::Relaxed);
+    }
+
     pub fn inc_signals_emitted(&self, n: u64) {
         self.signals_emitted.fetch_add(n, Ordering::Relaxed);
     }
@@ -72,10 +141,31 @@ impl HealthCounters {
         self.snapshots_stale_skipped.fetch_add(n, Ordering::Relaxed);
     }
 
+    pub fn inc_snapshots_published(&self, n: u64) {
+        self.snapshots_published.fetch_add(n, Ordering::Relaxed);
+    }
+
+    pub fn inc_snapshots_conflated(&self, n: u64) {
+        self.snapshots_conflated.fetch_add(n, Ordering::Relaxed);
+    }
+
     pub fn inc_shadow_processed(&self, n: u64) {
         self.shadow_processed.fetch_add(n, Ordering::Relaxed);
     }
 
+    /// Tracks the run of consecutive losing shadow settlements `risk::run` watches.
+    pub fn record_shadow_pnl(&self, total_pnl: f64) {
+        if total_pnl < 0.0 {
+            self.shadow_losing_streak.fetch_add(1, Ordering::Relaxed);
+        } else if total_pnl > 0.0 {
+            self.shadow_losing_streak.store(0, Ordering::Relaxed);
+        }
+    }
+
+    pub fn inc_ws_reconnects(&self, n: u64) {
+        self.ws_reconnects.fetch_add(n, Ordering::Relaxed);
+    }
+
     pub fn set_trade_store_size(&self, size: usize) {
         self.trade_store_size.store(size as u64, Ordering::Relaxed);
     }
@@ -84,6 +174,10 @@ impl HealthCounters {
         self.trade_store_evicted.fetch_add(n, Ordering::Relaxed);
     }
 
+    pub fn set_reconcile_breaks(&self, n: u64) {
+        self.reconcile_breaks.store(n, Ordering::Relaxed);
+    }
+
     pub fn set_last_tick_ingest_ms(&self, ts_ms: u64) {
         self.last_tick_ingest_ms.store(ts_ms, Ordering::Relaxed);
     }
@@ -96,6 +190,105 @@ impl HealthCounters {
         self.last_shadow_write_ms.store(ts_ms, Ordering::Relaxed);
     }
 
+    /// Reports this channel's policy and drops in every heartbeat.
+    pub fn track_channel(&self, stats: Arc<ChannelStats>) {
+        self.channels
+            .write()
+            .unwrap_or_else(|e| e.into_inner())
+            .push(stats);
+    }
+
+    /// Pre-registers markets so one that never produces data still shows up as stale.
+    pub fn track_markets<'a>(&self, market_ids: impl IntoIterator<Item = &'a str>) {
+        let mut map = self.markets.write().unwrap_or_else(|e| e.into_inner());
+        for id in market_ids {
+            map.entry(id.to_string()).or_default();
+        }
+    }
+
+    pub fn set_market_last_tick_ingest_ms(&self, market_id: &str, ts_ms: u64) {
+        self.with_market(market_id, |m| {
+            m.last_tick_ingest_ms.store(ts_ms, Ordering::Relaxed)
+        });
+    }
+
+    pub fn set_market_last_trade_ingest_ms(&self, market_id: &str, ts_ms: u64) {
+        self.with_market(market_id, |m| {
+            m.last_trade_ingest_ms.store(ts_ms, Ordering::Relaxed)
+        });
+    }
+
+    fn with_market(&self, market_id: &str, f: impl Fn(&MarketFreshness)) {
+        {
+            let map = self.markets.read().unwrap_or_else(|e| e.into_inner());
+            if let Some(m) = map.get(market_id) {
+                f(m);
+                return;
+            }
+        }
+        let mut map = self.markets.write().unwrap_or_else(|e| e.into_inner());
+        f(map.entry(market_id.to_string()).or_default());
+    }
+
+    pub fn market_freshness(&self) -> Vec<MarketFreshnessSnapshot> {
+        let map = self.markets.read().unwrap_or_else(|e| e.into_inner());
+        let mut out: Vec<MarketFreshnessSnapshot> = map
+            .iter()
+            .map(|(id, m)| MarketFreshnessSnapshot {
+                market_id: id.clone(),
+                last_tick_ingest_ms: m.last_tick_ingest_ms.load(Ordering::Relaxed),
+                last_trade_ingest_ms: m.last_trade_ingest_ms.load(Ordering::Relaxed),
+            })
+            .collect();
+        out.sort_by(|a, b| a.market_id.cmp(&b.market_id));
+        out
+    }
+
+    /// `delay_ms` is how long after `signal_ts_ms + window_end_ms` the signal was settled;
+    /// `window_complete` is false when the trade feed had not reached the window end yet.
+    pub fn record_settle(&self, delay_ms: u64, window_complete: bool) {
+        let idx = SETTLE_DELAY_BUCKETS_MS
+            .iter()
+            .position(|ub| delay_ms <= *ub)
+            .unwrap_or(SETTLE_DELAY_BUCKETS_MS.len());
+        self.settle_hist[idx].fetch_add(1, Ordering::Relaxed);
+        self.settle_count.fetch_add(1, Ordering::Relaxed);
+        self.settle_sum_ms.fetch_add(delay_ms, Ordering::Relaxed);
+        self.settle_max_ms.fetch_max(delay_ms, Ordering::Relaxed);
+        if !window_complete {
+            self.settle_window_partial.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+
+    pub fn settle_latency(&self) -> SettleLatencySummary {
+        let count = self.settle_count.load(Ordering::Relaxed);
+        let sum_ms = self.settle_sum_ms.load(Ordering::Relaxed);
+        SettleLatencySummary {
+            count,
+            mean_ms: if count > 0 {
+                sum_ms as f64 / count as f64
+            } else {
+                0.0
+            },
+            max_ms: self.settle_max_ms.load(Ordering::Relaxed),
+            hist: self
+                .settle_hist
+                .iter()
+                .map(|b| b.load(Ordering::Relaxed))
+                .collect(),
+            window_partial: self.settle_window_partial.load(Ordering::Relaxed),
+        }
+    }
+
+    pub fn set_disk_status(&self, level: DiskLevel, free_bytes: u64) {
+        self.disk_level.store(level as u64, Ordering::Relaxed);
+        self.disk_free_bytes.store(free_bytes, Ordering::Relaxed);
+    }
+
+    pub fn disk_level(&self) -> DiskLevel {
+        DiskLevel::from_u64(self.disk_level.load(Ordering::Relaxed))
+    }
+
     pub fn snapshot(&self) -> HealthSnapshot {
         HealthSnapshot {
             ts_ms: now_ms(),
@@ -103,26 +296,49 @@ impl HealthCounters {
             trades_written: self.trades_written.load(Ordering::Relaxed),
             trades_dropped: self.trades_dropped.load(Ordering::Relaxed),
             trades_duplicated: self.trades_duplicated.load(Ordering::Relaxed),
+            trades_dup_tx_key: self.trades_dup_tx_key.load(Ordering::Relaxed),
+            trades_dup_weak_key: self.trades_dup_weak_key.load(Ordering::Relaxed),
+            dedup_recent_ids: self.dedup_recent_ids.load(Ordering::Relaxed),
+            dedup_expired: self.dedup_expired.load(Ordering::Relaxed),
             trades_invalid: self.trades_invalid.load(Ordering::Relaxed),
+            trades_from_ws: self.trades_from_ws.load(Ordering::Relaxed),
+            trades_gap_filled: self.trades_gap_filled.load(Ordering::Relaxed),
             trade_poll_hit_limit: self.trade_poll_hit_limit.load(Ordering::Relaxed),
+            trade_poll_interval_ms: self.trade_poll_interval_ms.load(Ordering::Relaxed),
             signals_emitted: self.signals_emitted.load(Ordering::Relaxed),
             signals_suppressed: self.signals_suppressed.load(Ordering::Relaxed),
             signals_dropped: self.signals_dropped.load(Ordering::Relaxed),
             snapshots_stale_skipped: self.snapshots_stale_skipped.load(Ordering::Relaxed),
+            snapshots_published: self.snapshots_published.load(Ordering::Relaxed),
+            snapshots_conflated: self.snapshots_conflated.load(Ordering::Relaxed),
             shadow_processed: self.shadow_processed.load(Ordering::Relaxed),
+            shadow_losing_streak: self.shadow_losing_streak.load(Ordering::Relaxed),
+            ws_reconnects: self.ws_reconnects.load(Ordering::Relaxed),
             trade_store_size: self.trade_store_size.load(Ordering::Relaxed),
             trade_store_evicted: self.trade_store_evicted.load(Ordering::Relaxed),
             last_tick_ingest_ms: self.last_tick_ingest_ms.load(Ordering::Relaxed),
             last_trade_ingest_ms: self.last_trade_ingest_ms.load(Ordering::Relaxed),
             last_shadow_write_ms: self.last_shadow_write_ms.load(Ordering::Relaxed),
+            reconcile_breaks: self.reconcile_breaks.load(Ordering::Relaxed),
+            disk_level: self.disk_level().as_str(),
+            disk_free_bytes: self.disk_free_bytes.load(Ordering::Relaxed),
+            settle_latency: self.settle_latency(),
+            recorder_io: crate::recorder::io_metrics_snapshot(),
+            channels: self
+                .channels
+                .read()
+                .unwrap_or_else(|e| e.into_inner())
+                .iter()
+                .map(|c| c.snapshot())
+                .collect(),
         }
     }
 }
 
-#[derive(Debug, Clone, Serialize)]
+#[derive(Debug, Clone, Serialize, JsonSchema)]
 #[serde(tag = "type", rename_all = "snake_case")]
 pub enum HealthLine {
-    Heartbeat(HealthSnapshot),
+    Heartbeat(Box<HealthSnapshot>),
     TradePollHitLimit {
         ts_ms: u64,
         market_id: String,
@@ -130,37 +346,162 @@ pub enum HealthLine {
         earliest_ts_ms: u64,
         latest_ts_ms: u64,
     },
+    TradePollIntervalChanged {
+        ts_ms: u64,
+        from_ms: u64,
+        to_ms: u64,
+        hits_in_window: usize,
+    },
+    /// Edge-triggered: emitted once when a feed goes stale (`stale: true`, the deadman policy
+    /// then applies) and once when it recovers.
+    FeedStale {
+        ts_ms: u64,
+        feed: String,
+        age_ms: u64,
+        threshold_ms: u64,
+        policy: String,
+        stale: bool,
+    },
+    /// Edge-triggered: emitted once when a market goes stale and once when it recovers.
+    MarketStale {
+        ts_ms: u64,
+        market_id: String,
+        feed: String,
+        age_ms: u64,
+        threshold_ms: u64,
+        stale: bool,
+    },
+    DiskLevelChanged {
+        ts_ms: u64,
+        free_bytes: u64,
+        from: String,
+        to: String,
+    },
+    /// Free space under `run.data_dir` fell below `disk.shutdown_free_mb`; the disk guard asked
+    /// for a graceful shutdown (report + flush, exit code 0).
+    DiskShutdown {
+        ts_ms: u64,
+        free_bytes: u64,
+        threshold_mb: u64,
+    },
+    /// `raw_ws.jsonl` moved to a new segment (`reason` is `size` or `interval`); `removed` are
+    /// old segments deleted by retention.
+    RawWsRotated {
+        ts_ms: u64,
+        reason: String,
+        segment: String,
+        segment_bytes: u64,
+        removed: Vec<String>,
+    },
+    /// A SIGHUP re-read of the config file: the reloadable keys that changed (old/new), changed
+    /// keys that need a restart and were ignored, or why the reload failed.
+    ConfigReloaded {
+        ts_ms: u64,
+        path: String,
+        applied: Vec<ConfigChange>,
+        ignored: Vec<String>,
+        error: Option<String>,
+    },
+    /// A task error with its stable code: `fatal` ended the run; otherwise the task recovered
+    /// (e.g. a WS reconnect).
+    TaskError {
+        ts_ms: u64,
+        task: String,
+        code: ErrorCode,
+        error: String,
+        fatal: bool,
+    },
+    /// `[calibration.auto_tune]` moved a bucket's fill share; shadow rows settled from now on
+    /// carry the new value in `fill_share_p25_used`.
+    FillShareTuned {
+        ts_ms: u64,
+        bucket: String,
+        from: f64,
+        to: f64,
+        target: f64,
+        samples: usize,
+        confidence: String,
+    },
+    /// A `[risk]` condition tripped the global kill switch; the run stops right after.
+    RiskHardstop {
+        ts_ms: u64,
+        condition: String,
+        value: u64,
+        threshold: u64,
+    },
+    /// Graceful shutdown ran past `run.shutdown_deadline_ms`; `aborted` are the tasks that were
+    /// still running and got aborted.
+    ShutdownDeadline {
+        ts_ms: u64,
+        deadline_ms: u64,
+        aborted: Vec<String>,
+    },
 }
 
-#[derive(Debug, Clone, Serialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, JsonSchema)]
+pub struct ConfigChange {
+    /// Dotted config key, e.g. `brain.min_net_edge_bps`.
+    pub key: String,
+    pub old: serde_json::Value,
+    pub new: serde_json::Value,
+}
+
+#[derive(Debug, Clone, Serialize, JsonSchema)]
 pub struct HealthSnapshot {
     pub ts_ms: u64,
     pub ticks_processed: u64,
     pub trades_written: u64,
     pub trades_dropped: u64,
     pub trades_duplicated: u64,
+    pub trades_dup_tx_key: u64,
+    pub trades_dup_weak_key: u64,
+    /// Keys currently held by the trades poller's dedup window.
+    pub dedup_recent_ids: u64,
+    pub dedup_expired: u64,
     pub trades_invalid: u64,
+    /// Trades written from market-channel `last_trade_price` events (`shadow.trade_feed = "ws"`).
+    pub trades_from_ws: u64,
+    /// Trades the poller wrote that the WS had not delivered (`shadow.trade_feed = "ws"`).
+    pub trades_gap_filled: u64,
     pub trade_poll_hit_limit: u64,
+    pub trade_poll_interval_ms: u64,
     pub signals_emitted: u64,
     pub signals_suppressed: u64,
     pub signals_dropped: u64,
     pub snapshots_stale_skipped: u64,
+    /// Market snapshots the WS feed handed to brain.
+    pub snapshots_published: u64,
+    /// Book updates folded into a later snapshot (or dropped as too small) by `[conflation]`.
+    pub snapshots_conflated: u64,
     pub shadow_processed: u64,
+    /// Consecutive shadow settlements with `total_pnl < 0` (zero-PnL rows are skipped).
+    pub shadow_losing_streak: u64,
+    /// Market WS connections dropped with an error and retried.
+    pub ws_reconnects: u64,
     pub trade_store_size: u64,
     pub trade_store_evicted: u64,
     pub last_tick_ingest_ms: u64,
     pub last_trade_ingest_ms: u64,
     pub last_shadow_write_ms: u64,
+    /// Tokens whose on-chain balance disagreed with trade_log on the last two `[reconcile]` passes.
+    pub reconcile_breaks: u64,
+    pub disk_level: &'static str,
+    pub disk_free_bytes: u64,
+    pub settle_latency: SettleLatencySummary,
+    pub recorder_io: Vec<FileIoSnapshot>,
+    /// `[backpressure]` policy, queue length and realized drops per in-process channel.
+    pub channels: Vec<ChannelSnapshot>,
 }
 
 pub fn spawn_health_writer(
     path: PathBuf,
     counters: Arc<HealthCounters>,
+    mut reload: watch::Receiver<Config>,
     mut shutdown: watch::Receiver<bool>,
 ) -> anyhow::Result<(mpsc::Sender<HealthLine>, JoinHandle<()>)> {
     let (tx, mut rx) = mpsc::channel::<HealthLine>(10_000);
 
-    let handle = tokio::spawn(async move {
+    let writer = async move {
         let mut out = match JsonlAppender::open(&path) {
             Ok(v) => v,
             Err(e) => {
@@ -169,16 +510,25 @@ pub fn spawn_health_writer(
             }
         };
 
-        let mut tick = tokio::time::interval(Duration::from_secs(10));
+        let mut heartbeat_interval_ms = reload.borrow().health.heartbeat_interval_ms;
+        let mut tick = tokio::time::interval(Duration::from_millis(heartbeat_interval_ms));
         tick.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
         loop {
             tokio::select! {
                 _ = shutdown.changed() => {
                     if *shutdown.borrow() { break; }
                 }
+                Ok(()) = reload.changed() => {
+                    let ms = reload.borrow().health.heartbeat_interval_ms;
+                    if ms != heartbeat_interval_ms {
+                        heartbeat_interval_ms = ms;
+                        tick = tokio::time::interval(Duration::from_millis(ms));
+                        tick.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+                    }
+                }
                 _ = tick.tick() => {
                     let snap = counters.snapshot();
-                    let line = HealthLine::Heartbeat(snap);
+                    let line = HealthLine::Heartbeat(Box::new(snap));
                     if let Err(e) = write_line(&mut out, &line) {
                         warn!(error = %e, "health heartbeat write failed");
                     }
@@ -191,17 +541,128 @@ pub fn spawn_health_writer(
                 }
             }
         }
+        // Events queued before shutdown (e.g. the fatal `task_error`) still make it to disk.
+        while let Ok(line) = rx.try_recv() {
+            if let Err(e) = write_line(&mut out, &line) {
+                warn!(error = %e, "health event write failed");
+            }
+        }
 
         if let Err(e) = out.flush_and_sync() {
             warn!(error = %e, "health.jsonl flush/sync failed");
         }
-    });
+    };
+    let handle = crate::crash::spawn_supervised("health_writer", writer);
 
     Ok((tx, handle))
 }
 
+/// Appends one line to `health.jsonl` after the writer task has stopped.
+pub fn append_line(path: &Path, line: &HealthLine) -> anyhow::Result<()> {
+    let mut out = JsonlAppender::open(path)?;
+    write_line(&mut out, line)?;
+    out.flush_and_sync()
+}
+
 fn write_line(out: &mut JsonlAppender, line: &HealthLine) -> anyhow::Result<()> {
     let json = serde_json::to_string(line)?;
     out.write_line(&json)?;
     Ok(())
 }
+
+/// Staleness edge detector behind `FeedStale` / `MarketStale`: `update` returns the new state
+/// only when it flips, so an outage is one line in and one line out however long it lasts.
+#[derive(Debug)]
+pub struct StaleEdges<K> {
+    stale: HashSet<K>,
+}
+
+impl<K> Default for StaleEdges<K> {
+    fn default() -> Self {
+        Self {
+            stale: HashSet::new(),
+        }
+    }
+}
+
+impl<K: Eq + Hash> StaleEdges<K> {
+    pub fn update(&mut self, key: K, is_stale: bool) -> Option<bool> {
+        let flipped = if is_stale {
+            self.stale.insert(key)
+        } else {
+            self.stale.remove(&key)
+        };
+        flipped.then_some(is_stale)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn stale_edges_report_only_transitions() {
+        let mut edges = StaleEdges::default();
+        let got: Vec<Option<bool>> = [false, true, true, true, false, false, true]
+            .into_iter()
+            .map(|stale| edges.update("ticks", stale))
+            .collect();
+        assert_eq!(
+            got,
+            [None, Some(true), None, None, Some(false), None, Some(true)]
+        );
+        assert_eq!(edges.update("trades", false), None);
+
+        let line = serde_json::to_value(HealthLine::FeedStale {
+            ts_ms: 5,
+            feed: "ticks".into(),
+            age_ms: 31_000,
+            threshold_ms: 30_000,
+            policy: "warn".into(),
+            stale: false,
+        })
+        .unwrap();
+        assert_eq!(line["type"], "feed_stale");
+        assert_eq!(line["stale"], false);
+    }
+
+    #[test]
+    fn market_freshness_tracks_registered_and_new_markets() {
+        let h = HealthCounters::default();
+        h.track_markets(["b", "a"]);
+        h.set_market_last_tick_ingest_ms("a", 10);
+        h.set_market_last_trade_ingest_ms("c", 20);
+
+        let got = h.market_freshness();
+        let ids: Vec<&str> = got.iter().map(|m| m.market_id.as_str()).collect();
+        assert_eq!(ids, ["a", "b", "c"]);
+        assert_eq!(got[0].last_tick_ingest_ms, 10);
+        assert_eq!(got[1].last_tick_ingest_ms, 0);
+        assert_eq!(got[2].last_trade_ingest_ms, 20);
+    }
+
+    #[test]
+    fn settle_latency_buckets_by_upper_bound() {
+        let h = HealthCounters::default();
+        h.record_settle(50, true);
+        h.record_settle(51, true);
+        h.record_settle(60_000, false);
+
+        let lat = h.settle_latency();
+        assert_eq!(lat.count, 3);
+        assert_eq!(lat.max_ms, 60_000);
+        assert_eq!(lat.window_partial, 1);
+        assert_eq!(lat.hist, [1, 1, 0, 0, 0, 0, 1]);
+    }
+
+    #[test]
+    fn losing_streak_resets_on_a_win_and_skips_flat_settlements() {
+        let h = HealthCounters::default();
+        for pnl in [-1.0, -0.5, 0.0, -2.0] {
+            h.record_shadow_pnl(pnl);
+        }
+        assert_eq!(h.snapshot().shadow_losing_streak, 3);
+        h.record_shadow_pnl(0.1);
+        assert_eq!(h.snapshot().shadow_losing_streak, 0);
+    }
+}
@@ -0,0 +1,183 @@
+use std::collections::VecDeque;
+use std::fmt::Write as _;
+use std::sync::Arc;
+use std::time::Duration;
+
+use anyhow::Context as _;
+use tokio::sync::watch;
+use tracing::info;
+
+use crate::config::{Config, HealthConfig};
+use crate::health::{HealthCounters, HealthSnapshot};
+use crate::log_throttle::warn_throttled;
+
+const METRIC_PREFIX: &str = "razor_";
+
+/// Renders a heartbeat in the Prometheus text exposition format. Numeric fields become gauges,
+/// nested objects are prefixed (`settle_latency_mean_ms`), per-file recorder stats get a `file`
+/// label and string fields become `<name>_info{value="..."} 1`.
+pub fn render_exposition(snap: &HealthSnapshot) -> String {
+    let mut out = String::new();
+    let Ok(serde_json::Value::Object(fields)) = serde_json::to_value(snap) else {
+        return out;
+    };
+    for (name, v) in &fields {
+        match v {
+            serde_json::Value::Number(n) => push_sample(&mut out, name, "", n),
+            serde_json::Value::String(s) => {
+                let _ = writeln!(
+                    out,
+                    "{METRIC_PREFIX}{name}_info{{value=\"{}\"}} 1",
+                    escape_label(s)
+                );
+            }
+            serde_json::Value::Object(inner) => {
+                for (k, iv) in inner {
+                    if let serde_json::Value::Number(n) = iv {
+                        push_sample(&mut out, &format!("{name}_{k}"), "", n);
+                    }
+                }
+            }
+            serde_json::Value::Array(items) => {
+                for item in items {
+                    let Some(file) = item.get("file").and_then(|f| f.as_str()) else {
+                        continue;
+                    };
+                    let labels = format!("{{file=\"{}\"}}", escape_label(file));
+                    for (k, iv) in item.as_object().into_iter().flatten() {
+                        if let serde_json::Value::Number(n) = iv {
+                            push_sample(&mut out, &format!("{name}_{k}"), &labels, n);
+                        }
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+    out
+}
+
+fn push_sample(out: &mut String, name: &str, labels: &str, n: &serde_json::Number) {
+    let _ = writeln!(out, "{METRIC_PREFIX}{name}{labels} {n}");
+}
+
+fn escape_label(s: &str) -> String {
+    s.replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+}
+
+/// Pushes a heartbeat to a Prometheus Pushgateway every `push_interval_ms`, grouped by
+/// `job`/`run_id`. While the gateway is unreachable, bodies queue up (oldest dropped beyond
+/// `push_max_buffered`) and are replayed in order once it answers again.
+pub async fn run_health_pusher(
+    cfg: HealthConfig,
+    run_id: String,
+    counters: Arc<HealthCounters>,
+    mut reload: watch::Receiver<Config>,
+    mut shutdown: watch::Receiver<bool>,
+) -> anyhow::Result<()> {
+    let client = reqwest::Client::builder()
+        .user_agent(concat!("razor/", env!("CARGO_PKG_VERSION")))
+        .timeout(Duration::from_millis(
+            cfg.push_interval_ms.clamp(1_000, 10_000),
+        ))
+        .build()
+        .context("build pushgateway http client")?;
+    let url = format!(
+        "{}/metrics/job/{}/run_id/{}",
+        cfg.push_url.trim_end_matches('/'),
+        cfg.push_job,
+        run_id
+    );
+    info!(%url, interval_ms = cfg.push_interval_ms, "health pusher start");
+
+    let mut buffered: VecDeque<String> = VecDeque::new();
+    let mut dropped: u64 = 0;
+    let mut push_interval_ms = cfg.push_interval_ms;
+    let mut interval = tokio::time::interval(Duration::from_millis(push_interval_ms));
+    interval.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+
+    loop {
+        tokio::select! {
+            _ = shutdown.changed() => {
+                if *shutdown.borrow() { break; }
+            }
+            Ok(()) = reload.changed() => {
+                let ms = reload.borrow().health.push_interval_ms;
+                if ms != push_interval_ms {
+                    push_interval_ms = ms;
+                    interval = tokio::time::interval(Duration::from_millis(ms));
+                    interval.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+                }
+                continue;
+            }
+            _ = interval.tick() => {}
+        }
+        if *shutdown.borrow() {
+            break;
+        }
+
+        buffered.push_back(render_exposition(&counters.snapshot()));
+        while buffered.len() > cfg.push_max_buffered.max(1) {
+            buffered.pop_front();
+            dropped += 1;
+        }
+
+        let backlog = buffered.len();
+        while let Some(body) = buffered.front() {
+            match push(&client, &url, body.clone()).await {
+                Ok(()) => {
+                    buffered.pop_front();
+                }
+                Err(e) => {
+                    warn_throttled!(
+                        first = 3, every = 60;
+                        error = %e,
+                        buffered = buffered.len(),
+                        dropped,
+                        "health push failed; buffering"
+                    );
+                    break;
+                }
+            }
+        }
+        if backlog > 1 && buffered.is_empty() {
+            info!(replayed = backlog, dropped, "health push recovered");
+            dropped = 0;
+        }
+    }
+
+    // Best-effort final snapshot so the gateway holds the end-of-run totals.
+    let _ = push(&client, &url, render_exposition(&counters.snapshot())).await;
+    Ok(())
+}
+
+async fn push(client: &reqwest::Client, url: &str, body: String) -> anyhow::Result<()> {
+    client
+        .put(url)
+        .header("Content-Type", "text/plain; version=0.0.4")
+        .body(body)
+        .send()
+        .await
+        .context("pushgateway request")?
+        .error_for_status()
+        .context("pushgateway response")?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn exposition_flattens_nested_and_labels_files() {
+        let counters = HealthCounters::default();
+        counters.inc_ticks_processed(3);
+        let text = render_exposition(&counters.snapshot());
+        assert!(text.contains("razor_ticks_processed 3\n"));
+        assert!(text.contains("razor_settle_latency_count 0\n"));
+        assert!(text.contains("razor_disk_level_info{value=\"ok\"} 1\n"));
+        assert!(!text.contains("hist"));
+    }
+}
@@ -0,0 +1,566 @@
+//! `razor fetch-history`: downloads a market's past trades (data-api `/trades`) and per-token
+//! price history (CLOB `/prices-history`) into a run dir that `razor replay` and
+//! `razor brain-sweep` read like a recorded one, so periods we never collected live can be swept.
+//!
+//! Caveats, also recorded in `fetch_history.json`:
+//! - trades carry the exchange timestamp in `ts_ms` / `ingest_ts_ms` (there was no ingest);
+//!   `run_meta.json` says `trade_ts_source = "exchange"`;
+//! - there is no public historical order book: snapshots are built from the price series with
+//!   `best_bid = best_ask = price` and an unknown (`NaN`) `depth3_usdc`, so every snapshot
+//!   classifies as degraded `Thin` and signals only fire on price dislocations across legs.
+
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use anyhow::Context as _;
+use serde::{Deserialize, Serialize};
+use tracing::{info, warn};
+
+use crate::api_usage::{self, ApiEndpoint};
+use crate::config::{self, Config};
+use crate::recorder::{self, CsvAppender};
+use crate::rows::TradeRow;
+use crate::run_meta::{Provenance, RunMeta, SimStressProfile};
+use crate::schema::{
+    self, FILE_SNAPSHOTS, FILE_TRADES, SCHEMA_VERSION, SNAPSHOTS_HEADER, TRADES_HEADER,
+};
+use crate::types::{
+    normalize_ts_ms, now_ms, trade_dedup_key, MarketId, Price, Qty, Side, TokenId, TradeTick,
+};
+
+pub const FILE_FETCH_HISTORY_JSON: &str = "fetch_history.json";
+
+/// `book_source` in `fetch_history.json`.
+const BOOK_SOURCE_PRICES_HISTORY: &str = "prices_history";
+const BOOK_SOURCE_NONE: &str = "none";
+
+#[derive(Debug, Clone)]
+pub struct FetchHistoryOptions {
+    /// Gamma market id, as in `run.market_ids`.
+    pub market_id: String,
+    pub from_ms: u64,
+    pub to_ms: u64,
+    /// Default: `<data_dir>/history/<run_id>/`.
+    pub out_dir: Option<PathBuf>,
+    /// data-api `/trades` page size.
+    pub page_size: usize,
+    /// Stop paging after this many pages; the dataset is then marked truncated.
+    pub max_pages: usize,
+    /// `/prices-history` resolution in minutes.
+    pub fidelity_min: u32,
+}
+
+/// `fetch_history.json`: what was downloaded and how complete it is.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FetchHistorySummary {
+    pub run_id: String,
+    pub gamma_market_id: String,
+    pub market_id: String,
+    pub token_ids: Vec<String>,
+    pub from_ms: u64,
+    pub to_ms: u64,
+    pub trade_pages: usize,
+    pub trades_written: usize,
+    pub trades_duplicated: usize,
+    /// Paging stopped at `max_pages` before reaching `from_ms`: the oldest trades are missing.
+    pub trades_truncated: bool,
+    pub earliest_trade_ts_ms: Option<u64>,
+    pub snapshots_written: usize,
+    /// `prices_history` or `none` (price history unavailable; snapshots.csv is header-only).
+    pub book_source: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct GammaMarket {
+    #[serde(rename = "conditionId")]
+    condition_id: String,
+    #[serde(rename = "clobTokenIds")]
+    clob_token_ids: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct DataApiTrade {
+    #[serde(rename = "asset")]
+    asset_id: TokenId,
+    #[serde(rename = "conditionId")]
+    market_id: MarketId,
+    size: f64,
+    price: f64,
+    timestamp: u64,
+    #[serde(rename = "transactionHash", default)]
+    transaction_hash: String,
+    #[serde(default)]
+    side: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct PricesHistory {
+    #[serde(default)]
+    history: Vec<PricePoint>,
+}
+
+#[derive(Debug, Deserialize)]
+struct PricePoint {
+    t: u64,
+    p: f64,
+}
+
+pub async fn run(cfg: &Config, opts: FetchHistoryOptions) -> anyhow::Result<FetchHistorySummary> {
+    if opts.from_ms >= opts.to_ms {
+        anyhow::bail!("--from must be before --to");
+    }
+    if opts.page_size == 0 {
+        anyhow::bail!("--page-size must be > 0");
+    }
+
+    let started_at_ms = now_ms();
+    let run_id = format_run_id(started_at_ms);
+    let out_dir = opts
+        .out_dir
+        .clone()
+        .unwrap_or_else(|| default_out_dir(&cfg.run.data_dir, &run_id));
+    std::fs::create_dir_all(&out_dir).with_context(|| format!("create {}", out_dir.display()))?;
+    info!(run_id, out_dir = %out_dir.display(), "fetch_history run initialized");
+
+    let client = reqwest::Client::builder()
+        .user_agent(concat!("razor/", env!("CARGO_PKG_VERSION")))
+        .connect_timeout(Duration::from_millis(
+            cfg.polymarket.http_connect_timeout_ms,
+        ))
+        .timeout(Duration::from_millis(cfg.polymarket.http_timeout_ms))
+        .build()
+        .context("build http client")?;
+
+    let (market_id, token_ids) = fetch_market(cfg, &client, &opts.market_id).await?;
+    info!(%market_id, legs = token_ids.len(), "fetch_history market resolved");
+
+    let trades = fetch_trades(cfg, &client, &opts, &market_id, &token_ids).await?;
+    let mut trades_out =
+        CsvAppender::open(out_dir.join(FILE_TRADES), &TRADES_HEADER).context("open trades.csv")?;
+    for t in &trades.ticks {
+        trades_out
+            .write_row(&TradeRow::from(t))
+            .context("write trades.csv")?;
+    }
+    trades_out.flush_and_sync().context("flush trades.csv")?;
+
+    let mut series: Vec<Vec<(u64, f64)>> = Vec::with_capacity(token_ids.len());
+    for token_id in &token_ids {
+        match fetch_prices_history(cfg, &client, token_id, &opts).await {
+            Ok(s) => series.push(s),
+            Err(e) => {
+                warn!(token_id = %token_id, error = %e, "prices-history unavailable; writing header-only snapshots.csv");
+                series.clear();
+                break;
+            }
+        }
+    }
+    let book_source = if series.len() == token_ids.len() {
+        BOOK_SOURCE_PRICES_HISTORY
+    } else {
+        BOOK_SOURCE_NONE
+    };
+    let aligned = align_price_series(&series);
+    let mut snaps_out = CsvAppender::open(out_dir.join(FILE_SNAPSHOTS), &SNAPSHOTS_HEADER)
+        .context("open snapshots.csv")?;
+    for (ts_ms, prices) in &aligned {
+        snaps_out
+            .write_record(snapshot_record(*ts_ms, &market_id, &token_ids, prices))
+            .context("write snapshots.csv")?;
+    }
+    snaps_out.flush_and_sync().context("flush snapshots.csv")?;
+
+    write_run_files(cfg, &out_dir, &run_id, &opts.market_id, started_at_ms)?;
+
+    let summary = FetchHistorySummary {
+        run_id,
+        gamma_market_id: opts.market_id.clone(),
+        market_id: market_id.to_string(),
+        token_ids: token_ids.iter().map(|t| t.to_string()).collect(),
+        from_ms: opts.from_ms,
+        to_ms: opts.to_ms,
+        trade_pages: trades.pages,
+        trades_written: trades.ticks.len(),
+        trades_duplicated: trades.duplicated,
+        trades_truncated: trades.truncated,
+        earliest_trade_ts_ms: trades.ticks.first().map(|t| t.ts_ms),
+        snapshots_written: aligned.len(),
+        book_source: book_source.to_string(),
+    };
+    let path = out_dir.join(FILE_FETCH_HISTORY_JSON);
+    let json = serde_json::to_vec_pretty(&summary).context("serialize fetch_history.json")?;
+    std::fs::write(&path, json).with_context(|| format!("write {}", path.display()))?;
+
+    if summary.trades_truncated {
+        warn!(
+            pages = summary.trade_pages,
+            earliest_trade_ts_ms = ?summary.earliest_trade_ts_ms,
+            "trade paging hit --max-pages before --from; the oldest trades are missing"
+        );
+    }
+    info!(
+        out_dir = %out_dir.display(),
+        trades = summary.trades_written,
+        snapshots = summary.snapshots_written,
+        book_source = %summary.book_source,
+        "fetch_history done"
+    );
+    Ok(summary)
+}
+
+async fn fetch_market(
+    cfg: &Config,
+    client: &reqwest::Client,
+    id: &str,
+) -> anyhow::Result<(MarketId, Vec<TokenId>)> {
+    let url = format!(
+        "{}/markets",
+        cfg.polymarket.gamma_base.trim_end_matches('/')
+    );
+    let resp = api_usage::track(
+        ApiEndpoint::Gamma,
+        client.get(url).query(&[("id", id)]).send().await,
+    )
+    .with_context(|| format!("gamma markets?id={id}"))?;
+    let markets: Vec<GammaMarket> = resp.json().await.context("decode gamma market")?;
+    let Some(m) = markets.into_iter().next() else {
+        anyhow::bail!("gamma market id {id} not found");
+    };
+    let token_ids: Vec<TokenId> = serde_json::from_str(&m.clob_token_ids)
+        .with_context(|| format!("clobTokenIds for gamma market {id}"))?;
+    if token_ids.len() != 2 && token_ids.len() != 3 {
+        anyhow::bail!(
+            "gamma market {id} has {} legs (Phase 1 supports 2 or 3)",
+            token_ids.len()
+        );
+    }
+    Ok((m.condition_id.into(), token_ids))
+}
+
+#[derive(Debug, Default)]
+struct FetchedTrades {
+    /// Ascending by `ts_ms`.
+    ticks: Vec<TradeTick>,
+    pages: usize,
+    duplicated: usize,
+    truncated: bool,
+}
+
+/// Pages `/trades` (newest first) until a page is short, older than `from_ms` or `max_pages` is
+/// reached.
+async fn fetch_trades(
+    cfg: &Config,
+    client: &reqwest::Client,
+    opts: &FetchHistoryOptions,
+    market_id: &MarketId,
+    token_ids: &[TokenId],
+) -> anyhow::Result<FetchedTrades> {
+    let url = format!(
+        "{}/trades",
+        cfg.polymarket.data_api_base.trim_end_matches('/')
+    );
+    let tokens: HashSet<TokenId> = token_ids.iter().cloned().collect();
+    let mut seen: HashSet<String> = HashSet::new();
+    let mut out = FetchedTrades::default();
+
+    loop {
+        if out.pages >= opts.max_pages {
+            out.truncated = true;
+            break;
+        }
+        let offset = out.pages * opts.page_size;
+        let resp = api_usage::track(
+            ApiEndpoint::DataApiTrades,
+            client
+                .get(&url)
+                .query(&[
+                    ("limit", opts.page_size.to_string()),
+                    ("offset", offset.to_string()),
+                    ("takerOnly", cfg.shadow.trade_poll_taker_only.to_string()),
+                    ("market", market_id.to_string()),
+                ])
+                .send()
+                .await,
+        )
+        .with_context(|| format!("data-api trades offset={offset}"))?;
+        let page: Vec<DataApiTrade> = resp.json().await.context("decode data-api trades")?;
+        out.pages += 1;
+
+        let page_len = page.len();
+        let oldest = collect_page(
+            page,
+            market_id,
+            &tokens,
+            (opts.from_ms, opts.to_ms),
+            &mut seen,
+            &mut out,
+        );
+        info!(page = out.pages, page_len, oldest_ts_ms = ?oldest, "data-api trades page");
+        if page_len < opts.page_size || oldest.is_some_and(|ts| ts < opts.from_ms) {
+            break;
+        }
+    }
+
+    out.ticks.sort_by_key(|t| t.ts_ms);
+    Ok(out)
+}
+
+/// Keeps the page's trades for `market_id`'s tokens inside `[from_ms, to_ms]`, skipping
+/// duplicates by `trade_id`; returns the page's oldest exchange timestamp.
+fn collect_page(
+    page: Vec<DataApiTrade>,
+    market_id: &MarketId,
+    tokens: &HashSet<TokenId>,
+    (from_ms, to_ms): (u64, u64),
+    seen: &mut HashSet<String>,
+    out: &mut FetchedTrades,
+) -> Option<u64> {
+    let mut oldest: Option<u64> = None;
+    for t in page {
+        let ts_ms = normalize_ts_ms(t.timestamp);
+        oldest = Some(oldest.map_or(ts_ms, |o| o.min(ts_ms)));
+        if t.market_id != *market_id || !tokens.contains(&t.asset_id) {
+            continue;
+        }
+        if !(from_ms..=to_ms).contains(&ts_ms) {
+            continue;
+        }
+        let (Some(price), Some(size)) = (Price::from_f64(t.price), Qty::from_f64(t.size)) else {
+            continue;
+        };
+        if t.price < 0.0 || t.size <= 0.0 || t.price > 1.0 {
+            continue;
+        }
+        let trade_id = trade_dedup_key(
+            &t.market_id,
+            &t.asset_id,
+            ts_ms,
+            t.price,
+            t.size,
+            &t.transaction_hash,
+        );
+        if !seen.insert(trade_id.clone()) {
+            out.duplicated += 1;
+            continue;
+        }
+        out.ticks.push(TradeTick {
+            ts_ms,
+            ingest_ts_ms: ts_ms,
+            exchange_ts_ms: Some(ts_ms),
+            market_id: t.market_id,
+            token_id: t.asset_id,
+            price,
+            size,
+            trade_id,
+            side: t.side.as_deref().and_then(Side::parse),
+        });
+    }
+    oldest
+}
+
+async fn fetch_prices_history(
+    cfg: &Config,
+    client: &reqwest::Client,
+    token_id: &TokenId,
+    opts: &FetchHistoryOptions,
+) -> anyhow::Result<Vec<(u64, f64)>> {
+    let url = format!(
+        "{}/prices-history",
+        cfg.polymarket.clob_base.trim_end_matches('/')
+    );
+    let resp = api_usage::track(
+        ApiEndpoint::Clob,
+        client
+            .get(url)
+            .query(&[
+                ("market", token_id.to_string()),
+                ("startTs", (opts.from_ms / 1000).to_string()),
+                ("endTs", opts.to_ms.div_ceil(1000).to_string()),
+                ("fidelity", opts.fidelity_min.to_string()),
+            ])
+            .send()
+            .await,
+    )
+    .with_context(|| format!("prices-history market={token_id}"))?;
+    let body: PricesHistory = resp.json().await.context("decode prices-history")?;
+    Ok(body
+        .history
+        .into_iter()
+        .map(|p| (normalize_ts_ms(p.t), p.p))
+        .filter(|(ts, p)| (opts.from_ms..=opts.to_ms).contains(ts) && p.is_finite())
+        .collect())
+}
+
+/// Merges per-leg price series onto their union of timestamps, carrying each leg's last price
+/// forward; rows start once every leg has a price.
+fn align_price_series(series: &[Vec<(u64, f64)>]) -> Vec<(u64, Vec<f64>)> {
+    if series.is_empty() {
+        return Vec::new();
+    }
+    let mut ts: Vec<u64> = series.iter().flatten().map(|(t, _)| *t).collect();
+    ts.sort_unstable();
+    ts.dedup();
+
+    let mut sorted: Vec<Vec<(u64, f64)>> = series.to_vec();
+    for s in &mut sorted {
+        s.sort_by_key(|(t, _)| *t);
+    }
+    let mut cursor = vec![0usize; sorted.len()];
+    let mut last: Vec<Option<f64>> = vec![None; sorted.len()];
+    let mut out = Vec::with_capacity(ts.len());
+    for t in ts {
+        for (leg, s) in sorted.iter().enumerate() {
+            while cursor[leg] < s.len() && s[cursor[leg]].0 <= t {
+                last[leg] = Some(s[cursor[leg]].1);
+                cursor[leg] += 1;
+            }
+        }
+        if let Some(prices) = last.iter().copied().collect::<Option<Vec<f64>>>() {
+            out.push((t, prices));
+        }
+    }
+    out
+}
+
+/// One `snapshots.csv` row: `best_bid = best_ask = price`, depth unknown.
+fn snapshot_record(
+    ts_ms: u64,
+    market_id: &MarketId,
+    token_ids: &[TokenId],
+    prices: &[f64],
+) -> [String; 15] {
+    let mut cols: [String; 15] = Default::default();
+    cols[0] = ts_ms.to_string();
+    cols[1] = market_id.to_string();
+    cols[2] = token_ids.len().to_string();
+    for (i, (token_id, price)) in token_ids.iter().zip(prices).take(3).enumerate() {
+        let base = 3 + i * 4;
+        cols[base] = token_id.to_string();
+        cols[base + 1] = format!("{price:.6}");
+        cols[base + 2] = format!("{price:.6}");
+        cols[base + 3] = "NaN".to_string();
+    }
+    cols
+}
+
+/// `config.toml` (the effective config with `run.market_ids` pointed at the market, secrets
+/// redacted), `run_meta.json` and `schema_version.json`, as a recorded run has them.
+fn write_run_files(
+    cfg: &Config,
+    out_dir: &Path,
+    run_id: &str,
+    gamma_market_id: &str,
+    started_at_ms: u64,
+) -> anyhow::Result<()> {
+    let mut run_cfg = cfg.clone();
+    run_cfg.run.market_ids = vec![gamma_market_id.to_string()];
+    let mut doc = toml::Value::try_from(&run_cfg).context("serialize config")?;
+    let secret_fingerprints = config::redact_secrets(&mut doc);
+    recorder::write_run_config_snapshot(
+        out_dir,
+        &toml::to_string(&doc).context("render config.toml")?,
+    )?;
+
+    RunMeta {
+        run_id: run_id.to_string(),
+        schema_version: SCHEMA_VERSION.to_string(),
+        git_sha: crate::run_meta::env_git_sha(),
+        start_ts_unix_ms: started_at_ms,
+        config_path: "fetch-history".to_string(),
+        trade_ts_source: "exchange".to_string(),
+        notes_enum_version: "v1".to_string(),
+        trade_poll_taker_only: Some(cfg.shadow.trade_poll_taker_only),
+        sim_stress: SimStressProfile::default(),
+        abnormal_termination: None,
+        resumed_ts_unix_ms: Vec::new(),
+        provenance: Provenance::current(&run_cfg).context("collect run provenance")?,
+        recorder_durability: None,
+        secret_fingerprints: secret_fingerprints.into_iter().collect(),
+        tags: [("source".to_string(), "fetch-history".to_string())]
+            .into_iter()
+            .collect(),
+        live_interlocks: Vec::new(),
+    }
+    .write_to_dir(out_dir)?;
+
+    schema::write_schema_version_json(out_dir, SCHEMA_VERSION, started_at_ms)
+        .context("write schema_version.json")?;
+    Ok(())
+}
+
+fn default_out_dir(data_dir: &Path, run_id: &str) -> PathBuf {
+    data_dir.join("history").join(run_id)
+}
+
+fn format_run_id(now_ms: u64) -> String {
+    format!("hist_{}", crate::ulid::new_ulid(now_ms))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn trade(asset: &str, ts_s: u64, price: f64, tx: &str) -> DataApiTrade {
+        DataApiTrade {
+            asset_id: TokenId::from(asset),
+            market_id: MarketId::from("0xm"),
+            size: 5.0,
+            price,
+            timestamp: ts_s,
+            transaction_hash: tx.to_string(),
+            side: Some("BUY".to_string()),
+        }
+    }
+
+    #[test]
+    fn collect_page_filters_window_tokens_and_duplicates() {
+        let tokens: HashSet<TokenId> = ["a", "b"].into_iter().map(TokenId::from).collect();
+        let mut seen = HashSet::new();
+        let mut out = FetchedTrades::default();
+        let page = vec![
+            trade("a", 1_700_000_300, 0.4, "0x1"),
+            trade("a", 1_700_000_300, 0.4, "0x1"),
+            trade("c", 1_700_000_200, 0.4, "0x2"),
+            trade("b", 1_700_000_100, 1.5, "0x3"),
+            trade("b", 1_700_000_100, 0.6, ""),
+            trade("a", 1_699_999_000, 0.4, "0x4"),
+        ];
+        let oldest = collect_page(
+            page,
+            &MarketId::from("0xm"),
+            &tokens,
+            (1_700_000_000_000, 1_700_000_400_000),
+            &mut seen,
+            &mut out,
+        );
+        assert_eq!(oldest, Some(1_699_999_000_000));
+        assert_eq!(out.ticks.len(), 2);
+        assert_eq!(out.duplicated, 1);
+        assert_eq!(out.ticks[0].ingest_ts_ms, 1_700_000_300_000);
+        assert_eq!(out.ticks[0].side, Some(Side::Buy));
+        assert!(out.ticks[1].trade_id.starts_with("weak:"));
+    }
+
+    #[test]
+    fn align_price_series_carries_legs_forward() {
+        let series = vec![vec![(1_000, 0.4), (3_000, 0.45)], vec![(2_000, 0.5)]];
+        let rows = align_price_series(&series);
+        assert_eq!(
+            rows,
+            vec![(2_000, vec![0.4, 0.5]), (3_000, vec![0.45, 0.5])]
+        );
+        assert!(align_price_series(&[]).is_empty());
+
+        let rec = snapshot_record(
+            2_000,
+            &MarketId::from("0xm"),
+            &[TokenId::from("a"), TokenId::from("b")],
+            &rows[0].1,
+        );
+        assert_eq!(rec[2], "2");
+        assert_eq!(rec[4], rec[5]);
+        assert_eq!(rec[6], "NaN");
+        assert!(rec[11..].iter().all(String::is_empty));
+    }
+}
@@ -1,20 +1,73 @@
+//! Library side of razor: the pieces research notebooks and the helper binaries embed so they
+//! reuse the Frozen Spec instead of re-implementing it.
+//!
+//! Stable surface:
+//! - ingestion: [`rows`] (typed `ticks.csv` / `trades.csv` / `shadow_log.csv` rows),
+//!   [`recorder::csv_input_paths`] (partitioned/rotated inputs) and [`trade_store::TradeStore`]
+//!   (window volume at or better than a limit);
+//! - shadow accounting: [`accounting::settle_set`], the per-signal settlement behind
+//!   `shadow_log.csv`, at the rates of the [`fees::FeeRegistry`] model in force for the row,
+//!   plus [`replay`] to rebuild a shadow log offline from a run dir;
+//! - reporting: [`report`] (`report.json` / `report.md` from a shadow log), [`run_compare`] and
+//!   [`fill_xval`] (shadow fill predictions against SIM/LIVE fills);
+//! - historical datasets: [`history`] downloads past trades and price history into a run dir;
+//! - archived runs: [`archive::resolve_run_dir`] turns a `razor archive`d run back into a dir;
+//! - the offline subcommands: [`cli`] (`razor replay`, `razor sweep`, ...), one `run` per tool.
+//!
+//! - raw WS replay: [`feed::replay_raw_ws`] re-parses `raw_ws.jsonl` through the live feed.
+//!
+//! The rest of the async runtime (brain, shadow task, sniper, API server) stays in the binary.
+
+pub mod accounting;
+pub mod api_usage;
+pub mod archive;
+pub mod backpressure;
 pub mod brain_sweep;
 pub mod buckets;
+pub mod cli;
+#[cfg(feature = "live")]
 pub mod clob;
+#[cfg(feature = "live")]
 pub mod clob_order;
+pub mod clock;
 pub mod config;
+pub mod crash;
 pub mod dataset_split;
+pub mod disk_guard;
+pub mod errors;
+#[cfg(feature = "live")]
 pub mod eth;
 pub mod execution;
-pub mod json_util;
+pub mod feed;
+pub mod fees;
+pub mod fill_xval;
+pub mod fs_compat;
+pub mod gas;
+pub mod go_live;
+pub mod graceful_shutdown;
+pub mod health;
+pub mod history;
+pub mod live_gate;
+pub mod log_throttle;
 pub mod market_select;
+pub mod order_book;
+pub mod positions;
 pub mod reasons;
 pub mod recorder;
 pub mod replay;
 pub mod report;
+pub mod report_html;
+pub mod rows;
 pub mod run_compare;
+pub mod run_index;
 pub mod run_meta;
 pub mod schema;
+pub mod schema_adapt;
+pub mod secrets;
 pub mod shadow_sweep;
+pub mod strategy;
 pub mod trade_store;
 pub mod types;
+pub mod ulid;
+pub mod units;
+pub mod ws_msg;
@@ -0,0 +1,245 @@
+//! Layered interlocks in front of the live execution gateway. Live orders need every layer to
+//! agree: `--mode live`, `live.enabled = true`, `razor run --live`, `RAZOR_LIVE_CONFIRM` set to
+//! the token of this exact effective config, a valid `go_live_token.json` approving the config
+//! (see `go_live`), and a binary built with the `live` feature. Any
+//! layer asking for live while another disagrees refuses the start; each decision is recorded in
+//! `run_meta.json`.
+
+use serde::{Deserialize, Serialize};
+
+/// Hex chars of the effective config hash that make up the confirmation token.
+pub const CONFIRM_TOKEN_LEN: usize = 12;
+
+/// What the sniper executes against.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum GatewayDecision {
+    /// dry_run: no sniper.
+    None,
+    Sim,
+    Live,
+    /// Layers disagree; the run does not start.
+    Refused,
+}
+
+impl GatewayDecision {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            GatewayDecision::None => "none",
+            GatewayDecision::Sim => "sim",
+            GatewayDecision::Live => "live",
+            GatewayDecision::Refused => "refused",
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct InterlockCheck {
+    pub name: String,
+    pub passed: bool,
+    pub detail: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LiveInterlockDecision {
+    pub ts_ms: u64,
+    pub gateway: GatewayDecision,
+    pub checks: Vec<InterlockCheck>,
+    /// Why the gateway was chosen (or refused).
+    pub reason: String,
+}
+
+/// Everything the interlocks look at, gathered by the caller.
+#[derive(Debug, Clone)]
+pub struct LiveGateInputs {
+    /// `--mode live` / `RAZOR_MODE=live` (the live_sim pipeline with the sniper).
+    pub mode_live: bool,
+    pub config_enabled: bool,
+    pub cli_flag: bool,
+    /// `RAZOR_LIVE_CONFIRM`, if set.
+    pub env_token: Option<String>,
+    /// Hash of the effective config; its first [`CONFIRM_TOKEN_LEN`] chars are the token.
+    pub effective_config_sha256: String,
+    /// `go_live::check` of the data dir's token: `Ok` with who approved it, else why not.
+    pub go_live_token: Result<String, String>,
+    pub feature_built: bool,
+}
+
+/// The `RAZOR_LIVE_CONFIRM` value that confirms a config with this hash.
+pub fn confirm_token(effective_config_sha256: &str) -> &str {
+    let n = CONFIRM_TOKEN_LEN.min(effective_config_sha256.len());
+    &effective_config_sha256[..n]
+}
+
+pub fn decide(inputs: &LiveGateInputs, ts_ms: u64) -> LiveInterlockDecision {
+    let expected = confirm_token(&inputs.effective_config_sha256);
+    let token = inputs.env_token.as_deref().map(str::trim);
+    let token_ok = token == Some(expected);
+    let check = |name: &str, passed: bool, detail: &str| InterlockCheck {
+        name: name.to_string(),
+        passed,
+        detail: detail.to_string(),
+    };
+    let checks = vec![
+        check(
+            "mode",
+            inputs.mode_live,
+            if inputs.mode_live { "live" } else { "dry_run" },
+        ),
+        check(
+            "config",
+            inputs.config_enabled,
+            if inputs.config_enabled {
+                "live.enabled = true"
+            } else {
+                "live.enabled = false"
+            },
+        ),
+        check(
+            "cli_flag",
+            inputs.cli_flag,
+            if inputs.cli_flag {
+                "--live given"
+            } else {
+                "--live not given"
+            },
+        ),
+        check(
+            "env_token",
+            token_ok,
+            match token {
+                None => "RAZOR_LIVE_CONFIRM unset",
+                Some(_) if token_ok => "RAZOR_LIVE_CONFIRM matches this config",
+                Some(_) => "RAZOR_LIVE_CONFIRM does not match this config",
+            },
+        ),
+        check(
+            "go_live_token",
+            inputs.go_live_token.is_ok(),
+            match &inputs.go_live_token {
+                Ok(by) => by,
+                Err(why) => why,
+            },
+        ),
+        check(
+            "feature",
+            inputs.feature_built,
+            if inputs.feature_built {
+                "built with `live`"
+            } else {
+                "built without `live`"
+            },
+        ),
+    ];
+
+    let asked = inputs.config_enabled || inputs.cli_flag || token.is_some();
+    let (gateway, reason) = if !inputs.mode_live {
+        if inputs.cli_flag || token.is_some() {
+            (
+                GatewayDecision::Refused,
+                "--live / RAZOR_LIVE_CONFIRM given but the mode is dry_run".to_string(),
+            )
+        } else {
+            (GatewayDecision::None, "dry_run: no execution".to_string())
+        }
+    } else if !asked {
+        (
+            GatewayDecision::Sim,
+            "no live layer asserted: SIM gateway".to_string(),
+        )
+    } else {
+        let failed: Vec<&str> = checks
+            .iter()
+            .filter(|c| !c.passed)
+            .map(|c| c.name.as_str())
+            .collect();
+        if failed.is_empty() {
+            (
+                GatewayDecision::Live,
+                "all live interlocks agree".to_string(),
+            )
+        } else {
+            let mut reason = format!("live interlocks disagree: {} failed", failed.join(", "));
+            if !token_ok {
+                reason.push_str(&format!(
+                    " (to confirm this exact config set RAZOR_LIVE_CONFIRM={expected})"
+                ));
+            }
+            (GatewayDecision::Refused, reason)
+        }
+    };
+    LiveInterlockDecision {
+        ts_ms,
+        gateway,
+        checks,
+        reason,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn all_live() -> LiveGateInputs {
+        LiveGateInputs {
+            mode_live: true,
+            config_enabled: true,
+            cli_flag: true,
+            env_token: Some("0123456789ab".to_string()),
+            effective_config_sha256: "0123456789abcdef".to_string(),
+            go_live_token: Ok("approved by report for run_a".to_string()),
+            feature_built: true,
+        }
+    }
+
+    #[test]
+    fn live_needs_every_layer() {
+        assert_eq!(decide(&all_live(), 1).gateway, GatewayDecision::Live);
+
+        let mut i = all_live();
+        i.cli_flag = false;
+        let d = decide(&i, 1);
+        assert_eq!(d.gateway, GatewayDecision::Refused);
+        assert!(d.reason.contains("cli_flag"), "{}", d.reason);
+
+        let mut i = all_live();
+        i.env_token = Some("1".to_string());
+        let d = decide(&i, 1);
+        assert_eq!(d.gateway, GatewayDecision::Refused);
+        assert!(
+            d.reason.contains("RAZOR_LIVE_CONFIRM=0123456789ab"),
+            "{}",
+            d.reason
+        );
+
+        let mut i = all_live();
+        i.feature_built = false;
+        assert_eq!(decide(&i, 1).gateway, GatewayDecision::Refused);
+
+        let mut i = all_live();
+        i.go_live_token = Err("expired at 5".to_string());
+        let d = decide(&i, 1);
+        assert_eq!(d.gateway, GatewayDecision::Refused);
+        assert!(d.reason.contains("go_live_token"), "{}", d.reason);
+        assert!(d.checks.iter().any(|c| c.detail == "expired at 5"));
+    }
+
+    #[test]
+    fn no_live_layer_means_sim_or_none() {
+        let mut i = all_live();
+        i.config_enabled = false;
+        i.cli_flag = false;
+        i.env_token = None;
+        // Without a token SIM still runs: only asking for live needs the approval.
+        i.go_live_token = Err("no go_live_token.json".to_string());
+        assert_eq!(decide(&i, 1).gateway, GatewayDecision::Sim);
+
+        i.mode_live = false;
+        assert_eq!(decide(&i, 1).gateway, GatewayDecision::None);
+        // One config for both modes: live.enabled alone does nothing in dry_run.
+        i.config_enabled = true;
+        assert_eq!(decide(&i, 1).gateway, GatewayDecision::None);
+        i.cli_flag = true;
+        assert_eq!(decide(&i, 1).gateway, GatewayDecision::Refused);
+    }
+}
@@ -0,0 +1,111 @@
+use std::sync::atomic::{AtomicU64, Ordering};
+
+/// Default budget for `warn_throttled!`: log the first 10 occurrences, then 1 in every 1000; a
+/// site that has been quiet for a minute starts over with a fresh first 10.
+pub const DEFAULT_FIRST_N: u64 = 10;
+pub const DEFAULT_EVERY_M: u64 = 1_000;
+pub const DEFAULT_QUIET_MS: u64 = 60_000;
+
+/// Per-call-site log throttle: the first `first_n` occurrences pass, then one in every `every_m`.
+/// Occurrences that are dropped are counted and reported on the next one that passes. After
+/// `quiet_ms` without an occurrence the budget resets, so a later, separate burst (a second
+/// outage hours on) is logged like the first.
+pub struct LogThrottle {
+    first_n: u64,
+    every_m: u64,
+    quiet_ms: u64,
+    seen: AtomicU64,
+    suppressed: AtomicU64,
+    last_ms: AtomicU64,
+}
+
+impl LogThrottle {
+    pub const fn new(first_n: u64, every_m: u64, quiet_ms: u64) -> Self {
+        Self {
+            first_n,
+            every_m,
+            quiet_ms,
+            seen: AtomicU64::new(0),
+            suppressed: AtomicU64::new(0),
+            last_ms: AtomicU64::new(0),
+        }
+    }
+
+    /// Records one occurrence. Returns `Some(suppressed_since_last_log)` when it should be logged.
+    pub fn check(&self) -> Option<u64> {
+        self.check_at(crate::types::now_ms())
+    }
+
+    fn check_at(&self, now_ms: u64) -> Option<u64> {
+        let last_ms = self.last_ms.swap(now_ms, Ordering::Relaxed);
+        if now_ms.saturating_sub(last_ms) >= self.quiet_ms {
+            self.seen.store(0, Ordering::Relaxed);
+        }
+        let n = self.seen.fetch_add(1, Ordering::Relaxed);
+        let pass = n < self.first_n
+            || self.every_m <= 1
+            || (n - self.first_n + 1).is_multiple_of(self.every_m);
+        if pass {
+            Some(self.suppressed.swap(0, Ordering::Relaxed))
+        } else {
+            self.suppressed.fetch_add(1, Ordering::Relaxed);
+            None
+        }
+    }
+}
+
+/// `tracing::warn!` behind a per-call-site `LogThrottle` (first 10, then 1-in-1000, reset after
+/// a quiet minute by default).
+/// Emitted events carry a `suppressed` field with the number of dropped occurrences since the
+/// previous one.
+///
+/// `warn_throttled!(first = 5, every = 100; market_id = %id, "msg")` overrides the budget.
+macro_rules! warn_throttled {
+    (first = $first:expr, every = $every:expr; $($arg:tt)+) => {{
+        static THROTTLE: $crate::log_throttle::LogThrottle =
+            $crate::log_throttle::LogThrottle::new(
+                $first,
+                $every,
+                $crate::log_throttle::DEFAULT_QUIET_MS,
+            );
+        if let Some(suppressed) = THROTTLE.check() {
+            tracing::warn!(suppressed, $($arg)+);
+        }
+    }};
+    ($($arg:tt)+) => {
+        $crate::log_throttle::warn_throttled!(
+            first = $crate::log_throttle::DEFAULT_FIRST_N,
+            every = $crate::log_throttle::DEFAULT_EVERY_M;
+            $($arg)+
+        )
+    };
+}
+
+pub(crate) use warn_throttled;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn first_n_then_one_in_m_with_suppressed_counts() {
+        let t = LogThrottle::new(2, 3, 1_000);
+        let got: Vec<Option<u64>> = (0..8).map(|i| t.check_at(10_000 + i)).collect();
+        assert_eq!(
+            got,
+            [Some(0), Some(0), None, None, Some(2), None, None, Some(2)]
+        );
+    }
+
+    #[test]
+    fn budget_resets_after_a_quiet_period() {
+        let t = LogThrottle::new(1, 100, 1_000);
+        assert_eq!(t.check_at(10_000), Some(0));
+        assert_eq!(t.check_at(10_500), None);
+        // Still inside the burst: each gap is under quiet_ms.
+        assert_eq!(t.check_at(11_400), None);
+        // A second burst after a quiet second: logged at once, with the drops since.
+        assert_eq!(t.check_at(12_400), Some(2));
+        assert_eq!(t.check_at(12_401), None);
+    }
+}
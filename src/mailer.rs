@@ -0,0 +1,124 @@
+use std::path::Path;
+
+use anyhow::Context as _;
+use lettre::message::header::ContentType;
+use lettre::message::{Attachment, MultiPart, SinglePart};
+use lettre::transport::smtp::authentication::Credentials;
+use lettre::{AsyncSmtpTransport, AsyncTransport as _, Message, Tokio1Executor};
+
+use crate::config::{EmailConfig, SmtpTls};
+use crate::report::Report;
+use crate::schema::{FILE_REPORT_HTML, FILE_REPORT_JSON, FILE_REPORT_MD};
+
+pub fn report_subject(report: &Report) -> String {
+    format!(
+        "[razor] {} run={} signals={} total_shadow_pnl={:.4} avg_set_ratio={:.3}",
+        if report.verdict.go { "GO" } else { "NO-GO" },
+        report.run_id,
+        report.totals.signals,
+        report.totals.total_shadow_pnl,
+        report.totals.avg_set_ratio,
+    )
+}
+
+fn report_body(report: &Report) -> String {
+    let mut body = format!(
+        "run_id: {}\nverdict: {}\n",
+        report.run_id,
+        if report.verdict.go { "GO" } else { "NO-GO" }
+    );
+    for r in &report.verdict.reasons {
+        body.push_str(&format!("- {r}\n"));
+    }
+    body.push_str("\nreport.md, report.html and report.json are attached.\n");
+    body
+}
+
+/// Builds the report mail with `report.md` / `report.html` / `report.json` from `run_dir`
+/// attached.
+pub fn build_report_message(
+    cfg: &EmailConfig,
+    run_dir: &Path,
+    report: &Report,
+) -> anyhow::Result<Message> {
+    let mut builder = Message::builder()
+        .from(cfg.from.parse().context("parse email.from")?)
+        .subject(report_subject(report));
+    for to in &cfg.to {
+        builder = builder.to(to
+            .parse()
+            .with_context(|| format!("parse email.to entry {to:?}"))?);
+    }
+
+    let mut parts = MultiPart::mixed().singlepart(SinglePart::plain(report_body(report)));
+    for (name, content_type) in [
+        (FILE_REPORT_MD, "text/markdown; charset=utf-8"),
+        (FILE_REPORT_HTML, "text/html; charset=utf-8"),
+        (FILE_REPORT_JSON, "application/json"),
+    ] {
+        let path = run_dir.join(name);
+        let bytes = std::fs::read(&path).with_context(|| format!("read {}", path.display()))?;
+        let ct = ContentType::parse(content_type).context("attachment content type")?;
+        parts = parts.singlepart(Attachment::new(name.to_string()).body(bytes, ct));
+    }
+
+    builder.multipart(parts).context("build report email")
+}
+
+/// Mails the end-of-run report. The SMTP password (if `username` is set) comes from
+/// `cfg.password_env`.
+pub async fn send_report(cfg: &EmailConfig, run_dir: &Path, report: &Report) -> anyhow::Result<()> {
+    let msg = build_report_message(cfg, run_dir, report)?;
+
+    let mut transport = match cfg.tls {
+        SmtpTls::Starttls => AsyncSmtpTransport::<Tokio1Executor>::starttls_relay(&cfg.smtp_host)
+            .context("smtp starttls relay")?,
+        SmtpTls::Tls => {
+            AsyncSmtpTransport::<Tokio1Executor>::relay(&cfg.smtp_host).context("smtp tls relay")?
+        }
+        SmtpTls::None => AsyncSmtpTransport::<Tokio1Executor>::builder_dangerous(&cfg.smtp_host),
+    }
+    .port(cfg.smtp_port);
+    if !cfg.username.trim().is_empty() {
+        let password = crate::secrets::require(&cfg.password_env, "SMTP password")?;
+        transport = transport.credentials(Credentials::new(cfg.username.clone(), password));
+    }
+
+    transport
+        .build()
+        .send(msg)
+        .await
+        .context("send report email")?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::report::{self, ReportThresholds};
+    use crate::types::now_ms;
+
+    #[test]
+    fn message_carries_verdict_subject_and_report_attachments() {
+        let dir = std::env::temp_dir().join(format!("razor_mailer_{}", now_ms()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let r = report::generate_report_files(&dir, "run_x", ReportThresholds::default(), None)
+            .unwrap();
+
+        let cfg = EmailConfig {
+            enabled: true,
+            smtp_host: "localhost".to_string(),
+            from: "razor@example.com".to_string(),
+            to: vec!["ops@example.com".to_string()],
+            ..EmailConfig::default()
+        };
+        let msg = build_report_message(&cfg, &dir, &r).unwrap();
+        let raw = String::from_utf8(msg.formatted()).unwrap();
+        assert!(raw.contains("Subject: [razor] NO-GO run=run_x"));
+        assert!(raw.contains("filename=\"report.md\""));
+        assert!(raw.contains("filename=\"report.json\""));
+        assert!(raw.contains("filename=\"report.html\""));
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
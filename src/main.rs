@@ -1,38 +1,92 @@
+mod accounting;
+mod api;
+mod api_usage;
+mod archive;
+mod backpressure;
 mod brain;
 mod buckets;
 mod calibration;
+#[cfg(feature = "live")]
 mod clob;
+#[cfg(feature = "live")]
 mod clob_order;
+mod clock;
 mod config;
+mod crash;
+mod demo_feed;
+mod disk_guard;
+mod errors;
+mod escalation;
+#[cfg(feature = "live")]
 mod eth;
 mod execution;
+mod export;
 mod feed;
+mod fees;
+mod gas;
+mod gc;
+mod go_live;
 mod graceful_shutdown;
 mod health;
-mod json_util;
+mod health_push;
+mod live_gate;
+mod log_throttle;
+mod mailer;
+mod manifest;
+mod metrics;
+mod migrate;
+mod orchestrate;
+mod order_book;
+mod positions;
+mod preflight;
+mod profiler;
+mod progress;
 mod reasons;
+mod reconcile;
 mod recorder;
+mod reload;
 mod report;
+mod report_html;
+mod resume;
+mod risk;
+mod rows;
 mod run_context;
+mod run_index;
 mod run_meta;
+mod run_readme;
 mod schema;
+mod schema_adapt;
+mod schema_dump;
+mod secrets;
 mod shadow;
 #[allow(dead_code)]
 mod shadow_sweep;
+mod signal_trace;
 mod snapshot_logger;
 mod sniper;
+mod strategy;
+mod top;
 mod trade_store;
 mod types;
+mod ulid;
+mod units;
+mod upload;
+mod user_feed;
+mod ws_msg;
 
 use anyhow::{anyhow, Context as _};
 use clap::Parser;
+use razor::cli::{self, LogFormat};
+use razor::fs_compat;
 use std::time::Duration;
 use tokio::sync::{mpsc, watch};
 use tracing::{info, warn};
-use tracing_subscriber::EnvFilter;
 
 use crate::calibration::CalibrationEvent;
-use crate::types::{MarketSnapshot, Signal, Strategy, TradeTick};
+use crate::types::{MarketSnapshot, Signal, SignalId, Strategy, TradeTick};
+
+const TRADE_CHANNEL_CAP: usize = 50_000;
+const SIGNAL_CHANNEL_CAP: usize = 10_000;
 
 #[derive(Parser, Debug)]
 #[command(
@@ -41,28 +95,330 @@ use crate::types::{MarketSnapshot, Signal, Strategy, TradeTick};
     about = "Project Razor (Phase 1 dry-run; Phase 2 live-sim)"
 )]
 struct Args {
-    #[arg(long, default_value = "config/config.toml")]
+    #[arg(long, global = true, default_value = "config/config.toml")]
     config: String,
+    /// Use `<config-dir>/base.toml` + `<config-dir>/profiles/<NAME>.toml` + optional
+    /// `<config-dir>/local.toml` (merged, later wins) instead of `--config`.
+    #[arg(long, global = true, conflicts_with = "config")]
+    profile: Option<String>,
+    #[arg(long, global = true, default_value = "config")]
+    config_dir: std::path::PathBuf,
+    /// Log output format: `text` (human) or `json` (one object per line, for Loki/Elastic).
+    #[arg(long, global = true, value_enum, default_value_t = LogFormat::Text)]
+    log_format: LogFormat,
+    /// Without a subcommand, `razor [OPTIONS]` is `razor run [OPTIONS]`.
+    #[command(flatten)]
+    run: RunArgs,
+    #[command(subcommand)]
+    command: Option<Command>,
+}
+
+impl Args {
+    fn config_source(&self) -> config::ConfigSource {
+        match &self.profile {
+            Some(name) => config::ConfigSource::Profile {
+                dir: self.config_dir.clone(),
+                name: name.clone(),
+            },
+            None => config::ConfigSource::File(self.config.clone().into()),
+        }
+    }
+}
+
+#[derive(clap::Args, Debug)]
+struct RunArgs {
     /// Override mode (`dry_run` or `live`).
     #[arg(long)]
     mode: Option<String>,
+    /// Log every pipeline stage (brain emit, fan-out, sniper actions, shadow settle) of one
+    /// signal_id, or `all`, at INFO with a `signal_corr` field.
+    #[arg(long)]
+    trace_signal: Option<signal_trace::TraceSignal>,
+    /// Continue a crashed run in this run dir under its run_id, using its config.toml snapshot.
+    #[arg(long)]
+    resume: Option<std::path::PathBuf>,
+    /// Label the run (`key=value`, repeatable); merged over `[run.labels]` into run_meta.json,
+    /// report.json and runs_summary.csv.
+    #[arg(long = "tag", value_name = "KEY=VALUE", value_parser = run_meta::parse_tag)]
+    tags: Vec<(String, String)>,
+    /// Ask for the live execution gateway; also needs `--mode live`, `live.enabled = true`,
+    /// `RAZOR_LIVE_CONFIRM=<token>`, a `go_live_token.json` approving the config (GO report or
+    /// `razor approve`) and a `--features live` build.
+    #[arg(long)]
+    live: bool,
+}
+
+#[derive(clap::Subcommand, Debug)]
+enum Command {
+    /// Record a run: feed, brain, shadow (and live_sim sniper), then the Day14 report.
+    Run(RunArgs),
+    /// Record a run against the synthetic `[demo]` feed instead of Polymarket (no network).
+    Demo(RunArgs),
+    /// Pick markets to record by probing Gamma candidates (read-only).
+    #[command(alias = "market-select")]
+    Select(cli::select::SelectArgs),
+    /// Download a market's past trades and price history into a replayable run dir (read-only).
+    FetchHistory(cli::fetch_history::FetchHistoryArgs),
+    /// Rebuild a run's shadow log offline from snapshots.csv / trades.csv.
+    Replay(cli::replay::ReplayArgs),
+    /// Sweep shadow ledger assumptions (fill_share / dump_slippage) on a fixed shadow_log.csv.
+    #[command(alias = "shadow-sweep")]
+    Sweep(cli::sweep::SweepArgs),
+    /// Replay a run across a grid of brain thresholds.
+    BrainSweep(cli::brain_sweep::BrainSweepArgs),
+    /// Per-day scores and walk-forward splits of a run's shadow log.
+    Split(cli::split::SplitArgs),
+    /// Print the Day14 report (Phase 1 frozen verdict) for a run.
+    Report(cli::report::ReportArgs),
+    /// Approve a GO run's config for the live gateway (writes a signed go_live_token.json).
+    Approve(cli::approve::ApproveArgs),
+    /// Compare multiple run dirs.
+    Compare(cli::compare::CompareArgs),
+    /// Cross-validate shadow fill predictions against the SIM/LIVE fills of the same signals.
+    Xval(cli::xval::XvalArgs),
+    /// Live terminal dashboard for a run dir (tails health.jsonl, shadow_log.csv, snapshots.csv).
+    Top {
+        #[arg(long, default_value = "data/run_latest")]
+        run_dir: std::path::PathBuf,
+        #[arg(long, default_value_t = 1000)]
+        refresh_ms: u64,
+    },
+    /// Post-run export of a run dir's CSVs.
+    Export {
+        #[command(subcommand)]
+        target: ExportTarget,
+    },
+    /// Re-check a run dir against its manifest.json (sizes, row counts, SHA-256).
+    Verify { run_dir: std::path::PathBuf },
+    /// Upgrade an older run dir's frozen CSVs to the current schema (written alongside).
+    Migrate {
+        run_dir: std::path::PathBuf,
+        /// Output dir (default: <run_dir>/migrated_<SCHEMA_VERSION>).
+        #[arg(long)]
+        out: Option<std::path::PathBuf>,
+    },
+    /// Check a config (plus optional tool patches) without starting a run.
+    Config {
+        #[command(subcommand)]
+        action: ConfigAction,
+    },
+    /// Machine-readable descriptions of the recorded formats.
+    Schema {
+        #[command(subcommand)]
+        action: SchemaAction,
+    },
+    /// Data retention: prune old runs to report/shadow skeletons and expire raw_ws.jsonl.
+    Gc {
+        #[arg(long, default_value = "data")]
+        data_dir: std::path::PathBuf,
+        /// Newest runs left untouched (0 keeps all).
+        #[arg(long, default_value_t = 20)]
+        keep_runs: usize,
+        /// Delete raw_ws.jsonl segments older than this many days in kept runs (0 disables).
+        #[arg(long, default_value_t = 7)]
+        raw_ws_max_age_days: u64,
+        /// Print what would be removed without deleting anything.
+        #[arg(long)]
+        dry_run: bool,
+    },
+    /// Launch the `[[runs]]` of a plan file as child `razor run` processes, supervise them and
+    /// write a combined health log and summary.
+    Orchestrate { plan: std::path::PathBuf },
+    /// Dry-run the run's prerequisites (markets, WS, trades API, disk, clock, secrets) and print
+    /// a pass/fail checklist. Exits non-zero on any failure.
+    Preflight {
+        /// How long to listen for book events on the market WS.
+        #[arg(long, default_value = "10s", value_parser = razor::units::parse_duration_ms)]
+        ws_wait: u64,
+    },
+    /// Compress a finished run dir into `<run_dir>.tar.zst` (manifest-verified) and remove it.
+    Archive {
+        run_dir: std::path::PathBuf,
+        /// zstd compression level (1-22).
+        #[arg(long, default_value_t = 9)]
+        level: i32,
+    },
+}
+
+#[derive(clap::Subcommand, Debug)]
+enum ExportTarget {
+    /// Typed DuckDB tables + join views (writes export_duckdb.sql, loads it with the duckdb CLI).
+    Duckdb {
+        #[arg(long, default_value = "data/run_latest")]
+        run_dir: std::path::PathBuf,
+        /// Output database (default: <run_dir>/run.duckdb).
+        #[arg(long)]
+        out: Option<std::path::PathBuf>,
+        /// Only write the SQL script.
+        #[arg(long)]
+        sql_only: bool,
+    },
+}
+
+#[derive(clap::Subcommand, Debug)]
+enum SchemaAction {
+    /// JSON Schemas for ticks/trades/snapshots/shadow_log/trade_log rows and health.jsonl lines.
+    Dump {
+        /// Write `<file>.schema.json` per format plus `columns.md` here (default: one JSON
+        /// document on stdout).
+        #[arg(long)]
+        out: Option<std::path::PathBuf>,
+    },
+}
+
+#[derive(clap::Subcommand, Debug)]
+enum ConfigAction {
+    /// Parse, layer and validate the `--config`/`--profile` config; errors and warnings point at
+    /// file:line:col. Exits non-zero on any error.
+    Validate(cli::config_validate::ValidateArgs),
 }
 
 #[tokio::main]
 async fn main() -> anyhow::Result<()> {
-    let filter = EnvFilter::try_from_default_env().unwrap_or_else(|_| EnvFilter::new("info"));
-    tracing_subscriber::fmt().with_env_filter(filter).init();
-
     let args = Args::parse();
-    let mode = resolve_mode(args.mode.as_deref())?;
+    let config_source = args.config_source();
+    let (mut run_args, demo) = match args.command {
+        None => (args.run, false),
+        Some(Command::Run(run_args)) => (run_args, false),
+        Some(Command::Demo(run_args)) => (run_args, true),
+        Some(Command::Select(select)) => {
+            cli::init_tracing(args.log_format);
+            return cli::select::run(&lib_config_source(config_source), select).await;
+        }
+        Some(Command::FetchHistory(a)) => {
+            cli::init_tracing(args.log_format);
+            return cli::fetch_history::run(&lib_config_source(config_source), a).await;
+        }
+        Some(Command::Config {
+            action: ConfigAction::Validate(a),
+        }) => {
+            return run_tool(args.log_format, || {
+                cli::config_validate::run(&lib_config_source(config_source), a)
+            })
+        }
+        Some(Command::Replay(a)) => {
+            return run_tool(args.log_format, || {
+                cli::replay::run(a, Some(razor::feed::replay_raw_ws))
+            })
+        }
+        Some(Command::Sweep(a)) => return run_tool(args.log_format, || cli::sweep::run(a)),
+        Some(Command::BrainSweep(a)) => {
+            return run_tool(args.log_format, || cli::brain_sweep::run(a))
+        }
+        Some(Command::Split(a)) => return run_tool(args.log_format, || cli::split::run(a)),
+        Some(Command::Report(a)) => return run_tool(args.log_format, || cli::report::run(a)),
+        Some(Command::Approve(a)) => return run_tool(args.log_format, || cli::approve::run(a)),
+        Some(Command::Compare(a)) => return run_tool(args.log_format, || cli::compare::run(a)),
+        Some(Command::Xval(a)) => return run_tool(args.log_format, || cli::xval::run(a)),
+        Some(Command::Top {
+            run_dir,
+            refresh_ms,
+        }) => {
+            // No tracing subscriber: log lines would tear the terminal UI.
+            return tokio::task::spawn_blocking(move || top::run(run_dir, refresh_ms)).await?;
+        }
+        Some(Command::Export {
+            target:
+                ExportTarget::Duckdb {
+                    run_dir,
+                    out,
+                    sql_only,
+                },
+        }) => {
+            let run_dir = archive::resolve_run_dir(&run_dir)?;
+            return export::export_duckdb(&run_dir, out, sql_only);
+        }
+        Some(Command::Verify { run_dir }) => {
+            return manifest::run_verify(&archive::resolve_run_dir(&run_dir)?)
+        }
+        Some(Command::Migrate { run_dir, out }) => {
+            let run_dir = archive::resolve_run_dir(&run_dir)?;
+            return migrate::run_migrate(&run_dir, out);
+        }
+        Some(Command::Schema {
+            action: SchemaAction::Dump { out },
+        }) => return schema_dump::run_schema_dump(out.as_deref()),
+        Some(Command::Gc {
+            data_dir,
+            keep_runs,
+            raw_ws_max_age_days,
+            dry_run,
+        }) => {
+            let policy = gc::GcPolicy {
+                keep_runs,
+                raw_ws_max_age_days,
+            };
+            return gc::run_gc(&data_dir, policy, dry_run);
+        }
+        Some(Command::Archive { run_dir, level }) => return gc::run_archive(&run_dir, level),
+        Some(Command::Orchestrate { plan }) => {
+            cli::init_tracing(args.log_format);
+            let launcher = orchestrate::Launcher {
+                config: args.config,
+                profile: args.profile,
+                config_dir: args.config_dir,
+                log_format: args.log_format,
+            };
+            return orchestrate::run_orchestrate(&plan, &launcher).await;
+        }
+        Some(Command::Preflight { ws_wait }) => {
+            cli::init_tracing(args.log_format);
+            return preflight::run_preflight(config_source, Duration::from_millis(ws_wait)).await;
+        }
+    };
+    cli::init_tracing(args.log_format);
+    if let Some(filter) = run_args.trace_signal {
+        signal_trace::init(filter);
+    }
 
-    let cfg_path = std::path::PathBuf::from(&args.config);
-    let cfg_raw = std::fs::read_to_string(&cfg_path).context("read config")?;
-    let cfg: config::Config = toml::from_str(&cfg_raw).context("parse config")?;
+    let mode = resolve_mode(run_args.mode.as_deref())?;
+    if demo {
+        if run_args.resume.is_some() || run_args.live {
+            return Err(anyhow!("razor demo does not support --resume or --live"));
+        }
+        run_args
+            .tags
+            .push(("source".to_string(), "demo".to_string()));
+    }
+
+    let (cfg_source, cfg_raw) = match run_args.resume.as_deref() {
+        Some(run_dir) => {
+            let (path, raw) = resume::read_config_snapshot(run_dir).context("resume")?;
+            // Both sides with the current RAZOR__* overrides, which also restore any values
+            // redacted from the snapshot.
+            let layered = |s: &str| {
+                let mut v = toml::from_str::<toml::Value>(s).ok()?;
+                config::apply_env_overrides(&mut v, std::env::vars()).ok()?;
+                Some(v)
+            };
+            let given = config_source.read().ok().and_then(|s| layered(&s));
+            if given.is_some_and(|v| Some(v) != layered(&raw)) {
+                warn!(
+                    config = %config_source.describe(),
+                    "--config/--profile differs from the run's config.toml snapshot; resuming with the snapshot"
+                );
+            }
+            (config::ConfigSource::File(path), raw)
+        }
+        None => {
+            let raw = config_source.read()?;
+            (config_source, raw)
+        }
+    };
+    let (cfg, cfg_doc, env_overrides) =
+        config::parse_with_env_overrides(&cfg_raw).context("parse config")?;
     cfg.validate().context("validate config")?;
+    for o in &env_overrides {
+        info!(var = %o.var, "config override: {}", o.display_redacted());
+    }
+    let unrestored = config::redacted_keys(&cfg_doc);
+    if !unrestored.is_empty() {
+        warn!(keys = ?unrestored, "config still holds <redacted> values; set them via RAZOR__* env");
+    }
+    secrets::init(&cfg.secrets).context("load secrets keystore")?;
+    recorder::configure_group_commit(&cfg.recorder);
 
     std::fs::create_dir_all(&cfg.run.data_dir).context("create data_dir")?;
-    let run_ctx = run_context::create_run_context(&cfg.run.data_dir).context("init run context")?;
     if cfg.schema_version != schema::SCHEMA_VERSION {
         return Err(anyhow!(
             "schema_version mismatch: config={} code={}",
@@ -70,48 +426,172 @@ async fn main() -> anyhow::Result<()> {
             schema::SCHEMA_VERSION
         ));
     }
-    schema::write_schema_version_json(&run_ctx.run_dir, &cfg.schema_version, run_ctx.start_ts_ms)
-        .context("write schema_version.json")?;
-    recorder::write_run_config_snapshot(&run_ctx.run_dir, &cfg_raw)?;
+    if let Some(stop_ms) = cfg.run.schedule.stop_ms(crate::types::now_ms())? {
+        if run_args.resume.is_none() && stop_ms <= crate::types::now_ms() {
+            return Err(anyhow!(
+                "run.schedule.stop_at={} has already passed",
+                cfg.run.schedule.stop_at
+            ));
+        }
+    }
+    if let Some(start_ms) = cfg.run.schedule.start_ms()? {
+        let now = crate::types::now_ms();
+        if start_ms > now {
+            info!(
+                start_at = %cfg.run.schedule.start_at,
+                wait_ms = start_ms - now,
+                "run.schedule: waiting for start_at"
+            );
+            tokio::select! {
+                _ = clock::sleep_until_wall_ms(start_ms) => {}
+                name = graceful_shutdown::stop_signal() => {
+                    info!(signal = name, "stop signal received before run.schedule.start_at; exiting");
+                    return Ok(());
+                }
+            }
+        }
+    }
+    let live_decision = live_gate::decide(
+        &live_gate::LiveGateInputs {
+            mode_live: matches!(mode, Mode::LiveSim),
+            config_enabled: cfg.live.enabled,
+            cli_flag: run_args.live,
+            env_token: std::env::var("RAZOR_LIVE_CONFIRM").ok(),
+            effective_config_sha256: run_meta::config_sha256(&cfg)?,
+            go_live_token: go_live::check(
+                &cfg.run.data_dir,
+                &cfg,
+                secrets::get(&cfg.live.approval_key_env).as_deref(),
+                crate::types::now_ms(),
+            )
+            .map(|t| {
+                format!(
+                    "approved by {} for {} until {}",
+                    t.approved_by, t.run_id, t.expires_ts_ms
+                )
+            }),
+            feature_built: cfg!(feature = "live"),
+        },
+        crate::types::now_ms(),
+    );
+    let (run_ctx, resume_state) = match run_args.resume.as_deref() {
+        Some(run_dir) => {
+            let mut meta = run_meta::RunMeta::read_from_dir(run_dir).context("resume")?;
+            let run_ctx =
+                run_context::resume_run_context(run_dir, &meta.run_id, meta.start_ts_unix_ms)
+                    .context("resume run context")?;
+            let state = resume::load_resume_state(
+                run_dir,
+                crate::types::now_ms(),
+                cfg.shadow.trade_retention_ms,
+            )
+            .context("load resume state")?;
+            meta.resumed_ts_unix_ms.push(crate::types::now_ms());
+            meta.tags.extend(run_args.tags.iter().cloned());
+            meta.live_interlocks.push(live_decision.clone());
+            meta.write_to_dir(run_dir).context("write run_meta.json")?;
+            (run_ctx, Some(state))
+        }
+        None => {
+            let run_ctx =
+                run_context::create_run_context(&cfg.run.data_dir).context("init run context")?;
+            schema::write_schema_version_json(
+                &run_ctx.run_dir,
+                &cfg.schema_version,
+                run_ctx.start_ts_ms,
+            )
+            .context("write schema_version.json")?;
+            recorder::write_run_config_snapshot(
+                &run_ctx.run_dir,
+                &config::config_snapshot(&cfg_raw, &cfg_doc, &env_overrides)?,
+            )?;
+            run_meta::RunMeta {
+                run_id: run_ctx.run_id.clone(),
+                schema_version: schema::SCHEMA_VERSION.to_string(),
+                git_sha: run_meta::env_git_sha(),
+                start_ts_unix_ms: run_ctx.start_ts_ms,
+                config_path: cfg_source.describe(),
+                trade_ts_source: "local".to_string(),
+                notes_enum_version: "v1".to_string(),
+                trade_poll_taker_only: Some(cfg.shadow.trade_poll_taker_only),
+                sim_stress: sim_stress_profile_from_env(),
+                abnormal_termination: None,
+                resumed_ts_unix_ms: Vec::new(),
+                provenance: run_meta::Provenance::current(&cfg)
+                    .context("collect run provenance")?,
+                recorder_durability: Some((&cfg.recorder).into()),
+                secret_fingerprints: secrets::fingerprints(&cfg)
+                    .into_iter()
+                    .chain(config::redact_secrets(&mut cfg_doc.clone()))
+                    .collect(),
+                tags: cfg
+                    .run
+                    .labels
+                    .clone()
+                    .into_iter()
+                    .chain(run_args.tags.iter().cloned())
+                    .collect(),
+                live_interlocks: vec![live_decision.clone()],
+            }
+            .write_to_dir(&run_ctx.run_dir)
+            .context("write run_meta.json")?;
+            (run_ctx, None)
+        }
+    };
     recorder::write_run_meta_json(
         &run_ctx.run_dir,
         &run_ctx.run_id,
         run_ctx.start_ts_ms,
         &mode,
     )?;
-    run_meta::RunMeta {
+    let mut run_index_entry = run_index::RunIndexEntry {
         run_id: run_ctx.run_id.clone(),
-        schema_version: schema::SCHEMA_VERSION.to_string(),
-        git_sha: run_meta::env_git_sha(),
-        start_ts_unix_ms: run_ctx.start_ts_ms,
-        config_path: cfg_path.display().to_string(),
-        trade_ts_source: "local".to_string(),
-        notes_enum_version: "v1".to_string(),
-        trade_poll_taker_only: Some(cfg.shadow.trade_poll_taker_only),
-        sim_stress: sim_stress_profile_from_env(),
-    }
-    .write_to_dir(&run_ctx.run_dir)
-    .context("write run_meta.json")?;
+        dir: run_ctx.run_id.clone(),
+        start_ts_ms: run_ctx.start_ts_ms,
+        end_ts_ms: None,
+        mode: mode.to_string(),
+        verdict: None,
+    };
+    if let Err(e) = run_index::record(&run_ctx.run_dir, &run_index_entry) {
+        warn!(error = %e, "runs_index.jsonl start write failed");
+    }
     ensure_data_latest_file_links(&cfg.run.data_dir)
         .context("ensure data/ latest-file symlinks")?;
 
     let flush_guard = recorder::RecorderGuard::new(run_ctx.run_dir.clone());
 
+    // Every log line from here on (including spawned tasks, see `spawn_in_run`) carries run_id.
+    let _run_span = tracing::info_span!("run", run_id = %run_ctx.run_id).entered();
+    razor::fs_compat::warn_if_long_path(&run_ctx.run_dir);
+    let schedule_stop_ms = cfg.run.schedule.stop_ms(run_ctx.start_ts_ms)?;
+    if let Some(stop_ms) = schedule_stop_ms {
+        info!(stop_ms, "run.schedule: stop time set");
+    }
+
     info!(
-        run_id = %run_ctx.run_id,
         run_dir = %run_ctx.run_dir.display(),
         schema_version = %cfg.schema_version,
         %mode,
         "run start"
     );
 
-    if matches!(mode, Mode::LiveSim) && cfg.live.enabled && !env_flag("RAZOR_LIVE_CONFIRM") {
-        return Err(anyhow!(
-            "refusing to start: live.enabled=true requires RAZOR_LIVE_CONFIRM=1 (safety gate)"
-        ));
+    for c in &live_decision.checks {
+        info!(check = %c.name, passed = c.passed, detail = %c.detail, "live interlock");
+    }
+    info!(
+        gateway = live_decision.gateway.as_str(),
+        reason = %live_decision.reason,
+        "live interlock decision"
+    );
+    if live_decision.gateway == live_gate::GatewayDecision::Refused {
+        return Err(anyhow!("refusing to start: {}", live_decision.reason));
     }
 
-    let markets = feed::fetch_markets(&cfg).await.context("fetch markets")?;
+    let markets = if demo {
+        demo_feed::markets(&cfg.demo)
+    } else {
+        feed::fetch_markets(&cfg).await.context("fetch markets")?
+    };
     let (mut binary, mut triangle) = (0usize, 0usize);
     for m in &markets {
         match m.strategy().context("market strategy")? {
@@ -127,7 +607,8 @@ async fn main() -> anyhow::Result<()> {
         "loaded markets"
     );
 
-    let (trade_tx, trade_rx) = mpsc::channel::<TradeTick>(50_000);
+    let (trade_tx, trade_rx) =
+        backpressure::channel::<TradeTick>("trade", TRADE_CHANNEL_CAP, cfg.backpressure.trade);
     let (snap_tx, snap_rx) = watch::channel::<Option<MarketSnapshot>>(None);
 
     let ticks_path = run_ctx.run_dir.join(schema::FILE_TICKS);
@@ -140,55 +621,192 @@ async fn main() -> anyhow::Result<()> {
 
     let (shutdown_tx, shutdown_rx) = graceful_shutdown::channel();
 
+    // SIGHUP re-reads the config file; tasks that support it follow `reload_rx`.
+    let (reload_tx, reload_rx) = watch::channel(cfg.clone());
+
     let health_counters = std::sync::Arc::new(health::HealthCounters::default());
+    crash::install_panic_hook(
+        run_ctx.run_dir.clone(),
+        run_ctx.run_id.clone(),
+        health_counters.clone(),
+    );
     let (health_tx, health_handle) = health::spawn_health_writer(
         run_ctx.run_dir.join(schema::FILE_HEALTH_JSONL),
         health_counters.clone(),
+        reload_rx.clone(),
         shutdown_rx.clone(),
     )
     .context("start health writer")?;
+    let reload_handle = spawn_in_run(
+        "reload",
+        reload::run_reloader(
+            cfg_source.clone(),
+            reload_tx,
+            health_tx.clone(),
+            shutdown_rx.clone(),
+        ),
+    );
+    health_counters.track_markets(markets.iter().map(|m| m.market_id.as_str()));
+    health_counters.track_channel(trade_tx.stats());
+
+    let disk_handle = spawn_in_run(
+        "disk_guard",
+        disk_guard::run(
+            cfg.disk.clone(),
+            cfg.run.data_dir.clone(),
+            health_counters.clone(),
+            health_tx.clone(),
+            shutdown_tx.clone(),
+            shutdown_rx.clone(),
+        ),
+    );
 
-    let ws_handle = tokio::spawn(feed::run_market_ws(
-        cfg.clone(),
-        markets.clone(),
-        snap_tx,
-        ticks_path,
-        raw_ws_path,
-        health_counters.clone(),
-        shutdown_rx.clone(),
-    ));
+    let risk_handle = spawn_in_run(
+        "risk",
+        risk::run(
+            cfg.risk.clone(),
+            health_counters.clone(),
+            health_tx.clone(),
+            shutdown_rx.clone(),
+        ),
+    );
 
-    let snapshots_handle = tokio::spawn(snapshot_logger::run_snapshot_logger(
-        snapshots_path,
-        snap_rx.clone(),
-        cfg.run.snapshot_log_interval_ms,
-        shutdown_rx.clone(),
-    ));
+    let ws_reconnect = std::sync::Arc::new(tokio::sync::Notify::new());
+    let (demo_trade_tx, demo_trade_rx) = demo_feed::trade_queue();
+    let (ws_trade_tx, ws_trade_rx) = match cfg.shadow.trade_feed {
+        config::TradeFeed::Ws if !demo => {
+            let (tx, rx) = feed::ws_trade_queue();
+            (Some(tx), Some(rx))
+        }
+        _ => (None, None),
+    };
+    let ws_handle = if demo {
+        spawn_in_run(
+            "ws",
+            demo_feed::run_demo_books(
+                cfg.clone(),
+                markets.clone(),
+                snap_tx,
+                demo_trade_tx,
+                ticks_path,
+                health_counters.clone(),
+                shutdown_rx.clone(),
+            ),
+        )
+    } else {
+        spawn_in_run(
+            "ws",
+            feed::run_market_ws(
+                cfg.clone(),
+                markets.clone(),
+                snap_tx,
+                ticks_path,
+                raw_ws_path,
+                health_counters.clone(),
+                health_tx.clone(),
+                ws_reconnect.clone(),
+                ws_trade_tx,
+                shutdown_rx.clone(),
+            ),
+        )
+    };
 
-    let trades_handle = tokio::spawn(feed::run_trades_poller(
-        cfg.clone(),
-        markets.clone(),
-        trade_tx,
-        trades_path,
-        health_counters.clone(),
-        health_tx.clone(),
-        shutdown_rx.clone(),
+    let snapshots_handle = spawn_in_run(
+        "snapshots",
+        snapshot_logger::run_snapshot_logger(
+            snapshots_path,
+            snap_rx.clone(),
+            cfg.run.snapshot_log_interval_ms,
+            shutdown_rx.clone(),
+        ),
+    );
+
+    let first_signal_id = SignalId(resume_state.as_ref().map_or(1, |r| r.next_signal_id));
+    let dedup_seed = resume_state
+        .as_ref()
+        .map(|r| r.dedup_seed())
+        .unwrap_or_default();
+    let trade_arrivals = std::sync::Arc::new(buckets::TradeArrivals::new(
+        cfg.bucket_classifier.v2.window_ms,
     ));
+    if let Some(state) = &resume_state {
+        for t in &state.recent_trades {
+            trade_arrivals.record(&t.market_id, t.ingest_ts_ms);
+        }
+        // The shadow worker reloads its TradeStore from trades.csv itself, before settling.
+        info!(
+            recent_trades = state.recent_trades.len(),
+            first_signal_id = first_signal_id.get(),
+            "resuming run"
+        );
+    }
+
+    let trades_handle = if demo {
+        spawn_in_run(
+            "trades",
+            demo_feed::run_demo_trades(
+                cfg.clone(),
+                demo_trade_rx,
+                trade_tx,
+                trades_path,
+                health_counters.clone(),
+                trade_arrivals.clone(),
+                shutdown_rx.clone(),
+            ),
+        )
+    } else {
+        spawn_in_run(
+            "trades",
+            feed::run_trades_poller(
+                cfg.clone(),
+                markets.clone(),
+                trade_tx,
+                trades_path,
+                health_counters.clone(),
+                health_tx.clone(),
+                trade_arrivals.clone(),
+                dedup_seed,
+                ws_trade_rx,
+                shutdown_rx.clone(),
+            ),
+        )
+    };
 
     let health_log_handle = {
         let counters = health_counters.clone();
         let snap_rx = snap_rx.clone();
+        let health_tx = health_tx.clone();
+        let ws_reconnect = ws_reconnect.clone();
+        let mut health_cfg = cfg.health.clone();
+        let mut reload = reload_rx.clone();
         let mut shutdown = shutdown_rx.clone();
-        tokio::spawn(async move {
-            let mut interval = tokio::time::interval(Duration::from_secs(5));
+        spawn_in_run("health_log", async move {
+            let mut interval =
+                tokio::time::interval(Duration::from_millis(health_cfg.log_interval_ms));
             interval.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
-            const STALE_WARN_MS: u64 = 30_000;
+            let mut last_forced_reconnect_ms: u64 = 0;
+            // Markets that have never produced data age from task start.
+            let tracking_start_ms = crate::types::now_ms();
+            let mut stale_feeds = health::StaleEdges::default();
+            let mut stale_markets = health::StaleEdges::default();
 
             loop {
                 tokio::select! {
                     _ = shutdown.changed() => {
                         if *shutdown.borrow() { break; }
                     }
+                    Ok(()) = reload.changed() => {
+                        let reloaded = reload.borrow().health.clone();
+                        if reloaded.log_interval_ms != health_cfg.log_interval_ms {
+                            interval = tokio::time::interval(Duration::from_millis(
+                                reloaded.log_interval_ms,
+                            ));
+                            interval
+                                .set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+                        }
+                        health_cfg = reloaded;
+                        continue;
+                    }
                     _ = interval.tick() => {}
                 }
                 if *shutdown.borrow() {
@@ -219,68 +837,302 @@ async fn main() -> anyhow::Result<()> {
                     trades_dropped = snap.trades_dropped,
                     trades_duplicated = snap.trades_duplicated,
                     snapshots_stale_skipped = snap.snapshots_stale_skipped,
+                    snapshots_conflated = snap.snapshots_conflated,
                     signals_emitted = snap.signals_emitted,
                     shadow_processed = snap.shadow_processed,
                     "health"
                 );
 
-                if snap.last_tick_ingest_ms > 0 {
-                    let age = now_ms.saturating_sub(snap.last_tick_ingest_ms);
-                    if age > STALE_WARN_MS {
-                        warn!(age_ms = age, "no ticks observed recently");
+                let feeds = [
+                    (
+                        "ticks",
+                        snap.last_tick_ingest_ms,
+                        health_cfg.max_tick_staleness_ms,
+                    ),
+                    (
+                        "trades",
+                        snap.last_trade_ingest_ms,
+                        health_cfg.max_trade_staleness_ms,
+                    ),
+                ];
+                for (feed, last_ms, threshold_ms) in feeds {
+                    let age_ms = now_ms.saturating_sub(last_ms);
+                    let is_stale = last_ms != 0 && threshold_ms != 0 && age_ms > threshold_ms;
+                    let policy = health_cfg.staleness_policy;
+                    if let Some(stale) = stale_feeds.update(feed, is_stale) {
+                        if stale {
+                            warn!(
+                                feed,
+                                age_ms,
+                                threshold_ms,
+                                policy = policy.as_str(),
+                                "feed stale"
+                            );
+                        } else {
+                            info!(feed, age_ms, "feed fresh again");
+                        }
+                        let _ = health_tx.try_send(health::HealthLine::FeedStale {
+                            ts_ms: now_ms,
+                            feed: feed.to_string(),
+                            age_ms,
+                            threshold_ms,
+                            policy: policy.as_str().to_string(),
+                            stale,
+                        });
+                    }
+                    if !is_stale {
+                        continue;
+                    }
+
+                    match policy {
+                        config::StalenessPolicy::Warn => {}
+                        config::StalenessPolicy::Reconnect => {
+                            // Trades are polled over stateless HTTP; only the WS can be reset.
+                            if feed == "ticks"
+                                && now_ms.saturating_sub(last_forced_reconnect_ms) >= threshold_ms
+                            {
+                                last_forced_reconnect_ms = now_ms;
+                                warn!(age_ms, "deadman: forcing ws reconnect");
+                                ws_reconnect.notify_one();
+                            }
+                        }
+                        config::StalenessPolicy::Terminate => {
+                            return Err(anyhow!(
+                                "deadman: {feed} stale for {age_ms}ms (threshold {threshold_ms}ms)"
+                            ));
+                        }
                     }
                 }
-                if snap.last_trade_ingest_ms > 0 {
-                    let age = now_ms.saturating_sub(snap.last_trade_ingest_ms);
-                    if age > STALE_WARN_MS {
-                        warn!(age_ms = age, "no trades observed recently");
+
+                // Per-market freshness: warn-only and edge-triggered, so a single dead market is
+                // visible even while the global counters are kept fresh by the others.
+                for m in counters.market_freshness() {
+                    let feeds = [
+                        (
+                            "ticks",
+                            m.last_tick_ingest_ms,
+                            health_cfg.max_market_tick_staleness_ms,
+                        ),
+                        (
+                            "trades",
+                            m.last_trade_ingest_ms,
+                            health_cfg.max_market_trade_staleness_ms,
+                        ),
+                    ];
+                    for (feed, last_ms, threshold_ms) in feeds {
+                        if threshold_ms == 0 {
+                            continue;
+                        }
+                        let age_ms = now_ms.saturating_sub(last_ms.max(tracking_start_ms));
+                        let is_stale = age_ms > threshold_ms;
+                        let Some(is_stale) =
+                            stale_markets.update((m.market_id.clone(), feed), is_stale)
+                        else {
+                            continue;
+                        };
+                        if is_stale {
+                            warn!(market_id = %m.market_id, feed, age_ms, threshold_ms, "market stale");
+                        } else {
+                            info!(market_id = %m.market_id, feed, "market fresh again");
+                        }
+                        let _ = health_tx.try_send(health::HealthLine::MarketStale {
+                            ts_ms: now_ms,
+                            market_id: m.market_id.clone(),
+                            feed: feed.to_string(),
+                            age_ms,
+                            threshold_ms,
+                            stale: is_stale,
+                        });
                     }
                 }
             }
+            Ok::<(), anyhow::Error>(())
         })
     };
 
-    let (brain_handle, worker_handle) = match mode {
-        Mode::DryRun => {
-            let (signal_tx, signal_rx) = mpsc::channel::<Signal>(10_000);
+    let report_thresholds = report::ReportThresholds {
+        min_total_shadow_pnl: cfg.report.min_total_shadow_pnl,
+        min_avg_set_ratio: cfg.report.min_avg_set_ratio,
+    };
 
-            let brain_handle = tokio::spawn(brain::run(
-                cfg.clone(),
+    // Observer only: a failing progress summary is logged but never stops the run.
+    let progress_handle = (cfg.report.progress_interval_ms > 0).then(|| {
+        spawn_in_run(
+            "progress_summary",
+            progress::run_progress_summarizer(
+                run_ctx.run_dir.clone(),
                 run_ctx.run_id.clone(),
-                markets.clone(),
-                snap_rx.clone(),
-                signal_tx,
-                health_counters.clone(),
+                run_ctx.start_ts_ms,
+                cfg.report.progress_interval_ms,
+                cfg.report.target_days,
+                report_thresholds,
                 shutdown_rx.clone(),
-            ));
-
-            let worker_handle = tokio::spawn(shadow::run(
-                cfg.clone(),
-                markets.clone(),
-                trade_rx,
-                signal_rx,
-                shadow_path,
+            ),
+        )
+    });
+
+    let push_handle = (!cfg.health.push_url.trim().is_empty()).then(|| {
+        spawn_in_run(
+            "health_push",
+            health_push::run_health_pusher(
+                cfg.health.clone(),
+                run_ctx.run_id.clone(),
                 health_counters.clone(),
+                reload_rx.clone(),
                 shutdown_rx.clone(),
-            ));
+            ),
+        )
+    });
+
+    let profile_handle = (cfg.health.profile_interval_ms > 0).then(|| {
+        spawn_in_run(
+            "profiler",
+            profiler::run_profiler(
+                run_ctx.run_dir.join(schema::FILE_PROFILE_JSONL),
+                cfg.health.profile_interval_ms,
+                shutdown_rx.clone(),
+            ),
+        )
+    });
+
+    let api_usage_handle = spawn_in_run(
+        "api_usage",
+        api_usage::run_api_usage_writer(
+            run_ctx.run_dir.join(schema::FILE_API_USAGE_JSONL),
+            shutdown_rx.clone(),
+        ),
+    );
+
+    let run_state = cfg.api.enabled.then(|| {
+        std::sync::Arc::new(api::RunState::new(
+            run_ctx.run_id.clone(),
+            mode.to_string(),
+            run_ctx.start_ts_ms,
+            reload_rx.clone(),
+            cfg.api.recent_signals,
+        ))
+    });
+    let api_handle = match run_state.clone() {
+        Some(state) => {
+            let listener = tokio::net::TcpListener::bind(&cfg.api.bind)
+                .await
+                .with_context(|| format!("bind api {}", cfg.api.bind))?;
+            info!(bind = %cfg.api.bind, "read-only api listening");
+            Some(spawn_in_run(
+                "api",
+                api::serve(
+                    listener,
+                    state,
+                    health_counters.clone(),
+                    snap_rx.clone(),
+                    shutdown_rx.clone(),
+                ),
+            ))
+        }
+        None => None,
+    };
+    let metrics_handle = match cfg.metrics.listen_addr.as_deref() {
+        Some(addr) => {
+            let listener = tokio::net::TcpListener::bind(addr)
+                .await
+                .with_context(|| format!("bind metrics {addr}"))?;
+            info!(listen_addr = %addr, "prometheus metrics listening");
+            Some(spawn_in_run(
+                "metrics",
+                metrics::serve(listener, health_counters.clone(), shutdown_rx.clone()),
+            ))
+        }
+        None => None,
+    };
+
+    let clock = clock::system();
+    let (brain_handle, worker_handle) = match mode {
+        Mode::DryRun => {
+            let (signal_tx, signal_rx) = backpressure::channel::<Signal>(
+                "signal",
+                SIGNAL_CHANNEL_CAP,
+                cfg.backpressure.signal,
+            );
+            health_counters.track_channel(signal_tx.stats());
+
+            let brain_handle = spawn_in_run(
+                "brain",
+                brain::run(
+                    cfg.clone(),
+                    run_ctx.run_id.clone(),
+                    markets.clone(),
+                    snap_rx.clone(),
+                    signal_tx,
+                    health_counters.clone(),
+                    trade_arrivals.clone(),
+                    first_signal_id,
+                    clock.clone(),
+                    reload_rx.clone(),
+                    shutdown_rx.clone(),
+                ),
+            );
+
+            let worker_handle = spawn_in_run(
+                "worker",
+                shadow::run(
+                    cfg.clone(),
+                    markets.clone(),
+                    trade_rx,
+                    signal_rx,
+                    shadow_path,
+                    health_counters.clone(),
+                    run_state.clone(),
+                    clock.clone(),
+                    // No calibration in dry_run: the fill shares stay as configured.
+                    watch::channel(cfg.buckets.clone()).1,
+                    shutdown_rx.clone(),
+                ),
+            );
 
             (brain_handle, worker_handle)
         }
         Mode::LiveSim => {
-            let (brain_signal_tx, mut brain_signal_rx) = mpsc::channel::<Signal>(10_000);
-            let (shadow_signal_tx, shadow_signal_rx) = mpsc::channel::<Signal>(10_000);
-            let (sniper_signal_tx, sniper_signal_rx) = mpsc::channel::<Signal>(10_000);
+            let (brain_signal_tx, mut brain_signal_rx) = backpressure::channel::<Signal>(
+                "signal",
+                SIGNAL_CHANNEL_CAP,
+                cfg.backpressure.signal,
+            );
+            let (shadow_signal_tx, shadow_signal_rx) = backpressure::channel::<Signal>(
+                "shadow_signal",
+                SIGNAL_CHANNEL_CAP,
+                cfg.backpressure.shadow_signal,
+            );
+            let (sniper_signal_tx, sniper_signal_rx) = backpressure::channel::<Signal>(
+                "sniper_signal",
+                SIGNAL_CHANNEL_CAP,
+                cfg.backpressure.sniper_signal,
+            );
+            for tx_stats in [
+                brain_signal_tx.stats(),
+                shadow_signal_tx.stats(),
+                sniper_signal_tx.stats(),
+            ] {
+                health_counters.track_channel(tx_stats);
+            }
             let (calibration_tx, calibration_rx) = mpsc::channel::<CalibrationEvent>(10_000);
-
-            let brain_handle = tokio::spawn(brain::run(
-                cfg.clone(),
-                run_ctx.run_id.clone(),
-                markets.clone(),
-                snap_rx.clone(),
-                brain_signal_tx,
-                health_counters.clone(),
-                shutdown_rx.clone(),
-            ));
+            let (fill_share_tx, fill_share_rx) = watch::channel(cfg.buckets.clone());
+
+            let brain_handle = spawn_in_run(
+                "brain",
+                brain::run(
+                    cfg.clone(),
+                    run_ctx.run_id.clone(),
+                    markets.clone(),
+                    snap_rx.clone(),
+                    brain_signal_tx,
+                    health_counters.clone(),
+                    trade_arrivals.clone(),
+                    first_signal_id,
+                    clock.clone(),
+                    reload_rx.clone(),
+                    shutdown_rx.clone(),
+                ),
+            );
 
             let mut shutdown = shutdown_rx.clone();
             let signal_tee_fut = async move {
@@ -299,12 +1151,22 @@ async fn main() -> anyhow::Result<()> {
                                 continue;
                             }
 
-                            if shadow_signal_tx.try_send(sig.clone()).is_err() {
-                                warn!(signal_id = sig.signal_id, "shadow signal channel full/closed; dropped");
+                            let signal_id = sig.signal_id.get();
+                            let shadow_ok = shadow_signal_tx
+                                .send(sig.clone())
+                                .await
+                                .is_ok_and(|o| o.is_queued());
+                            if !shadow_ok {
+                                log_throttle::warn_throttled!(signal_id, "shadow signal channel full/closed; dropped");
                             }
-                            if sniper_signal_tx.try_send(sig).is_err() {
-                                warn!("sniper signal channel full/closed; dropped signal");
+                            let sniper_ok = sniper_signal_tx
+                                .send(sig)
+                                .await
+                                .is_ok_and(|o| o.is_queued());
+                            if !sniper_ok {
+                                log_throttle::warn_throttled!("sniper signal channel full/closed; dropped signal");
                             }
+                            signal_trace::trace_signal!(signal_id, "fanout_enqueue", shadow_ok, sniper_ok);
                         }
                     }
                 }
@@ -318,15 +1180,26 @@ async fn main() -> anyhow::Result<()> {
                 shadow_signal_rx,
                 shadow_path,
                 health_counters.clone(),
+                run_state.clone(),
+                clock.clone(),
+                fill_share_rx,
                 shutdown_rx.clone(),
             );
 
+            let escalator = escalation::Escalator::from_config(&cfg, &run_ctx.run_id)
+                .context("init escalation")?;
+            let gas_price = gas::GasPriceFeed::default();
             let sniper_fut = sniper::run(
                 cfg.clone(),
                 snap_rx.clone(),
                 sniper_signal_rx,
                 trade_log_path,
                 calibration_tx,
+                escalator,
+                clock.clone(),
+                live_decision.gateway == live_gate::GatewayDecision::Live,
+                gas_price.clone(),
+                positions::PositionBook::default(),
                 shutdown_rx.clone(),
             );
 
@@ -335,11 +1208,36 @@ async fn main() -> anyhow::Result<()> {
                 calibration_rx,
                 calibration_log_path,
                 run_ctx.run_dir.clone(),
+                fill_share_tx,
+                health_tx.clone(),
+                shutdown_rx.clone(),
+            );
+
+            let reconcile_fut = reconcile::run(
+                cfg.clone(),
+                markets.clone(),
+                run_ctx.run_dir.clone(),
+                health_counters.clone(),
                 shutdown_rx.clone(),
             );
 
-            let worker_handle = tokio::spawn(async move {
-                tokio::try_join!(signal_tee_fut, shadow_fut, sniper_fut, calibration_fut)?;
+            let gas_oracle_fut = gas::run_oracle(
+                cfg.gas.clone(),
+                cfg.polymarket.http_connect_timeout_ms,
+                cfg.polymarket.http_timeout_ms,
+                gas_price,
+                shutdown_rx.clone(),
+            );
+
+            let worker_handle = spawn_in_run("worker", async move {
+                tokio::try_join!(
+                    signal_tee_fut,
+                    shadow_fut,
+                    sniper_fut,
+                    calibration_fut,
+                    reconcile_fut,
+                    gas_oracle_fut
+                )?;
                 Ok::<(), anyhow::Error>(())
             });
 
@@ -354,9 +1252,16 @@ async fn main() -> anyhow::Result<()> {
     let mut worker_handle = Some(worker_handle);
     let mut health_handle = Some(health_handle);
     let mut health_log_handle = Some(health_log_handle);
+    let mut disk_handle = Some(disk_handle);
+    let mut risk_handle = Some(risk_handle);
 
     enum ExitReason {
-        CtrlC,
+        /// ctrl-c, SIGTERM, SIGQUIT or a Windows console event (see `graceful_shutdown`).
+        Signal(&'static str),
+        /// A supervised task without its own arm below panicked (see `crash::spawn_supervised`).
+        TaskPanic(&'static str),
+        /// `[run.schedule]` stop time reached.
+        Schedule,
         Ws,
         Snapshots,
         Trades,
@@ -364,6 +1269,26 @@ async fn main() -> anyhow::Result<()> {
         Worker,
         HealthWriter,
         HealthLog,
+        DiskGuard,
+        Risk,
+    }
+
+    impl ExitReason {
+        fn task(&self) -> &'static str {
+            match self {
+                ExitReason::Signal(name) | ExitReason::TaskPanic(name) => name,
+                ExitReason::Schedule => "schedule",
+                ExitReason::Ws => "ws",
+                ExitReason::Snapshots => "snapshots",
+                ExitReason::Trades => "trades",
+                ExitReason::Brain => "brain",
+                ExitReason::Worker => "worker",
+                ExitReason::HealthWriter => "health_writer",
+                ExitReason::HealthLog => "health_log",
+                ExitReason::DiskGuard => "disk_guard",
+                ExitReason::Risk => "risk",
+            }
+        }
     }
 
     let mut first_err: Option<anyhow::Error> = None;
@@ -377,7 +1302,7 @@ async fn main() -> anyhow::Result<()> {
                     if first_err.is_none() { first_err = Some(add_context(e, "ws task failed")); }
                 }
                 Err(e) => {
-                    if first_err.is_none() { first_err = Some(add_context(anyhow!(e), "ws task join failed")); }
+                    if first_err.is_none() { first_err = Some(join_error(e, "ws task join failed")); }
                 }
             }
             ExitReason::Ws
@@ -390,7 +1315,7 @@ async fn main() -> anyhow::Result<()> {
                     if first_err.is_none() { first_err = Some(add_context(e, "snapshots task failed")); }
                 }
                 Err(e) => {
-                    if first_err.is_none() { first_err = Some(add_context(anyhow!(e), "snapshots task join failed")); }
+                    if first_err.is_none() { first_err = Some(join_error(e, "snapshots task join failed")); }
                 }
             }
             ExitReason::Snapshots
@@ -403,7 +1328,7 @@ async fn main() -> anyhow::Result<()> {
                     if first_err.is_none() { first_err = Some(add_context(e, "trades task failed")); }
                 }
                 Err(e) => {
-                    if first_err.is_none() { first_err = Some(add_context(anyhow!(e), "trades task join failed")); }
+                    if first_err.is_none() { first_err = Some(join_error(e, "trades task join failed")); }
                 }
             }
             ExitReason::Trades
@@ -416,7 +1341,7 @@ async fn main() -> anyhow::Result<()> {
                     if first_err.is_none() { first_err = Some(add_context(e, "brain task failed")); }
                 }
                 Err(e) => {
-                    if first_err.is_none() { first_err = Some(add_context(anyhow!(e), "brain task join failed")); }
+                    if first_err.is_none() { first_err = Some(join_error(e, "brain task join failed")); }
                 }
             }
             ExitReason::Brain
@@ -429,7 +1354,7 @@ async fn main() -> anyhow::Result<()> {
                     if first_err.is_none() { first_err = Some(add_context(e, "worker task failed")); }
                 }
                 Err(e) => {
-                    if first_err.is_none() { first_err = Some(add_context(anyhow!(e), "worker task join failed")); }
+                    if first_err.is_none() { first_err = Some(join_error(e, "worker task join failed")); }
                 }
             }
             ExitReason::Worker
@@ -437,117 +1362,140 @@ async fn main() -> anyhow::Result<()> {
         res = health_handle.as_mut().unwrap() => {
             health_handle.take();
             if let Err(e) = res {
-                if first_err.is_none() { first_err = Some(add_context(anyhow!(e), "health writer join failed")); }
+                if first_err.is_none() { first_err = Some(join_error(e, "health writer join failed")); }
             }
             ExitReason::HealthWriter
         }
         res = health_log_handle.as_mut().unwrap() => {
             health_log_handle.take();
-            if let Err(e) = res {
-                if first_err.is_none() { first_err = Some(add_context(anyhow!(e), "health log task join failed")); }
-            }
-            ExitReason::HealthLog
-        }
-        _ = tokio::signal::ctrl_c() => {
-            info!("ctrl-c received; shutting down");
-            ExitReason::CtrlC
-        }
-    };
-
-    graceful_shutdown::request(&shutdown_tx);
-
-    if let Some(h) = ws_handle.take() {
-        match h.await {
-            Ok(Ok(())) => {}
-            Ok(Err(e)) => {
-                if first_err.is_none() {
-                    first_err = Some(add_context(e, "ws task failed"));
+            match res {
+                Ok(Ok(())) => {}
+                Ok(Err(e)) => {
+                    if first_err.is_none() { first_err = Some(add_context(e, "health log task failed")); }
                 }
-            }
-            Err(e) => {
-                if first_err.is_none() {
-                    first_err = Some(add_context(anyhow!(e), "ws task join failed"));
+                Err(e) => {
+                    if first_err.is_none() { first_err = Some(join_error(e, "health log task join failed")); }
                 }
             }
+            ExitReason::HealthLog
         }
-    }
-    if let Some(h) = snapshots_handle.take() {
-        match h.await {
-            Ok(Ok(())) => {}
-            Ok(Err(e)) => {
-                if first_err.is_none() {
-                    first_err = Some(add_context(e, "snapshots task failed"));
+        res = disk_handle.as_mut().unwrap() => {
+            disk_handle.take();
+            match res {
+                Ok(Ok(())) => {}
+                Ok(Err(e)) => {
+                    if first_err.is_none() { first_err = Some(add_context(e, "disk guard task failed")); }
                 }
-            }
-            Err(e) => {
-                if first_err.is_none() {
-                    first_err = Some(add_context(anyhow!(e), "snapshots task join failed"));
+                Err(e) => {
+                    if first_err.is_none() { first_err = Some(join_error(e, "disk guard join failed")); }
                 }
             }
+            ExitReason::DiskGuard
         }
-    }
-    if let Some(h) = trades_handle.take() {
-        match h.await {
-            Ok(Ok(())) => {}
-            Ok(Err(e)) => {
-                if first_err.is_none() {
-                    first_err = Some(add_context(e, "trades task failed"));
+        res = risk_handle.as_mut().unwrap() => {
+            risk_handle.take();
+            match res {
+                Ok(Ok(())) => {}
+                Ok(Err(e)) => {
+                    if first_err.is_none() { first_err = Some(add_context(e, "risk kill switch stopped the run")); }
                 }
-            }
-            Err(e) => {
-                if first_err.is_none() {
-                    first_err = Some(add_context(anyhow!(e), "trades task join failed"));
+                Err(e) => {
+                    if first_err.is_none() { first_err = Some(join_error(e, "risk task join failed")); }
                 }
             }
+            ExitReason::Risk
         }
-    }
-    if let Some(h) = brain_handle.take() {
-        match h.await {
-            Ok(Ok(())) => {}
-            Ok(Err(e)) => {
-                if first_err.is_none() {
-                    first_err = Some(add_context(e, "brain task failed"));
-                }
-            }
-            Err(e) => {
-                if first_err.is_none() {
-                    first_err = Some(add_context(anyhow!(e), "brain task join failed"));
-                }
-            }
+        task = crash::task_panicked() => {
+            first_err = Some(task_panic_error(task));
+            ExitReason::TaskPanic(task)
+        }
+        name = graceful_shutdown::stop_signal() => {
+            info!(signal = name, "stop signal received; shutting down");
+            ExitReason::Signal(name)
         }
+        _ = scheduled_stop(schedule_stop_ms) => {
+            info!(stop_ms = schedule_stop_ms, "run.schedule stop time reached; shutting down");
+            ExitReason::Schedule
+        }
+    };
+
+    if let Some(e) = &first_err {
+        let _ = health_tx.try_send(health::HealthLine::TaskError {
+            ts_ms: crate::types::now_ms(),
+            task: exit_reason.task().to_string(),
+            code: errors::code_of(e),
+            error: format!("{e:#}"),
+            fatal: true,
+        });
     }
-    if let Some(h) = worker_handle.take() {
-        match h.await {
-            Ok(Ok(())) => {}
-            Ok(Err(e)) => {
-                if first_err.is_none() {
-                    first_err = Some(add_context(e, "worker task failed"));
-                }
-            }
-            Err(e) => {
-                if first_err.is_none() {
-                    first_err = Some(add_context(anyhow!(e), "worker task join failed"));
-                }
-            }
+
+    graceful_shutdown::request(&shutdown_tx);
+
+    // Tasks still running at the deadline (e.g. a WS read that never returns) are aborted so exit
+    // cannot hang; the report and the recorder flush guard below still run.
+    let deadline_ms = cfg.run.shutdown_deadline_ms;
+    let deadline =
+        (deadline_ms > 0).then(|| tokio::time::Instant::now() + Duration::from_millis(deadline_ms));
+    let mut joiner = TaskJoiner::new(deadline, first_err.take());
+    // Tasks whose error fails the run (if nothing did yet), then observers that only log.
+    for (h, task) in [
+        (ws_handle.take(), "ws"),
+        (snapshots_handle.take(), "snapshots"),
+        (trades_handle.take(), "trades"),
+        (brain_handle.take(), "brain"),
+        (worker_handle.take(), "worker"),
+        (health_log_handle.take(), "health_log"),
+        (disk_handle.take(), "disk_guard"),
+        (risk_handle.take(), "risk"),
+    ] {
+        if let Some(h) = h {
+            joiner.join(h, task, true).await;
         }
     }
-    if let Some(h) = health_log_handle.take() {
-        if let Err(e) = h.await {
-            if first_err.is_none() {
-                first_err = Some(add_context(anyhow!(e), "health log task join failed"));
-            }
+    for (h, task) in [
+        (api_handle, "api"),
+        (metrics_handle, "metrics"),
+        (profile_handle, "profiler"),
+        (Some(api_usage_handle), "api_usage"),
+        (push_handle, "health_push"),
+        (progress_handle, "progress_summary"),
+        (Some(reload_handle), "reload"),
+    ] {
+        if let Some(h) = h {
+            joiner.join(h, task, false).await;
         }
     }
     if let Some(h) = health_handle.take() {
-        if let Err(e) = h.await {
-            if first_err.is_none() {
-                first_err = Some(add_context(anyhow!(e), "health writer join failed"));
-            }
+        joiner.join(h, "health_writer", true).await;
+    }
+    let TaskJoiner {
+        aborted,
+        first_err: joined_err,
+        ..
+    } = joiner;
+    first_err = joined_err;
+
+    if !aborted.is_empty() {
+        warn!(
+            deadline_ms,
+            tasks = ?aborted,
+            "shutdown deadline passed; aborted tasks that were still running"
+        );
+        // The health writer has stopped by now, so the line is appended directly.
+        let line = health::HealthLine::ShutdownDeadline {
+            ts_ms: crate::types::now_ms(),
+            deadline_ms,
+            aborted: aborted.iter().map(|t| t.to_string()).collect(),
+        };
+        if let Err(e) = health::append_line(&run_ctx.run_dir.join(schema::FILE_HEALTH_JSONL), &line)
+        {
+            warn!(error = %e, "health.jsonl shutdown_deadline write failed");
         }
     }
 
     match exit_reason {
-        ExitReason::CtrlC => {}
+        ExitReason::Signal(_) | ExitReason::Schedule => {}
+        ExitReason::TaskPanic(task) => info!(task, "panicked task ended the run"),
         ExitReason::Ws => info!("ws task exited"),
         ExitReason::Snapshots => info!("snapshots task exited"),
         ExitReason::Trades => info!("trades task exited"),
@@ -555,14 +1503,17 @@ async fn main() -> anyhow::Result<()> {
         ExitReason::Worker => info!("worker task exited"),
         ExitReason::HealthWriter => info!("health writer task exited"),
         ExitReason::HealthLog => info!("health log task exited"),
+        ExitReason::DiskGuard => info!("disk guard task exited"),
+        ExitReason::Risk => info!("risk task exited"),
     }
 
-    let thresholds = report::ReportThresholds {
-        min_total_shadow_pnl: cfg.report.min_total_shadow_pnl,
-        min_avg_set_ratio: cfg.report.min_avg_set_ratio,
-    };
-    let report = report::generate_report_files(&run_ctx.run_dir, &run_ctx.run_id, thresholds)
-        .context("generate report")?;
+    let report = report::generate_report_files(
+        &run_ctx.run_dir,
+        &run_ctx.run_id,
+        report_thresholds,
+        Some(health_counters.settle_latency()),
+    )
+    .context("generate report")?;
     info!(
         run_id = %report.run_id,
         total_shadow_pnl = report.totals.total_shadow_pnl,
@@ -570,11 +1521,83 @@ async fn main() -> anyhow::Result<()> {
         go = report.verdict.go,
         "report written"
     );
+    for d in &report.degraded_files {
+        warn!(
+            file = %d.file,
+            degraded_ts_ms = d.ts_ms,
+            error = %d.error,
+            "recorded file is incomplete: writes stopped after an error (on_write_error=degrade)"
+        );
+    }
+    if let Err(e) = run_readme::write_run_readme(&run_ctx.run_dir, &report) {
+        warn!(error = %e, "run README write failed");
+    }
+    run_index_entry.end_ts_ms = Some(crate::types::now_ms());
+    run_index_entry.verdict = Some(if report.verdict.go { "GO" } else { "NO_GO" }.to_string());
+    if let Err(e) = run_index::record(&run_ctx.run_dir, &run_index_entry) {
+        warn!(error = %e, "runs_index.jsonl end write failed");
+    }
+    if report.verdict.go {
+        match secrets::get(&cfg.live.approval_key_env) {
+            Some(key) => match go_live::issue(
+                &cfg.run.data_dir,
+                &cfg,
+                &run_ctx.run_id,
+                "report",
+                false,
+                &key,
+                crate::types::now_ms(),
+            ) {
+                Ok(t) => info!(
+                    expires_ts_ms = t.expires_ts_ms,
+                    "go_live_token.json written"
+                ),
+                Err(e) => warn!(error = %e, "go_live_token.json write failed"),
+            },
+            None => info!(
+                key_env = %cfg.live.approval_key_env,
+                "GO verdict but no approval key set; no go-live token"
+            ),
+        }
+    }
+    // Recipients may have been changed by a SIGHUP reload.
+    let email_cfg = reload_rx.borrow().email.clone();
+    if email_cfg.enabled {
+        match mailer::send_report(&email_cfg, &run_ctx.run_dir, &report).await {
+            Ok(()) => info!(to = ?email_cfg.to, "report emailed"),
+            Err(e) => warn!(error = %e, "report email failed"),
+        }
+    }
 
     flush_guard
         .flush_all()
         .context("final flush/sync of run outputs")?;
 
+    match manifest::write_manifest(&run_ctx.run_dir, &run_ctx.run_id) {
+        Ok(m) => info!(artifacts = m.artifacts.len(), "manifest written"),
+        Err(e) => warn!(error = %e, "manifest write failed"),
+    }
+
+    if let Err(e) = publish_data_latest_files(&cfg.run.data_dir, &run_ctx.run_dir, &run_ctx.run_id)
+    {
+        warn!(error = %e, "latest alias publish failed");
+    }
+
+    if cfg.upload.enabled {
+        match upload::upload_run(&cfg.upload, &run_ctx.run_dir, &run_ctx.run_id).await {
+            Ok(m) => info!(
+                objects = m.objects.len(),
+                bytes = m.objects.iter().map(|o| o.bytes).sum::<u64>(),
+                "run uploaded"
+            ),
+            Err(e) => warn!(error = %e, "run upload failed"),
+        }
+    }
+
+    // A panic during shutdown (or in a task that is never joined) still fails the process.
+    if first_err.is_none() {
+        first_err = crash::panicked_task().map(task_panic_error);
+    }
     if let Some(e) = first_err {
         return Err(e);
     }
@@ -583,7 +1606,141 @@ async fn main() -> anyhow::Result<()> {
     Ok(())
 }
 
-fn add_context(err: anyhow::Error, ctx: &'static str) -> anyhow::Error {
+/// Offline subcommands share the run's logging setup but not its runtime or run dir.
+/// The offline tools link the library's copy of the config types.
+fn lib_config_source(source: config::ConfigSource) -> razor::config::ConfigSource {
+    match source {
+        config::ConfigSource::File(path) => razor::config::ConfigSource::File(path),
+        config::ConfigSource::Profile { dir, name } => {
+            razor::config::ConfigSource::Profile { dir, name }
+        }
+    }
+}
+
+fn run_tool(
+    log_format: LogFormat,
+    tool: impl FnOnce() -> anyhow::Result<()>,
+) -> anyhow::Result<()> {
+    cli::init_tracing(log_format);
+    tool()
+}
+
+/// `tokio::spawn` that keeps the caller's `run` span so task logs stay tagged with run_id; a panic
+/// in the task ends the run (see `crash::spawn_supervised`).
+fn spawn_in_run<F>(task: &'static str, fut: F) -> tokio::task::JoinHandle<F::Output>
+where
+    F: std::future::Future + Send + 'static,
+    F::Output: Send + 'static,
+{
+    crash::spawn_supervised(task, fut)
+}
+
+/// Resolves at the `[run.schedule]` stop time; never when the run has none.
+async fn scheduled_stop(stop_ms: Option<u64>) {
+    match stop_ms {
+        Some(ms) => clock::sleep_until_wall_ms(ms).await,
+        None => std::future::pending().await,
+    }
+}
+
+/// Awaits `h` until the shutdown `deadline` (`None` = no deadline); past it the task is aborted,
+/// recorded in `aborted` and `None` is returned.
+async fn join_by<T>(
+    mut h: tokio::task::JoinHandle<T>,
+    deadline: Option<tokio::time::Instant>,
+    task: &'static str,
+    aborted: &mut Vec<&'static str>,
+) -> Option<Result<T, tokio::task::JoinError>> {
+    let Some(deadline) = deadline else {
+        return Some(h.await);
+    };
+    match tokio::time::timeout_at(deadline, &mut h).await {
+        Ok(res) => Some(res),
+        Err(_) => {
+            h.abort();
+            aborted.push(task);
+            None
+        }
+    }
+}
+
+/// What a joined run task returns: its own error, or nothing (the health writer).
+trait TaskOutput {
+    fn into_result(self) -> anyhow::Result<()>;
+}
+
+impl TaskOutput for () {
+    fn into_result(self) -> anyhow::Result<()> {
+        Ok(())
+    }
+}
+
+impl TaskOutput for anyhow::Result<()> {
+    fn into_result(self) -> anyhow::Result<()> {
+        self
+    }
+}
+
+/// Joins the run's tasks after shutdown was requested, all against one deadline: stragglers are
+/// aborted and named in `aborted`, and the first error of a `fatal` task becomes the run's error
+/// unless one is already set. Errors of other tasks are only logged.
+struct TaskJoiner {
+    deadline: Option<tokio::time::Instant>,
+    aborted: Vec<&'static str>,
+    first_err: Option<anyhow::Error>,
+}
+
+impl TaskJoiner {
+    fn new(deadline: Option<tokio::time::Instant>, first_err: Option<anyhow::Error>) -> Self {
+        Self {
+            deadline,
+            aborted: Vec::new(),
+            first_err,
+        }
+    }
+
+    async fn join<T: TaskOutput>(
+        &mut self,
+        h: tokio::task::JoinHandle<T>,
+        task: &'static str,
+        fatal: bool,
+    ) {
+        let err = match join_by(h, self.deadline, task, &mut self.aborted).await {
+            None => return,
+            Some(Ok(out)) => match out.into_result() {
+                Ok(()) => return,
+                Err(e) => add_context(e, format!("{task} task failed")),
+            },
+            Some(Err(e)) => join_error(e, format!("{task} task join failed")),
+        };
+        if !fatal {
+            warn!(task, error = %format!("{err:#}"), "task failed during shutdown");
+        } else if self.first_err.is_none() {
+            self.first_err = Some(err);
+        }
+    }
+}
+
+fn join_error<C>(err: tokio::task::JoinError, ctx: C) -> anyhow::Error
+where
+    C: std::fmt::Display + Send + Sync + 'static,
+{
+    let err = if err.is_panic() {
+        anyhow!(errors::RazorError::Panic(err.into()))
+    } else {
+        anyhow!(err)
+    };
+    add_context(err, ctx)
+}
+
+fn task_panic_error(task: &'static str) -> anyhow::Error {
+    anyhow!(errors::RazorError::Panic(format!("{task} task").into()))
+}
+
+fn add_context<C>(err: anyhow::Error, ctx: C) -> anyhow::Error
+where
+    C: std::fmt::Display + Send + Sync + 'static,
+{
     Err::<(), _>(err).context(ctx).unwrap_err()
 }
 
@@ -615,50 +1772,85 @@ fn resolve_mode(cli: Option<&str>) -> anyhow::Result<Mode> {
     }
 }
 
+/// Convenience aliases `data/<file>` for the latest run's main outputs.
+const DATA_LATEST_FILES: [&str; 8] = [
+    schema::FILE_TICKS,
+    schema::FILE_TRADES,
+    schema::FILE_SNAPSHOTS,
+    schema::FILE_SHADOW_LOG,
+    schema::FILE_TRADE_LOG,
+    schema::FILE_CALIBRATION_LOG,
+    schema::FILE_CALIBRATION_SUGGEST,
+    schema::FILE_SCHEMA_VERSION,
+];
+
 fn ensure_data_latest_file_links(data_dir: &std::path::Path) -> anyhow::Result<()> {
-    ensure_latest_file_symlink(data_dir, schema::FILE_TICKS)?;
-    ensure_latest_file_symlink(data_dir, schema::FILE_TRADES)?;
-    ensure_latest_file_symlink(data_dir, schema::FILE_SNAPSHOTS)?;
-    ensure_latest_file_symlink(data_dir, schema::FILE_SHADOW_LOG)?;
-    ensure_latest_file_symlink(data_dir, schema::FILE_TRADE_LOG)?;
-    ensure_latest_file_symlink(data_dir, schema::FILE_CALIBRATION_LOG)?;
-    ensure_latest_file_symlink(data_dir, schema::FILE_CALIBRATION_SUGGEST)?;
-    ensure_latest_file_symlink(data_dir, schema::FILE_SCHEMA_VERSION)?;
+    for file_name in DATA_LATEST_FILES {
+        ensure_latest_file_symlink(data_dir, file_name)?;
+    }
     Ok(())
 }
 
 fn ensure_latest_file_symlink(data_dir: &std::path::Path, file_name: &str) -> anyhow::Result<()> {
-    #[cfg(unix)]
-    {
-        use std::os::unix::fs::symlink;
-        use std::path::Path;
-
-        let link_path = data_dir.join(file_name);
-        let target = Path::new("run_latest").join(file_name);
-
-        if let Ok(meta) = std::fs::symlink_metadata(&link_path) {
-            if meta.file_type().is_symlink() {
-                std::fs::remove_file(&link_path)?;
-            } else if meta.is_file() {
-                let backup_name = format!("{}.legacy_{}", file_name, crate::types::now_ms());
-                let backup_path = data_dir.join(backup_name);
-                std::fs::rename(&link_path, &backup_path)?;
-            } else {
-                anyhow::bail!("refusing to replace non-file {}", link_path.display());
+    use std::path::Path;
+
+    let link_path = data_dir.join(file_name);
+    let target = Path::new(run_context::RUN_LATEST_LINK).join(file_name);
+
+    if let Ok(meta) = std::fs::symlink_metadata(&link_path) {
+        if meta.file_type().is_symlink() {
+            std::fs::remove_file(&link_path)?;
+        } else if meta.is_file() {
+            if cfg!(windows) {
+                // The previous run's copy; replaced when this run finishes.
+                return Ok(());
             }
+            let backup_name = format!("{}.legacy_{}", file_name, crate::types::now_ms());
+            let backup_path = data_dir.join(backup_name);
+            std::fs::rename(&link_path, &backup_path)?;
+        } else {
+            anyhow::bail!("refusing to replace non-file {}", link_path.display());
         }
-
-        symlink(target, link_path)?;
     }
 
-    #[cfg(not(unix))]
-    {
-        let _ = (data_dir, file_name);
-    }
+    #[cfg(unix)]
+    std::os::unix::fs::symlink(target, link_path)?;
+
+    // File symlinks need Developer Mode or admin rights on Windows; without them the alias is
+    // a copy made when the run finishes (`publish_data_latest_files`).
+    #[cfg(windows)]
+    let _ = std::os::windows::fs::symlink_file(target, link_path);
+
+    #[cfg(not(any(unix, windows)))]
+    let _ = (target, link_path);
 
     Ok(())
 }
 
+/// End of run: marks `latest.json` complete and, where `data/<file>` could not be a symlink,
+/// refreshes it with a copy of the finished run's file.
+fn publish_data_latest_files(
+    data_dir: &std::path::Path,
+    run_dir: &std::path::Path,
+    run_id: &str,
+) -> anyhow::Result<()> {
+    run_context::mark_latest_complete(data_dir, run_id)?;
+    if run_context::read_latest_json(data_dir).is_none_or(|p| p.run_id != run_id) {
+        return Ok(());
+    }
+    for file_name in DATA_LATEST_FILES {
+        let alias = data_dir.join(file_name);
+        let src = run_dir.join(file_name);
+        let is_link = std::fs::symlink_metadata(&alias).is_ok_and(|m| m.file_type().is_symlink());
+        if is_link || !src.is_file() {
+            continue;
+        }
+        std::fs::copy(&src, &alias)
+            .with_context(|| format!("copy {} -> {}", src.display(), alias.display()))?;
+    }
+    Ok(())
+}
+
 fn sim_stress_profile_from_env() -> run_meta::SimStressProfile {
     run_meta::SimStressProfile {
         force_chase_fail: env_flag("RAZOR_SIM_FORCE_CHASE_FAIL"),
@@ -695,3 +1887,61 @@ fn env_f64(name: &str) -> Option<f64> {
         None
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn joiner_aborts_tasks_past_the_deadline_and_keeps_the_first_fatal_error() {
+        let deadline = tokio::time::Instant::now() + Duration::from_millis(50);
+        let mut joiner = TaskJoiner::new(Some(deadline), None);
+
+        let (dropped_tx, dropped_rx) = tokio::sync::oneshot::channel::<()>();
+        let stuck = tokio::spawn(async move {
+            let _guard = dropped_tx;
+            std::future::pending::<anyhow::Result<()>>().await
+        });
+        // These finish while the stuck task eats the deadline.
+        let observer = tokio::spawn(async { Err::<(), _>(anyhow!("observer failed")) });
+        let first = tokio::spawn(async { Err::<(), _>(anyhow!("boom")) });
+        let second = tokio::spawn(async { Err::<(), _>(anyhow!("later")) });
+        let writer = tokio::spawn(async {});
+
+        joiner.join(stuck, "ws", true).await;
+        // Aborting drops the task's future, and with it the sender.
+        assert!(dropped_rx.await.is_err());
+        joiner.join(observer, "metrics", false).await;
+        joiner.join(first, "brain", true).await;
+        joiner.join(second, "trades", true).await;
+        joiner.join(writer, "health_writer", true).await;
+
+        assert_eq!(joiner.aborted, ["ws"]);
+        let err = joiner.first_err.expect("fatal error kept");
+        assert_eq!(format!("{err:#}"), "brain task failed: boom");
+    }
+
+    #[test]
+    fn shutdown_deadline_line_is_appended_to_health_jsonl() {
+        let path = std::env::temp_dir().join(format!(
+            "razor_shutdown_deadline_{}.jsonl",
+            crate::types::now_ms()
+        ));
+        let line = health::HealthLine::ShutdownDeadline {
+            ts_ms: 1,
+            deadline_ms: 50,
+            aborted: vec!["ws".to_string()],
+        };
+        health::append_line(&path, &line).unwrap();
+        health::append_line(&path, &line).unwrap();
+        let raw = std::fs::read_to_string(&path).unwrap();
+        let _ = std::fs::remove_file(&path);
+        let lines: Vec<serde_json::Value> = raw
+            .lines()
+            .map(|l| serde_json::from_str(l).unwrap())
+            .collect();
+        assert_eq!(lines.len(), 2);
+        assert_eq!(lines[0]["type"], "shutdown_deadline");
+        assert_eq!(lines[0]["aborted"], serde_json::json!(["ws"]));
+    }
+}
@@ -0,0 +1,267 @@
+use std::io::{Read as _, Write as _};
+use std::path::Path;
+
+use anyhow::Context as _;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest as _, Sha256};
+
+use crate::gc::FILE_GC_PRUNED;
+use crate::schema::FILE_MANIFEST_JSON;
+use crate::types::now_ms;
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ManifestEntry {
+    /// Run-relative, `/`-separated.
+    pub path: String,
+    pub bytes: u64,
+    /// Data rows (newline-terminated lines, minus the header for CSVs); `None` for non-tabular
+    /// artifacts.
+    pub rows: Option<u64>,
+    pub sha256: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RunManifest {
+    pub run_id: String,
+    pub created_ts_ms: u64,
+    pub artifacts: Vec<ManifestEntry>,
+}
+
+pub struct FileDigest {
+    pub bytes: u64,
+    pub lines: u64,
+    pub sha256: String,
+}
+
+/// SHA-256, size and newline count in one streaming pass.
+pub fn digest_file(path: &Path) -> anyhow::Result<FileDigest> {
+    let mut f = std::fs::File::open(path).with_context(|| format!("open {}", path.display()))?;
+    let mut hasher = Sha256::new();
+    let mut buf = vec![0u8; 1 << 20];
+    let (mut bytes, mut lines) = (0u64, 0u64);
+    loop {
+        let n = f
+            .read(&mut buf)
+            .with_context(|| format!("read {}", path.display()))?;
+        if n == 0 {
+            break;
+        }
+        hasher.update(&buf[..n]);
+        bytes += n as u64;
+        lines += buf[..n].iter().filter(|b| **b == b'\n').count() as u64;
+    }
+    Ok(FileDigest {
+        bytes,
+        lines,
+        sha256: hex::encode(hasher.finalize()),
+    })
+}
+
+fn entry_for(run_dir: &Path, rel: &str) -> anyhow::Result<ManifestEntry> {
+    let d = digest_file(&run_dir.join(rel))?;
+    let rows = if rel.ends_with(".csv") {
+        Some(d.lines.saturating_sub(1))
+    } else if rel.ends_with(".jsonl") || rel.contains(".jsonl.rotated_") {
+        Some(d.lines)
+    } else {
+        None
+    };
+    Ok(ManifestEntry {
+        path: rel.to_string(),
+        bytes: d.bytes,
+        rows,
+        sha256: d.sha256,
+    })
+}
+
+/// Run-relative paths of every regular file under `run_dir` (partitions included), sorted.
+pub fn list_artifacts(run_dir: &Path) -> anyhow::Result<Vec<String>> {
+    fn walk(dir: &Path, rel: &str, out: &mut Vec<String>) -> anyhow::Result<()> {
+        for entry in std::fs::read_dir(dir).with_context(|| format!("read {}", dir.display()))? {
+            let entry = entry?;
+            let name = entry.file_name().to_string_lossy().to_string();
+            let rel_path = if rel.is_empty() {
+                name
+            } else {
+                format!("{rel}/{name}")
+            };
+            let ft = entry.file_type()?;
+            if ft.is_dir() {
+                walk(&entry.path(), &rel_path, out)?;
+            } else if ft.is_file() {
+                out.push(rel_path);
+            }
+        }
+        Ok(())
+    }
+    let mut out = Vec::new();
+    walk(run_dir, "", &mut out)?;
+    out.sort();
+    Ok(out)
+}
+
+/// Writes `manifest.json` covering every artifact in the (already flushed) run dir.
+pub fn write_manifest(run_dir: &Path, run_id: &str) -> anyhow::Result<RunManifest> {
+    let mut artifacts = Vec::new();
+    for rel in list_artifacts(run_dir)? {
+        if rel == FILE_MANIFEST_JSON {
+            continue;
+        }
+        artifacts.push(entry_for(run_dir, &rel)?);
+    }
+    let manifest = RunManifest {
+        run_id: run_id.to_string(),
+        created_ts_ms: now_ms(),
+        artifacts,
+    };
+    let path = run_dir.join(FILE_MANIFEST_JSON);
+    let mut f =
+        std::fs::File::create(&path).with_context(|| format!("create {}", path.display()))?;
+    f.write_all(serde_json::to_string_pretty(&manifest)?.as_bytes())?;
+    f.write_all(b"\n")?;
+    f.sync_all()
+        .with_context(|| format!("sync {}", path.display()))?;
+    Ok(manifest)
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum VerifyIssue {
+    Missing(String),
+    SizeMismatch {
+        path: String,
+        expected: u64,
+        got: u64,
+    },
+    RowMismatch {
+        path: String,
+        expected: u64,
+        got: u64,
+    },
+    ChecksumMismatch(String),
+}
+
+impl std::fmt::Display for VerifyIssue {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            VerifyIssue::Missing(p) => write!(f, "missing {p}"),
+            VerifyIssue::SizeMismatch {
+                path,
+                expected,
+                got,
+            } => write!(f, "size_mismatch {path} expected={expected} got={got}"),
+            VerifyIssue::RowMismatch {
+                path,
+                expected,
+                got,
+            } => write!(f, "row_mismatch {path} expected={expected} got={got}"),
+            VerifyIssue::ChecksumMismatch(p) => write!(f, "sha256_mismatch {p}"),
+        }
+    }
+}
+
+/// Re-checks every manifest entry. Files `razor gc` removed (listed in `PRUNED`) are not issues.
+pub fn verify_run_dir(run_dir: &Path) -> anyhow::Result<(RunManifest, Vec<VerifyIssue>)> {
+    let path = run_dir.join(FILE_MANIFEST_JSON);
+    let raw = std::fs::read_to_string(&path).with_context(|| format!("read {}", path.display()))?;
+    let manifest: RunManifest =
+        serde_json::from_str(&raw).with_context(|| format!("parse {}", path.display()))?;
+    let pruned = std::fs::read_to_string(run_dir.join(FILE_GC_PRUNED)).unwrap_or_default();
+    let pruned: Vec<&str> = pruned.lines().collect();
+
+    let mut issues = Vec::new();
+    for want in &manifest.artifacts {
+        let file = run_dir.join(&want.path);
+        if !file.is_file() {
+            let top = want.path.split('/').next().unwrap_or_default();
+            if !pruned.contains(&top) {
+                issues.push(VerifyIssue::Missing(want.path.clone()));
+            }
+            continue;
+        }
+        let got = entry_for(run_dir, &want.path)?;
+        if got.bytes != want.bytes {
+            issues.push(VerifyIssue::SizeMismatch {
+                path: want.path.clone(),
+                expected: want.bytes,
+                got: got.bytes,
+            });
+        }
+        if let (Some(expected), Some(got_rows)) = (want.rows, got.rows) {
+            if expected != got_rows {
+                issues.push(VerifyIssue::RowMismatch {
+                    path: want.path.clone(),
+                    expected,
+                    got: got_rows,
+                });
+            }
+        }
+        if got.sha256 != want.sha256 {
+            issues.push(VerifyIssue::ChecksumMismatch(want.path.clone()));
+        }
+    }
+    Ok((manifest, issues))
+}
+
+/// `razor verify <run_dir>`: prints every issue and fails if there is any.
+pub fn run_verify(run_dir: &Path) -> anyhow::Result<()> {
+    let (manifest, issues) = verify_run_dir(run_dir)?;
+    for issue in &issues {
+        println!("{issue}");
+    }
+    println!(
+        "verify: run_id={} artifacts={} issues={}",
+        manifest.run_id,
+        manifest.artifacts.len(),
+        issues.len()
+    );
+    if !issues.is_empty() {
+        anyhow::bail!(
+            "{} integrity issue(s) in {}",
+            issues.len(),
+            run_dir.display()
+        );
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn detects_truncation_and_tolerates_gc_pruned_files() {
+        let dir = std::env::temp_dir().join(format!("razor_manifest_{}", now_ms()));
+        std::fs::create_dir_all(dir.join("2026-01-01/00")).unwrap();
+        std::fs::write(dir.join("shadow_log.csv"), "a,b\n1,2\n3,4\n").unwrap();
+        std::fs::write(dir.join("2026-01-01/00/ticks.csv"), "a\n1\n").unwrap();
+        std::fs::write(dir.join("health.jsonl"), "{}\n{}\n").unwrap();
+
+        let m = write_manifest(&dir, "run_x").unwrap();
+        assert_eq!(m.artifacts.len(), 3);
+        assert_eq!(m.artifacts[0].path, "2026-01-01/00/ticks.csv");
+        assert_eq!(m.artifacts[2].rows, Some(2));
+        assert!(verify_run_dir(&dir).unwrap().1.is_empty());
+
+        std::fs::write(dir.join("shadow_log.csv"), "a,b\n1,2\n3,").unwrap();
+        std::fs::remove_dir_all(dir.join("2026-01-01")).unwrap();
+        std::fs::write(dir.join(FILE_GC_PRUNED), "pruned_ts_ms=1\n2026-01-01\n").unwrap();
+        let issues = verify_run_dir(&dir).unwrap().1;
+        assert_eq!(
+            issues,
+            vec![
+                VerifyIssue::SizeMismatch {
+                    path: "shadow_log.csv".to_string(),
+                    expected: 12,
+                    got: 10,
+                },
+                VerifyIssue::RowMismatch {
+                    path: "shadow_log.csv".to_string(),
+                    expected: 2,
+                    got: 1,
+                },
+                VerifyIssue::ChecksumMismatch("shadow_log.csv".to_string()),
+            ]
+        );
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
@@ -2,8 +2,10 @@ use anyhow::Context as _;
 use serde_json::Value;
 use std::time::Duration;
 
+use crate::api_usage::{self, ApiEndpoint};
 use crate::config::Config;
 use crate::market_select::metrics::ProbePhase;
+use crate::types::parse_rfc3339_to_unix_secs;
 
 #[derive(Clone, Debug)]
 pub struct GammaMarket {
@@ -34,16 +36,19 @@ pub async fn fetch_candidate_pool(cfg: &Config, limit: usize) -> anyhow::Result<
         cfg.polymarket.gamma_base.trim_end_matches('/')
     );
 
-    let resp = client
-        .get(url)
-        .query(&[
-            ("active", "true"),
-            ("closed", "false"),
-            ("limit", &limit.to_string()),
-        ])
-        .send()
-        .await
-        .context("gamma markets request")?;
+    let resp = api_usage::track(
+        ApiEndpoint::Gamma,
+        client
+            .get(url)
+            .query(&[
+                ("active", "true"),
+                ("closed", "false"),
+                ("limit", &limit.to_string()),
+            ])
+            .send()
+            .await,
+    )
+    .context("gamma markets request")?;
 
     let list: Vec<Value> = resp.json().await.context("decode gamma response")?;
 
@@ -146,67 +151,3 @@ fn parse_market_phase(end_date_rfc3339: &str) -> Option<ProbePhase> {
         Some(ProbePhase::Lt24h)
     }
 }
-
-fn parse_rfc3339_to_unix_secs(s: &str) -> Option<u64> {
-    // Accept formats like:
-    // - "2026-02-28T12:00:00Z"
-    // - "2025-01-08T01:33:54.924Z"
-    let (date, time) = s.split_once('T')?;
-    let (y, m, d) = parse_ymd(date)?;
-
-    let time = time.strip_suffix('Z')?;
-    let (hms, _frac) = time.split_once('.').unwrap_or((time, ""));
-    let mut it = hms.split(':');
-    let hh = it.next()?.parse::<u32>().ok()?;
-    let mm = it.next()?.parse::<u32>().ok()?;
-    let ss = it.next()?.parse::<u32>().ok()?;
-
-    let days = days_from_civil(y, m, d)?;
-    let secs = days
-        .checked_mul(86_400)?
-        .checked_add((hh as u64).checked_mul(3600)?)?
-        .checked_add((mm as u64).checked_mul(60)?)?
-        .checked_add(ss as u64)?;
-    Some(secs)
-}
-
-fn parse_ymd(s: &str) -> Option<(i32, u32, u32)> {
-    let mut it = s.split('-');
-    let y = it.next()?.parse::<i32>().ok()?;
-    let m = it.next()?.parse::<u32>().ok()?;
-    let d = it.next()?.parse::<u32>().ok()?;
-    Some((y, m, d))
-}
-
-// Inverse of civil_from_days (Howard Hinnant's algorithm).
-// Returns days since 1970-01-01.
-fn days_from_civil(year: i32, month: u32, day: u32) -> Option<u64> {
-    if month == 0 || month > 12 || day == 0 || day > 31 {
-        return None;
-    }
-    let y = i64::from(year) - i64::from(month <= 2);
-    let m = i64::from(month) + if month <= 2 { 9 } else { -3 };
-    let d = i64::from(day);
-    let era = if y >= 0 { y } else { y - 399 }.div_euclid(400);
-    let yoe = y - era * 400;
-    let doy = (153 * m + 2).div_euclid(5) + d - 1;
-    let doe = yoe * 365 + yoe.div_euclid(4) - yoe.div_euclid(100) + doy;
-    let days = era * 146_097 + doe - 719_468;
-    if days < 0 {
-        None
-    } else {
-        Some(days as u64)
-    }
-}
-
-#[cfg(test)]
-mod tests {
-    use super::*;
-
-    #[test]
-    fn parses_end_date_with_fractional_seconds() {
-        let secs = parse_rfc3339_to_unix_secs("2025-01-08T01:33:54.924Z").unwrap();
-        let secs2 = parse_rfc3339_to_unix_secs("2025-01-08T01:33:54Z").unwrap();
-        assert_eq!(secs, secs2);
-    }
-}
@@ -150,7 +150,7 @@ pub async fn run(cfg: &Config, opts: MarketSelectOptions) -> anyhow::Result<()>
                             }
                         }
                         rows.push(r);
-                        if probes_completed_ok % 10 == 0 {
+                        if probes_completed_ok.is_multiple_of(10) {
                             info!(completed_ok = probes_completed_ok, total = candidates_total, "probe progress");
                         }
                     }
@@ -286,8 +286,5 @@ fn default_out_dir(data_dir: &Path, run_id: &str) -> PathBuf {
 }
 
 fn format_run_id(now_ms: u64) -> String {
-    // Deterministic-ish: use unix ms plus pid for uniqueness, no external RNG.
-    let pid = std::process::id();
-    let rand6 = ((now_ms as u32) ^ pid) % 1_000_000;
-    format!("msel_{now_ms}_{rand6:06}")
+    format!("msel_{}", crate::ulid::new_ulid(now_ms))
 }
@@ -8,12 +8,15 @@ use serde::Deserialize;
 use tokio_tungstenite::tungstenite::Message;
 use tracing::{info, warn};
 
-use crate::buckets::classify_bucket;
+use crate::api_usage::{self, ApiEndpoint};
+use crate::buckets::{classify_bucket_at, FROZEN_DEPTH_LEVELS};
 use crate::config::Config;
-use crate::json_util::parse_f64;
 use crate::market_select::gamma::GammaMarket;
 use crate::market_select::metrics::{self, MarketScoreRowComputed, SnapshotAccum, TradesAccum};
-use crate::types::{now_ms, now_us, LegSnapshot, MarketSnapshot};
+use crate::types::{normalize_ts_ms, now_ms, now_us, LegSnapshot, MarketSnapshot, Price, Qty};
+use crate::ws_msg::{
+    self, ask_depth3_usdc, best_level, BookEvent, PriceChangeEvent, PriceSide, WsEvent,
+};
 
 static SIM_HTTP_429_SEQ: AtomicU64 = AtomicU64::new(0);
 
@@ -292,26 +295,27 @@ fn sample_snapshot(
             depth3 = 0.0; // force degrade in bucket classifier
         }
         snap_legs.push(LegSnapshot {
-            token_id: l.token_id.clone(),
-            best_ask: l.best_ask,
-            best_bid: l.best_bid,
-            best_ask_size_best: 0.0,
-            best_bid_size_best: 0.0,
+            token_id: l.token_id.as_str().into(),
+            best_ask: Price::from_f64(l.best_ask).unwrap_or(Price::MISSING_ASK),
+            best_bid: Price::from_f64(l.best_bid).unwrap_or(Price::MISSING_BID),
+            best_ask_size_best: Qty::ZERO,
+            best_bid_size_best: Qty::ZERO,
             ask_depth3_usdc: depth3,
+            asks: Default::default(),
             ts_recv_us,
         });
     }
 
     let snapshot = MarketSnapshot {
-        market_id: m.condition_id.clone(),
+        market_id: m.condition_id.as_str().into(),
         legs: snap_legs,
     };
 
-    let bucket_decision = classify_bucket(&snapshot);
+    let bucket_decision = classify_bucket_at(&snapshot, FROZEN_DEPTH_LEVELS);
     let bucket = bucket_decision.bucket;
 
-    let best_bids: Vec<f64> = snapshot.legs.iter().map(|l| l.best_bid).collect();
-    let best_asks: Vec<f64> = snapshot.legs.iter().map(|l| l.best_ask).collect();
+    let best_bids: Vec<f64> = snapshot.legs.iter().map(|l| l.best_bid.to_f64()).collect();
+    let best_asks: Vec<f64> = snapshot.legs.iter().map(|l| l.best_ask.to_f64()).collect();
     let depth3_usdc: Vec<f64> = snapshot.legs.iter().map(|l| l.ask_depth3_usdc).collect();
 
     let sum_ask: f64 = best_asks.iter().copied().sum();
@@ -347,7 +351,7 @@ async fn poll_trades(
             let seq = SIM_HTTP_429_SEQ
                 .fetch_add(1, Ordering::Relaxed)
                 .saturating_add(1);
-            if seq % every == 0 {
+            if seq.is_multiple_of(every) {
                 warn!(
                     condition_id,
                     seq, every, "SIM injected HTTP 429 (skipping this poll)"
@@ -357,16 +361,18 @@ async fn poll_trades(
         }
     }
 
-    let resp = match client
-        .get(url)
-        .query(&[
-            ("limit", trade_poll_limit.to_string()),
-            ("takerOnly", trade_poll_taker_only.to_string()),
-            ("market", condition_id.to_string()),
-        ])
-        .send()
-        .await
-    {
+    let resp = match api_usage::track(
+        ApiEndpoint::DataApiTrades,
+        client
+            .get(url)
+            .query(&[
+                ("limit", trade_poll_limit.to_string()),
+                ("takerOnly", trade_poll_taker_only.to_string()),
+                ("market", condition_id.to_string()),
+            ])
+            .send()
+            .await,
+    ) {
         Ok(r) => r,
         Err(_) => return,
     };
@@ -433,110 +439,46 @@ fn env_u64(name: &str) -> Option<u64> {
     raw.trim().parse::<u64>().ok()
 }
 
-fn normalize_ts_ms(ts: u64) -> u64 {
-    // Normalize unix timestamps to milliseconds (see `feed::normalize_ts_ms`).
-    match ts {
-        0..=99_999_999_999 => ts.saturating_mul(1_000),
-        100_000_000_000..=99_999_999_999_999 => ts,
-        100_000_000_000_000..=99_999_999_999_999_999 => ts / 1_000,
-        _ => ts / 1_000_000,
-    }
-}
-
 fn handle_ws_text(txt: &str, legs: &mut [LegState]) -> anyhow::Result<()> {
     if txt == "PONG" {
         return Ok(());
     }
 
-    let v: serde_json::Value = match serde_json::from_str(txt) {
-        Ok(v) => v,
-        Err(_) => return Ok(()),
-    };
-
-    match v {
-        serde_json::Value::Array(items) => {
-            for item in items {
-                if let serde_json::Value::Object(obj) = item {
-                    handle_ws_obj(obj, legs)?;
-                }
-            }
+    // Undecodable events only cost this probe a sample; they are not logged.
+    ws_msg::for_each_event(txt, |ev| {
+        match ev {
+            WsEvent::Book(book) => handle_ws_book(&book, legs),
+            WsEvent::PriceChange(pc) => handle_ws_price_change(&pc, legs),
+            WsEvent::LastTrade(_) | WsEvent::Other => {}
         }
-        serde_json::Value::Object(obj) => {
-            handle_ws_obj(obj, legs)?;
-        }
-        _ => {}
-    }
-
+        Ok::<_, anyhow::Error>(())
+    })?;
     Ok(())
 }
 
-fn handle_ws_obj(
-    obj: serde_json::Map<String, serde_json::Value>,
-    legs: &mut [LegState],
-) -> anyhow::Result<()> {
-    let Some(event_type) = obj.get("event_type").and_then(|v| v.as_str()) else {
-        return Ok(());
+fn handle_ws_book(book: &BookEvent<'_>, legs: &mut [LegState]) {
+    let Some(idx) = legs.iter().position(|l| l.token_id == book.asset_id) else {
+        return;
     };
 
-    match event_type {
-        "book" => handle_ws_book(obj, legs),
-        "price_change" => handle_ws_price_change(obj, legs),
-        _ => Ok(()),
-    }
-}
-
-fn handle_ws_book(
-    obj: serde_json::Map<String, serde_json::Value>,
-    legs: &mut [LegState],
-) -> anyhow::Result<()> {
-    let Some(token_id) = obj.get("asset_id").and_then(|v| v.as_str()) else {
-        return Ok(());
-    };
-    let Some(idx) = legs.iter().position(|l| l.token_id == token_id) else {
-        return Ok(());
-    };
-
-    let bids: &[serde_json::Value] = obj
-        .get("bids")
-        .and_then(|v| v.as_array())
-        .map(|v| v.as_slice())
-        .unwrap_or(&[]);
-    let asks: &[serde_json::Value] = obj
-        .get("asks")
-        .and_then(|v| v.as_array())
-        .map(|v| v.as_slice())
-        .unwrap_or(&[]);
-
-    let (best_bid, _best_bid_sz) = best_level(bids, PriceSide::Bid).unwrap_or((0.0, 0.0));
-    let (best_ask, _best_ask_sz) = best_level(asks, PriceSide::Ask).unwrap_or((1.0, 0.0));
-    let ask_depth3_usdc = ask_depth3_usdc(asks);
+    let (best_bid, _best_bid_sz) = best_level(&book.bids, PriceSide::Bid).unwrap_or((0.0, 0.0));
+    let (best_ask, _best_ask_sz) = best_level(&book.asks, PriceSide::Ask).unwrap_or((1.0, 0.0));
+    let ask_depth3_usdc = ask_depth3_usdc(&book.asks);
 
     let leg = &mut legs[idx];
     leg.best_bid = best_bid;
     leg.best_ask = best_ask;
     leg.ask_depth3_usdc = ask_depth3_usdc;
     leg.ready = leg.best_ask.is_finite() && leg.best_ask > 0.0;
-    Ok(())
 }
 
-fn handle_ws_price_change(
-    obj: serde_json::Map<String, serde_json::Value>,
-    legs: &mut [LegState],
-) -> anyhow::Result<()> {
-    let Some(changes) = obj.get("price_changes").and_then(|v| v.as_array()) else {
-        return Ok(());
-    };
-
-    for ch in changes {
-        let Some(ch) = ch.as_object() else { continue };
-        let Some(token_id) = ch.get("asset_id").and_then(|v| v.as_str()) else {
-            continue;
-        };
-        let Some(idx) = legs.iter().position(|l| l.token_id == token_id) else {
+fn handle_ws_price_change(pc: &PriceChangeEvent<'_>, legs: &mut [LegState]) {
+    for ch in &pc.price_changes {
+        let Some(idx) = legs.iter().position(|l| l.token_id == ch.asset_id) else {
             continue;
         };
-        let best_bid = parse_f64(ch.get("best_bid")).unwrap_or(0.0);
-        let best_ask = parse_f64(ch.get("best_ask")).unwrap_or(0.0);
+        let best_bid = ch.best_bid.unwrap_or(0.0);
+        let best_ask = ch.best_ask.unwrap_or(0.0);
 
         let leg = &mut legs[idx];
         leg.best_bid = if best_bid.is_finite() && best_bid > 0.0 {
@@ -551,112 +493,14 @@ fn handle_ws_price_change(
         };
         leg.ready = leg.best_ask.is_finite() && leg.best_ask > 0.0;
     }
-
-    Ok(())
-}
-
-#[derive(Clone, Copy)]
-enum PriceSide {
-    Bid,
-    Ask,
-}
-
-fn best_level(levels: &[serde_json::Value], side: PriceSide) -> Option<(f64, f64)> {
-    let mut best: Option<(f64, f64)> = None;
-    for lvl in levels {
-        let Some(px) = parse_f64(lvl.get("price")).filter(|v| v.is_finite() && *v > 0.0) else {
-            continue;
-        };
-
-        let sz = parse_f64(lvl.get("size"))
-            .filter(|s| s.is_finite() && *s > 0.0)
-            .unwrap_or(0.0);
-
-        best = match (best, side) {
-            (None, _) => Some((px, sz)),
-            (Some((cur_px, cur_sz)), PriceSide::Bid) => {
-                if px > cur_px {
-                    Some((px, sz))
-                } else {
-                    Some((cur_px, cur_sz))
-                }
-            }
-            (Some((cur_px, cur_sz)), PriceSide::Ask) => {
-                if px < cur_px {
-                    Some((px, sz))
-                } else {
-                    Some((cur_px, cur_sz))
-                }
-            }
-        };
-    }
-    best
-}
-
-fn ask_depth3_usdc(levels: &[serde_json::Value]) -> f64 {
-    let mut best = [(f64::INFINITY, 0.0f64); 3];
-    for lvl in levels {
-        let Some(px) = parse_f64(lvl.get("price")).filter(|v| v.is_finite() && *v > 0.0) else {
-            continue;
-        };
-        let Some(sz) = parse_f64(lvl.get("size")).filter(|v| v.is_finite() && *v > 0.0) else {
-            continue;
-        };
-
-        if px >= best[2].0 {
-            continue;
-        }
-        best[2] = (px, sz);
-        best.sort_by(|a, b| a.0.partial_cmp(&b.0).unwrap_or(std::cmp::Ordering::Equal));
-    }
-
-    best.iter()
-        .filter(|(px, _)| px.is_finite())
-        .map(|(px, sz)| px * sz)
-        .sum()
 }
 
 #[cfg(test)]
 mod tests {
-    use super::*;
-    use assert_approx_eq::assert_approx_eq;
-    use serde_json::json;
-
-    #[test]
-    fn ws_book_level_parses_numeric_and_string_fields() {
-        let bids = vec![
-            json!({"price": 0.49, "size": 1.0}),
-            json!({"price": "0.5", "size": "2"}),
-        ];
-        let (px, sz) = best_level(&bids, PriceSide::Bid).expect("best bid");
-        assert_approx_eq!(px, 0.5);
-        assert_approx_eq!(sz, 2.0);
-
-        let asks = vec![
-            json!({"price": 0.6, "size": 1.0}),
-            json!({"price": "0.55", "size": "2"}),
-        ];
-        let (px, sz) = best_level(&asks, PriceSide::Ask).expect("best ask");
-        assert_approx_eq!(px, 0.55);
-        assert_approx_eq!(sz, 2.0);
-    }
-
-    #[test]
-    fn ws_book_depth3_parses_numeric_and_sums_top3() {
-        let asks = vec![
-            json!({"price": 0.6, "size": 10.0}),    // 6
-            json!({"price": "0.55", "size": 20.0}), // 11
-            json!({"price": 0.50, "size": "30"}),   // 15
-            json!({"price": 0.65, "size": 40.0}),   // excluded
-        ];
-        let d = ask_depth3_usdc(&asks);
-        assert_approx_eq!(d, 32.0);
-    }
-
     #[test]
     fn http_429_every_k_logic_is_stable() {
         fn fires(seq: u64, every: u64) -> bool {
-            every > 0 && seq % every == 0
+            every > 0 && seq.is_multiple_of(every)
         }
         assert!(!fires(1, 3));
         assert!(!fires(2, 3));
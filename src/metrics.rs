@@ -0,0 +1,184 @@
+//! Prometheus text exposition of the health counters on `GET /metrics` (`[metrics] listen_addr`).
+//! Every scrape renders a fresh [`HealthSnapshot`]; nothing is cached or pushed.
+
+use std::fmt::Write as _;
+use std::sync::Arc;
+
+use axum::extract::State;
+use axum::http::header;
+use axum::response::IntoResponse;
+use axum::routing::get;
+use axum::Router;
+use tokio::net::TcpListener;
+use tokio::sync::watch;
+
+use crate::backpressure::ChannelSnapshot;
+use crate::health::{HealthCounters, HealthSnapshot};
+
+const CONTENT_TYPE: &str = "text/plain; version=0.0.4; charset=utf-8";
+
+pub async fn serve(
+    listener: TcpListener,
+    health: Arc<HealthCounters>,
+    mut shutdown: watch::Receiver<bool>,
+) -> anyhow::Result<()> {
+    let app = Router::new()
+        .route("/metrics", get(scrape))
+        .with_state(health);
+    axum::serve(listener, app)
+        .with_graceful_shutdown(async move {
+            while !*shutdown.borrow() {
+                if shutdown.changed().await.is_err() {
+                    break;
+                }
+            }
+        })
+        .await?;
+    Ok(())
+}
+
+async fn scrape(State(health): State<Arc<HealthCounters>>) -> impl IntoResponse {
+    (
+        [(header::CONTENT_TYPE, CONTENT_TYPE)],
+        render(&health.snapshot()),
+    )
+}
+
+pub fn render(s: &HealthSnapshot) -> String {
+    let mut out = String::new();
+    let counters: [(&str, &str, u64); 11] = [
+        (
+            "ticks_processed",
+            "Ticks written from the market WS.",
+            s.ticks_processed,
+        ),
+        (
+            "trades_written",
+            "Trades written to trades.csv.",
+            s.trades_written,
+        ),
+        (
+            "trades_from_ws",
+            "Trades taken from the market WS.",
+            s.trades_from_ws,
+        ),
+        (
+            "trades_gap_filled",
+            "Trades the poller backfilled past the WS.",
+            s.trades_gap_filled,
+        ),
+        (
+            "trades_dropped",
+            "Trades dropped before shadow.",
+            s.trades_dropped,
+        ),
+        (
+            "signals_emitted",
+            "Signals emitted by brain.",
+            s.signals_emitted,
+        ),
+        (
+            "signals_dropped",
+            "Signals dropped on a full channel.",
+            s.signals_dropped,
+        ),
+        (
+            "snapshots_published",
+            "Market snapshots handed to brain.",
+            s.snapshots_published,
+        ),
+        (
+            "shadow_processed",
+            "Signals settled by shadow.",
+            s.shadow_processed,
+        ),
+        (
+            "ws_reconnects",
+            "Market WS connections retried after an error.",
+            s.ws_reconnects,
+        ),
+        (
+            "trade_store_evicted",
+            "Trades evicted from the shadow store.",
+            s.trade_store_evicted,
+        ),
+    ];
+    for (name, help, v) in counters {
+        let _ = writeln!(out, "# HELP razor_{name}_total {help}");
+        let _ = writeln!(out, "# TYPE razor_{name}_total counter");
+        let _ = writeln!(out, "razor_{name}_total {v}");
+    }
+    let gauges: [(&str, &str, u64); 3] = [
+        (
+            "trade_store_size",
+            "Trades held in the shadow store.",
+            s.trade_store_size,
+        ),
+        (
+            "disk_free_bytes",
+            "Free bytes on the data volume.",
+            s.disk_free_bytes,
+        ),
+        (
+            "last_tick_ingest_ms",
+            "Unix ms of the last tick.",
+            s.last_tick_ingest_ms,
+        ),
+    ];
+    for (name, help, v) in gauges {
+        let _ = writeln!(out, "# HELP razor_{name} {help}");
+        let _ = writeln!(out, "# TYPE razor_{name} gauge");
+        let _ = writeln!(out, "razor_{name} {v}");
+    }
+    type ChannelValue = fn(&ChannelSnapshot) -> u64;
+    let channel_series: [(&str, &str, &str, ChannelValue); 3] = [
+        (
+            "channel_depth",
+            "gauge",
+            "Items queued per in-process channel.",
+            |c| c.len as u64,
+        ),
+        (
+            "channel_capacity",
+            "gauge",
+            "Capacity per in-process channel.",
+            |c| c.capacity as u64,
+        ),
+        (
+            "channel_dropped_total",
+            "counter",
+            "Items dropped per in-process channel.",
+            |c| c.dropped,
+        ),
+    ];
+    for (name, kind, help, value) in channel_series {
+        let _ = writeln!(out, "# HELP razor_{name} {help}");
+        let _ = writeln!(out, "# TYPE razor_{name} {kind}");
+        for c in &s.channels {
+            let _ = writeln!(out, "razor_{name}{{channel=\"{}\"}} {}", c.name, value(c));
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::backpressure;
+    use crate::config::ChannelPolicy;
+
+    #[test]
+    fn renders_counters_and_per_channel_depth() {
+        let health = HealthCounters::default();
+        health.inc_ticks_processed(7);
+        health.inc_ws_reconnects(2);
+        let (tx, _rx) = backpressure::channel::<u32>("signal", 16, ChannelPolicy::default());
+        health.track_channel(tx.stats());
+        let text = render(&health.snapshot());
+        assert!(text.contains("# TYPE razor_ticks_processed_total counter\n"));
+        assert!(text.contains("razor_ticks_processed_total 7\n"));
+        assert!(text.contains("razor_ws_reconnects_total 2\n"));
+        assert!(text.contains("razor_channel_depth{channel=\"signal\"} 0\n"));
+        assert!(text.contains("razor_channel_capacity{channel=\"signal\"} 16\n"));
+    }
+}
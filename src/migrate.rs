@@ -0,0 +1,215 @@
+use std::path::{Path, PathBuf};
+
+use anyhow::Context as _;
+use serde::Serialize;
+
+use crate::recorder::{csv_input_paths, TICKS_HEADER};
+use crate::schema::{
+    CALIBRATION_LOG_HEADER, FILE_CALIBRATION_LOG, FILE_META_JSON, FILE_RUN_CONFIG,
+    FILE_RUN_META_JSON, FILE_SCHEMA_VERSION, FILE_SHADOW_LOG, FILE_SNAPSHOTS, FILE_TICKS,
+    FILE_TRADES, FILE_TRADE_LOG, SCHEMA_VERSION, SHADOW_HEADER, SNAPSHOTS_HEADER, TRADES_HEADER,
+    TRADE_LOG_HEADER,
+};
+use crate::schema_adapt::{FrozenCsvReader, SchemaMode};
+use crate::types::now_ms;
+
+pub const FILE_MIGRATION_JSON: &str = "migration.json";
+
+/// Frozen CSVs the migrator rewrites, with their current header.
+const FROZEN_CSVS: [(&str, &[&str]); 6] = [
+    (FILE_TICKS, &TICKS_HEADER),
+    (FILE_TRADES, &TRADES_HEADER),
+    (FILE_SNAPSHOTS, &SNAPSHOTS_HEADER),
+    (FILE_SHADOW_LOG, &SHADOW_HEADER),
+    (FILE_TRADE_LOG, &TRADE_LOG_HEADER),
+    (FILE_CALIBRATION_LOG, &CALIBRATION_LOG_HEADER),
+];
+
+/// Small run metadata copied verbatim so the migrated dir works as a replay/sweep input.
+const COPIED_AS_IS: [&str; 3] = [FILE_RUN_CONFIG, FILE_RUN_META_JSON, FILE_META_JSON];
+
+#[derive(Debug, Clone, Serialize)]
+pub struct MigratedFile {
+    pub path: String,
+    /// `copied` (already current) or `upgraded`.
+    pub action: &'static str,
+    pub from_version: Option<&'static str>,
+    pub rows: u64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct MigrationReport {
+    pub source_run_dir: String,
+    pub to_schema_version: String,
+    pub migrated_ts_ms: u64,
+    pub files: Vec<MigratedFile>,
+}
+
+fn migrate_csv(
+    file: &str,
+    current: &[&str],
+    src: &Path,
+    dst: &Path,
+) -> anyhow::Result<(&'static str, Option<&'static str>, u64)> {
+    let mut rdr = FrozenCsvReader::open(src, file, current, SchemaMode::Compat)
+        .with_context(|| format!("no migration adapter for {}", src.display()))?;
+    // shadow_log rows carry their own spec version; a differing one means the accounting
+    // semantics changed and only an explicit adapter may relabel it.
+    let schema_col = (file == FILE_SHADOW_LOG)
+        .then(|| current.iter().position(|h| *h == "schema_version"))
+        .flatten();
+
+    if let Some(parent) = dst.parent() {
+        std::fs::create_dir_all(parent).with_context(|| format!("create {}", parent.display()))?;
+    }
+    let mut out =
+        csv::Writer::from_path(dst).with_context(|| format!("create {}", dst.display()))?;
+    out.write_record(rdr.header())?;
+    let mut rows = 0u64;
+    let from_version = rdr.adapted_from();
+    for record in rdr.records() {
+        let record = record?;
+        if let Some(i) = schema_col {
+            let v = record.get(i).unwrap_or("");
+            if !v.eq_ignore_ascii_case(SCHEMA_VERSION) {
+                anyhow::bail!(
+                    "no migration adapter for {} rows with schema_version={v} (current {SCHEMA_VERSION})",
+                    src.display()
+                );
+            }
+        }
+        out.write_record(&record)?;
+        rows += 1;
+    }
+    out.flush()?;
+    crate::recorder::write_csv_sidecar(dst, current)?;
+    Ok(match from_version {
+        Some(v) => ("upgraded", Some(v), rows),
+        None => ("copied", None, rows),
+    })
+}
+
+/// Upgrades a run dir's frozen CSVs to the current schema into `out_dir` (default
+/// `<run_dir>/migrated_<SCHEMA_VERSION>`). The source is never modified and an existing output
+/// dir is never overwritten.
+pub fn migrate_run(run_dir: &Path, out_dir: Option<PathBuf>) -> anyhow::Result<MigrationReport> {
+    anyhow::ensure!(run_dir.is_dir(), "run dir not found: {}", run_dir.display());
+    let out_dir = out_dir.unwrap_or_else(|| run_dir.join(format!("migrated_{SCHEMA_VERSION}")));
+    anyhow::ensure!(
+        !out_dir.exists(),
+        "output dir already exists: {}",
+        out_dir.display()
+    );
+    // Built under a scratch name and renamed at the end, so a failed migration leaves no
+    // half-written output behind.
+    let scratch = PathBuf::from(format!("{}.partial", out_dir.display()));
+    if scratch.exists() {
+        std::fs::remove_dir_all(&scratch)
+            .with_context(|| format!("remove {}", scratch.display()))?;
+    }
+    match write_migration(run_dir, &scratch) {
+        Ok(report) => {
+            std::fs::rename(&scratch, &out_dir).with_context(|| {
+                format!("rename {} -> {}", scratch.display(), out_dir.display())
+            })?;
+            Ok(report)
+        }
+        Err(e) => {
+            let _ = std::fs::remove_dir_all(&scratch);
+            Err(e)
+        }
+    }
+}
+
+fn write_migration(run_dir: &Path, out_dir: &Path) -> anyhow::Result<MigrationReport> {
+    std::fs::create_dir_all(out_dir).with_context(|| format!("create {}", out_dir.display()))?;
+
+    let mut files = Vec::new();
+    for (file, current) in FROZEN_CSVS {
+        for src in csv_input_paths(run_dir, file) {
+            let rel = src.strip_prefix(run_dir).unwrap_or(&src).to_path_buf();
+            let (action, from_version, rows) =
+                migrate_csv(file, current, &src, &out_dir.join(&rel))?;
+            files.push(MigratedFile {
+                path: rel.display().to_string(),
+                action,
+                from_version,
+                rows,
+            });
+        }
+    }
+    for file in COPIED_AS_IS {
+        let src = run_dir.join(file);
+        if src.is_file() {
+            std::fs::copy(&src, out_dir.join(file))
+                .with_context(|| format!("copy {}", src.display()))?;
+        }
+    }
+    let ts = now_ms();
+    crate::schema::write_schema_version_json(out_dir, SCHEMA_VERSION, ts)
+        .with_context(|| format!("write {FILE_SCHEMA_VERSION}"))?;
+
+    let report = MigrationReport {
+        source_run_dir: run_dir.display().to_string(),
+        to_schema_version: SCHEMA_VERSION.to_string(),
+        migrated_ts_ms: ts,
+        files,
+    };
+    let path = out_dir.join(FILE_MIGRATION_JSON);
+    std::fs::write(&path, serde_json::to_vec_pretty(&report)?)
+        .with_context(|| format!("write {}", path.display()))?;
+    Ok(report)
+}
+
+/// `razor migrate <run_dir>`.
+pub fn run_migrate(run_dir: &Path, out_dir: Option<PathBuf>) -> anyhow::Result<()> {
+    let report = migrate_run(run_dir, out_dir)?;
+    for f in &report.files {
+        match f.from_version {
+            Some(v) => println!("{} {} (from {v}, {} rows)", f.action, f.path, f.rows),
+            None => println!("{} {} ({} rows)", f.action, f.path, f.rows),
+        }
+    }
+    println!("migrate: to_schema_version={}", report.to_schema_version);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn upgrades_old_trades_and_refuses_unknown_shadow_versions() {
+        let run = std::env::temp_dir().join(format!("razor_migrate_{}", now_ms()));
+        std::fs::create_dir_all(&run).unwrap();
+        std::fs::write(
+            run.join(FILE_TRADES),
+            "ts_ms,market_id,token_id,price,size,trade_id\n1000,m1,t1,0.5,10,x1\n",
+        )
+        .unwrap();
+        std::fs::write(run.join(FILE_SNAPSHOTS), SNAPSHOTS_HEADER.join(",") + "\n").unwrap();
+
+        let report = migrate_run(&run, None).unwrap();
+        let out = run.join(format!("migrated_{SCHEMA_VERSION}"));
+        assert_eq!(
+            std::fs::read_to_string(out.join(FILE_TRADES)).unwrap(),
+            format!("{}\n1000,m1,t1,0.5,10,x1,1000,,\n", TRADES_HEADER.join(","))
+        );
+        assert_eq!(report.files[0].from_version, Some("v1"));
+        assert_eq!(report.files[1].action, "copied");
+        assert!(out.join(FILE_SCHEMA_VERSION).exists());
+        assert!(migrate_run(&run, None).is_err(), "never overwrites");
+
+        let mut row = vec![""; SHADOW_HEADER.len()];
+        row[1] = "1.3.1";
+        std::fs::write(
+            run.join(FILE_SHADOW_LOG),
+            format!("{}\n{}\n", SHADOW_HEADER.join(","), row.join(",")),
+        )
+        .unwrap();
+        let err = migrate_run(&run, Some(run.join("m2"))).unwrap_err();
+        assert!(err.to_string().contains("schema_version=1.3.1"));
+        assert!(!run.join("m2").exists() && !run.join("m2.partial").exists());
+        let _ = std::fs::remove_dir_all(&run);
+    }
+}
@@ -0,0 +1,609 @@
+//! `razor orchestrate <plan.toml>`: launches several configured runs as child `razor run`
+//! processes, supervises them, aggregates their heartbeats and writes a combined summary.
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::path::{Path, PathBuf};
+use std::process::Stdio;
+use std::time::Duration;
+
+use anyhow::Context as _;
+use razor::cli::LogFormat;
+use razor::run_compare::{summarize_run_dir, write_runs_summary_csv, write_runs_summary_md};
+use serde::{Deserialize, Serialize};
+use tracing::{error, info, warn};
+
+use crate::config::ENV_OVERRIDE_PREFIX;
+use crate::graceful_shutdown;
+use crate::run_context::latest_run_dir;
+use crate::schema::FILE_HEALTH_JSONL;
+use crate::top::Tail;
+use crate::types::now_ms;
+
+pub const FILE_ORCHESTRATE_HEALTH: &str = "orchestrate_health.jsonl";
+pub const FILE_ORCHESTRATE_SUMMARY: &str = "orchestrate_summary.json";
+/// Child stdout/stderr, in the child's data dir.
+const FILE_CHILD_LOG: &str = "razor.log";
+
+const POLL_INTERVAL: Duration = Duration::from_millis(500);
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Plan {
+    /// Each run records under `<out_dir>/<name>` unless its `set` overrides `run.data_dir`.
+    #[serde(default = "default_out_dir")]
+    pub out_dir: PathBuf,
+    /// Aggregated heartbeat cadence (`orchestrate_health.jsonl`).
+    #[serde(default = "default_health_interval_ms")]
+    pub health_interval_ms: u64,
+    /// Stop the remaining runs as soon as one exits non-zero.
+    #[serde(default)]
+    pub fail_fast: bool,
+    pub runs: Vec<PlanRun>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct PlanRun {
+    pub name: String,
+    /// Config file for this run (default: the orchestrator's `--config`/`--profile`).
+    #[serde(default)]
+    pub config: Option<PathBuf>,
+    /// Profile under the orchestrator's `--config-dir`; exclusive with `config`.
+    #[serde(default)]
+    pub profile: Option<String>,
+    /// `--mode` for the child (default: its own `RAZOR_MODE` / dry_run).
+    #[serde(default)]
+    pub mode: Option<String>,
+    /// Dotted config keys passed to the child as `RAZOR__*` overrides.
+    #[serde(default)]
+    pub set: BTreeMap<String, toml::Value>,
+    /// Passed to the child as `--tag key=value` (see `[run.labels]`).
+    #[serde(default)]
+    pub tags: BTreeMap<String, String>,
+}
+
+fn default_out_dir() -> PathBuf {
+    PathBuf::from("data/orchestrate")
+}
+
+fn default_health_interval_ms() -> u64 {
+    30_000
+}
+
+impl Plan {
+    pub fn load(path: &Path) -> anyhow::Result<Self> {
+        let raw =
+            std::fs::read_to_string(path).with_context(|| format!("read {}", path.display()))?;
+        let plan: Plan =
+            toml::from_str(&raw).with_context(|| format!("parse {}", path.display()))?;
+        plan.validate()?;
+        Ok(plan)
+    }
+
+    fn validate(&self) -> anyhow::Result<()> {
+        anyhow::ensure!(!self.runs.is_empty(), "plan has no [[runs]]");
+        anyhow::ensure!(
+            self.health_interval_ms > 0,
+            "health_interval_ms must be > 0"
+        );
+        let mut names = BTreeSet::new();
+        let mut data_dirs = BTreeSet::new();
+        for run in &self.runs {
+            anyhow::ensure!(
+                !run.name.is_empty()
+                    && run
+                        .name
+                        .chars()
+                        .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-'),
+                "run name {:?} must be non-empty [A-Za-z0-9_-]",
+                run.name
+            );
+            anyhow::ensure!(
+                names.insert(run.name.as_str()),
+                "duplicate run name {:?}",
+                run.name
+            );
+            anyhow::ensure!(
+                run.config.is_none() || run.profile.is_none(),
+                "run {:?}: set either config or profile, not both",
+                run.name
+            );
+            for key in run.set.keys() {
+                anyhow::ensure!(
+                    key.split('.').all(|p| !p.is_empty()),
+                    "run {:?}: bad set key {key:?} (expected section.key)",
+                    run.name
+                );
+            }
+            for (key, value) in &run.tags {
+                crate::run_meta::validate_tag(key, value)
+                    .map_err(|e| anyhow::anyhow!("run {:?}: {e}", run.name))?;
+            }
+            let data_dir = self.data_dir(run);
+            anyhow::ensure!(
+                data_dirs.insert(data_dir.clone()),
+                "run {:?}: data dir {} is shared with another run",
+                run.name,
+                data_dir.display()
+            );
+        }
+        Ok(())
+    }
+
+    fn data_dir(&self, run: &PlanRun) -> PathBuf {
+        match run.set.get("run.data_dir") {
+            Some(toml::Value::String(s)) => PathBuf::from(s),
+            _ => self.out_dir.join(&run.name),
+        }
+    }
+}
+
+/// `set` entries as `RAZOR__SECTION__KEY` variables; strings are passed raw, everything else as
+/// its TOML literal (which `config::apply_env_overrides` parses back).
+pub fn env_overrides(set: &BTreeMap<String, toml::Value>) -> Vec<(String, String)> {
+    set.iter()
+        .map(|(key, value)| {
+            let var = format!(
+                "{ENV_OVERRIDE_PREFIX}{}",
+                key.split('.')
+                    .map(str::to_ascii_uppercase)
+                    .collect::<Vec<_>>()
+                    .join("__")
+            );
+            let value = match value {
+                toml::Value::String(s) => s.clone(),
+                other => other.to_string(),
+            };
+            (var, value)
+        })
+        .collect()
+}
+
+/// Global options of the orchestrator invocation, inherited by children that don't name their
+/// own config.
+pub struct Launcher {
+    pub config: String,
+    pub profile: Option<String>,
+    pub config_dir: PathBuf,
+    pub log_format: LogFormat,
+}
+
+impl Launcher {
+    fn command(&self, plan: &Plan, run: &PlanRun) -> anyhow::Result<tokio::process::Command> {
+        let exe = std::env::current_exe().context("locate razor executable")?;
+        let mut cmd = tokio::process::Command::new(exe);
+        match (&run.config, &run.profile, &self.profile) {
+            (Some(config), _, _) => {
+                cmd.arg("--config").arg(config);
+            }
+            (None, Some(profile), _) | (None, None, Some(profile)) => {
+                cmd.arg("--profile")
+                    .arg(profile)
+                    .arg("--config-dir")
+                    .arg(&self.config_dir);
+            }
+            (None, None, None) => {
+                cmd.arg("--config").arg(&self.config);
+            }
+        }
+        cmd.arg("--log-format").arg(match self.log_format {
+            LogFormat::Text => "text",
+            LogFormat::Json => "json",
+        });
+        cmd.arg("run");
+        if let Some(mode) = &run.mode {
+            cmd.arg("--mode").arg(mode);
+        }
+        for (key, value) in &run.tags {
+            cmd.arg("--tag").arg(format!("{key}={value}"));
+        }
+        cmd.envs(env_overrides(&run.set));
+        if !run.set.contains_key("run.data_dir") {
+            cmd.env(
+                format!("{ENV_OVERRIDE_PREFIX}RUN__DATA_DIR"),
+                plan.data_dir(run),
+            );
+        }
+        Ok(cmd)
+    }
+}
+
+struct Child {
+    name: String,
+    data_dir: PathBuf,
+    process: Option<tokio::process::Child>,
+    started_ms: u64,
+    exited_ms: Option<u64>,
+    exit_code: Option<i32>,
+    run_dir: Option<PathBuf>,
+    health: Option<Tail>,
+    heartbeat: Option<serde_json::Value>,
+}
+
+impl Child {
+    fn running(&self) -> bool {
+        self.process.is_some()
+    }
+
+    fn succeeded(&self) -> bool {
+        self.exit_code == Some(0)
+    }
+
+    /// Follows the child's current run dir (it appears only once the child has started) and
+    /// keeps its newest heartbeat.
+    fn refresh_health(&mut self) {
+        let Some(run_dir) = latest_run_dir(&self.data_dir) else {
+            return;
+        };
+        if self.run_dir.as_deref() != Some(run_dir.as_path()) {
+            self.health = Some(Tail::new(run_dir.join(FILE_HEALTH_JSONL)));
+            self.heartbeat = None;
+            self.run_dir = Some(run_dir);
+        }
+        let Some(tail) = self.health.as_mut() else {
+            return;
+        };
+        let lines = match tail.read_new_lines() {
+            Ok(v) => v,
+            Err(e) => {
+                warn!(run = %self.name, error = %e, "read child health.jsonl failed");
+                return;
+            }
+        };
+        if let Some(hb) = last_heartbeat(&lines) {
+            self.heartbeat = Some(hb);
+        }
+    }
+
+    fn status(&self, now: u64) -> ChildStatus {
+        let hb = self.heartbeat.as_ref();
+        let field = |k: &str| hb.and_then(|v| v.get(k)).and_then(|v| v.as_u64());
+        ChildStatus {
+            name: self.name.clone(),
+            state: match (self.running(), self.exit_code) {
+                (true, _) => "running",
+                (false, Some(0)) => "exited",
+                (false, _) => "failed",
+            },
+            exit_code: self.exit_code,
+            heartbeat_age_ms: field("ts_ms").map(|ts| now.saturating_sub(ts)),
+            ticks_processed: field("ticks_processed"),
+            trades_written: field("trades_written"),
+            signals_emitted: field("signals_emitted"),
+            shadow_processed: field("shadow_processed"),
+        }
+    }
+}
+
+/// Newest `heartbeat` line among `lines`.
+pub fn last_heartbeat(lines: &[String]) -> Option<serde_json::Value> {
+    lines.iter().rev().find_map(|line| {
+        let v: serde_json::Value = serde_json::from_str(line).ok()?;
+        (v.get("type").and_then(|t| t.as_str()) == Some("heartbeat")).then_some(v)
+    })
+}
+
+#[derive(Debug, Serialize)]
+struct ChildStatus {
+    name: String,
+    state: &'static str,
+    exit_code: Option<i32>,
+    heartbeat_age_ms: Option<u64>,
+    ticks_processed: Option<u64>,
+    trades_written: Option<u64>,
+    signals_emitted: Option<u64>,
+    shadow_processed: Option<u64>,
+}
+
+#[derive(Debug, Serialize)]
+struct HealthRecord {
+    ts_ms: u64,
+    running: usize,
+    failed: usize,
+    runs: Vec<ChildStatus>,
+}
+
+#[derive(Debug, Serialize)]
+struct RunOutcome {
+    name: String,
+    data_dir: PathBuf,
+    run_dir: Option<PathBuf>,
+    started_ms: u64,
+    exited_ms: Option<u64>,
+    exit_code: Option<i32>,
+    last_heartbeat: Option<serde_json::Value>,
+}
+
+#[derive(Debug, Serialize)]
+struct Summary {
+    plan: PathBuf,
+    started_ms: u64,
+    finished_ms: u64,
+    stopped_by: Option<String>,
+    runs: Vec<RunOutcome>,
+}
+
+pub async fn run_orchestrate(plan_path: &Path, launcher: &Launcher) -> anyhow::Result<()> {
+    let plan = Plan::load(plan_path)?;
+    std::fs::create_dir_all(&plan.out_dir)
+        .with_context(|| format!("create {}", plan.out_dir.display()))?;
+    let started_ms = now_ms();
+
+    let mut children = Vec::with_capacity(plan.runs.len());
+    for run in &plan.runs {
+        let data_dir = plan.data_dir(run);
+        std::fs::create_dir_all(&data_dir)
+            .with_context(|| format!("create {}", data_dir.display()))?;
+        let log_path = data_dir.join(FILE_CHILD_LOG);
+        let log = std::fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&log_path)
+            .with_context(|| format!("open {}", log_path.display()))?;
+        let mut cmd = launcher.command(&plan, run)?;
+        cmd.stdin(Stdio::null())
+            .stdout(log.try_clone()?)
+            .stderr(log)
+            .kill_on_drop(true);
+        let process = cmd
+            .spawn()
+            .with_context(|| format!("spawn run {:?}", run.name))?;
+        info!(
+            run = %run.name,
+            pid = process.id().unwrap_or(0),
+            data_dir = %data_dir.display(),
+            log = %log_path.display(),
+            "orchestrate: run started"
+        );
+        children.push(Child {
+            name: run.name.clone(),
+            data_dir,
+            process: Some(process),
+            started_ms: now_ms(),
+            exited_ms: None,
+            exit_code: None,
+            run_dir: None,
+            health: None,
+            heartbeat: None,
+        });
+    }
+
+    let health_path = plan.out_dir.join(FILE_ORCHESTRATE_HEALTH);
+    let mut health_tick = tokio::time::interval(Duration::from_millis(plan.health_interval_ms));
+    let mut poll = tokio::time::interval(POLL_INTERVAL);
+    let stop = graceful_shutdown::stop_signal();
+    tokio::pin!(stop);
+    let mut stopped_by: Option<String> = None;
+
+    while children.iter().any(Child::running) {
+        tokio::select! {
+            name = &mut stop, if stopped_by.is_none() => {
+                info!(signal = name, "orchestrate: stop signal received; stopping runs");
+                stopped_by = Some(name.to_string());
+                children.iter_mut().for_each(request_stop);
+            }
+            _ = health_tick.tick() => {
+                let now = now_ms();
+                let runs: Vec<ChildStatus> = children
+                    .iter_mut()
+                    .map(|c| {
+                        c.refresh_health();
+                        c.status(now)
+                    })
+                    .collect();
+                for s in &runs {
+                    info!(
+                        run = %s.name,
+                        state = s.state,
+                        heartbeat_age_ms = ?s.heartbeat_age_ms,
+                        ticks = ?s.ticks_processed,
+                        signals = ?s.signals_emitted,
+                        "orchestrate: run health"
+                    );
+                }
+                let record = HealthRecord {
+                    ts_ms: now,
+                    running: runs.iter().filter(|s| s.state == "running").count(),
+                    failed: runs.iter().filter(|s| s.state == "failed").count(),
+                    runs,
+                };
+                if let Err(e) = append_json_line(&health_path, &record) {
+                    warn!(error = %e, "orchestrate: write {} failed", health_path.display());
+                }
+            }
+            _ = poll.tick() => {
+                let mut failed = None;
+                for c in children.iter_mut() {
+                    let Some(process) = c.process.as_mut() else {
+                        continue;
+                    };
+                    let status = match process.try_wait() {
+                        Ok(Some(status)) => status,
+                        Ok(None) => continue,
+                        Err(e) => {
+                            warn!(run = %c.name, error = %e, "orchestrate: wait failed");
+                            continue;
+                        }
+                    };
+                    c.process = None;
+                    c.exited_ms = Some(now_ms());
+                    c.exit_code = status.code();
+                    if status.success() {
+                        info!(run = %c.name, "orchestrate: run finished");
+                    } else {
+                        error!(run = %c.name, %status, "orchestrate: run failed");
+                        failed.get_or_insert_with(|| c.name.clone());
+                    }
+                }
+                if let Some(name) = failed {
+                    if plan.fail_fast && stopped_by.is_none() {
+                        warn!(run = %name, "orchestrate: fail_fast; stopping remaining runs");
+                        stopped_by = Some(format!("fail_fast:{name}"));
+                        children.iter_mut().for_each(request_stop);
+                    }
+                }
+            }
+        }
+    }
+
+    for c in children.iter_mut() {
+        c.refresh_health();
+    }
+    let summary = Summary {
+        plan: plan_path.to_path_buf(),
+        started_ms,
+        finished_ms: now_ms(),
+        stopped_by,
+        runs: children
+            .iter()
+            .map(|c| RunOutcome {
+                name: c.name.clone(),
+                data_dir: c.data_dir.clone(),
+                run_dir: c.run_dir.clone(),
+                started_ms: c.started_ms,
+                exited_ms: c.exited_ms,
+                exit_code: c.exit_code,
+                last_heartbeat: c.heartbeat.clone(),
+            })
+            .collect(),
+    };
+    let summary_path = plan.out_dir.join(FILE_ORCHESTRATE_SUMMARY);
+    std::fs::write(&summary_path, serde_json::to_vec_pretty(&summary)?)
+        .with_context(|| format!("write {}", summary_path.display()))?;
+
+    let mut compared = Vec::new();
+    for c in &children {
+        let Some(run_dir) = c.run_dir.as_deref() else {
+            continue;
+        };
+        match summarize_run_dir(run_dir) {
+            Ok(s) => compared.push(s),
+            Err(e) => warn!(run = %c.name, error = %e, "orchestrate: run not summarized"),
+        }
+    }
+    if !compared.is_empty() {
+        write_runs_summary_csv(&plan.out_dir, &compared)?;
+        let md = write_runs_summary_md(&plan.out_dir, &compared)?;
+        info!(path = %md.display(), runs = compared.len(), "orchestrate: runs summary written");
+    }
+    info!(path = %summary_path.display(), "orchestrate: summary written");
+
+    let failed: Vec<&str> = children
+        .iter()
+        .filter(|c| !c.succeeded())
+        .map(|c| c.name.as_str())
+        .collect();
+    anyhow::ensure!(
+        failed.is_empty(),
+        "orchestrate: runs failed: {}",
+        failed.join(", ")
+    );
+    Ok(())
+}
+
+/// Asks a child for the same graceful shutdown as Ctrl-C (SIGTERM); on Windows, where there is no
+/// per-process console signal, it is killed.
+fn request_stop(child: &mut Child) {
+    let Some(process) = child.process.as_mut() else {
+        return;
+    };
+    #[cfg(unix)]
+    if let Some(pid) = process.id() {
+        // SAFETY: plain kill(2) on a pid we spawned and have not reaped yet.
+        unsafe {
+            libc::kill(pid as libc::pid_t, libc::SIGTERM);
+        }
+        return;
+    }
+    if let Err(e) = process.start_kill() {
+        warn!(run = %child.name, error = %e, "orchestrate: kill failed");
+    }
+}
+
+fn append_json_line<T: Serialize>(path: &Path, value: &T) -> anyhow::Result<()> {
+    use std::io::Write as _;
+    let mut f = std::fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path)
+        .with_context(|| format!("open {}", path.display()))?;
+    let mut line = serde_json::to_vec(value)?;
+    line.push(b'\n');
+    f.write_all(&line)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn plan_parses_defaults_and_rejects_bad_runs() {
+        let plan: Plan = toml::from_str(
+            r#"
+            [[runs]]
+            name = "a"
+            [runs.set]
+            "brain.q_req" = 12.5
+
+            [[runs]]
+            name = "b-2"
+            profile = "prod"
+            "#,
+        )
+        .unwrap();
+        plan.validate().unwrap();
+        assert_eq!(plan.out_dir, PathBuf::from("data/orchestrate"));
+        assert_eq!(plan.health_interval_ms, 30_000);
+        assert_eq!(
+            plan.data_dir(&plan.runs[1]),
+            PathBuf::from("data/orchestrate/b-2")
+        );
+
+        let dup: Plan = toml::from_str("[[runs]]\nname = \"a\"\n[[runs]]\nname = \"a\"\n").unwrap();
+        assert!(dup.validate().is_err());
+        let bad_name: Plan = toml::from_str("[[runs]]\nname = \"a/b\"\n").unwrap();
+        assert!(bad_name.validate().is_err());
+        let shared: Plan = toml::from_str(
+            "out_dir = \"d\"\n[[runs]]\nname = \"a\"\n[[runs]]\nname = \"b\"\n[runs.set]\n\"run.data_dir\" = \"d/a\"\n",
+        )
+        .unwrap();
+        assert!(shared.validate().is_err());
+        assert!(toml::from_str::<Plan>("[[runs]]\nname = \"a\"\nbogus = 1\n").is_err());
+        let bad_tag: Plan =
+            toml::from_str("[[runs]]\nname = \"a\"\n[runs.tags]\nmarket = \"nba;nfl\"\n").unwrap();
+        assert!(bad_tag.validate().is_err());
+    }
+
+    #[test]
+    fn set_maps_to_env_overrides() {
+        let mut set = BTreeMap::new();
+        set.insert("brain.q_req".to_string(), toml::Value::Float(12.5));
+        set.insert(
+            "run.market_ids".to_string(),
+            toml::Value::Array(vec![toml::Value::String("1".into())]),
+        );
+        set.insert("run.run_id".to_string(), toml::Value::String("x".into()));
+        assert_eq!(
+            env_overrides(&set),
+            vec![
+                ("RAZOR__BRAIN__Q_REQ".to_string(), "12.5".to_string()),
+                ("RAZOR__RUN__MARKET_IDS".to_string(), "[\"1\"]".to_string()),
+                ("RAZOR__RUN__RUN_ID".to_string(), "x".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn last_heartbeat_skips_other_lines() {
+        let lines = [
+            r#"{"type":"heartbeat","ts_ms":1}"#,
+            r#"{"type":"heartbeat","ts_ms":2}"#,
+            r#"{"type":"feed_stale","ts_ms":3}"#,
+            "not json",
+        ]
+        .map(String::from);
+        assert_eq!(last_heartbeat(&lines).unwrap()["ts_ms"], 2);
+        assert!(last_heartbeat(&lines[2..]).is_none());
+    }
+}
@@ -0,0 +1,175 @@
+//! Per-token L2 order book: a `book` frame replaces both sides, `price_change` entries set one
+//! level's aggregate size (0 removes it). Snapshots carry the ask side as an [`AskLadder`] so
+//! brain and buckets can ask how much is offered up to a price instead of only the top-3 sum.
+//! `ask_depth3_usdc` is still taken from `book` frames alone, as before.
+
+use std::collections::BTreeMap;
+use std::sync::Arc;
+
+use crate::types::{Price, Qty, Side};
+use crate::ws_msg::Level;
+
+#[derive(Debug, Clone, Default)]
+pub struct OrderBook {
+    bids: BTreeMap<Price, Qty>,
+    asks: BTreeMap<Price, Qty>,
+}
+
+#[allow(dead_code)]
+impl OrderBook {
+    /// Replaces the book with a full snapshot; levels without a positive price and size are
+    /// dropped.
+    pub fn apply_snapshot(&mut self, bids: &[Level], asks: &[Level]) {
+        self.bids = side_levels(bids);
+        self.asks = side_levels(asks);
+    }
+
+    /// Sets the aggregate size resting at `price` on `side` (`Buy` = bids); zero removes it.
+    pub fn apply_change(&mut self, side: Side, price: Price, size: Qty) {
+        if !price.is_positive() {
+            return;
+        }
+        let levels = match side {
+            Side::Buy => &mut self.bids,
+            Side::Sell => &mut self.asks,
+        };
+        if size.is_positive() {
+            levels.insert(price, size);
+        } else {
+            levels.remove(&price);
+        }
+    }
+
+    pub fn best_bid(&self) -> Option<(Price, Qty)> {
+        self.bids.iter().next_back().map(|(p, q)| (*p, *q))
+    }
+
+    pub fn best_ask(&self) -> Option<(Price, Qty)> {
+        self.asks.iter().next().map(|(p, q)| (*p, *q))
+    }
+
+    /// The ask side, cheapest first.
+    pub fn ask_ladder(&self) -> AskLadder {
+        AskLadder(self.asks.iter().map(|(p, q)| (*p, *q)).collect())
+    }
+}
+
+fn side_levels(levels: &[Level]) -> BTreeMap<Price, Qty> {
+    levels
+        .iter()
+        .filter_map(|l| {
+            let px = Price::from_f64(l.price?)?;
+            let sz = Qty::from_f64(l.size?)?;
+            (px.is_positive() && sz.is_positive()).then_some((px, sz))
+        })
+        .collect()
+}
+
+/// Ask levels `(price, size)` cheapest first, shared cheaply between snapshot clones. Empty
+/// when the source has no L2 book (snapshots.csv replay, demo feed).
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct AskLadder(Arc<[(Price, Qty)]>);
+
+#[allow(dead_code)]
+impl AskLadder {
+    pub fn from_levels(mut levels: Vec<(Price, Qty)>) -> Self {
+        levels.retain(|(p, q)| p.is_positive() && q.is_positive());
+        levels.sort_by_key(|(p, _)| *p);
+        Self(levels.into())
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    pub fn levels(&self) -> &[(Price, Qty)] {
+        &self.0
+    }
+
+    /// USDC notional of the `n` cheapest levels.
+    pub fn depth_usdc(&self, n: usize) -> f64 {
+        self.0.iter().take(n).map(|(p, q)| p.notional(*q)).sum()
+    }
+
+    /// Shares offered at `limit` or cheaper.
+    pub fn qty_at_or_below(&self, limit: Price) -> Qty {
+        self.0
+            .iter()
+            .take_while(|(p, _)| *p <= limit)
+            .fold(Qty::ZERO, |acc, (_, q)| acc.saturating_add(*q))
+    }
+
+    /// USDC notional offered at `limit` or cheaper.
+    pub fn usdc_at_or_below(&self, limit: Price) -> f64 {
+        self.0
+            .iter()
+            .take_while(|(p, _)| *p <= limit)
+            .map(|(p, q)| p.notional(*q))
+            .sum()
+    }
+
+    /// Average price of buying `qty` by walking the book; `None` when the book is too thin.
+    pub fn vwap_for_qty(&self, qty: Qty) -> Option<f64> {
+        if !qty.is_positive() {
+            return None;
+        }
+        let mut left = qty;
+        let mut cost = 0.0;
+        for (p, q) in self.0.iter() {
+            let take = (*q).min(left);
+            cost += p.notional(take);
+            left = left.saturating_sub(take);
+            if !left.is_positive() {
+                return Some(cost / qty.to_f64());
+            }
+        }
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn px(x: f64) -> Price {
+        Price::from_f64(x).unwrap()
+    }
+
+    fn qty(x: f64) -> Qty {
+        Qty::from_f64(x).unwrap()
+    }
+
+    #[test]
+    fn snapshot_then_deltas_maintain_both_sides_and_depth_queries() {
+        let levels: Vec<Level> = serde_json::from_str(
+            r#"[{"price":"0.50","size":"10"},{"price":"0.52","size":"20"},{"price":"0.55","size":"30"},
+                {"price":"0.60","size":"40"},{"price":"0","size":"5"}]"#,
+        )
+        .unwrap();
+        let bids: Vec<Level> =
+            serde_json::from_str(r#"[{"price":"0.48","size":"7"},{"price":"0.47","size":"9"}]"#)
+                .unwrap();
+        let mut book = OrderBook::default();
+        book.apply_snapshot(&bids, &levels);
+        assert_eq!(book.best_bid(), Some((px(0.48), qty(7.0))));
+        assert_eq!(book.best_ask(), Some((px(0.50), qty(10.0))));
+
+        book.apply_change(Side::Sell, px(0.50), Qty::ZERO);
+        book.apply_change(Side::Sell, px(0.51), qty(5.0));
+        book.apply_change(Side::Buy, px(0.49), qty(3.0));
+        assert_eq!(book.best_bid(), Some((px(0.49), qty(3.0))));
+        assert_eq!(book.best_ask(), Some((px(0.51), qty(5.0))));
+
+        let asks = book.ask_ladder();
+        assert_eq!(asks.levels().len(), 4);
+        // 2.55 + 10.4 + 16.5: the 0.60 level is the fourth.
+        assert!((asks.depth_usdc(3) - 29.45).abs() < 1e-9);
+        assert!((asks.depth_usdc(10) - 53.45).abs() < 1e-9);
+        assert_eq!(asks.qty_at_or_below(px(0.55)), qty(55.0));
+        assert!((asks.usdc_at_or_below(px(0.52)) - 12.95).abs() < 1e-9);
+        // 5 @ 0.51 + 15 @ 0.52.
+        assert!((asks.vwap_for_qty(qty(20.0)).unwrap() - 0.5175).abs() < 1e-9);
+        assert_eq!(asks.vwap_for_qty(qty(1_000.0)), None);
+        assert!(AskLadder::default().is_empty());
+    }
+}
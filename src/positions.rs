@@ -0,0 +1,139 @@
+//! Net inventory per token, updated from the sniper's `FillReport`s (SIM or live) and merges.
+//! Exposure is the cost basis of what is still held: buys add their notional, sells and merges
+//! release it pro rata. [`PositionBook`] is a cheap clone over shared state so other tasks can
+//! read exposure while the sniper writes it.
+
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+
+use crate::types::{FillReport, MarketId, Qty, Side, TokenId};
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Position {
+    pub market_id: MarketId,
+    pub qty: Qty,
+    /// USDC paid for `qty`.
+    pub cost_usdc: f64,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct PositionBook(Arc<Mutex<HashMap<TokenId, Position>>>);
+
+impl PositionBook {
+    fn with<T>(&self, f: impl FnOnce(&mut HashMap<TokenId, Position>) -> T) -> T {
+        let mut guard = self.0.lock().unwrap_or_else(|e| e.into_inner());
+        f(&mut guard)
+    }
+
+    /// Books the filled part of `fill`.
+    pub fn apply_fill(
+        &self,
+        market_id: &MarketId,
+        token_id: &TokenId,
+        side: Side,
+        fill: &FillReport,
+    ) {
+        if !fill.filled_qty.is_positive() {
+            return;
+        }
+        match side {
+            Side::Buy => self.with(|book| {
+                let pos = book.entry(token_id.clone()).or_insert_with(|| Position {
+                    market_id: market_id.clone(),
+                    qty: Qty::ZERO,
+                    cost_usdc: 0.0,
+                });
+                pos.qty = pos.qty.saturating_add(fill.filled_qty);
+                pos.cost_usdc += fill.avg_price.notional(fill.filled_qty);
+            }),
+            Side::Sell => self.release(token_id, fill.filled_qty),
+        }
+    }
+
+    /// `sets` complete sets of `token_ids` merged back into collateral.
+    pub fn merge(&self, token_ids: &[&TokenId], sets: Qty) {
+        for token_id in token_ids {
+            self.release(token_id, sets);
+        }
+    }
+
+    fn release(&self, token_id: &TokenId, qty: Qty) {
+        self.with(|book| {
+            let Some(pos) = book.get_mut(token_id) else {
+                return;
+            };
+            let out = qty.min(pos.qty);
+            if out >= pos.qty {
+                book.remove(token_id);
+                return;
+            }
+            pos.cost_usdc -= pos.cost_usdc * out.to_f64() / pos.qty.to_f64();
+            pos.qty = pos.qty.saturating_sub(out);
+        });
+    }
+
+    #[allow(dead_code)]
+    pub fn position(&self, token_id: &TokenId) -> Option<Position> {
+        self.with(|book| book.get(token_id).cloned())
+    }
+
+    #[allow(dead_code)]
+    pub fn token_exposure_usdc(&self, token_id: &TokenId) -> f64 {
+        self.position(token_id).map_or(0.0, |p| p.cost_usdc)
+    }
+
+    pub fn market_exposure_usdc(&self, market_id: &MarketId) -> f64 {
+        self.with(|book| {
+            book.values()
+                .filter(|p| &p.market_id == market_id)
+                .map(|p| p.cost_usdc)
+                .sum()
+        })
+    }
+
+    pub fn total_exposure_usdc(&self) -> f64 {
+        self.with(|book| book.values().map(|p| p.cost_usdc).sum())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::{FillStatus, Price};
+
+    fn fill(qty: f64, px: f64) -> FillReport {
+        FillReport {
+            requested_qty: Qty::from_f64(qty).unwrap(),
+            filled_qty: Qty::from_f64(qty).unwrap(),
+            avg_price: Price::from_f64(px).unwrap(),
+            status: FillStatus::Full,
+            order_id: "o".to_string(),
+            latency_ms: 0,
+        }
+    }
+
+    #[test]
+    fn buys_add_cost_and_sells_and_merges_release_it_pro_rata() {
+        let book = PositionBook::default();
+        let (m, m2): (MarketId, MarketId) = ("m1".into(), "m2".into());
+        let (a, b, c): (TokenId, TokenId, TokenId) = ("a".into(), "b".into(), "c".into());
+        book.apply_fill(&m, &a, Side::Buy, &fill(10.0, 0.40));
+        book.apply_fill(&m, &b, Side::Buy, &fill(10.0, 0.55));
+        book.apply_fill(&m2, &c, Side::Buy, &fill(4.0, 0.50));
+        assert!((book.market_exposure_usdc(&m) - 9.5).abs() < 1e-9);
+        assert!((book.total_exposure_usdc() - 11.5).abs() < 1e-9);
+
+        // Selling 2.5 of 10 releases a quarter of the cost, whatever the sale price.
+        book.apply_fill(&m, &a, Side::Sell, &fill(2.5, 0.10));
+        assert!((book.token_exposure_usdc(&a) - 3.0).abs() < 1e-9);
+        assert_eq!(book.position(&a).unwrap().qty, Qty::from_f64(7.5).unwrap());
+
+        book.merge(&[&a, &b], Qty::from_f64(7.5).unwrap());
+        assert_eq!(book.position(&a), None);
+        assert!((book.token_exposure_usdc(&b) - 1.375).abs() < 1e-9);
+
+        // Overselling just closes the position.
+        book.apply_fill(&m2, &c, Side::Sell, &fill(9.0, 0.5));
+        assert_eq!(book.market_exposure_usdc(&m2), 0.0);
+    }
+}
@@ -0,0 +1,502 @@
+//! `razor preflight`: a short dress rehearsal of a run — config, market resolution, a brief WS
+//! subscription, one trades page per market, disk space, clock skew and secrets — printed as a
+//! pass/fail checklist, so dead tokens and auth/network problems surface before a multi-day run.
+
+use std::collections::BTreeSet;
+use std::time::{Duration, Instant};
+
+use anyhow::Context as _;
+use futures_util::{SinkExt as _, StreamExt as _};
+use tokio_tungstenite::tungstenite::Message;
+
+use crate::config::{self, Config, ConfigSource};
+use crate::feed::{self, DataApiTrade};
+use crate::secrets;
+use crate::types::{now_ms, parse_http_date_secs, MarketDef};
+use crate::units::fmt_ms;
+use crate::ws_msg::{self, WsEvent};
+
+const MIB: u64 = 1 << 20;
+/// Clock skew above which the checklist warns / fails (wall-clock timestamps in every CSV).
+const SKEW_WARN_MS: u64 = 2_000;
+const SKEW_FAIL_MS: u64 = 10_000;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Status {
+    Pass,
+    Warn,
+    Fail,
+}
+
+impl Status {
+    fn as_str(self) -> &'static str {
+        match self {
+            Status::Pass => "PASS",
+            Status::Warn => "WARN",
+            Status::Fail => "FAIL",
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Check {
+    pub name: &'static str,
+    pub status: Status,
+    pub detail: String,
+}
+
+impl Check {
+    fn new(name: &'static str, status: Status, detail: impl Into<String>) -> Self {
+        Check {
+            name,
+            status,
+            detail: detail.into(),
+        }
+    }
+}
+
+impl std::fmt::Display for Check {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "  [{}] {:<8} {}",
+            self.status.as_str(),
+            self.name,
+            self.detail
+        )
+    }
+}
+
+/// Runs every check, prints the checklist and fails when any check failed.
+pub async fn run_preflight(source: ConfigSource, ws_wait: Duration) -> anyhow::Result<()> {
+    let checks = match load_config(&source) {
+        Ok(cfg) => run_checks(&cfg, ws_wait).await,
+        Err(e) => vec![Check::new("config", Status::Fail, format!("{e:#}"))],
+    };
+    println!("preflight: {}", source.describe());
+    for c in &checks {
+        println!("{c}");
+    }
+    let (status, warnings) = verdict(&checks);
+    let line = format!("preflight: {} ({warnings} warning(s))", status.as_str());
+    if status == Status::Fail {
+        anyhow::bail!(line);
+    }
+    println!("{line}");
+    Ok(())
+}
+
+fn load_config(source: &ConfigSource) -> anyhow::Result<Config> {
+    let raw = source.read()?;
+    let (cfg, _, _) = config::parse_with_env_overrides(&raw).context("parse config")?;
+    cfg.validate().context("validate config")?;
+    secrets::init(&cfg.secrets).context("load secrets keystore")?;
+    Ok(cfg)
+}
+
+async fn run_checks(cfg: &Config, ws_wait: Duration) -> Vec<Check> {
+    let mut checks = vec![Check::new(
+        "config",
+        Status::Pass,
+        format!("{} market id(s)", cfg.run.market_ids.len()),
+    )];
+    let markets = match feed::fetch_markets(cfg).await {
+        Ok(markets) => {
+            let status = if markets.len() < cfg.run.market_ids.len() {
+                Status::Warn
+            } else {
+                Status::Pass
+            };
+            checks.push(Check::new(
+                "markets",
+                status,
+                format!(
+                    "{}/{} resolved via gamma",
+                    markets.len(),
+                    cfg.run.market_ids.len()
+                ),
+            ));
+            markets
+        }
+        Err(e) => {
+            checks.push(Check::new("markets", Status::Fail, format!("{e:#}")));
+            Vec::new()
+        }
+    };
+    if !markets.is_empty() {
+        checks.push(check_ws(cfg, &markets, ws_wait).await);
+        checks.push(check_trades(cfg, &markets).await);
+    }
+    checks.push(check_disk(cfg));
+    checks.push(check_clock(cfg).await);
+    checks.push(check_secrets(cfg));
+    checks
+}
+
+/// Worst status across the checklist and the number of warnings.
+pub fn verdict(checks: &[Check]) -> (Status, usize) {
+    let worst = checks
+        .iter()
+        .map(|c| c.status)
+        .max()
+        .unwrap_or(Status::Pass);
+    let warnings = checks.iter().filter(|c| c.status == Status::Warn).count();
+    (worst, warnings)
+}
+
+/// Subscribes to every token for `wait` and reports the ones that sent no book/price_change.
+async fn check_ws(cfg: &Config, markets: &[MarketDef], wait: Duration) -> Check {
+    let url = format!("{}/ws/market", cfg.polymarket.ws_base.trim_end_matches('/'));
+    let tokens: Vec<&str> = markets
+        .iter()
+        .flat_map(|m| m.token_ids.iter().map(|t| t.as_str()))
+        .collect();
+    let connect = tokio::time::timeout(
+        Duration::from_millis(cfg.polymarket.ws_connect_timeout_ms),
+        tokio_tungstenite::connect_async(url.as_str()),
+    )
+    .await;
+    let mut ws = match connect {
+        Ok(Ok((ws, _))) => ws,
+        Ok(Err(e)) => return Check::new("ws", Status::Fail, format!("connect {url}: {e}")),
+        Err(_) => {
+            return Check::new(
+                "ws",
+                Status::Fail,
+                format!(
+                    "connect {url}: timed out after {}",
+                    fmt_ms(cfg.polymarket.ws_connect_timeout_ms)
+                ),
+            )
+        }
+    };
+    let subscribe = serde_json::json!({ "assets_ids": tokens, "type": "market" });
+    if let Err(e) = ws.send(Message::Text(subscribe.to_string().into())).await {
+        return Check::new("ws", Status::Fail, format!("send subscribe: {e}"));
+    }
+
+    let mut seen: BTreeSet<String> = BTreeSet::new();
+    let mut events = 0u64;
+    let deadline = tokio::time::Instant::now() + wait;
+    while seen.len() < tokens.len() {
+        let msg = match tokio::time::timeout_at(deadline, ws.next()).await {
+            Err(_) | Ok(None) => break,
+            Ok(Some(Err(e))) => {
+                return Check::new("ws", Status::Fail, format!("read: {e}"));
+            }
+            Ok(Some(Ok(msg))) => msg,
+        };
+        let Message::Text(txt) = msg else {
+            continue;
+        };
+        let _ = ws_msg::for_each_event(&txt, |ev| {
+            match ev {
+                WsEvent::Book(book) => {
+                    seen.insert(book.asset_id.into_owned());
+                }
+                WsEvent::PriceChange(pc) => {
+                    for ch in pc.price_changes {
+                        seen.insert(ch.asset_id.into_owned());
+                    }
+                }
+                WsEvent::LastTrade(_) | WsEvent::Other => return Ok::<_, ()>(()),
+            }
+            events += 1;
+            Ok(())
+        });
+    }
+    let _ = ws.close(None).await;
+
+    let silent: Vec<&str> = tokens
+        .iter()
+        .copied()
+        .filter(|t| !seen.contains(*t))
+        .collect();
+    let window = fmt_ms(wait.as_millis() as u64);
+    if events == 0 {
+        Check::new(
+            "ws",
+            Status::Fail,
+            format!("no book events within {window}"),
+        )
+    } else if !silent.is_empty() {
+        Check::new(
+            "ws",
+            Status::Warn,
+            format!(
+                "{}/{} token(s) silent for {window} (dead or delisted?): {}",
+                silent.len(),
+                tokens.len(),
+                silent.join(", ")
+            ),
+        )
+    } else {
+        Check::new(
+            "ws",
+            Status::Pass,
+            format!("{events} event(s), all {} token(s) live", tokens.len()),
+        )
+    }
+}
+
+/// One data-api trades page per market, with the run's own poll parameters.
+async fn check_trades(cfg: &Config, markets: &[MarketDef]) -> Check {
+    let client = match http_client(cfg) {
+        Ok(c) => c,
+        Err(e) => return Check::new("trades", Status::Fail, format!("{e:#}")),
+    };
+    let url = format!(
+        "{}/trades",
+        cfg.polymarket.data_api_base.trim_end_matches('/')
+    );
+    let mut empty = Vec::new();
+    let mut total = 0usize;
+    for m in markets {
+        let resp = client
+            .get(&url)
+            .query(&[
+                ("limit", cfg.shadow.trade_poll_limit.to_string()),
+                ("takerOnly", cfg.shadow.trade_poll_taker_only.to_string()),
+                ("market", m.market_id.to_string()),
+            ])
+            .send()
+            .await
+            .and_then(|r| r.error_for_status());
+        let list: Vec<DataApiTrade> = match resp {
+            Ok(r) => match r.json().await {
+                Ok(list) => list,
+                Err(e) => {
+                    let detail = format!("{}: decode: {e}", m.market_id);
+                    return Check::new("trades", Status::Fail, detail);
+                }
+            },
+            Err(e) => return Check::new("trades", Status::Fail, format!("{}: {e}", m.market_id)),
+        };
+        if list.is_empty() {
+            empty.push(m.market_id.to_string());
+        }
+        total += list.len();
+    }
+    if empty.is_empty() {
+        Check::new(
+            "trades",
+            Status::Pass,
+            format!("{total} trade(s) across {} market(s)", markets.len()),
+        )
+    } else {
+        Check::new(
+            "trades",
+            Status::Warn,
+            format!("no recent trades for {}", empty.join(", ")),
+        )
+    }
+}
+
+fn check_disk(cfg: &Config) -> Check {
+    let dir = &cfg.run.data_dir;
+    let probe = dir.join(".razor_preflight");
+    let writable = std::fs::create_dir_all(dir)
+        .and_then(|_| std::fs::write(&probe, b"ok"))
+        .and_then(|_| std::fs::remove_file(&probe));
+    if let Err(e) = writable {
+        let detail = format!("{} not writable: {e}", dir.display());
+        return Check::new("disk", Status::Fail, detail);
+    }
+    match razor::fs_compat::available_bytes(dir) {
+        Some(bytes) => {
+            let (status, detail) = classify_free(cfg, bytes / MIB);
+            Check::new("disk", status, format!("{}: {detail}", dir.display()))
+        }
+        None => Check::new(
+            "disk",
+            Status::Warn,
+            format!("{}: free space unknown on this platform", dir.display()),
+        ),
+    }
+}
+
+/// Free MiB against the disk guard's thresholds: below `shutdown_free_mb` the run would stop at
+/// once, below `raw_ws_off_free_mb` it would start without raw_ws.jsonl.
+pub fn classify_free(cfg: &Config, free_mb: u64) -> (Status, String) {
+    let d = &cfg.disk;
+    if free_mb < d.shutdown_free_mb {
+        let detail = format!(
+            "{free_mb} MiB free < disk.shutdown_free_mb ({})",
+            d.shutdown_free_mb
+        );
+        (Status::Fail, detail)
+    } else if free_mb < d.raw_ws_off_free_mb {
+        let detail = format!(
+            "{free_mb} MiB free < disk.raw_ws_off_free_mb ({})",
+            d.raw_ws_off_free_mb
+        );
+        (Status::Warn, detail)
+    } else {
+        (Status::Pass, format!("{free_mb} MiB free"))
+    }
+}
+
+/// Local clock against gamma's HTTP `Date` header, corrected by half the round trip.
+async fn check_clock(cfg: &Config) -> Check {
+    let client = match http_client(cfg) {
+        Ok(c) => c,
+        Err(e) => return Check::new("clock", Status::Fail, format!("{e:#}")),
+    };
+    let url = format!(
+        "{}/markets",
+        cfg.polymarket.gamma_base.trim_end_matches('/')
+    );
+    let sent_ms = now_ms();
+    let started = Instant::now();
+    let resp = match client.get(&url).query(&[("limit", "1")]).send().await {
+        Ok(r) => r,
+        Err(e) => return Check::new("clock", Status::Fail, format!("{url}: {e}")),
+    };
+    let rtt_ms = started.elapsed().as_millis() as u64;
+    let server_secs = resp
+        .headers()
+        .get(reqwest::header::DATE)
+        .and_then(|v| v.to_str().ok())
+        .and_then(parse_http_date_secs);
+    let Some(server_secs) = server_secs else {
+        return Check::new(
+            "clock",
+            Status::Warn,
+            "gamma sent no parseable Date header; skew not checked",
+        );
+    };
+    // `Date` has 1s resolution: compare against the middle of its second.
+    let server_ms = server_secs * 1000 + 500;
+    let local_ms = sent_ms + rtt_ms / 2;
+    let skew_ms = local_ms as i64 - server_ms as i64;
+    let (status, detail) = classify_skew(skew_ms, rtt_ms);
+    Check::new("clock", status, detail)
+}
+
+/// `skew_ms` is local minus server; the `Date` header's 1s resolution is within the warn margin.
+pub fn classify_skew(skew_ms: i64, rtt_ms: u64) -> (Status, String) {
+    let abs = skew_ms.unsigned_abs();
+    let status = if abs > SKEW_FAIL_MS {
+        Status::Fail
+    } else if abs > SKEW_WARN_MS {
+        Status::Warn
+    } else {
+        Status::Pass
+    };
+    let sign = if skew_ms < 0 { "-" } else { "+" };
+    let detail = format!(
+        "local {sign}{} vs gamma (rtt {}){}",
+        fmt_ms(abs),
+        fmt_ms(rtt_ms),
+        if status == Status::Pass {
+            ""
+        } else {
+            "; sync the clock (NTP) before recording"
+        }
+    );
+    (status, detail)
+}
+
+/// Every enabled integration's `*_env` secret must resolve now, not hours into the run.
+fn check_secrets(cfg: &Config) -> Check {
+    let mut needed: Vec<(&str, &str)> = Vec::new();
+    if cfg.live.enabled {
+        if !cfg!(feature = "live") {
+            return Check::new(
+                "secrets",
+                Status::Fail,
+                "live.enabled = true but this binary was built without the `live` feature",
+            );
+        }
+        needed.push(("live.private_key_env", &cfg.live.private_key_env));
+    }
+    if cfg.email.enabled {
+        needed.push(("email.password_env", &cfg.email.password_env));
+    }
+    if cfg.escalation.enabled {
+        needed.push((
+            "escalation.routing_key_env",
+            &cfg.escalation.routing_key_env,
+        ));
+    }
+    if cfg.upload.enabled {
+        needed.push(("upload.access_key_env", &cfg.upload.access_key_env));
+        needed.push(("upload.secret_key_env", &cfg.upload.secret_key_env));
+    }
+    let missing: Vec<String> = needed
+        .iter()
+        .filter(|(_, var)| secrets::get(var).is_none())
+        .map(|(key, var)| format!("{key} ({})", var.trim()))
+        .collect();
+    if !missing.is_empty() {
+        Check::new(
+            "secrets",
+            Status::Fail,
+            format!("not set in env or keystore: {}", missing.join(", ")),
+        )
+    } else if needed.is_empty() {
+        Check::new("secrets", Status::Pass, "no integration needs one")
+    } else {
+        Check::new(
+            "secrets",
+            Status::Pass,
+            format!("{} secret(s) resolved", needed.len()),
+        )
+    }
+}
+
+fn http_client(cfg: &Config) -> anyhow::Result<reqwest::Client> {
+    reqwest::Client::builder()
+        .user_agent(concat!("razor/", env!("CARGO_PKG_VERSION")))
+        .connect_timeout(Duration::from_millis(
+            cfg.polymarket.http_connect_timeout_ms,
+        ))
+        .timeout(Duration::from_millis(cfg.polymarket.http_timeout_ms))
+        .build()
+        .context("build http client")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn check(status: Status) -> Check {
+        Check::new("x", status, "")
+    }
+
+    #[test]
+    fn verdict_is_the_worst_check() {
+        assert_eq!(verdict(&[]), (Status::Pass, 0));
+        assert_eq!(
+            verdict(&[
+                check(Status::Pass),
+                check(Status::Warn),
+                check(Status::Warn)
+            ]),
+            (Status::Warn, 2)
+        );
+        assert_eq!(
+            verdict(&[check(Status::Fail), check(Status::Warn)]),
+            (Status::Fail, 1)
+        );
+    }
+
+    #[test]
+    fn skew_thresholds() {
+        assert_eq!(classify_skew(1_500, 80).0, Status::Pass);
+        assert_eq!(classify_skew(-3_000, 80).0, Status::Warn);
+        let (status, detail) = classify_skew(-12_000, 80);
+        assert_eq!(status, Status::Fail);
+        assert!(detail.starts_with("local -12s"), "{detail}");
+    }
+
+    #[test]
+    fn free_space_against_disk_guard_thresholds() {
+        let mut cfg: Config = toml::from_str("[run]\nmarket_ids = [\"1\"]\n").unwrap();
+        cfg.disk.shutdown_free_mb = 100;
+        cfg.disk.raw_ws_off_free_mb = 1_000;
+        assert_eq!(classify_free(&cfg, 50).0, Status::Fail);
+        assert_eq!(classify_free(&cfg, 500).0, Status::Warn);
+        assert_eq!(classify_free(&cfg, 5_000).0, Status::Pass);
+    }
+}
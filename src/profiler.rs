@@ -0,0 +1,177 @@
+use std::path::PathBuf;
+use std::time::{Duration, Instant};
+
+use serde::Serialize;
+use tokio::sync::watch;
+
+use crate::recorder::JsonlAppender;
+use crate::types::now_ms;
+
+/// One line of `profile.jsonl`. OS-level fields are `None` where the platform can't provide them
+/// (everything but Linux for CPU/RSS, non-glibc for allocator stats).
+#[derive(Debug, Clone, Serialize)]
+pub struct ProfileLine {
+    pub ts_ms: u64,
+    /// Process CPU time over the last interval, in percent of one core.
+    pub cpu_pct: Option<f64>,
+    pub rss_bytes: Option<u64>,
+    pub threads: Option<u64>,
+    pub tokio_workers: usize,
+    pub tokio_alive_tasks: usize,
+    pub tokio_global_queue_depth: usize,
+    pub alloc: Option<AllocStats>,
+}
+
+/// glibc `mallinfo2` summary.
+#[derive(Debug, Clone, Serialize)]
+pub struct AllocStats {
+    pub in_use_bytes: u64,
+    pub free_bytes: u64,
+    pub mmap_bytes: u64,
+    pub arena_bytes: u64,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+struct ProcStat {
+    cpu_ticks: u64,
+    threads: u64,
+    rss_pages: u64,
+}
+
+/// Parses `/proc/self/stat`. The command name (field 2) may contain spaces, so fields are
+/// counted from the closing parenthesis.
+#[cfg_attr(not(target_os = "linux"), allow(dead_code))]
+fn parse_proc_stat(raw: &str) -> Option<ProcStat> {
+    let rest = &raw[raw.rfind(')')? + 1..];
+    let f: Vec<&str> = rest.split_whitespace().collect();
+    // After ")": state is field 3, so field N lives at index N - 3.
+    let utime: u64 = f.get(14 - 3)?.parse().ok()?;
+    let stime: u64 = f.get(15 - 3)?.parse().ok()?;
+    let threads: u64 = f.get(20 - 3)?.parse().ok()?;
+    let rss_pages: u64 = f.get(24 - 3)?.parse().ok()?;
+    Some(ProcStat {
+        cpu_ticks: utime + stime,
+        threads,
+        rss_pages,
+    })
+}
+
+#[cfg(target_os = "linux")]
+fn read_proc_stat() -> Option<ProcStat> {
+    parse_proc_stat(&std::fs::read_to_string("/proc/self/stat").ok()?)
+}
+
+#[cfg(not(target_os = "linux"))]
+fn read_proc_stat() -> Option<ProcStat> {
+    None
+}
+
+#[cfg(target_os = "linux")]
+fn clock_ticks_and_page_size() -> (u64, u64) {
+    // SAFETY: sysconf has no preconditions.
+    let (tck, page) = unsafe {
+        (
+            libc::sysconf(libc::_SC_CLK_TCK),
+            libc::sysconf(libc::_SC_PAGESIZE),
+        )
+    };
+    (tck.max(1) as u64, page.max(1) as u64)
+}
+
+#[cfg(not(target_os = "linux"))]
+fn clock_ticks_and_page_size() -> (u64, u64) {
+    (100, 4096)
+}
+
+#[cfg(all(target_os = "linux", target_env = "gnu"))]
+fn alloc_stats() -> Option<AllocStats> {
+    // SAFETY: mallinfo2 only reads allocator bookkeeping and returns it by value.
+    let mi = unsafe { libc::mallinfo2() };
+    Some(AllocStats {
+        in_use_bytes: mi.uordblks as u64,
+        free_bytes: mi.fordblks as u64,
+        mmap_bytes: mi.hblkhd as u64,
+        arena_bytes: mi.arena as u64,
+    })
+}
+
+#[cfg(not(all(target_os = "linux", target_env = "gnu")))]
+fn alloc_stats() -> Option<AllocStats> {
+    None
+}
+
+/// Samples process and runtime stats every `interval_ms` into `profile.jsonl`, so resource use
+/// can be compared between releases from run artifacts alone.
+pub async fn run_profiler(
+    path: PathBuf,
+    interval_ms: u64,
+    mut shutdown: watch::Receiver<bool>,
+) -> anyhow::Result<()> {
+    let mut out = JsonlAppender::open(&path)?;
+    let (clk_tck, page_size) = clock_ticks_and_page_size();
+    let rt = tokio::runtime::Handle::current().metrics();
+
+    let mut interval = tokio::time::interval(Duration::from_millis(interval_ms));
+    interval.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+    let mut prev: Option<(Instant, ProcStat)> = None;
+
+    loop {
+        tokio::select! {
+            _ = shutdown.changed() => {
+                if *shutdown.borrow() { break; }
+            }
+            _ = interval.tick() => {}
+        }
+        if *shutdown.borrow() {
+            break;
+        }
+
+        let now = Instant::now();
+        let stat = read_proc_stat();
+        let cpu_pct = match (prev, stat) {
+            (Some((t0, s0)), Some(s1)) => {
+                let wall = now.duration_since(t0).as_secs_f64();
+                let cpu = s1.cpu_ticks.saturating_sub(s0.cpu_ticks) as f64 / clk_tck as f64;
+                (wall > 0.0).then(|| cpu / wall * 100.0)
+            }
+            _ => None,
+        };
+        prev = stat.map(|s| (now, s));
+
+        let line = ProfileLine {
+            ts_ms: now_ms(),
+            cpu_pct,
+            rss_bytes: stat.map(|s| s.rss_pages * page_size),
+            threads: stat.map(|s| s.threads),
+            tokio_workers: rt.num_workers(),
+            tokio_alive_tasks: rt.num_alive_tasks(),
+            tokio_global_queue_depth: rt.global_queue_depth(),
+            alloc: alloc_stats(),
+        };
+        out.write_line(&serde_json::to_string(&line)?)?;
+        out.flush_and_sync()?;
+    }
+
+    out.flush_and_sync()?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_proc_stat_with_spaces_in_comm() {
+        let raw = "1234 (razor (main)) S 1 1234 1234 0 -1 4194560 100 0 0 0 \
+                   250 50 0 0 20 0 7 0 12345 104857600 2560 18446744073709551615";
+        let s = parse_proc_stat(raw).unwrap();
+        assert_eq!(
+            s,
+            ProcStat {
+                cpu_ticks: 300,
+                threads: 7,
+                rss_pages: 2560,
+            }
+        );
+    }
+}
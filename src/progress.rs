@@ -0,0 +1,156 @@
+use std::path::PathBuf;
+use std::time::Duration;
+
+use serde::Serialize;
+use tokio::sync::watch;
+use tracing::{info, warn};
+
+use crate::recorder::JsonlAppender;
+use crate::report::{self, ReportThresholds};
+use crate::schema::{FILE_PROGRESS_JSONL, FILE_SHADOW_LOG};
+use crate::types::now_ms;
+
+const DAY_MS: u64 = 24 * 60 * 60 * 1000;
+
+/// One line of `progress.jsonl`: cumulative shadow results so far plus a linear extrapolation to
+/// the Day-N target, checked against the same thresholds the final report uses.
+#[derive(Debug, Clone, Serialize)]
+pub struct ProgressLine {
+    pub ts_ms: u64,
+    pub run_id: String,
+    pub elapsed_ms: u64,
+    pub target_days: u64,
+    pub signals: u64,
+    pub total_shadow_pnl: f64,
+    pub avg_set_ratio: f64,
+    pub projected_signals: u64,
+    pub projected_total_shadow_pnl: f64,
+    pub min_total_shadow_pnl: f64,
+    pub min_avg_set_ratio: f64,
+    /// Verdict on the data so far (same rules as report.json).
+    pub go_so_far: bool,
+    /// Whether the Day-N projection clears the PnL threshold and the set ratio holds.
+    pub on_track: bool,
+    pub reasons: Vec<String>,
+    pub eta_target_ms: u64,
+}
+
+pub fn project(
+    run_id: &str,
+    start_ts_ms: u64,
+    now: u64,
+    target_days: u64,
+    thresholds: ReportThresholds,
+    r: &report::Report,
+) -> ProgressLine {
+    let elapsed_ms = now.saturating_sub(start_ts_ms).max(1);
+    let target_ms = target_days.saturating_mul(DAY_MS);
+    let scale = target_ms as f64 / elapsed_ms as f64;
+
+    let projected_signals = (r.totals.signals as f64 * scale).round() as u64;
+    let projected_total_shadow_pnl = r.totals.total_shadow_pnl * scale;
+    let on_track = projected_total_shadow_pnl > thresholds.min_total_shadow_pnl
+        && (r.totals.signals == 0 || r.totals.avg_set_ratio >= thresholds.min_avg_set_ratio);
+
+    ProgressLine {
+        ts_ms: now,
+        run_id: run_id.to_string(),
+        elapsed_ms,
+        target_days,
+        signals: r.totals.signals,
+        total_shadow_pnl: r.totals.total_shadow_pnl,
+        avg_set_ratio: r.totals.avg_set_ratio,
+        projected_signals,
+        projected_total_shadow_pnl,
+        min_total_shadow_pnl: thresholds.min_total_shadow_pnl,
+        min_avg_set_ratio: thresholds.min_avg_set_ratio,
+        go_so_far: r.verdict.go,
+        on_track,
+        reasons: r.verdict.reasons.clone(),
+        eta_target_ms: target_ms.saturating_sub(elapsed_ms),
+    }
+}
+
+/// Every `interval_ms`, recomputes the report over the live `shadow_log.csv`, logs a summary and
+/// appends it to `progress.jsonl` so operators can decide mid-run whether to restart/repick.
+pub async fn run_progress_summarizer(
+    run_dir: PathBuf,
+    run_id: String,
+    start_ts_ms: u64,
+    interval_ms: u64,
+    target_days: u64,
+    thresholds: ReportThresholds,
+    mut shutdown: watch::Receiver<bool>,
+) -> anyhow::Result<()> {
+    let mut out = JsonlAppender::open(run_dir.join(FILE_PROGRESS_JSONL))?;
+    let mut interval = tokio::time::interval(Duration::from_millis(interval_ms));
+    interval.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+    // The first tick fires immediately; there's nothing to summarize yet.
+    interval.tick().await;
+
+    loop {
+        tokio::select! {
+            _ = shutdown.changed() => {
+                if *shutdown.borrow() { break; }
+            }
+            _ = interval.tick() => {}
+        }
+        if *shutdown.borrow() {
+            break;
+        }
+
+        let shadow_path = run_dir.join(FILE_SHADOW_LOG);
+        let id = run_id.clone();
+        let computed = tokio::task::spawn_blocking(move || {
+            report::compute_report(&shadow_path, &id, thresholds)
+        })
+        .await?;
+        let r = match computed {
+            Ok(r) => r,
+            Err(e) => {
+                warn!(error = %e, "progress summary: compute report failed");
+                continue;
+            }
+        };
+
+        let line = project(&run_id, start_ts_ms, now_ms(), target_days, thresholds, &r);
+        info!(
+            elapsed_ms = line.elapsed_ms,
+            signals = line.signals,
+            total_shadow_pnl = line.total_shadow_pnl,
+            avg_set_ratio = line.avg_set_ratio,
+            target_days = line.target_days,
+            projected_total_shadow_pnl = line.projected_total_shadow_pnl,
+            go_so_far = line.go_so_far,
+            on_track = line.on_track,
+            "progress"
+        );
+        out.write_line(&serde_json::to_string(&line)?)?;
+        out.flush_and_sync()?;
+    }
+
+    out.flush_and_sync()?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn projection_scales_linearly_to_target() {
+        let dir = std::env::temp_dir().join(format!("razor_progress_{}", now_ms()));
+        let r = report::compute_report(&dir.join("missing.csv"), "r", ReportThresholds::default())
+            .unwrap();
+        let mut r = r;
+        r.totals.signals = 10;
+        r.totals.total_shadow_pnl = 0.5;
+        r.totals.avg_set_ratio = 0.9;
+
+        let line = project("r", 0, DAY_MS, 14, ReportThresholds::default(), &r);
+        assert_eq!(line.projected_signals, 140);
+        assert!((line.projected_total_shadow_pnl - 7.0).abs() < 1e-9);
+        assert!(line.on_track);
+        assert_eq!(line.eta_target_ms, 13 * DAY_MS);
+    }
+}
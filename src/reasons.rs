@@ -22,6 +22,8 @@ pub enum ShadowNoteReason {
     InternalError,
     InvalidPrice,
     InvalidQty,
+    /// The trade channel dropped trades during the window (`[backpressure.trade]`).
+    TradeChannelDrop,
 }
 
 impl ShadowNoteReason {
@@ -43,8 +45,35 @@ impl ShadowNoteReason {
             ShadowNoteReason::InternalError => "INTERNAL_ERROR",
             ShadowNoteReason::InvalidPrice => "INVALID_PRICE",
             ShadowNoteReason::InvalidQty => "INVALID_QTY",
+            ShadowNoteReason::TradeChannelDrop => "TRADE_CHANNEL_DROP",
         }
     }
+
+    pub const ALL: [ShadowNoteReason; 17] = [
+        ShadowNoteReason::NoTrades,
+        ShadowNoteReason::WindowEmpty,
+        ShadowNoteReason::WindowDataGap,
+        ShadowNoteReason::TradeSizeSuspect,
+        ShadowNoteReason::MissingBid,
+        ShadowNoteReason::MissingBook,
+        ShadowNoteReason::BucketThinNan,
+        ShadowNoteReason::BucketLiquidNan,
+        ShadowNoteReason::DepthUnitSuspect,
+        ShadowNoteReason::FillShareP25Zero,
+        ShadowNoteReason::DedupHit,
+        ShadowNoteReason::SignalTooOld,
+        ShadowNoteReason::LegsMismatch,
+        ShadowNoteReason::InternalError,
+        ShadowNoteReason::InvalidPrice,
+        ShadowNoteReason::InvalidQty,
+        ShadowNoteReason::TradeChannelDrop,
+    ];
+
+    /// Inverse of [`ShadowNoteReason::as_str`].
+    pub fn parse(s: &str) -> Option<Self> {
+        let s = s.trim();
+        Self::ALL.into_iter().find(|r| r.as_str() == s)
+    }
 }
 
 impl fmt::Display for ShadowNoteReason {
@@ -62,7 +91,6 @@ pub fn format_notes(reasons: &[ShadowNoteReason]) -> String {
     uniq.into_iter().collect::<Vec<_>>().join(",")
 }
 
-#[allow(dead_code)]
 pub fn parse_notes_reasons(notes: &str) -> Vec<String> {
     notes
         .split(',')
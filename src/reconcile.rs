@@ -0,0 +1,496 @@
+//! On-chain reconciliation (`[reconcile]`, live_sim only): periodically reads the wallet's
+//! conditional-token balances for the run's legs over Polygon JSON-RPC and compares how far they
+//! moved since the first read with the net `LIVE` fills in `trade_log.csv`. A mismatch that
+//! survives two passes is a break; each pass rewrites `reconciliation.json`.
+//!
+//! Strictly read-only: `eth_call` of `balanceOf` against a public address, no key is loaded.
+//! Paper (`SIM`) runs have no `LIVE` fills, so the check degrades to "the wallet did not move".
+
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::Duration;
+
+use anyhow::Context as _;
+use serde::Serialize;
+use tokio::sync::watch;
+use tracing::{info, warn};
+
+use crate::api_usage::{self, ApiEndpoint};
+use crate::config::{Config, ReconcileConfig};
+use crate::health::HealthCounters;
+use crate::rows::TradeLogRow;
+use crate::schema::{FILE_RECONCILIATION_JSON, FILE_TRADE_LOG, TRADE_LOG_HEADER};
+use crate::schema_adapt::{FrozenCsvReader, SchemaMode};
+use crate::types::{now_ms, MarketDef};
+
+/// `balanceOf(address,uint256)` (ERC-1155).
+const SELECTOR_BALANCE_OF_1155: &str = "00fdd58e";
+/// `balanceOf(address)` (ERC-20).
+const SELECTOR_BALANCE_OF_20: &str = "70a08231";
+/// Conditional tokens and USDC.e both use 6 decimals.
+const TOKEN_DECIMALS: i32 = 6;
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ReconciliationReport {
+    pub version: String,
+    pub ts_ms: u64,
+    pub address: String,
+    pub passes: u64,
+    /// ms of the first successful balance read; on-chain deltas are relative to it.
+    pub baseline_ts_ms: Option<u64>,
+    pub last_ok_ts_ms: Option<u64>,
+    pub last_error: Option<String>,
+    pub collateral_baseline: Option<f64>,
+    pub collateral_now: Option<f64>,
+    pub tokens: Vec<TokenReconciliation>,
+    /// Tokens whose status is `break`.
+    pub breaks: u64,
+}
+
+#[derive(Debug, Clone, Serialize, PartialEq)]
+pub struct TokenReconciliation {
+    pub market_id: String,
+    pub token_id: String,
+    pub onchain_baseline: f64,
+    pub onchain_now: f64,
+    /// Net `LIVE` fills since the run started (BUY +, SELL -).
+    pub trade_log_live_net: f64,
+    /// Net `SIM` fills, informational: they never touch the wallet.
+    pub trade_log_sim_net: f64,
+    /// `(onchain_now - onchain_baseline) - trade_log_live_net`.
+    pub diff: f64,
+    /// `ok`, `pending` (first pass over tolerance) or `break`.
+    pub status: &'static str,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct NetFills {
+    pub live: f64,
+    pub sim: f64,
+}
+
+/// Net filled quantity per token from `trade_log.csv` rows.
+pub fn net_fills<'a>(
+    rows: impl IntoIterator<Item = &'a TradeLogRow>,
+) -> BTreeMap<String, NetFills> {
+    let mut out: BTreeMap<String, NetFills> = BTreeMap::new();
+    for row in rows {
+        if !row.fill_qty.is_finite() || row.fill_qty <= 0.0 {
+            continue;
+        }
+        let signed = match row.side.as_str() {
+            "BUY" => row.fill_qty,
+            "SELL" => -row.fill_qty,
+            _ => continue,
+        };
+        let e = out.entry(row.token_id.clone()).or_default();
+        if row.phase == "LIVE" {
+            e.live += signed;
+        } else {
+            e.sim += signed;
+        }
+    }
+    out
+}
+
+/// Rows written so far; a torn last line (writer mid-flush) is skipped, not an error.
+fn read_trade_log(path: &Path) -> anyhow::Result<Vec<TradeLogRow>> {
+    if !path.exists() {
+        return Ok(Vec::new());
+    }
+    let mut rdr = FrozenCsvReader::open(
+        path,
+        FILE_TRADE_LOG,
+        &TRADE_LOG_HEADER,
+        SchemaMode::from_env(),
+    )?;
+    Ok(rdr
+        .deserialize::<TradeLogRow>()
+        .filter_map(Result::ok)
+        .collect())
+}
+
+/// Per-token mismatch streaks across passes.
+#[derive(Debug, Default)]
+pub struct Reconciler {
+    mismatch_streak: BTreeMap<String, u32>,
+}
+
+impl Reconciler {
+    pub fn compare(
+        &mut self,
+        tolerance: f64,
+        market_id: &str,
+        token_id: &str,
+        baseline: f64,
+        now: f64,
+        fills: NetFills,
+    ) -> TokenReconciliation {
+        let diff = (now - baseline) - fills.live;
+        let streak = self
+            .mismatch_streak
+            .entry(token_id.to_string())
+            .or_default();
+        if diff.abs() > tolerance {
+            *streak += 1;
+        } else {
+            *streak = 0;
+        }
+        let status = match *streak {
+            0 => "ok",
+            1 => "pending",
+            _ => "break",
+        };
+        TokenReconciliation {
+            market_id: market_id.to_string(),
+            token_id: token_id.to_string(),
+            onchain_baseline: baseline,
+            onchain_now: now,
+            trade_log_live_net: fills.live,
+            trade_log_sim_net: fills.sim,
+            diff,
+            status,
+        }
+    }
+}
+
+/// Decimal token id (as on the CLOB) to a 32-byte big-endian `uint256`.
+pub fn token_id_word(token_id: &str) -> anyhow::Result<[u8; 32]> {
+    let s = token_id.trim();
+    anyhow::ensure!(
+        !s.is_empty() && s.bytes().all(|b| b.is_ascii_digit()),
+        "token id {token_id:?} is not a decimal integer"
+    );
+    let mut word = [0u8; 32];
+    for d in s.bytes().map(|b| (b - b'0') as u32) {
+        let mut carry = d;
+        for byte in word.iter_mut().rev() {
+            let v = (*byte as u32) * 10 + carry;
+            *byte = (v & 0xff) as u8;
+            carry = v >> 8;
+        }
+        anyhow::ensure!(carry == 0, "token id {token_id:?} overflows uint256");
+    }
+    Ok(word)
+}
+
+fn address_word(addr: &str) -> anyhow::Result<[u8; 32]> {
+    let raw = addr.trim().strip_prefix("0x").unwrap_or(addr.trim());
+    let bytes = hex::decode(raw).with_context(|| format!("address {addr:?}"))?;
+    anyhow::ensure!(bytes.len() == 20, "address {addr:?} is not 20 bytes");
+    let mut word = [0u8; 32];
+    word[12..].copy_from_slice(&bytes);
+    Ok(word)
+}
+
+/// `eth_call` result (`0x` + big-endian uint256) scaled to whole tokens.
+pub fn parse_balance(result: &str) -> anyhow::Result<f64> {
+    let raw = result.trim().strip_prefix("0x").unwrap_or(result.trim());
+    anyhow::ensure!(!raw.is_empty(), "empty eth_call result");
+    let mut v = 0f64;
+    for c in raw.chars() {
+        let d = c
+            .to_digit(16)
+            .with_context(|| format!("eth_call result {result:?} is not hex"))?;
+        v = v * 16.0 + d as f64;
+    }
+    Ok(v / 10f64.powi(TOKEN_DECIMALS))
+}
+
+struct RpcClient {
+    http: reqwest::Client,
+    url: String,
+    next_id: u64,
+}
+
+impl RpcClient {
+    async fn eth_call(&mut self, to: &str, data: String) -> anyhow::Result<f64> {
+        self.next_id += 1;
+        let body = serde_json::json!({
+            "jsonrpc": "2.0",
+            "id": self.next_id,
+            "method": "eth_call",
+            "params": [{ "to": to, "data": data }, "latest"],
+        });
+        let resp = api_usage::track(
+            ApiEndpoint::PolygonRpc,
+            self.http.post(&self.url).json(&body).send().await,
+        )
+        .context("polygon rpc request")?
+        .error_for_status()
+        .context("polygon rpc status")?;
+        let v: serde_json::Value = resp.json().await.context("polygon rpc body")?;
+        if let Some(err) = v.get("error") {
+            anyhow::bail!("polygon rpc error: {err}");
+        }
+        let result = v
+            .get("result")
+            .and_then(|r| r.as_str())
+            .context("polygon rpc: missing result")?;
+        parse_balance(result)
+    }
+
+    async fn balance_1155(&mut self, rc: &ReconcileConfig, token_id: &str) -> anyhow::Result<f64> {
+        let data = format!(
+            "0x{SELECTOR_BALANCE_OF_1155}{}{}",
+            hex::encode(address_word(&rc.address)?),
+            hex::encode(token_id_word(token_id)?)
+        );
+        self.eth_call(&rc.ctf_address, data).await
+    }
+
+    async fn balance_20(&mut self, rc: &ReconcileConfig) -> anyhow::Result<f64> {
+        let data = format!(
+            "0x{SELECTOR_BALANCE_OF_20}{}",
+            hex::encode(address_word(&rc.address)?)
+        );
+        self.eth_call(&rc.collateral_address, data).await
+    }
+}
+
+struct Balances {
+    tokens: BTreeMap<String, f64>,
+    collateral: f64,
+}
+
+async fn fetch_balances(
+    rpc: &mut RpcClient,
+    rc: &ReconcileConfig,
+    markets: &[MarketDef],
+) -> anyhow::Result<Balances> {
+    let mut tokens = BTreeMap::new();
+    for m in markets {
+        for t in &m.token_ids {
+            let b = rpc
+                .balance_1155(rc, t.as_str())
+                .await
+                .with_context(|| format!("balanceOf token {t}"))?;
+            tokens.insert(t.to_string(), b);
+        }
+    }
+    let collateral = rpc.balance_20(rc).await.context("collateral balanceOf")?;
+    Ok(Balances { tokens, collateral })
+}
+
+fn write_report(path: &Path, report: &ReconciliationReport) -> anyhow::Result<()> {
+    let tmp = path.with_extension("json.tmp");
+    let body = serde_json::to_vec_pretty(report).context("serialize reconciliation.json")?;
+    std::fs::write(&tmp, body).with_context(|| format!("write {}", tmp.display()))?;
+    std::fs::rename(&tmp, path).with_context(|| format!("rename {}", path.display()))?;
+    Ok(())
+}
+
+/// Runs until shutdown (one last pass then); returns at once when `[reconcile]` is disabled.
+pub async fn run(
+    cfg: Config,
+    markets: Vec<MarketDef>,
+    run_dir: PathBuf,
+    health: Arc<HealthCounters>,
+    mut shutdown: watch::Receiver<bool>,
+) -> anyhow::Result<()> {
+    let rc = cfg.reconcile.clone();
+    if !rc.enabled {
+        return Ok(());
+    }
+    let http = reqwest::Client::builder()
+        .user_agent(concat!("razor/", env!("CARGO_PKG_VERSION")))
+        .connect_timeout(Duration::from_millis(
+            cfg.polymarket.http_connect_timeout_ms,
+        ))
+        .timeout(Duration::from_millis(cfg.polymarket.http_timeout_ms))
+        .build()
+        .context("build polygon rpc http client")?;
+    let mut rpc = RpcClient {
+        http,
+        url: rc.rpc_url.clone(),
+        next_id: 0,
+    };
+    let trade_log_path = run_dir.join(FILE_TRADE_LOG);
+    let out_path = run_dir.join(FILE_RECONCILIATION_JSON);
+    info!(address = %rc.address, interval_ms = rc.interval_ms, "reconcile started");
+
+    let mut reconciler = Reconciler::default();
+    let mut baseline: Option<(u64, Balances)> = None;
+    let mut report = ReconciliationReport {
+        version: "v1".to_string(),
+        ts_ms: now_ms(),
+        address: rc.address.clone(),
+        passes: 0,
+        baseline_ts_ms: None,
+        last_ok_ts_ms: None,
+        last_error: None,
+        collateral_baseline: None,
+        collateral_now: None,
+        tokens: Vec::new(),
+        breaks: 0,
+    };
+
+    let mut tick = tokio::time::interval(Duration::from_millis(rc.interval_ms));
+    tick.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+    loop {
+        let last = tokio::select! {
+            _ = tick.tick() => false,
+            _ = shutdown.changed() => {
+                if !*shutdown.borrow() { continue; }
+                true
+            }
+        };
+
+        report.ts_ms = now_ms();
+        report.passes += 1;
+        match fetch_balances(&mut rpc, &rc, &markets).await {
+            Ok(now) => {
+                let ts = now_ms();
+                let (base_ts, base) = baseline.get_or_insert((
+                    ts,
+                    Balances {
+                        tokens: now.tokens.clone(),
+                        collateral: now.collateral,
+                    },
+                ));
+                let fills = match read_trade_log(&trade_log_path) {
+                    Ok(rows) => net_fills(&rows),
+                    Err(e) => {
+                        warn!(error = %e, "reconcile: trade_log unreadable, pass skipped");
+                        report.last_error = Some(format!("{e:#}"));
+                        write_report(&out_path, &report)?;
+                        if last {
+                            break;
+                        }
+                        continue;
+                    }
+                };
+                report.tokens = markets
+                    .iter()
+                    .flat_map(|m| m.token_ids.iter().map(move |t| (m, t)))
+                    .map(|(m, t)| {
+                        let base_b = base.tokens.get(t.as_str()).copied().unwrap_or(0.0);
+                        let now_b = now.tokens.get(t.as_str()).copied().unwrap_or(0.0);
+                        reconciler.compare(
+                            rc.tolerance_qty,
+                            m.market_id.as_str(),
+                            t.as_str(),
+                            base_b,
+                            now_b,
+                            fills.get(t.as_str()).copied().unwrap_or_default(),
+                        )
+                    })
+                    .collect();
+                report.breaks = report.tokens.iter().filter(|t| t.status == "break").count() as u64;
+                report.baseline_ts_ms = Some(*base_ts);
+                report.last_ok_ts_ms = Some(ts);
+                report.last_error = None;
+                report.collateral_baseline = Some(base.collateral);
+                report.collateral_now = Some(now.collateral);
+                for t in report.tokens.iter().filter(|t| t.status == "break") {
+                    warn!(
+                        market_id = %t.market_id,
+                        token_id = %t.token_id,
+                        diff = t.diff,
+                        "reconcile: on-chain balance disagrees with trade_log"
+                    );
+                }
+                health.set_reconcile_breaks(report.breaks);
+            }
+            Err(e) => {
+                warn!(error = %format!("{e:#}"), "reconcile: balance read failed");
+                report.last_error = Some(format!("{e:#}"));
+            }
+        }
+        write_report(&out_path, &report)?;
+        if last {
+            break;
+        }
+    }
+    info!(
+        passes = report.passes,
+        breaks = report.breaks,
+        "reconcile stopped"
+    );
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn row(phase: &str, token: &str, side: &str, fill_qty: f64) -> TradeLogRow {
+        TradeLogRow {
+            ts_ms: 1,
+            signal_id: 1,
+            market_id: "m".to_string(),
+            strategy: "binary".to_string(),
+            bucket: "liquid".to_string(),
+            phase: phase.to_string(),
+            action: "fill".to_string(),
+            leg_index: 0,
+            token_id: token.to_string(),
+            side: side.to_string(),
+            limit_price: 0.5,
+            req_qty: fill_qty,
+            fill_qty,
+            fill_status: "FILLED".to_string(),
+            expected_net_bps: 0,
+            notes: String::new(),
+        }
+    }
+
+    #[test]
+    fn net_fills_splits_live_and_sim_and_signs_sides() {
+        let rows = vec![
+            row("LIVE", "1", "BUY", 10.0),
+            row("LIVE", "1", "SELL", 4.0),
+            row("SIM", "1", "BUY", 7.0),
+            row("LIVE", "2", "BUY", 0.0),
+            row("LIVE", "2", "HOLD", 3.0),
+        ];
+        let net = net_fills(&rows);
+        assert_eq!(
+            net["1"],
+            NetFills {
+                live: 6.0,
+                sim: 7.0
+            }
+        );
+        assert!(!net.contains_key("2"));
+    }
+
+    #[test]
+    fn mismatch_is_pending_then_break_and_clears() {
+        let mut r = Reconciler::default();
+        let fills = NetFills {
+            live: 5.0,
+            sim: 0.0,
+        };
+        assert_eq!(r.compare(0.01, "m", "1", 2.0, 7.0, fills).status, "ok");
+        let t = r.compare(0.01, "m", "1", 2.0, 6.0, fills);
+        assert_eq!((t.status, t.diff), ("pending", -1.0));
+        assert_eq!(r.compare(0.01, "m", "1", 2.0, 6.0, fills).status, "break");
+        assert_eq!(r.compare(0.01, "m", "1", 2.0, 7.005, fills).status, "ok");
+    }
+
+    #[test]
+    fn abi_words_and_balance_decode() {
+        let w = token_id_word("258").unwrap();
+        assert_eq!(&w[30..], &[1, 2]);
+        assert!(w[..30].iter().all(|b| *b == 0));
+        let max = "115792089237316195423570985008687907853269984665640564039457584007913129639935";
+        assert_eq!(token_id_word(max).unwrap(), [0xff; 32]);
+        assert!(token_id_word(
+            "115792089237316195423570985008687907853269984665640564039457584007913129639936"
+        )
+        .is_err());
+        assert!(token_id_word("0x12").is_err());
+
+        let a = address_word("0x4D97DCd97eC945f40cF65F87097ACe5EA0476045").unwrap();
+        assert_eq!(
+            hex::encode(&a[12..]),
+            "4d97dcd97ec945f40cf65f87097ace5ea0476045"
+        );
+
+        let bal = format!("0x{:064x}", 12_345_678u64);
+        assert!((parse_balance(&bal).unwrap() - 12.345678).abs() < 1e-9);
+        assert!(parse_balance("0x").is_err());
+    }
+}
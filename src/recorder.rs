@@ -1,15 +1,20 @@
+use std::collections::BTreeMap;
 use std::fs::{File, OpenOptions};
-use std::io::{BufRead, BufReader, BufWriter, Write as _};
+use std::io::{BufRead, BufReader, Read, Seek, SeekFrom, Write};
 use std::path::{Path, PathBuf};
-use std::time::{SystemTime, UNIX_EPOCH};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex, OnceLock, Weak};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
 
 use anyhow::Context as _;
-use serde::Serialize;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
 use tracing::warn;
 
-use crate::types::now_ms;
+use crate::config::{CommitPolicy, FsyncPolicy, RecorderConfig, RecorderErrorPolicy};
+use crate::types::{civil_from_days, now_ms};
 
-pub const TRADES_HEADER: [&str; 8] = crate::schema::TRADES_HEADER;
+pub const TRADES_HEADER: [&str; 9] = crate::schema::TRADES_HEADER;
 
 pub const TICKS_HEADER: [&str; 6] = [
     "ts_recv_us",
@@ -20,21 +25,656 @@ pub const TICKS_HEADER: [&str; 6] = [
     "ask_depth3_usdc",
 ];
 
-pub const SHADOW_HEADER: [&str; 38] = crate::schema::SHADOW_HEADER;
+pub const SHADOW_HEADER: [&str; 53] = crate::schema::SHADOW_HEADER;
 
-const CSV_FLUSH_EVERY_RECORDS: usize = 200;
-const CSV_FLUSH_EVERY_MS: u64 = 1_000;
+const CSV_COMMIT_EVERY_ROWS: usize = 200;
+// JSONL can be high-volume (raw WS); commit in larger batches to keep IO off the hot path.
+const JSONL_COMMIT_EVERY_ROWS: usize = 500;
+/// How often the background committer looks for buffers past their `flush_interval_ms`.
+const COMMITTER_TICK_MS: u64 = 20;
+
+/// Upper bounds (us) of the fsync latency buckets; one overflow bucket follows.
+const FSYNC_LATENCY_BUCKETS_US: [u64; 9] = [
+    100, 500, 1_000, 5_000, 10_000, 50_000, 100_000, 500_000, 1_000_000,
+];
+
+/// Per-file recorder I/O counters, shared by every appender writing the same file name.
+#[derive(Default)]
+pub struct FileIoMetrics {
+    rows: AtomicU64,
+    bytes: AtomicU64,
+    write_errors: AtomicU64,
+    fsyncs: AtomicU64,
+    fsync_max_us: AtomicU64,
+    fsync_hist: [AtomicU64; FSYNC_LATENCY_BUCKETS_US.len() + 1],
+    /// Set once a write error degraded the file (`on_write_error = "degrade"`).
+    degraded: OnceLock<DegradedFile>,
+}
+
+/// A file the recorder stopped writing after an error, under `on_write_error = "degrade"`.
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct DegradedFile {
+    pub file: String,
+    pub ts_ms: u64,
+    pub error: String,
+}
+
+#[derive(Debug, Clone, Serialize, JsonSchema)]
+pub struct FileIoSnapshot {
+    pub file: String,
+    pub rows: u64,
+    /// Bytes handed to the OS (i.e. after buffering), so it lags `rows` until the next flush.
+    pub bytes: u64,
+    pub write_errors: u64,
+    pub fsyncs: u64,
+    /// Bucket upper bound containing the 95th percentile (overflow reports the max).
+    pub fsync_p95_us: u64,
+    pub fsync_max_us: u64,
+    /// When the file was degraded (no longer written); absent while it is healthy.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub degraded_ts_ms: Option<u64>,
+}
+
+impl FileIoMetrics {
+    fn record_fsync(&self, elapsed_us: u64) {
+        let idx = FSYNC_LATENCY_BUCKETS_US
+            .iter()
+            .position(|ub| elapsed_us <= *ub)
+            .unwrap_or(FSYNC_LATENCY_BUCKETS_US.len());
+        self.fsync_hist[idx].fetch_add(1, Ordering::Relaxed);
+        self.fsyncs.fetch_add(1, Ordering::Relaxed);
+        self.fsync_max_us.fetch_max(elapsed_us, Ordering::Relaxed);
+    }
+
+    fn inc_write_errors(&self) {
+        self.write_errors.fetch_add(1, Ordering::Relaxed);
+    }
+
+    fn snapshot(&self, file: &str) -> FileIoSnapshot {
+        let fsyncs = self.fsyncs.load(Ordering::Relaxed);
+        let fsync_max_us = self.fsync_max_us.load(Ordering::Relaxed);
+        let target = (fsyncs * 95).div_ceil(100);
+        let mut seen = 0u64;
+        let mut fsync_p95_us = 0u64;
+        if fsyncs > 0 {
+            for (i, b) in self.fsync_hist.iter().enumerate() {
+                seen += b.load(Ordering::Relaxed);
+                if seen >= target {
+                    fsync_p95_us = FSYNC_LATENCY_BUCKETS_US
+                        .get(i)
+                        .copied()
+                        .unwrap_or(fsync_max_us);
+                    break;
+                }
+            }
+        }
+        FileIoSnapshot {
+            file: file.to_string(),
+            rows: self.rows.load(Ordering::Relaxed),
+            bytes: self.bytes.load(Ordering::Relaxed),
+            write_errors: self.write_errors.load(Ordering::Relaxed),
+            fsyncs,
+            fsync_p95_us,
+            fsync_max_us,
+            degraded_ts_ms: self.degraded.get().map(|d| d.ts_ms),
+        }
+    }
+
+    fn is_degraded(&self) -> bool {
+        self.degraded.get().is_some()
+    }
+
+    /// Applies the file's error policy to the outcome of a write: under `degrade` the first
+    /// error marks the file degraded and is swallowed.
+    fn apply_error_policy(
+        &self,
+        file: &str,
+        policy: RecorderErrorPolicy,
+        res: anyhow::Result<()>,
+    ) -> anyhow::Result<()> {
+        match res {
+            Err(e) if policy == RecorderErrorPolicy::Degrade => {
+                let error = format!("{e:#}");
+                let first = self
+                    .degraded
+                    .set(DegradedFile {
+                        file: file.to_string(),
+                        ts_ms: now_ms(),
+                        error: error.clone(),
+                    })
+                    .is_ok();
+                if first {
+                    tracing::error!(
+                        file,
+                        error,
+                        "recorder write failed; file degraded, no longer written"
+                    );
+                }
+                Ok(())
+            }
+            other => other,
+        }
+    }
+}
+
+fn io_registry() -> &'static Mutex<BTreeMap<String, Arc<FileIoMetrics>>> {
+    static REGISTRY: OnceLock<Mutex<BTreeMap<String, Arc<FileIoMetrics>>>> = OnceLock::new();
+    REGISTRY.get_or_init(|| Mutex::new(BTreeMap::new()))
+}
+
+fn io_metrics_for(path: &Path) -> Arc<FileIoMetrics> {
+    let key = path
+        .file_name()
+        .map(|s| s.to_string_lossy().to_string())
+        .unwrap_or_else(|| path.display().to_string());
+    let mut map = io_registry().lock().unwrap_or_else(|e| e.into_inner());
+    map.entry(key).or_default().clone()
+}
+
+/// Files degraded by a write error in this process, for the end-of-run report.
+pub fn degraded_files() -> Vec<DegradedFile> {
+    let map = io_registry().lock().unwrap_or_else(|e| e.into_inner());
+    map.values()
+        .filter_map(|m| m.degraded.get().cloned())
+        .collect()
+}
+
+/// Snapshot of recorder I/O counters for every file opened by an appender in this process.
+pub fn io_metrics_snapshot() -> Vec<FileIoSnapshot> {
+    let map = io_registry().lock().unwrap_or_else(|e| e.into_inner());
+    map.iter().map(|(k, m)| m.snapshot(k)).collect()
+}
+
+/// `File` wrapper counting bytes and write errors as buffered data reaches the OS.
+struct MeteredFile {
+    file: File,
+    metrics: Arc<FileIoMetrics>,
+}
+
+impl MeteredFile {
+    fn new(file: File, metrics: Arc<FileIoMetrics>) -> Self {
+        Self { file, metrics }
+    }
+
+    fn sync_all(&self) -> std::io::Result<()> {
+        let started = Instant::now();
+        let res = self.file.sync_all();
+        self.metrics
+            .record_fsync(started.elapsed().as_micros().min(u64::MAX as u128) as u64);
+        if res.is_err() {
+            self.metrics.inc_write_errors();
+        }
+        res
+    }
+}
+
+impl Write for MeteredFile {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        match self.file.write(buf) {
+            Ok(n) => {
+                self.metrics.bytes.fetch_add(n as u64, Ordering::Relaxed);
+                Ok(n)
+            }
+            Err(e) => {
+                self.metrics.inc_write_errors();
+                Err(e)
+            }
+        }
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        self.file.flush()
+    }
+}
+
+// Group commit
+//
+// Appenders never write rows to the file directly: each complete row is appended to an
+// in-memory `GroupBuffer`, and the buffer is written out with a single `write` (a "commit") when
+// `max_rows` rows are pending (inline, on the writer's thread) or the oldest pending row has
+// waited `flush_interval_ms` (by the background committer thread). Crash-safety:
+// - a process crash loses at most the uncommitted rows: fewer than `max_rows`, and no more than
+//   `flush_interval_ms` (+ one committer tick) worth of data;
+// - committed rows are in the OS page cache and survive a process crash; `fsync_policy` decides
+//   when they are also synced and survive power loss: `interval` on every commit, `per_row`
+//   commits and syncs each row as it is written, `on_close` only at `flush_and_sync`;
+// - the buffer only ever holds whole rows, so a crash never leaves a torn line behind (short of
+//   the OS failing mid-`write`);
+// - `flush_and_sync` commits and fsyncs regardless of policy, so a clean shutdown loses nothing.
+
+struct CommitPolicies {
+    max_rows: Option<usize>,
+    flush_interval_ms: u64,
+    fsync_policy: FsyncPolicy,
+    files: BTreeMap<String, CommitPolicy>,
+    on_write_error: RecorderErrorPolicy,
+    on_write_error_files: BTreeMap<String, RecorderErrorPolicy>,
+}
+
+fn commit_policies() -> &'static Mutex<CommitPolicies> {
+    static POLICIES: OnceLock<Mutex<CommitPolicies>> = OnceLock::new();
+    POLICIES.get_or_init(|| {
+        let cfg = RecorderConfig::default();
+        Mutex::new(CommitPolicies {
+            max_rows: cfg.commit_max_rows,
+            flush_interval_ms: cfg.flush_interval_ms,
+            fsync_policy: cfg.fsync_policy,
+            files: cfg.files,
+            on_write_error: cfg.on_write_error,
+            on_write_error_files: cfg.on_write_error_files,
+        })
+    })
+}
+
+/// Installs the `[recorder]` group-commit and write-error policies for appenders opened from
+/// now on.
+pub fn configure_group_commit(cfg: &RecorderConfig) {
+    let mut p = commit_policies().lock().unwrap_or_else(|e| e.into_inner());
+    *p = CommitPolicies {
+        max_rows: cfg.commit_max_rows,
+        flush_interval_ms: cfg.flush_interval_ms,
+        fsync_policy: cfg.fsync_policy,
+        files: cfg.files.clone(),
+        on_write_error: cfg.on_write_error,
+        on_write_error_files: cfg.on_write_error_files.clone(),
+    };
+}
+
+fn file_key(path: &Path) -> String {
+    path.file_name()
+        .map(|s| s.to_string_lossy().to_string())
+        .unwrap_or_default()
+}
+
+fn error_policy_for(path: &Path) -> RecorderErrorPolicy {
+    let p = commit_policies().lock().unwrap_or_else(|e| e.into_inner());
+    p.on_write_error_files
+        .get(&file_key(path))
+        .copied()
+        .unwrap_or(p.on_write_error)
+}
+
+fn commit_policy_for(path: &Path, default_max_rows: usize) -> CommitPolicy {
+    let p = commit_policies().lock().unwrap_or_else(|e| e.into_inner());
+    p.files
+        .get(&file_key(path))
+        .copied()
+        .unwrap_or(CommitPolicy {
+            max_rows: p.max_rows.unwrap_or(default_max_rows),
+            flush_interval_ms: p.flush_interval_ms,
+            fsync_policy: p.fsync_policy,
+        })
+}
+
+/// Whole rows waiting for the next commit, shared with the background committer.
+struct GroupBuffer {
+    file: MeteredFile,
+    buf: Vec<u8>,
+    pending_rows: usize,
+    oldest_pending_ms: Option<u64>,
+    policy: CommitPolicy,
+    /// Error from a background commit, surfaced on the owner's next write.
+    deferred_err: Option<std::io::Error>,
+}
+
+type SharedGroup = Arc<Mutex<GroupBuffer>>;
+
+impl GroupBuffer {
+    fn open(file: MeteredFile, policy: CommitPolicy) -> SharedGroup {
+        let group = Arc::new(Mutex::new(Self {
+            file,
+            buf: Vec::new(),
+            pending_rows: 0,
+            oldest_pending_ms: None,
+            policy,
+            deferred_err: None,
+        }));
+        let mut groups = committer_groups().lock().unwrap_or_else(|e| e.into_inner());
+        groups.push(Arc::downgrade(&group));
+        group
+    }
+
+    fn take_deferred_err(&mut self) -> std::io::Result<()> {
+        self.deferred_err.take().map_or(Ok(()), Err)
+    }
+
+    /// Accounts one whole row already appended to `buf`; commits inline at `max_rows` (or at
+    /// once under `per_row`).
+    fn row_added(&mut self) -> std::io::Result<bool> {
+        self.pending_rows += 1;
+        self.oldest_pending_ms.get_or_insert_with(now_ms);
+        if self.pending_rows < self.policy.max_rows
+            && self.policy.fsync_policy != FsyncPolicy::PerRow
+        {
+            return Ok(false);
+        }
+        self.commit(self.policy.fsync_policy.fsync_each_commit())?;
+        Ok(true)
+    }
+
+    /// Writes every pending row in one call. A failed batch is dropped (and counted) rather
+    /// than retried, since a partial write followed by a retry would duplicate rows.
+    fn commit(&mut self, fsync: bool) -> std::io::Result<()> {
+        self.pending_rows = 0;
+        self.oldest_pending_ms = None;
+        if !self.buf.is_empty() {
+            let res = self.file.write_all(&self.buf);
+            self.buf.clear();
+            res?;
+        }
+        if fsync {
+            self.file.sync_all()?;
+        }
+        Ok(())
+    }
+}
+
+impl Drop for GroupBuffer {
+    fn drop(&mut self) {
+        let _ = self.commit(false);
+    }
+}
+
+/// `csv::Writer` sink that only moves bytes into the group buffer.
+struct GroupWriter(SharedGroup);
+
+impl Write for GroupWriter {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        let mut g = self.0.lock().unwrap_or_else(|e| e.into_inner());
+        g.buf.extend_from_slice(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+/// Buffers the background committer watches, started with the first appender.
+fn committer_groups() -> &'static Mutex<Vec<Weak<Mutex<GroupBuffer>>>> {
+    static GROUPS: OnceLock<Mutex<Vec<Weak<Mutex<GroupBuffer>>>>> = OnceLock::new();
+    GROUPS.get_or_init(|| {
+        let spawned = std::thread::Builder::new()
+            .name("razor-group-commit".to_string())
+            .spawn(run_committer);
+        if let Err(e) = spawned {
+            warn!(error = %e, "group committer thread failed to start; commits are row-driven only");
+        }
+        Mutex::new(Vec::new())
+    })
+}
+
+fn run_committer() {
+    loop {
+        std::thread::sleep(Duration::from_millis(COMMITTER_TICK_MS));
+        let live: Vec<SharedGroup> = {
+            let mut groups = committer_groups().lock().unwrap_or_else(|e| e.into_inner());
+            groups.retain(|w| w.strong_count() > 0);
+            groups.iter().filter_map(Weak::upgrade).collect()
+        };
+        let now = now_ms();
+        for group in live {
+            let mut g = group.lock().unwrap_or_else(|e| e.into_inner());
+            let due = g
+                .oldest_pending_ms
+                .is_some_and(|t| now.saturating_sub(t) >= g.policy.flush_interval_ms);
+            if due {
+                let fsync = g.policy.fsync_policy.fsync_each_commit();
+                if let Err(e) = g.commit(fsync) {
+                    g.deferred_err = Some(e);
+                }
+            }
+        }
+    }
+}
 
 pub struct CsvAppender {
-    writer: csv::Writer<BufWriter<File>>,
-    pending_records: usize,
-    last_flush_ms: u64,
+    writer: csv::Writer<GroupWriter>,
+    group: SharedGroup,
+    metrics: Arc<FileIoMetrics>,
+    partition: Option<HourPartition>,
+    file: String,
+    on_write_error: RecorderErrorPolicy,
+}
+
+/// Current hour of a partitioned appender; the file rolls when the wall clock leaves it.
+struct HourPartition {
+    run_dir: PathBuf,
+    file_name: String,
+    header: Vec<String>,
+    hour_start_ms: u64,
+}
+
+const HOUR_MS: u64 = 3_600_000;
+
+/// `YYYY-MM-DD/HH` (UTC) partition of a unix-ms timestamp.
+pub fn hour_partition(ts_ms: u64) -> String {
+    let secs = (ts_ms / 1000) as i64;
+    let (year, month, day) = civil_from_days(secs.div_euclid(86_400));
+    let hour = secs.rem_euclid(86_400) / 3600;
+    format!("{year:04}-{month:02}-{day:02}/{hour:02}")
+}
+
+fn is_partition_component(name: &str, len: usize) -> bool {
+    name.len() == len && name.bytes().all(|b| b.is_ascii_digit() || b == b'-')
+}
+
+/// Every CSV a reader should load for `file_name` in `run_dir`: the flat file (if any) followed
+/// by its hourly `YYYY-MM-DD/HH/<file_name>` partitions in chronological order.
+pub fn csv_input_paths(run_dir: &Path, file_name: &str) -> Vec<PathBuf> {
+    let mut out = Vec::new();
+    let flat = run_dir.join(file_name);
+    if flat.is_file() {
+        out.push(flat);
+    }
+    let mut parts = Vec::new();
+    for day in std::fs::read_dir(run_dir).into_iter().flatten().flatten() {
+        if !is_partition_component(&day.file_name().to_string_lossy(), "YYYY-MM-DD".len()) {
+            continue;
+        }
+        for hour in std::fs::read_dir(day.path())
+            .into_iter()
+            .flatten()
+            .flatten()
+        {
+            if !is_partition_component(&hour.file_name().to_string_lossy(), 2) {
+                continue;
+            }
+            let p = hour.path().join(file_name);
+            if p.is_file() {
+                parts.push(p);
+            }
+        }
+    }
+    parts.sort();
+    out.extend(parts);
+    out
+}
+
+/// Every segment of a rotated JSONL `file_name` in `run_dir`, oldest first: the
+/// `<file_name>.rotated_<ms>` segments by rotation time, then the live file (if any).
+#[allow(dead_code)]
+pub fn jsonl_input_paths(run_dir: &Path, file_name: &str) -> Vec<PathBuf> {
+    let prefix = format!("{file_name}.rotated_");
+    let mut segments: Vec<(u64, PathBuf)> = std::fs::read_dir(run_dir)
+        .into_iter()
+        .flatten()
+        .flatten()
+        .filter_map(|e| {
+            let name = e.file_name().to_string_lossy().to_string();
+            let ms = name.strip_prefix(&prefix)?.parse::<u64>().ok()?;
+            Some((ms, e.path()))
+        })
+        .collect();
+    segments.sort();
+    let mut out: Vec<PathBuf> = segments.into_iter().map(|(_, p)| p).collect();
+    let live = run_dir.join(file_name);
+    if live.is_file() {
+        out.push(live);
+    }
+    out
+}
+
+#[derive(Serialize)]
+struct PartitionManifestLine<'a> {
+    file: &'a str,
+    partition: &'a str,
+    path: &'a str,
+    opened_ts_ms: u64,
+}
+
+fn append_partition_manifest(
+    run_dir: &Path,
+    file_name: &str,
+    partition: &str,
+) -> anyhow::Result<()> {
+    let path = run_dir.join(crate::schema::FILE_PARTITIONS_JSONL);
+    let rel = format!("{partition}/{file_name}");
+    let mut line = serde_json::to_string(&PartitionManifestLine {
+        file: file_name,
+        partition,
+        path: &rel,
+        opened_ts_ms: now_ms(),
+    })?;
+    line.push('\n');
+    // One write per line on an O_APPEND handle, so the ticks and trades writers can share it.
+    OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(&path)
+        .and_then(|mut f| f.write_all(line.as_bytes()))
+        .with_context(|| format!("append {}", path.display()))
+}
+
+/// Byte offset just past the last `\n` in `file` (0 if there is none).
+fn end_of_last_line(file: &mut File, len: u64) -> std::io::Result<u64> {
+    const CHUNK: u64 = 64 * 1024;
+    let mut end = len;
+    let mut buf = vec![0u8; CHUNK as usize];
+    while end > 0 {
+        let start = end.saturating_sub(CHUNK);
+        let chunk = &mut buf[..(end - start) as usize];
+        file.seek(SeekFrom::Start(start))?;
+        file.read_exact(chunk)?;
+        if let Some(i) = chunk.iter().rposition(|b| *b == b'\n') {
+            return Ok(start + i as u64 + 1);
+        }
+        end = start;
+    }
+    Ok(0)
+}
+
+/// If `path` doesn't end in `\n` (the process died mid-write), moves the partial last line to
+/// `<file>.torn_tail_<ms>` and truncates the file back to its last complete line, so appends
+/// don't glue a new row onto it. Returns the backup path when a repair was made.
+pub fn repair_torn_tail(path: &Path) -> anyhow::Result<Option<PathBuf>> {
+    let mut file = match OpenOptions::new().read(true).write(true).open(path) {
+        Ok(f) => f,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+        Err(e) => return Err(e).with_context(|| format!("open {}", path.display())),
+    };
+    let len = file
+        .metadata()
+        .with_context(|| format!("stat {}", path.display()))?
+        .len();
+    if len == 0 {
+        return Ok(None);
+    }
+    let mut last = [0u8; 1];
+    file.seek(SeekFrom::Start(len - 1))
+        .and_then(|_| file.read_exact(&mut last))
+        .with_context(|| format!("read tail {}", path.display()))?;
+    if last[0] == b'\n' {
+        return Ok(None);
+    }
+
+    let keep = end_of_last_line(&mut file, len)
+        .with_context(|| format!("scan tail {}", path.display()))?;
+    let mut torn = Vec::with_capacity((len - keep) as usize);
+    file.seek(SeekFrom::Start(keep))
+        .and_then(|_| file.read_to_end(&mut torn))
+        .with_context(|| format!("read tail {}", path.display()))?;
+    let backup = suffixed_backup_path(path, "torn_tail");
+    std::fs::write(&backup, &torn).with_context(|| format!("write {}", backup.display()))?;
+    file.set_len(keep)
+        .and_then(|()| file.sync_all())
+        .with_context(|| format!("truncate {}", path.display()))?;
+    warn!(
+        path = %path.display(),
+        backup = %backup.display(),
+        torn_bytes = torn.len(),
+        "torn final line; truncated to last complete line"
+    );
+    Ok(Some(backup))
+}
+
+#[derive(Serialize)]
+struct SidecarColumn<'a> {
+    name: &'a str,
+    #[serde(rename = "type")]
+    kind: &'static str,
+    unit: Option<&'static str>,
+    description: &'static str,
+}
+
+#[derive(Serialize)]
+struct CsvSidecar<'a> {
+    file: &'a str,
+    file_version: Option<String>,
+    schema_version: &'static str,
+    producer: Option<&'static str>,
+    binary_version: &'static str,
+    git_sha: String,
+    created_ts_ms: u64,
+    columns: Vec<SidecarColumn<'a>>,
+}
+
+/// `<file>.meta.json` next to a CSV.
+pub fn csv_sidecar_path(csv_path: &Path) -> PathBuf {
+    let name = csv_path
+        .file_name()
+        .map(|s| s.to_string_lossy().to_string())
+        .unwrap_or_default();
+    csv_path.with_file_name(format!("{name}.meta.json"))
+}
+
+/// Writes the CSV's self-describing sidecar (schema and file version, per-column type, unit
+/// and meaning, producing module, build), so a copy taken out of its run dir stays readable.
+pub fn write_csv_sidecar(csv_path: &Path, header: &[&str]) -> anyhow::Result<()> {
+    use crate::schema::{column_doc, column_kind, column_unit, file_versions, producer_module};
+
+    let file = csv_path
+        .file_name()
+        .map(|s| s.to_string_lossy().to_string())
+        .unwrap_or_default();
+    let sidecar = CsvSidecar {
+        file: &file,
+        file_version: file_versions().remove(&file),
+        schema_version: crate::schema::SCHEMA_VERSION,
+        producer: producer_module(&file),
+        binary_version: env!("CARGO_PKG_VERSION"),
+        git_sha: crate::run_meta::env_git_sha(),
+        created_ts_ms: now_ms(),
+        columns: header
+            .iter()
+            .map(|col| SidecarColumn {
+                name: col,
+                kind: column_kind(col).as_str(),
+                unit: column_unit(col),
+                description: column_doc(&file, col),
+            })
+            .collect(),
+    };
+    let path = csv_sidecar_path(csv_path);
+    std::fs::write(&path, serde_json::to_vec_pretty(&sidecar)?)
+        .with_context(|| format!("write {}", path.display()))
 }
 
 impl CsvAppender {
     pub fn open(path: impl AsRef<Path>, header: &[&str]) -> anyhow::Result<Self> {
         let path = path.as_ref();
         let expected = header.join(",");
+        repair_torn_tail(path)?;
 
         if let Ok(meta) = std::fs::metadata(path) {
             if meta.len() > 0 {
@@ -76,69 +716,204 @@ impl CsvAppender {
             .len()
             == 0;
 
+        let metrics = io_metrics_for(path);
+        let group = GroupBuffer::open(
+            MeteredFile::new(file, metrics.clone()),
+            commit_policy_for(path, CSV_COMMIT_EVERY_ROWS),
+        );
         let mut writer = csv::WriterBuilder::new()
             .has_headers(false)
-            .from_writer(BufWriter::new(file));
+            .from_writer(GroupWriter(group.clone()));
 
         if is_empty {
             writer
                 .write_record(header)
+                .and_then(|()| writer.flush().map_err(csv::Error::from))
                 .with_context(|| format!("write header {}", path.display()))?;
-            writer
-                .flush()
+            lock_group(&group)
+                .commit(false)
                 .with_context(|| format!("flush {}", path.display()))?;
         }
+        if is_empty || !csv_sidecar_path(path).exists() {
+            write_csv_sidecar(path, header)?;
+        }
 
         Ok(Self {
             writer,
-            pending_records: 0,
-            last_flush_ms: now_ms(),
+            group,
+            metrics,
+            partition: None,
+            file: file_key(path),
+            on_write_error: error_policy_for(path),
         })
     }
 
+    /// Like [`CsvAppender::open`], but writes `<run_dir>/YYYY-MM-DD/HH/<file>` (UTC, by write
+    /// time) for the `flat_path` it would otherwise use, rolling to a new partition every hour
+    /// and recording each one in `partitions.jsonl`.
+    pub fn open_partitioned(flat_path: impl AsRef<Path>, header: &[&str]) -> anyhow::Result<Self> {
+        let flat_path = flat_path.as_ref();
+        let run_dir = flat_path
+            .parent()
+            .context("partitioned csv path has no parent dir")?;
+        let file_name = flat_path
+            .file_name()
+            .context("partitioned csv path has no file name")?
+            .to_string_lossy()
+            .to_string();
+        let partition = HourPartition {
+            run_dir: run_dir.to_path_buf(),
+            file_name,
+            header: header.iter().map(|h| h.to_string()).collect(),
+            hour_start_ms: 0,
+        };
+        let (mut me, hour_start_ms) = partition.open_at(now_ms())?;
+        me.partition = Some(HourPartition {
+            hour_start_ms,
+            ..partition
+        });
+        Ok(me)
+    }
+
+    fn maybe_roll_partition(&mut self) -> anyhow::Result<()> {
+        let now = now_ms();
+        let Some(p) = self.partition.as_ref() else {
+            return Ok(());
+        };
+        if now >= p.hour_start_ms && now < p.hour_start_ms + HOUR_MS {
+            return Ok(());
+        }
+        let (next, hour_start_ms) = p.open_at(now)?;
+        self.try_flush_and_sync()?;
+        let mut p = self.partition.take().expect("checked above");
+        p.hour_start_ms = hour_start_ms;
+        *self = Self {
+            partition: Some(p),
+            ..next
+        };
+        Ok(())
+    }
+
     pub fn write_record<I, S>(&mut self, record: I) -> anyhow::Result<()>
     where
         I: IntoIterator<Item = S>,
         S: AsRef<[u8]>,
     {
-        self.writer.write_record(record)?;
-        self.pending_records = self.pending_records.saturating_add(1);
-        self.maybe_flush()?;
+        self.append_with(|w| w.write_record(record))
+    }
+
+    /// Appends one typed row (see `crate::rows`); the header is written at open, never here.
+    pub fn write_row<R: serde::Serialize>(&mut self, row: &R) -> anyhow::Result<()> {
+        self.append_with(|w| w.serialize(row))
+    }
+
+    /// A degraded file (see `RecorderErrorPolicy::Degrade`) silently drops every row.
+    fn append_with(
+        &mut self,
+        write: impl FnOnce(&mut csv::Writer<GroupWriter>) -> csv::Result<()>,
+    ) -> anyhow::Result<()> {
+        if self.metrics.is_degraded() {
+            return Ok(());
+        }
+        let res = self.try_append(write);
+        self.metrics
+            .apply_error_policy(&self.file, self.on_write_error, res)
+    }
+
+    fn try_append(
+        &mut self,
+        write: impl FnOnce(&mut csv::Writer<GroupWriter>) -> csv::Result<()>,
+    ) -> anyhow::Result<()> {
+        self.maybe_roll_partition()?;
+        lock_group(&self.group)
+            .take_deferred_err()
+            .context("group commit")?;
+        // The csv writer keeps its own buffer; flushing it after every record is what keeps
+        // only whole rows in the group buffer.
+        if let Err(e) =
+            write(&mut self.writer).and_then(|()| self.writer.flush().map_err(csv::Error::from))
+        {
+            self.metrics.inc_write_errors();
+            return Err(e.into());
+        }
+        self.metrics.rows.fetch_add(1, Ordering::Relaxed);
+        lock_group(&self.group)
+            .row_added()
+            .context("group commit")?;
         Ok(())
     }
 
     pub fn flush_and_sync(&mut self) -> anyhow::Result<()> {
+        if self.metrics.is_degraded() {
+            return Ok(());
+        }
+        let res = self.try_flush_and_sync();
+        self.metrics
+            .apply_error_policy(&self.file, self.on_write_error, res)
+    }
+
+    fn try_flush_and_sync(&mut self) -> anyhow::Result<()> {
         self.writer.flush()?;
-        self.pending_records = 0;
-        self.last_flush_ms = now_ms();
-        self.writer
-            .get_ref()
-            .get_ref()
-            .sync_all()
-            .context("sync csv file")?;
+        let mut g = lock_group(&self.group);
+        g.take_deferred_err().context("group commit")?;
+        g.commit(true).context("sync csv file")?;
         Ok(())
     }
+}
 
-    fn maybe_flush(&mut self) -> anyhow::Result<()> {
-        let now = now_ms();
-        let due = self.pending_records >= CSV_FLUSH_EVERY_RECORDS
-            || now.saturating_sub(self.last_flush_ms) >= CSV_FLUSH_EVERY_MS;
-        if due {
-            self.writer.flush()?;
-            self.pending_records = 0;
-            self.last_flush_ms = now;
-        }
-        Ok(())
+fn lock_group(group: &SharedGroup) -> std::sync::MutexGuard<'_, GroupBuffer> {
+    group.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+impl HourPartition {
+    /// Opens (or reopens, after a restart within the same hour) the partition holding `ts_ms`.
+    fn open_at(&self, ts_ms: u64) -> anyhow::Result<(CsvAppender, u64)> {
+        let partition = hour_partition(ts_ms);
+        let dir = self.run_dir.join(&partition);
+        std::fs::create_dir_all(&dir).with_context(|| format!("create {}", dir.display()))?;
+        let header: Vec<&str> = self.header.iter().map(String::as_str).collect();
+        let appender = CsvAppender::open(dir.join(&self.file_name), &header)?;
+        append_partition_manifest(&self.run_dir, &self.file_name, &partition)?;
+        Ok((appender, ts_ms - ts_ms % HOUR_MS))
     }
 }
 
+/// When a `JsonlAppender` rotates its file to `<file>.rotated_<ms>` segments, and which old
+/// segments it deletes afterwards. `None` (or `0`) disables a limit.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct RotationPolicy {
+    /// Rotate once the live file reaches this size.
+    pub max_bytes: Option<u64>,
+    /// Rotate once the live segment has been open this long (wall clock).
+    pub max_age_ms: Option<u64>,
+    /// Keep at most this many rotated segments.
+    pub keep_files: Option<usize>,
+    /// Delete the oldest segments while live file + segments exceed this many bytes.
+    pub max_total_bytes: Option<u64>,
+}
+
+/// One completed rotation, reported to the appender's `on_rotate` hook.
+#[derive(Debug, Clone)]
+pub struct JsonlRotation {
+    pub ts_ms: u64,
+    /// `size` or `interval`.
+    pub reason: &'static str,
+    pub segment: PathBuf,
+    pub segment_bytes: u64,
+    /// Old segments deleted by retention right after this rotation.
+    pub removed: Vec<PathBuf>,
+}
+
+type RotationHook = Box<dyn FnMut(JsonlRotation) + Send>;
+
 pub struct JsonlAppender {
     path: PathBuf,
-    out: BufWriter<File>,
-    pending_lines: usize,
-    last_flush_ms: u64,
-    rotate_max_bytes: Option<u64>,
-    rotate_keep_files: Option<usize>,
+    group: SharedGroup,
+    metrics: Arc<FileIoMetrics>,
+    on_write_error: RecorderErrorPolicy,
+    rotation: RotationPolicy,
+    segment_opened_ms: u64,
+    on_rotate: Option<RotationHook>,
 }
 
 pub struct RecorderGuard {
@@ -156,13 +931,20 @@ impl RecorderGuard {
             crate::schema::FILE_TRADES,
             crate::schema::FILE_SNAPSHOTS,
             crate::schema::FILE_SHADOW_LOG,
+            crate::schema::FILE_SHADOW_LOG_JSONL,
+            crate::schema::FILE_SIGNALS,
             crate::schema::FILE_RAW_WS_JSONL,
             crate::schema::FILE_HEALTH_JSONL,
+            crate::schema::FILE_PROGRESS_JSONL,
+            crate::schema::FILE_DEDUP_COLLISIONS_JSONL,
+            crate::schema::FILE_PROFILE_JSONL,
+            crate::schema::FILE_PARTITIONS_JSONL,
             crate::schema::FILE_TRADE_LOG,
             crate::schema::FILE_CALIBRATION_LOG,
             crate::schema::FILE_CALIBRATION_SUGGEST,
             crate::schema::FILE_REPORT_JSON,
             crate::schema::FILE_REPORT_MD,
+            crate::schema::FILE_REPORT_HTML,
             crate::schema::FILE_SCHEMA_VERSION,
             crate::schema::FILE_RUN_CONFIG,
             crate::schema::FILE_RUN_META_JSON,
@@ -192,6 +974,11 @@ impl Drop for RecorderGuard {
 }
 
 fn schema_mismatch_backup_path(path: &Path) -> anyhow::Result<PathBuf> {
+    Ok(suffixed_backup_path(path, "schema_mismatch"))
+}
+
+/// `<file>.<tag>_<unix ms>` next to `path`.
+fn suffixed_backup_path(path: &Path, tag: &str) -> PathBuf {
     let now_ms = SystemTime::now()
         .duration_since(UNIX_EPOCH)
         .unwrap_or_default()
@@ -200,85 +987,119 @@ fn schema_mismatch_backup_path(path: &Path) -> anyhow::Result<PathBuf> {
         .file_name()
         .map(|s| s.to_string_lossy().to_string())
         .unwrap_or_else(|| "unknown.csv".to_string());
-    let backup_name = format!("{base_name}.schema_mismatch_{now_ms}");
-    Ok(path.with_file_name(backup_name))
+    path.with_file_name(format!("{base_name}.{tag}_{now_ms}"))
 }
 
 impl JsonlAppender {
     pub fn open(path: impl AsRef<Path>) -> anyhow::Result<Self> {
-        Self::open_with_rotation(path, None, None)
+        Self::open_with_policy(path, RotationPolicy::default())
     }
 
-    pub fn open_with_rotation(
+    pub fn open_with_policy(
         path: impl AsRef<Path>,
-        rotate_max_bytes: Option<u64>,
-        rotate_keep_files: Option<usize>,
+        rotation: RotationPolicy,
     ) -> anyhow::Result<Self> {
         let path = path.as_ref();
+        repair_torn_tail(path)?;
         let file = OpenOptions::new()
             .create(true)
             .append(true)
             .open(path)
             .with_context(|| format!("open {}", path.display()))?;
+        let metrics = io_metrics_for(path);
+        let group = GroupBuffer::open(
+            MeteredFile::new(file, metrics.clone()),
+            commit_policy_for(path, JSONL_COMMIT_EVERY_ROWS),
+        );
         Ok(Self {
             path: path.to_path_buf(),
-            out: BufWriter::new(file),
-            pending_lines: 0,
-            last_flush_ms: now_ms(),
-            rotate_max_bytes,
-            rotate_keep_files,
+            group,
+            metrics,
+            on_write_error: error_policy_for(path),
+            rotation,
+            segment_opened_ms: now_ms(),
+            on_rotate: None,
         })
     }
 
-    pub fn write_line(&mut self, line: &str) -> anyhow::Result<()> {
-        self.out.write_all(line.as_bytes())?;
-        self.out.write_all(b"\n")?;
-        self.pending_lines = self.pending_lines.saturating_add(1);
-        self.maybe_flush()?;
-        Ok(())
+    /// Called after every rotation (e.g. to record it in health.jsonl).
+    pub fn set_on_rotate(&mut self, hook: impl FnMut(JsonlRotation) + Send + 'static) {
+        self.on_rotate = Some(Box::new(hook));
     }
 
-    pub fn flush_and_sync(&mut self) -> anyhow::Result<()> {
-        self.flush_internal()?;
-        self.out.get_ref().sync_all().context("sync jsonl file")?;
-        Ok(())
+    pub fn write_line(&mut self, line: &str) -> anyhow::Result<()> {
+        if self.metrics.is_degraded() {
+            return Ok(());
+        }
+        let res = self.try_write_line(line);
+        self.metrics
+            .apply_error_policy(&file_key(&self.path), self.on_write_error, res)
     }
 
-    fn maybe_flush(&mut self) -> anyhow::Result<()> {
-        // JSONL can be high-volume (raw WS); flush in batches to avoid IO becoming the bottleneck.
-        const JSONL_FLUSH_EVERY_LINES: usize = 500;
-        const JSONL_FLUSH_EVERY_MS: u64 = 1_000;
-
-        let now = now_ms();
-        let due = self.pending_lines >= JSONL_FLUSH_EVERY_LINES
-            || now.saturating_sub(self.last_flush_ms) >= JSONL_FLUSH_EVERY_MS;
-        if due {
-            self.flush_internal()?;
+    fn try_write_line(&mut self, line: &str) -> anyhow::Result<()> {
+        // Interval rotation happens before the line, so it opens the new segment.
+        self.maybe_rotate(false)?;
+        let committed = {
+            let mut g = lock_group(&self.group);
+            g.take_deferred_err().context("group commit")?;
+            g.buf.extend_from_slice(line.as_bytes());
+            g.buf.push(b'\n');
+            self.metrics.rows.fetch_add(1, Ordering::Relaxed);
+            g.row_added().context("group commit")?
+        };
+        // The size check needs a stat, so it only runs when bytes actually reached the file.
+        if committed {
+            self.maybe_rotate(true)?;
         }
         Ok(())
     }
 
-    fn flush_internal(&mut self) -> anyhow::Result<()> {
-        self.out.flush()?;
-        self.pending_lines = 0;
-        self.last_flush_ms = now_ms();
+    pub fn flush_and_sync(&mut self) -> anyhow::Result<()> {
+        if self.metrics.is_degraded() {
+            return Ok(());
+        }
+        let res = self.try_flush_and_sync();
+        self.metrics
+            .apply_error_policy(&file_key(&self.path), self.on_write_error, res)
+    }
 
-        if let Some(max_bytes) = self.rotate_max_bytes {
-            self.rotate_if_needed(max_bytes)?;
+    fn try_flush_and_sync(&mut self) -> anyhow::Result<()> {
+        {
+            let mut g = lock_group(&self.group);
+            g.take_deferred_err().context("group commit")?;
+            g.commit(true).context("sync jsonl file")?;
         }
-        Ok(())
+        self.maybe_rotate(true)
     }
 
-    fn rotate_if_needed(&mut self, max_bytes: u64) -> anyhow::Result<()> {
-        if max_bytes == 0 {
-            return Ok(());
+    fn maybe_rotate(&mut self, check_size: bool) -> anyhow::Result<()> {
+        let now = now_ms();
+        let aged = self
+            .rotation
+            .max_age_ms
+            .is_some_and(|age| age > 0 && now.saturating_sub(self.segment_opened_ms) >= age);
+        let full = check_size
+            && self.rotation.max_bytes.is_some_and(|max| {
+                max > 0 && std::fs::metadata(&self.path).is_ok_and(|m| m.len() >= max)
+            });
+        if aged {
+            self.rotate("interval")
+        } else if full {
+            self.rotate("size")
+        } else {
+            Ok(())
         }
+    }
 
-        let meta = match std::fs::metadata(&self.path) {
-            Ok(m) => m,
-            Err(_) => return Ok(()),
-        };
-        if meta.len() < max_bytes {
+    fn rotate(&mut self, reason: &'static str) -> anyhow::Result<()> {
+        // Commit and sync everything pending into the segment being rotated out, and hold the
+        // lock so the background committer can't write into it after the rename.
+        let mut g = lock_group(&self.group);
+        g.commit(true).context("sync before rotate")?;
+        let segment_bytes = g.file.file.metadata().map(|m| m.len()).unwrap_or(0);
+        self.segment_opened_ms = now_ms();
+        if segment_bytes == 0 {
+            // Nothing arrived during the interval; don't leave empty segments behind.
             return Ok(());
         }
 
@@ -286,16 +1107,11 @@ impl JsonlAppender {
         warn!(
             path = %self.path.display(),
             rotated = %rotated.display(),
-            max_bytes,
-            "jsonl reached size cap; rotating"
+            reason,
+            segment_bytes,
+            "rotating jsonl"
         );
 
-        // Ensure durability for the rotated segment (best-effort).
-        self.out
-            .get_ref()
-            .sync_all()
-            .context("sync before rotate")?;
-
         // Best-effort rotation: on Unix renaming an open file works; we then reopen a fresh file.
         let _ = std::fs::rename(&self.path, &rotated);
         let file = OpenOptions::new()
@@ -303,38 +1119,54 @@ impl JsonlAppender {
             .append(true)
             .open(&self.path)
             .with_context(|| format!("reopen {}", self.path.display()))?;
-        self.out = BufWriter::new(file);
+        g.file = MeteredFile::new(file, self.metrics.clone());
+        drop(g);
 
-        if let Some(keep) = self.rotate_keep_files {
-            if keep > 0 {
-                cleanup_rotated_files(&self.path, keep)?;
-            }
+        let removed = cleanup_rotated_files(
+            &self.path,
+            self.rotation.keep_files.unwrap_or(0),
+            self.rotation.max_total_bytes.unwrap_or(0),
+        )?;
+        if let Some(hook) = self.on_rotate.as_mut() {
+            hook(JsonlRotation {
+                ts_ms: self.segment_opened_ms,
+                reason,
+                segment: rotated,
+                segment_bytes,
+                removed,
+            });
         }
         Ok(())
     }
 }
 
-fn cleanup_rotated_files(path: &Path, keep: usize) -> anyhow::Result<()> {
-    if keep == 0 {
-        return Ok(());
+/// Deletes the oldest rotated segments of `path` beyond `keep` of them, then while the live
+/// file plus the remaining segments exceed `max_total_bytes` (`0` disables either limit).
+fn cleanup_rotated_files(
+    path: &Path,
+    keep: usize,
+    max_total_bytes: u64,
+) -> anyhow::Result<Vec<PathBuf>> {
+    if keep == 0 && max_total_bytes == 0 {
+        return Ok(Vec::new());
     }
 
     let Some(dir) = path.parent() else {
-        return Ok(());
+        return Ok(Vec::new());
     };
     let base = path
         .file_name()
         .map(|s| s.to_string_lossy().to_string())
         .unwrap_or_default();
     if base.is_empty() {
-        return Ok(());
+        return Ok(Vec::new());
     }
     let prefix = format!("{base}.rotated_");
 
-    let mut rotated: Vec<(u128, PathBuf)> = Vec::new();
+    let mut rotated: Vec<(u128, PathBuf, u64)> = Vec::new();
     let entries = match std::fs::read_dir(dir) {
         Ok(v) => v,
-        Err(_) => return Ok(()),
+        Err(_) => return Ok(Vec::new()),
     };
     for entry in entries.flatten() {
         let name = entry.file_name().to_string_lossy().to_string();
@@ -345,20 +1177,36 @@ fn cleanup_rotated_files(path: &Path, keep: usize) -> anyhow::Result<()> {
             .strip_prefix(&prefix)
             .and_then(|s| s.parse::<u128>().ok())
             .unwrap_or(0);
-        rotated.push((ts, entry.path()));
+        let len = entry.metadata().map(|m| m.len()).unwrap_or(0);
+        rotated.push((ts, entry.path(), len));
     }
+    rotated.sort_by_key(|(ts, _, _)| *ts);
 
-    if rotated.len() <= keep {
-        return Ok(());
+    let mut remove_n = if keep > 0 {
+        rotated.len().saturating_sub(keep)
+    } else {
+        0
+    };
+    if max_total_bytes > 0 {
+        let live = std::fs::metadata(path).map(|m| m.len()).unwrap_or(0);
+        let mut total = live + rotated.iter().map(|(_, _, len)| len).sum::<u64>();
+        total -= rotated[..remove_n]
+            .iter()
+            .map(|(_, _, len)| len)
+            .sum::<u64>();
+        while total > max_total_bytes && remove_n < rotated.len() {
+            total -= rotated[remove_n].2;
+            remove_n += 1;
+        }
     }
-    rotated.sort_by_key(|(ts, _)| *ts);
 
-    let remove_n = rotated.len().saturating_sub(keep);
-    for (_ts, p) in rotated.into_iter().take(remove_n) {
+    let mut removed = Vec::with_capacity(remove_n);
+    for (_ts, p, _len) in rotated.into_iter().take(remove_n) {
         let _ = std::fs::remove_file(&p);
-        warn!(path = %p.display(), keep, "removed old rotated jsonl segment");
+        warn!(path = %p.display(), keep, max_total_bytes, "removed old rotated jsonl segment");
+        removed.push(p);
     }
-    Ok(())
+    Ok(removed)
 }
 
 fn rotated_path(path: &Path) -> anyhow::Result<PathBuf> {
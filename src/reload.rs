@@ -0,0 +1,184 @@
+//! SIGHUP reload: re-reads the config the run started from (file or profile layers) and applies
+//! only the keys that
+//! are safe to change mid-run. Every reload (applied, partly ignored or failed) is audited as a
+//! `config_reloaded` line in health.jsonl.
+
+use anyhow::Context as _;
+use tokio::sync::{mpsc, watch};
+use tracing::{info, warn};
+
+use crate::config::{self, Config, ConfigSource};
+use crate::health::{ConfigChange, HealthLine};
+use crate::types::now_ms;
+
+/// Dotted keys a SIGHUP may change: brain thresholds, health intervals and alert recipients.
+/// Anything else (markets, data_dir, sizing, recorder, ...) needs a restart.
+pub const RELOADABLE_KEYS: [&str; 8] = [
+    "brain.risk_premium_bps",
+    "brain.min_net_edge_bps",
+    "brain.signal_cooldown_ms",
+    "brain.max_snapshot_staleness_ms",
+    "health.heartbeat_interval_ms",
+    "health.log_interval_ms",
+    "health.push_interval_ms",
+    "email.to",
+];
+
+fn flatten(prefix: &str, v: &serde_json::Value, out: &mut Vec<(String, serde_json::Value)>) {
+    match v {
+        serde_json::Value::Object(map) => {
+            for (k, child) in map {
+                let key = if prefix.is_empty() {
+                    k.clone()
+                } else {
+                    format!("{prefix}.{k}")
+                };
+                flatten(&key, child, out);
+            }
+        }
+        _ => out.push((prefix.to_string(), v.clone())),
+    }
+}
+
+fn leaves(cfg: &Config) -> anyhow::Result<Vec<(String, serde_json::Value)>> {
+    let mut out = Vec::new();
+    flatten("", &serde_json::to_value(cfg)?, &mut out);
+    Ok(out)
+}
+
+/// `current` with the reloadable keys taken from `reread`; returns the applied changes and the
+/// changed keys that were ignored.
+pub fn merge_reloadable(
+    current: &Config,
+    reread: &Config,
+) -> anyhow::Result<(Config, Vec<ConfigChange>, Vec<String>)> {
+    let old = leaves(current)?;
+    let new: std::collections::BTreeMap<String, serde_json::Value> =
+        leaves(reread)?.into_iter().collect();
+    let mut merged = serde_json::to_value(current)?;
+    let mut applied = Vec::new();
+    let mut ignored = Vec::new();
+    for (key, old_value) in &old {
+        let new_value = new.get(key).cloned().unwrap_or(serde_json::Value::Null);
+        if &new_value == old_value {
+            continue;
+        }
+        if !RELOADABLE_KEYS.contains(&key.as_str()) {
+            ignored.push(key.clone());
+            continue;
+        }
+        let slot = key
+            .split('.')
+            .try_fold(&mut merged, |v, part| v.get_mut(part))
+            .context("reloadable key missing from config")?;
+        *slot = new_value.clone();
+        applied.push(ConfigChange {
+            key: key.clone(),
+            old: old_value.clone(),
+            new: new_value,
+        });
+    }
+    // Keys only present in the re-read file (an Option that was unset) are never reloadable.
+    let known: std::collections::BTreeSet<&str> = old.iter().map(|(k, _)| k.as_str()).collect();
+    ignored.extend(new.keys().filter(|k| !known.contains(k.as_str())).cloned());
+
+    let merged: Config = serde_json::from_value(merged).context("rebuild config")?;
+    merged.validate().context("validate reloaded config")?;
+    Ok((merged, applied, ignored))
+}
+
+fn reload_once(
+    source: &ConfigSource,
+    current: &Config,
+) -> anyhow::Result<(Config, Vec<ConfigChange>, Vec<String>)> {
+    let raw = source.read()?;
+    let (reread, _, _) = config::parse_with_env_overrides(&raw).context("parse config")?;
+    reread.validate().context("validate config")?;
+    merge_reloadable(current, &reread)
+}
+
+/// Waits for SIGHUP (Unix only) and publishes each successfully merged config on `tx`.
+pub async fn run_reloader(
+    source: ConfigSource,
+    tx: watch::Sender<Config>,
+    health_tx: mpsc::Sender<HealthLine>,
+    mut shutdown: watch::Receiver<bool>,
+) -> anyhow::Result<()> {
+    #[cfg(unix)]
+    {
+        use tokio::signal::unix::{signal, SignalKind};
+        let mut hup = signal(SignalKind::hangup()).context("install SIGHUP handler")?;
+        loop {
+            tokio::select! {
+                _ = shutdown.changed() => {
+                    if *shutdown.borrow() { break; }
+                }
+                got = hup.recv() => {
+                    if got.is_none() { break; }
+                    let current = tx.borrow().clone();
+                    let (applied, ignored, error) = match reload_once(&source, &current) {
+                        Ok((merged, applied, ignored)) => {
+                            if !applied.is_empty() {
+                                tx.send_replace(merged);
+                            }
+                            (applied, ignored, None)
+                        }
+                        Err(e) => (Vec::new(), Vec::new(), Some(format!("{e:#}"))),
+                    };
+                    for c in &applied {
+                        info!(key = %c.key, old = %c.old, new = %c.new, "config reloaded");
+                    }
+                    if !ignored.is_empty() {
+                        warn!(keys = ?ignored, "SIGHUP: changed keys need a restart; ignored");
+                    }
+                    if let Some(e) = &error {
+                        warn!(error = %e, "SIGHUP: config reload failed; keeping current config");
+                    }
+                    let _ = health_tx.try_send(HealthLine::ConfigReloaded {
+                        ts_ms: now_ms(),
+                        path: source.describe(),
+                        applied,
+                        ignored,
+                        error,
+                    });
+                }
+            }
+        }
+    }
+    #[cfg(not(unix))]
+    {
+        let _ = (source, tx, health_tx);
+        while shutdown.changed().await.is_ok() {
+            if *shutdown.borrow() {
+                break;
+            }
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn merge_applies_reloadable_keys_and_ignores_the_rest() {
+        let current: Config = toml::from_str("[run]\nmarket_ids = [\"1\"]\n").unwrap();
+        let reread: Config = toml::from_str(
+            "[run]\nmarket_ids = [\"2\"]\n[brain]\nmin_net_edge_bps = 25\n\
+             [email]\nto = [\"ops@example.com\"]\n",
+        )
+        .unwrap();
+
+        let (merged, applied, ignored) = merge_reloadable(&current, &reread).unwrap();
+
+        assert_eq!(merged.brain.min_net_edge_bps, 25);
+        assert_eq!(merged.email.to, vec!["ops@example.com".to_string()]);
+        assert_eq!(merged.run.market_ids, vec!["1".to_string()]);
+        let keys: Vec<&str> = applied.iter().map(|c| c.key.as_str()).collect();
+        assert_eq!(keys, ["brain.min_net_edge_bps", "email.to"]);
+        assert_eq!(applied[0].old, serde_json::json!(10));
+        assert_eq!(applied[0].new, serde_json::json!(25));
+        assert_eq!(ignored, ["run.market_ids"]);
+    }
+}
@@ -1,26 +1,61 @@
 use std::collections::HashMap;
+use std::io::{BufRead as _, BufReader};
 use std::path::{Path, PathBuf};
 
 use anyhow::Context as _;
 
-use crate::buckets::{classify_bucket, fill_share_p25};
-use crate::config::Config;
+use crate::accounting::{settle_set_per_leg, SetLeg, SetSettlement};
+use crate::buckets::{fill_share_p25, trade_rate_per_min, BucketClassifier};
+use crate::clock::{Clock as _, SimClock};
+use crate::config::{Config, VolumeMode};
+use crate::fees::FeeRegistry;
 use crate::reasons::{format_notes, ShadowNoteReason};
+use crate::recorder::{csv_input_paths, jsonl_input_paths};
 use crate::report::{generate_report_files, ReportThresholds};
+use crate::rows::{ShadowLegCols, ShadowRow, TradeRow};
 use crate::schema::{
-    FILE_REPORT_JSON, FILE_REPORT_MD, FILE_RUN_CONFIG, FILE_SHADOW_LOG, FILE_SNAPSHOTS,
-    FILE_TRADES, SCHEMA_VERSION, SHADOW_HEADER, SNAPSHOTS_HEADER, TRADES_HEADER,
+    FILE_RAW_WS_JSONL, FILE_REPORT_JSON, FILE_REPORT_MD, FILE_RUN_CONFIG, FILE_SHADOW_LOG,
+    FILE_SNAPSHOTS, FILE_TRADES, SCHEMA_VERSION, SHADOW_HEADER, SNAPSHOTS_HEADER, TRADES_HEADER,
 };
-use crate::types::{Bps, LegSnapshot, MarketSnapshot, Signal, SignalLeg, Strategy, TradeTick};
+use crate::schema_adapt::{FrozenCsvReader, SchemaMode};
+use crate::trade_store::side_matches;
+use crate::types::{
+    Bps, LegSnapshot, MarketDef, MarketId, MarketSnapshot, Price, Qty, Side, Signal, SignalId,
+    SignalLeg, Strategy, TokenId, TradeTick,
+};
+use crate::ws_msg::{self, WsEvent};
 
 pub const FILE_REPLAY_SHADOW_LOG: &str = "replay_shadow_log.csv";
 pub const FILE_REPLAY_REPORT_JSON: &str = "replay_report.json";
 pub const FILE_REPLAY_REPORT_MD: &str = "replay_report.md";
 
+/// Ticks written while rebuilding snapshots from `raw_ws.jsonl`.
+pub const FILE_REPLAY_TICKS: &str = "replay_ticks.csv";
+
+/// Re-parses `raw_ws.jsonl` segments (oldest first) for `markets` through the WS feed's frame
+/// path, writing ticks to the given path; returns every published snapshot with its frame
+/// timestamp (ms); [`crate::feed::replay_raw_ws`] is the implementation.
+pub type RawWsRebuild =
+    fn(&Config, Vec<MarketDef>, &[PathBuf], &Path) -> anyhow::Result<Vec<(u64, MarketSnapshot)>>;
+
+/// Where replay takes its market snapshots from.
+#[derive(Debug, Clone, Copy, Default)]
+pub enum ReplaySource {
+    /// The sampled `snapshots.csv` rows.
+    #[default]
+    SnapshotsCsv,
+    /// `raw_ws.jsonl` rebuilt by the WS parser, so parser bugs show up in the replay.
+    RawWs(RawWsRebuild),
+}
+
 #[derive(Debug, Clone)]
 pub struct ReplayOptions {
     pub out_dir: PathBuf,
     pub replay_run_id: String,
+    pub source: ReplaySource,
+    /// Virtual-clock pacing: 0 = as fast as possible, 1.0 = realtime, 10.0 = ten times faster.
+    /// Only wall time changes; the output is the same at every speed.
+    pub speed: f64,
 }
 
 #[derive(Debug)]
@@ -28,6 +63,7 @@ pub struct ReplayResult {
     pub run_dir: PathBuf,
     pub out_dir: PathBuf,
     pub replay_run_id: String,
+    pub snapshots: u64,
     pub signals: u64,
     pub shadow_rows: u64,
 }
@@ -41,8 +77,9 @@ struct TimedSnapshot {
 #[derive(Debug, Clone, Copy)]
 struct TradeLite {
     ts_ms: u64,
-    price: f64,
-    size: f64,
+    price: Price,
+    size: Qty,
+    side: Option<Side>,
 }
 
 pub fn run_replay(run_dir: &Path, opts: ReplayOptions) -> anyhow::Result<ReplayResult> {
@@ -54,33 +91,66 @@ pub fn run_replay(run_dir: &Path, opts: ReplayOptions) -> anyhow::Result<ReplayR
     let cfg: Config = toml::from_str(&cfg_raw).context("parse run config snapshot")?;
 
     let snapshots_path = run_dir.join(FILE_SNAPSHOTS);
-    let trades_path = run_dir.join(FILE_TRADES);
+    let trades_paths = csv_input_paths(run_dir, FILE_TRADES);
 
-    let snapshots = read_snapshots_csv(&snapshots_path).context("read snapshots.csv")?;
-    let trades_by_key = read_trades_by_key(&trades_path).context("read trades.csv")?;
+    let snapshots = match opts.source {
+        ReplaySource::SnapshotsCsv => {
+            read_snapshots_csv(&snapshots_path).context("read snapshots.csv")?
+        }
+        ReplaySource::RawWs(rebuild) => {
+            let raw_paths = jsonl_input_paths(run_dir, FILE_RAW_WS_JSONL);
+            anyhow::ensure!(
+                !raw_paths.is_empty(),
+                "no {FILE_RAW_WS_JSONL} in {}",
+                run_dir.display()
+            );
+            let markets = recorded_markets(&snapshots_path, &raw_paths)?;
+            let rebuilt = rebuild(
+                &cfg,
+                markets,
+                &raw_paths,
+                &opts.out_dir.join(FILE_REPLAY_TICKS),
+            )
+            .context("rebuild snapshots from raw_ws.jsonl")?;
+            rebuilt
+                .into_iter()
+                .map(|(ts_ms, snapshot)| TimedSnapshot { ts_ms, snapshot })
+                .collect()
+        }
+    };
+    let trades_by_key = read_trades_by_key(&trades_paths).context("read trades.csv")?;
+    let arrivals = trade_arrivals_by_market(&trades_by_key);
 
-    let signals = generate_signals(&cfg, &opts.replay_run_id, &snapshots);
+    anyhow::ensure!(
+        opts.speed.is_finite() && opts.speed >= 0.0,
+        "invalid replay speed {} (must be >= 0)",
+        opts.speed
+    );
 
     let out_shadow_path = opts.out_dir.join(FILE_REPLAY_SHADOW_LOG);
-    write_replay_shadow_log(
+    let counts = run_clocked(
         &cfg,
         &opts.replay_run_id,
         &out_shadow_path,
-        &signals,
+        &snapshots,
         &trades_by_key,
+        &arrivals,
+        opts.speed,
     )
     .context("write replay_shadow_log.csv")?;
 
-    // Generate report.json/md using the existing report generator by symlinking/copying
-    // the replay shadow log into the expected file name.
+    // Generate report.json/md using the existing report generator by aliasing (symlink, hard
+    // link or copy) the replay shadow log under the expected file name.
     let shadow_link = opts.out_dir.join(FILE_SHADOW_LOG);
-    link_or_copy(&out_shadow_path, &shadow_link).context("link shadow_log.csv")?;
+    let how = crate::fs_compat::alias_file(&out_shadow_path, &shadow_link)
+        .with_context(|| format!("alias {}", shadow_link.display()))?;
+    tracing::debug!(how = how.as_str(), "replay shadow_log.csv alias");
 
     let thresholds = ReportThresholds {
         min_total_shadow_pnl: cfg.report.min_total_shadow_pnl,
         min_avg_set_ratio: cfg.report.min_avg_set_ratio,
     };
-    let _report = generate_report_files(&opts.out_dir, &opts.replay_run_id, thresholds)
+    let _report = generate_report_files(&opts.out_dir, &opts.replay_run_id, thresholds, None)
         .context("generate report for replay")?;
 
     let report_json = opts.out_dir.join(FILE_REPORT_JSON);
@@ -94,55 +164,79 @@ pub fn run_replay(run_dir: &Path, opts: ReplayOptions) -> anyhow::Result<ReplayR
         run_dir: run_dir.to_path_buf(),
         out_dir: opts.out_dir,
         replay_run_id: opts.replay_run_id,
-        signals: signals.len() as u64,
-        shadow_rows: signals.len() as u64,
+        snapshots: snapshots.len() as u64,
+        signals: counts.signals,
+        shadow_rows: counts.shadow_rows,
     })
 }
 
-fn generate_signals(cfg: &Config, run_id: &str, snapshots: &[TimedSnapshot]) -> Vec<Signal> {
-    let mut out: Vec<Signal> = Vec::new();
-    let mut next_signal_id: u64 = 1;
-    let mut last_by_key: HashMap<(String, Strategy, i32), u64> = HashMap::new();
+/// Brain's signal rule applied one snapshot at a time, with its cooldown state.
+struct SignalGenerator<'a> {
+    cfg: &'a Config,
+    run_id: &'a str,
+    arrivals: &'a HashMap<MarketId, Vec<u64>>,
+    classifier: BucketClassifier,
+    fees: FeeRegistry,
+    next_signal_id: SignalId,
+    last_by_key: HashMap<(MarketId, Strategy, i32), u64>,
+}
 
-    let cooldown_ms = cfg.brain.signal_cooldown_ms;
-    let min_net_edge = Bps::new(cfg.brain.min_net_edge_bps);
+impl<'a> SignalGenerator<'a> {
+    fn new(cfg: &'a Config, run_id: &'a str, arrivals: &'a HashMap<MarketId, Vec<u64>>) -> Self {
+        Self {
+            cfg,
+            run_id,
+            arrivals,
+            classifier: BucketClassifier::new(&cfg.bucket_classifier)
+                .with_depth_levels(cfg.buckets.depth_levels),
+            fees: FeeRegistry::from_config(&cfg.fees),
+            next_signal_id: SignalId(1),
+            last_by_key: HashMap::new(),
+        }
+    }
 
-    for s in snapshots {
-        let snap = &s.snapshot;
+    /// Evaluates `snap` at virtual time `now_ms`; cooldowns run on the same clock.
+    fn on_snapshot(&mut self, snap: &MarketSnapshot, now_ms: u64) -> Option<Signal> {
+        let cfg = self.cfg;
         let strategy = match snap.legs.len() {
             2 => Strategy::Binary,
             3 => Strategy::Triangle,
-            _ => continue,
+            _ => return None,
         };
 
-        let decision = classify_bucket(snap);
+        let window_ms = cfg.bucket_classifier.v2.window_ms;
+        let trades_per_min = self
+            .arrivals
+            .get(&snap.market_id)
+            .map_or(0.0, |ts| trade_rate_per_min(ts, now_ms, window_ms));
+        let decision = self.classifier.classify(snap, now_ms, trades_per_min);
 
-        let sum_ask: f64 = snap.legs.iter().map(|l| l.best_ask).sum();
-        if !sum_ask.is_finite() || sum_ask <= 0.0 {
-            continue;
+        let sum_ask: Price = snap.legs.iter().map(|l| l.best_ask).sum();
+        if !sum_ask.is_positive() {
+            return None;
         }
 
-        let raw_cost_bps = Bps::from_price_cost(sum_ask);
+        let raw_cost_bps = Bps::from_price_cost(sum_ask.to_f64());
         let raw_edge_bps = Bps::ONE_HUNDRED_PERCENT - raw_cost_bps;
 
-        let hard_fees_bps = Bps::FEE_POLY + Bps::FEE_MERGE;
+        let hard_fees_bps = self.fees.resolve(now_ms).rates.hard_fees_bps();
         let risk_premium_bps = Bps::new(cfg.brain.risk_premium_bps);
         let expected_net_bps = raw_edge_bps - hard_fees_bps - risk_premium_bps;
 
-        if expected_net_bps < min_net_edge {
-            continue;
+        if expected_net_bps < Bps::new(cfg.brain.min_net_edge_bps) {
+            return None;
         }
 
         let rounded_cost_bps = (raw_cost_bps.raw() / 2) * 2;
         let key = (snap.market_id.clone(), strategy, rounded_cost_bps);
-        if let Some(prev_ts) = last_by_key.get(&key) {
-            let elapsed = s.ts_ms.saturating_sub(*prev_ts);
-            if elapsed < cooldown_ms {
-                continue;
+        if let Some(prev_ts) = self.last_by_key.get(&key) {
+            let elapsed = now_ms.saturating_sub(*prev_ts);
+            if elapsed < cfg.brain.signal_cooldown_ms {
+                return None;
             }
         }
 
-        let q_req = cfg.brain.q_req;
+        let q_req = cfg.market(&snap.market_id).q_req;
         let legs: Vec<SignalLeg> = snap
             .legs
             .iter()
@@ -158,10 +252,10 @@ fn generate_signals(cfg: &Config, run_id: &str, snapshots: &[TimedSnapshot]) ->
             })
             .collect();
 
-        out.push(Signal {
-            run_id: run_id.to_string(),
-            signal_id: next_signal_id,
-            signal_ts_ms: s.ts_ms,
+        let signal = Signal {
+            run_id: self.run_id.to_string(),
+            signal_id: self.next_signal_id,
+            signal_ts_ms: now_ms,
             market_id: snap.market_id.clone(),
             strategy,
             bucket: decision.bucket,
@@ -174,22 +268,64 @@ fn generate_signals(cfg: &Config, run_id: &str, snapshots: &[TimedSnapshot]) ->
             expected_net_bps,
             bucket_metrics: decision.metrics,
             legs,
-        });
+        };
 
-        last_by_key.insert(key, s.ts_ms);
-        next_signal_id += 1;
+        self.last_by_key.insert(key, now_ms);
+        self.next_signal_id = self.next_signal_id.next();
+        Some(signal)
     }
+}
 
-    out
+/// Holds wall time to `speed` × virtual time; `speed == 0` never waits.
+struct Pacer {
+    speed: f64,
+    wall_start: std::time::Instant,
+    virtual_start_ms: Option<u64>,
+}
+
+impl Pacer {
+    fn new(speed: f64) -> Self {
+        Self {
+            speed,
+            wall_start: std::time::Instant::now(),
+            virtual_start_ms: None,
+        }
+    }
+
+    fn wait_until(&mut self, virtual_ms: u64) {
+        if self.speed <= 0.0 {
+            return;
+        }
+        let start_ms = *self.virtual_start_ms.get_or_insert(virtual_ms);
+        let due = std::time::Duration::from_secs_f64(
+            virtual_ms.saturating_sub(start_ms) as f64 / 1000.0 / self.speed,
+        );
+        if let Some(wait) = due.checked_sub(self.wall_start.elapsed()) {
+            std::thread::sleep(wait);
+        }
+    }
+}
+
+#[derive(Debug, Default, Clone, Copy)]
+struct ClockedCounts {
+    signals: u64,
+    shadow_rows: u64,
 }
 
-fn write_replay_shadow_log(
+/// Replays snapshots and trades in timestamp order on a [`SimClock`]: trades become visible as
+/// the clock reaches them, signals come from snapshots with the brain's cooldown, and each
+/// signal settles once the clock passes its window end (shadow's `settle_ready` rule). Signals
+/// still open when the input runs out settle with the trades seen so far, as if the clock kept
+/// running.
+fn run_clocked(
     cfg: &Config,
     run_id: &str,
     out_path: &Path,
-    signals: &[Signal],
-    trades_by_key: &HashMap<(String, String), Vec<TradeLite>>,
-) -> anyhow::Result<()> {
+    snapshots: &[TimedSnapshot],
+    trades_by_key: &HashMap<(MarketId, TokenId), Vec<TradeLite>>,
+    arrivals: &HashMap<MarketId, Vec<u64>>,
+    speed: f64,
+) -> anyhow::Result<ClockedCounts> {
     let mut wtr = csv::WriterBuilder::new()
         .has_headers(false)
         .from_path(out_path)
@@ -197,206 +333,279 @@ fn write_replay_shadow_log(
     wtr.write_record(SHADOW_HEADER)
         .context("write replay shadow header")?;
 
-    for s in signals {
-        let legs_n = s.legs.len();
-        if !(2..=3).contains(&legs_n) {
-            continue;
-        }
+    let mut snaps: Vec<&TimedSnapshot> = snapshots.iter().collect();
+    snaps.sort_by_key(|s| s.ts_ms);
+    let mut trades: Vec<(&(MarketId, TokenId), TradeLite)> = trades_by_key
+        .iter()
+        .flat_map(|(key, v)| v.iter().map(move |t| (key, *t)))
+        .collect();
+    trades.sort_by_key(|(_, t)| t.ts_ms);
+
+    let clock = SimClock::starting_at_ms(0);
+    let mut pacer = Pacer::new(speed);
+    let mut generator = SignalGenerator::new(cfg, run_id, arrivals);
+    let fees = FeeRegistry::from_config(&cfg.fees);
+    let mut seen: HashMap<(MarketId, TokenId), Vec<TradeLite>> = HashMap::new();
+    let mut pending: Vec<Signal> = Vec::new();
+    let mut counts = ClockedCounts::default();
+    let (mut si, mut ti) = (0usize, 0usize);
 
-        let window_start_ms = s.signal_ts_ms + cfg.shadow.window_start_ms;
-        let window_end_ms = s.signal_ts_ms + cfg.shadow.window_end_ms;
-
-        let fill_share_used = fill_share_p25(s.bucket, &cfg.buckets);
-
-        let mut legs_sorted = s.legs.clone();
-        legs_sorted.sort_by_key(|l| l.leg_index);
-
-        let mut v_mkt: [f64; 3] = [0.0, 0.0, 0.0];
-        let mut q_fill: [f64; 3] = [0.0, 0.0, 0.0];
+    loop {
+        let next_ms = match (snaps.get(si), trades.get(ti)) {
+            (Some(s), Some((_, t))) => s.ts_ms.min(t.ts_ms),
+            (Some(s), None) => s.ts_ms,
+            (None, Some((_, t))) => t.ts_ms,
+            (None, None) => break,
+        };
+        clock.set_ms(next_ms);
+        pacer.wait_until(next_ms);
+        let now_ms = clock.now_ms();
 
-        let mut invalid_limit = false;
-        for (i, leg) in legs_sorted.iter().take(3).enumerate() {
-            if !leg.limit_price.is_finite() || leg.limit_price <= 0.0 {
-                invalid_limit = true;
-                continue;
-            }
-            let key = (s.market_id.clone(), leg.token_id.clone());
-            if let Some(trades) = trades_by_key.get(&key) {
-                v_mkt[i] = volume_at_or_better_price(
-                    trades,
-                    window_start_ms,
-                    window_end_ms,
-                    leg.limit_price,
-                );
+        while let Some((key, t)) = trades.get(ti).filter(|(_, t)| t.ts_ms == now_ms) {
+            seen.entry((*key).clone()).or_default().push(*t);
+            ti += 1;
+        }
+        while let Some(s) = snaps.get(si).filter(|s| s.ts_ms == now_ms) {
+            if let Some(signal) = generator.on_snapshot(&s.snapshot, now_ms) {
+                counts.signals += 1;
+                pending.push(signal);
             }
-            q_fill[i] = (v_mkt[i] * fill_share_used).min(s.q_req);
+            si += 1;
         }
 
-        let q_set = q_fill[..legs_n]
-            .iter()
-            .copied()
-            .fold(f64::INFINITY, f64::min)
-            .min(s.q_req);
-        let q_set = if q_set.is_finite() { q_set } else { 0.0 };
-
-        let mut cost_set_per_unit: f64 = 0.0;
-        for leg in &legs_sorted {
-            cost_set_per_unit += Bps::FEE_POLY.apply_cost(leg.limit_price);
-        }
-        let cost_set = q_set * cost_set_per_unit;
-        let proceeds_set = q_set * Bps::FEE_MERGE.apply_proceeds(1.0);
-        let pnl_set = proceeds_set - cost_set;
-
-        let dump_slippage_assumed = crate::schema::DUMP_SLIPPAGE_ASSUMED;
-        let mut pnl_left_total: f64 = 0.0;
-        for (i, leg) in legs_sorted.iter().take(3).enumerate() {
-            let q_left = q_fill[i] - q_set;
-            if q_left <= 0.0 {
+        let mut still_pending = Vec::with_capacity(pending.len());
+        for s in pending.drain(..) {
+            if now_ms < s.signal_ts_ms + cfg.market(&s.market_id).window_end_ms {
+                still_pending.push(s);
                 continue;
             }
-            let exit_price = leg.best_bid_at_signal.max(0.0) * (1.0 - dump_slippage_assumed);
-            let proceeds_left_per_unit = Bps::FEE_POLY.apply_proceeds(exit_price);
-            let cost_left_per_unit = Bps::FEE_POLY.apply_cost(leg.limit_price);
-            pnl_left_total += q_left * (proceeds_left_per_unit - cost_left_per_unit);
+            write_replay_row(&mut wtr, cfg, &fees, run_id, &s, &seen)?;
+            counts.shadow_rows += 1;
         }
+        pending = still_pending;
+    }
 
-        let total_pnl = pnl_set + pnl_left_total;
-        let q_fill_avg = q_fill[..legs_n].iter().sum::<f64>() / (legs_n as f64);
-        let set_ratio = if q_fill_avg > 0.0 {
-            q_set / q_fill_avg
-        } else {
-            0.0
-        };
+    for s in &pending {
+        write_replay_row(&mut wtr, cfg, &fees, run_id, s, &seen)?;
+        counts.shadow_rows += 1;
+    }
 
-        let window_stats = window_stats_for_signal(
-            trades_by_key,
-            &s.market_id,
-            &legs_sorted[..legs_n],
-            window_start_ms,
-            window_end_ms,
-        );
+    wtr.flush().context("flush replay shadow_log")?;
+    Ok(counts)
+}
 
-        let mut reasons: Vec<ShadowNoteReason> = s.reasons.clone();
-        if fill_share_used <= 0.0 || !fill_share_used.is_finite() {
-            reasons.push(ShadowNoteReason::FillShareP25Zero);
-        }
-        if s.q_req <= 0.0 || !s.q_req.is_finite() {
-            reasons.push(ShadowNoteReason::InvalidQty);
-        }
-        if invalid_limit {
-            reasons.push(ShadowNoteReason::InvalidPrice);
-        }
+fn write_replay_row(
+    wtr: &mut csv::Writer<std::fs::File>,
+    cfg: &Config,
+    fees: &FeeRegistry,
+    run_id: &str,
+    s: &Signal,
+    trades_by_key: &HashMap<(MarketId, TokenId), Vec<TradeLite>>,
+) -> anyhow::Result<()> {
+    let legs_n = s.legs.len();
+    if !(2..=3).contains(&legs_n) {
+        return Ok(());
+    }
 
-        let mut bid_missing_any = false;
-        let mut book_missing_any = false;
-        for l in &legs_sorted[..legs_n] {
-            let bid_missing = !l.best_bid_at_signal.is_finite() || l.best_bid_at_signal <= 0.0;
-            if bid_missing {
-                bid_missing_any = true;
-                let ask_missing = !l.best_ask_at_signal.is_finite() || l.best_ask_at_signal <= 0.0;
-                if ask_missing {
-                    book_missing_any = true;
-                }
-            }
-        }
-        if bid_missing_any {
-            reasons.push(ShadowNoteReason::MissingBid);
-        }
-        if book_missing_any {
-            reasons.push(ShadowNoteReason::MissingBook);
-        }
+    let market = cfg.market(&s.market_id);
+    let fee_model = fees.resolve(s.signal_ts_ms);
+    let window_start_ms = s.signal_ts_ms + market.window_start_ms;
+    let window_end_ms = s.signal_ts_ms + market.window_end_ms;
 
-        if window_stats.trades_in_window == 0 {
-            reasons.push(ShadowNoteReason::WindowEmpty);
-        }
-        if cfg.shadow.max_trade_gap_ms > 0
-            && window_stats.trades_in_window > 1
-            && window_stats.max_gap_ms > cfg.shadow.max_trade_gap_ms
-        {
-            reasons.push(ShadowNoteReason::WindowDataGap);
-        }
+    let fill_share_used = fill_share_p25(s.bucket, &market.buckets);
+
+    let mut legs_sorted = s.legs.clone();
+    legs_sorted.sort_by_key(|l| l.leg_index);
+    let leg_fill_shares: Vec<f64> = legs_sorted
+        .iter()
+        .map(|l| fill_share_p25(s.leg_bucket(l.leg_index), &market.buckets))
+        .collect();
 
-        let v_mkt_sum: f64 = v_mkt[..legs_n].iter().sum();
-        if v_mkt_sum <= 0.0 {
-            reasons.push(ShadowNoteReason::NoTrades);
+    let mut v_mkt: [f64; 3] = [0.0, 0.0, 0.0];
+    let mut v_mkt_all: [f64; 3] = [0.0, 0.0, 0.0];
+    let mut v_mkt_sided: [f64; 3] = [0.0, 0.0, 0.0];
+
+    let mut invalid_limit = false;
+    for (i, leg) in legs_sorted.iter().take(3).enumerate() {
+        if !leg.limit_price.is_positive() {
+            invalid_limit = true;
+            continue;
         }
+        let key = (s.market_id.clone(), leg.token_id.clone());
+        if let Some(trades) = trades_by_key.get(&key) {
+            let volume = |aggressor| {
+                volume_at_or_better_price(
+                    trades,
+                    window_start_ms,
+                    window_end_ms,
+                    leg.limit_price,
+                    aggressor,
+                )
+                .to_f64()
+            };
+            v_mkt_all[i] = volume(None);
+            v_mkt_sided[i] = volume(Some(leg.side.opposite()));
+            v_mkt[i] = match cfg.shadow.volume_mode {
+                VolumeMode::All => v_mkt_all[i],
+                VolumeMode::Sided => v_mkt_sided[i],
+            };
+        }
+    }
 
-        let bucket_nan = reasons.iter().any(|r| {
-            matches!(
-                r,
-                ShadowNoteReason::BucketThinNan | ShadowNoteReason::BucketLiquidNan
-            )
-        });
-        let worst_leg_token_id = if bucket_nan {
-            String::new()
-        } else {
-            legs_sorted
-                .iter()
-                .find(|l| l.leg_index == s.bucket_metrics.worst_leg_index)
-                .map(|l| l.token_id.clone())
-                .unwrap_or_default()
-        };
+    let set_legs: Vec<SetLeg> = legs_sorted
+        .iter()
+        .zip(&v_mkt)
+        .map(|(l, &v)| SetLeg {
+            p_limit: l.limit_price.to_f64(),
+            best_bid: l.best_bid_at_signal.to_f64(),
+            v_mkt: v,
+        })
+        .collect();
+    let dump_slippage_assumed = crate::schema::DUMP_SLIPPAGE_ASSUMED;
+    let SetSettlement {
+        q_fill,
+        q_set,
+        cost_set,
+        proceeds_set,
+        pnl_set,
+        pnl_left_total,
+        total_pnl,
+        q_fill_avg,
+        set_ratio,
+    } = settle_set_per_leg(
+        s.q_req.to_f64(),
+        &set_legs,
+        &leg_fill_shares,
+        dump_slippage_assumed,
+        fee_model.rates,
+    );
+
+    let window_stats = window_stats_for_signal(
+        trades_by_key,
+        &s.market_id,
+        &legs_sorted[..legs_n],
+        window_start_ms,
+        window_end_ms,
+    );
+
+    let mut reasons: Vec<ShadowNoteReason> = s.reasons.clone();
+    if leg_fill_shares.iter().any(|&f| f <= 0.0 || !f.is_finite()) {
+        reasons.push(ShadowNoteReason::FillShareP25Zero);
+    }
+    if !s.q_req.is_positive() {
+        reasons.push(ShadowNoteReason::InvalidQty);
+    }
+    if invalid_limit {
+        reasons.push(ShadowNoteReason::InvalidPrice);
+    }
 
-        if worst_leg_token_id.is_empty() {
-            match s.bucket {
-                crate::types::LiquidityBucket::Liquid => {
-                    reasons.push(ShadowNoteReason::BucketLiquidNan)
-                }
-                crate::types::LiquidityBucket::Thin => {
-                    reasons.push(ShadowNoteReason::BucketThinNan)
-                }
+    let mut bid_missing_any = false;
+    let mut book_missing_any = false;
+    for l in &legs_sorted[..legs_n] {
+        let bid_missing = !l.best_bid_at_signal.is_positive();
+        if bid_missing {
+            bid_missing_any = true;
+            let ask_missing = !l.best_ask_at_signal.is_positive();
+            if ask_missing {
+                book_missing_any = true;
             }
         }
+    }
+    if bid_missing_any {
+        reasons.push(ShadowNoteReason::MissingBid);
+    }
+    if book_missing_any {
+        reasons.push(ShadowNoteReason::MissingBook);
+    }
 
-        let notes = format_notes(&reasons);
-
-        let mut record: Vec<String> = Vec::with_capacity(SHADOW_HEADER.len());
-        record.push(run_id.to_string());
-        record.push(SCHEMA_VERSION.to_string());
-        record.push(s.signal_id.to_string());
-        record.push(s.signal_ts_ms.to_string());
-        record.push(cfg.shadow.window_start_ms.to_string());
-        record.push(cfg.shadow.window_end_ms.to_string());
-        record.push(s.market_id.clone());
-        record.push(s.strategy.as_str().to_string());
-        record.push(s.bucket.as_str().to_ascii_lowercase());
-        record.push(worst_leg_token_id);
-        record.push(s.q_req.to_string());
-        record.push((legs_n as u8).to_string());
-        record.push(q_set.to_string());
-
-        for i in 0..3 {
-            if i < legs_n {
-                let leg = &s.legs[i];
-                record.push(leg.token_id.clone());
-                record.push(leg.limit_price.to_string());
-                record.push(leg.best_bid_at_signal.to_string());
-                record.push(v_mkt[i].to_string());
-                record.push(q_fill[i].to_string());
-            } else {
-                record.push(String::new());
-                record.push("0".to_string());
-                record.push("0".to_string());
-                record.push("0".to_string());
-                record.push("0".to_string());
+    if window_stats.trades_in_window == 0 {
+        reasons.push(ShadowNoteReason::WindowEmpty);
+    }
+    if cfg.shadow.max_trade_gap_ms > 0
+        && window_stats.trades_in_window > 1
+        && window_stats.max_gap_ms > cfg.shadow.max_trade_gap_ms
+    {
+        reasons.push(ShadowNoteReason::WindowDataGap);
+    }
+
+    let v_mkt_sum: f64 = v_mkt[..legs_n].iter().sum();
+    if v_mkt_sum <= 0.0 {
+        reasons.push(ShadowNoteReason::NoTrades);
+    }
+
+    let bucket_nan = reasons.iter().any(|r| {
+        matches!(
+            r,
+            ShadowNoteReason::BucketThinNan | ShadowNoteReason::BucketLiquidNan
+        )
+    });
+    let worst_leg_token_id = if bucket_nan {
+        String::new()
+    } else {
+        legs_sorted
+            .iter()
+            .find(|l| l.leg_index == s.bucket_metrics.worst_leg_index)
+            .map(|l| l.token_id.to_string())
+            .unwrap_or_default()
+    };
+
+    if worst_leg_token_id.is_empty() {
+        match s.bucket {
+            crate::types::LiquidityBucket::Liquid => {
+                reasons.push(ShadowNoteReason::BucketLiquidNan)
             }
+            crate::types::LiquidityBucket::Thin => reasons.push(ShadowNoteReason::BucketThinNan),
         }
-
-        record.push(cost_set.to_string());
-        record.push(proceeds_set.to_string());
-        record.push(pnl_set.to_string());
-        record.push(pnl_left_total.to_string());
-        record.push(total_pnl.to_string());
-        record.push(q_fill_avg.to_string());
-        record.push(set_ratio.to_string());
-        record.push(fill_share_used.to_string());
-        record.push(dump_slippage_assumed.to_string());
-        record.push(notes);
-        debug_assert_eq!(record.len(), SHADOW_HEADER.len());
-        wtr.write_record(record).context("write replay row")?;
     }
 
-    wtr.flush().context("flush replay shadow_log")?;
+    let notes = format_notes(&reasons);
+
+    let mut row = ShadowRow {
+        run_id: run_id.to_string(),
+        schema_version: SCHEMA_VERSION.to_string(),
+        signal_id: s.signal_id.get(),
+        signal_ts_unix_ms: s.signal_ts_ms,
+        window_start_ms: market.window_start_ms,
+        window_end_ms: market.window_end_ms,
+        market_id: s.market_id.to_string(),
+        strategy: s.strategy.as_str().to_string(),
+        bucket: s.bucket.as_str().to_ascii_lowercase(),
+        bucket_classifier: s.bucket_metrics.classifier.as_str().to_string(),
+        worst_leg_token_id,
+        q_req: s.q_req.to_f64(),
+        legs_n: legs_n as u8,
+        q_set,
+        cost_set,
+        proceeds_set,
+        pnl_set,
+        pnl_left_total,
+        total_pnl,
+        q_fill_avg,
+        set_ratio,
+        fill_share_p25_used: fill_share_used,
+        dump_slippage_assumed,
+        volume_mode: cfg.shadow.volume_mode.as_str().to_string(),
+        fee_model: fee_model.id.clone(),
+        notes,
+        ..Default::default()
+    };
+    for (i, leg) in s.legs.iter().take(legs_n).enumerate() {
+        row.set_leg(
+            i,
+            ShadowLegCols {
+                token_id: leg.token_id.to_string(),
+                p_limit: leg.limit_price.to_f64(),
+                best_bid: leg.best_bid_at_signal.to_f64(),
+                v_mkt: v_mkt[i],
+                v_mkt_all: v_mkt_all[i],
+                v_mkt_sided: v_mkt_sided[i],
+                q_fill: q_fill[i],
+                bucket: s.leg_bucket(leg.leg_index).as_str().to_ascii_lowercase(),
+                fill_share: leg_fill_shares[i],
+            },
+        );
+    }
+    wtr.serialize(&row).context("write replay row")?;
     Ok(())
 }
 
@@ -404,19 +613,20 @@ fn volume_at_or_better_price(
     trades: &[TradeLite],
     start_ms: u64,
     end_ms: u64,
-    price_limit: f64,
-) -> f64 {
-    if start_ms > end_ms || !price_limit.is_finite() {
-        return 0.0;
+    price_limit: Price,
+    aggressor: Option<Side>,
+) -> Qty {
+    if start_ms > end_ms {
+        return Qty::ZERO;
     }
 
     let start_idx = lower_bound(trades, start_ms);
-    let mut vol: f64 = 0.0;
+    let mut vol = Qty::ZERO;
     for t in &trades[start_idx..] {
         if t.ts_ms > end_ms {
             break;
         }
-        if t.price <= price_limit {
+        if t.price <= price_limit && side_matches(t.side, aggressor) {
             vol += t.size;
         }
     }
@@ -438,25 +648,18 @@ fn lower_bound(trades: &[TradeLite], ts_ms: u64) -> usize {
 }
 
 fn read_snapshots_csv(path: &Path) -> anyhow::Result<Vec<TimedSnapshot>> {
-    let mut rdr = csv::ReaderBuilder::new()
-        .flexible(true)
-        .trim(csv::Trim::All)
-        .from_path(path)
-        .with_context(|| format!("open {}", path.display()))?;
-    let header = rdr
-        .headers()
-        .with_context(|| format!("read header {}", path.display()))?
-        .clone();
-    if header.iter().map(|s| s.trim()).collect::<Vec<_>>() != SNAPSHOTS_HEADER {
-        // We keep this strict so that replay is reproducible and errors are explicit.
-        anyhow::bail!("snapshots.csv header mismatch (expected frozen SNAPSHOTS_HEADER)");
-    }
+    let mut rdr = FrozenCsvReader::open(
+        path,
+        FILE_SNAPSHOTS,
+        &SNAPSHOTS_HEADER,
+        SchemaMode::from_env(),
+    )?;
 
     let mut out: Vec<TimedSnapshot> = Vec::new();
     for record in rdr.records() {
         let record = record?;
         let ts_ms = record.get(0).and_then(parse_u64).context("ts_ms")?;
-        let market_id = record.get(1).unwrap_or("").trim().to_string();
+        let market_id = MarketId::from(record.get(1).unwrap_or("").trim());
         let legs_n = record.get(2).and_then(parse_u64).context("legs_n")? as usize;
         if !(2..=3).contains(&legs_n) {
             continue;
@@ -465,20 +668,29 @@ fn read_snapshots_csv(path: &Path) -> anyhow::Result<Vec<TimedSnapshot>> {
         let mut legs: Vec<LegSnapshot> = Vec::with_capacity(legs_n);
         for i in 0..legs_n {
             let base = 3 + i * 4;
-            let token_id = record.get(base).unwrap_or("").trim().to_string();
+            let token_id = TokenId::from(record.get(base).unwrap_or("").trim());
             if token_id.is_empty() {
                 continue;
             }
-            let best_bid = record.get(base + 1).and_then(parse_f64).unwrap_or(0.0);
-            let best_ask = record.get(base + 2).and_then(parse_f64).unwrap_or(1.0);
+            let best_bid = record
+                .get(base + 1)
+                .and_then(parse_f64)
+                .and_then(Price::from_f64)
+                .unwrap_or(Price::MISSING_BID);
+            let best_ask = record
+                .get(base + 2)
+                .and_then(parse_f64)
+                .and_then(Price::from_f64)
+                .unwrap_or(Price::MISSING_ASK);
             let depth3 = record.get(base + 3).and_then(parse_f64).unwrap_or(f64::NAN);
             legs.push(LegSnapshot {
                 token_id,
                 best_bid,
                 best_ask,
-                best_ask_size_best: 0.0,
-                best_bid_size_best: 0.0,
+                best_ask_size_best: Qty::ZERO,
+                best_bid_size_best: Qty::ZERO,
                 ask_depth3_usdc: depth3,
+                asks: Default::default(),
                 ts_recv_us: ts_ms * 1000,
             });
         }
@@ -495,36 +707,104 @@ fn read_snapshots_csv(path: &Path) -> anyhow::Result<Vec<TimedSnapshot>> {
     Ok(out)
 }
 
-fn read_trades_by_key(path: &Path) -> anyhow::Result<HashMap<(String, String), Vec<TradeLite>>> {
-    let mut rdr = csv::ReaderBuilder::new()
-        .flexible(true)
-        .trim(csv::Trim::All)
-        .from_path(path)
-        .with_context(|| format!("open {}", path.display()))?;
-    let header = rdr
-        .headers()
-        .with_context(|| format!("read header {}", path.display()))?
-        .clone();
-    if header.iter().map(|s| s.trim()).collect::<Vec<_>>() != TRADES_HEADER {
-        anyhow::bail!("trades.csv header mismatch (expected frozen TRADES_HEADER)");
-    }
-
-    let mut out: HashMap<(String, String), Vec<TradeLite>> = HashMap::new();
-    for record in rdr.records() {
-        let record = record?;
-        let tick = parse_trade_tick(&record)?;
-        let ts_ms = if tick.ingest_ts_ms > 0 {
-            tick.ingest_ts_ms
-        } else {
-            tick.ts_ms
+/// The run's markets with their tokens in leg order: from `snapshots.csv` when it has rows,
+/// otherwise from the `book` frames of `raw_ws.jsonl` in the order tokens first appear.
+fn recorded_markets(
+    snapshots_path: &Path,
+    raw_paths: &[PathBuf],
+) -> anyhow::Result<Vec<MarketDef>> {
+    fn add(out: &mut Vec<MarketDef>, market_id: &str, token_id: &str) {
+        let idx = match out.iter().position(|m| m.market_id == market_id) {
+            Some(i) => i,
+            None => {
+                out.push(MarketDef {
+                    market_id: market_id.into(),
+                    token_ids: Vec::new(),
+                });
+                out.len() - 1
+            }
         };
-        out.entry((tick.market_id, tick.token_id))
+        let tokens = &mut out[idx].token_ids;
+        if !tokens.iter().any(|t| t == token_id) {
+            tokens.push(token_id.into());
+        }
+    }
+
+    let mut out: Vec<MarketDef> = Vec::new();
+
+    if snapshots_path.is_file() {
+        for s in read_snapshots_csv(snapshots_path).context("read snapshots.csv")? {
+            for leg in &s.snapshot.legs {
+                add(&mut out, &s.snapshot.market_id, &leg.token_id);
+            }
+        }
+    }
+    if out.is_empty() {
+        for path in raw_paths {
+            let f =
+                std::fs::File::open(path).with_context(|| format!("open {}", path.display()))?;
+            for line in BufReader::new(f).lines() {
+                let line = line.with_context(|| format!("read {}", path.display()))?;
+                let _ = ws_msg::for_each_event(&line, |ev| {
+                    if let WsEvent::Book(b) = ev {
+                        if let Some(market) = b.market.as_deref() {
+                            add(&mut out, market, &b.asset_id);
+                        }
+                    }
+                    Ok::<_, ()>(())
+                });
+            }
+        }
+    }
+    out.retain(|m| (2..=3).contains(&m.token_ids.len()));
+    anyhow::ensure!(!out.is_empty(), "no 2- or 3-leg markets recorded");
+    Ok(out)
+}
+
+/// Sorted trade arrival times per market, the v2 classifier's trade-rate input.
+fn trade_arrivals_by_market(
+    trades_by_key: &HashMap<(MarketId, TokenId), Vec<TradeLite>>,
+) -> HashMap<MarketId, Vec<u64>> {
+    let mut out: HashMap<MarketId, Vec<u64>> = HashMap::new();
+    for ((market_id, _), trades) in trades_by_key {
+        out.entry(market_id.clone())
             .or_default()
-            .push(TradeLite {
-                ts_ms,
-                price: tick.price,
-                size: tick.size,
-            });
+            .extend(trades.iter().map(|t| t.ts_ms));
+    }
+    for ts in out.values_mut() {
+        ts.sort_unstable();
+    }
+    out
+}
+
+/// Reads every `trades.csv` of a run: the flat file and/or its hourly partitions.
+fn read_trades_by_key(
+    paths: &[PathBuf],
+) -> anyhow::Result<HashMap<(MarketId, TokenId), Vec<TradeLite>>> {
+    if paths.is_empty() {
+        anyhow::bail!("no trades.csv found (flat or partitioned)");
+    }
+    let mut out: HashMap<(MarketId, TokenId), Vec<TradeLite>> = HashMap::new();
+    for path in paths {
+        let mut rdr =
+            FrozenCsvReader::open(path, FILE_TRADES, &TRADES_HEADER, SchemaMode::from_env())?;
+
+        for row in rdr.deserialize::<TradeRow>() {
+            let tick = TradeTick::from(row.with_context(|| format!("read {}", path.display()))?);
+            let ts_ms = if tick.ingest_ts_ms > 0 {
+                tick.ingest_ts_ms
+            } else {
+                tick.ts_ms
+            };
+            out.entry((tick.market_id, tick.token_id))
+                .or_default()
+                .push(TradeLite {
+                    ts_ms,
+                    price: tick.price,
+                    size: tick.size,
+                    side: tick.side,
+                });
+        }
     }
     for v in out.values_mut() {
         v.sort_by_key(|t| t.ts_ms);
@@ -532,31 +812,9 @@ fn read_trades_by_key(path: &Path) -> anyhow::Result<HashMap<(String, String), V
     Ok(out)
 }
 
-fn parse_trade_tick(record: &csv::StringRecord) -> anyhow::Result<TradeTick> {
-    let ts_ms = record.get(0).and_then(parse_u64).context("ts_ms")?;
-    let market_id = record.get(1).unwrap_or("").trim().to_string();
-    let token_id = record.get(2).unwrap_or("").trim().to_string();
-    let price = record.get(3).and_then(parse_f64).context("price")?;
-    let size = record.get(4).and_then(parse_f64).context("size")?;
-    let trade_id = record.get(5).unwrap_or("").trim().to_string();
-    let ingest_ts_ms = record.get(6).and_then(parse_u64).unwrap_or(ts_ms);
-    let exchange_ts_ms = record.get(7).and_then(parse_u64);
-
-    Ok(TradeTick {
-        ts_ms,
-        ingest_ts_ms,
-        exchange_ts_ms,
-        market_id,
-        token_id,
-        price,
-        size,
-        trade_id,
-    })
-}
-
 fn window_stats_for_signal(
-    trades_by_key: &HashMap<(String, String), Vec<TradeLite>>,
-    market_id: &str,
+    trades_by_key: &HashMap<(MarketId, TokenId), Vec<TradeLite>>,
+    market_id: &MarketId,
     legs: &[SignalLeg],
     start_ms: u64,
     end_ms: u64,
@@ -567,7 +825,7 @@ fn window_stats_for_signal(
 
     let mut leg_trades: Vec<&[TradeLite]> = Vec::with_capacity(legs.len());
     for leg in legs.iter().take(3) {
-        let key = (market_id.to_string(), leg.token_id.clone());
+        let key = (market_id.clone(), leg.token_id.clone());
         if let Some(v) = trades_by_key.get(&key) {
             leg_trades.push(v.as_slice());
         } else {
@@ -627,29 +885,6 @@ fn window_stats_for_signal(
     }
 }
 
-fn link_or_copy(src: &Path, dst: &Path) -> anyhow::Result<()> {
-    if dst.exists() {
-        let _ = std::fs::remove_file(dst);
-    }
-
-    #[cfg(unix)]
-    {
-        use std::os::unix::fs::symlink;
-        let target = src.file_name().unwrap_or_default();
-        if let Err(e) = symlink(target, dst) {
-            tracing::warn!(error = %e, "symlink failed; copying instead");
-            std::fs::copy(src, dst).with_context(|| format!("copy {}", dst.display()))?;
-        }
-        Ok(())
-    }
-
-    #[cfg(not(unix))]
-    {
-        std::fs::copy(src, dst).with_context(|| format!("copy {}", dst.display()))?;
-        Ok(())
-    }
-}
-
 fn parse_u64(s: &str) -> Option<u64> {
     s.trim().parse::<u64>().ok()
 }
@@ -679,7 +914,7 @@ mod tests {
     fn trades_header_is_strict() {
         assert_eq!(
             TRADES_HEADER.join(","),
-            "ts_ms,market_id,token_id,price,size,trade_id,ingest_ts_ms,exchange_ts_ms"
+            "ts_ms,market_id,token_id,price,size,trade_id,ingest_ts_ms,exchange_ts_ms,side"
         );
     }
 }
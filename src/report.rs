@@ -1,9 +1,21 @@
+use std::collections::BTreeMap;
 use std::path::Path;
 
 use anyhow::Context as _;
-use serde::Serialize;
-
-use crate::schema::{FILE_REPORT_JSON, FILE_REPORT_MD, FILE_SHADOW_LOG, SCHEMA_VERSION};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+use crate::api_usage::EndpointTotals;
+use crate::config::GasConfig;
+use crate::fees::FeeRegistry;
+use crate::gas::GasQuote;
+use crate::recorder::DegradedFile;
+use crate::rows::TradeLogRow;
+use crate::schema::{
+    FILE_API_USAGE_JSONL, FILE_REPORT_HTML, FILE_REPORT_JSON, FILE_REPORT_MD, FILE_SHADOW_LOG,
+    FILE_TRADE_LOG, SCHEMA_VERSION,
+};
+use crate::types::Bps;
 
 #[derive(Clone, Copy, Debug)]
 pub struct ReportThresholds {
@@ -20,25 +32,72 @@ impl Default for ReportThresholds {
     }
 }
 
+/// Upper bounds (ms) of the signal-to-settlement delay buckets; one overflow bucket follows.
+pub const SETTLE_DELAY_BUCKETS_MS: [u64; 6] = [50, 100, 250, 500, 1_000, 5_000];
+
+/// How late shadow settled each signal past `signal_ts_ms + window_end_ms`, plus how often the
+/// trade feed had not yet reached the window end at settle time (window possibly truncated).
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize, JsonSchema)]
+pub struct SettleLatencySummary {
+    pub count: u64,
+    pub mean_ms: f64,
+    pub max_ms: u64,
+    /// Counts per `SETTLE_DELAY_BUCKETS_MS` entry, then the overflow bucket.
+    pub hist: Vec<u64>,
+    pub window_partial: u64,
+}
+
 #[derive(Debug, Serialize)]
 pub struct Report {
     pub schema_version: String,
     pub run_id: String,
     pub trade_poll_taker_only: Option<bool>,
+    /// Run tags from `run_meta.json` (`[run.labels]` + `--tag`).
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    pub tags: BTreeMap<String, String>,
     pub period: Period,
     pub totals: Totals,
     pub by_bucket: ByBucket,
+    /// `buckets.depth_levels` the run bucketed with (3 = frozen `ask_depth3_usdc`).
+    pub bucket_depth_levels: usize,
     pub by_strategy: ByStrategy,
     pub worst_20: Vec<WorstEntry>,
     pub verdict: Verdict,
     pub stress: Option<crate::shadow_sweep::StressSummary>,
-
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub settle_latency: Option<SettleLatencySummary>,
+    /// Recorded files the live run stopped writing after an error (`on_write_error = "degrade"`);
+    /// their contents end early.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub degraded_files: Vec<DegradedFile>,
+    /// Requests per API endpoint over the run, from `api_usage.jsonl`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub api_usage: Option<BTreeMap<String, EndpointTotals>>,
+    /// Gateway of the latest live interlock decision in `run_meta.json` (`sim`, `live`, ...).
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub execution_gateway: Option<String>,
+    /// Sniper fills from `trade_log.csv` by `phase` (`SIM` / `LIVE`).
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    pub execution: BTreeMap<String, ExecutionSummary>,
+
+    /// The run's settled rows, for the `report.html` charts.
+    #[serde(skip_serializing)]
+    pub points: Vec<ReportPoint>,
     #[serde(skip_serializing)]
     pub rows_total: u64,
     #[serde(skip_serializing)]
     pub rows_bad: u64,
 }
 
+/// One valid `shadow_log.csv` row of the run, in file order.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ReportPoint {
+    pub signal_ts_ms: u64,
+    pub bucket: String,
+    pub total_pnl: f64,
+    pub set_ratio: f64,
+}
+
 #[derive(Debug, Serialize)]
 pub struct Period {
     pub start_unix_ms: u64,
@@ -94,16 +153,151 @@ pub struct VerdictThresholds {
     pub min_avg_set_ratio: f64,
 }
 
+/// Realized PnL of the sniper's fills, one `phase` of `trade_log.csv`. Per signal the complete
+/// sets held at the end (min net qty over its legs) are merged at `FEE_MERGE`; anything left over
+/// is `open_qty` and not valued. `gas_cost` charges one merge transaction per signal that merged
+/// (at the cost its `MERGE` row recorded, else the static `[gas]` estimate) and one redeem
+/// transaction per signal left with `open_qty`; `net_pnl = pnl - gas_cost`.
+#[derive(Debug, Default, Clone, PartialEq, Serialize)]
+pub struct ExecutionSummary {
+    pub signals: u64,
+    pub fills: u64,
+    pub buy_cost: f64,
+    pub sell_proceeds: f64,
+    pub merged_sets: f64,
+    pub merge_proceeds: f64,
+    pub pnl: f64,
+    pub open_qty: f64,
+    pub merge_txs: u64,
+    pub redeem_txs: u64,
+    pub gas_cost: f64,
+    pub net_pnl: f64,
+}
+
+/// Summarizes `trade_log.csv` per phase; empty when the file is missing (dry_run).
+pub fn summarize_trade_log(path: &Path) -> anyhow::Result<BTreeMap<String, ExecutionSummary>> {
+    if !path.exists() {
+        return Ok(BTreeMap::new());
+    }
+    let mut rdr = csv::ReaderBuilder::new()
+        .trim(csv::Trim::All)
+        .from_path(path)
+        .with_context(|| format!("open {}", path.display()))?;
+    // A crash can leave a torn last line.
+    let rows: Vec<TradeLogRow> = rdr.deserialize().flatten().collect();
+    let run_dir = path.parent().unwrap_or(Path::new("."));
+    let fees = FeeRegistry::for_run_dir(run_dir);
+    let gas = crate::gas::config_for_run_dir(run_dir);
+    Ok(summarize_execution(&rows, &fees, &gas))
+}
+
+struct SignalNet {
+    legs: usize,
+    /// Net qty per leg_index.
+    net: BTreeMap<i32, f64>,
+    /// Merge rate in force at the signal's first row.
+    merge: Bps,
+    /// Gas the sniper recorded on the signal's `MERGE` row.
+    merge_gas: Option<f64>,
+}
+
+fn summarize_execution(
+    rows: &[TradeLogRow],
+    fees: &FeeRegistry,
+    gas: &GasConfig,
+) -> BTreeMap<String, ExecutionSummary> {
+    let mut signals: BTreeMap<(String, u64), SignalNet> = BTreeMap::new();
+    let mut out: BTreeMap<String, ExecutionSummary> = BTreeMap::new();
+    for r in rows {
+        let legs = if r.strategy.eq_ignore_ascii_case("triangle") {
+            3
+        } else {
+            2
+        };
+        let sig = signals
+            .entry((r.phase.clone(), r.signal_id))
+            .or_insert_with(|| SignalNet {
+                legs,
+                net: BTreeMap::new(),
+                merge: fees.resolve(r.ts_ms).rates.merge,
+                merge_gas: None,
+            });
+        let summary = out.entry(r.phase.clone()).or_default();
+        if r.action == "MERGE" {
+            sig.merge_gas = crate::gas::parse_gas_usdc(&r.notes);
+            continue;
+        }
+        if r.fill_qty <= 0.0 || r.leg_index < 0 {
+            continue;
+        }
+        summary.fills += 1;
+        let net = sig.net.entry(r.leg_index).or_default();
+        let taker = fees.resolve(r.ts_ms).rates.taker;
+        if r.side.eq_ignore_ascii_case("SELL") {
+            summary.sell_proceeds += taker.apply_proceeds(r.limit_price) * r.fill_qty;
+            *net -= r.fill_qty;
+        } else {
+            summary.buy_cost += taker.apply_cost(r.limit_price) * r.fill_qty;
+            *net += r.fill_qty;
+        }
+    }
+    let static_quote = GasQuote::fixed(gas);
+    for ((phase, _), sig) in signals {
+        let summary = out.entry(phase).or_default();
+        summary.signals += 1;
+        let q_set = if sig.net.len() == sig.legs {
+            sig.net
+                .values()
+                .copied()
+                .fold(f64::INFINITY, f64::min)
+                .max(0.0)
+        } else {
+            0.0
+        };
+        summary.merged_sets += q_set;
+        summary.merge_proceeds += q_set * sig.merge.apply_proceeds(1.0);
+        if q_set > 0.0 {
+            summary.merge_txs += 1;
+            summary.gas_cost += sig
+                .merge_gas
+                .unwrap_or_else(|| crate::gas::merge_cost_usdc(gas, static_quote));
+        }
+        let open: f64 = sig.net.values().map(|q| (q - q_set).max(0.0)).sum();
+        summary.open_qty += open;
+        if open > 0.0 {
+            summary.redeem_txs += 1;
+            summary.gas_cost += crate::gas::redeem_cost_usdc(gas, static_quote);
+        }
+    }
+    for s in out.values_mut() {
+        s.pnl = s.merge_proceeds + s.sell_proceeds - s.buy_cost;
+        s.net_pnl = s.pnl - s.gas_cost;
+    }
+    out
+}
+
+/// `settle_latency` comes from the live run's health counters; replays pass `None`.
 pub fn generate_report_files(
     data_dir: &Path,
     run_id: &str,
     thresholds: ReportThresholds,
+    settle_latency: Option<SettleLatencySummary>,
 ) -> anyhow::Result<Report> {
     let shadow_path = data_dir.join(FILE_SHADOW_LOG);
 
     let mut report = compute_report(&shadow_path, run_id, thresholds)?;
+    report.settle_latency = settle_latency;
+    report.degraded_files = crate::recorder::degraded_files();
+    report.api_usage = crate::api_usage::summarize_file(&data_dir.join(FILE_API_USAGE_JSONL));
+    report.execution = summarize_trade_log(&data_dir.join(FILE_TRADE_LOG))?;
+    report.bucket_depth_levels = crate::buckets::depth_levels_for_run_dir(data_dir);
     if let Ok(meta) = crate::run_meta::RunMeta::read_from_dir(data_dir) {
         report.trade_poll_taker_only = meta.trade_poll_taker_only;
+        report.tags = meta.tags;
+        report.execution_gateway = meta
+            .live_interlocks
+            .last()
+            .map(|d| d.gateway.as_str().to_string());
     }
     write_report_files(data_dir, &report)?;
 
@@ -113,6 +307,7 @@ pub fn generate_report_files(
 pub fn write_report_files(data_dir: &Path, report: &Report) -> anyhow::Result<()> {
     let out_json = data_dir.join(FILE_REPORT_JSON);
     let out_md = data_dir.join(FILE_REPORT_MD);
+    let out_html = data_dir.join(FILE_REPORT_HTML);
 
     let json = serde_json::to_vec_pretty(report).context("serialize report.json")?;
     std::fs::write(&out_json, json).with_context(|| format!("write {}", out_json.display()))?;
@@ -121,6 +316,10 @@ pub fn write_report_files(data_dir: &Path, report: &Report) -> anyhow::Result<()
     std::fs::write(&out_md, md.as_bytes())
         .with_context(|| format!("write {}", out_md.display()))?;
 
+    let html = crate::report_html::render_report_html(report);
+    std::fs::write(&out_html, html.as_bytes())
+        .with_context(|| format!("write {}", out_html.display()))?;
+
     Ok(())
 }
 
@@ -135,6 +334,7 @@ pub fn compute_report(
             schema_version: SCHEMA_VERSION.to_string(),
             run_id: run_id.to_string(),
             trade_poll_taker_only: None,
+            tags: BTreeMap::new(),
             period: Period {
                 start_unix_ms: 0,
                 end_unix_ms: 0,
@@ -145,6 +345,7 @@ pub fn compute_report(
                 avg_set_ratio: 0.0,
             },
             by_bucket: ByBucket::default(),
+            bucket_depth_levels: crate::buckets::FROZEN_DEPTH_LEVELS,
             by_strategy: ByStrategy::default(),
             worst_20: Vec::new(),
             verdict: Verdict {
@@ -159,6 +360,12 @@ pub fn compute_report(
                 },
             },
             stress: None,
+            settle_latency: None,
+            degraded_files: Vec::new(),
+            api_usage: None,
+            execution_gateway: None,
+            execution: BTreeMap::new(),
+            points: Vec::new(),
             rows_total: 0,
             rows_bad: 0,
         });
@@ -193,6 +400,7 @@ pub fn compute_report(
     let mut acc_strategy_triangle = Accum::default();
 
     let mut worst: Vec<WorstEntry> = Vec::new();
+    let mut points: Vec<ReportPoint> = Vec::new();
 
     for record in rdr.records() {
         rows_total += 1;
@@ -256,6 +464,12 @@ pub fn compute_report(
                     _ => unreachable!("validated strategy"),
                 }
 
+                points.push(ReportPoint {
+                    signal_ts_ms: r.signal_ts_ms,
+                    bucket: bucket.to_string(),
+                    total_pnl: r.total_pnl,
+                    set_ratio: r.set_ratio,
+                });
                 worst.push(WorstEntry {
                     signal_id: r.signal_id,
                     market_id: r.market_id,
@@ -301,6 +515,7 @@ pub fn compute_report(
         schema_version: SCHEMA_VERSION.to_string(),
         run_id: run_id.to_string(),
         trade_poll_taker_only: None,
+        tags: BTreeMap::new(),
         period: Period {
             start_unix_ms: min_ts.unwrap_or(0),
             end_unix_ms: max_ts.unwrap_or(0),
@@ -314,6 +529,7 @@ pub fn compute_report(
             liquid: acc_bucket_liquid.finish(),
             thin: acc_bucket_thin.finish(),
         },
+        bucket_depth_levels: crate::buckets::FROZEN_DEPTH_LEVELS,
         by_strategy: ByStrategy {
             binary: acc_strategy_binary.finish(),
             triangle: acc_strategy_triangle.finish(),
@@ -328,6 +544,12 @@ pub fn compute_report(
             },
         },
         stress,
+        settle_latency: None,
+        degraded_files: Vec::new(),
+        api_usage: None,
+        execution_gateway: None,
+        execution: BTreeMap::new(),
+        points,
         rows_total,
         rows_bad,
     })
@@ -384,6 +606,12 @@ fn render_report_md(report: &Report) -> String {
             .map(|v| v.to_string())
             .unwrap_or_else(|| "unknown".to_string())
     ));
+    if !report.tags.is_empty() {
+        out.push_str(&format!(
+            "tags: `{}`\n\n",
+            crate::run_meta::format_tags(&report.tags)
+        ));
+    }
     out.push_str(&format!(
         "period: {} .. {}\n\n",
         report.period.start_unix_ms, report.period.end_unix_ms
@@ -404,6 +632,85 @@ fn render_report_md(report: &Report) -> String {
         report.rows_bad, report.rows_total
     ));
 
+    if !report.degraded_files.is_empty() {
+        out.push_str("## Warnings\n\n");
+        for d in &report.degraded_files {
+            out.push_str(&format!(
+                "- recorder stopped writing `{}` at {} after a write error (on_write_error=degrade); it is incomplete: {}\n",
+                d.file, d.ts_ms, d.error
+            ));
+        }
+        out.push('\n');
+    }
+
+    if let Some(usage) = report.api_usage.as_ref() {
+        out.push_str("## API usage\n\n");
+        out.push_str("| endpoint | requests | peak_hour | peak_10s | http_429 | http_4xx | http_5xx | transport_errors |\n");
+        out.push_str("|---|---:|---:|---:|---:|---:|---:|---:|\n");
+        for (endpoint, t) in usage {
+            out.push_str(&format!(
+                "| {endpoint} | {} | {} | {} | {} | {} | {} | {} |\n",
+                t.requests,
+                t.peak_hour,
+                t.peak_10s,
+                t.http_429,
+                t.http_4xx,
+                t.http_5xx,
+                t.transport_errors
+            ));
+        }
+        out.push('\n');
+    }
+
+    if !report.execution.is_empty() {
+        out.push_str("## Execution PnL (trade_log)\n\n");
+        if let Some(gw) = report.execution_gateway.as_deref() {
+            out.push_str(&format!("gateway: `{gw}`\n\n"));
+        }
+        out.push_str("| phase | signals | fills | buy_cost | sell_proceeds | merged_sets | merge_proceeds | pnl | open_qty | gas_cost | net_pnl |\n");
+        out.push_str("|---|---:|---:|---:|---:|---:|---:|---:|---:|---:|---:|\n");
+        for (phase, e) in &report.execution {
+            out.push_str(&format!(
+                "| {phase} | {} | {} | {:.6} | {:.6} | {:.6} | {:.6} | {:.6} | {:.6} | {:.6} | {:.6} |\n",
+                e.signals,
+                e.fills,
+                e.buy_cost,
+                e.sell_proceeds,
+                e.merged_sets,
+                e.merge_proceeds,
+                e.pnl,
+                e.open_qty,
+                e.gas_cost,
+                e.net_pnl
+            ));
+        }
+        out.push('\n');
+    }
+
+    if let Some(lat) = report.settle_latency.as_ref() {
+        out.push_str("## Settle latency (signal window end -> settle)\n\n");
+        out.push_str(&format!(
+            "- settled: {} (mean {:.1}ms, max {}ms)\n",
+            lat.count, lat.mean_ms, lat.max_ms
+        ));
+        out.push_str(&format!(
+            "- window_partial (trade feed behind window end at settle): {}\n\n",
+            lat.window_partial
+        ));
+        out.push_str("| delay_ms | signals |\n|---|---:|\n");
+        for (i, n) in lat.hist.iter().enumerate() {
+            let label = match SETTLE_DELAY_BUCKETS_MS.get(i) {
+                Some(ub) => format!("<= {ub}"),
+                None => format!(
+                    "> {}",
+                    SETTLE_DELAY_BUCKETS_MS[SETTLE_DELAY_BUCKETS_MS.len() - 1]
+                ),
+            };
+            out.push_str(&format!("| {label} | {n} |\n"));
+        }
+        out.push('\n');
+    }
+
     if let Some(stress) = report.stress.as_ref() {
         out.push_str("## Stress (does NOT change verdict)\n\n");
         out.push_str("| variant | rows_ok | rows_bad | total_pnl_sum | avg_set_ratio | legging_rate | worst_20_pnl_sum |\n");
@@ -436,7 +743,7 @@ fn render_report_md(report: &Report) -> String {
             stress.fill_share_x0_70.worst_20_pnl_sum,
         ));
         out.push_str(&format!(
-            "| dump=0.10 & fill*0.70 | {} | {} | {:.6} | {:.6} | {:.6} | {:.6} |\n\n",
+            "| dump=0.10 & fill*0.70 | {} | {} | {:.6} | {:.6} | {:.6} | {:.6} |\n",
             stress.dump_0_10_fill_share_x0_70.rows_ok,
             stress.dump_0_10_fill_share_x0_70.rows_bad,
             stress.dump_0_10_fill_share_x0_70.total_pnl_sum,
@@ -444,9 +751,33 @@ fn render_report_md(report: &Report) -> String {
             stress.dump_0_10_fill_share_x0_70.legging_rate,
             stress.dump_0_10_fill_share_x0_70.worst_20_pnl_sum,
         ));
+        out.push_str(&format!(
+            "| volume=all | {} | {} | {:.6} | {:.6} | {:.6} | {:.6} |\n",
+            stress.volume_all.rows_ok,
+            stress.volume_all.rows_bad,
+            stress.volume_all.total_pnl_sum,
+            stress.volume_all.set_ratio_avg,
+            stress.volume_all.legging_rate,
+            stress.volume_all.worst_20_pnl_sum,
+        ));
+        out.push_str(&format!(
+            "| volume=sided | {} | {} | {:.6} | {:.6} | {:.6} | {:.6} |\n\n",
+            stress.volume_sided.rows_ok,
+            stress.volume_sided.rows_bad,
+            stress.volume_sided.total_pnl_sum,
+            stress.volume_sided.set_ratio_avg,
+            stress.volume_sided.legging_rate,
+            stress.volume_sided.worst_20_pnl_sum,
+        ));
     }
 
     out.push_str("## By Bucket\n\n");
+    if report.bucket_depth_levels != crate::buckets::FROZEN_DEPTH_LEVELS {
+        out.push_str(&format!(
+            "leg depth: top-{} asks from the L2 book (`buckets.depth_levels`)\n\n",
+            report.bucket_depth_levels
+        ));
+    }
     out.push_str("| bucket | signals | pnl | avg_set_ratio |\n");
     out.push_str("|---|---:|---:|---:|\n");
     out.push_str(&format!(
@@ -0,0 +1,436 @@
+//! `report.html`: the Day14 verdict as one self-contained page for reviewers who don't read
+//! CSV. The charts are inline SVG built from the run's `shadow_log.csv` rows
+//! (`Report::points`); no scripts or external assets, so the page renders offline and as a
+//! mail attachment.
+
+use std::fmt::Write as _;
+
+use crate::report::{BucketStats, Report, ReportPoint};
+use crate::types::civil_from_days;
+
+const CHART_W: f64 = 640.0;
+const CHART_H: f64 = 220.0;
+/// Room for the axis labels left of and below the plot area.
+const PAD_LEFT: f64 = 64.0;
+const PAD_BOTTOM: f64 = 24.0;
+const PAD_TOP: f64 = 12.0;
+/// Bins of the set_ratio histogram over `[0, 1]`; a ratio of 1 lands in the last bin.
+pub const SET_RATIO_BINS: usize = 10;
+
+const STYLE: &str = "body{font-family:system-ui,sans-serif;margin:2em auto;max-width:720px;\
+color:#222}h1{font-size:1.4em}h2{font-size:1.1em;margin-top:1.6em}table{border-collapse:\
+collapse}td,th{border:1px solid #ccc;padding:2px 8px;text-align:right}td:first-child,\
+th:first-child{text-align:left}.go{background:#1a7f37}.nogo{background:#c62828}.verdict{\
+color:#fff;display:inline-block;padding:4px 14px;font-weight:bold;border-radius:4px}\
+.muted{color:#666}svg text{font-size:11px;fill:#444}";
+
+pub fn render_report_html(report: &Report) -> String {
+    let mut out = String::new();
+    let (class, label) = if report.verdict.go {
+        ("go", "GO")
+    } else {
+        ("nogo", "NO GO")
+    };
+
+    let _ = write!(
+        out,
+        "<!DOCTYPE html>\n<html lang=\"en\"><head><meta charset=\"utf-8\">\
+         <title>Razor report {run_id}</title><style>{STYLE}</style></head><body>\n\
+         <h1>Razor Day14 report</h1>\n\
+         <p class=\"muted\">run_id <code>{run_id}</code> &middot; schema {schema}</p>\n",
+        run_id = escape(&report.run_id),
+        schema = escape(&report.schema_version),
+    );
+    if !report.tags.is_empty() {
+        let _ = writeln!(
+            out,
+            "<p class=\"muted\">tags <code>{}</code></p>",
+            escape(&crate::run_meta::format_tags(&report.tags))
+        );
+    }
+    let _ = writeln!(out, "<p><span class=\"verdict {class}\">{label}</span></p>");
+    out.push_str("<ul>\n");
+    for r in &report.verdict.reasons {
+        let _ = writeln!(out, "<li>{}</li>", escape(r));
+    }
+    out.push_str("</ul>\n");
+    let _ = writeln!(
+        out,
+        "<p class=\"muted\">thresholds: min_total_shadow_pnl={}, min_avg_set_ratio={}</p>",
+        report.verdict.thresholds.min_total_shadow_pnl, report.verdict.thresholds.min_avg_set_ratio
+    );
+
+    out.push_str("<h2>Totals</h2>\n<table>\n");
+    let period = if report.totals.signals > 0 {
+        format!(
+            "{} &ndash; {}",
+            fmt_utc(report.period.start_unix_ms),
+            fmt_utc(report.period.end_unix_ms)
+        )
+    } else {
+        "&ndash;".to_string()
+    };
+    for (name, value) in [
+        ("period", period),
+        ("signals", report.totals.signals.to_string()),
+        (
+            "total_shadow_pnl",
+            format!("{:.6}", report.totals.total_shadow_pnl),
+        ),
+        (
+            "avg_set_ratio",
+            format!("{:.6}", report.totals.avg_set_ratio),
+        ),
+        (
+            "bad_rows",
+            format!("{} / {}", report.rows_bad, report.rows_total),
+        ),
+    ] {
+        let _ = writeln!(out, "<tr><th>{name}</th><td>{value}</td></tr>");
+    }
+    out.push_str("</table>\n");
+
+    if !report.degraded_files.is_empty() {
+        out.push_str("<h2>Warnings</h2>\n<ul>\n");
+        for d in &report.degraded_files {
+            let _ = writeln!(
+                out,
+                "<li>recorder stopped writing <code>{}</code> at {} after a write error; it is \
+                 incomplete: {}</li>",
+                escape(&d.file),
+                d.ts_ms,
+                escape(&d.error)
+            );
+        }
+        out.push_str("</ul>\n");
+    }
+
+    out.push_str("<h2>Cumulative shadow PnL</h2>\n");
+    out.push_str(&cumulative_pnl_svg(&report.points));
+
+    out.push_str("<h2>set_ratio distribution</h2>\n");
+    out.push_str(&histogram_svg(
+        &set_ratio_histogram(&report.points),
+        report.verdict.thresholds.min_avg_set_ratio,
+    ));
+
+    out.push_str("<h2>By bucket</h2>\n");
+    let buckets = [
+        ("liquid", &report.by_bucket.liquid),
+        ("thin", &report.by_bucket.thin),
+    ];
+    out.push_str(&bucket_pnl_svg(&buckets));
+    stats_table(&mut out, "bucket", &buckets);
+
+    out.push_str("<h2>By strategy</h2>\n");
+    stats_table(
+        &mut out,
+        "strategy",
+        &[
+            ("binary", &report.by_strategy.binary),
+            ("triangle", &report.by_strategy.triangle),
+        ],
+    );
+
+    out.push_str(
+        "<h2>Worst 20</h2>\n<table>\n<tr><th>#</th><th>signal_id</th><th>market_id</th>\
+         <th>strategy</th><th>bucket</th><th>total_pnl</th><th>set_ratio</th></tr>\n",
+    );
+    for (i, w) in report.worst_20.iter().enumerate() {
+        let _ = writeln!(
+            out,
+            "<tr><td>{}</td><td>{}</td><td>{}</td><td>{}</td><td>{}</td><td>{:.6}</td>\
+             <td>{:.6}</td></tr>",
+            i + 1,
+            w.signal_id,
+            escape(&w.market_id),
+            escape(&w.strategy),
+            escape(&w.bucket),
+            w.total_pnl,
+            w.set_ratio
+        );
+    }
+    out.push_str("</table>\n</body></html>\n");
+    out
+}
+
+/// Rows per `SET_RATIO_BINS` bin of `[0, 1]`; out-of-range ratios are clamped into the ends.
+pub fn set_ratio_histogram(points: &[ReportPoint]) -> [u64; SET_RATIO_BINS] {
+    let mut bins = [0u64; SET_RATIO_BINS];
+    for p in points {
+        let idx = (p.set_ratio.clamp(0.0, 1.0) * SET_RATIO_BINS as f64) as usize;
+        bins[idx.min(SET_RATIO_BINS - 1)] += 1;
+    }
+    bins
+}
+
+fn cumulative_pnl_svg(points: &[ReportPoint]) -> String {
+    if points.is_empty() {
+        return empty_chart();
+    }
+    let mut sorted: Vec<&ReportPoint> = points.iter().collect();
+    sorted.sort_by_key(|p| p.signal_ts_ms);
+    let mut cum = 0.0;
+    let series: Vec<(u64, f64)> = sorted
+        .iter()
+        .map(|p| {
+            cum += p.total_pnl;
+            (p.signal_ts_ms, cum)
+        })
+        .collect();
+
+    let (t0, t1) = (series[0].0, series[series.len() - 1].0);
+    let lo = series.iter().map(|(_, v)| *v).fold(0.0, f64::min);
+    let hi = series.iter().map(|(_, v)| *v).fold(0.0, f64::max);
+    let x = |t: u64| scale(t as f64, t0 as f64, t1 as f64, PAD_LEFT, CHART_W);
+    let y = |v: f64| scale(v, lo, hi, CHART_H - PAD_BOTTOM, PAD_TOP);
+
+    let mut svg = svg_open();
+    let _ = write!(
+        svg,
+        "<line x1=\"{PAD_LEFT}\" y1=\"{z:.1}\" x2=\"{CHART_W}\" y2=\"{z:.1}\" stroke=\"#999\" \
+         stroke-dasharray=\"4 3\"/>",
+        z = y(0.0)
+    );
+    svg.push_str("<polyline fill=\"none\" stroke=\"#1565c0\" stroke-width=\"1.5\" points=\"");
+    for (t, v) in &series {
+        let _ = write!(svg, "{:.1},{:.1} ", x(*t), y(*v));
+    }
+    svg.push_str("\"/>");
+    let _ = write!(
+        svg,
+        "<text x=\"4\" y=\"{:.1}\">{hi:.2}</text><text x=\"4\" y=\"{:.1}\">{lo:.2}</text>\
+         <text x=\"{PAD_LEFT}\" y=\"{CHART_H}\">{}</text>\
+         <text x=\"{CHART_W}\" y=\"{CHART_H}\" text-anchor=\"end\">{}</text>",
+        y(hi) + 4.0,
+        y(lo),
+        fmt_utc(t0),
+        fmt_utc(t1)
+    );
+    svg.push_str("</svg>\n");
+    svg
+}
+
+fn histogram_svg(bins: &[u64; SET_RATIO_BINS], threshold: f64) -> String {
+    let max = bins.iter().copied().max().unwrap_or(0);
+    if max == 0 {
+        return empty_chart();
+    }
+    let plot_w = CHART_W - PAD_LEFT;
+    let bar_w = plot_w / SET_RATIO_BINS as f64;
+    let y = |n: u64| scale(n as f64, 0.0, max as f64, CHART_H - PAD_BOTTOM, PAD_TOP);
+
+    let mut svg = svg_open();
+    for (i, n) in bins.iter().enumerate() {
+        let x0 = PAD_LEFT + i as f64 * bar_w;
+        let top = y(*n);
+        let _ = write!(
+            svg,
+            "<rect x=\"{:.1}\" y=\"{top:.1}\" width=\"{:.1}\" height=\"{:.1}\" fill=\"#1565c0\">\
+             <title>{:.1}&ndash;{:.1}: {n}</title></rect>",
+            x0 + 1.0,
+            bar_w - 2.0,
+            (CHART_H - PAD_BOTTOM - top).max(0.0),
+            i as f64 / SET_RATIO_BINS as f64,
+            (i + 1) as f64 / SET_RATIO_BINS as f64,
+        );
+    }
+    let tx = PAD_LEFT + threshold.clamp(0.0, 1.0) * plot_w;
+    let _ = write!(
+        svg,
+        "<line x1=\"{tx:.1}\" y1=\"{PAD_TOP}\" x2=\"{tx:.1}\" y2=\"{:.1}\" stroke=\"#c62828\" \
+         stroke-dasharray=\"4 3\"/>\
+         <text x=\"4\" y=\"{:.1}\">{max}</text><text x=\"4\" y=\"{:.1}\">0</text>\
+         <text x=\"{PAD_LEFT}\" y=\"{CHART_H}\">0.0</text>\
+         <text x=\"{CHART_W}\" y=\"{CHART_H}\" text-anchor=\"end\">1.0</text>",
+        CHART_H - PAD_BOTTOM,
+        PAD_TOP + 4.0,
+        CHART_H - PAD_BOTTOM,
+    );
+    svg.push_str("</svg>\n");
+    svg
+}
+
+/// Horizontal PnL bar per bucket around a zero axis.
+fn bucket_pnl_svg(buckets: &[(&str, &BucketStats)]) -> String {
+    if buckets.iter().all(|(_, b)| b.signals == 0) {
+        return empty_chart();
+    }
+    let lo = buckets.iter().map(|(_, b)| b.pnl).fold(0.0, f64::min);
+    let hi = buckets.iter().map(|(_, b)| b.pnl).fold(0.0, f64::max);
+    let x = |v: f64| scale(v, lo, hi, PAD_LEFT, CHART_W - PAD_LEFT);
+    let row_h = 32.0;
+    let height = row_h * buckets.len() as f64 + PAD_TOP;
+
+    let mut svg = format!(
+        "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{CHART_W}\" height=\"{height}\" \
+         viewBox=\"0 0 {CHART_W} {height}\">"
+    );
+    let z = x(0.0);
+    for (i, (name, b)) in buckets.iter().enumerate() {
+        let top = PAD_TOP + i as f64 * row_h;
+        let (x0, x1) = if b.pnl < 0.0 {
+            (x(b.pnl), z)
+        } else {
+            (z, x(b.pnl))
+        };
+        let fill = if b.pnl < 0.0 { "#c62828" } else { "#1a7f37" };
+        let _ = write!(
+            svg,
+            "<text x=\"4\" y=\"{:.1}\">{name}</text>\
+             <rect x=\"{x0:.1}\" y=\"{top:.1}\" width=\"{:.1}\" height=\"{:.1}\" fill=\"{fill}\"/>\
+             <text x=\"{:.1}\" y=\"{:.1}\">{:.4} ({} signals)</text>",
+            top + row_h / 2.0,
+            (x1 - x0).max(1.0),
+            row_h - 10.0,
+            x1.max(z) + 4.0,
+            top + row_h / 2.0,
+            b.pnl,
+            b.signals
+        );
+    }
+    let _ = write!(
+        svg,
+        "<line x1=\"{z:.1}\" y1=\"{PAD_TOP}\" x2=\"{z:.1}\" y2=\"{height}\" stroke=\"#999\"/>"
+    );
+    svg.push_str("</svg>\n");
+    svg
+}
+
+fn stats_table(out: &mut String, key: &str, rows: &[(&str, &BucketStats)]) {
+    let _ = writeln!(
+        out,
+        "<table>\n<tr><th>{key}</th><th>signals</th><th>pnl</th><th>avg_set_ratio</th></tr>"
+    );
+    for (name, b) in rows {
+        let _ = writeln!(
+            out,
+            "<tr><td>{name}</td><td>{}</td><td>{:.6}</td><td>{:.6}</td></tr>",
+            b.signals, b.pnl, b.avg_set_ratio
+        );
+    }
+    out.push_str("</table>\n");
+}
+
+fn svg_open() -> String {
+    format!(
+        "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{CHART_W}\" height=\"{CHART_H}\" \
+         viewBox=\"0 0 {CHART_W} {CHART_H}\">"
+    )
+}
+
+fn empty_chart() -> String {
+    "<p class=\"muted\">No settled signals.</p>\n".to_string()
+}
+
+/// Maps `v` from `[lo, hi]` onto `[out_lo, out_hi]`; a flat range maps to the midpoint.
+fn scale(v: f64, lo: f64, hi: f64, out_lo: f64, out_hi: f64) -> f64 {
+    if hi - lo <= f64::EPSILON {
+        return (out_lo + out_hi) / 2.0;
+    }
+    out_lo + (v - lo) / (hi - lo) * (out_hi - out_lo)
+}
+
+/// `YYYY-MM-DD HH:MM` (UTC).
+fn fmt_utc(ts_ms: u64) -> String {
+    let secs = (ts_ms / 1000) as i64;
+    let (year, month, day) = civil_from_days(secs.div_euclid(86_400));
+    let sod = secs.rem_euclid(86_400);
+    format!(
+        "{year:04}-{month:02}-{day:02} {:02}:{:02}",
+        sod / 3600,
+        sod % 3600 / 60
+    )
+}
+
+fn escape(s: &str) -> String {
+    let mut out = String::with_capacity(s.len());
+    for c in s.chars() {
+        match c {
+            '&' => out.push_str("&amp;"),
+            '<' => out.push_str("&lt;"),
+            '>' => out.push_str("&gt;"),
+            '"' => out.push_str("&quot;"),
+            '\'' => out.push_str("&#39;"),
+            _ => out.push(c),
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::report::{compute_report, ReportThresholds};
+    use crate::schema::{SCHEMA_VERSION, SHADOW_HEADER};
+
+    fn point(ts_ms: u64, bucket: &str, total_pnl: f64, set_ratio: f64) -> ReportPoint {
+        ReportPoint {
+            signal_ts_ms: ts_ms,
+            bucket: bucket.to_string(),
+            total_pnl,
+            set_ratio,
+        }
+    }
+
+    #[test]
+    fn set_ratio_histogram_clamps_into_the_end_bins() {
+        let bins = set_ratio_histogram(&[
+            point(1, "thin", 0.0, -0.2),
+            point(2, "thin", 0.0, 0.05),
+            point(3, "liquid", 0.0, 0.55),
+            point(4, "liquid", 0.0, 1.0),
+            point(5, "liquid", 0.0, 1.3),
+        ]);
+        assert_eq!(bins, [2, 0, 0, 0, 0, 1, 0, 0, 0, 2]);
+    }
+
+    #[test]
+    fn page_has_verdict_charts_and_escaped_ids() {
+        let col = |name: &str| SHADOW_HEADER.iter().position(|h| *h == name).unwrap();
+        let mut csv = format!("{}\n", SHADOW_HEADER.join(","));
+        for (id, ts, market, bucket, pnl, ratio) in [
+            (1, 1_700_000_000_000u64, "m<1>", "liquid", "0.5", "0.9"),
+            (2, 1_700_000_060_000, "m2", "thin", "-0.2", "0.4"),
+        ] {
+            let mut cols = vec![String::new(); SHADOW_HEADER.len()];
+            cols[col("run_id")] = "run_h".to_string();
+            cols[col("schema_version")] = SCHEMA_VERSION.to_string();
+            cols[col("signal_id")] = id.to_string();
+            cols[col("signal_ts_unix_ms")] = ts.to_string();
+            cols[col("market_id")] = market.to_string();
+            cols[col("strategy")] = "binary".to_string();
+            cols[col("bucket")] = bucket.to_string();
+            cols[col("total_pnl")] = pnl.to_string();
+            cols[col("set_ratio")] = ratio.to_string();
+            csv.push_str(&cols.join(","));
+            csv.push('\n');
+        }
+        let path = std::env::temp_dir().join(format!(
+            "razor_report_html_{}_{}.csv",
+            std::process::id(),
+            crate::types::now_ms()
+        ));
+        std::fs::write(&path, csv).unwrap();
+        let report = compute_report(&path, "run_h", ReportThresholds::default()).unwrap();
+        let _ = std::fs::remove_file(&path);
+        assert_eq!(report.points.len(), 2);
+
+        let html = render_report_html(&report);
+        assert!(html.contains("<span class=\"verdict nogo\">NO GO</span>"));
+        assert!(html.contains("2023-11-14 22:13 &ndash; 2023-11-14 22:14"));
+        assert_eq!(html.matches("<svg").count(), 3);
+        assert!(html.contains("<polyline"));
+        assert!(html.contains("m&lt;1&gt;"));
+        assert!(!html.contains("m<1>"));
+    }
+
+    #[test]
+    fn empty_run_renders_without_charts() {
+        let path = std::env::temp_dir().join("razor_report_html_missing.csv");
+        let report = compute_report(&path, "run_e", ReportThresholds::default()).unwrap();
+        let html = render_report_html(&report);
+        assert_eq!(html.matches("<svg").count(), 0);
+        assert_eq!(html.matches("No settled signals.").count(), 3);
+    }
+}
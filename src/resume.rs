@@ -0,0 +1,222 @@
+use std::collections::{BTreeMap, VecDeque};
+use std::path::{Path, PathBuf};
+
+use anyhow::Context as _;
+use tracing::warn;
+
+use crate::recorder::csv_input_paths;
+use crate::rows::TradeRow;
+use crate::schema::{
+    file_versions, FILE_RUN_CONFIG, FILE_SCHEMA_VERSION, FILE_SHADOW_LOG, FILE_SIGNALS,
+    FILE_TRADES, FILE_TRADE_LOG, SCHEMA_VERSION, TRADES_HEADER,
+};
+use crate::schema_adapt::{FrozenCsvReader, SchemaMode};
+use crate::types::TradeTick;
+
+/// What `razor --resume <run_dir>` carries over from the crashed process. Signals that were
+/// still waiting to settle when it died come back through `signals.csv`, which shadow reloads
+/// on its own at startup.
+pub struct ResumeState {
+    /// Trades still inside `shadow.trade_retention_ms`, oldest first: they re-seed the trades
+    /// poller's dedup cursor and the v2 classifier's arrival counts (shadow reloads its own
+    /// TradeStore from the same file).
+    pub recent_trades: Vec<TradeTick>,
+    /// One past the highest signal_id already written, so ids stay unique within the run_id.
+    pub next_signal_id: u64,
+}
+
+/// The run dir's `config.toml` snapshot, which a resumed run always uses. Refuses a dir
+/// recorded under another schema version, or holding a file whose per-file version in
+/// `schema_version.json` differs from this build's: appending would otherwise rotate the old
+/// CSV aside (header mismatch) and split the run across two layouts.
+pub fn read_config_snapshot(run_dir: &Path) -> anyhow::Result<(PathBuf, String)> {
+    anyhow::ensure!(
+        run_dir.is_dir(),
+        "resume run dir not found: {}",
+        run_dir.display()
+    );
+    let sv_path = run_dir.join(FILE_SCHEMA_VERSION);
+    let raw = std::fs::read(&sv_path).with_context(|| format!("read {}", sv_path.display()))?;
+    let sv: serde_json::Value =
+        serde_json::from_slice(&raw).with_context(|| format!("parse {}", sv_path.display()))?;
+    let recorded = sv
+        .get("schema_version")
+        .and_then(|v| v.as_str())
+        .unwrap_or_default();
+    anyhow::ensure!(
+        recorded == SCHEMA_VERSION,
+        "cannot resume {}: recorded schema_version={recorded} code={SCHEMA_VERSION}",
+        run_dir.display()
+    );
+    let recorded_files: BTreeMap<String, String> = sv
+        .get("files")
+        .cloned()
+        .map(serde_json::from_value)
+        .transpose()
+        .with_context(|| format!("parse files in {}", sv_path.display()))?
+        .unwrap_or_default();
+    let stale: Vec<String> = file_versions()
+        .into_iter()
+        .filter(|(file, _)| !csv_input_paths(run_dir, file).is_empty())
+        .filter_map(|(file, current)| {
+            let recorded = recorded_files
+                .get(&file)
+                .map_or("unrecorded", String::as_str);
+            (recorded != current).then(|| format!("{file} {recorded} (code {current})"))
+        })
+        .collect();
+    anyhow::ensure!(
+        stale.is_empty(),
+        "cannot resume {}: recorded under other file versions: {}; `razor migrate` it and start a new run",
+        run_dir.display(),
+        stale.join(", ")
+    );
+
+    let cfg_path = run_dir.join(FILE_RUN_CONFIG);
+    let cfg_raw = std::fs::read_to_string(&cfg_path)
+        .with_context(|| format!("read {}", cfg_path.display()))?;
+    Ok((cfg_path, cfg_raw))
+}
+
+/// Trades recorded in `run_dir` with `ingest_ts_ms >= since_ms`, oldest first.
+pub fn read_recent_trades(run_dir: &Path, since_ms: u64) -> anyhow::Result<Vec<TradeTick>> {
+    let mut out = Vec::new();
+    for path in csv_input_paths(run_dir, FILE_TRADES) {
+        let mut rdr =
+            match FrozenCsvReader::open(&path, FILE_TRADES, &TRADES_HEADER, SchemaMode::Compat) {
+                Ok(r) => r,
+                Err(e) => {
+                    warn!(error = %format!("{e:#}"), "trades not restored");
+                    continue;
+                }
+            };
+        for row in rdr.deserialize::<TradeRow>() {
+            let tick = TradeTick::from(row?);
+            if tick.ingest_ts_ms >= since_ms {
+                out.push(tick);
+            }
+        }
+    }
+    out.sort_by_key(|t| t.ingest_ts_ms);
+    Ok(out)
+}
+
+fn max_signal_id(path: &Path) -> anyhow::Result<u64> {
+    if !path.is_file() {
+        return Ok(0);
+    }
+    let mut rdr = csv::ReaderBuilder::new()
+        .flexible(true)
+        .trim(csv::Trim::All)
+        .from_path(path)
+        .with_context(|| format!("open {}", path.display()))?;
+    let Some(col) = rdr
+        .headers()
+        .with_context(|| format!("read header {}", path.display()))?
+        .iter()
+        .position(|h| h == "signal_id")
+    else {
+        return Ok(0);
+    };
+    let mut max = 0u64;
+    for record in rdr.records() {
+        let record = record.with_context(|| format!("read {}", path.display()))?;
+        if let Some(id) = record.get(col).and_then(|v| v.parse::<u64>().ok()) {
+            max = max.max(id);
+        }
+    }
+    Ok(max)
+}
+
+pub fn load_resume_state(
+    run_dir: &Path,
+    now_ms: u64,
+    trade_retention_ms: u64,
+) -> anyhow::Result<ResumeState> {
+    let recent_trades = read_recent_trades(run_dir, now_ms.saturating_sub(trade_retention_ms))?;
+    let max_id = max_signal_id(&run_dir.join(FILE_SHADOW_LOG))?
+        .max(max_signal_id(&run_dir.join(FILE_TRADE_LOG))?)
+        .max(max_signal_id(&run_dir.join(FILE_SIGNALS))?);
+    Ok(ResumeState {
+        recent_trades,
+        next_signal_id: max_id + 1,
+    })
+}
+
+impl ResumeState {
+    /// `(ingest_ts_ms, trade_id)` in arrival order, the shape of the poller's dedup queue.
+    pub fn dedup_seed(&self) -> VecDeque<(u64, String)> {
+        self.recent_trades
+            .iter()
+            .map(|t| (t.ingest_ts_ms, t.trade_id.clone()))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::schema::SHADOW_HEADER;
+
+    #[test]
+    fn restores_recent_trades_and_continues_signal_ids() {
+        let run = std::env::temp_dir().join(format!("razor_resume_{}", crate::types::now_ms()));
+        std::fs::create_dir_all(&run).unwrap();
+        std::fs::write(
+            run.join(FILE_TRADES),
+            format!(
+                "{}\n1000,m1,t1,0.5,10,old,1000,\n9000,m1,t1,0.6,5,new,9000,8990\n",
+                TRADES_HEADER.join(",")
+            ),
+        )
+        .unwrap();
+        let mut row = vec![""; SHADOW_HEADER.len()];
+        row[2] = "41";
+        std::fs::write(
+            run.join(FILE_SHADOW_LOG),
+            format!("{}\n{}\n", SHADOW_HEADER.join(","), row.join(",")),
+        )
+        .unwrap();
+
+        let state = load_resume_state(&run, 10_000, 5_000).unwrap();
+        assert_eq!(state.recent_trades.len(), 1);
+        assert_eq!(state.recent_trades[0].exchange_ts_ms, Some(8990));
+        assert_eq!(
+            state.dedup_seed(),
+            VecDeque::from([(9000, "new".to_string())])
+        );
+        assert_eq!(state.next_signal_id, 42);
+
+        assert!(
+            read_config_snapshot(&run).is_err(),
+            "no schema_version.json"
+        );
+        let _ = std::fs::remove_dir_all(&run);
+    }
+
+    #[test]
+    fn refuses_files_recorded_under_another_version() {
+        let run =
+            std::env::temp_dir().join(format!("razor_resume_versions_{}", crate::types::now_ms()));
+        std::fs::create_dir_all(&run).unwrap();
+        std::fs::write(run.join(FILE_RUN_CONFIG), "").unwrap();
+        std::fs::write(
+            run.join(FILE_TRADES),
+            format!("{}\n", TRADES_HEADER.join(",")),
+        )
+        .unwrap();
+        crate::schema::write_schema_version_json(&run, SCHEMA_VERSION, 1).unwrap();
+        assert!(read_config_snapshot(&run).is_ok());
+
+        let mut files = file_versions();
+        files.insert(FILE_TRADES.to_string(), "v3".to_string());
+        // Not on disk: an old version of it does not matter.
+        files.insert(FILE_TRADE_LOG.to_string(), "v0".to_string());
+        let sv = serde_json::json!({ "schema_version": SCHEMA_VERSION, "files": files });
+        std::fs::write(run.join(FILE_SCHEMA_VERSION), sv.to_string()).unwrap();
+        let err = read_config_snapshot(&run).unwrap_err().to_string();
+        assert!(err.contains("trades.csv v3 (code v4)"), "{err}");
+        assert!(!err.contains(FILE_TRADE_LOG), "{err}");
+        let _ = std::fs::remove_dir_all(&run);
+    }
+}
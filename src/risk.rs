@@ -0,0 +1,210 @@
+//! Global kill switch. Watches the health counters and shadow PnL and stops the whole run on a
+//! `[risk]` condition; unlike the per-signal sniper HARDSTOP this ends every task (the run's
+//! shutdown watch channel fires once `run` returns its `RISK_HARDSTOP` error).
+
+use std::collections::VecDeque;
+use std::sync::Arc;
+use std::time::Duration;
+
+use tokio::sync::{mpsc, watch};
+use tracing::{error, info};
+
+use crate::config::RiskConfig;
+use crate::errors::RazorError;
+use crate::health::{HealthCounters, HealthLine, HealthSnapshot};
+
+/// The condition that tripped, with the observed value and its configured limit.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RiskTrip {
+    pub condition: &'static str,
+    pub value: u64,
+    pub threshold: u64,
+}
+
+impl RiskTrip {
+    fn describe(&self) -> String {
+        format!(
+            "{} = {} (limit {})",
+            self.condition, self.value, self.threshold
+        )
+    }
+}
+
+/// Evaluates `[risk]` against successive health snapshots; keeps the `trades_dropped` samples
+/// the drop window needs.
+pub struct RiskMonitor {
+    cfg: RiskConfig,
+    started_ms: u64,
+    drop_samples: VecDeque<(u64, u64)>,
+}
+
+impl RiskMonitor {
+    /// `started_ms` stands in for the last tick until the WS delivers one, so a feed that never
+    /// comes up still trips `max_ws_staleness_ms`.
+    pub fn new(cfg: RiskConfig, started_ms: u64) -> Self {
+        Self {
+            cfg,
+            started_ms,
+            drop_samples: VecDeque::new(),
+        }
+    }
+
+    pub fn check(&mut self, snap: &HealthSnapshot) -> Option<RiskTrip> {
+        let now_ms = snap.ts_ms;
+
+        if self.cfg.max_consecutive_losses > 0
+            && snap.shadow_losing_streak >= self.cfg.max_consecutive_losses
+        {
+            return Some(RiskTrip {
+                condition: "consecutive_losses",
+                value: snap.shadow_losing_streak,
+                threshold: self.cfg.max_consecutive_losses,
+            });
+        }
+
+        if self.cfg.max_ws_staleness_ms > 0 {
+            let age_ms = now_ms.saturating_sub(snap.last_tick_ingest_ms.max(self.started_ms));
+            if age_ms > self.cfg.max_ws_staleness_ms {
+                return Some(RiskTrip {
+                    condition: "ws_staleness_ms",
+                    value: age_ms,
+                    threshold: self.cfg.max_ws_staleness_ms,
+                });
+            }
+        }
+
+        if self.cfg.max_trade_drops > 0 {
+            self.drop_samples.push_back((now_ms, snap.trades_dropped));
+            let window_start_ms = now_ms.saturating_sub(self.cfg.trade_drop_window_ms);
+            while self
+                .drop_samples
+                .front()
+                .is_some_and(|(ts_ms, _)| *ts_ms < window_start_ms)
+            {
+                self.drop_samples.pop_front();
+            }
+            let base = self.drop_samples.front().map_or(0, |(_, n)| *n);
+            let dropped = snap.trades_dropped.saturating_sub(base);
+            if dropped > self.cfg.max_trade_drops {
+                return Some(RiskTrip {
+                    condition: "trade_drops",
+                    value: dropped,
+                    threshold: self.cfg.max_trade_drops,
+                });
+            }
+        }
+
+        None
+    }
+}
+
+/// Checks `[risk]` every `check_interval_ms`. Returns a `RISK_HARDSTOP` error on the first
+/// tripped condition so the run stops gracefully (report + flush); runs until shutdown otherwise.
+pub async fn run(
+    cfg: RiskConfig,
+    health: Arc<HealthCounters>,
+    health_tx: mpsc::Sender<HealthLine>,
+    mut shutdown: watch::Receiver<bool>,
+) -> anyhow::Result<()> {
+    let mut interval = tokio::time::interval(Duration::from_millis(cfg.check_interval_ms));
+    interval.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+    info!(
+        max_consecutive_losses = cfg.max_consecutive_losses,
+        max_ws_staleness_ms = cfg.max_ws_staleness_ms,
+        max_trade_drops = cfg.max_trade_drops,
+        trade_drop_window_ms = cfg.trade_drop_window_ms,
+        "risk kill switch armed"
+    );
+    let mut monitor = RiskMonitor::new(cfg, crate::types::now_ms());
+
+    loop {
+        tokio::select! {
+            _ = shutdown.changed() => {
+                if *shutdown.borrow() { break; }
+            }
+            _ = interval.tick() => {}
+        }
+        if *shutdown.borrow() {
+            break;
+        }
+
+        let snap = health.snapshot();
+        let Some(trip) = monitor.check(&snap) else {
+            continue;
+        };
+        error!(
+            condition = trip.condition,
+            value = trip.value,
+            threshold = trip.threshold,
+            "risk HARDSTOP; stopping the run"
+        );
+        let _ = health_tx.try_send(HealthLine::RiskHardstop {
+            ts_ms: snap.ts_ms,
+            condition: trip.condition.to_string(),
+            value: trip.value,
+            threshold: trip.threshold,
+        });
+        return Err(RazorError::RiskHardstop(trip.describe()).into());
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn cfg() -> RiskConfig {
+        RiskConfig {
+            check_interval_ms: 1_000,
+            max_consecutive_losses: 3,
+            max_ws_staleness_ms: 30_000,
+            max_trade_drops: 5,
+            trade_drop_window_ms: 60_000,
+        }
+    }
+
+    fn snap(ts_ms: u64, last_tick_ms: u64, trades_dropped: u64) -> HealthSnapshot {
+        let mut s = HealthCounters::default().snapshot();
+        s.ts_ms = ts_ms;
+        s.last_tick_ingest_ms = last_tick_ms;
+        s.trades_dropped = trades_dropped;
+        s
+    }
+
+    #[test]
+    fn trips_on_losing_streak_and_ws_staleness() {
+        let mut m = RiskMonitor::new(cfg(), 100_000);
+        let mut s = snap(110_000, 109_000, 0);
+        s.shadow_losing_streak = 2;
+        assert_eq!(m.check(&s), None);
+        s.shadow_losing_streak = 3;
+        assert_eq!(m.check(&s).map(|t| t.condition), Some("consecutive_losses"));
+
+        // No tick yet: staleness ages from the monitor start.
+        let mut m = RiskMonitor::new(cfg(), 100_000);
+        assert_eq!(m.check(&snap(130_000, 0, 0)), None);
+        let trip = m.check(&snap(130_001, 0, 0)).expect("stale");
+        assert_eq!(
+            (trip.condition, trip.value, trip.threshold),
+            ("ws_staleness_ms", 30_001, 30_000)
+        );
+    }
+
+    #[test]
+    fn trade_drops_count_only_within_the_window() {
+        let mut m = RiskMonitor::new(cfg(), 0);
+        assert_eq!(m.check(&snap(0, 0, 10)), None);
+        // +5 within 60s is at the limit, not over it.
+        assert_eq!(m.check(&snap(20_000, 20_000, 15)), None);
+        // The t=0 baseline leaves the window; +3 since t=20s.
+        assert_eq!(m.check(&snap(70_000, 70_000, 18)), None);
+        let trip = m.check(&snap(75_000, 75_000, 21)).expect("drops");
+        assert_eq!((trip.condition, trip.value), ("trade_drops", 6));
+
+        let mut off = RiskMonitor::new(RiskConfig::default(), 0);
+        let mut s = snap(10_000_000, 0, 1_000_000);
+        s.shadow_losing_streak = 1_000;
+        assert_eq!(off.check(&s), None);
+    }
+}
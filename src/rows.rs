@@ -0,0 +1,837 @@
+//! Typed rows of the frozen CSVs. Writers serialize these and readers deserialize them by
+//! header name, so a column added, dropped or reordered without touching the `*_HEADER`
+//! constants fails the header test below instead of silently shifting cells.
+
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+use crate::reasons::{format_notes, parse_notes_reasons, ShadowNoteReason};
+use crate::types::{
+    Bps, Bucket, BucketClassifierVersion, BucketMetrics, Price, Qty, Side, Signal, SignalId,
+    SignalLeg, Strategy, TradeTick,
+};
+
+/// One `ticks.csv` row (`TICKS_HEADER`).
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TickRow {
+    pub ts_recv_us: u64,
+    pub market_id: String,
+    pub token_id: String,
+    pub best_bid: f64,
+    pub best_ask: f64,
+    pub ask_depth3_usdc: f64,
+}
+
+/// One `trades.csv` row (`TRADES_HEADER`). Field order is the column order, which differs from
+/// `TradeTick`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TradeRow {
+    pub ts_ms: u64,
+    pub market_id: String,
+    pub token_id: String,
+    /// A non-finite price or size fails to deserialize.
+    pub price: Price,
+    pub size: Qty,
+    pub trade_id: String,
+    /// Empty in rows migrated from before it was recorded; readers fall back to `ts_ms`.
+    pub ingest_ts_ms: Option<u64>,
+    pub exchange_ts_ms: Option<u64>,
+    /// Taker side (`BUY` / `SELL`); empty when unknown, including rows from before it was recorded.
+    pub side: Option<Side>,
+}
+
+impl From<&TradeTick> for TradeRow {
+    fn from(t: &TradeTick) -> Self {
+        Self {
+            ts_ms: t.ts_ms,
+            market_id: t.market_id.to_string(),
+            token_id: t.token_id.to_string(),
+            price: t.price,
+            size: t.size,
+            trade_id: t.trade_id.clone(),
+            ingest_ts_ms: Some(t.ingest_ts_ms),
+            exchange_ts_ms: t.exchange_ts_ms,
+            side: t.side,
+        }
+    }
+}
+
+impl From<TradeRow> for TradeTick {
+    fn from(r: TradeRow) -> Self {
+        Self {
+            ts_ms: r.ts_ms,
+            ingest_ts_ms: r.ingest_ts_ms.unwrap_or(r.ts_ms),
+            exchange_ts_ms: r.exchange_ts_ms,
+            market_id: r.market_id.into(),
+            token_id: r.token_id.into(),
+            price: r.price,
+            size: r.size,
+            trade_id: r.trade_id,
+            side: r.side,
+        }
+    }
+}
+
+/// The nine per-leg columns of a shadow row; an absent third leg is all empty/zero.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, JsonSchema)]
+pub struct ShadowLegCols {
+    pub token_id: String,
+    pub p_limit: f64,
+    pub best_bid: f64,
+    pub v_mkt: f64,
+    /// Window volume at or better than `p_limit`, any aggressor.
+    pub v_mkt_all: f64,
+    /// Window volume whose aggressor hit our side (unknown side counts).
+    pub v_mkt_sided: f64,
+    pub q_fill: f64,
+    /// Lowercase bucket label, as in the `bucket` column.
+    pub bucket: String,
+    pub fill_share: f64,
+}
+
+/// One `shadow_log.csv` row (`SHADOW_HEADER`). The leg columns stay flat so the row
+/// deserializes by header name; fill them with [`ShadowRow::set_leg`].
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct ShadowRow {
+    pub run_id: String,
+    pub schema_version: String,
+    pub signal_id: u64,
+    pub signal_ts_unix_ms: u64,
+    pub window_start_ms: u64,
+    pub window_end_ms: u64,
+    pub market_id: String,
+    pub strategy: String,
+    pub bucket: String,
+    pub worst_leg_token_id: String,
+    pub bucket_classifier: String,
+    pub q_req: f64,
+    pub legs_n: u8,
+    pub q_set: f64,
+    pub leg0_token_id: String,
+    pub leg0_p_limit: f64,
+    pub leg0_best_bid: f64,
+    pub leg0_v_mkt: f64,
+    pub leg0_v_mkt_all: f64,
+    pub leg0_v_mkt_sided: f64,
+    pub leg0_q_fill: f64,
+    pub leg0_bucket: String,
+    pub leg0_fill_share: f64,
+    pub leg1_token_id: String,
+    pub leg1_p_limit: f64,
+    pub leg1_best_bid: f64,
+    pub leg1_v_mkt: f64,
+    pub leg1_v_mkt_all: f64,
+    pub leg1_v_mkt_sided: f64,
+    pub leg1_q_fill: f64,
+    pub leg1_bucket: String,
+    pub leg1_fill_share: f64,
+    pub leg2_token_id: String,
+    pub leg2_p_limit: f64,
+    pub leg2_best_bid: f64,
+    pub leg2_v_mkt: f64,
+    pub leg2_v_mkt_all: f64,
+    pub leg2_v_mkt_sided: f64,
+    pub leg2_q_fill: f64,
+    pub leg2_bucket: String,
+    pub leg2_fill_share: f64,
+    pub cost_set: f64,
+    pub proceeds_set: f64,
+    pub pnl_set: f64,
+    pub pnl_left_total: f64,
+    pub total_pnl: f64,
+    pub q_fill_avg: f64,
+    pub set_ratio: f64,
+    pub fill_share_p25_used: f64,
+    pub dump_slippage_assumed: f64,
+    pub volume_mode: String,
+    /// Id of the `fees` registry model the row was settled with.
+    pub fee_model: String,
+    pub notes: String,
+}
+
+impl ShadowRow {
+    /// Sets the columns of leg `i` (0..3); other indices are ignored.
+    pub fn set_leg(&mut self, i: usize, leg: ShadowLegCols) {
+        let (
+            token_id,
+            p_limit,
+            best_bid,
+            v_mkt,
+            v_mkt_all,
+            v_mkt_sided,
+            q_fill,
+            bucket,
+            fill_share,
+        ) = match i {
+            0 => (
+                &mut self.leg0_token_id,
+                &mut self.leg0_p_limit,
+                &mut self.leg0_best_bid,
+                &mut self.leg0_v_mkt,
+                &mut self.leg0_v_mkt_all,
+                &mut self.leg0_v_mkt_sided,
+                &mut self.leg0_q_fill,
+                &mut self.leg0_bucket,
+                &mut self.leg0_fill_share,
+            ),
+            1 => (
+                &mut self.leg1_token_id,
+                &mut self.leg1_p_limit,
+                &mut self.leg1_best_bid,
+                &mut self.leg1_v_mkt,
+                &mut self.leg1_v_mkt_all,
+                &mut self.leg1_v_mkt_sided,
+                &mut self.leg1_q_fill,
+                &mut self.leg1_bucket,
+                &mut self.leg1_fill_share,
+            ),
+            2 => (
+                &mut self.leg2_token_id,
+                &mut self.leg2_p_limit,
+                &mut self.leg2_best_bid,
+                &mut self.leg2_v_mkt,
+                &mut self.leg2_v_mkt_all,
+                &mut self.leg2_v_mkt_sided,
+                &mut self.leg2_q_fill,
+                &mut self.leg2_bucket,
+                &mut self.leg2_fill_share,
+            ),
+            _ => return,
+        };
+        *token_id = leg.token_id;
+        *p_limit = leg.p_limit;
+        *best_bid = leg.best_bid;
+        *v_mkt = leg.v_mkt;
+        *v_mkt_all = leg.v_mkt_all;
+        *v_mkt_sided = leg.v_mkt_sided;
+        *q_fill = leg.q_fill;
+        *bucket = leg.bucket;
+        *fill_share = leg.fill_share;
+    }
+
+    /// The columns of leg `i` (0..3); an out-of-range index reads as an absent leg.
+    pub fn leg(&self, i: usize) -> ShadowLegCols {
+        let (
+            token_id,
+            p_limit,
+            best_bid,
+            v_mkt,
+            v_mkt_all,
+            v_mkt_sided,
+            q_fill,
+            bucket,
+            fill_share,
+        ) = match i {
+            0 => (
+                &self.leg0_token_id,
+                self.leg0_p_limit,
+                self.leg0_best_bid,
+                self.leg0_v_mkt,
+                self.leg0_v_mkt_all,
+                self.leg0_v_mkt_sided,
+                self.leg0_q_fill,
+                &self.leg0_bucket,
+                self.leg0_fill_share,
+            ),
+            1 => (
+                &self.leg1_token_id,
+                self.leg1_p_limit,
+                self.leg1_best_bid,
+                self.leg1_v_mkt,
+                self.leg1_v_mkt_all,
+                self.leg1_v_mkt_sided,
+                self.leg1_q_fill,
+                &self.leg1_bucket,
+                self.leg1_fill_share,
+            ),
+            2 => (
+                &self.leg2_token_id,
+                self.leg2_p_limit,
+                self.leg2_best_bid,
+                self.leg2_v_mkt,
+                self.leg2_v_mkt_all,
+                self.leg2_v_mkt_sided,
+                self.leg2_q_fill,
+                &self.leg2_bucket,
+                self.leg2_fill_share,
+            ),
+            _ => return ShadowLegCols::default(),
+        };
+        ShadowLegCols {
+            token_id: token_id.clone(),
+            p_limit,
+            best_bid,
+            v_mkt,
+            v_mkt_all,
+            v_mkt_sided,
+            q_fill,
+            bucket: bucket.clone(),
+            fill_share,
+        }
+    }
+}
+
+/// One `shadow_log.jsonl` line: the same settlement as a [`ShadowRow`], with the legs nested
+/// (only the `legs_n` real ones) and the `notes` codes as a `reasons` array. The CSV stays the
+/// Frozen Spec record; new fields can land here without a header bump.
+#[derive(Debug, Clone, PartialEq, Serialize, JsonSchema)]
+pub struct ShadowRecord {
+    pub run_id: String,
+    pub schema_version: String,
+    pub signal_id: u64,
+    pub signal_ts_unix_ms: u64,
+    pub window_start_ms: u64,
+    pub window_end_ms: u64,
+    pub market_id: String,
+    pub strategy: String,
+    pub bucket: String,
+    pub worst_leg_token_id: String,
+    pub bucket_classifier: String,
+    pub q_req: f64,
+    pub legs_n: u8,
+    pub q_set: f64,
+    /// In `leg_index` order.
+    pub legs: Vec<ShadowLegCols>,
+    pub cost_set: f64,
+    pub proceeds_set: f64,
+    pub pnl_set: f64,
+    pub pnl_left_total: f64,
+    pub total_pnl: f64,
+    pub q_fill_avg: f64,
+    pub set_ratio: f64,
+    pub fill_share_p25_used: f64,
+    pub dump_slippage_assumed: f64,
+    pub volume_mode: String,
+    pub fee_model: String,
+    /// `ShadowNoteReason` codes, sorted and deduplicated like `notes`.
+    pub reasons: Vec<String>,
+}
+
+impl From<&ShadowRow> for ShadowRecord {
+    fn from(row: &ShadowRow) -> Self {
+        Self {
+            run_id: row.run_id.clone(),
+            schema_version: row.schema_version.clone(),
+            signal_id: row.signal_id,
+            signal_ts_unix_ms: row.signal_ts_unix_ms,
+            window_start_ms: row.window_start_ms,
+            window_end_ms: row.window_end_ms,
+            market_id: row.market_id.clone(),
+            strategy: row.strategy.clone(),
+            bucket: row.bucket.clone(),
+            worst_leg_token_id: row.worst_leg_token_id.clone(),
+            bucket_classifier: row.bucket_classifier.clone(),
+            q_req: row.q_req,
+            legs_n: row.legs_n,
+            q_set: row.q_set,
+            legs: (0..usize::from(row.legs_n).min(3))
+                .map(|i| row.leg(i))
+                .collect(),
+            cost_set: row.cost_set,
+            proceeds_set: row.proceeds_set,
+            pnl_set: row.pnl_set,
+            pnl_left_total: row.pnl_left_total,
+            total_pnl: row.total_pnl,
+            q_fill_avg: row.q_fill_avg,
+            set_ratio: row.set_ratio,
+            fill_share_p25_used: row.fill_share_p25_used,
+            dump_slippage_assumed: row.dump_slippage_assumed,
+            volume_mode: row.volume_mode.clone(),
+            fee_model: row.fee_model.clone(),
+            reasons: parse_notes_reasons(&row.notes),
+        }
+    }
+}
+
+/// One `trade_log.csv` row (`TRADE_LOG_HEADER`).
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TradeLogRow {
+    pub ts_ms: u64,
+    pub signal_id: u64,
+    pub market_id: String,
+    pub strategy: String,
+    pub bucket: String,
+    pub phase: String,
+    pub action: String,
+    pub leg_index: i32,
+    pub token_id: String,
+    pub side: String,
+    pub limit_price: f64,
+    pub req_qty: f64,
+    pub fill_qty: f64,
+    pub fill_status: String,
+    pub expected_net_bps: i32,
+    pub notes: String,
+}
+
+/// The seven per-leg columns of a `signals.csv` row; an absent third leg is all empty/zero.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct SignalLegCols {
+    pub token_id: String,
+    pub side: String,
+    pub p_limit: f64,
+    pub qty: f64,
+    pub best_bid: f64,
+    pub best_ask: f64,
+    /// Lowercase per-leg bucket; empty when the signal has no per-leg buckets.
+    pub bucket: String,
+}
+
+/// One `signals.csv` row (`SIGNALS_HEADER`): everything shadow needs to settle the signal
+/// after a restart.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct SignalRow {
+    pub run_id: String,
+    pub signal_id: u64,
+    pub signal_ts_unix_ms: u64,
+    pub market_id: String,
+    pub strategy: String,
+    pub bucket: String,
+    pub bucket_classifier: String,
+    pub worst_leg_index: usize,
+    pub worst_spread_bps: i32,
+    pub worst_depth3_usdc: f64,
+    pub is_depth3_degraded: u8,
+    pub depth_levels: usize,
+    pub worst_depth_usdc: f64,
+    pub q_req: f64,
+    pub raw_cost_bps: i32,
+    pub raw_edge_bps: i32,
+    pub hard_fees_bps: i32,
+    pub risk_premium_bps: i32,
+    pub expected_net_bps: i32,
+    pub legs_n: u8,
+    pub leg0_token_id: String,
+    pub leg0_side: String,
+    pub leg0_p_limit: f64,
+    pub leg0_qty: f64,
+    pub leg0_best_bid: f64,
+    pub leg0_best_ask: f64,
+    pub leg0_bucket: String,
+    pub leg1_token_id: String,
+    pub leg1_side: String,
+    pub leg1_p_limit: f64,
+    pub leg1_qty: f64,
+    pub leg1_best_bid: f64,
+    pub leg1_best_ask: f64,
+    pub leg1_bucket: String,
+    pub leg2_token_id: String,
+    pub leg2_side: String,
+    pub leg2_p_limit: f64,
+    pub leg2_qty: f64,
+    pub leg2_best_bid: f64,
+    pub leg2_best_ask: f64,
+    pub leg2_bucket: String,
+    pub notes: String,
+}
+
+impl SignalRow {
+    /// Sets the columns of leg `i` (0..3); other indices are ignored.
+    pub fn set_leg(&mut self, i: usize, leg: SignalLegCols) {
+        let (token_id, side, p_limit, qty, best_bid, best_ask, bucket) = match i {
+            0 => (
+                &mut self.leg0_token_id,
+                &mut self.leg0_side,
+                &mut self.leg0_p_limit,
+                &mut self.leg0_qty,
+                &mut self.leg0_best_bid,
+                &mut self.leg0_best_ask,
+                &mut self.leg0_bucket,
+            ),
+            1 => (
+                &mut self.leg1_token_id,
+                &mut self.leg1_side,
+                &mut self.leg1_p_limit,
+                &mut self.leg1_qty,
+                &mut self.leg1_best_bid,
+                &mut self.leg1_best_ask,
+                &mut self.leg1_bucket,
+            ),
+            2 => (
+                &mut self.leg2_token_id,
+                &mut self.leg2_side,
+                &mut self.leg2_p_limit,
+                &mut self.leg2_qty,
+                &mut self.leg2_best_bid,
+                &mut self.leg2_best_ask,
+                &mut self.leg2_bucket,
+            ),
+            _ => return,
+        };
+        *token_id = leg.token_id;
+        *side = leg.side;
+        *p_limit = leg.p_limit;
+        *qty = leg.qty;
+        *best_bid = leg.best_bid;
+        *best_ask = leg.best_ask;
+        *bucket = leg.bucket;
+    }
+
+    /// The columns of leg `i` (0..3); an out-of-range index reads as an absent leg.
+    pub fn leg(&self, i: usize) -> SignalLegCols {
+        let (token_id, side, p_limit, qty, best_bid, best_ask, bucket) = match i {
+            0 => (
+                &self.leg0_token_id,
+                &self.leg0_side,
+                &self.leg0_p_limit,
+                &self.leg0_qty,
+                &self.leg0_best_bid,
+                &self.leg0_best_ask,
+                &self.leg0_bucket,
+            ),
+            1 => (
+                &self.leg1_token_id,
+                &self.leg1_side,
+                &self.leg1_p_limit,
+                &self.leg1_qty,
+                &self.leg1_best_bid,
+                &self.leg1_best_ask,
+                &self.leg1_bucket,
+            ),
+            2 => (
+                &self.leg2_token_id,
+                &self.leg2_side,
+                &self.leg2_p_limit,
+                &self.leg2_qty,
+                &self.leg2_best_bid,
+                &self.leg2_best_ask,
+                &self.leg2_bucket,
+            ),
+            _ => return SignalLegCols::default(),
+        };
+        SignalLegCols {
+            token_id: token_id.clone(),
+            side: side.clone(),
+            p_limit: *p_limit,
+            qty: *qty,
+            best_bid: *best_bid,
+            best_ask: *best_ask,
+            bucket: bucket.clone(),
+        }
+    }
+
+    /// Rebuilds the signal; fails on labels this build does not know.
+    pub fn into_signal(self) -> anyhow::Result<Signal> {
+        let signal_id = self.signal_id;
+        let strategy = Strategy::parse(&self.strategy)
+            .ok_or_else(|| anyhow::anyhow!("signal {signal_id}: strategy {:?}", self.strategy))?;
+        let bucket = Bucket::parse(&self.bucket)
+            .ok_or_else(|| anyhow::anyhow!("signal {signal_id}: bucket {:?}", self.bucket))?;
+        let classifier =
+            BucketClassifierVersion::parse(&self.bucket_classifier).ok_or_else(|| {
+                anyhow::anyhow!(
+                    "signal {signal_id}: bucket_classifier {:?}",
+                    self.bucket_classifier
+                )
+            })?;
+        let mut reasons = Vec::new();
+        for code in self
+            .notes
+            .split(',')
+            .map(str::trim)
+            .filter(|c| !c.is_empty())
+        {
+            reasons.push(
+                ShadowNoteReason::parse(code)
+                    .ok_or_else(|| anyhow::anyhow!("signal {signal_id}: reason {code:?}"))?,
+            );
+        }
+        let legs_n = usize::from(self.legs_n).min(3);
+        let mut legs = Vec::with_capacity(legs_n);
+        let mut leg_buckets = Vec::with_capacity(legs_n);
+        for i in 0..legs_n {
+            let cols = self.leg(i);
+            let price = |col: &str, x: f64| {
+                Price::from_f64(x)
+                    .ok_or_else(|| anyhow::anyhow!("signal {signal_id}: leg{i}_{col} {x}"))
+            };
+            legs.push(SignalLeg {
+                leg_index: i,
+                token_id: cols.token_id.into(),
+                side: Side::parse(&cols.side)
+                    .ok_or_else(|| anyhow::anyhow!("signal {signal_id}: side {:?}", cols.side))?,
+                limit_price: price("p_limit", cols.p_limit)?,
+                qty: Qty::from_f64(cols.qty).ok_or_else(|| {
+                    anyhow::anyhow!("signal {signal_id}: leg{i}_qty {}", cols.qty)
+                })?,
+                best_bid_at_signal: price("best_bid", cols.best_bid)?,
+                best_ask_at_signal: price("best_ask", cols.best_ask)?,
+            });
+            if let Some(b) = Bucket::parse(&cols.bucket) {
+                leg_buckets.push(b);
+            }
+        }
+        if leg_buckets.len() != legs_n {
+            leg_buckets.clear();
+        }
+        Ok(Signal {
+            run_id: self.run_id,
+            signal_id: SignalId(signal_id),
+            signal_ts_ms: self.signal_ts_unix_ms,
+            market_id: self.market_id.into(),
+            strategy,
+            bucket,
+            reasons,
+            q_req: Qty::from_f64(self.q_req)
+                .ok_or_else(|| anyhow::anyhow!("signal {signal_id}: q_req {}", self.q_req))?,
+            raw_cost_bps: Bps::new(self.raw_cost_bps),
+            raw_edge_bps: Bps::new(self.raw_edge_bps),
+            hard_fees_bps: Bps::new(self.hard_fees_bps),
+            risk_premium_bps: Bps::new(self.risk_premium_bps),
+            expected_net_bps: Bps::new(self.expected_net_bps),
+            bucket_metrics: BucketMetrics {
+                worst_leg_index: self.worst_leg_index,
+                worst_spread_bps: self.worst_spread_bps,
+                worst_depth3_usdc: self.worst_depth3_usdc,
+                is_depth3_degraded: self.is_depth3_degraded != 0,
+                depth_levels: self.depth_levels,
+                worst_depth_usdc: self.worst_depth_usdc,
+                classifier,
+                leg_buckets,
+            },
+            legs,
+        })
+    }
+}
+
+impl From<&Signal> for SignalRow {
+    fn from(s: &Signal) -> Self {
+        let mut legs: Vec<&SignalLeg> = s.legs.iter().collect();
+        legs.sort_by_key(|l| l.leg_index);
+        let mut row = SignalRow {
+            run_id: s.run_id.clone(),
+            signal_id: s.signal_id.get(),
+            signal_ts_unix_ms: s.signal_ts_ms,
+            market_id: s.market_id.to_string(),
+            strategy: s.strategy.as_str().to_string(),
+            bucket: s.bucket.as_str().to_ascii_lowercase(),
+            bucket_classifier: s.bucket_metrics.classifier.as_str().to_string(),
+            worst_leg_index: s.bucket_metrics.worst_leg_index,
+            worst_spread_bps: s.bucket_metrics.worst_spread_bps,
+            worst_depth3_usdc: s.bucket_metrics.worst_depth3_usdc,
+            is_depth3_degraded: u8::from(s.bucket_metrics.is_depth3_degraded),
+            depth_levels: s.bucket_metrics.depth_levels,
+            worst_depth_usdc: s.bucket_metrics.worst_depth_usdc,
+            q_req: s.q_req.to_f64(),
+            raw_cost_bps: s.raw_cost_bps.raw(),
+            raw_edge_bps: s.raw_edge_bps.raw(),
+            hard_fees_bps: s.hard_fees_bps.raw(),
+            risk_premium_bps: s.risk_premium_bps.raw(),
+            expected_net_bps: s.expected_net_bps.raw(),
+            legs_n: legs.len() as u8,
+            notes: format_notes(&s.reasons),
+            ..Default::default()
+        };
+        for (i, l) in legs.iter().take(3).enumerate() {
+            row.set_leg(
+                i,
+                SignalLegCols {
+                    token_id: l.token_id.to_string(),
+                    side: l.side.as_str().to_string(),
+                    p_limit: l.limit_price.to_f64(),
+                    qty: l.qty.to_f64(),
+                    best_bid: l.best_bid_at_signal.to_f64(),
+                    best_ask: l.best_ask_at_signal.to_f64(),
+                    bucket: s
+                        .bucket_metrics
+                        .leg_buckets
+                        .get(l.leg_index)
+                        .map(|b| b.as_str().to_ascii_lowercase())
+                        .unwrap_or_default(),
+                },
+            );
+        }
+        row
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::recorder::TICKS_HEADER;
+    use crate::schema::{SHADOW_HEADER, SIGNALS_HEADER, TRADES_HEADER, TRADE_LOG_HEADER};
+
+    fn header_of<R: Serialize>(row: &R) -> Vec<String> {
+        let mut w = csv::Writer::from_writer(Vec::new());
+        w.serialize(row).unwrap();
+        let out = String::from_utf8(w.into_inner().unwrap()).unwrap();
+        out.lines()
+            .next()
+            .unwrap()
+            .split(',')
+            .map(String::from)
+            .collect()
+    }
+
+    #[test]
+    fn row_structs_match_frozen_headers_and_round_trip() {
+        let tick = TickRow {
+            ts_recv_us: 1,
+            market_id: "m".into(),
+            token_id: "t".into(),
+            best_bid: 0.4,
+            best_ask: 0.6,
+            ask_depth3_usdc: 10.0,
+        };
+        assert_eq!(header_of(&tick), TICKS_HEADER);
+
+        let trade = TradeTick {
+            ts_ms: 1000,
+            ingest_ts_ms: 1000,
+            exchange_ts_ms: None,
+            market_id: "m".into(),
+            token_id: "t".into(),
+            price: Price::from_f64(0.5).unwrap(),
+            size: Qty::from_f64(10.0).unwrap(),
+            trade_id: "x".to_string(),
+            side: None,
+        };
+        assert_eq!(header_of(&TradeRow::from(&trade)), TRADES_HEADER);
+
+        let mut shadow = ShadowRow::default();
+        shadow.set_leg(
+            2,
+            ShadowLegCols {
+                token_id: "t2".into(),
+                q_fill: 3.0,
+                ..Default::default()
+            },
+        );
+        assert_eq!(header_of(&shadow), SHADOW_HEADER);
+        let mut w = csv::Writer::from_writer(Vec::new());
+        w.serialize(&shadow).unwrap();
+        let raw = w.into_inner().unwrap();
+        let back: ShadowRow = csv::Reader::from_reader(raw.as_slice())
+            .deserialize()
+            .next()
+            .unwrap()
+            .unwrap();
+        assert_eq!(back, shadow);
+        assert_eq!(back.leg2_token_id, "t2");
+
+        let log = TradeLogRow {
+            ts_ms: 1,
+            signal_id: 2,
+            market_id: "m".into(),
+            strategy: "binary".to_string(),
+            bucket: "liquid".to_string(),
+            phase: "SIM".to_string(),
+            action: "entry".to_string(),
+            leg_index: 0,
+            token_id: "t".into(),
+            side: "BUY".to_string(),
+            limit_price: 0.5,
+            req_qty: 1.0,
+            fill_qty: 1.0,
+            fill_status: "filled".to_string(),
+            expected_net_bps: 12,
+            notes: String::new(),
+        };
+        assert_eq!(header_of(&log), TRADE_LOG_HEADER);
+
+        let migrated: TradeRow = csv::Reader::from_reader(
+            format!("{}\n1000,m,t,0.5,10,x,,,SELL\n", TRADES_HEADER.join(",")).as_bytes(),
+        )
+        .deserialize()
+        .next()
+        .unwrap()
+        .unwrap();
+        let migrated = TradeTick::from(migrated);
+        assert_eq!(migrated.ingest_ts_ms, 1000);
+        assert_eq!(migrated.side, Some(Side::Sell));
+    }
+
+    #[test]
+    fn signal_row_round_trips_a_signal() {
+        let leg = |leg_index: usize, token_id: &str, ask: f64| SignalLeg {
+            leg_index,
+            token_id: token_id.into(),
+            side: Side::Buy,
+            limit_price: Price::from_f64(ask).unwrap(),
+            qty: Qty::from_f64(10.0).unwrap(),
+            best_bid_at_signal: Price::from_f64(ask - 0.01).unwrap(),
+            best_ask_at_signal: Price::from_f64(ask).unwrap(),
+        };
+        let signal = Signal {
+            run_id: "run".to_string(),
+            signal_id: SignalId(9),
+            signal_ts_ms: 1_700_000_000_000,
+            market_id: "m".into(),
+            strategy: Strategy::Triangle,
+            bucket: Bucket::Thin,
+            reasons: vec![ShadowNoteReason::DepthUnitSuspect],
+            q_req: Qty::from_f64(10.0).unwrap(),
+            raw_cost_bps: Bps::new(9_700),
+            raw_edge_bps: Bps::new(300),
+            hard_fees_bps: Bps::FEE_POLY + Bps::FEE_MERGE,
+            risk_premium_bps: Bps::new(80),
+            expected_net_bps: Bps::new(10),
+            bucket_metrics: BucketMetrics {
+                worst_leg_index: 2,
+                worst_spread_bps: 30,
+                worst_depth3_usdc: 120.5,
+                is_depth3_degraded: false,
+                depth_levels: 5,
+                worst_depth_usdc: 180.25,
+                classifier: BucketClassifierVersion::V2,
+                leg_buckets: vec![Bucket::Liquid, Bucket::Liquid, Bucket::Thin],
+            },
+            legs: vec![leg(0, "a", 0.3), leg(1, "b", 0.33), leg(2, "c", 0.34)],
+        };
+        let row = SignalRow::from(&signal);
+        assert_eq!(header_of(&row), SIGNALS_HEADER);
+
+        let mut w = csv::Writer::from_writer(Vec::new());
+        w.serialize(&row).unwrap();
+        let raw = w.into_inner().unwrap();
+        let back: SignalRow = csv::Reader::from_reader(raw.as_slice())
+            .deserialize()
+            .next()
+            .unwrap()
+            .unwrap();
+        assert_eq!(back, row);
+        let back = back.into_signal().unwrap();
+        assert_eq!(back.signal_id, signal.signal_id);
+        assert_eq!(back.strategy, Strategy::Triangle);
+        assert_eq!(back.reasons, signal.reasons);
+        assert_eq!(
+            back.bucket_metrics.leg_buckets,
+            signal.bucket_metrics.leg_buckets
+        );
+        assert_eq!(back.legs.len(), 3);
+        assert_eq!(back.legs[2].token_id, signal.legs[2].token_id);
+        assert_eq!(back.legs[2].limit_price, signal.legs[2].limit_price);
+    }
+
+    #[test]
+    fn shadow_record_nests_real_legs_and_lists_reasons() {
+        let mut row = ShadowRow {
+            signal_id: 4,
+            legs_n: 2,
+            total_pnl: -0.25,
+            notes: "NO_TRADES,WINDOW_EMPTY".to_string(),
+            ..Default::default()
+        };
+        for (i, token_id) in ["a", "b"].into_iter().enumerate() {
+            row.set_leg(
+                i,
+                ShadowLegCols {
+                    token_id: token_id.into(),
+                    p_limit: 0.48,
+                    ..Default::default()
+                },
+            );
+        }
+
+        let json = serde_json::to_value(ShadowRecord::from(&row)).unwrap();
+        assert_eq!(json["legs"].as_array().unwrap().len(), 2);
+        assert_eq!(json["legs"][1]["token_id"], "b");
+        assert_eq!(json["legs"][0]["p_limit"], 0.48);
+        assert_eq!(
+            json["reasons"],
+            serde_json::json!(["NO_TRADES", "WINDOW_EMPTY"])
+        );
+        assert!(json.get("notes").is_none());
+        assert!(json.get("leg0_token_id").is_none());
+    }
+}
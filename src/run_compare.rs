@@ -4,14 +4,15 @@ use std::path::{Path, PathBuf};
 use anyhow::Context as _;
 use serde::Serialize;
 
+use crate::archive::{resolve_run_dir, ARCHIVE_SUFFIX};
 use crate::reasons::parse_notes_reasons;
-use crate::run_meta::RunMeta;
+use crate::run_meta::{format_tags, Provenance, RunMeta};
 use crate::schema::{FILE_SHADOW_LOG, SCHEMA_VERSION};
 
 pub const FILE_RUNS_SUMMARY_CSV: &str = "runs_summary.csv";
 pub const FILE_RUNS_SUMMARY_MD: &str = "runs_summary.md";
 
-pub const RUNS_SUMMARY_HEADER: [&str; 24] = [
+pub const RUNS_SUMMARY_HEADER: [&str; 25] = [
     "run_id",
     "run_dir",
     "rows_total",
@@ -36,6 +37,7 @@ pub const RUNS_SUMMARY_HEADER: [&str; 24] = [
     "top_reason_1",
     "top_reason_1_count",
     "top_reason_2",
+    "tags",
 ];
 
 const SET_RATIO_THRESHOLD: f64 = 0.85;
@@ -60,6 +62,12 @@ pub struct RunSummary {
     pub by_bucket: BTreeMap<String, BucketAgg>,
     pub by_reason: BTreeMap<String, ReasonAgg>,
     pub by_bucket_reason: BTreeMap<(String, String), ReasonAgg>,
+
+    /// From `run_meta.json`; `None` when it is missing.
+    pub git_sha: Option<String>,
+    pub provenance: Option<Provenance>,
+    /// Run tags from `run_meta.json`; empty when it is missing or has none.
+    pub tags: BTreeMap<String, String>,
 }
 
 #[derive(Debug, Default, Clone, Serialize)]
@@ -98,6 +106,8 @@ impl ReasonAgg {
     }
 }
 
+/// `run_*` dirs under `data_dir` that have a shadow log, oldest first; archived runs
+/// (`run_*.tar.zst`) are included through their extracted copy.
 pub fn discover_run_dirs(data_dir: &Path) -> anyhow::Result<Vec<PathBuf>> {
     let mut out: Vec<PathBuf> = Vec::new();
     if !data_dir.exists() {
@@ -108,20 +118,25 @@ pub fn discover_run_dirs(data_dir: &Path) -> anyhow::Result<Vec<PathBuf>> {
         std::fs::read_dir(data_dir).with_context(|| format!("read {}", data_dir.display()))?
     {
         let entry = entry?;
-        let path = entry.path();
-        if !path.is_dir() {
-            continue;
-        }
+        let mut path = entry.path();
         let name = entry.file_name().to_string_lossy().to_string();
         if !name.starts_with("run_") {
             continue;
         }
+        if name.ends_with(ARCHIVE_SUFFIX) {
+            path = resolve_run_dir(&path)?;
+        }
+        if !path.is_dir() {
+            continue;
+        }
         if path.join(FILE_SHADOW_LOG).exists() {
             out.push(path);
         }
     }
 
     out.sort_by(|a, b| a.file_name().cmp(&b.file_name()));
+    out.dedup_by(|a, b| a.file_name() == b.file_name());
+    crate::run_index::sort_run_dirs(data_dir, &mut out);
     Ok(out)
 }
 
@@ -131,12 +146,19 @@ pub fn summarize_run_dir(run_dir: &Path) -> anyhow::Result<RunSummary> {
         anyhow::bail!("missing {}", shadow_path.display());
     }
 
-    let run_id = match RunMeta::read_from_dir(run_dir) {
-        Ok(m) => m.run_id,
-        Err(_) => infer_last_run_id(&shadow_path)?,
+    let meta = RunMeta::read_from_dir(run_dir).ok();
+    let run_id = match &meta {
+        Some(m) => m.run_id.clone(),
+        None => infer_last_run_id(&shadow_path)?,
     };
 
-    summarize_shadow_log(&shadow_path, &run_id, run_dir)
+    let mut summary = summarize_shadow_log(&shadow_path, &run_id, run_dir)?;
+    if let Some(m) = meta {
+        summary.git_sha = Some(m.git_sha);
+        summary.provenance = Some(m.provenance);
+        summary.tags = m.tags;
+    }
+    Ok(summary)
 }
 
 fn summarize_shadow_log(
@@ -295,6 +317,9 @@ fn summarize_shadow_log(
         by_bucket,
         by_reason,
         by_bucket_reason,
+        git_sha: None,
+        provenance: None,
+        tags: BTreeMap::new(),
     })
 }
 
@@ -317,7 +342,7 @@ pub fn write_runs_summary_csv(out_dir: &Path, runs: &[RunSummary]) -> anyhow::Re
         let top1 = top_reasons.first().cloned().unwrap_or_default();
         let top2 = top_reasons.get(1).cloned().unwrap_or_default();
 
-        let rec: [String; 24] = [
+        let rec: [String; 25] = [
             r.run_id.clone(),
             r.run_dir.display().to_string(),
             r.rows_total.to_string(),
@@ -342,6 +367,7 @@ pub fn write_runs_summary_csv(out_dir: &Path, runs: &[RunSummary]) -> anyhow::Re
             top1.0,
             top1.1.to_string(),
             top2.0,
+            format_tags(&r.tags),
         ];
         wtr.write_record(rec).context("write row")?;
     }
@@ -354,23 +380,26 @@ pub fn write_runs_summary_md(out_dir: &Path, runs: &[RunSummary]) -> anyhow::Res
     let path = out_dir.join(FILE_RUNS_SUMMARY_MD);
     let mut out = String::new();
     out.push_str("# Razor Run Compare\n\n");
-    out.push_str("| run_id | signals | total_pnl_sum | avg_set_ratio | legging_rate | liquid_pnl | thin_pnl |\n");
-    out.push_str("|---|---:|---:|---:|---:|---:|---:|\n");
+    out.push_str("| run_id | signals | total_pnl_sum | avg_set_ratio | legging_rate | liquid_pnl | thin_pnl | tags |\n");
+    out.push_str("|---|---:|---:|---:|---:|---:|---:|---|\n");
     for r in runs {
         let liquid = r.by_bucket.get("liquid").cloned().unwrap_or_default();
         let thin = r.by_bucket.get("thin").cloned().unwrap_or_default();
         out.push_str(&format!(
-            "| {} | {} | {:.6} | {:.6} | {:.6} | {:.6} | {:.6} |\n",
+            "| {} | {} | {:.6} | {:.6} | {:.6} | {:.6} | {:.6} | {} |\n",
             r.run_id,
             r.signals,
             r.total_pnl_sum,
             r.avg_set_ratio,
             r.legging_rate,
             liquid.pnl_sum,
-            thin.pnl_sum
+            thin.pnl_sum,
+            format_tags(&r.tags)
         ));
     }
     out.push('\n');
+    push_tag_groups_md(&mut out, runs);
+    push_provenance_md(&mut out, runs);
 
     for r in runs {
         out.push_str(&format!("## Run `{}`\n\n", r.run_id));
@@ -417,6 +446,138 @@ pub fn write_runs_summary_md(out_dir: &Path, runs: &[RunSummary]) -> anyhow::Res
     Ok(path)
 }
 
+/// Runs carrying every `key=value` in `filter`.
+pub fn filter_by_tags(runs: Vec<RunSummary>, filter: &[(String, String)]) -> Vec<RunSummary> {
+    runs.into_iter()
+        .filter(|r| filter.iter().all(|(k, v)| r.tags.get(k) == Some(v)))
+        .collect()
+}
+
+/// Totals of the runs sharing one value of a tag key.
+#[derive(Debug, Default, Clone, PartialEq, Serialize)]
+pub struct TagGroup {
+    pub runs: u64,
+    pub signals: u64,
+    pub total_pnl_sum: f64,
+    /// Signal-weighted over the group's runs.
+    pub avg_set_ratio: f64,
+    pub legging_rate: f64,
+}
+
+/// `tag key -> tag value -> group` over every key any run carries; runs without the key are
+/// grouped under `-`.
+pub fn group_by_tags(runs: &[RunSummary]) -> BTreeMap<String, BTreeMap<String, TagGroup>> {
+    let keys: std::collections::BTreeSet<&String> =
+        runs.iter().flat_map(|r| r.tags.keys()).collect();
+    let mut out = BTreeMap::new();
+    for key in keys {
+        let mut groups: BTreeMap<String, TagGroup> = BTreeMap::new();
+        for r in runs {
+            let value = r.tags.get(key).map_or("-", String::as_str);
+            let g = groups.entry(value.to_string()).or_default();
+            g.runs += 1;
+            g.signals += r.signals;
+            g.total_pnl_sum += r.total_pnl_sum;
+            g.avg_set_ratio += r.avg_set_ratio * r.signals as f64;
+            g.legging_rate += r.legging_rate * r.signals as f64;
+        }
+        for g in groups.values_mut() {
+            if g.signals > 0 {
+                g.avg_set_ratio /= g.signals as f64;
+                g.legging_rate /= g.signals as f64;
+            }
+        }
+        out.insert(key.clone(), groups);
+    }
+    out
+}
+
+fn push_tag_groups_md(out: &mut String, runs: &[RunSummary]) {
+    for (key, groups) in group_by_tags(runs) {
+        out.push_str(&format!("## By tag `{key}`\n\n"));
+        out.push_str("| value | runs | signals | total_pnl_sum | avg_set_ratio | legging_rate |\n");
+        out.push_str("|---|---:|---:|---:|---:|---:|\n");
+        for (value, g) in groups {
+            out.push_str(&format!(
+                "| {value} | {} | {} | {:.6} | {:.6} | {:.6} |\n",
+                g.runs, g.signals, g.total_pnl_sum, g.avg_set_ratio, g.legging_rate
+            ));
+        }
+        out.push('\n');
+    }
+}
+
+/// Which runs were produced by a different build or effective config than the first one.
+/// Runs without recorded provenance are listed but never flagged.
+pub fn provenance_mismatches(runs: &[RunSummary]) -> Vec<String> {
+    let known: Vec<(&RunSummary, &Provenance)> = runs
+        .iter()
+        .filter_map(|r| r.provenance.as_ref().map(|p| (r, p)))
+        .filter(|(_, p)| !p.effective_config_sha256.is_empty())
+        .collect();
+    let Some((base, base_p)) = known.first() else {
+        return Vec::new();
+    };
+    let mut out = Vec::new();
+    for (r, p) in &known[1..] {
+        if p.effective_config_sha256 != base_p.effective_config_sha256 {
+            out.push(format!(
+                "`{}` effective config differs from `{}`",
+                r.run_id, base.run_id
+            ));
+        }
+        if r.git_sha != base.git_sha {
+            out.push(format!(
+                "`{}` git_sha differs from `{}`",
+                r.run_id, base.run_id
+            ));
+        }
+        if p.cargo_features != base_p.cargo_features {
+            out.push(format!(
+                "`{}` cargo features differ from `{}`",
+                r.run_id, base.run_id
+            ));
+        }
+    }
+    out
+}
+
+fn push_provenance_md(out: &mut String, runs: &[RunSummary]) {
+    out.push_str("## Provenance\n\n");
+    out.push_str("| run_id | git_sha | host | os | cpus | features | config_sha256 |\n");
+    out.push_str("|---|---|---|---|---:|---|---|\n");
+    for r in runs {
+        let git_sha = r.git_sha.as_deref().unwrap_or("-");
+        match &r.provenance {
+            Some(p) if !p.effective_config_sha256.is_empty() => {
+                out.push_str(&format!(
+                    "| {} | {} | {} | {} | {} | {} | {} |\n",
+                    r.run_id,
+                    git_sha,
+                    p.hostname,
+                    p.os,
+                    p.cpu_count,
+                    p.cargo_features.join(" "),
+                    &p.effective_config_sha256[..p.effective_config_sha256.len().min(12)],
+                ));
+            }
+            _ => out.push_str(&format!(
+                "| {} | {git_sha} | - | - | - | - | - |\n",
+                r.run_id
+            )),
+        }
+    }
+    out.push('\n');
+    let mismatches = provenance_mismatches(runs);
+    if !mismatches.is_empty() {
+        out.push_str("> **Not like-for-like:**\n");
+        for m in mismatches {
+            out.push_str(&format!("> - {m}\n"));
+        }
+        out.push('\n');
+    }
+}
+
 fn top_reasons(agg: &BTreeMap<String, ReasonAgg>, n: usize) -> Vec<(String, u64)> {
     let mut v: Vec<(&String, &ReasonAgg)> = agg.iter().collect();
     v.sort_by(|(ra, a), (rb, b)| b.count.cmp(&a.count).then_with(|| ra.cmp(rb)));
@@ -491,7 +652,7 @@ mod tests {
 
     #[test]
     fn runs_summary_header_is_frozen() {
-        assert_eq!(RUNS_SUMMARY_HEADER.join(","), "run_id,run_dir,rows_total,rows_ok,rows_bad,rows_schema_mismatch,signals,total_pnl_sum,pnl_set_sum,pnl_left_total_sum,avg_set_ratio,legging_rate,liquid_signals,liquid_pnl_sum,liquid_avg_set_ratio,thin_signals,thin_pnl_sum,thin_avg_set_ratio,unknown_signals,unknown_pnl_sum,unknown_avg_set_ratio,top_reason_1,top_reason_1_count,top_reason_2");
+        assert_eq!(RUNS_SUMMARY_HEADER.join(","), "run_id,run_dir,rows_total,rows_ok,rows_bad,rows_schema_mismatch,signals,total_pnl_sum,pnl_set_sum,pnl_left_total_sum,avg_set_ratio,legging_rate,liquid_signals,liquid_pnl_sum,liquid_avg_set_ratio,thin_signals,thin_pnl_sum,thin_avg_set_ratio,unknown_signals,unknown_pnl_sum,unknown_avg_set_ratio,top_reason_1,top_reason_1_count,top_reason_2,tags");
     }
 
     #[test]
@@ -514,6 +675,13 @@ mod tests {
             notes_enum_version: "v1".to_string(),
             trade_poll_taker_only: None,
             sim_stress: crate::run_meta::SimStressProfile::default(),
+            abnormal_termination: None,
+            resumed_ts_unix_ms: Vec::new(),
+            provenance: crate::run_meta::Provenance::default(),
+            recorder_durability: None,
+            secret_fingerprints: Default::default(),
+            tags: [("market".to_string(), "nba".to_string())].into(),
+            live_interlocks: Vec::new(),
         };
         meta.write_to_dir(&tmp).expect("write run_meta.json");
 
@@ -557,6 +725,34 @@ mod tests {
 
         assert_eq!(s.by_reason.get("NO_TRADES").unwrap().count, 2);
         assert_eq!(s.by_reason.get("MISSING_BID").unwrap().count, 1);
+        assert_eq!(s.git_sha.as_deref(), Some("unknown"));
+        assert_eq!(format_tags(&s.tags), "market=nba");
+
+        let mut other = s.clone();
+        other.run_id = "run_z".to_string();
+        other.tags = [("market".to_string(), "nfl".to_string())].into();
+        let groups = group_by_tags(&[s.clone(), other.clone(), other.clone()]);
+        assert_eq!(groups["market"]["nba"].runs, 1);
+        assert_eq!(groups["market"]["nfl"].signals, 4);
+        assert!((groups["market"]["nfl"].avg_set_ratio - 0.85).abs() < 1e-12);
+        let nba = [("market".to_string(), "nba".to_string())];
+        let kept = filter_by_tags(vec![s.clone(), other], &nba);
+        assert_eq!(kept.len(), 1);
+        assert_eq!(kept[0].run_id, "run_x");
+
+        let mut a = s.clone();
+        a.provenance = Some(Provenance {
+            effective_config_sha256: "aa".to_string(),
+            ..Provenance::default()
+        });
+        let mut b = a.clone();
+        b.run_id = "run_y".to_string();
+        assert!(provenance_mismatches(&[a.clone(), b.clone(), s.clone()]).is_empty());
+        b.provenance.as_mut().unwrap().effective_config_sha256 = "bb".to_string();
+        assert_eq!(
+            provenance_mismatches(&[a, b]),
+            vec!["`run_y` effective config differs from `run_x`".to_string()]
+        );
 
         let _ = std::fs::remove_dir_all(&tmp);
     }
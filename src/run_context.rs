@@ -1,7 +1,26 @@
 use std::path::{Path, PathBuf};
 
+use anyhow::Context as _;
+use serde::{Deserialize, Serialize};
+
+use crate::schema::FILE_LATEST_JSON;
 use crate::types::now_ms;
 
+pub const RUN_LATEST_LINK: &str = "run_latest";
+
+/// `<data_dir>/latest.json`: the platform-independent "latest run" pointer. `run_latest` is a
+/// symlink on Unix and a junction on Windows, but either can be missing (no privilege, FAT
+/// volume, copied data dir); tools should prefer this file.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LatestPointer {
+    pub run_id: String,
+    /// Relative to the data dir.
+    pub run_dir: String,
+    pub updated_ts_ms: u64,
+    /// Set once the run has finished and its outputs are flushed.
+    pub complete: bool,
+}
+
 #[derive(Clone, Debug)]
 pub struct RunContext {
     pub run_id: String,
@@ -13,16 +32,15 @@ pub fn create_run_context(base_data_dir: &Path) -> anyhow::Result<RunContext> {
     std::fs::create_dir_all(base_data_dir)?;
 
     let start_ts_ms = now_ms();
-    let pid = std::process::id();
 
-    for attempt in 0..1000u32 {
-        let run_id = format_run_id(start_ts_ms, pid, attempt);
+    for _ in 0..1000u32 {
+        let run_id = format_run_id(start_ts_ms);
         let run_dir = base_data_dir.join(&run_id);
         if run_dir.exists() {
             continue;
         }
         std::fs::create_dir_all(&run_dir)?;
-        update_run_latest_symlink(base_data_dir, &run_dir)?;
+        point_run_latest(base_data_dir, &run_dir)?;
         write_latest_marker(&run_dir)?;
         return Ok(RunContext {
             run_id,
@@ -34,12 +52,99 @@ pub fn create_run_context(base_data_dir: &Path) -> anyhow::Result<RunContext> {
     anyhow::bail!("failed to allocate unique run_dir after many attempts")
 }
 
-fn update_run_latest_symlink(base_data_dir: &Path, run_dir: &Path) -> anyhow::Result<()> {
+/// Re-attaches to an existing run dir (`razor --resume`): same run_id and start time, with
+/// `run_latest` pointed back at it.
+pub fn resume_run_context(
+    run_dir: &Path,
+    run_id: &str,
+    start_ts_ms: u64,
+) -> anyhow::Result<RunContext> {
+    let dir_name = run_dir
+        .file_name()
+        .map(|s| s.to_string_lossy().to_string())
+        .unwrap_or_default();
+    anyhow::ensure!(
+        dir_name == run_id,
+        "run_meta.json run_id={run_id} does not match run dir {}",
+        run_dir.display()
+    );
+    if let Some(base_data_dir) = run_dir.parent() {
+        point_run_latest(base_data_dir, run_dir)?;
+    }
+    write_latest_marker(run_dir)?;
+    Ok(RunContext {
+        run_id: run_id.to_string(),
+        run_dir: run_dir.to_path_buf(),
+        start_ts_ms,
+    })
+}
+
+/// Points `run_latest` and `latest.json` at `run_dir` (a direct child of `base_data_dir`).
+pub fn point_run_latest(base_data_dir: &Path, run_dir: &Path) -> anyhow::Result<()> {
+    update_run_latest_link(base_data_dir, run_dir)?;
+    let run_id = run_dir
+        .file_name()
+        .map(|s| s.to_string_lossy().to_string())
+        .unwrap_or_default();
+    write_latest_json(
+        base_data_dir,
+        &LatestPointer {
+            run_dir: run_id.clone(),
+            run_id,
+            updated_ts_ms: now_ms(),
+            complete: false,
+        },
+    )
+}
+
+/// Marks the `latest.json` of `run_id` complete; a pointer already moved to a newer run is
+/// left alone.
+pub fn mark_latest_complete(base_data_dir: &Path, run_id: &str) -> anyhow::Result<()> {
+    match read_latest_json(base_data_dir) {
+        Some(mut p) if p.run_id == run_id => {
+            p.complete = true;
+            p.updated_ts_ms = now_ms();
+            write_latest_json(base_data_dir, &p)
+        }
+        _ => Ok(()),
+    }
+}
+
+pub fn read_latest_json(base_data_dir: &Path) -> Option<LatestPointer> {
+    let raw = std::fs::read(base_data_dir.join(FILE_LATEST_JSON)).ok()?;
+    serde_json::from_slice(&raw).ok()
+}
+
+/// The run dir the latest alias refers to: `latest.json` first, then the `run_latest` link.
+pub fn latest_run_dir(base_data_dir: &Path) -> Option<PathBuf> {
+    if let Some(p) = read_latest_json(base_data_dir) {
+        return Some(base_data_dir.join(p.run_dir));
+    }
+    let target = std::fs::read_link(base_data_dir.join(RUN_LATEST_LINK)).ok()?;
+    Some(if target.is_absolute() {
+        target
+    } else {
+        base_data_dir.join(target)
+    })
+}
+
+/// Written to a temp name and renamed, so readers never see a half-written pointer.
+fn write_latest_json(base_data_dir: &Path, pointer: &LatestPointer) -> anyhow::Result<()> {
+    let path = base_data_dir.join(FILE_LATEST_JSON);
+    let tmp = base_data_dir.join(format!("{FILE_LATEST_JSON}.tmp"));
+    std::fs::write(&tmp, serde_json::to_vec_pretty(pointer)?)
+        .with_context(|| format!("write {}", tmp.display()))?;
+    std::fs::rename(&tmp, &path).with_context(|| format!("rename {}", path.display()))?;
+    Ok(())
+}
+
+fn update_run_latest_link(base_data_dir: &Path, run_dir: &Path) -> anyhow::Result<()> {
+    let link_path = base_data_dir.join(RUN_LATEST_LINK);
+
     #[cfg(unix)]
     {
         use std::os::unix::fs::symlink;
 
-        let link_path = base_data_dir.join("run_latest");
         let target = run_dir.strip_prefix(base_data_dir).unwrap_or(run_dir);
         if let Ok(meta) = std::fs::symlink_metadata(&link_path) {
             if !meta.file_type().is_symlink() {
@@ -52,41 +157,53 @@ fn update_run_latest_symlink(base_data_dir: &Path, run_dir: &Path) -> anyhow::Re
         Ok(())
     }
 
-    #[cfg(not(unix))]
+    // A directory symlink needs Developer Mode or admin rights; a junction needs neither but
+    // must target an absolute path. When both fail, `latest.json` is the only alias.
+    #[cfg(windows)]
     {
-        let _ = (base_data_dir, run_dir);
+        use std::os::windows::fs::symlink_dir;
+
+        if std::fs::symlink_metadata(&link_path).is_ok() {
+            // Dir symlinks and junctions both go with `remove_dir`, which refuses a real,
+            // non-empty directory instead of deleting a run's contents.
+            std::fs::remove_dir(&link_path).with_context(|| {
+                format!("refusing to overwrite non-link {}", link_path.display())
+            })?;
+        }
+
+        let target = run_dir.strip_prefix(base_data_dir).unwrap_or(run_dir);
+        if symlink_dir(target, &link_path).is_ok() {
+            return Ok(());
+        }
+        // Not `canonicalize`: its `\\?\` verbatim prefix makes `mklink` fail.
+        let abs_target = std::path::absolute(run_dir)?;
+        let status = std::process::Command::new("cmd")
+            .arg("/C")
+            .arg("mklink")
+            .arg("/J")
+            .arg(&link_path)
+            .arg(&abs_target)
+            .stdout(std::process::Stdio::null())
+            .status();
+        if !status.is_ok_and(|s| s.success()) {
+            tracing::warn!(
+                link = %link_path.display(),
+                "run_latest junction failed; use latest.json"
+            );
+        }
         Ok(())
     }
-}
-
-fn format_run_id(start_ts_ms: u64, pid: u32, attempt: u32) -> String {
-    let secs = (start_ts_ms / 1000) as i64;
-    let days = secs.div_euclid(86_400);
-    let sec_of_day = secs.rem_euclid(86_400);
-    let hour = (sec_of_day / 3600) as u32;
-    let minute = ((sec_of_day % 3600) / 60) as u32;
-    let second = (sec_of_day % 60) as u32;
-
-    let (year, month, day) = civil_from_days(days);
-    let rand6 = ((start_ts_ms as u32) ^ pid ^ attempt) % 1_000_000;
 
-    format!("run_{year:04}{month:02}{day:02}_{hour:02}{minute:02}{second:02}_{rand6:06}")
+    #[cfg(not(any(unix, windows)))]
+    {
+        let _ = (link_path, run_dir);
+        Ok(())
+    }
 }
 
-// UTC date conversion (Howard Hinnant's algorithm).
-// Input: days since 1970-01-01.
-fn civil_from_days(days_since_epoch: i64) -> (i32, u32, u32) {
-    let z = days_since_epoch + 719_468;
-    let era = if z >= 0 { z } else { z - 146_096 }.div_euclid(146_097);
-    let doe = z - era * 146_097;
-    let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096).div_euclid(365);
-    let y = yoe + era * 400;
-    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
-    let mp = (5 * doy + 2).div_euclid(153);
-    let d = doy - (153 * mp + 2).div_euclid(5) + 1;
-    let m = mp + if mp < 10 { 3 } else { -9 };
-    let year = y + i64::from(m <= 2);
-    (year as i32, m as u32, d as u32)
+/// `run_<ULID>`: sorts by start time and stays unique across hosts and processes.
+fn format_run_id(start_ts_ms: u64) -> String {
+    format!("run_{}", crate::ulid::new_ulid(start_ts_ms))
 }
 
 fn write_latest_marker(run_dir: &Path) -> anyhow::Result<()> {
@@ -101,9 +218,34 @@ mod tests {
 
     #[test]
     fn run_id_format_is_stable() {
-        let id = format_run_id(1_700_000_000_000, 1234, 0);
+        let id = format_run_id(1_700_000_000_000);
         assert!(id.starts_with("run_"));
-        assert!(id.contains('_'));
-        assert_eq!(id.len(), "run_YYYYMMDD_HHMMSS_000000".len());
+        assert_eq!(id.len(), "run_".len() + crate::ulid::ULID_LEN);
+        assert_eq!(
+            crate::run_index::start_ts_from_run_id(&id),
+            Some(1_700_000_000_000)
+        );
+        assert_ne!(id, format_run_id(1_700_000_000_000));
+    }
+
+    #[test]
+    fn latest_json_follows_the_newest_run_and_is_marked_complete() {
+        let data = std::env::temp_dir().join(format!("razor_latest_{}", now_ms()));
+        let first = create_run_context(&data).unwrap();
+        assert_eq!(latest_run_dir(&data), Some(first.run_dir.clone()));
+
+        let second_dir = data.join("run_20260101_000000_000001");
+        std::fs::create_dir_all(&second_dir).unwrap();
+        point_run_latest(&data, &second_dir).unwrap();
+        mark_latest_complete(&data, &first.run_id).unwrap();
+        let p = read_latest_json(&data).unwrap();
+        assert_eq!(p.run_id, "run_20260101_000000_000001");
+        assert!(!p.complete, "stale run must not complete the newer pointer");
+
+        mark_latest_complete(&data, &p.run_id).unwrap();
+        assert!(read_latest_json(&data).unwrap().complete);
+        assert_eq!(latest_run_dir(&data), Some(second_dir));
+        assert!(!data.join(format!("{FILE_LATEST_JSON}.tmp")).exists());
+        let _ = std::fs::remove_dir_all(&data);
     }
 }
@@ -0,0 +1,144 @@
+//! `<data_dir>/runs_index.jsonl`: one line per run at start and another at end (same run_id, now
+//! with `end_ts_ms` and `verdict`); the last line per run wins. Compare and gc order runs by it
+//! instead of by dir name, since ULID run ids and the older `run_YYYYMMDD_HHMMSS_*` ids do not
+//! sort together.
+
+use std::io::Write as _;
+use std::path::{Path, PathBuf};
+
+use anyhow::Context as _;
+use serde::{Deserialize, Serialize};
+
+use crate::schema::FILE_RUNS_INDEX;
+use crate::types::parse_rfc3339_to_unix_secs;
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RunIndexEntry {
+    pub run_id: String,
+    /// Run dir, relative to the data dir.
+    pub dir: String,
+    pub start_ts_ms: u64,
+    /// `None` while the run is going (or if it died before writing its report).
+    #[serde(default)]
+    pub end_ts_ms: Option<u64>,
+    /// `dry_run` / `live_sim`.
+    pub mode: String,
+    /// `GO` / `NO_GO` from the end-of-run report.
+    #[serde(default)]
+    pub verdict: Option<String>,
+}
+
+/// Appends `entry` to the index of the data dir holding `run_dir`.
+pub fn record(run_dir: &Path, entry: &RunIndexEntry) -> anyhow::Result<()> {
+    let data_dir = run_dir.parent().unwrap_or(Path::new("."));
+    let path = data_dir.join(FILE_RUNS_INDEX);
+    let mut f = std::fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(&path)
+        .with_context(|| format!("open {}", path.display()))?;
+    writeln!(f, "{}", serde_json::to_string(entry)?)
+        .with_context(|| format!("append {}", path.display()))?;
+    Ok(())
+}
+
+/// Latest entry per run, by start time; unparsable lines are skipped.
+pub fn read(data_dir: &Path) -> Vec<RunIndexEntry> {
+    let mut out: Vec<RunIndexEntry> = Vec::new();
+    for e in std::fs::read_to_string(data_dir.join(FILE_RUNS_INDEX))
+        .unwrap_or_default()
+        .lines()
+        .filter_map(|l| serde_json::from_str::<RunIndexEntry>(l).ok())
+    {
+        match out.iter_mut().find(|o| o.run_id == e.run_id) {
+            Some(o) => *o = e,
+            None => out.push(e),
+        }
+    }
+    out.sort_by(|a, b| (a.start_ts_ms, &a.run_id).cmp(&(b.start_ts_ms, &b.run_id)));
+    out
+}
+
+/// Start time encoded in a run id: the ULID timestamp of `run_<ULID>`, or the UTC second of a
+/// legacy `run_YYYYMMDD_HHMMSS_NNNNNN`.
+pub fn start_ts_from_run_id(run_id: &str) -> Option<u64> {
+    let rest = run_id.strip_prefix("run_")?;
+    if let Some(ts) = crate::ulid::timestamp_ms(rest) {
+        return Some(ts);
+    }
+    let (date, time) = (rest.get(0..8)?, rest.get(9..15)?);
+    let iso = format!(
+        "{}-{}-{}T{}:{}:{}Z",
+        &date[0..4],
+        &date[4..6],
+        &date[6..8],
+        &time[0..2],
+        &time[2..4],
+        &time[4..6]
+    );
+    parse_rfc3339_to_unix_secs(&iso).map(|s| s * 1000)
+}
+
+/// Sorts run dirs of `data_dir` oldest first: index start time, else the time in the run id;
+/// dirs with neither go last, by name.
+pub fn sort_run_dirs(data_dir: &Path, dirs: &mut [PathBuf]) {
+    let index = read(data_dir);
+    let key = |p: &PathBuf| {
+        let name = p
+            .file_name()
+            .map(|n| n.to_string_lossy().to_string())
+            .unwrap_or_default();
+        let ts = index
+            .iter()
+            .find(|e| e.dir == name)
+            .map(|e| e.start_ts_ms)
+            .or_else(|| start_ts_from_run_id(&name))
+            .unwrap_or(u64::MAX);
+        (ts, name)
+    };
+    dirs.sort_by_cached_key(key);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn last_line_wins_and_mixed_ids_sort_by_start_time() {
+        let data = std::env::temp_dir().join(format!("razor_runs_index_{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&data);
+        std::fs::create_dir_all(&data).unwrap();
+        let ulid_run = format!("run_{}", crate::ulid::new_ulid(1_800_000_000_000));
+        let start = RunIndexEntry {
+            run_id: ulid_run.clone(),
+            dir: ulid_run.clone(),
+            start_ts_ms: 1_800_000_000_000,
+            end_ts_ms: None,
+            mode: "dry_run".to_string(),
+            verdict: None,
+        };
+        record(&data.join(&ulid_run), &start).unwrap();
+        let end = RunIndexEntry {
+            end_ts_ms: Some(1_800_000_100_000),
+            verdict: Some("GO".to_string()),
+            ..start.clone()
+        };
+        record(&data.join(&ulid_run), &end).unwrap();
+        assert_eq!(read(&data), vec![end]);
+
+        let legacy = "run_20231114_221320_000042";
+        assert_eq!(start_ts_from_run_id(legacy), Some(1_700_000_000_000));
+        let unindexed = format!("run_{}", crate::ulid::new_ulid(1_750_000_000_000));
+        let mut dirs: Vec<PathBuf> = [ulid_run.as_str(), "run_other", legacy, &unindexed]
+            .iter()
+            .map(|n| data.join(n))
+            .collect();
+        sort_run_dirs(&data, &mut dirs);
+        let names: Vec<_> = dirs.iter().map(|d| d.file_name().unwrap()).collect();
+        assert_eq!(
+            names,
+            [legacy, unindexed.as_str(), ulid_run.as_str(), "run_other"]
+        );
+        let _ = std::fs::remove_dir_all(&data);
+    }
+}
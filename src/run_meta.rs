@@ -1,8 +1,11 @@
+use std::collections::BTreeMap;
 use std::path::Path;
 
 use anyhow::Context as _;
 use serde::{Deserialize, Serialize};
 
+use crate::config::{FsyncPolicy, RecorderConfig};
+use crate::live_gate::LiveInterlockDecision;
 use crate::schema::FILE_RUN_META_JSON;
 
 #[derive(Debug, Clone, Serialize, Deserialize, Default)]
@@ -32,6 +35,116 @@ pub struct RunMeta {
     pub trade_poll_taker_only: Option<bool>,
     #[serde(default)]
     pub sim_stress: SimStressProfile,
+    /// Set when the run did not end normally (e.g. `"panic"`, see `crash_report.json`).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub abnormal_termination: Option<String>,
+    /// Every `razor --resume` of this run_id (unix ms), oldest first.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub resumed_ts_unix_ms: Vec<u64>,
+    /// Where and how the run was produced; empty in run dirs written before it was recorded.
+    #[serde(default)]
+    pub provenance: Provenance,
+    /// `[recorder]` flush/fsync cadence the appenders ran with; `None` in run dirs written
+    /// before it was recorded.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub recorder_durability: Option<RecorderDurability>,
+    /// Credential config keys (`*_env` secrets that resolved, literals redacted from the config
+    /// snapshot) -> `sha256:` fingerprint of the value; never the value itself.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub secret_fingerprints: BTreeMap<String, String>,
+    /// `[run.labels]` merged with `--tag key=value` (tags win); copied to report.json and
+    /// runs_summary.csv so experiments can be filtered and grouped.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub tags: BTreeMap<String, String>,
+    /// Live interlock decision of the initial start and of every resume, oldest first.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub live_interlocks: Vec<LiveInterlockDecision>,
+}
+
+/// Global flush/fsync settings; per-file overrides are in `run_config.toml`.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+pub struct RecorderDurability {
+    pub fsync_policy: FsyncPolicy,
+    pub flush_interval_ms: u64,
+}
+
+impl From<&RecorderConfig> for RecorderDurability {
+    fn from(cfg: &RecorderConfig) -> Self {
+        Self {
+            fsync_policy: cfg.fsync_policy,
+            flush_interval_ms: cfg.flush_interval_ms,
+        }
+    }
+}
+
+/// Machine, build and invocation details, so `run_compare` can tell apart runs that differ
+/// only in where or how they were produced.
+#[derive(Debug, Clone, Serialize, Deserialize, Default, PartialEq, Eq)]
+pub struct Provenance {
+    #[serde(default)]
+    pub hostname: String,
+    /// `std::env::consts::{OS, ARCH}`, e.g. `linux/x86_64`.
+    #[serde(default)]
+    pub os: String,
+    #[serde(default)]
+    pub cpu_count: usize,
+    #[serde(default)]
+    pub cargo_features: Vec<String>,
+    #[serde(default)]
+    pub argv: Vec<String>,
+    /// SHA-256 of the parsed config (defaults filled in, overrides applied) as canonical JSON
+    /// with sorted keys; equal hashes mean the run used the same effective settings.
+    #[serde(default)]
+    pub effective_config_sha256: String,
+}
+
+impl Provenance {
+    /// Provenance of the current process for a run using `effective_config`.
+    pub fn current<C: Serialize>(effective_config: &C) -> anyhow::Result<Self> {
+        Ok(Self {
+            hostname: hostname(),
+            os: format!("{}/{}", std::env::consts::OS, std::env::consts::ARCH),
+            cpu_count: std::thread::available_parallelism().map_or(0, |n| n.get()),
+            cargo_features: enabled_cargo_features(),
+            argv: std::env::args().collect(),
+            effective_config_sha256: config_sha256(effective_config)?,
+        })
+    }
+}
+
+/// Hash over `serde_json::Value`, whose maps are sorted, so field order in the source TOML and
+/// struct layout do not matter.
+pub fn config_sha256<C: Serialize>(cfg: &C) -> anyhow::Result<String> {
+    use sha2::{Digest as _, Sha256};
+
+    let canonical = serde_json::to_value(cfg).context("serialize effective config")?;
+    let bytes = serde_json::to_vec(&canonical)?;
+    Ok(hex::encode(Sha256::digest(&bytes)))
+}
+
+/// Cargo features this binary was built with; add an entry for each `[features]` flag.
+fn enabled_cargo_features() -> Vec<String> {
+    let mut features = Vec::new();
+    if cfg!(feature = "live") {
+        features.push("live".to_string());
+    }
+    features
+}
+
+fn hostname() -> String {
+    #[cfg(unix)]
+    {
+        let mut buf = [0u8; 256];
+        // SAFETY: `buf` is valid for `buf.len()` bytes; gethostname NUL-terminates on success.
+        let rc = unsafe { libc::gethostname(buf.as_mut_ptr().cast(), buf.len()) };
+        if rc == 0 {
+            let end = buf.iter().position(|b| *b == 0).unwrap_or(buf.len());
+            return String::from_utf8_lossy(&buf[..end]).to_string();
+        }
+    }
+    std::env::var("COMPUTERNAME")
+        .or_else(|_| std::env::var("HOSTNAME"))
+        .unwrap_or_else(|_| "unknown".to_string())
 }
 
 impl RunMeta {
@@ -42,7 +155,6 @@ impl RunMeta {
         Ok(())
     }
 
-    #[allow(dead_code)]
     pub fn read_from_dir(run_dir: &Path) -> anyhow::Result<Self> {
         let path = run_dir.join(FILE_RUN_META_JSON);
         let raw = std::fs::read(&path).with_context(|| format!("read {}", path.display()))?;
@@ -50,6 +162,42 @@ impl RunMeta {
     }
 }
 
+/// Checks a tag/label: key `[A-Za-z0-9_.-]+`, value non-empty without `;` or line breaks (the
+/// `runs_summary.csv` `tags` cell is `k=v;k=v`).
+pub fn validate_tag(key: &str, value: &str) -> Result<(), String> {
+    let key_ok = !key.is_empty()
+        && key
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || matches!(c, '_' | '.' | '-'));
+    if !key_ok {
+        return Err(format!("tag key {key:?} must match [A-Za-z0-9_.-]+"));
+    }
+    if value.is_empty() || value.contains([';', '\n', '\r']) {
+        return Err(format!(
+            "tag {key}: value {value:?} must be non-empty without ';' or line breaks"
+        ));
+    }
+    Ok(())
+}
+
+/// Parses a `--tag key=value` argument.
+pub fn parse_tag(s: &str) -> Result<(String, String), String> {
+    let (key, value) = s
+        .split_once('=')
+        .ok_or_else(|| format!("tag {s:?} must be key=value"))?;
+    let (key, value) = (key.trim(), value.trim());
+    validate_tag(key, value)?;
+    Ok((key.to_string(), value.to_string()))
+}
+
+/// `k=v;k=v`, sorted by key.
+pub fn format_tags(tags: &BTreeMap<String, String>) -> String {
+    tags.iter()
+        .map(|(k, v)| format!("{k}={v}"))
+        .collect::<Vec<_>>()
+        .join(";")
+}
+
 pub fn env_git_sha() -> String {
     std::env::var("GIT_SHA")
         .ok()
@@ -88,3 +236,53 @@ fn read_git_commit() -> Option<String> {
         Some(head.to_string())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn config_hash_ignores_key_order_and_old_meta_still_parses() {
+        let a: toml::Value = toml::from_str("x = 1\n[s]\nb = 2\na = 3\n").unwrap();
+        let b: toml::Value = toml::from_str("[s]\na = 3\nb = 2\n\n[t]\n").unwrap();
+        let b_same: toml::Value = toml::from_str("[s]\na = 3\nb = 2\n").unwrap();
+        assert_ne!(config_sha256(&a).unwrap(), config_sha256(&b).unwrap());
+        let mut a = a;
+        a.as_table_mut().unwrap().remove("x");
+        assert_eq!(config_sha256(&a).unwrap(), config_sha256(&b_same).unwrap());
+
+        let p = Provenance::current(&a).unwrap();
+        assert!(p.cpu_count > 0 && !p.argv.is_empty() && !p.hostname.is_empty());
+
+        let old: RunMeta = serde_json::from_str(
+            r#"{"run_id":"r","schema_version":"v","git_sha":"g","start_ts_unix_ms":0,
+                "config_path":"c","trade_ts_source":"local","notes_enum_version":"v1"}"#,
+        )
+        .unwrap();
+        assert_eq!(old.provenance, Provenance::default());
+        assert!(old.tags.is_empty());
+    }
+
+    #[test]
+    fn tags_parse_and_format() {
+        assert_eq!(
+            parse_tag("fee_model = v2"),
+            Ok(("fee_model".to_string(), "v2".to_string()))
+        );
+        assert_eq!(
+            parse_tag("note=a=b"),
+            Ok(("note".to_string(), "a=b".to_string()))
+        );
+        assert!(parse_tag("market").is_err());
+        assert!(parse_tag("=nba").is_err());
+        assert!(parse_tag("market=").is_err());
+        assert!(parse_tag("market=nba;nfl").is_err());
+        assert!(parse_tag("mar ket=nba").is_err());
+
+        let tags: BTreeMap<String, String> = [("market", "nba"), ("fee_model", "v2")]
+            .into_iter()
+            .map(|(k, v)| (k.to_string(), v.to_string()))
+            .collect();
+        assert_eq!(format_tags(&tags), "fee_model=v2;market=nba");
+    }
+}
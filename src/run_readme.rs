@@ -0,0 +1,263 @@
+use std::collections::BTreeMap;
+use std::io::Read as _;
+use std::path::Path;
+
+use anyhow::Context as _;
+
+use crate::manifest::list_artifacts;
+use crate::report::Report;
+use crate::schema::{
+    file_versions, FILE_API_USAGE_JSONL, FILE_CALIBRATION_LOG, FILE_CALIBRATION_PATCH,
+    FILE_CALIBRATION_SUGGEST, FILE_CRASH_REPORT_JSON, FILE_DEDUP_COLLISIONS_JSONL,
+    FILE_HEALTH_JSONL, FILE_META_JSON, FILE_PARTITIONS_JSONL, FILE_PROFILE_JSONL,
+    FILE_PROGRESS_JSONL, FILE_RAW_WS_JSONL, FILE_RECONCILIATION_JSON, FILE_REPORT_HTML,
+    FILE_REPORT_JSON, FILE_REPORT_MD, FILE_RUN_CONFIG, FILE_RUN_META_JSON, FILE_RUN_README,
+    FILE_SCHEMA_VERSION, FILE_SHADOW_LOG, FILE_SHADOW_LOG_JSONL, FILE_SIGNALS, FILE_SNAPSHOTS,
+    FILE_TICKS, FILE_TRADES, FILE_TRADE_LOG, SCHEMA_VERSION,
+};
+
+/// One line per artifact the run can produce; anything else is listed without a description.
+const DESCRIPTIONS: [(&str, &str); 26] = [
+    (FILE_REPORT_MD, "Day14 report (human-readable)"),
+    (
+        FILE_REPORT_HTML,
+        "Day14 verdict page with PnL, set_ratio and per-bucket charts",
+    ),
+    (
+        FILE_REPORT_JSON,
+        "Day14 report: totals, buckets, worst 20, verdict",
+    ),
+    (
+        FILE_SHADOW_LOG,
+        "one settled shadow signal per row (Frozen Spec accounting)",
+    ),
+    (
+        FILE_SHADOW_LOG_JSONL,
+        "shadow_log.csv rows as JSON with nested legs and reason arrays",
+    ),
+    (
+        FILE_SIGNALS,
+        "every signal shadow accepted, for settling across a restart",
+    ),
+    (FILE_TRADE_LOG, "live_sim OMS actions per leg"),
+    (FILE_TICKS, "top-of-book per token update"),
+    (FILE_TRADES, "public trades per market/token (deduplicated)"),
+    (
+        FILE_SNAPSHOTS,
+        "per-market leg snapshots at the snapshot interval",
+    ),
+    (FILE_RAW_WS_JSONL, "raw market WebSocket frames"),
+    (FILE_HEALTH_JSONL, "periodic health counters"),
+    (FILE_PROGRESS_JSONL, "periodic progress lines"),
+    (
+        FILE_CALIBRATION_LOG,
+        "live_sim fill_share calibration samples",
+    ),
+    (
+        FILE_CALIBRATION_SUGGEST,
+        "suggested fill_share patch from calibration",
+    ),
+    (
+        FILE_CALIBRATION_PATCH,
+        "rolling per-bucket/per-token fill_share estimates with confidence",
+    ),
+    (
+        FILE_RECONCILIATION_JSON,
+        "live_sim on-chain balances vs trade_log fills, with breaks",
+    ),
+    (FILE_RUN_CONFIG, "effective config snapshot"),
+    (
+        FILE_RUN_META_JSON,
+        "run metadata: git sha, provenance, markets, timing",
+    ),
+    (FILE_META_JSON, "run start metadata"),
+    (
+        FILE_SCHEMA_VERSION,
+        "schema version of every recorded format",
+    ),
+    (FILE_PARTITIONS_JSONL, "hourly partition open/close events"),
+    (
+        FILE_DEDUP_COLLISIONS_JSONL,
+        "trade ids seen with differing contents",
+    ),
+    (FILE_PROFILE_JSONL, "self-profiling samples"),
+    (
+        FILE_API_USAGE_JSONL,
+        "API requests per endpoint per hour (status classes, 429s, peak 10s)",
+    ),
+    (FILE_CRASH_REPORT_JSON, "panic/crash details"),
+];
+
+#[derive(Default)]
+struct ArtifactGroup {
+    files: u64,
+    bytes: u64,
+    rows: Option<u64>,
+}
+
+/// Newline count; `None` for files that are not line-oriented tables.
+fn count_rows(path: &Path, name: &str) -> anyhow::Result<Option<u64>> {
+    let is_csv = name.ends_with(".csv");
+    if !is_csv && !name.ends_with(".jsonl") {
+        return Ok(None);
+    }
+    let mut f = std::fs::File::open(path).with_context(|| format!("open {}", path.display()))?;
+    let mut buf = vec![0u8; 1 << 20];
+    let mut lines = 0u64;
+    loop {
+        let n = f.read(&mut buf)?;
+        if n == 0 {
+            break;
+        }
+        lines += buf[..n].iter().filter(|b| **b == b'\n').count() as u64;
+    }
+    Ok(Some(if is_csv {
+        lines.saturating_sub(1)
+    } else {
+        lines
+    }))
+}
+
+/// Base artifact name: hourly partitions (`YYYY-MM-DD/HH/ticks.csv`) and rotated segments
+/// (`raw_ws.jsonl.rotated_*`) fold into their file.
+fn group_name(rel: &str) -> String {
+    let file = rel.rsplit('/').next().unwrap_or(rel);
+    match file.split_once(".rotated_") {
+        Some((base, _)) => base.to_string(),
+        None => file.to_string(),
+    }
+}
+
+fn render(run_dir: &Path, report: &Report) -> anyhow::Result<String> {
+    let mut groups: BTreeMap<String, ArtifactGroup> = BTreeMap::new();
+    for rel in list_artifacts(run_dir)? {
+        if rel == FILE_RUN_README || (rel.contains('/') && !rel.starts_with("20")) {
+            // Tool output dirs (replay/, brain_sweep/, ...) describe themselves.
+            continue;
+        }
+        let name = group_name(&rel);
+        let path = run_dir.join(&rel);
+        let g = groups.entry(name.clone()).or_default();
+        g.files += 1;
+        g.bytes += std::fs::metadata(&path)?.len();
+        if let Some(rows) = count_rows(&path, &name)? {
+            *g.rows.get_or_insert(0) += rows;
+        }
+    }
+
+    let versions = file_versions();
+    let verdict = if report.verdict.go { "GO" } else { "NO GO" };
+    let mut out = String::new();
+    out.push_str(&format!("# Razor run `{}`\n\n", report.run_id));
+    out.push_str("Written by `razor` at the end of the run.\n\n");
+    out.push_str("## Key results\n\n");
+    out.push_str(&format!("- run_id: `{}`\n", report.run_id));
+    out.push_str(&format!("- schema_version: `{SCHEMA_VERSION}`\n"));
+    out.push_str(&format!(
+        "- period (unix ms): {} .. {}\n",
+        report.period.start_unix_ms, report.period.end_unix_ms
+    ));
+    out.push_str(&format!("- verdict: **{verdict}**"));
+    if !report.verdict.reasons.is_empty() {
+        out.push_str(&format!(" ({})", report.verdict.reasons.join("; ")));
+    }
+    out.push('\n');
+    out.push_str(&format!("- signals: {}\n", report.totals.signals));
+    out.push_str(&format!(
+        "- total_shadow_pnl: {:.6}\n",
+        report.totals.total_shadow_pnl
+    ));
+    out.push_str(&format!(
+        "- avg_set_ratio: {:.6}\n",
+        report.totals.avg_set_ratio
+    ));
+    for d in &report.degraded_files {
+        out.push_str(&format!(
+            "- WARNING: `{}` stopped being written at {} ({})\n",
+            d.file, d.ts_ms, d.error
+        ));
+    }
+    out.push_str("\nSee `report.md` for buckets, strategies, worst signals and stress.\n\n");
+
+    out.push_str("## Artifacts\n\n");
+    out.push_str("| file | format version | files | rows | bytes | contents |\n");
+    out.push_str("|---|---|---:|---:|---:|---|\n");
+    for (name, g) in &groups {
+        let desc = DESCRIPTIONS
+            .iter()
+            .find(|(f, _)| f == name)
+            .map(|(_, d)| *d)
+            .unwrap_or("");
+        out.push_str(&format!(
+            "| `{name}` | {} | {} | {} | {} | {desc} |\n",
+            versions.get(name).map(String::as_str).unwrap_or("-"),
+            g.files,
+            g.rows
+                .map(|r| r.to_string())
+                .unwrap_or_else(|| "-".to_string()),
+            g.bytes,
+        ));
+    }
+    out.push_str(
+        "\nRows exclude CSV headers. Partitioned (`YYYY-MM-DD/HH/`) and rotated files are \
+         summed per artifact; `razor verify <run_dir>` re-checks every file against \
+         `manifest.json`.\n",
+    );
+    Ok(out)
+}
+
+/// Writes `README.md` into the run dir describing what it contains. Called after the report,
+/// before the manifest so the manifest covers it.
+pub fn write_run_readme(run_dir: &Path, report: &Report) -> anyhow::Result<()> {
+    let body = render(run_dir, report)?;
+    let path = run_dir.join(FILE_RUN_README);
+    std::fs::write(&path, body).with_context(|| format!("write {}", path.display()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::report::{generate_report_files, ReportThresholds};
+    use crate::schema::SHADOW_HEADER;
+
+    #[test]
+    fn readme_lists_artifacts_with_rows_and_key_results() {
+        let dir = std::env::temp_dir().join(format!("razor_readme_{}", crate::types::now_ms()));
+        std::fs::create_dir_all(dir.join("2026-01-01/00")).unwrap();
+        std::fs::create_dir_all(dir.join("2026-01-01/01")).unwrap();
+        std::fs::write(
+            dir.join(FILE_SHADOW_LOG),
+            format!("{}\n", SHADOW_HEADER.join(",")),
+        )
+        .unwrap();
+        std::fs::write(dir.join("2026-01-01/00").join(FILE_TICKS), "h\n1\n2\n").unwrap();
+        std::fs::write(dir.join("2026-01-01/01").join(FILE_TICKS), "h\n3\n").unwrap();
+        std::fs::write(dir.join(FILE_RAW_WS_JSONL), "{}\n").unwrap();
+        std::fs::write(
+            dir.join(format!("{FILE_RAW_WS_JSONL}.rotated_1")),
+            "{}\n{}\n",
+        )
+        .unwrap();
+
+        let report = generate_report_files(
+            &dir,
+            "run_x",
+            ReportThresholds {
+                min_total_shadow_pnl: 0.0,
+                min_avg_set_ratio: 0.85,
+            },
+            None,
+        )
+        .unwrap();
+        write_run_readme(&dir, &report).unwrap();
+        let md = std::fs::read_to_string(dir.join(FILE_RUN_README)).unwrap();
+
+        assert!(md.contains("# Razor run `run_x`"), "{md}");
+        assert!(md.contains("- verdict: **NO GO**"), "{md}");
+        assert!(md.contains("| `ticks.csv` | v1 | 2 | 3 |"), "{md}");
+        assert!(md.contains("| `raw_ws.jsonl` | v1 | 2 | 3 |"), "{md}");
+        assert!(md.contains("| `shadow_log.csv` | v9 | 1 | 0 |"), "{md}");
+        assert!(!md.contains("| `README.md` |"), "{md}");
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
@@ -4,27 +4,56 @@ use std::path::Path;
 use anyhow::Context as _;
 use serde::Serialize;
 
-pub const SCHEMA_VERSION: &str = "1.3.2a";
+/// Spec version stamped on every frozen row; bumped whenever a frozen CSV header changes.
+/// `1.3.2b`: shadow_log v9 (per-leg bucket/fill share, sided volume, fee model) and trades v4
+/// (`side`).
+pub const SCHEMA_VERSION: &str = "1.3.2b";
+/// The version before `SCHEMA_VERSION`; only the `schema_adapt` upgrades relabel its rows.
+pub const PREVIOUS_SCHEMA_VERSION: &str = "1.3.2a";
 
 pub const FILE_TICKS: &str = "ticks.csv";
 pub const FILE_TRADES: &str = "trades.csv";
 pub const FILE_SNAPSHOTS: &str = "snapshots.csv";
 pub const FILE_SHADOW_LOG: &str = "shadow_log.csv";
+/// Optional nested mirror of `shadow_log.csv` (`shadow.jsonl_log`, see `rows::ShadowRecord`).
+pub const FILE_SHADOW_LOG_JSONL: &str = "shadow_log.jsonl";
 pub const FILE_REPORT_JSON: &str = "report.json";
 pub const FILE_REPORT_MD: &str = "report.md";
+pub const FILE_REPORT_HTML: &str = "report.html";
 pub const FILE_SCHEMA_VERSION: &str = "schema_version.json";
 pub const FILE_RUN_CONFIG: &str = "config.toml";
 pub const FILE_META_JSON: &str = "meta.json";
 pub const FILE_RUN_META_JSON: &str = "run_meta.json";
 pub const FILE_HEALTH_JSONL: &str = "health.jsonl";
+pub const FILE_PROGRESS_JSONL: &str = "progress.jsonl";
+pub const FILE_CRASH_REPORT_JSON: &str = "crash_report.json";
+pub const FILE_DEDUP_COLLISIONS_JSONL: &str = "dedup_collisions.jsonl";
+pub const FILE_PROFILE_JSONL: &str = "profile.jsonl";
+pub const FILE_API_USAGE_JSONL: &str = "api_usage.jsonl";
+pub const FILE_PARTITIONS_JSONL: &str = "partitions.jsonl";
+pub const FILE_MANIFEST_JSON: &str = "manifest.json";
+pub const FILE_RUN_README: &str = "README.md";
 pub const FILE_RAW_WS_JSONL: &str = "raw_ws.jsonl";
 pub const FILE_TRADE_LOG: &str = "trade_log.csv";
+pub const FILE_SIGNALS: &str = "signals.csv";
 pub const FILE_CALIBRATION_LOG: &str = "calibration_log.csv";
 pub const FILE_CALIBRATION_SUGGEST: &str = "calibration_suggest.toml";
+pub const FILE_CALIBRATION_PATCH: &str = "calibration_patch.toml";
+pub const FILE_RECONCILIATION_JSON: &str = "reconciliation.json";
+/// Live gateway only: user-channel order/trade frames (see `user_feed`).
+pub const FILE_USER_EVENTS_JSONL: &str = "user_events.jsonl";
+/// Lives in the data dir, next to the run dirs (see `run_context::LatestPointer`).
+pub const FILE_LATEST_JSON: &str = "latest.json";
+/// Lives in the data dir; one line per run `razor archive` packed (see `archive`).
+pub const FILE_ARCHIVE_INDEX: &str = "archive_index.jsonl";
+/// Lives in the data dir; the signed approval the live gateway requires (see `go_live`).
+pub const FILE_GO_LIVE_TOKEN: &str = "go_live_token.json";
+/// Lives in the data dir; run start/end lines (see `run_index`).
+pub const FILE_RUNS_INDEX: &str = "runs_index.jsonl";
 
 pub const DUMP_SLIPPAGE_ASSUMED: f64 = 0.05;
 
-pub const TRADES_HEADER: [&str; 8] = [
+pub const TRADES_HEADER: [&str; 9] = [
     "ts_ms",
     "market_id",
     "token_id",
@@ -33,6 +62,7 @@ pub const TRADES_HEADER: [&str; 8] = [
     "trade_id",
     "ingest_ts_ms",
     "exchange_ts_ms",
+    "side",
 ];
 
 pub const SNAPSHOTS_HEADER: [&str; 15] = [
@@ -53,7 +83,7 @@ pub const SNAPSHOTS_HEADER: [&str; 15] = [
     "leg2_depth3_usdc",
 ];
 
-pub const SHADOW_HEADER: [&str; 38] = [
+pub const SHADOW_HEADER: [&str; 53] = [
     "run_id",
     "schema_version",
     "signal_id",
@@ -64,6 +94,7 @@ pub const SHADOW_HEADER: [&str; 38] = [
     "strategy",
     "bucket",
     "worst_leg_token_id",
+    "bucket_classifier",
     "q_req",
     "legs_n",
     "q_set",
@@ -71,17 +102,29 @@ pub const SHADOW_HEADER: [&str; 38] = [
     "leg0_p_limit",
     "leg0_best_bid",
     "leg0_v_mkt",
+    "leg0_v_mkt_all",
+    "leg0_v_mkt_sided",
     "leg0_q_fill",
+    "leg0_bucket",
+    "leg0_fill_share",
     "leg1_token_id",
     "leg1_p_limit",
     "leg1_best_bid",
     "leg1_v_mkt",
+    "leg1_v_mkt_all",
+    "leg1_v_mkt_sided",
     "leg1_q_fill",
+    "leg1_bucket",
+    "leg1_fill_share",
     "leg2_token_id",
     "leg2_p_limit",
     "leg2_best_bid",
     "leg2_v_mkt",
+    "leg2_v_mkt_all",
+    "leg2_v_mkt_sided",
     "leg2_q_fill",
+    "leg2_bucket",
+    "leg2_fill_share",
     "cost_set",
     "proceeds_set",
     "pnl_set",
@@ -91,6 +134,54 @@ pub const SHADOW_HEADER: [&str; 38] = [
     "set_ratio",
     "fill_share_p25_used",
     "dump_slippage_assumed",
+    "volume_mode",
+    "fee_model",
+    "notes",
+];
+
+/// Signal ledger written by shadow on enqueue, so a restart can settle what was still pending.
+pub const SIGNALS_HEADER: [&str; 42] = [
+    "run_id",
+    "signal_id",
+    "signal_ts_unix_ms",
+    "market_id",
+    "strategy",
+    "bucket",
+    "bucket_classifier",
+    "worst_leg_index",
+    "worst_spread_bps",
+    "worst_depth3_usdc",
+    "is_depth3_degraded",
+    "depth_levels",
+    "worst_depth_usdc",
+    "q_req",
+    "raw_cost_bps",
+    "raw_edge_bps",
+    "hard_fees_bps",
+    "risk_premium_bps",
+    "expected_net_bps",
+    "legs_n",
+    "leg0_token_id",
+    "leg0_side",
+    "leg0_p_limit",
+    "leg0_qty",
+    "leg0_best_bid",
+    "leg0_best_ask",
+    "leg0_bucket",
+    "leg1_token_id",
+    "leg1_side",
+    "leg1_p_limit",
+    "leg1_qty",
+    "leg1_best_bid",
+    "leg1_best_ask",
+    "leg1_bucket",
+    "leg2_token_id",
+    "leg2_side",
+    "leg2_p_limit",
+    "leg2_qty",
+    "leg2_best_bid",
+    "leg2_best_ask",
+    "leg2_bucket",
     "notes",
 ];
 
@@ -136,11 +227,8 @@ struct SchemaVersionFile {
     files: BTreeMap<String, String>,
 }
 
-pub fn write_schema_version_json(
-    data_dir: &Path,
-    schema_version: &str,
-    generated_at_unix_ms: u64,
-) -> anyhow::Result<()> {
+/// Per-file format versions recorded in `schema_version.json`.
+pub fn file_versions() -> BTreeMap<String, String> {
     let mut files = BTreeMap::new();
     files.insert(FILE_SCHEMA_VERSION.to_string(), "v1".to_string());
     files.insert(FILE_RUN_CONFIG.to_string(), "v1".to_string());
@@ -149,14 +237,29 @@ pub fn write_schema_version_json(
     files.insert(FILE_HEALTH_JSONL.to_string(), "v1".to_string());
     files.insert(FILE_RAW_WS_JSONL.to_string(), "v1".to_string());
     files.insert(FILE_TICKS.to_string(), "v1".to_string());
-    files.insert(FILE_TRADES.to_string(), "v3".to_string());
+    files.insert(FILE_TRADES.to_string(), "v4".to_string());
     files.insert(FILE_SNAPSHOTS.to_string(), "v1".to_string());
-    files.insert(FILE_SHADOW_LOG.to_string(), "v5".to_string());
+    files.insert(FILE_SHADOW_LOG.to_string(), "v9".to_string());
+    files.insert(FILE_SHADOW_LOG_JSONL.to_string(), "v1".to_string());
     files.insert(FILE_REPORT_JSON.to_string(), "v1".to_string());
     files.insert(FILE_REPORT_MD.to_string(), "v1".to_string());
+    files.insert(FILE_REPORT_HTML.to_string(), "v1".to_string());
     files.insert(FILE_TRADE_LOG.to_string(), "v1".to_string());
+    files.insert(FILE_SIGNALS.to_string(), "v1".to_string());
     files.insert(FILE_CALIBRATION_LOG.to_string(), "v1".to_string());
     files.insert(FILE_CALIBRATION_SUGGEST.to_string(), "v1".to_string());
+    files.insert(FILE_CALIBRATION_PATCH.to_string(), "v1".to_string());
+    files.insert(FILE_RECONCILIATION_JSON.to_string(), "v1".to_string());
+    files.insert(FILE_USER_EVENTS_JSONL.to_string(), "v1".to_string());
+    files
+}
+
+pub fn write_schema_version_json(
+    data_dir: &Path,
+    schema_version: &str,
+    generated_at_unix_ms: u64,
+) -> anyhow::Result<()> {
+    let files = file_versions();
 
     let payload = SchemaVersionFile {
         schema_version: schema_version.to_string(),
@@ -169,3 +272,197 @@ pub fn write_schema_version_json(
     std::fs::write(&out_path, json).with_context(|| format!("write {}", out_path.display()))?;
     Ok(())
 }
+
+/// Value kind of a frozen CSV column (empty cells mean "not applicable", e.g. leg2 of a binary).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ColumnKind {
+    Text,
+    Integer,
+    Float,
+}
+
+impl ColumnKind {
+    /// JSON Schema type name.
+    pub fn as_str(self) -> &'static str {
+        match self {
+            ColumnKind::Text => "string",
+            ColumnKind::Integer => "integer",
+            ColumnKind::Float => "number",
+        }
+    }
+}
+
+/// IDs and labels stay text (token ids overflow 64-bit ints), timestamps and counts are
+/// integers, everything else is a price/qty/bps float.
+pub fn column_kind(col: &str) -> ColumnKind {
+    const TEXT: [&str; 16] = [
+        "run_id",
+        "schema_version",
+        "market_id",
+        "strategy",
+        "bucket",
+        "notes",
+        "trade_id",
+        "phase",
+        "action",
+        "side",
+        "fill_status",
+        "token_id",
+        "worst_leg_token_id",
+        "bucket_classifier",
+        "volume_mode",
+        "fee_model",
+    ];
+    if TEXT.contains(&col)
+        || col.ends_with("_token_id")
+        || col.ends_with("_bucket")
+        || col.ends_with("_side")
+    {
+        ColumnKind::Text
+    } else if col == "signal_id"
+        || col == "legs_n"
+        || col == "leg_index"
+        || col == "worst_leg_index"
+        || col == "is_depth3_degraded"
+        || col.ends_with("_ms")
+        || col.ends_with("_us")
+    {
+        ColumnKind::Integer
+    } else {
+        ColumnKind::Float
+    }
+}
+
+/// One-line meaning of a frozen CSV column; `leg<i>_` columns share the per-leg text.
+pub fn column_doc(file: &str, col: &str) -> &'static str {
+    let leg_col = col
+        .strip_prefix("leg")
+        .and_then(|rest| rest.split_once('_'))
+        .filter(|(i, _)| i.len() == 1 && i.bytes().all(|b| b.is_ascii_digit()))
+        .map(|(_, c)| c);
+    if let Some(c) = leg_col {
+        return match c {
+            "token_id" => "Leg token id (asset_id); empty for legs beyond legs_n.",
+            "best_bid" => "Leg best bid at snapshot/signal time.",
+            "best_ask" => "Leg best ask at snapshot time.",
+            "depth3_usdc" => "Leg top-3 ask depth, sum of price*size in USDC.",
+            "p_limit" => "Leg limit price used by the shadow fill.",
+            "side" => "Leg order side (BUY / SELL).",
+            "qty" => "Leg requested quantity.",
+            "v_mkt" => "Leg market volume the fill was settled with (v_mkt_all or v_mkt_sided).",
+            "v_mkt_all" => "Leg market volume traded at or better than p_limit inside the window.",
+            "v_mkt_sided" => {
+                "Like v_mkt_all, counting only trades whose aggressor hit our side (unknown side counts)."
+            }
+            "q_fill" => "Leg simulated fill quantity (min(q_req, fill_share * v_mkt)).",
+            "bucket" => {
+                "Leg bucket whose fill share settled the leg (the market bucket unless per-leg)."
+            }
+            "fill_share" => "Fill share applied to the leg.",
+            _ => "",
+        };
+    }
+    match (file, col) {
+        (FILE_TRADES, "ts_ms") => {
+            "Local ingest time (ms); the frozen timestamp shadow windows align on."
+        }
+        (FILE_TRADE_LOG, "ts_ms") => "Time of the OMS action (ms).",
+        (_, "ts_ms") => "Sample time (ms).",
+        (_, "ts_recv_us") => "Local receive time (us).",
+        (_, "run_id") => "Run that produced the row.",
+        (_, "schema_version") => "Frozen spec version the row was written under.",
+        (_, "market_id") => "Market conditionId.",
+        (_, "token_id") => "Token id (asset_id).",
+        (_, "best_bid") => "Best bid.",
+        (_, "best_ask") => "Best ask.",
+        (_, "ask_depth3_usdc") => "Top-3 ask depth, sum of price*size in USDC.",
+        (_, "price") => "Trade price.",
+        (_, "size") => "Trade size (shares).",
+        (_, "trade_id") => "Trade identity used for dedup (transaction hash based when available).",
+        (_, "ingest_ts_ms") => "Local ingest time (ms); same as ts_ms, kept for compatibility.",
+        (_, "exchange_ts_ms") => "Exchange timestamp (ms) when parseable; diagnostics only.",
+        (_, "legs_n") => "Number of legs (2 binary, 3 triangle).",
+        (_, "signal_id") => "Signal id, unique within a run.",
+        (_, "signal_ts_unix_ms") => "Signal emission time (ms).",
+        (_, "window_start_ms") => "Shadow window start, relative to signal_ts_unix_ms (ms).",
+        (_, "window_end_ms") => "Shadow window end, relative to signal_ts_unix_ms (ms).",
+        (_, "strategy") => "Strategy label (binary / triangle).",
+        (_, "bucket") => "Liquidity bucket (liquid / thin).",
+        (_, "worst_leg_token_id") => "Token of the thinnest leg, which decided the bucket.",
+        (_, "bucket_classifier") => "Bucket classifier version that decided the bucket (v1 / v2).",
+        (_, "q_req") => "Requested set quantity.",
+        (_, "worst_leg_index") => "Index of the thinnest leg, which decided the bucket.",
+        (_, "worst_spread_bps") => "Spread of the thinnest leg at signal time (bps).",
+        (_, "worst_depth3_usdc") => "Top-3 ask depth of the thinnest leg at signal time (USDC).",
+        (_, "is_depth3_degraded") => "1 when depth3 fell back to a degraded estimate, else 0.",
+        (_, "depth_levels") => "Ask levels the bucket was decided on (buckets.depth_levels).",
+        (_, "worst_depth_usdc") => {
+            "Ask depth over depth_levels of the leg that decided the bucket (USDC)."
+        }
+        (_, "raw_cost_bps") => "Sum of leg asks at signal time (bps of 1 USDC).",
+        (_, "raw_edge_bps") => "10000 - raw_cost_bps.",
+        (_, "hard_fees_bps") => "Trading + merge fees charged against the edge (bps).",
+        (_, "risk_premium_bps") => "Risk premium charged against the edge (bps).",
+        (_, "q_set") => "Complete sets filled (min over legs of q_fill).",
+        (_, "cost_set") => "Cost of the complete sets.",
+        (_, "proceeds_set") => "Proceeds of the complete sets at settlement.",
+        (_, "pnl_set") => "proceeds_set - cost_set.",
+        (_, "pnl_left_total") => "PnL of dumping unmatched leg inventory with slippage.",
+        (_, "total_pnl") => "pnl_set + pnl_left_total.",
+        (_, "q_fill_avg") => "Mean leg fill quantity.",
+        (_, "set_ratio") => "q_set / q_fill_avg; 1.0 means no legging.",
+        (_, "fill_share_p25_used") => "Fill share assumed for the bucket.",
+        (_, "dump_slippage_assumed") => "Slippage assumed when dumping leftovers.",
+        (_, "volume_mode") => "Which leg volume settled the fills (all / sided).",
+        (_, "fee_model") => "Fee model (fees registry id) whose rates settled the row.",
+        (FILE_TRADE_LOG, "notes") => "Free-form action detail.",
+        (_, "notes") => "Comma-separated reason codes.",
+        (_, "phase") => "OMS phase of the signal when the action ran.",
+        (_, "action") => {
+            "OMS action (FIRE_LEG1 / CHASE / FLATTEN / COOLDOWN / HARDSTOP / DEDUP_HIT)."
+        }
+        (_, "leg_index") => "Leg the action applies to.",
+        (FILE_TRADES, "side") => "Aggressor (taker) side, BUY / SELL; empty when not reported.",
+        (_, "side") => "BUY / SELL.",
+        (_, "limit_price") => "Order limit price.",
+        (_, "req_qty") => "Requested quantity.",
+        (_, "fill_qty") => "Filled quantity.",
+        (_, "fill_status") => "Fill outcome of the action.",
+        (_, "expected_net_bps") => "Expected net edge at decision time (bps).",
+        _ => "",
+    }
+}
+
+/// Unit of a frozen CSV column's values; `None` for ids, labels, counts and ratios.
+pub fn column_unit(col: &str) -> Option<&'static str> {
+    let col = col
+        .strip_prefix("leg")
+        .and_then(|rest| rest.split_once('_'))
+        .filter(|(i, _)| i.len() == 1 && i.bytes().all(|b| b.is_ascii_digit()))
+        .map_or(col, |(_, c)| c);
+    match col {
+        "window_start_ms" | "window_end_ms" => Some("ms (relative to signal_ts_unix_ms)"),
+        "ts_recv_us" => Some("unix_us"),
+        "price" | "best_bid" | "best_ask" | "p_limit" | "limit_price" => Some("USDC per share"),
+        "size" | "v_mkt" | "v_mkt_all" | "v_mkt_sided" | "q_fill" | "q_req" | "q_set"
+        | "q_fill_avg" | "req_qty" | "fill_qty" | "qty" => Some("shares"),
+        "cost_set" | "proceeds_set" | "pnl_set" | "pnl_left_total" | "total_pnl" => Some("USDC"),
+        _ if col.ends_with("_ms") => Some("unix_ms"),
+        _ if col.ends_with("_usdc") => Some("USDC"),
+        _ if col.ends_with("_bps") => Some("bps"),
+        _ => None,
+    }
+}
+
+/// Module whose writer produces a frozen CSV.
+pub fn producer_module(file: &str) -> Option<&'static str> {
+    match file {
+        FILE_TICKS | FILE_TRADES => Some("feed"),
+        FILE_SNAPSHOTS => Some("snapshot_logger"),
+        FILE_SHADOW_LOG => Some("shadow"),
+        FILE_TRADE_LOG => Some("sniper"),
+        FILE_SIGNALS => Some("shadow"),
+        FILE_CALIBRATION_LOG => Some("calibration"),
+        _ => None,
+    }
+}
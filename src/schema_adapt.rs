@@ -0,0 +1,608 @@
+//! Reading frozen CSVs written under an older schema. Every layout change to a frozen CSV adds
+//! an explicit [`CsvAdapter`] here that maps the old columns onto the current header (filling
+//! defaults for columns that did not exist); replay, the sweeps and `razor migrate` all read
+//! through [`FrozenCsvReader`], so an N-1 run dir stays usable without a migrate step.
+//!
+//! `RAZOR_SCHEMA_STRICT=1` restores the old hard failure on any header other than the current
+//! one (for CI, where a stale fixture should break the build rather than be adapted).
+
+use std::path::{Path, PathBuf};
+
+use anyhow::Context as _;
+use serde::de::DeserializeOwned;
+
+use crate::schema::{FILE_SHADOW_LOG, FILE_TRADES, PREVIOUS_SCHEMA_VERSION, SCHEMA_VERSION};
+
+pub const ENV_SCHEMA_STRICT: &str = "RAZOR_SCHEMA_STRICT";
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum SchemaMode {
+    /// Current header, or an older one with a registered adapter.
+    #[default]
+    Compat,
+    /// Current header only.
+    Strict,
+}
+
+impl SchemaMode {
+    /// `Strict` when `RAZOR_SCHEMA_STRICT` is set to anything but empty/`0`/`false`.
+    #[allow(dead_code)]
+    pub fn from_env() -> Self {
+        match std::env::var(ENV_SCHEMA_STRICT) {
+            Ok(v) if !matches!(v.trim(), "" | "0" | "false") => SchemaMode::Strict,
+            _ => SchemaMode::Compat,
+        }
+    }
+}
+
+/// trades.csv before `ingest_ts_ms` was recorded.
+const TRADES_V1_HEADER: [&str; 6] = [
+    "ts_ms",
+    "market_id",
+    "token_id",
+    "price",
+    "size",
+    "trade_id",
+];
+
+/// trades.csv before `exchange_ts_ms` was split out.
+const TRADES_V2_HEADER: [&str; 7] = [
+    "ts_ms",
+    "market_id",
+    "token_id",
+    "price",
+    "size",
+    "trade_id",
+    "ingest_ts_ms",
+];
+
+/// trades.csv before the aggressor `side` was recorded.
+const TRADES_V3_HEADER: [&str; 8] = [
+    "ts_ms",
+    "market_id",
+    "token_id",
+    "price",
+    "size",
+    "trade_id",
+    "ingest_ts_ms",
+    "exchange_ts_ms",
+];
+
+/// shadow_log.csv before `bucket_classifier` was recorded (every row was classified by v1).
+const SHADOW_V5_HEADER: [&str; 38] = [
+    "run_id",
+    "schema_version",
+    "signal_id",
+    "signal_ts_unix_ms",
+    "window_start_ms",
+    "window_end_ms",
+    "market_id",
+    "strategy",
+    "bucket",
+    "worst_leg_token_id",
+    "q_req",
+    "legs_n",
+    "q_set",
+    "leg0_token_id",
+    "leg0_p_limit",
+    "leg0_best_bid",
+    "leg0_v_mkt",
+    "leg0_q_fill",
+    "leg1_token_id",
+    "leg1_p_limit",
+    "leg1_best_bid",
+    "leg1_v_mkt",
+    "leg1_q_fill",
+    "leg2_token_id",
+    "leg2_p_limit",
+    "leg2_best_bid",
+    "leg2_v_mkt",
+    "leg2_q_fill",
+    "cost_set",
+    "proceeds_set",
+    "pnl_set",
+    "pnl_left_total",
+    "total_pnl",
+    "q_fill_avg",
+    "set_ratio",
+    "fill_share_p25_used",
+    "dump_slippage_assumed",
+    "notes",
+];
+
+/// shadow_log.csv before per-leg `legN_bucket` / `legN_fill_share` were recorded (every leg used
+/// the market bucket and `fill_share_p25_used`).
+const SHADOW_V6_HEADER: [&str; 39] = [
+    "run_id",
+    "schema_version",
+    "signal_id",
+    "signal_ts_unix_ms",
+    "window_start_ms",
+    "window_end_ms",
+    "market_id",
+    "strategy",
+    "bucket",
+    "worst_leg_token_id",
+    "bucket_classifier",
+    "q_req",
+    "legs_n",
+    "q_set",
+    "leg0_token_id",
+    "leg0_p_limit",
+    "leg0_best_bid",
+    "leg0_v_mkt",
+    "leg0_q_fill",
+    "leg1_token_id",
+    "leg1_p_limit",
+    "leg1_best_bid",
+    "leg1_v_mkt",
+    "leg1_q_fill",
+    "leg2_token_id",
+    "leg2_p_limit",
+    "leg2_best_bid",
+    "leg2_v_mkt",
+    "leg2_q_fill",
+    "cost_set",
+    "proceeds_set",
+    "pnl_set",
+    "pnl_left_total",
+    "total_pnl",
+    "q_fill_avg",
+    "set_ratio",
+    "fill_share_p25_used",
+    "dump_slippage_assumed",
+    "notes",
+];
+
+/// shadow_log.csv before the all/sided leg volumes and `volume_mode` were recorded (every leg
+/// settled on all volume at or better than the limit).
+const SHADOW_V7_HEADER: [&str; 45] = [
+    "run_id",
+    "schema_version",
+    "signal_id",
+    "signal_ts_unix_ms",
+    "window_start_ms",
+    "window_end_ms",
+    "market_id",
+    "strategy",
+    "bucket",
+    "worst_leg_token_id",
+    "bucket_classifier",
+    "q_req",
+    "legs_n",
+    "q_set",
+    "leg0_token_id",
+    "leg0_p_limit",
+    "leg0_best_bid",
+    "leg0_v_mkt",
+    "leg0_q_fill",
+    "leg0_bucket",
+    "leg0_fill_share",
+    "leg1_token_id",
+    "leg1_p_limit",
+    "leg1_best_bid",
+    "leg1_v_mkt",
+    "leg1_q_fill",
+    "leg1_bucket",
+    "leg1_fill_share",
+    "leg2_token_id",
+    "leg2_p_limit",
+    "leg2_best_bid",
+    "leg2_v_mkt",
+    "leg2_q_fill",
+    "leg2_bucket",
+    "leg2_fill_share",
+    "cost_set",
+    "proceeds_set",
+    "pnl_set",
+    "pnl_left_total",
+    "total_pnl",
+    "q_fill_avg",
+    "set_ratio",
+    "fill_share_p25_used",
+    "dump_slippage_assumed",
+    "notes",
+];
+
+/// shadow_log.csv before `fee_model` was recorded (every row settled at the Frozen Spec
+/// `FEE_POLY` / `FEE_MERGE`).
+const SHADOW_V8_HEADER: [&str; 52] = [
+    "run_id",
+    "schema_version",
+    "signal_id",
+    "signal_ts_unix_ms",
+    "window_start_ms",
+    "window_end_ms",
+    "market_id",
+    "strategy",
+    "bucket",
+    "worst_leg_token_id",
+    "bucket_classifier",
+    "q_req",
+    "legs_n",
+    "q_set",
+    "leg0_token_id",
+    "leg0_p_limit",
+    "leg0_best_bid",
+    "leg0_v_mkt",
+    "leg0_v_mkt_all",
+    "leg0_v_mkt_sided",
+    "leg0_q_fill",
+    "leg0_bucket",
+    "leg0_fill_share",
+    "leg1_token_id",
+    "leg1_p_limit",
+    "leg1_best_bid",
+    "leg1_v_mkt",
+    "leg1_v_mkt_all",
+    "leg1_v_mkt_sided",
+    "leg1_q_fill",
+    "leg1_bucket",
+    "leg1_fill_share",
+    "leg2_token_id",
+    "leg2_p_limit",
+    "leg2_best_bid",
+    "leg2_v_mkt",
+    "leg2_v_mkt_all",
+    "leg2_v_mkt_sided",
+    "leg2_q_fill",
+    "leg2_bucket",
+    "leg2_fill_share",
+    "cost_set",
+    "proceeds_set",
+    "pnl_set",
+    "pnl_left_total",
+    "total_pnl",
+    "q_fill_avg",
+    "set_ratio",
+    "fill_share_p25_used",
+    "dump_slippage_assumed",
+    "volume_mode",
+    "notes",
+];
+
+/// Where `bucket_classifier` goes: right after `worst_leg_token_id`.
+const SHADOW_V5_CLASSIFIER_AT: usize = 10;
+
+/// One explicit upgrade from an older frozen layout of `file` to the current one. Every schema
+/// bump that changes a CSV must add its adapter here.
+pub struct CsvAdapter {
+    pub file: &'static str,
+    /// Per-file version label (as in `schema_version.json`) of `from_header`.
+    pub from_version: &'static str,
+    pub from_header: &'static [&'static str],
+    pub upgrade: fn(&csv::StringRecord) -> Vec<String>,
+}
+
+fn fields(r: &csv::StringRecord, n: usize) -> Vec<String> {
+    (0..n).map(|i| r.get(i).unwrap_or("").to_string()).collect()
+}
+
+/// `ingest_ts_ms` falls back to `ts_ms`, exactly as the trades readers treat a missing value;
+/// the exchange timestamp is unknown.
+fn trades_v1_to_current(r: &csv::StringRecord) -> Vec<String> {
+    let mut row = fields(r, TRADES_V1_HEADER.len());
+    row.push(r.get(0).unwrap_or("").to_string());
+    trades_v2_to_current(&csv::StringRecord::from(row))
+}
+
+fn trades_v2_to_current(r: &csv::StringRecord) -> Vec<String> {
+    let mut row = fields(r, TRADES_V2_HEADER.len());
+    row.push(String::new());
+    trades_v3_to_current(&csv::StringRecord::from(row))
+}
+
+/// The aggressor side is unknown; sided volume counts such trades on either side.
+fn trades_v3_to_current(r: &csv::StringRecord) -> Vec<String> {
+    let mut row = fields(r, TRADES_V3_HEADER.len());
+    row.push(String::new());
+    row
+}
+
+fn shadow_v5_to_current(r: &csv::StringRecord) -> Vec<String> {
+    let mut row = fields(r, SHADOW_V5_HEADER.len());
+    row.insert(SHADOW_V5_CLASSIFIER_AT, "v1".to_string());
+    shadow_v6_to_current(&csv::StringRecord::from(row))
+}
+
+/// Legs present in the row get the market bucket and `fill_share_p25_used` (what v6 settled them
+/// with); absent legs stay empty, as the writer leaves them.
+fn shadow_v6_to_current(r: &csv::StringRecord) -> Vec<String> {
+    let col = |name: &str| {
+        SHADOW_V6_HEADER
+            .iter()
+            .position(|h| *h == name)
+            .and_then(|i| r.get(i))
+            .unwrap_or("")
+            .to_string()
+    };
+    let legs_n: usize = col("legs_n").trim().parse().unwrap_or(0);
+    let mut row = Vec::with_capacity(SHADOW_V6_HEADER.len() + 6);
+    for (i, name) in SHADOW_V6_HEADER.iter().enumerate() {
+        row.push(r.get(i).unwrap_or("").to_string());
+        let leg = match name
+            .strip_prefix("leg")
+            .and_then(|n| n.strip_suffix("_q_fill"))
+        {
+            Some(leg) => leg.parse::<usize>().unwrap_or(usize::MAX),
+            None => continue,
+        };
+        if leg < legs_n {
+            row.push(col("bucket"));
+            row.push(col("fill_share_p25_used"));
+        } else {
+            row.push(String::new());
+            row.push("0.0".to_string());
+        }
+    }
+    shadow_v7_to_current(&csv::StringRecord::from(row))
+}
+
+/// Both per-leg volumes are the recorded `legN_v_mkt`, which v7 computed over all trades.
+fn shadow_v7_to_current(r: &csv::StringRecord) -> Vec<String> {
+    let mut row = Vec::with_capacity(SHADOW_V7_HEADER.len() + 7);
+    for (i, name) in SHADOW_V7_HEADER.iter().enumerate() {
+        let v = r.get(i).unwrap_or("").to_string();
+        if *name == "notes" {
+            row.push("all".to_string());
+        }
+        row.push(v.clone());
+        if name.starts_with("leg") && name.ends_with("_v_mkt") {
+            row.push(v.clone());
+            row.push(v);
+        }
+    }
+    shadow_v8_to_current(&csv::StringRecord::from(row))
+}
+
+/// Rows from before the fee registry were settled with the built-in model. Every shadow upgrade
+/// ends here, so this is also where `PREVIOUS_SCHEMA_VERSION` rows take the current label (the
+/// filled columns are exactly what that spec settled with); any other version is left as is.
+fn shadow_v8_to_current(r: &csv::StringRecord) -> Vec<String> {
+    let mut row = fields(r, SHADOW_V8_HEADER.len());
+    row.insert(
+        SHADOW_V8_HEADER.len() - 1,
+        crate::fees::BUILTIN_FEE_MODEL_ID.to_string(),
+    );
+    if row[1].trim().eq_ignore_ascii_case(PREVIOUS_SCHEMA_VERSION) {
+        row[1] = SCHEMA_VERSION.to_string();
+    }
+    row
+}
+
+pub const ADAPTERS: [CsvAdapter; 7] = [
+    CsvAdapter {
+        file: FILE_TRADES,
+        from_version: "v1",
+        from_header: &TRADES_V1_HEADER,
+        upgrade: trades_v1_to_current,
+    },
+    CsvAdapter {
+        file: FILE_TRADES,
+        from_version: "v2",
+        from_header: &TRADES_V2_HEADER,
+        upgrade: trades_v2_to_current,
+    },
+    CsvAdapter {
+        file: FILE_TRADES,
+        from_version: "v3",
+        from_header: &TRADES_V3_HEADER,
+        upgrade: trades_v3_to_current,
+    },
+    CsvAdapter {
+        file: FILE_SHADOW_LOG,
+        from_version: "v5",
+        from_header: &SHADOW_V5_HEADER,
+        upgrade: shadow_v5_to_current,
+    },
+    CsvAdapter {
+        file: FILE_SHADOW_LOG,
+        from_version: "v6",
+        from_header: &SHADOW_V6_HEADER,
+        upgrade: shadow_v6_to_current,
+    },
+    CsvAdapter {
+        file: FILE_SHADOW_LOG,
+        from_version: "v7",
+        from_header: &SHADOW_V7_HEADER,
+        upgrade: shadow_v7_to_current,
+    },
+    CsvAdapter {
+        file: FILE_SHADOW_LOG,
+        from_version: "v8",
+        from_header: &SHADOW_V8_HEADER,
+        upgrade: shadow_v8_to_current,
+    },
+];
+
+/// The adapter for `file` written with `header`, if one is registered.
+pub fn find_adapter(file: &str, header: &[&str]) -> Option<&'static CsvAdapter> {
+    ADAPTERS
+        .iter()
+        .find(|a| a.file == file && a.from_header == header)
+}
+
+/// A frozen CSV whose records come out in the current column layout whatever layout it was
+/// written in.
+pub struct FrozenCsvReader {
+    path: PathBuf,
+    rdr: csv::Reader<std::fs::File>,
+    header: csv::StringRecord,
+    adapter: Option<&'static CsvAdapter>,
+}
+
+impl FrozenCsvReader {
+    /// Opens `path` (a `file` CSV such as `trades.csv`) and checks its header against `current`.
+    pub fn open(
+        path: &Path,
+        file: &str,
+        current: &[&str],
+        mode: SchemaMode,
+    ) -> anyhow::Result<Self> {
+        let mut rdr = csv::ReaderBuilder::new()
+            .flexible(true)
+            .trim(csv::Trim::All)
+            .from_path(path)
+            .with_context(|| format!("open {}", path.display()))?;
+        let found = rdr
+            .headers()
+            .with_context(|| format!("read header {}", path.display()))?
+            .clone();
+        let found: Vec<&str> = found.iter().collect();
+
+        let adapter = if found == current {
+            None
+        } else {
+            let adapter = match mode {
+                SchemaMode::Compat => find_adapter(file, &found),
+                SchemaMode::Strict => None,
+            };
+            let Some(adapter) = adapter else {
+                anyhow::bail!(
+                    "{}: header mismatch (expected frozen {file} header, got [{}]{})",
+                    path.display(),
+                    found.join(","),
+                    if mode == SchemaMode::Strict {
+                        "; strict schema mode"
+                    } else {
+                        "; no adapter registered"
+                    }
+                );
+            };
+            tracing::warn!(
+                path = %path.display(),
+                from_version = adapter.from_version,
+                "reading older {file} layout through schema adapter"
+            );
+            Some(adapter)
+        };
+        Ok(Self {
+            path: path.to_path_buf(),
+            rdr,
+            header: csv::StringRecord::from(current.to_vec()),
+            adapter,
+        })
+    }
+
+    /// The current header the records are laid out in.
+    pub fn header(&self) -> &csv::StringRecord {
+        &self.header
+    }
+
+    /// Version label of the adapted layout; `None` when the file is already current.
+    pub fn adapted_from(&self) -> Option<&'static str> {
+        self.adapter.map(|a| a.from_version)
+    }
+
+    /// Records in the current layout.
+    pub fn records(&mut self) -> impl Iterator<Item = anyhow::Result<csv::StringRecord>> + '_ {
+        let path = &self.path;
+        let adapter = self.adapter;
+        self.rdr.records().map(move |r| {
+            let r = r.with_context(|| format!("read {}", path.display()))?;
+            Ok(match adapter {
+                Some(a) => csv::StringRecord::from((a.upgrade)(&r)),
+                None => r,
+            })
+        })
+    }
+
+    /// Rows deserialized by current column name (see `rows`).
+    pub fn deserialize<T: DeserializeOwned>(
+        &mut self,
+    ) -> impl Iterator<Item = anyhow::Result<T>> + '_ {
+        let header = self.header.clone();
+        let path = self.path.clone();
+        self.records().map(move |r| {
+            r?.deserialize(Some(&header))
+                .with_context(|| format!("read {}", path.display()))
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rows::TradeRow;
+    use crate::schema::{SHADOW_HEADER, TRADES_HEADER};
+
+    #[test]
+    fn adapts_older_trades_layout_unless_strict() {
+        let path =
+            std::env::temp_dir().join(format!("razor_schema_adapt_{}.csv", crate::types::now_ms()));
+        std::fs::write(
+            &path,
+            format!("{}\n1000,m,t,0.5,10,x\n", TRADES_V1_HEADER.join(",")),
+        )
+        .unwrap();
+
+        let mut rdr =
+            FrozenCsvReader::open(&path, FILE_TRADES, &TRADES_HEADER, SchemaMode::Compat).unwrap();
+        assert_eq!(rdr.adapted_from(), Some("v1"));
+        let rows: Vec<TradeRow> = rdr.deserialize().collect::<anyhow::Result<_>>().unwrap();
+        assert_eq!(rows[0].ingest_ts_ms, Some(1000));
+        assert_eq!(rows[0].exchange_ts_ms, None);
+        assert_eq!(rows[0].side, None);
+
+        let err = FrozenCsvReader::open(&path, FILE_TRADES, &TRADES_HEADER, SchemaMode::Strict)
+            .err()
+            .unwrap();
+        assert!(err.to_string().contains("strict schema mode"), "{err}");
+        let _ = std::fs::remove_file(&path);
+    }
+
+    #[test]
+    fn shadow_v5_rows_read_as_v1_classified() {
+        let path = std::env::temp_dir().join(format!(
+            "razor_schema_adapt_shadow_{}.csv",
+            crate::types::now_ms()
+        ));
+        let mut row = vec![""; SHADOW_V5_HEADER.len()];
+        row[1] = PREVIOUS_SCHEMA_VERSION;
+        row[8] = "liquid";
+        row[SHADOW_V5_CLASSIFIER_AT - 1] = "tok";
+        row[11] = "2";
+        row[35] = "0.3";
+        row[37] = "OK";
+        let v_mkt_at = SHADOW_V5_HEADER
+            .iter()
+            .position(|h| *h == "leg0_v_mkt")
+            .unwrap();
+        row[v_mkt_at] = "12.5";
+        std::fs::write(
+            &path,
+            format!("{}\n{}\n", SHADOW_V5_HEADER.join(","), row.join(",")),
+        )
+        .unwrap();
+
+        let mut rdr =
+            FrozenCsvReader::open(&path, FILE_SHADOW_LOG, &SHADOW_HEADER, SchemaMode::Compat)
+                .unwrap();
+        assert_eq!(rdr.adapted_from(), Some("v5"));
+        let rec = rdr.records().next().unwrap().unwrap();
+        assert_eq!(rec.len(), SHADOW_HEADER.len());
+        let col = |name: &str| {
+            let i = SHADOW_HEADER.iter().position(|h| *h == name).unwrap();
+            rec.get(i).unwrap().to_string()
+        };
+        assert_eq!(col("schema_version"), SCHEMA_VERSION);
+        assert_eq!(col("worst_leg_token_id"), "tok");
+        assert_eq!(col("bucket_classifier"), "v1");
+        assert_eq!(
+            (col("leg1_bucket"), col("leg1_fill_share")),
+            ("liquid".to_string(), "0.3".to_string())
+        );
+        assert_eq!(
+            (col("leg2_bucket"), col("leg2_fill_share")),
+            (String::new(), "0.0".to_string())
+        );
+        assert_eq!(
+            (col("leg0_v_mkt_all"), col("leg0_v_mkt_sided")),
+            ("12.5".to_string(), "12.5".to_string())
+        );
+        assert_eq!(col("volume_mode"), "all");
+        assert_eq!(col("fee_model"), crate::fees::BUILTIN_FEE_MODEL_ID);
+        assert_eq!(col("notes"), "OK");
+        let _ = std::fs::remove_file(&path);
+    }
+}
@@ -0,0 +1,196 @@
+use std::fmt::Write as _;
+use std::path::Path;
+
+use anyhow::Context as _;
+use serde_json::{json, Value};
+
+use crate::health::HealthLine;
+use crate::recorder::TICKS_HEADER;
+use crate::rows::ShadowRecord;
+use crate::schema::{
+    column_doc, column_kind, file_versions, ColumnKind, FILE_HEALTH_JSONL, FILE_SHADOW_LOG,
+    FILE_SHADOW_LOG_JSONL, FILE_SIGNALS, FILE_SNAPSHOTS, FILE_TICKS, FILE_TRADES, FILE_TRADE_LOG,
+    SCHEMA_VERSION, SHADOW_HEADER, SIGNALS_HEADER, SNAPSHOTS_HEADER, TRADES_HEADER,
+    TRADE_LOG_HEADER,
+};
+
+const JSON_SCHEMA_DIALECT: &str = "https://json-schema.org/draft/2020-12/schema";
+
+/// CSVs covered by `razor schema dump`, with the header constant their writer uses.
+const CSV_FORMATS: [(&str, &[&str]); 6] = [
+    (FILE_TICKS, &TICKS_HEADER),
+    (FILE_TRADES, &TRADES_HEADER),
+    (FILE_SNAPSHOTS, &SNAPSHOTS_HEADER),
+    (FILE_SHADOW_LOG, &SHADOW_HEADER),
+    (FILE_TRADE_LOG, &TRADE_LOG_HEADER),
+    (FILE_SIGNALS, &SIGNALS_HEADER),
+];
+
+fn file_version(file: &str) -> String {
+    file_versions()
+        .remove(file)
+        .unwrap_or_else(|| "v1".to_string())
+}
+
+/// JSON Schema for one CSV row read as an object keyed by header name. Numeric cells may be
+/// empty (not applicable), which consumers should map to null.
+fn csv_row_schema(file: &str, header: &[&str]) -> Value {
+    let mut properties = serde_json::Map::new();
+    for (i, col) in header.iter().enumerate() {
+        let ty = match column_kind(col) {
+            ColumnKind::Text => json!("string"),
+            kind => json!([kind.as_str(), "null"]),
+        };
+        properties.insert(
+            col.to_string(),
+            json!({ "type": ty, "description": column_doc(file, col), "x-column-index": i }),
+        );
+    }
+    json!({
+        "$schema": JSON_SCHEMA_DIALECT,
+        "$id": format!("razor/{SCHEMA_VERSION}/{file}"),
+        "title": file,
+        "description": format!(
+            "One row of {file} (file version {}, schema {SCHEMA_VERSION}); columns appear in \
+             x-csv-header order.",
+            file_version(file)
+        ),
+        "type": "object",
+        "properties": properties,
+        "required": header,
+        "additionalProperties": false,
+        "x-csv-header": header,
+    })
+}
+
+/// JSON Schema for one `health.jsonl` line, derived from the `HealthLine` type the writer
+/// serializes.
+fn health_line_schema() -> Value {
+    let mut schema = serde_json::to_value(schemars::schema_for!(HealthLine)).unwrap_or_default();
+    if let Value::Object(m) = &mut schema {
+        m.insert(
+            "$id".to_string(),
+            json!(format!("razor/{SCHEMA_VERSION}/{FILE_HEALTH_JSONL}")),
+        );
+        m.insert(
+            "description".to_string(),
+            json!(format!(
+                "One line of {FILE_HEALTH_JSONL} (file version {}), tagged by `type`.",
+                file_version(FILE_HEALTH_JSONL)
+            )),
+        );
+    }
+    schema
+}
+
+/// JSON Schema for one `shadow_log.jsonl` line, derived from `ShadowRecord`.
+fn shadow_record_schema() -> Value {
+    let mut schema = serde_json::to_value(schemars::schema_for!(ShadowRecord)).unwrap_or_default();
+    if let Value::Object(m) = &mut schema {
+        m.insert(
+            "$id".to_string(),
+            json!(format!("razor/{SCHEMA_VERSION}/{FILE_SHADOW_LOG_JSONL}")),
+        );
+        m.insert(
+            "description".to_string(),
+            json!(format!(
+                "One line of {FILE_SHADOW_LOG_JSONL} (file version {}): a {FILE_SHADOW_LOG} row \
+                 with nested legs and the notes codes as `reasons`.",
+                file_version(FILE_SHADOW_LOG_JSONL)
+            )),
+        );
+    }
+    schema
+}
+
+/// Every recorded format keyed by file name.
+pub fn all_schemas() -> Vec<(&'static str, Value)> {
+    let mut out: Vec<(&'static str, Value)> = CSV_FORMATS
+        .iter()
+        .map(|(file, header)| (*file, csv_row_schema(file, header)))
+        .collect();
+    out.push((FILE_HEALTH_JSONL, health_line_schema()));
+    out.push((FILE_SHADOW_LOG_JSONL, shadow_record_schema()));
+    out
+}
+
+/// Markdown column reference for the CSV formats.
+pub fn render_column_docs() -> String {
+    let mut md = format!("# Razor recorded CSV columns (schema {SCHEMA_VERSION})\n");
+    for (file, header) in CSV_FORMATS {
+        let _ = write!(
+            md,
+            "\n## {file} ({})\n\n| # | column | type | description |\n|---|---|---|---|\n",
+            file_version(file)
+        );
+        for (i, col) in header.iter().enumerate() {
+            let _ = writeln!(
+                md,
+                "| {i} | `{col}` | {} | {} |",
+                column_kind(col).as_str(),
+                column_doc(file, col)
+            );
+        }
+    }
+    md
+}
+
+/// `razor schema dump`: one JSON document on stdout, or with `out` a `<file>.schema.json` per
+/// format plus `columns.md`.
+pub fn run_schema_dump(out: Option<&Path>) -> anyhow::Result<()> {
+    let schemas = all_schemas();
+    let Some(out) = out else {
+        let formats: serde_json::Map<String, Value> = schemas
+            .into_iter()
+            .map(|(file, schema)| (file.to_string(), schema))
+            .collect();
+        let doc = json!({ "schema_version": SCHEMA_VERSION, "formats": formats });
+        println!("{}", serde_json::to_string_pretty(&doc)?);
+        return Ok(());
+    };
+
+    std::fs::create_dir_all(out).with_context(|| format!("create {}", out.display()))?;
+    for (file, schema) in schemas {
+        let path = out.join(format!("{file}.schema.json"));
+        std::fs::write(&path, serde_json::to_vec_pretty(&schema)?)
+            .with_context(|| format!("write {}", path.display()))?;
+        println!("schema={}", path.display());
+    }
+    let path = out.join("columns.md");
+    std::fs::write(&path, render_column_docs())
+        .with_context(|| format!("write {}", path.display()))?;
+    println!("columns={}", path.display());
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn every_column_is_documented_and_health_variants_are_covered() {
+        for (file, header) in CSV_FORMATS {
+            for col in header {
+                assert!(
+                    !column_doc(file, col).is_empty(),
+                    "{file}: undocumented {col}"
+                );
+            }
+            let schema = csv_row_schema(file, header);
+            assert_eq!(schema["required"].as_array().unwrap().len(), header.len());
+        }
+        let shadow = csv_row_schema(FILE_SHADOW_LOG, &SHADOW_HEADER);
+        assert_eq!(shadow["properties"]["signal_id"]["type"][0], "integer");
+        assert_eq!(shadow["properties"]["leg2_token_id"]["type"], "string");
+
+        let health = health_line_schema().to_string();
+        for tag in ["heartbeat", "trade_poll_hit_limit", "disk_level_changed"] {
+            assert!(health.contains(&format!("\"{tag}\"")), "missing {tag}");
+        }
+        assert!(health.contains("fsync_p95_us"));
+
+        let record = shadow_record_schema();
+        assert_eq!(record["properties"]["legs"]["type"], "array");
+        assert_eq!(record["properties"]["reasons"]["type"], "array");
+    }
+}
@@ -0,0 +1,76 @@
+//! Credentials, kept out of config files: every `*_env` config key names a variable that is read
+//! from the process environment first, then from the optional `[secrets] keystore_path` file.
+//! Run artifacts only ever record fingerprints of the values (`config::secret_fingerprint`).
+
+use std::collections::BTreeMap;
+use std::sync::OnceLock;
+
+use anyhow::Context as _;
+
+use crate::config::{secret_fingerprint, Config, SecretsConfig};
+
+static KEYSTORE: OnceLock<BTreeMap<String, String>> = OnceLock::new();
+
+/// Loads the keystore once at startup; without it secrets come from the environment only.
+pub fn init(cfg: &SecretsConfig) -> anyhow::Result<()> {
+    let path = cfg.keystore_path.trim();
+    if path.is_empty() {
+        return Ok(());
+    }
+    let raw = std::fs::read_to_string(path).with_context(|| format!("read keystore {path}"))?;
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt as _;
+        let mode = std::fs::metadata(path)?.permissions().mode();
+        if mode & 0o077 != 0 {
+            tracing::warn!(
+                path,
+                mode = format!("{:o}", mode & 0o777),
+                "keystore is readable by group/others; chmod 600 it"
+            );
+        }
+    }
+    let entries: BTreeMap<String, String> = toml::from_str(&raw)
+        .with_context(|| format!("parse keystore {path} (NAME = \"value\")"))?;
+    let _ = KEYSTORE.set(entries);
+    Ok(())
+}
+
+/// `name` from the environment, else the keystore.
+pub fn get(name: &str) -> Option<String> {
+    let name = name.trim();
+    if name.is_empty() {
+        return None;
+    }
+    std::env::var(name)
+        .ok()
+        .or_else(|| KEYSTORE.get()?.get(name).cloned())
+}
+
+/// [`get`], failing with what the secret is for when it is set nowhere.
+pub fn require(name: &str, what: &str) -> anyhow::Result<String> {
+    get(name).with_context(|| {
+        format!(
+            "missing {what}: set env var {} or add it to the keystore",
+            name.trim()
+        )
+    })
+}
+
+/// The `*_env` config keys whose secret resolves, with the value's fingerprint.
+pub fn fingerprints(cfg: &Config) -> BTreeMap<String, String> {
+    [
+        ("live.private_key_env", &cfg.live.private_key_env),
+        ("live.approval_key_env", &cfg.live.approval_key_env),
+        ("email.password_env", &cfg.email.password_env),
+        (
+            "escalation.routing_key_env",
+            &cfg.escalation.routing_key_env,
+        ),
+        ("upload.access_key_env", &cfg.upload.access_key_env),
+        ("upload.secret_key_env", &cfg.upload.secret_key_env),
+    ]
+    .into_iter()
+    .filter_map(|(key, name)| Some((key.to_string(), secret_fingerprint(&get(name)?))))
+    .collect()
+}
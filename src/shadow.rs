@@ -1,80 +1,114 @@
-use std::path::PathBuf;
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
 use std::sync::Arc;
 use std::time::Duration;
 
-use anyhow::Context as _;
-use tokio::sync::{mpsc, watch};
-use tracing::{debug, info};
+use tokio::sync::watch;
+use tracing::{debug, info, warn};
 
+use crate::accounting::{settle_set_per_leg, SetLeg, SetSettlement};
+use crate::api::{RunState, SettlementView};
+use crate::backpressure::{self, ChannelStats};
 use crate::buckets::fill_share_p25;
-use crate::config::Config;
+use crate::clock::SharedClock;
+use crate::config::{BucketConfig, Config, MarketParams, VolumeMode};
+use crate::errors::{code_of, ErrorContext as _, RazorError};
+use crate::fees::FeeRegistry;
 use crate::health::HealthCounters;
 use crate::reasons::{format_notes, ShadowNoteReason};
-use crate::recorder::{CsvAppender, SHADOW_HEADER};
-use crate::schema::{DUMP_SLIPPAGE_ASSUMED, SCHEMA_VERSION};
+use crate::recorder::{csv_input_paths, CsvAppender, JsonlAppender, SHADOW_HEADER};
+use crate::resume::read_recent_trades;
+use crate::rows::{ShadowLegCols, ShadowRecord, ShadowRow, SignalRow};
+use crate::schema::{
+    DUMP_SLIPPAGE_ASSUMED, FILE_SHADOW_LOG, FILE_SHADOW_LOG_JSONL, FILE_SIGNALS, SCHEMA_VERSION,
+    SIGNALS_HEADER,
+};
+use crate::schema_adapt::{FrozenCsvReader, SchemaMode};
+use crate::signal_trace::trace_signal;
 use crate::trade_store::TradeStore;
-use crate::types::{now_ms, Bps, Leg, MarketDef, Side, Signal, TradeTick};
-
-const LEFTOVER_DUMP_MULT: f64 = 1.0 - DUMP_SLIPPAGE_ASSUMED;
+use crate::types::{Leg, MarketDef, Price, Qty, Side, Signal, SignalId, TokenId, TradeTick};
 
+#[allow(clippy::too_many_arguments)]
 pub async fn run(
-    cfg: Config,
+    mut cfg: Config,
     _markets: Vec<MarketDef>,
-    mut trade_rx: mpsc::Receiver<TradeTick>,
-    mut signal_rx: mpsc::Receiver<Signal>,
+    mut trade_rx: backpressure::Receiver<TradeTick>,
+    mut signal_rx: backpressure::Receiver<Signal>,
     shadow_path: PathBuf,
     health: Arc<HealthCounters>,
+    run_state: Option<Arc<RunState>>,
+    clock: SharedClock,
+    mut fill_shares: watch::Receiver<BucketConfig>,
     mut shutdown: watch::Receiver<bool>,
 ) -> anyhow::Result<()> {
-    let mut out = CsvAppender::open(shadow_path, &SHADOW_HEADER).context("open shadow_log.csv")?;
-
-    let window_start_ms = cfg.shadow.window_start_ms;
-    let window_end_ms = cfg.shadow.window_end_ms;
-
-    let mut store = TradeStore::new_with_cap(cfg.shadow.trade_retention_ms, cfg.shadow.max_trades);
-    let mut pending: Vec<Signal> = Vec::new();
-    let mut last_written_signal_id: u64 = 0;
+    let run_dir = shadow_path.parent().unwrap_or(Path::new(".")).to_path_buf();
+    let mut pending = recover_pending(&cfg, &run_dir, clock.now_ms());
+    let mut out = ShadowOutput::open(&shadow_path, cfg.shadow.jsonl_log)?;
+    let mut ledger = CsvAppender::open(run_dir.join(FILE_SIGNALS), &SIGNALS_HEADER)
+        .io_context("open signals.csv")?;
+
+    let mut store = TradeStore::new_with_cap(cfg.shadow.trade_retention_ms, cfg.shadow.max_trades)
+        .with_clock(clock.clone());
+    let restored = restore_trades(
+        &mut store,
+        &run_dir,
+        clock.now_ms(),
+        cfg.shadow.trade_retention_ms,
+    );
+    if restored > 0 {
+        info!(restored_trades = restored, "restored shadow trade store");
+        health.set_trade_store_size(store.len());
+    }
+    let fees = FeeRegistry::from_config(&cfg.fees);
+    let mut written_signal_ids: HashSet<SignalId> = HashSet::new();
+    let trade_drops = trade_rx.stats();
 
-    let mut tick = tokio::time::interval(Duration::from_millis(50));
+    let mut tick = tokio::time::interval(Duration::from_millis(cfg.shadow.settle_tick_ms));
     tick.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
 
     loop {
         tokio::select! {
             _ = shutdown.changed() => {
                 if *shutdown.borrow() {
-                    let now = now_ms();
+                    let now = clock.now_ms();
                     settle_ready(
                         &cfg,
+                        &fees,
                         &mut out,
                         &store,
                         &mut pending,
-                        &mut last_written_signal_id,
+                        &mut written_signal_ids,
                         now,
-                        window_start_ms,
-                        window_end_ms,
                         health.as_ref(),
+                        run_state.as_deref(),
+                        Some(&trade_drops),
                     )?;
                     break;
                 }
             }
+            // `[calibration.auto_tune]`; rows record the value they settled with.
+            Ok(()) = fill_shares.changed() => {
+                cfg.buckets = fill_shares.borrow().clone();
+            }
             maybe = trade_rx.recv() => {
                 let Some(t) = maybe else {
                     if *shutdown.borrow() {
-                        let now = now_ms();
+                        let now = clock.now_ms();
                         settle_ready(
                             &cfg,
+                            &fees,
                             &mut out,
                             &store,
                             &mut pending,
-                            &mut last_written_signal_id,
+                            &mut written_signal_ids,
                             now,
-                            window_start_ms,
-                            window_end_ms,
                             health.as_ref(),
+                            run_state.as_deref(),
+                            Some(&trade_drops),
                         )?;
                         break;
                     }
-                    return Err(anyhow::anyhow!("trade channel closed"));
+                    return Err(RazorError::ChannelClosed("trade").into());
                 };
                 let push = store.push(t);
                 if push.evicted > 0 {
@@ -85,56 +119,206 @@ pub async fn run(
             maybe = signal_rx.recv() => {
                 let Some(s) = maybe else {
                     if *shutdown.borrow() {
-                        let now = now_ms();
+                        let now = clock.now_ms();
                         settle_ready(
                             &cfg,
+                            &fees,
                             &mut out,
                             &store,
                             &mut pending,
-                            &mut last_written_signal_id,
+                            &mut written_signal_ids,
                             now,
-                            window_start_ms,
-                            window_end_ms,
                             health.as_ref(),
+                            run_state.as_deref(),
+                            Some(&trade_drops),
                         )?;
                         break;
                     }
-                    return Err(anyhow::anyhow!("signal channel closed"));
+                    return Err(RazorError::ChannelClosed("signal").into());
                 };
+                if let Some(state) = run_state.as_deref() {
+                    state.record_signal(&s);
+                }
+                ledger
+                    .write_row(&SignalRow::from(&s))
+                    .io_context("write signals.csv")?;
+                trace_signal!(s.signal_id, "shadow_enqueue", pending = pending.len() + 1);
                 pending.push(s);
             }
             _ = tick.tick() => {
-                let now = now_ms();
+                let now = clock.now_ms();
                 settle_ready(
                     &cfg,
+                    &fees,
                     &mut out,
                     &store,
                     &mut pending,
-                    &mut last_written_signal_id,
+                    &mut written_signal_ids,
                     now,
-                    window_start_ms,
-                    window_end_ms,
                     health.as_ref(),
+                    run_state.as_deref(),
+                    Some(&trade_drops),
                 )?;
+                if let Some(state) = run_state.as_deref() {
+                    state.set_pending_shadow(&pending, &cfg);
+                }
             }
         }
     }
 
-    out.flush_and_sync().context("flush shadow_log.csv")?;
+    ledger.flush_and_sync().io_context("flush signals.csv")?;
+    out.flush_and_sync()?;
     Ok(())
 }
 
+/// Where settled rows go: `shadow_log.csv`, mirrored to `shadow_log.jsonl` when
+/// `shadow.jsonl_log` is on.
+struct ShadowOutput {
+    csv: CsvAppender,
+    jsonl: Option<JsonlAppender>,
+}
+
+impl ShadowOutput {
+    fn open(shadow_path: &Path, jsonl_log: bool) -> anyhow::Result<Self> {
+        let csv =
+            CsvAppender::open(shadow_path, &SHADOW_HEADER).io_context("open shadow_log.csv")?;
+        let jsonl = if jsonl_log {
+            let path = shadow_path.with_file_name(FILE_SHADOW_LOG_JSONL);
+            Some(JsonlAppender::open(&path).io_context("open shadow_log.jsonl")?)
+        } else {
+            None
+        };
+        Ok(Self { csv, jsonl })
+    }
+
+    fn write_row(&mut self, row: &ShadowRow) -> anyhow::Result<()> {
+        self.csv.write_row(row).io_context("write shadow_log.csv")?;
+        if let Some(jsonl) = self.jsonl.as_mut() {
+            let line = serde_json::to_string(&ShadowRecord::from(row))?;
+            jsonl
+                .write_line(&line)
+                .io_context("write shadow_log.jsonl")?;
+        }
+        Ok(())
+    }
+
+    fn flush_and_sync(&mut self) -> anyhow::Result<()> {
+        self.csv
+            .flush_and_sync()
+            .io_context("flush shadow_log.csv")?;
+        if let Some(jsonl) = self.jsonl.as_mut() {
+            jsonl
+                .flush_and_sync()
+                .io_context("flush shadow_log.jsonl")?;
+        }
+        Ok(())
+    }
+}
+
+/// Signals a previous process of this run dir wrote to `signals.csv` but never settled into
+/// `shadow_log.csv`. Only those whose window is still open come back: a window that closed
+/// while razor was down has no trades to settle against.
+fn recover_pending(cfg: &Config, run_dir: &Path, now_ms: u64) -> Vec<Signal> {
+    match read_unsettled_signals(run_dir) {
+        Ok(unsettled) => {
+            let total = unsettled.len();
+            let pending: Vec<Signal> = unsettled
+                .into_iter()
+                .filter(|s| now_ms < s.signal_ts_ms + cfg.market(&s.market_id).window_end_ms)
+                .collect();
+            if total > 0 {
+                info!(
+                    recovered = pending.len(),
+                    expired = total - pending.len(),
+                    "recovered unsettled signals"
+                );
+            }
+            pending
+        }
+        Err(e) => {
+            warn!(error = %format!("{e:#}"), "signals not recovered");
+            Vec::new()
+        }
+    }
+}
+
+/// Loads the run dir's `trades.csv` tail into `store` before the first settle tick, so
+/// signals recovered on `--resume` settle against the trades recorded before the restart.
+fn restore_trades(store: &mut TradeStore, run_dir: &Path, now_ms: u64, retention_ms: u64) -> usize {
+    match read_recent_trades(run_dir, now_ms.saturating_sub(retention_ms)) {
+        Ok(trades) => {
+            let n = trades.len();
+            for t in trades {
+                let _ = store.push(t);
+            }
+            n
+        }
+        Err(e) => {
+            warn!(error = %format!("{e:#}"), "trades not restored");
+            0
+        }
+    }
+}
+
+fn read_unsettled_signals(run_dir: &Path) -> anyhow::Result<Vec<Signal>> {
+    let ledger_path = run_dir.join(FILE_SIGNALS);
+    if !ledger_path.is_file() {
+        return Ok(Vec::new());
+    }
+
+    let mut settled: HashSet<u64> = HashSet::new();
+    for path in csv_input_paths(run_dir, FILE_SHADOW_LOG) {
+        let mut rdr =
+            FrozenCsvReader::open(&path, FILE_SHADOW_LOG, &SHADOW_HEADER, SchemaMode::Compat)?;
+        let col = rdr
+            .header()
+            .iter()
+            .position(|h| h == "signal_id")
+            .ok_or_else(|| anyhow::anyhow!("{} has no signal_id column", path.display()))?;
+        for record in rdr.records() {
+            if let Some(id) = record?.get(col).and_then(|v| v.parse::<u64>().ok()) {
+                settled.insert(id);
+            }
+        }
+    }
+
+    let mut rdr = FrozenCsvReader::open(
+        &ledger_path,
+        FILE_SIGNALS,
+        &SIGNALS_HEADER,
+        SchemaMode::Compat,
+    )?;
+    let mut out = Vec::new();
+    for row in rdr.deserialize::<SignalRow>() {
+        let row = row?;
+        if !settled.contains(&row.signal_id) {
+            out.push(row.into_signal()?);
+        }
+    }
+    out.sort_by_key(|s| s.signal_id);
+    Ok(out)
+}
+
+/// Headline numbers of a settled shadow row (the full row goes to shadow_log.csv).
+struct SettleOutcome {
+    q_set: f64,
+    total_pnl: f64,
+    set_ratio: f64,
+    notes: String,
+}
+
 #[allow(clippy::too_many_arguments)]
 fn settle_ready(
     cfg: &Config,
-    out: &mut CsvAppender,
+    fees: &FeeRegistry,
+    out: &mut ShadowOutput,
     store: &TradeStore,
     pending: &mut Vec<Signal>,
-    last_written_signal_id: &mut u64,
+    written_signal_ids: &mut HashSet<SignalId>,
     now_ms: u64,
-    window_start_ms: u64,
-    window_end_ms: u64,
     health: &HealthCounters,
+    run_state: Option<&RunState>,
+    trade_drops: Option<&ChannelStats>,
 ) -> anyhow::Result<()> {
     if pending.is_empty() {
         return Ok(());
@@ -142,23 +326,64 @@ fn settle_ready(
 
     let mut still_pending = Vec::with_capacity(pending.len());
     for mut s in pending.drain(..) {
-        if now_ms < s.signal_ts_ms + window_end_ms {
+        let market = cfg.market(&s.market_id);
+        if now_ms < s.signal_ts_ms + market.window_end_ms {
             still_pending.push(s);
             continue;
         }
 
-        let is_dup = s.signal_id <= *last_written_signal_id;
+        // Per-market windows settle signals out of id order, so a watermark would misfire.
+        let is_dup = written_signal_ids.contains(&s.signal_id);
         if is_dup {
             s.reasons.push(ShadowNoteReason::DedupHit);
         }
+        if trade_drops.is_some_and(|d| {
+            d.dropped_between(
+                s.signal_ts_ms + market.window_start_ms,
+                s.signal_ts_ms + market.window_end_ms,
+            )
+        }) {
+            s.reasons.push(ShadowNoteReason::TradeChannelDrop);
+        }
 
-        if let Err(e) = settle_one(cfg, out, store, &s, window_start_ms, window_end_ms) {
-            tracing::warn!(signal_id = s.signal_id, market_id = %s.market_id, error = %e, "shadow settle error");
-            write_internal_error_row(cfg, out, &s, window_start_ms, window_end_ms)?;
-        } else if !is_dup {
-            *last_written_signal_id = s.signal_id;
+        match settle_one(cfg, fees, out, store, &s, &market, now_ms) {
+            Err(e) => {
+                crate::log_throttle::warn_throttled!(signal_id = s.signal_id.get(), market_id = %s.market_id, code = %code_of(&e), error = %e, "shadow settle error");
+                trace_signal!(s.signal_id, "shadow_settle_error", error = %e);
+                write_internal_error_row(cfg, fees, out, &s, &market)?;
+            }
+            Ok(outcome) => {
+                trace_signal!(
+                    s.signal_id,
+                    "shadow_settle",
+                    q_set = outcome.q_set,
+                    total_pnl = outcome.total_pnl,
+                    set_ratio = outcome.set_ratio,
+                    notes = %outcome.notes
+                );
+                if let Some(state) = run_state {
+                    state.record_settlement(SettlementView {
+                        signal_id: s.signal_id,
+                        market_id: s.market_id.clone(),
+                        signal_ts_ms: s.signal_ts_ms,
+                        q_set: outcome.q_set,
+                        total_pnl: outcome.total_pnl,
+                        set_ratio: outcome.set_ratio,
+                        notes: outcome.notes,
+                    });
+                }
+                if !is_dup {
+                    written_signal_ids.insert(s.signal_id);
+                    health.record_shadow_pnl(outcome.total_pnl);
+                }
+            }
         }
 
+        let window_end_abs_ms = s.signal_ts_ms + market.window_end_ms;
+        health.record_settle(
+            now_ms.saturating_sub(window_end_abs_ms),
+            store.latest_ingest_ts_ms() >= window_end_abs_ms,
+        );
         health.set_last_shadow_write_ms(now_ms);
         health.inc_shadow_processed(1);
     }
@@ -168,10 +393,10 @@ fn settle_ready(
 
 fn write_internal_error_row(
     cfg: &Config,
-    out: &mut CsvAppender,
+    fees: &FeeRegistry,
+    out: &mut ShadowOutput,
     s: &Signal,
-    window_start_ms: u64,
-    window_end_ms: u64,
+    market: &MarketParams,
 ) -> anyhow::Result<()> {
     let legs_n = s.legs.len();
     let mut legs_sorted = s.legs.clone();
@@ -179,68 +404,77 @@ fn write_internal_error_row(
     while legs_sorted.len() < 3 {
         legs_sorted.push(Leg {
             leg_index: legs_sorted.len(),
-            token_id: String::new(),
+            token_id: TokenId::default(),
             side: Side::Buy,
-            limit_price: 0.0,
-            qty: 0.0,
-            best_bid_at_signal: 0.0,
-            best_ask_at_signal: 0.0,
+            limit_price: Price::ZERO,
+            qty: Qty::ZERO,
+            best_bid_at_signal: Price::ZERO,
+            best_ask_at_signal: Price::ZERO,
         });
     }
 
     let notes = format_notes(&[ShadowNoteReason::InternalError]);
 
-    let mut record: Vec<String> = Vec::with_capacity(SHADOW_HEADER.len());
-    record.push(s.run_id.clone());
-    record.push(SCHEMA_VERSION.to_string());
-    record.push(s.signal_id.to_string());
-    record.push(s.signal_ts_ms.to_string());
-    record.push(window_start_ms.to_string());
-    record.push(window_end_ms.to_string());
-    record.push(s.market_id.clone());
-    record.push(s.strategy.as_str().to_string());
-    record.push(s.bucket.as_str().to_ascii_lowercase());
-    record.push(String::new()); // worst_leg_token_id
-    record.push(s.q_req.to_string());
-    record.push((legs_n as u8).to_string());
-    record.push("0".to_string()); // q_set
-
-    for leg in legs_sorted.iter().take(3) {
-        record.push(leg.token_id.clone());
-        record.push(leg.limit_price.to_string());
-        record.push(leg.best_bid_at_signal.to_string());
-        record.push("0".to_string()); // v_mkt
-        record.push("0".to_string()); // q_fill
-    }
-
-    record.push("0".to_string()); // cost_set
-    record.push("0".to_string()); // proceeds_set
-    record.push("0".to_string()); // pnl_set
-    record.push("0".to_string()); // pnl_left_total
-    record.push("0".to_string()); // total_pnl
-    record.push("0".to_string()); // q_fill_avg
-    record.push("0".to_string()); // set_ratio
-    record.push(fill_share_p25(s.bucket, &cfg.buckets).to_string());
-    record.push(DUMP_SLIPPAGE_ASSUMED.to_string());
-    record.push(notes);
-    debug_assert_eq!(record.len(), SHADOW_HEADER.len());
-
-    out.write_record(record)?;
+    let mut row = ShadowRow {
+        run_id: s.run_id.clone(),
+        schema_version: SCHEMA_VERSION.to_string(),
+        signal_id: s.signal_id.get(),
+        signal_ts_unix_ms: s.signal_ts_ms,
+        window_start_ms: market.window_start_ms,
+        window_end_ms: market.window_end_ms,
+        market_id: s.market_id.to_string(),
+        strategy: s.strategy.as_str().to_string(),
+        bucket: s.bucket.as_str().to_ascii_lowercase(),
+        bucket_classifier: s.bucket_metrics.classifier.as_str().to_string(),
+        q_req: s.q_req.to_f64(),
+        legs_n: legs_n as u8,
+        fill_share_p25_used: fill_share_p25(s.bucket, &market.buckets),
+        dump_slippage_assumed: DUMP_SLIPPAGE_ASSUMED,
+        volume_mode: cfg.shadow.volume_mode.as_str().to_string(),
+        fee_model: fees.resolve(s.signal_ts_ms).id.clone(),
+        notes,
+        ..Default::default()
+    };
+    for (i, leg) in legs_sorted.iter().take(3).enumerate() {
+        let (bucket, fill_share) = if i < legs_n {
+            let b = s.leg_bucket(i);
+            (
+                b.as_str().to_ascii_lowercase(),
+                fill_share_p25(b, &market.buckets),
+            )
+        } else {
+            (String::new(), 0.0)
+        };
+        row.set_leg(
+            i,
+            ShadowLegCols {
+                token_id: leg.token_id.to_string(),
+                p_limit: leg.limit_price.to_f64(),
+                best_bid: leg.best_bid_at_signal.to_f64(),
+                bucket,
+                fill_share,
+                ..Default::default()
+            },
+        );
+    }
+
+    out.write_row(&row)?;
     Ok(())
 }
 
 fn settle_one(
     cfg: &Config,
-    out: &mut CsvAppender,
+    fees: &FeeRegistry,
+    out: &mut ShadowOutput,
     store: &TradeStore,
     s: &Signal,
-    window_start_ms: u64,
-    window_end_ms: u64,
-) -> anyhow::Result<()> {
-    let start_ms = s.signal_ts_ms + window_start_ms;
-    let end_ms = s.signal_ts_ms + window_end_ms;
+    market: &MarketParams,
+    now_ms: u64,
+) -> anyhow::Result<SettleOutcome> {
+    let start_ms = s.signal_ts_ms + market.window_start_ms;
+    let end_ms = s.signal_ts_ms + market.window_end_ms;
 
-    let fill_share_used = fill_share_p25(s.bucket, &cfg.buckets);
+    let fill_share_used = fill_share_p25(s.bucket, &market.buckets);
     let window_stats = store.window_stats(&s.market_id, start_ms, end_ms);
 
     let legs_n = s.legs.len();
@@ -248,53 +482,87 @@ fn settle_one(
     // Make the CSV stable: always log legs in `leg_index` order (0..).
     let mut legs_sorted = s.legs.clone();
     legs_sorted.sort_by_key(|l| l.leg_index);
+    let leg_fill_shares: Vec<f64> = legs_sorted
+        .iter()
+        .map(|l| fill_share_p25(s.leg_bucket(l.leg_index), &market.buckets))
+        .collect();
 
     let mut reasons: Vec<ShadowNoteReason> = s.reasons.clone();
 
     let mut v_mkt: Vec<f64> = vec![0.0; legs_n.min(3)];
-    let mut q_fill: Vec<f64> = vec![0.0; legs_n.min(3)];
+    let mut v_mkt_all: Vec<f64> = vec![0.0; 3];
+    let mut v_mkt_sided: Vec<f64> = vec![0.0; 3];
     let mut invalid_limit = false;
 
     for (i, leg) in legs_sorted.iter().take(3).enumerate() {
-        if !leg.limit_price.is_finite() || leg.limit_price <= 0.0 {
+        if !leg.limit_price.is_positive() {
             invalid_limit = true;
             continue;
         }
-        let v = store.volume_at_or_better_price(
-            &s.market_id,
-            &leg.token_id,
-            start_ms,
-            end_ms,
-            leg.limit_price,
-        );
-        v_mkt[i] = v;
-        let v_my = v * fill_share_used;
-        q_fill[i] = s.q_req.min(v_my);
+        v_mkt_all[i] = store
+            .volume_at_or_better_price(
+                &s.market_id,
+                &leg.token_id,
+                start_ms,
+                end_ms,
+                leg.limit_price,
+            )
+            .to_f64();
+        // A resting buy fills against sellers crossing into it, and vice versa.
+        v_mkt_sided[i] = store
+            .sided_volume_at_or_better_price(
+                &s.market_id,
+                &leg.token_id,
+                start_ms,
+                end_ms,
+                leg.limit_price,
+                leg.side.opposite(),
+            )
+            .to_f64();
+        v_mkt[i] = match cfg.shadow.volume_mode {
+            VolumeMode::All => v_mkt_all[i],
+            VolumeMode::Sided => v_mkt_sided[i],
+        };
     }
 
-    let q_set = q_fill
+    let set_legs: Vec<SetLeg> = legs_sorted
         .iter()
-        .copied()
-        .fold(f64::INFINITY, |a, b| a.min(b))
-        .min(s.q_req);
-
-    let q_fill_sum: f64 = q_fill.iter().copied().sum();
-    let q_fill_avg = if legs_n > 0 {
-        q_fill_sum / (legs_n as f64)
-    } else {
-        0.0
-    };
+        .zip(&v_mkt)
+        .map(|(l, &v)| SetLeg {
+            p_limit: l.limit_price.to_f64(),
+            best_bid: l.best_bid_at_signal.to_f64(),
+            v_mkt: v,
+        })
+        .collect();
+    let fee_model = fees.resolve(s.signal_ts_ms);
+    let SetSettlement {
+        mut q_fill,
+        q_set,
+        cost_set,
+        proceeds_set,
+        pnl_set,
+        pnl_left_total,
+        total_pnl,
+        q_fill_avg,
+        set_ratio,
+    } = settle_set_per_leg(
+        s.q_req.to_f64(),
+        &set_legs,
+        &leg_fill_shares,
+        DUMP_SLIPPAGE_ASSUMED,
+        fee_model.rates,
+    );
 
     let mut legs: Vec<Leg> = legs_sorted;
     while legs.len() < 3 {
         legs.push(Leg {
             leg_index: legs.len(),
-            token_id: String::new(),
+            token_id: TokenId::default(),
             side: Side::Buy,
-            limit_price: 0.0,
-            qty: 0.0,
-            best_bid_at_signal: 0.0,
-            best_ask_at_signal: 0.0,
+            limit_price: Price::ZERO,
+            qty: Qty::ZERO,
+            best_bid_at_signal: Price::ZERO,
+            best_ask_at_signal: Price::ZERO,
         });
     }
     while v_mkt.len() < 3 {
@@ -304,64 +572,31 @@ fn settle_one(
         q_fill.push(0.0);
     }
 
-    let mut q_left: Vec<f64> = vec![0.0; 3];
-    for i in 0..legs_n.min(3) {
-        q_left[i] = q_fill[i] - q_set;
-    }
-
-    let cost_per_set: f64 = legs
-        .iter()
-        .take(legs_n.min(3))
-        .map(|l| Bps::FEE_POLY.apply_cost(l.limit_price))
-        .sum();
-    let proceeds_per_set = Bps::FEE_MERGE.apply_proceeds(1.0);
-
-    let cost_set = q_set * cost_per_set;
-    let proceeds_set = q_set * proceeds_per_set;
-    let pnl_set = proceeds_set - cost_set;
-
-    let mut pnl_left_total = 0.0f64;
     let mut bid_missing_legs: Vec<usize> = Vec::new();
     let mut book_missing_legs: Vec<usize> = Vec::new();
     for (i, l) in legs.iter().take(legs_n.min(3)).enumerate() {
-        let bid_missing = !l.best_bid_at_signal.is_finite() || l.best_bid_at_signal <= 0.0;
+        let bid_missing = !l.best_bid_at_signal.is_positive();
         if bid_missing {
             bid_missing_legs.push(i);
-            let ask_missing = !l.best_ask_at_signal.is_finite() || l.best_ask_at_signal <= 0.0;
+            let ask_missing = !l.best_ask_at_signal.is_positive();
             if ask_missing {
                 book_missing_legs.push(i);
             }
         }
-        let exit_price = if bid_missing {
-            0.0
-        } else {
-            l.best_bid_at_signal * LEFTOVER_DUMP_MULT
-        };
-        let cost = q_left[i] * Bps::FEE_POLY.apply_cost(l.limit_price);
-        let proceeds = q_left[i] * Bps::FEE_POLY.apply_proceeds(exit_price);
-        let pnl = proceeds - cost;
-        pnl_left_total += pnl;
     }
 
-    let total_pnl = pnl_set + pnl_left_total;
-    let set_ratio = if q_fill_avg > 0.0 {
-        q_set / q_fill_avg
-    } else {
-        0.0
-    };
-
     if legs_n != 2 && legs_n != 3 {
         reasons.push(ShadowNoteReason::LegsMismatch);
     }
 
-    if !s.q_req.is_finite() || s.q_req <= 0.0 {
+    if !s.q_req.is_positive() {
         reasons.push(ShadowNoteReason::InvalidQty);
     }
 
     if legs
         .iter()
         .take(legs_n.min(3))
-        .any(|l| !l.qty.is_finite() || l.qty <= 0.0)
+        .any(|l| !l.qty.is_positive())
     {
         reasons.push(ShadowNoteReason::InvalidQty);
     }
@@ -407,11 +642,11 @@ fn settle_one(
         reasons.push(ShadowNoteReason::NoTrades);
     }
 
-    if fill_share_used <= 0.0 || !fill_share_used.is_finite() {
+    if leg_fill_shares.iter().any(|&f| f <= 0.0 || !f.is_finite()) {
         reasons.push(ShadowNoteReason::FillShareP25Zero);
     }
 
-    if now_ms().saturating_sub(s.signal_ts_ms) > cfg.shadow.trade_retention_ms {
+    if now_ms.saturating_sub(s.signal_ts_ms) > cfg.shadow.trade_retention_ms {
         reasons.push(ShadowNoteReason::SignalTooOld);
     }
 
@@ -427,7 +662,7 @@ fn settle_one(
     } else {
         legs.iter()
             .find(|l| l.leg_index == s.bucket_metrics.worst_leg_index)
-            .map(|l| l.token_id.clone())
+            .map(|l| l.token_id.to_string())
             .unwrap_or_default()
     };
 
@@ -442,66 +677,98 @@ fn settle_one(
 
     let notes = format_notes(&reasons);
 
-    let mut record: Vec<String> = Vec::with_capacity(SHADOW_HEADER.len());
-    record.push(s.run_id.clone());
-    record.push(SCHEMA_VERSION.to_string());
-    record.push(s.signal_id.to_string());
-    record.push(s.signal_ts_ms.to_string());
-    record.push(window_start_ms.to_string());
-    record.push(window_end_ms.to_string());
-    record.push(s.market_id.clone());
-    record.push(s.strategy.as_str().to_string());
-    record.push(s.bucket.as_str().to_ascii_lowercase());
-    record.push(worst_leg_token_id);
-    record.push(s.q_req.to_string());
-    record.push((legs_n as u8).to_string());
-    record.push(q_set.to_string());
-
+    let mut row = ShadowRow {
+        run_id: s.run_id.clone(),
+        schema_version: SCHEMA_VERSION.to_string(),
+        signal_id: s.signal_id.get(),
+        signal_ts_unix_ms: s.signal_ts_ms,
+        window_start_ms: market.window_start_ms,
+        window_end_ms: market.window_end_ms,
+        market_id: s.market_id.to_string(),
+        strategy: s.strategy.as_str().to_string(),
+        bucket: s.bucket.as_str().to_ascii_lowercase(),
+        bucket_classifier: s.bucket_metrics.classifier.as_str().to_string(),
+        worst_leg_token_id,
+        q_req: s.q_req.to_f64(),
+        legs_n: legs_n as u8,
+        q_set,
+        cost_set,
+        proceeds_set,
+        pnl_set,
+        pnl_left_total,
+        total_pnl,
+        q_fill_avg,
+        set_ratio,
+        fill_share_p25_used: fill_share_used,
+        dump_slippage_assumed: DUMP_SLIPPAGE_ASSUMED,
+        volume_mode: cfg.shadow.volume_mode.as_str().to_string(),
+        fee_model: fee_model.id.clone(),
+        notes: notes.clone(),
+        ..Default::default()
+    };
     for i in 0..3 {
-        record.push(legs[i].token_id.clone());
-        record.push(legs[i].limit_price.to_string());
-        record.push(legs[i].best_bid_at_signal.to_string());
-        record.push(v_mkt[i].to_string());
-        record.push(q_fill[i].to_string());
+        let (bucket, fill_share) = match leg_fill_shares.get(i) {
+            Some(&share) => (
+                s.leg_bucket(legs[i].leg_index)
+                    .as_str()
+                    .to_ascii_lowercase(),
+                share,
+            ),
+            None => (String::new(), 0.0),
+        };
+        row.set_leg(
+            i,
+            ShadowLegCols {
+                token_id: legs[i].token_id.to_string(),
+                p_limit: legs[i].limit_price.to_f64(),
+                best_bid: legs[i].best_bid_at_signal.to_f64(),
+                v_mkt: v_mkt[i],
+                v_mkt_all: v_mkt_all[i],
+                v_mkt_sided: v_mkt_sided[i],
+                q_fill: q_fill[i],
+                bucket,
+                fill_share,
+            },
+        );
     }
 
-    record.push(cost_set.to_string());
-    record.push(proceeds_set.to_string());
-    record.push(pnl_set.to_string());
-    record.push(pnl_left_total.to_string());
-    record.push(total_pnl.to_string());
-    record.push(q_fill_avg.to_string());
-    record.push(set_ratio.to_string());
-    record.push(fill_share_used.to_string());
-    record.push(DUMP_SLIPPAGE_ASSUMED.to_string());
-    record.push(notes);
-    debug_assert_eq!(record.len(), SHADOW_HEADER.len());
+    out.write_row(&row)?;
 
-    out.write_record(record)?;
+    debug!(
+        signal_id = s.signal_id.get(),
+        q_set, total_pnl, "shadow settle"
+    );
 
-    debug!(signal_id = s.signal_id, q_set, total_pnl, "shadow settle");
-
-    if s.signal_id % 100 == 0 {
-        info!(signal_id = s.signal_id, "shadow checkpoint");
+    if s.signal_id.get().is_multiple_of(100) {
+        info!(signal_id = s.signal_id.get(), "shadow checkpoint");
     }
 
-    Ok(())
+    Ok(SettleOutcome {
+        q_set,
+        total_pnl,
+        set_ratio,
+        notes,
+    })
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::clock::{Clock as _, SimClock};
     use crate::config::{
-        BrainConfig, BucketConfig, CalibrationConfig, Config, LiveConfig, MarketSelectConfig,
-        PolymarketConfig, ReportConfig, RunConfig, ShadowConfig, SimConfig,
+        ApiConfig, BrainConfig, BucketConfig, CalibrationConfig, Config, DiskConfig, EmailConfig,
+        EscalationConfig, HealthConfig, LiveConfig, MarketSelectConfig, PolymarketConfig,
+        RecorderConfig, ReportConfig, RiskConfig, RunConfig, ShadowConfig, SimConfig, UploadConfig,
     };
-    use crate::recorder::CsvAppender;
-    use crate::types::{Bps, Bucket, BucketMetrics, Leg, Side, Strategy};
+    use crate::recorder::{CsvAppender, TRADES_HEADER};
+    use crate::schema::FILE_TRADES;
+    use crate::types::{Bps, Bucket, BucketClassifierVersion, BucketMetrics, Leg, Side, Strategy};
     use assert_approx_eq::assert_approx_eq;
 
     #[test]
     fn settles_binary_signal_with_leftover_penalty() {
-        let base_ms = now_ms();
+        let clock = SimClock::starting_at_ms(1_700_000_000_000);
+        let base_ms = clock.now_ms();
         let cfg = Config {
             polymarket: PolymarketConfig::default(),
             run: RunConfig {
@@ -509,18 +776,26 @@ mod tests {
                 market_ids: vec![],
                 snapshot_log_interval_ms: 1_000,
                 raw_ws_rotate_keep: 0,
+                raw_ws_rotate_interval_ms: 0,
+                raw_ws_max_total_mb: 0,
+                partition_hourly: false,
+                shutdown_deadline_ms: 10_000,
+                schedule: Default::default(),
+                labels: Default::default(),
             },
             schema_version: crate::schema::SCHEMA_VERSION.to_string(),
             brain: BrainConfig {
                 risk_premium_bps: 80,
                 min_net_edge_bps: 10,
-                q_req: 10.0,
+                q_req: Qty::from_f64(10.0).unwrap(),
                 signal_cooldown_ms: 0,
                 max_snapshot_staleness_ms: 500,
+                ..BrainConfig::default()
             },
             buckets: BucketConfig {
                 fill_share_liquid_p25: 0.5,
                 fill_share_thin_p25: 0.1,
+                ..BucketConfig::default()
             },
             shadow: ShadowConfig::default(),
             market_select: MarketSelectConfig::default(),
@@ -528,22 +803,40 @@ mod tests {
             live: LiveConfig::default(),
             calibration: CalibrationConfig::default(),
             sim: SimConfig::default(),
+            health: HealthConfig::default(),
+            disk: DiskConfig::default(),
+            risk: RiskConfig::default(),
+            api: ApiConfig::default(),
+            email: EmailConfig::default(),
+            escalation: EscalationConfig::default(),
+            upload: UploadConfig::default(),
+            recorder: RecorderConfig::default(),
+            backpressure: Default::default(),
+            bucket_classifier: Default::default(),
+            conflation: Default::default(),
+            demo: Default::default(),
+            reconcile: Default::default(),
+            fees: Default::default(),
+            gas: Default::default(),
+            metrics: Default::default(),
+            markets: Default::default(),
+            secrets: Default::default(),
         };
 
         let tmp =
             std::env::temp_dir().join(format!("razor_shadow_test_{}.csv", std::process::id()));
         let _ = std::fs::remove_file(&tmp);
-        let mut out = CsvAppender::open(&tmp, &SHADOW_HEADER).expect("open csv");
+        let mut out = ShadowOutput::open(&tmp, false).expect("open csv");
 
         let s = Signal {
             run_id: "run_test".to_string(),
-            signal_id: 1,
+            signal_id: SignalId(1),
             signal_ts_ms: base_ms,
-            market_id: "mkt".to_string(),
+            market_id: "mkt".into(),
             strategy: Strategy::Binary,
             bucket: Bucket::Liquid,
             reasons: Vec::new(),
-            q_req: 10.0,
+            q_req: Qty::from_f64(10.0).unwrap(),
             raw_cost_bps: Bps::from_price_cost(0.97),
             raw_edge_bps: Bps::new(300),
             hard_fees_bps: Bps::FEE_POLY + Bps::FEE_MERGE,
@@ -554,52 +847,66 @@ mod tests {
                 worst_spread_bps: 0,
                 worst_depth3_usdc: 1000.0,
                 is_depth3_degraded: false,
+                depth_levels: 3,
+                worst_depth_usdc: 1000.0,
+                classifier: BucketClassifierVersion::V1,
+                leg_buckets: Vec::new(),
             },
             legs: vec![
                 Leg {
                     leg_index: 0,
-                    token_id: "A".to_string(),
+                    token_id: "A".into(),
                     side: Side::Buy,
-                    limit_price: 0.49,
-                    qty: 10.0,
-                    best_bid_at_signal: 0.48,
-                    best_ask_at_signal: 0.49,
+                    limit_price: Price::from_f64(0.49).unwrap(),
+                    qty: Qty::from_f64(10.0).unwrap(),
+                    best_bid_at_signal: Price::from_f64(0.48).unwrap(),
+                    best_ask_at_signal: Price::from_f64(0.49).unwrap(),
                 },
                 Leg {
                     leg_index: 1,
-                    token_id: "B".to_string(),
+                    token_id: "B".into(),
                     side: Side::Buy,
-                    limit_price: 0.48,
-                    qty: 10.0,
-                    best_bid_at_signal: 0.47,
-                    best_ask_at_signal: 0.48,
+                    limit_price: Price::from_f64(0.48).unwrap(),
+                    qty: Qty::from_f64(10.0).unwrap(),
+                    best_bid_at_signal: Price::from_f64(0.47).unwrap(),
+                    best_ask_at_signal: Price::from_f64(0.48).unwrap(),
                 },
             ],
         };
 
-        let mut store = TradeStore::new_with_cap(60_000, usize::MAX);
+        let mut store = TradeStore::new_with_cap(60_000, usize::MAX).with_clock(clock.clone());
         let _ = store.push(TradeTick {
             ts_ms: base_ms + 200,
             ingest_ts_ms: base_ms + 200,
             exchange_ts_ms: Some(base_ms + 200),
-            market_id: "mkt".to_string(),
-            token_id: "A".to_string(),
-            price: 0.48,
-            size: 30.0,
+            market_id: "mkt".into(),
+            token_id: "A".into(),
+            price: Price::from_f64(0.48).unwrap(),
+            size: Qty::from_f64(30.0).unwrap(),
             trade_id: "t1".to_string(),
+            side: None,
         });
         let _ = store.push(TradeTick {
             ts_ms: base_ms + 200,
             ingest_ts_ms: base_ms + 200,
             exchange_ts_ms: Some(base_ms + 200),
-            market_id: "mkt".to_string(),
-            token_id: "B".to_string(),
-            price: 0.48,
-            size: 12.0,
+            market_id: "mkt".into(),
+            token_id: "B".into(),
+            price: Price::from_f64(0.48).unwrap(),
+            size: Qty::from_f64(12.0).unwrap(),
             trade_id: "t2".to_string(),
+            // A buyer lifting offers never reaches our resting bid: only `volume_mode = sided`
+            // drops it.
+            side: Some(Side::Buy),
         });
 
-        settle_one(&cfg, &mut out, &store, &s, 100, 1_100).expect("settle");
+        let market = MarketParams {
+            window_start_ms: 100,
+            window_end_ms: 1_100,
+            ..cfg.market(&s.market_id)
+        };
+        let fees = FeeRegistry::from_config(&cfg.fees);
+        settle_one(&cfg, &fees, &mut out, &store, &s, &market, clock.now_ms()).expect("settle");
         out.flush_and_sync().expect("flush");
 
         let text = std::fs::read_to_string(&tmp).expect("read csv");
@@ -628,6 +935,10 @@ mod tests {
         let pnl_total: f64 = cols[idx("total_pnl")].parse().expect("total_pnl");
         let notes = cols[idx("notes")];
         assert_eq!(notes, "");
+        assert_eq!(cols[idx("volume_mode")], "all");
+        assert_eq!(cols[idx("leg1_v_mkt")], cols[idx("leg1_v_mkt_all")]);
+        let v_sided: f64 = cols[idx("leg1_v_mkt_sided")].parse().expect("v_mkt_sided");
+        assert_approx_eq!(v_sided, 0.0, 1e-9);
 
         // q_fill: A=10, B=6 => q_set=6, q_fill_avg=8, set_ratio=0.75
         assert_approx_eq!(q_set, 6.0, 1e-9);
@@ -638,7 +949,7 @@ mod tests {
         let proceeds_per_set = Bps::FEE_MERGE.apply_proceeds(1.0);
         let expected_pnl_set = (6.0 * proceeds_per_set) - (6.0 * cost_per_set);
 
-        let exit_a = 0.48 * LEFTOVER_DUMP_MULT;
+        let exit_a = 0.48 * (1.0 - DUMP_SLIPPAGE_ASSUMED);
         let expected_left_cost_a = 4.0 * Bps::FEE_POLY.apply_cost(0.49);
         let expected_left_proceeds_a = 4.0 * Bps::FEE_POLY.apply_proceeds(exit_a);
         let expected_pnl_left = expected_left_proceeds_a - expected_left_cost_a;
@@ -652,7 +963,8 @@ mod tests {
 
     #[test]
     fn bid_missing_hard_penalty_is_visible_in_notes() {
-        let base_ms = now_ms();
+        let clock = SimClock::starting_at_ms(1_700_000_000_000);
+        let base_ms = clock.now_ms();
         let cfg = Config {
             polymarket: PolymarketConfig::default(),
             run: RunConfig {
@@ -660,18 +972,26 @@ mod tests {
                 market_ids: vec![],
                 snapshot_log_interval_ms: 1_000,
                 raw_ws_rotate_keep: 0,
+                raw_ws_rotate_interval_ms: 0,
+                raw_ws_max_total_mb: 0,
+                partition_hourly: false,
+                shutdown_deadline_ms: 10_000,
+                schedule: Default::default(),
+                labels: Default::default(),
             },
             schema_version: crate::schema::SCHEMA_VERSION.to_string(),
             brain: BrainConfig {
                 risk_premium_bps: 80,
                 min_net_edge_bps: 10,
-                q_req: 10.0,
+                q_req: Qty::from_f64(10.0).unwrap(),
                 signal_cooldown_ms: 0,
                 max_snapshot_staleness_ms: 500,
+                ..BrainConfig::default()
             },
             buckets: BucketConfig {
                 fill_share_liquid_p25: 0.5,
                 fill_share_thin_p25: 0.1,
+                ..BucketConfig::default()
             },
             shadow: ShadowConfig::default(),
             market_select: MarketSelectConfig::default(),
@@ -679,6 +999,24 @@ mod tests {
             live: LiveConfig::default(),
             calibration: CalibrationConfig::default(),
             sim: SimConfig::default(),
+            health: HealthConfig::default(),
+            disk: DiskConfig::default(),
+            risk: RiskConfig::default(),
+            api: ApiConfig::default(),
+            email: EmailConfig::default(),
+            escalation: EscalationConfig::default(),
+            upload: UploadConfig::default(),
+            recorder: RecorderConfig::default(),
+            backpressure: Default::default(),
+            bucket_classifier: Default::default(),
+            conflation: Default::default(),
+            demo: Default::default(),
+            reconcile: Default::default(),
+            fees: Default::default(),
+            gas: Default::default(),
+            metrics: Default::default(),
+            markets: Default::default(),
+            secrets: Default::default(),
         };
 
         let tmp = std::env::temp_dir().join(format!(
@@ -686,17 +1024,17 @@ mod tests {
             std::process::id()
         ));
         let _ = std::fs::remove_file(&tmp);
-        let mut out = CsvAppender::open(&tmp, &SHADOW_HEADER).expect("open csv");
+        let mut out = ShadowOutput::open(&tmp, false).expect("open csv");
 
         let s = Signal {
             run_id: "run_test".to_string(),
-            signal_id: 1,
+            signal_id: SignalId(1),
             signal_ts_ms: base_ms,
-            market_id: "mkt".to_string(),
+            market_id: "mkt".into(),
             strategy: Strategy::Binary,
             bucket: Bucket::Liquid,
             reasons: Vec::new(),
-            q_req: 10.0,
+            q_req: Qty::from_f64(10.0).unwrap(),
             raw_cost_bps: Bps::from_price_cost(0.97),
             raw_edge_bps: Bps::new(300),
             hard_fees_bps: Bps::FEE_POLY + Bps::FEE_MERGE,
@@ -707,52 +1045,64 @@ mod tests {
                 worst_spread_bps: 0,
                 worst_depth3_usdc: 1000.0,
                 is_depth3_degraded: false,
+                depth_levels: 3,
+                worst_depth_usdc: 1000.0,
+                classifier: BucketClassifierVersion::V1,
+                leg_buckets: Vec::new(),
             },
             legs: vec![
                 Leg {
                     leg_index: 0,
-                    token_id: "A".to_string(),
+                    token_id: "A".into(),
                     side: Side::Buy,
-                    limit_price: 0.49,
-                    qty: 10.0,
-                    best_bid_at_signal: 0.0, // missing
-                    best_ask_at_signal: 0.49,
+                    limit_price: Price::from_f64(0.49).unwrap(),
+                    qty: Qty::from_f64(10.0).unwrap(),
+                    best_bid_at_signal: Price::ZERO, // missing
+                    best_ask_at_signal: Price::from_f64(0.49).unwrap(),
                 },
                 Leg {
                     leg_index: 1,
-                    token_id: "B".to_string(),
+                    token_id: "B".into(),
                     side: Side::Buy,
-                    limit_price: 0.48,
-                    qty: 10.0,
-                    best_bid_at_signal: 0.47,
-                    best_ask_at_signal: 0.48,
+                    limit_price: Price::from_f64(0.48).unwrap(),
+                    qty: Qty::from_f64(10.0).unwrap(),
+                    best_bid_at_signal: Price::from_f64(0.47).unwrap(),
+                    best_ask_at_signal: Price::from_f64(0.48).unwrap(),
                 },
             ],
         };
 
-        let mut store = TradeStore::new_with_cap(60_000, usize::MAX);
+        let mut store = TradeStore::new_with_cap(60_000, usize::MAX).with_clock(clock.clone());
         let _ = store.push(TradeTick {
             ts_ms: base_ms + 200,
             ingest_ts_ms: base_ms + 200,
             exchange_ts_ms: Some(base_ms + 200),
-            market_id: "mkt".to_string(),
-            token_id: "A".to_string(),
-            price: 0.48,
-            size: 30.0,
+            market_id: "mkt".into(),
+            token_id: "A".into(),
+            price: Price::from_f64(0.48).unwrap(),
+            size: Qty::from_f64(30.0).unwrap(),
             trade_id: "t1".to_string(),
+            side: None,
         });
         let _ = store.push(TradeTick {
             ts_ms: base_ms + 200,
             ingest_ts_ms: base_ms + 200,
             exchange_ts_ms: Some(base_ms + 200),
-            market_id: "mkt".to_string(),
-            token_id: "B".to_string(),
-            price: 0.48,
-            size: 12.0,
+            market_id: "mkt".into(),
+            token_id: "B".into(),
+            price: Price::from_f64(0.48).unwrap(),
+            size: Qty::from_f64(12.0).unwrap(),
             trade_id: "t2".to_string(),
+            side: None,
         });
 
-        settle_one(&cfg, &mut out, &store, &s, 100, 1_100).expect("settle");
+        let market = MarketParams {
+            window_start_ms: 100,
+            window_end_ms: 1_100,
+            ..cfg.market(&s.market_id)
+        };
+        let fees = FeeRegistry::from_config(&cfg.fees);
+        settle_one(&cfg, &fees, &mut out, &store, &s, &market, clock.now_ms()).expect("settle");
         out.flush_and_sync().expect("flush");
 
         let text = std::fs::read_to_string(&tmp).expect("read csv");
@@ -785,7 +1135,8 @@ mod tests {
 
     #[test]
     fn trade_size_suspect_reason_is_emitted() {
-        let base_ms = now_ms();
+        let clock = SimClock::starting_at_ms(1_700_000_000_000);
+        let base_ms = clock.now_ms();
         let mut cfg = Config {
             polymarket: PolymarketConfig::default(),
             run: RunConfig {
@@ -793,12 +1144,19 @@ mod tests {
                 market_ids: vec![],
                 snapshot_log_interval_ms: 1_000,
                 raw_ws_rotate_keep: 0,
+                raw_ws_rotate_interval_ms: 0,
+                raw_ws_max_total_mb: 0,
+                partition_hourly: false,
+                shutdown_deadline_ms: 10_000,
+                schedule: Default::default(),
+                labels: Default::default(),
             },
             schema_version: crate::schema::SCHEMA_VERSION.to_string(),
             brain: BrainConfig::default(),
             buckets: BucketConfig {
                 fill_share_liquid_p25: 0.5,
                 fill_share_thin_p25: 0.1,
+                ..BucketConfig::default()
             },
             shadow: ShadowConfig::default(),
             market_select: MarketSelectConfig::default(),
@@ -806,6 +1164,24 @@ mod tests {
             live: LiveConfig::default(),
             calibration: CalibrationConfig::default(),
             sim: SimConfig::default(),
+            health: HealthConfig::default(),
+            disk: DiskConfig::default(),
+            risk: RiskConfig::default(),
+            api: ApiConfig::default(),
+            email: EmailConfig::default(),
+            escalation: EscalationConfig::default(),
+            upload: UploadConfig::default(),
+            recorder: RecorderConfig::default(),
+            backpressure: Default::default(),
+            bucket_classifier: Default::default(),
+            conflation: Default::default(),
+            demo: Default::default(),
+            reconcile: Default::default(),
+            fees: Default::default(),
+            gas: Default::default(),
+            metrics: Default::default(),
+            markets: Default::default(),
+            secrets: Default::default(),
         };
         cfg.shadow.trade_size_suspect_threshold = 10.0;
         cfg.shadow.trade_notional_suspect_threshold = 0.0;
@@ -815,17 +1191,17 @@ mod tests {
             std::process::id()
         ));
         let _ = std::fs::remove_file(&tmp);
-        let mut out = CsvAppender::open(&tmp, &SHADOW_HEADER).expect("open csv");
+        let mut out = ShadowOutput::open(&tmp, false).expect("open csv");
 
         let s = Signal {
             run_id: "run_test".to_string(),
-            signal_id: 1,
+            signal_id: SignalId(1),
             signal_ts_ms: base_ms,
-            market_id: "mkt".to_string(),
+            market_id: "mkt".into(),
             strategy: Strategy::Binary,
             bucket: Bucket::Liquid,
             reasons: Vec::new(),
-            q_req: 10.0,
+            q_req: Qty::from_f64(10.0).unwrap(),
             raw_cost_bps: Bps::from_price_cost(0.97),
             raw_edge_bps: Bps::new(300),
             hard_fees_bps: Bps::FEE_POLY + Bps::FEE_MERGE,
@@ -836,42 +1212,53 @@ mod tests {
                 worst_spread_bps: 0,
                 worst_depth3_usdc: 1000.0,
                 is_depth3_degraded: false,
+                depth_levels: 3,
+                worst_depth_usdc: 1000.0,
+                classifier: BucketClassifierVersion::V1,
+                leg_buckets: Vec::new(),
             },
             legs: vec![
                 Leg {
                     leg_index: 0,
-                    token_id: "A".to_string(),
+                    token_id: "A".into(),
                     side: Side::Buy,
-                    limit_price: 0.49,
-                    qty: 10.0,
-                    best_bid_at_signal: 0.48,
-                    best_ask_at_signal: 0.49,
+                    limit_price: Price::from_f64(0.49).unwrap(),
+                    qty: Qty::from_f64(10.0).unwrap(),
+                    best_bid_at_signal: Price::from_f64(0.48).unwrap(),
+                    best_ask_at_signal: Price::from_f64(0.49).unwrap(),
                 },
                 Leg {
                     leg_index: 1,
-                    token_id: "B".to_string(),
+                    token_id: "B".into(),
                     side: Side::Buy,
-                    limit_price: 0.48,
-                    qty: 10.0,
-                    best_bid_at_signal: 0.47,
-                    best_ask_at_signal: 0.48,
+                    limit_price: Price::from_f64(0.48).unwrap(),
+                    qty: Qty::from_f64(10.0).unwrap(),
+                    best_bid_at_signal: Price::from_f64(0.47).unwrap(),
+                    best_ask_at_signal: Price::from_f64(0.48).unwrap(),
                 },
             ],
         };
 
-        let mut store = TradeStore::new_with_cap(60_000, usize::MAX);
+        let mut store = TradeStore::new_with_cap(60_000, usize::MAX).with_clock(clock.clone());
         let _ = store.push(TradeTick {
             ts_ms: base_ms + 200,
             ingest_ts_ms: base_ms + 200,
             exchange_ts_ms: Some(base_ms + 200),
-            market_id: "mkt".to_string(),
-            token_id: "A".to_string(),
-            price: 0.48,
-            size: 30.0,
+            market_id: "mkt".into(),
+            token_id: "A".into(),
+            price: Price::from_f64(0.48).unwrap(),
+            size: Qty::from_f64(30.0).unwrap(),
             trade_id: "t1".to_string(),
+            side: None,
         });
 
-        settle_one(&cfg, &mut out, &store, &s, 100, 1_100).expect("settle");
+        let market = MarketParams {
+            window_start_ms: 100,
+            window_end_ms: 1_100,
+            ..cfg.market(&s.market_id)
+        };
+        let fees = FeeRegistry::from_config(&cfg.fees);
+        settle_one(&cfg, &fees, &mut out, &store, &s, &market, clock.now_ms()).expect("settle");
         out.flush_and_sync().expect("flush");
 
         let text = std::fs::read_to_string(&tmp).expect("read csv");
@@ -891,4 +1278,407 @@ mod tests {
         let notes = cols[idx("notes")];
         assert_eq!(notes, "TRADE_SIZE_SUSPECT");
     }
+
+    #[test]
+    fn settle_ready_waits_for_the_window_on_the_injected_clock() {
+        let cfg: Config = toml::from_str("[run]\nmarket_ids = [\"mkt\"]\n").unwrap();
+        let clock = SimClock::starting_at_ms(1_700_000_000_000);
+        let signal_ts_ms = clock.now_ms();
+        let window_end_ms = cfg.market("mkt").window_end_ms;
+
+        let tmp = std::env::temp_dir().join(format!(
+            "razor_shadow_clock_test_{}.csv",
+            std::process::id()
+        ));
+        let _ = std::fs::remove_file(&tmp);
+        let mut out = ShadowOutput::open(&tmp, false).expect("open csv");
+        let store = TradeStore::new_with_cap(60_000, usize::MAX).with_clock(clock.clone());
+        let health = HealthCounters::default();
+        let leg = |leg_index: usize, token_id: &str| Leg {
+            leg_index,
+            token_id: token_id.into(),
+            side: Side::Buy,
+            limit_price: Price::from_f64(0.48).unwrap(),
+            qty: Qty::from_f64(10.0).unwrap(),
+            best_bid_at_signal: Price::from_f64(0.47).unwrap(),
+            best_ask_at_signal: Price::from_f64(0.48).unwrap(),
+        };
+        let mut pending = vec![Signal {
+            run_id: "run_test".to_string(),
+            signal_id: SignalId(7),
+            signal_ts_ms,
+            market_id: "mkt".into(),
+            strategy: Strategy::Binary,
+            bucket: Bucket::Liquid,
+            reasons: Vec::new(),
+            q_req: Qty::from_f64(10.0).unwrap(),
+            raw_cost_bps: Bps::from_price_cost(0.96),
+            raw_edge_bps: Bps::new(400),
+            hard_fees_bps: Bps::FEE_POLY + Bps::FEE_MERGE,
+            risk_premium_bps: Bps::new(80),
+            expected_net_bps: Bps::new(10),
+            bucket_metrics: BucketMetrics {
+                worst_leg_index: 0,
+                worst_spread_bps: 0,
+                worst_depth3_usdc: 1000.0,
+                is_depth3_degraded: false,
+                depth_levels: 3,
+                worst_depth_usdc: 1000.0,
+                classifier: BucketClassifierVersion::V1,
+                leg_buckets: Vec::new(),
+            },
+            legs: vec![leg(0, "A"), leg(1, "B")],
+        }];
+        let mut written = HashSet::new();
+
+        clock.set_ms(signal_ts_ms + window_end_ms - 1);
+        let fees = FeeRegistry::from_config(&cfg.fees);
+        settle_ready(
+            &cfg,
+            &fees,
+            &mut out,
+            &store,
+            &mut pending,
+            &mut written,
+            clock.now_ms(),
+            &health,
+            None,
+            None,
+        )
+        .expect("settle");
+        assert_eq!(pending.len(), 1);
+        assert!(written.is_empty());
+
+        clock.advance_ms(1);
+        settle_ready(
+            &cfg,
+            &fees,
+            &mut out,
+            &store,
+            &mut pending,
+            &mut written,
+            clock.now_ms(),
+            &health,
+            None,
+            None,
+        )
+        .expect("settle");
+        assert!(pending.is_empty());
+        assert!(written.contains(&SignalId(7)));
+        let _ = std::fs::remove_file(&tmp);
+    }
+
+    #[test]
+    fn per_market_windows_settle_out_of_id_order_without_dedup_hits() {
+        let cfg: Config = toml::from_str(
+            "[run]\nmarket_ids = [\"slow\", \"fast\"]\n\
+             [markets.\"slow\"]\nwindow_end_ms = 4000\n\
+             [markets.\"fast\"]\nwindow_end_ms = 2000\n",
+        )
+        .unwrap();
+        cfg.validate().unwrap();
+        let clock = SimClock::starting_at_ms(1_700_000_000_000);
+        let signal_ts_ms = clock.now_ms();
+
+        let tmp = std::env::temp_dir().join(format!(
+            "razor_shadow_window_order_test_{}.csv",
+            std::process::id()
+        ));
+        let _ = std::fs::remove_file(&tmp);
+        let mut out = ShadowOutput::open(&tmp, false).expect("open csv");
+        let store = TradeStore::new_with_cap(60_000, usize::MAX).with_clock(clock.clone());
+        let health = HealthCounters::default();
+        let signal = |id: u64, market_id: &str| {
+            let leg = |leg_index: usize, token_id: &str| Leg {
+                leg_index,
+                token_id: token_id.into(),
+                side: Side::Buy,
+                limit_price: Price::from_f64(0.48).unwrap(),
+                qty: Qty::from_f64(10.0).unwrap(),
+                best_bid_at_signal: Price::from_f64(0.47).unwrap(),
+                best_ask_at_signal: Price::from_f64(0.48).unwrap(),
+            };
+            Signal {
+                run_id: "run_test".to_string(),
+                signal_id: SignalId(id),
+                signal_ts_ms,
+                market_id: market_id.into(),
+                strategy: Strategy::Binary,
+                bucket: Bucket::Liquid,
+                reasons: Vec::new(),
+                q_req: Qty::from_f64(10.0).unwrap(),
+                raw_cost_bps: Bps::from_price_cost(0.96),
+                raw_edge_bps: Bps::new(400),
+                hard_fees_bps: Bps::FEE_POLY + Bps::FEE_MERGE,
+                risk_premium_bps: Bps::new(80),
+                expected_net_bps: Bps::new(10),
+                bucket_metrics: BucketMetrics {
+                    worst_leg_index: 0,
+                    worst_spread_bps: 0,
+                    worst_depth3_usdc: 1000.0,
+                    is_depth3_degraded: false,
+                    depth_levels: 3,
+                    worst_depth_usdc: 1000.0,
+                    classifier: BucketClassifierVersion::V1,
+                    leg_buckets: Vec::new(),
+                },
+                legs: vec![leg(0, "A"), leg(1, "B")],
+            }
+        };
+        // The lower id sits in the long window, so the higher id settles first.
+        let mut pending = vec![signal(1, "slow"), signal(2, "fast")];
+        let mut written = HashSet::new();
+
+        for at in [signal_ts_ms + 2_000, signal_ts_ms + 4_000] {
+            clock.set_ms(at);
+            let fees = FeeRegistry::from_config(&cfg.fees);
+            settle_ready(
+                &cfg,
+                &fees,
+                &mut out,
+                &store,
+                &mut pending,
+                &mut written,
+                clock.now_ms(),
+                &health,
+                None,
+                None,
+            )
+            .expect("settle");
+        }
+        assert!(pending.is_empty());
+        out.flush_and_sync().unwrap();
+
+        let raw = std::fs::read_to_string(&tmp).unwrap();
+        let mut lines = raw.lines();
+        let names: Vec<&str> = lines.next().unwrap().split(',').collect();
+        let col = |name: &str| names.iter().position(|n| *n == name).unwrap();
+        let rows: Vec<Vec<&str>> = lines.map(|l| l.split(',').collect()).collect();
+        assert_eq!(rows.len(), 2);
+        assert_eq!(rows[0][col("signal_id")], "2");
+        assert_eq!(rows[1][col("signal_id")], "1");
+        for row in &rows {
+            assert!(!row[col("notes")].contains("DEDUP_HIT"), "{row:?}");
+        }
+        let _ = std::fs::remove_file(&tmp);
+    }
+
+    #[test]
+    fn jsonl_log_mirrors_each_csv_row() {
+        let dir = std::env::temp_dir().join(format!(
+            "razor_shadow_jsonl_{}_{}",
+            std::process::id(),
+            crate::types::now_ms()
+        ));
+        std::fs::create_dir_all(&dir).unwrap();
+        let mut out = ShadowOutput::open(&dir.join(FILE_SHADOW_LOG), true).expect("open");
+        let mut row = ShadowRow {
+            signal_id: 3,
+            legs_n: 2,
+            notes: format_notes(&[ShadowNoteReason::NoTrades, ShadowNoteReason::WindowEmpty]),
+            ..Default::default()
+        };
+        row.set_leg(
+            1,
+            ShadowLegCols {
+                token_id: "B".to_string(),
+                ..Default::default()
+            },
+        );
+        out.write_row(&row).expect("write");
+        out.flush_and_sync().expect("flush");
+
+        let csv = std::fs::read_to_string(dir.join(FILE_SHADOW_LOG)).unwrap();
+        assert_eq!(csv.lines().count(), 2);
+        let jsonl = std::fs::read_to_string(dir.join(FILE_SHADOW_LOG_JSONL)).unwrap();
+        let lines: Vec<&str> = jsonl.lines().collect();
+        assert_eq!(lines.len(), 1);
+        let v: serde_json::Value = serde_json::from_str(lines[0]).unwrap();
+        assert_eq!(v["signal_id"], 3);
+        assert_eq!(v["legs"][1]["token_id"], "B");
+        assert_eq!(
+            v["reasons"],
+            serde_json::json!([
+                ShadowNoteReason::NoTrades.as_str(),
+                ShadowNoteReason::WindowEmpty.as_str()
+            ])
+        );
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn recovers_unsettled_signals_whose_window_is_still_open() {
+        let cfg: Config = toml::from_str("[run]\nmarket_ids = [\"mkt\"]\n").unwrap();
+        let window_end_ms = cfg.market("mkt").window_end_ms;
+        let run = std::env::temp_dir().join(format!(
+            "razor_shadow_recover_{}_{}",
+            std::process::id(),
+            crate::types::now_ms()
+        ));
+        std::fs::create_dir_all(&run).unwrap();
+        let signal = |id: u64, signal_ts_ms: u64| Signal {
+            run_id: "run_test".to_string(),
+            signal_id: SignalId(id),
+            signal_ts_ms,
+            market_id: "mkt".into(),
+            strategy: Strategy::Binary,
+            bucket: Bucket::Liquid,
+            reasons: Vec::new(),
+            q_req: Qty::from_f64(10.0).unwrap(),
+            raw_cost_bps: Bps::from_price_cost(0.96),
+            raw_edge_bps: Bps::new(400),
+            hard_fees_bps: Bps::FEE_POLY + Bps::FEE_MERGE,
+            risk_premium_bps: Bps::new(80),
+            expected_net_bps: Bps::new(10),
+            bucket_metrics: BucketMetrics {
+                worst_leg_index: 0,
+                worst_spread_bps: 0,
+                worst_depth3_usdc: 1000.0,
+                is_depth3_degraded: false,
+                depth_levels: 3,
+                worst_depth_usdc: 1000.0,
+                classifier: BucketClassifierVersion::V1,
+                leg_buckets: Vec::new(),
+            },
+            legs: vec![Leg {
+                leg_index: 0,
+                token_id: "A".into(),
+                side: Side::Buy,
+                limit_price: Price::from_f64(0.48).unwrap(),
+                qty: Qty::from_f64(10.0).unwrap(),
+                best_bid_at_signal: Price::from_f64(0.47).unwrap(),
+                best_ask_at_signal: Price::from_f64(0.48).unwrap(),
+            }],
+        };
+
+        let now_ms = 1_700_000_000_000;
+        let mut ledger = CsvAppender::open(run.join(FILE_SIGNALS), &SIGNALS_HEADER).unwrap();
+        // 1: settled before the crash; 2: window closed while down; 3: still open.
+        for s in [
+            signal(1, now_ms - 1),
+            signal(2, now_ms - window_end_ms),
+            signal(3, now_ms - 1),
+        ] {
+            ledger.write_row(&SignalRow::from(&s)).unwrap();
+        }
+        ledger.flush_and_sync().unwrap();
+        let mut shadow = CsvAppender::open(run.join(FILE_SHADOW_LOG), &SHADOW_HEADER).unwrap();
+        shadow
+            .write_row(&ShadowRow {
+                signal_id: 1,
+                ..Default::default()
+            })
+            .unwrap();
+        shadow.flush_and_sync().unwrap();
+
+        let pending = recover_pending(&cfg, &run, now_ms);
+        assert_eq!(pending.len(), 1);
+        assert_eq!(pending[0].signal_id, SignalId(3));
+        assert_eq!(pending[0].legs[0].token_id.as_str(), "A");
+        let _ = std::fs::remove_dir_all(&run);
+    }
+
+    #[test]
+    fn recovered_signals_settle_against_trades_reloaded_from_disk() {
+        let cfg: Config = toml::from_str("[run]\nmarket_ids = [\"mkt\"]\n").unwrap();
+        let window_end_ms = cfg.market("mkt").window_end_ms;
+        let run = std::env::temp_dir().join(format!(
+            "razor_shadow_restore_{}_{}",
+            std::process::id(),
+            crate::types::now_ms()
+        ));
+        std::fs::create_dir_all(&run).unwrap();
+        let signal_ts_ms = 1_700_000_000_000;
+        let leg = |leg_index: usize, token_id: &str| Leg {
+            leg_index,
+            token_id: token_id.into(),
+            side: Side::Buy,
+            limit_price: Price::from_f64(0.48).unwrap(),
+            qty: Qty::from_f64(10.0).unwrap(),
+            best_bid_at_signal: Price::from_f64(0.47).unwrap(),
+            best_ask_at_signal: Price::from_f64(0.48).unwrap(),
+        };
+        let signal = Signal {
+            run_id: "run_test".to_string(),
+            signal_id: SignalId(7),
+            signal_ts_ms,
+            market_id: "mkt".into(),
+            strategy: Strategy::Binary,
+            bucket: Bucket::Liquid,
+            reasons: Vec::new(),
+            q_req: Qty::from_f64(10.0).unwrap(),
+            raw_cost_bps: Bps::from_price_cost(0.96),
+            raw_edge_bps: Bps::new(400),
+            hard_fees_bps: Bps::FEE_POLY + Bps::FEE_MERGE,
+            risk_premium_bps: Bps::new(80),
+            expected_net_bps: Bps::new(10),
+            bucket_metrics: BucketMetrics {
+                worst_leg_index: 0,
+                worst_spread_bps: 0,
+                worst_depth3_usdc: 1000.0,
+                is_depth3_degraded: false,
+                depth_levels: 3,
+                worst_depth_usdc: 1000.0,
+                classifier: BucketClassifierVersion::V1,
+                leg_buckets: Vec::new(),
+            },
+            legs: vec![leg(0, "A"), leg(1, "B")],
+        };
+        let mut ledger = CsvAppender::open(run.join(FILE_SIGNALS), &SIGNALS_HEADER).unwrap();
+        ledger.write_row(&SignalRow::from(&signal)).unwrap();
+        ledger.flush_and_sync().unwrap();
+        // Both legs traded inside the window before the restart.
+        let ts = signal_ts_ms + 100;
+        std::fs::write(
+            run.join(FILE_TRADES),
+            format!(
+                "{}\n{ts},mkt,A,0.47,100,ta,{ts},{ts},\n{ts},mkt,B,0.47,100,tb,{ts},{ts},\n",
+                TRADES_HEADER.join(",")
+            ),
+        )
+        .unwrap();
+
+        // The process comes back while the window is still open.
+        let clock = SimClock::starting_at_ms(signal_ts_ms + 1_000);
+        let mut pending = recover_pending(&cfg, &run, clock.now_ms());
+        assert_eq!(pending.len(), 1);
+        let mut store = TradeStore::new_with_cap(cfg.shadow.trade_retention_ms, usize::MAX)
+            .with_clock(clock.clone());
+        let restored = restore_trades(
+            &mut store,
+            &run,
+            clock.now_ms(),
+            cfg.shadow.trade_retention_ms,
+        );
+        assert_eq!(restored, 2);
+
+        clock.set_ms(signal_ts_ms + window_end_ms);
+        let mut out = ShadowOutput::open(&run.join(FILE_SHADOW_LOG), false).expect("open");
+        let fees = FeeRegistry::from_config(&cfg.fees);
+        settle_ready(
+            &cfg,
+            &fees,
+            &mut out,
+            &store,
+            &mut pending,
+            &mut HashSet::new(),
+            clock.now_ms(),
+            &HealthCounters::default(),
+            None,
+            None,
+        )
+        .expect("settle");
+        assert!(pending.is_empty());
+        out.flush_and_sync().unwrap();
+
+        let raw = std::fs::read_to_string(run.join(FILE_SHADOW_LOG)).unwrap();
+        let mut lines = raw.lines();
+        let names: Vec<&str> = lines.next().unwrap().split(',').collect();
+        let col = |name: &str| names.iter().position(|n| *n == name).unwrap();
+        let row: Vec<&str> = lines.next().unwrap().split(',').collect();
+        assert_eq!(row[col("signal_id")], "7");
+        assert!(row[col("q_set")].parse::<f64>().unwrap() > 0.0, "{row:?}");
+        assert!(!row[col("notes")].contains("NO_TRADES"), "{row:?}");
+        let _ = std::fs::remove_dir_all(&run);
+    }
 }
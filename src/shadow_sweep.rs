@@ -3,8 +3,9 @@ use std::path::{Path, PathBuf};
 use anyhow::Context as _;
 use serde::Serialize;
 
+use crate::accounting::{settle_set_per_leg, SetLeg};
+use crate::fees::{FeeRates, FeeRegistry};
 use crate::schema::SCHEMA_VERSION;
-use crate::types::Bps;
 
 pub const FILE_SWEEP_SCORES: &str = "sweep_scores.csv";
 pub const FILE_BEST_PATCH: &str = "best_patch.toml";
@@ -79,47 +80,51 @@ pub struct RecomputeLeg {
 /// Recompute a single shadow ledger entry under a hypothetical `(fill_share_used, dump_slippage_assumed)`.
 ///
 /// This is intentionally independent of bucket logic so that other tools (day14_report stress)
-/// can reuse it while keeping the Frozen Spec accounting formula identical.
+/// can reuse it while keeping the Frozen Spec accounting formula identical. `fees` are the rates
+/// of the row's `fee_model`.
 pub fn recompute_ledger_row(
     q_req: f64,
     legs: &[RecomputeLeg],
     fill_share_used: f64,
     dump_slippage_assumed: f64,
+    fees: FeeRates,
 ) -> (f64, f64) {
-    if !q_req.is_finite() || q_req <= 0.0 || !fill_share_used.is_finite() || legs.is_empty() {
-        return (0.0, 0.0);
-    }
+    recompute_ledger_row_per_leg(
+        q_req,
+        legs,
+        &vec![fill_share_used; legs.len()],
+        dump_slippage_assumed,
+        fees,
+    )
+}
 
-    let mut q_fills: Vec<f64> = Vec::with_capacity(legs.len());
-    for leg in legs {
-        let v_mkt = if leg.v_mkt.is_finite() && leg.v_mkt > 0.0 {
-            leg.v_mkt
-        } else {
-            0.0
-        };
-        let q_fill = (v_mkt * fill_share_used).min(q_req);
-        q_fills.push(q_fill);
+/// [`recompute_ledger_row`] with one fill share per leg (`fill_shares[i]` for `legs[i]`), for
+/// rows written with `bucket_classifier.per_leg`.
+pub fn recompute_ledger_row_per_leg(
+    q_req: f64,
+    legs: &[RecomputeLeg],
+    fill_shares: &[f64],
+    dump_slippage_assumed: f64,
+    fees: FeeRates,
+) -> (f64, f64) {
+    if !q_req.is_finite()
+        || q_req <= 0.0
+        || fill_shares.len() != legs.len()
+        || fill_shares.iter().any(|f| !f.is_finite())
+        || legs.is_empty()
+    {
+        return (0.0, 0.0);
     }
 
-    let q_set = q_fills
+    let finite_pos = |v: f64| if v.is_finite() && v > 0.0 { v } else { 0.0 };
+    let set_legs: Vec<SetLeg> = legs
         .iter()
-        .copied()
-        .fold(f64::INFINITY, f64::min)
-        .min(q_req);
-    let q_set = if q_set.is_finite() { q_set } else { 0.0 };
-
-    let mut cost_set_per_unit: f64 = 0.0;
-    for leg in legs {
-        let p = if leg.p_limit.is_finite() && leg.p_limit > 0.0 {
-            leg.p_limit
-        } else {
-            0.0
-        };
-        cost_set_per_unit += Bps::FEE_POLY.apply_cost(p);
-    }
-    let cost_set = q_set * cost_set_per_unit;
-    let proceeds_set = q_set * Bps::FEE_MERGE.apply_proceeds(1.0);
-    let pnl_set = proceeds_set - cost_set;
+        .map(|l| SetLeg {
+            p_limit: finite_pos(l.p_limit),
+            best_bid: finite_pos(l.best_bid),
+            v_mkt: finite_pos(l.v_mkt),
+        })
+        .collect();
 
     let dump_slippage_assumed = if dump_slippage_assumed.is_finite() {
         dump_slippage_assumed.clamp(0.0, 0.99)
@@ -127,38 +132,8 @@ pub fn recompute_ledger_row(
         0.0
     };
 
-    let mut pnl_left_total: f64 = 0.0;
-    for (idx, leg) in legs.iter().enumerate() {
-        let q_left = q_fills.get(idx).copied().unwrap_or(0.0) - q_set;
-        if q_left <= 0.0 {
-            continue;
-        }
-        let p_limit = if leg.p_limit.is_finite() && leg.p_limit > 0.0 {
-            leg.p_limit
-        } else {
-            0.0
-        };
-        let best_bid = if leg.best_bid.is_finite() && leg.best_bid > 0.0 {
-            leg.best_bid
-        } else {
-            0.0
-        };
-        let exit = best_bid * (1.0 - dump_slippage_assumed);
-        let proceeds_left_per_unit = Bps::FEE_POLY.apply_proceeds(exit);
-        let cost_left_per_unit = Bps::FEE_POLY.apply_cost(p_limit);
-        pnl_left_total += q_left * (proceeds_left_per_unit - cost_left_per_unit);
-    }
-
-    let total_pnl = pnl_set + pnl_left_total;
-
-    let q_fill_avg = q_fills.iter().sum::<f64>() / (legs.len() as f64);
-    let set_ratio = if q_fill_avg > 0.0 {
-        q_set / q_fill_avg
-    } else {
-        0.0
-    };
-
-    (total_pnl, set_ratio)
+    let settled = settle_set_per_leg(q_req, &set_legs, fill_shares, dump_slippage_assumed, fees);
+    (settled.total_pnl, settled.set_ratio)
 }
 
 impl SweepScoreRow {
@@ -183,13 +158,16 @@ impl SweepScoreRow {
 
 #[derive(Debug, Clone)]
 struct LedgerRow {
-    bucket: BucketKey,
     q_req: f64,
     legs: Vec<LedgerLeg>,
+    /// Rates of the row's `fee_model`.
+    fees: FeeRates,
 }
 
 #[derive(Debug, Clone)]
 struct LedgerLeg {
+    /// `legN_bucket` when the row has it (shadow_log v7, `per_leg` on), else the row's bucket.
+    bucket: BucketKey,
     p_limit: f64,
     best_bid: f64,
     v_mkt: f64,
@@ -238,6 +216,10 @@ pub struct StressSummary {
     pub dump_0_10: StressMetrics,
     pub fill_share_x0_70: StressMetrics,
     pub dump_0_10_fill_share_x0_70: StressMetrics,
+    /// Every leg settled on `legN_v_mkt_all` (all trades at or better than the limit).
+    pub volume_all: StressMetrics,
+    /// Every leg settled on `legN_v_mkt_sided` (only trades whose aggressor hit our side).
+    pub volume_sided: StressMetrics,
 }
 
 /// Compute stress variants using only fields already present in `shadow_log.csv`.
@@ -269,6 +251,9 @@ pub fn compute_stress_summary(
         find_col(&header, "fill_share_p25_used").context("missing column: fill_share_p25_used")?;
     let idx_dump = find_col(&header, "dump_slippage_assumed")
         .context("missing column: dump_slippage_assumed")?;
+    // Before shadow_log v9 every row settled at the built-in rates.
+    let idx_fee_model = find_col(&header, "fee_model");
+    let fee_registry = FeeRegistry::for_run_dir(shadow_log_path.parent().unwrap_or(Path::new(".")));
 
     let leg0 = StressLegIdxs::new(&header, 0)?;
     let leg1 = StressLegIdxs::new(&header, 1)?;
@@ -278,6 +263,8 @@ pub fn compute_stress_summary(
     let mut dump10 = StressAgg::new(set_ratio_threshold);
     let mut fill70 = StressAgg::new(set_ratio_threshold);
     let mut dump10_fill70 = StressAgg::new(set_ratio_threshold);
+    let mut vol_all = StressAgg::new(set_ratio_threshold);
+    let mut vol_sided = StressAgg::new(set_ratio_threshold);
 
     for record in rdr.records() {
         let record = match record {
@@ -301,6 +288,8 @@ pub fn compute_stress_summary(
                 dump10.bad();
                 fill70.bad();
                 dump10_fill70.bad();
+                vol_all.bad();
+                vol_sided.bad();
                 continue;
             }
         };
@@ -309,6 +298,8 @@ pub fn compute_stress_summary(
             dump10.bad();
             fill70.bad();
             dump10_fill70.bad();
+            vol_all.bad();
+            vol_sided.bad();
             continue;
         }
 
@@ -319,6 +310,8 @@ pub fn compute_stress_summary(
                 dump10.bad();
                 fill70.bad();
                 dump10_fill70.bad();
+                vol_all.bad();
+                vol_sided.bad();
                 continue;
             }
         };
@@ -330,12 +323,27 @@ pub fn compute_stress_summary(
                 dump10.bad();
                 fill70.bad();
                 dump10_fill70.bad();
+                vol_all.bad();
+                vol_sided.bad();
                 continue;
             }
         };
         let dump_base = record.get(idx_dump).and_then(parse_f64).unwrap_or(0.05);
+        let fee_model = idx_fee_model.and_then(|i| record.get(i)).unwrap_or("");
+        let Some(fees) = fee_registry.rates_for_row(fee_model) else {
+            base.bad();
+            dump10.bad();
+            fill70.bad();
+            dump10_fill70.bad();
+            vol_all.bad();
+            vol_sided.bad();
+            continue;
+        };
 
         let mut legs: Vec<RecomputeLeg> = Vec::with_capacity(legs_n);
+        let mut legs_all: Vec<RecomputeLeg> = Vec::with_capacity(legs_n);
+        let mut legs_sided: Vec<RecomputeLeg> = Vec::with_capacity(legs_n);
+        let mut fill_shares: Vec<f64> = Vec::with_capacity(legs_n);
         for (i, idxs) in [leg0, leg1, leg2].into_iter().enumerate() {
             if i >= legs_n {
                 break;
@@ -355,11 +363,33 @@ pub fn compute_stress_summary(
                 }
             };
             let best_bid = record.get(idxs.best_bid).and_then(parse_f64).unwrap_or(0.0);
+            let fill_share = idxs
+                .fill_share
+                .and_then(|idx| record.get(idx))
+                .and_then(parse_f64)
+                .filter(|v| *v > 0.0)
+                .unwrap_or(fill_share_base);
+            let volume = |idx: Option<usize>| {
+                idx.and_then(|idx| record.get(idx))
+                    .and_then(parse_f64)
+                    .unwrap_or(v_mkt)
+            };
             legs.push(RecomputeLeg {
                 p_limit,
                 best_bid,
                 v_mkt,
             });
+            legs_all.push(RecomputeLeg {
+                p_limit,
+                best_bid,
+                v_mkt: volume(idxs.v_mkt_all),
+            });
+            legs_sided.push(RecomputeLeg {
+                p_limit,
+                best_bid,
+                v_mkt: volume(idxs.v_mkt_sided),
+            });
+            fill_shares.push(fill_share);
         }
 
         if legs.len() != legs_n {
@@ -367,22 +397,36 @@ pub fn compute_stress_summary(
             dump10.bad();
             fill70.bad();
             dump10_fill70.bad();
+            vol_all.bad();
+            vol_sided.bad();
             continue;
         }
 
-        let (pnl_base, sr_base) = recompute_ledger_row(q_req, &legs, fill_share_base, dump_base);
+        let fill_shares_70: Vec<f64> = fill_shares.iter().map(|f| f * 0.70).collect();
+
+        let (pnl_base, sr_base) =
+            recompute_ledger_row_per_leg(q_req, &legs, &fill_shares, dump_base, fees);
         base.ok(pnl_base, sr_base);
 
-        let (pnl_dump10, sr_dump10) = recompute_ledger_row(q_req, &legs, fill_share_base, 0.10);
+        let (pnl_dump10, sr_dump10) =
+            recompute_ledger_row_per_leg(q_req, &legs, &fill_shares, 0.10, fees);
         dump10.ok(pnl_dump10, sr_dump10);
 
         let (pnl_fill70, sr_fill70) =
-            recompute_ledger_row(q_req, &legs, fill_share_base * 0.70, dump_base);
+            recompute_ledger_row_per_leg(q_req, &legs, &fill_shares_70, dump_base, fees);
         fill70.ok(pnl_fill70, sr_fill70);
 
         let (pnl_dump10_fill70, sr_dump10_fill70) =
-            recompute_ledger_row(q_req, &legs, fill_share_base * 0.70, 0.10);
+            recompute_ledger_row_per_leg(q_req, &legs, &fill_shares_70, 0.10, fees);
         dump10_fill70.ok(pnl_dump10_fill70, sr_dump10_fill70);
+
+        let (pnl_all, sr_all) =
+            recompute_ledger_row_per_leg(q_req, &legs_all, &fill_shares, dump_base, fees);
+        vol_all.ok(pnl_all, sr_all);
+
+        let (pnl_sided, sr_sided) =
+            recompute_ledger_row_per_leg(q_req, &legs_sided, &fill_shares, dump_base, fees);
+        vol_sided.ok(pnl_sided, sr_sided);
     }
 
     Ok(StressSummary {
@@ -390,6 +434,8 @@ pub fn compute_stress_summary(
         dump_0_10: dump10.finish(),
         fill_share_x0_70: fill70.finish(),
         dump_0_10_fill_share_x0_70: dump10_fill70.finish(),
+        volume_all: vol_all.finish(),
+        volume_sided: vol_sided.finish(),
     })
 }
 
@@ -398,6 +444,11 @@ struct StressLegIdxs {
     p_limit: usize,
     best_bid: usize,
     v_mkt: usize,
+    /// Absent before shadow_log v7; the row's `fill_share_p25_used` applies then.
+    fill_share: Option<usize>,
+    /// Absent before shadow_log v8; `legN_v_mkt` applies then.
+    v_mkt_all: Option<usize>,
+    v_mkt_sided: Option<usize>,
 }
 
 impl StressLegIdxs {
@@ -408,10 +459,16 @@ impl StressLegIdxs {
             .with_context(|| format!("missing column: leg{i}_best_bid"))?;
         let v_mkt = find_col(header, &format!("leg{i}_v_mkt"))
             .with_context(|| format!("missing column: leg{i}_v_mkt"))?;
+        let fill_share = find_col(header, &format!("leg{i}_fill_share"));
+        let v_mkt_all = find_col(header, &format!("leg{i}_v_mkt_all"));
+        let v_mkt_sided = find_col(header, &format!("leg{i}_v_mkt_sided"));
         Ok(Self {
             p_limit,
             best_bid,
             v_mkt,
+            fill_share,
+            v_mkt_all,
+            v_mkt_sided,
         })
     }
 }
@@ -587,10 +644,14 @@ fn aggregate_combo(
     let mut legging_miss: u64 = 0;
 
     for row in rows {
-        let fill_share = match row.bucket {
-            BucketKey::Liquid => fill_share_liquid,
-            BucketKey::Thin => fill_share_thin,
-        };
+        let fill_shares: Vec<f64> = row
+            .legs
+            .iter()
+            .map(|l| match l.bucket {
+                BucketKey::Liquid => fill_share_liquid,
+                BucketKey::Thin => fill_share_thin,
+            })
+            .collect();
 
         let legs: Vec<RecomputeLeg> = row
             .legs
@@ -601,8 +662,13 @@ fn aggregate_combo(
                 v_mkt: l.v_mkt,
             })
             .collect();
-        let (total_pnl, set_ratio) =
-            recompute_ledger_row(row.q_req, &legs, fill_share, dump_slippage_assumed);
+        let (total_pnl, set_ratio) = recompute_ledger_row_per_leg(
+            row.q_req,
+            &legs,
+            &fill_shares,
+            dump_slippage_assumed,
+            row.fees,
+        );
         sum_total_pnl += total_pnl;
         total_pnls.push(total_pnl);
         set_ratio_sum += set_ratio;
@@ -783,6 +849,8 @@ fn parse_ledger_rows(input: &Path, run_id: &str) -> anyhow::Result<(Vec<LedgerRo
     let idx_bucket = find_col(&header, "bucket").context("missing column: bucket")?;
     let idx_legs_n = find_col(&header, "legs_n").context("missing column: legs_n")?;
     let idx_q_req = find_col(&header, "q_req").context("missing column: q_req")?;
+    let idx_fee_model = find_col(&header, "fee_model");
+    let fee_registry = FeeRegistry::for_run_dir(input.parent().unwrap_or(Path::new(".")));
 
     let leg0 = LegIdxs::new(&header, 0)?;
     let leg1 = LegIdxs::new(&header, 1)?;
@@ -838,6 +906,12 @@ fn parse_ledger_rows(input: &Path, run_id: &str) -> anyhow::Result<(Vec<LedgerRo
             }
         };
 
+        let fee_model = idx_fee_model.and_then(|i| record.get(i)).unwrap_or("");
+        let Some(fees) = fee_registry.rates_for_row(fee_model) else {
+            rows_bad += 1;
+            continue;
+        };
+
         let mut legs: Vec<LedgerLeg> = Vec::with_capacity(legs_n);
         for (i, idxs) in [leg0, leg1, leg2].into_iter().enumerate() {
             if i >= legs_n {
@@ -858,7 +932,13 @@ fn parse_ledger_rows(input: &Path, run_id: &str) -> anyhow::Result<(Vec<LedgerRo
                 }
             };
             let best_bid = record.get(idxs.best_bid).and_then(parse_f64).unwrap_or(0.0);
+            let leg_bucket = idxs
+                .bucket
+                .and_then(|idx| record.get(idx))
+                .and_then(BucketKey::parse)
+                .unwrap_or(bucket);
             legs.push(LedgerLeg {
+                bucket: leg_bucket,
                 p_limit,
                 best_bid,
                 v_mkt,
@@ -870,11 +950,7 @@ fn parse_ledger_rows(input: &Path, run_id: &str) -> anyhow::Result<(Vec<LedgerRo
             continue;
         }
 
-        out.push(LedgerRow {
-            bucket,
-            q_req,
-            legs,
-        });
+        out.push(LedgerRow { q_req, legs, fees });
     }
 
     Ok((out, rows_total, rows_bad))
@@ -885,6 +961,7 @@ struct LegIdxs {
     p_limit: usize,
     best_bid: usize,
     v_mkt: usize,
+    bucket: Option<usize>,
 }
 
 impl LegIdxs {
@@ -895,10 +972,12 @@ impl LegIdxs {
             .with_context(|| format!("missing column: leg{i}_best_bid"))?;
         let v_mkt = find_col(header, &format!("leg{i}_v_mkt"))
             .with_context(|| format!("missing column: leg{i}_v_mkt"))?;
+        let bucket = find_col(header, &format!("leg{i}_bucket"));
         Ok(Self {
             p_limit,
             best_bid,
             v_mkt,
+            bucket,
         })
     }
 }
@@ -978,20 +1057,22 @@ mod tests {
     #[test]
     fn recompute_matches_spec_for_simple_binary() {
         let row = LedgerRow {
-            bucket: BucketKey::Liquid,
             q_req: 10.0,
             legs: vec![
                 LedgerLeg {
+                    bucket: BucketKey::Liquid,
                     p_limit: 0.49,
                     best_bid: 0.48,
                     v_mkt: 100.0,
                 },
                 LedgerLeg {
+                    bucket: BucketKey::Liquid,
                     p_limit: 0.48,
                     best_bid: 0.47,
                     v_mkt: 60.0,
                 },
             ],
+            fees: FeeRates::FROZEN,
         };
 
         let (sum_pnl, set_ratio_avg, legging_rate, worst_20) =
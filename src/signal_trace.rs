@@ -0,0 +1,65 @@
+use std::str::FromStr;
+use std::sync::OnceLock;
+
+/// Which signals `--trace-signal` follows through the pipeline.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TraceSignal {
+    All,
+    Id(u64),
+}
+
+impl FromStr for TraceSignal {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim();
+        if s.eq_ignore_ascii_case("all") {
+            return Ok(TraceSignal::All);
+        }
+        s.parse::<u64>()
+            .map(TraceSignal::Id)
+            .map_err(|_| format!("expected a signal_id or `all`, got {s:?}"))
+    }
+}
+
+static FILTER: OnceLock<TraceSignal> = OnceLock::new();
+
+/// Sets the process-wide filter once at startup; without it no signal is traced.
+pub fn init(filter: TraceSignal) {
+    let _ = FILTER.set(filter);
+}
+
+pub fn is_traced(signal_id: u64) -> bool {
+    match FILTER.get() {
+        None => false,
+        Some(TraceSignal::All) => true,
+        Some(TraceSignal::Id(id)) => *id == signal_id,
+    }
+}
+
+/// Logs one lifecycle stage of a traced signal at INFO with the `signal_corr` correlation field,
+/// so `grep signal_corr=<id>` (or the JSON field) shows the signal end to end:
+///
+/// `trace_signal!(s.signal_id, "shadow_settle", q_set, total_pnl)`
+macro_rules! trace_signal {
+    ($signal_id:expr, $stage:expr $(, $($field:tt)+)?) => {{
+        let signal_corr: u64 = $signal_id.into();
+        if $crate::signal_trace::is_traced(signal_corr) {
+            tracing::info!(signal_corr, stage = $stage, $($($field)+ ,)? "signal trace");
+        }
+    }};
+}
+
+pub(crate) use trace_signal;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_id_and_all() {
+        assert_eq!("all".parse::<TraceSignal>(), Ok(TraceSignal::All));
+        assert_eq!(" 42 ".parse::<TraceSignal>(), Ok(TraceSignal::Id(42)));
+        assert!("x".parse::<TraceSignal>().is_err());
+    }
+}
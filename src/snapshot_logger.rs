@@ -58,14 +58,14 @@ pub async fn run_snapshot_logger(
 
         let mut cols: [String; 15] = Default::default();
         cols[0] = ts_ms.to_string();
-        cols[1] = snap.market_id.clone();
+        cols[1] = snap.market_id.to_string();
         cols[2] = legs_n.to_string();
 
         for (i, leg) in snap.legs.iter().take(3).enumerate() {
             let base = 3 + i * 4;
-            cols[base] = leg.token_id.clone();
-            cols[base + 1] = fmt_f64(leg.best_bid);
-            cols[base + 2] = fmt_f64(leg.best_ask);
+            cols[base] = leg.token_id.to_string();
+            cols[base + 1] = fmt_f64(leg.best_bid.to_f64());
+            cols[base + 2] = fmt_f64(leg.best_ask.to_f64());
             cols[base + 3] = fmt_f64(leg.ask_depth3_usdc);
         }
 
@@ -87,7 +87,7 @@ fn fmt_f64(v: f64) -> String {
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::types::{LegSnapshot, MarketSnapshot};
+    use crate::types::{LegSnapshot, MarketSnapshot, Price, Qty};
 
     #[test]
     fn snapshots_header_is_frozen() {
@@ -97,24 +97,26 @@ mod tests {
     #[test]
     fn snapshot_row_has_fixed_columns() {
         let snap = MarketSnapshot {
-            market_id: "m1".to_string(),
+            market_id: "m1".into(),
             legs: vec![
                 LegSnapshot {
-                    token_id: "t0".to_string(),
-                    best_ask: 0.49,
-                    best_bid: 0.48,
-                    best_ask_size_best: 1.0,
-                    best_bid_size_best: 1.0,
+                    token_id: "t0".into(),
+                    best_ask: Price::from_f64(0.49).unwrap(),
+                    best_bid: Price::from_f64(0.48).unwrap(),
+                    best_ask_size_best: Qty::from_f64(1.0).unwrap(),
+                    best_bid_size_best: Qty::from_f64(1.0).unwrap(),
                     ask_depth3_usdc: 100.0,
+                    asks: Default::default(),
                     ts_recv_us: 1_700_000_000_000_000,
                 },
                 LegSnapshot {
-                    token_id: "t1".to_string(),
-                    best_ask: 0.51,
-                    best_bid: 0.50,
-                    best_ask_size_best: 1.0,
-                    best_bid_size_best: 1.0,
+                    token_id: "t1".into(),
+                    best_ask: Price::from_f64(0.51).unwrap(),
+                    best_bid: Price::from_f64(0.50).unwrap(),
+                    best_ask_size_best: Qty::from_f64(1.0).unwrap(),
+                    best_bid_size_best: Qty::from_f64(1.0).unwrap(),
                     ask_depth3_usdc: 200.0,
+                    asks: Default::default(),
                     ts_recv_us: 1_700_000_000_000_100,
                 },
             ],
@@ -123,13 +125,13 @@ mod tests {
         let ts_ms = snap.legs.iter().map(|l| l.ts_recv_us / 1000).max().unwrap();
         let mut cols: [String; 15] = Default::default();
         cols[0] = ts_ms.to_string();
-        cols[1] = snap.market_id.clone();
+        cols[1] = snap.market_id.to_string();
         cols[2] = snap.legs.len().to_string();
         for (i, leg) in snap.legs.iter().take(3).enumerate() {
             let base = 3 + i * 4;
-            cols[base] = leg.token_id.clone();
-            cols[base + 1] = fmt_f64(leg.best_bid);
-            cols[base + 2] = fmt_f64(leg.best_ask);
+            cols[base] = leg.token_id.to_string();
+            cols[base + 1] = fmt_f64(leg.best_bid.to_f64());
+            cols[base + 2] = fmt_f64(leg.best_ask.to_f64());
             cols[base + 3] = fmt_f64(leg.ask_depth3_usdc);
         }
         assert_eq!(cols.len(), 15);
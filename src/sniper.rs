@@ -6,12 +6,28 @@ use std::time::Duration;
 use tokio::sync::{mpsc, watch, Mutex};
 use tracing::{debug, error, info, warn};
 
+use crate::backpressure;
 use crate::calibration::CalibrationEvent;
+use crate::clock::SharedClock;
 use crate::config::Config;
+use crate::errors::{code_of, into_razor, ErrorCode, ErrorContext as _, RazorError};
+use crate::escalation::{EscalationEvent, Escalator};
 use crate::execution::{top_of_book, ExecKind, ExecutionGateway, PlaceIocRequest, TopOfBook};
+use crate::gas::{self, GasPriceFeed};
+use crate::log_throttle::warn_throttled;
+use crate::positions::PositionBook;
 use crate::recorder::CsvAppender;
+use crate::rows::TradeLogRow;
 use crate::schema::TRADE_LOG_HEADER;
-use crate::types::{now_ms, Bps, FillReport, FillStatus, MarketSnapshot, Side, Signal};
+use crate::signal_trace::trace_signal;
+use crate::types::{
+    Bps, FillReport, FillStatus, MarketId, MarketSnapshot, Price, Qty, Side, Signal, SignalId,
+    TokenId,
+};
+use crate::user_feed::{FillLedger, UserFill};
+
+#[cfg(feature = "live")]
+const USER_FILL_CHANNEL_CAP: usize = 1_024;
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 enum OmsAction {
@@ -21,6 +37,8 @@ enum OmsAction {
     HardStop,
     Cooldown,
     DedupHit,
+    Merge,
+    ExposureCap,
 }
 
 impl OmsAction {
@@ -32,6 +50,8 @@ impl OmsAction {
             OmsAction::HardStop => "HARDSTOP",
             OmsAction::Cooldown => "COOLDOWN",
             OmsAction::DedupHit => "DEDUP_HIT",
+            OmsAction::Merge => "MERGE",
+            OmsAction::ExposureCap => "EXPOSURE_CAP",
         }
     }
 
@@ -40,7 +60,11 @@ impl OmsAction {
             OmsAction::FireLeg1 => Some(ExecKind::FireLeg1),
             OmsAction::Chase => Some(ExecKind::Chase),
             OmsAction::Flatten => Some(ExecKind::Flatten),
-            OmsAction::HardStop | OmsAction::Cooldown | OmsAction::DedupHit => None,
+            OmsAction::HardStop
+            | OmsAction::Cooldown
+            | OmsAction::DedupHit
+            | OmsAction::Merge
+            | OmsAction::ExposureCap => None,
         }
     }
 }
@@ -51,23 +75,68 @@ enum OmsState {
     HardStop { reason: String },
 }
 
+/// trade_log.csv plus the clock its `ts_ms` column is stamped from.
+struct TradeLog {
+    out: CsvAppender,
+    clock: SharedClock,
+    /// `LIVE` behind the live gateway, `SIM` otherwise (`phase`/`mode` columns).
+    phase: &'static str,
+}
+
+/// Per-market cooldown deadlines (unix ms) after a completed signal.
+#[derive(Debug, Default)]
+struct Cooldowns {
+    until_by_market: HashMap<MarketId, u64>,
+}
+
+impl Cooldowns {
+    /// The deadline if `market_id` is still cooling down at `now_ms`; expired entries are dropped.
+    fn active(&mut self, market_id: &MarketId, now_ms: u64) -> Option<u64> {
+        let until_ms = self.until_by_market.get(market_id).copied()?;
+        if now_ms < until_ms {
+            return Some(until_ms);
+        }
+        self.until_by_market.remove(market_id);
+        None
+    }
+
+    fn start(&mut self, market_id: &MarketId, now_ms: u64, cooldown_ms: u64) -> u64 {
+        let until_ms = now_ms.saturating_add(cooldown_ms);
+        self.until_by_market.insert(market_id.clone(), until_ms);
+        until_ms
+    }
+}
+
 #[derive(Debug, Clone)]
 struct PositionChunk {
-    token_id: String,
-    qty: f64,
+    token_id: TokenId,
+    qty: Qty,
 }
 
+#[allow(clippy::too_many_arguments)]
 pub async fn run(
     cfg: Config,
     mut snap_rx: watch::Receiver<Option<MarketSnapshot>>,
-    mut signal_rx: mpsc::Receiver<Signal>,
+    mut signal_rx: backpressure::Receiver<Signal>,
     trade_log_path: PathBuf,
     calibration_tx: mpsc::Sender<CalibrationEvent>,
+    escalator: Option<Escalator>,
+    clock: SharedClock,
+    live_gateway: bool,
+    gas_price: GasPriceFeed,
+    positions: PositionBook,
     mut shutdown: watch::Receiver<bool>,
 ) -> anyhow::Result<()> {
-    let mut trade_log = CsvAppender::open(trade_log_path, &TRADE_LOG_HEADER)?;
+    #[cfg(feature = "live")]
+    let user_events_path = trade_log_path.with_file_name(crate::schema::FILE_USER_EVENTS_JSONL);
+    let mut trade_log = TradeLog {
+        out: CsvAppender::open(trade_log_path, &TRADE_LOG_HEADER)
+            .io_context("open trade_log.csv")?,
+        clock: clock.clone(),
+        phase: if live_gateway { "LIVE" } else { "SIM" },
+    };
 
-    let snapshots: Arc<Mutex<HashMap<String, MarketSnapshot>>> =
+    let snapshots: Arc<Mutex<HashMap<MarketId, MarketSnapshot>>> =
         Arc::new(Mutex::new(HashMap::new()));
     spawn_snapshot_ingest(&mut snap_rx, Arc::clone(&snapshots));
 
@@ -75,27 +144,51 @@ pub async fn run(
     if force_chase_fail {
         warn!("RAZOR_SIM_FORCE_CHASE_FAIL=1 enabled: all CHASE orders will fill NONE");
     }
-    let exec = if cfg.live.enabled {
-        info!("LIVE mode enabled: deriving API creds (orders not implemented yet)");
+    // `live_gateway` is the live_gate decision; `live.enabled` alone never selects it.
+    let exec = if live_gateway {
+        info!("LIVE gateway: deriving API creds");
         ExecutionGateway::new_live(&cfg).await?
     } else {
         ExecutionGateway::new_sim(&cfg, force_chase_fail)
     };
 
+    // Real fills from the user channel; only the live gateway has the creds to subscribe.
+    #[allow(unused_mut)]
+    let mut user_fills: Option<mpsc::Receiver<UserFill>> = None;
+    #[cfg(feature = "live")]
+    if let Some(creds) = exec.live_creds() {
+        let (fill_tx, fill_rx) = mpsc::channel::<UserFill>(USER_FILL_CHANNEL_CAP);
+        let user_ws = crate::user_feed::run_user_ws(
+            cfg.clone(),
+            creds,
+            user_events_path,
+            fill_tx,
+            shutdown.clone(),
+        );
+        crate::crash::spawn_supervised("user_ws", async move {
+            if let Err(e) = user_ws.await {
+                error!(error = %format!("{e:#}"), "user ws task failed");
+            }
+        });
+        user_fills = Some(fill_rx);
+    }
+    let mut fill_ledger = FillLedger::default();
+
     info!(
         enabled = cfg.live.enabled,
         cooldown_ms = cfg.live.cooldown_ms,
         chase_cap_bps = cfg.live.chase_cap_bps,
         ladder_step1_bps = cfg.live.ladder_step1_bps,
-        "sniper start (SIM)"
+        phase = trade_log.phase,
+        "sniper start"
     );
 
     let mut state = OmsState::Idle;
     let mut hardstop_heartbeat = tokio::time::interval(Duration::from_secs(5));
     hardstop_heartbeat.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
 
-    let mut cooldown_by_market: HashMap<String, u64> = HashMap::new();
-    let mut seen_signal_ids: HashMap<u64, u64> = HashMap::new();
+    let mut cooldowns = Cooldowns::default();
+    let mut seen_signal_ids: HashMap<SignalId, u64> = HashMap::new();
     let mut last_prune_ms: u64 = 0;
     const PRUNE_EVERY_MS: u64 = 60_000;
     const TTL_MS: u64 = 60 * 60_000;
@@ -110,6 +203,25 @@ pub async fn run(
                     warn!(%reason, "sniper HARDSTOP (heartbeat)");
                 }
             }
+            maybe = next_user_fill(&mut user_fills) => {
+                let Some(fill) = maybe else {
+                    user_fills = None;
+                    continue;
+                };
+                if fill_ledger.apply(&fill) {
+                    info!(
+                        order_id = %fill.order_id,
+                        trade_id = %fill.trade_id,
+                        token_id = %fill.asset_id,
+                        status = %fill.status,
+                        maker = fill.maker,
+                        price = fill.price,
+                        size = fill.size,
+                        order_filled = fill_ledger.filled(&fill.order_id),
+                        "user channel fill"
+                    );
+                }
+            }
             maybe = signal_rx.recv() => {
                 let Some(signal) = maybe else { break; };
 
@@ -117,34 +229,31 @@ pub async fn run(
                     break;
                 }
 
-                let now = now_ms();
+                let now = clock.now_ms();
 
                 match &state {
                     OmsState::HardStop{ reason } => {
-                        warn!(signal_id = signal.signal_id, %reason, "hardstop; ignoring signal");
+                        warn!(signal_id = signal.signal_id.get(), %reason, "hardstop; ignoring signal");
                         continue;
                     }
                     OmsState::Idle => {}
                 }
 
-                if let Some(until_ms) = cooldown_by_market.get(&signal.market_id).copied() {
-                    if now < until_ms {
-                        write_trade_row(
-                            &mut trade_log,
-                            &signal,
-                            OmsAction::Cooldown,
-                            -1,
-                            "",
-                            Side::Buy,
-                            0.0,
-                            0.0,
-                            0.0,
-                            FillStatus::None,
-                            &format!("cooldown_until_ms={until_ms}"),
-                        )?;
-                        continue;
-                    }
-                    cooldown_by_market.remove(&signal.market_id);
+                if let Some(until_ms) = cooldowns.active(&signal.market_id, now) {
+                    write_trade_row(
+                        &mut trade_log,
+                        &signal,
+                        OmsAction::Cooldown,
+                        -1,
+                        "",
+                        Side::Buy,
+                        Price::ZERO,
+                        Qty::ZERO,
+                        Qty::ZERO,
+                        FillStatus::None,
+                        &format!("cooldown_until_ms={until_ms}"),
+                    )?;
+                    continue;
                 }
 
                 if let Some(prev_ts_ms) = seen_signal_ids.get(&signal.signal_id).copied() {
@@ -155,15 +264,37 @@ pub async fn run(
                         -1,
                         "",
                         Side::Buy,
-                        0.0,
-                        0.0,
-                        0.0,
+                        Price::ZERO,
+                        Qty::ZERO,
+                        Qty::ZERO,
                         FillStatus::None,
                         &format!("dedup_prev_ts_ms={prev_ts_ms}"),
                     )?;
                     continue;
                 }
 
+                let exposure_usdc = positions.total_exposure_usdc();
+                if cfg.live.max_position_usdc > 0.0 && exposure_usdc > cfg.live.max_position_usdc {
+                    write_trade_row(
+                        &mut trade_log,
+                        &signal,
+                        OmsAction::ExposureCap,
+                        -1,
+                        "",
+                        Side::Buy,
+                        Price::ZERO,
+                        Qty::ZERO,
+                        Qty::ZERO,
+                        FillStatus::None,
+                        &format!(
+                            "exposure_usdc={exposure_usdc:.6}|market_exposure_usdc={:.6}|max_position_usdc={}",
+                            positions.market_exposure_usdc(&signal.market_id),
+                            cfg.live.max_position_usdc
+                        ),
+                    )?;
+                    continue;
+                }
+
                 let outcome = process_signal_sim(
                     &cfg,
                     &signal,
@@ -171,6 +302,8 @@ pub async fn run(
                     &mut trade_log,
                     &calibration_tx,
                     &exec,
+                    &gas_price,
+                    &positions,
                 ).await;
 
                 seen_signal_ids.insert(signal.signal_id, now);
@@ -182,7 +315,8 @@ pub async fn run(
 
                 match outcome {
                     SignalOutcome::Completed => {
-                        let until_ms = now_ms().saturating_add(cfg.live.cooldown_ms);
+                        let until_ms =
+                            cooldowns.start(&signal.market_id, clock.now_ms(), cfg.live.cooldown_ms);
                         write_trade_row(
                             &mut trade_log,
                             &signal,
@@ -190,15 +324,15 @@ pub async fn run(
                             -1,
                             "",
                             Side::Buy,
-                            0.0,
-                            0.0,
-                            0.0,
+                            Price::ZERO,
+                            Qty::ZERO,
+                            Qty::ZERO,
                             FillStatus::None,
                             &format!("until_ms={until_ms}"),
                         )?;
-                        cooldown_by_market.insert(signal.market_id.clone(), until_ms);
                     }
-                    SignalOutcome::HardStop { reason } => {
+                    SignalOutcome::HardStop { code, reason } => {
+                        let reason = format!("code={code}|{reason}");
                         write_trade_row(
                             &mut trade_log,
                             &signal,
@@ -206,13 +340,20 @@ pub async fn run(
                             -1,
                             "",
                             Side::Sell,
-                            0.0,
-                            0.0,
-                            0.0,
+                            Price::ZERO,
+                            Qty::ZERO,
+                            Qty::ZERO,
                             FillStatus::None,
                             &reason,
                         )?;
-                        error!(signal_id = signal.signal_id, %reason, "sniper entered HARDSTOP");
+                        error!(signal_id = signal.signal_id.get(), %code, %reason, "sniper entered HARDSTOP");
+                        if let Some(esc) = &escalator {
+                            esc.escalate(EscalationEvent::hardstop(
+                                signal.signal_id,
+                                &signal.market_id,
+                                &reason,
+                            ));
+                        }
                         state = OmsState::HardStop{ reason };
                     }
                 }
@@ -220,25 +361,55 @@ pub async fn run(
         }
     }
 
-    trade_log.flush_and_sync()?;
+    if fill_ledger.orders() > 0 {
+        info!(
+            orders = fill_ledger.orders(),
+            "user channel fills reconciled"
+        );
+    }
+    trade_log
+        .out
+        .flush_and_sync()
+        .io_context("flush trade_log.csv")?;
     Ok(())
 }
 
+/// Next fill from the user channel; never resolves without one.
+async fn next_user_fill(rx: &mut Option<mpsc::Receiver<UserFill>>) -> Option<UserFill> {
+    match rx {
+        Some(rx) => rx.recv().await,
+        None => std::future::pending().await,
+    }
+}
+
 enum SignalOutcome {
     Completed,
-    HardStop { reason: String },
+    HardStop { code: ErrorCode, reason: String },
 }
 
+impl SignalOutcome {
+    fn failed(e: RazorError) -> Self {
+        let e = anyhow::Error::from(e);
+        SignalOutcome::HardStop {
+            code: code_of(&e),
+            reason: format!("{e:#}"),
+        }
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
 async fn process_signal_sim(
     cfg: &Config,
     signal: &Signal,
-    snapshots: &Arc<Mutex<HashMap<String, MarketSnapshot>>>,
-    trade_log: &mut CsvAppender,
+    snapshots: &Arc<Mutex<HashMap<MarketId, MarketSnapshot>>>,
+    trade_log: &mut TradeLog,
     calibration_tx: &mpsc::Sender<CalibrationEvent>,
     exec: &ExecutionGateway,
+    gas_price: &GasPriceFeed,
+    book: &PositionBook,
 ) -> SignalOutcome {
     info!(
-        signal_id = signal.signal_id,
+        signal_id = signal.signal_id.get(),
         market_id = %signal.market_id,
         strategy = %signal.strategy.as_str(),
         bucket = %signal.bucket.as_str(),
@@ -247,7 +418,7 @@ async fn process_signal_sim(
     );
 
     let Some(snap) = latest_market_snapshot(snapshots, &signal.market_id).await else {
-        warn!(signal_id = signal.signal_id, market_id = %signal.market_id, "no snapshot; skip");
+        warn_throttled!(signal_id = signal.signal_id.get(), market_id = %signal.market_id, "no snapshot; skip");
         let _ = write_trade_row(
             trade_log,
             signal,
@@ -255,9 +426,9 @@ async fn process_signal_sim(
             -1,
             "",
             Side::Buy,
-            0.0,
+            Price::ZERO,
             signal.q_req,
-            0.0,
+            Qty::ZERO,
             FillStatus::None,
             "no_snapshot",
         );
@@ -288,8 +459,8 @@ async fn process_signal_sim(
     };
 
     let Some(top1) = top_of_book(&snap, &signal.legs[leg1_idx].token_id) else {
-        warn!(
-            signal_id = signal.signal_id,
+        warn_throttled!(
+            signal_id = signal.signal_id.get(),
             "leg1 token missing in snapshot; skip"
         );
         let _ = write_trade_row(
@@ -299,9 +470,9 @@ async fn process_signal_sim(
             leg1_idx as i32,
             &signal.legs[leg1_idx].token_id,
             Side::Buy,
-            0.0,
+            Price::ZERO,
             signal.q_req,
-            0.0,
+            Qty::ZERO,
             FillStatus::None,
             "no_leg_snapshot",
         );
@@ -313,8 +484,8 @@ async fn process_signal_sim(
     let leg1_req = signal
         .legs
         .get(leg1_idx)
-        .and_then(|l| l.qty.is_finite().then_some(l.qty))
-        .filter(|q| *q > 0.0)
+        .map(|l| l.qty)
+        .filter(|q| q.is_positive())
         .unwrap_or(signal.q_req);
     let leg1_side = signal
         .legs
@@ -327,6 +498,7 @@ async fn process_signal_sim(
         trade_log,
         calibration_tx,
         exec,
+        book,
         OmsAction::FireLeg1,
         leg1_idx as i32,
         &signal.legs[leg1_idx].token_id,
@@ -339,20 +511,20 @@ async fn process_signal_sim(
     .await
     {
         Ok(r) => r,
-        Err(e) => return SignalOutcome::HardStop { reason: e },
+        Err(e) => return SignalOutcome::failed(e),
     };
 
-    if leg1_fill.status == FillStatus::None || leg1_fill.filled_qty <= 0.0 {
+    if leg1_fill.status == FillStatus::None || !leg1_fill.filled_qty.is_positive() {
         return SignalOutcome::Completed;
     }
 
     debug!(
-        signal_id = signal.signal_id,
+        signal_id = signal.signal_id.get(),
         leg_index = leg1_idx,
         token_id = %signal.legs[leg1_idx].token_id,
-        requested_qty = leg1_fill.requested_qty,
-        filled_qty = leg1_fill.filled_qty,
-        avg_price = leg1_fill.avg_price,
+        requested_qty = leg1_fill.requested_qty.to_f64(),
+        filled_qty = leg1_fill.filled_qty.to_f64(),
+        avg_price = leg1_fill.avg_price.to_f64(),
         "leg1 fill"
     );
 
@@ -371,6 +543,7 @@ async fn process_signal_sim(
             trade_log,
             calibration_tx,
             exec,
+            book,
             positions,
         )
         .await;
@@ -379,7 +552,7 @@ async fn process_signal_sim(
     for &idx in &leg_idxs[1..] {
         let token_id = &signal.legs[idx].token_id;
         let Some(top) = top_of_book(&snap, token_id) else {
-            warn!(signal_id = signal.signal_id, %token_id, "token missing in snapshot; flatten");
+            warn!(signal_id = signal.signal_id.get(), %token_id, "token missing in snapshot; flatten");
             return flatten_positions(
                 cfg,
                 signal,
@@ -387,21 +560,22 @@ async fn process_signal_sim(
                 trade_log,
                 calibration_tx,
                 exec,
+                book,
                 positions,
             )
             .await;
         };
 
         let step1_bps = Bps::new(cfg.live.ladder_step1_bps);
-        let p1 = top.best_ask * (1.0 + step1_bps.to_f64());
-        let p2 = top.best_ask * (1.0 + max_chase_bps.to_f64());
+        let p1 = top.best_ask.offset_bps(step1_bps);
+        let p2 = top.best_ask.offset_bps(max_chase_bps);
 
-        let mut filled = 0.0f64;
+        let mut filled = Qty::ZERO;
         for (attempt, px) in [(1, p1), (2, p2)] {
-            if filled + 1e-12 >= target_qty {
+            if filled >= target_qty {
                 break;
             }
-            let need = (target_qty - filled).max(0.0);
+            let need = target_qty - filled;
             let notes = if attempt == 1 {
                 format!("ladder_step1_bps={}", step1_bps.raw())
             } else {
@@ -415,6 +589,7 @@ async fn process_signal_sim(
                 trade_log,
                 calibration_tx,
                 exec,
+                book,
                 OmsAction::Chase,
                 idx as i32,
                 token_id,
@@ -427,22 +602,22 @@ async fn process_signal_sim(
             .await
             {
                 Ok(r) => r,
-                Err(e) => return SignalOutcome::HardStop { reason: e },
+                Err(e) => return SignalOutcome::failed(e),
             };
 
             filled += r.filled_qty;
         }
 
-        if filled + 1e-9 < target_qty {
+        if filled < target_qty {
             warn!(
-                signal_id = signal.signal_id,
+                signal_id = signal.signal_id.get(),
                 leg_index = idx,
                 %token_id,
-                filled,
-                target_qty,
+                %filled,
+                %target_qty,
                 "legging failed; flatten"
             );
-            if filled > 0.0 {
+            if filled.is_positive() {
                 positions.push(PositionChunk {
                     token_id: token_id.clone(),
                     qty: filled,
@@ -455,6 +630,7 @@ async fn process_signal_sim(
                 trade_log,
                 calibration_tx,
                 exec,
+                book,
                 positions,
             )
             .await;
@@ -466,19 +642,42 @@ async fn process_signal_sim(
         });
     }
 
+    // Every leg holds target_qty: the set is merged on-chain, which costs gas.
+    let quote = gas_price.quote(&cfg.gas);
+    let gas_usdc = gas::merge_cost_usdc(&cfg.gas, quote);
+    if let Err(e) = write_trade_row(
+        trade_log,
+        signal,
+        OmsAction::Merge,
+        -1,
+        "",
+        Side::Sell,
+        Price::ZERO,
+        target_qty,
+        Qty::ZERO,
+        FillStatus::None,
+        &gas::merge_notes(target_qty.to_f64(), gas_usdc, quote),
+    ) {
+        return SignalOutcome::failed(e);
+    }
+    let set_tokens: Vec<&TokenId> = signal.legs.iter().map(|l| &l.token_id).collect();
+    book.merge(&set_tokens, target_qty);
+
     SignalOutcome::Completed
 }
 
+#[allow(clippy::too_many_arguments)]
 async fn flatten_positions(
     cfg: &Config,
     signal: &Signal,
-    snapshots: &Arc<Mutex<HashMap<String, MarketSnapshot>>>,
-    trade_log: &mut CsvAppender,
+    snapshots: &Arc<Mutex<HashMap<MarketId, MarketSnapshot>>>,
+    trade_log: &mut TradeLog,
     calibration_tx: &mpsc::Sender<CalibrationEvent>,
     exec: &ExecutionGateway,
+    book: &PositionBook,
     mut positions: Vec<PositionChunk>,
 ) -> SignalOutcome {
-    positions.retain(|p| p.qty.is_finite() && p.qty > 0.0 && !p.token_id.is_empty());
+    positions.retain(|p| p.qty.is_positive() && !p.token_id.is_empty());
     if positions.is_empty() {
         return SignalOutcome::Completed;
     }
@@ -498,6 +697,7 @@ async fn flatten_positions(
 
         let Some(snap) = latest_market_snapshot(snapshots, &signal.market_id).await else {
             return SignalOutcome::HardStop {
+                code: ErrorCode::Exec,
                 reason: "flatten_failed:no_snapshot".to_string(),
             };
         };
@@ -508,7 +708,7 @@ async fn flatten_positions(
                 still.push(p);
                 continue;
             };
-            let limit_price = top.best_bid * (1.0 - lvl.to_f64());
+            let limit_price = top.best_bid.offset_bps(Bps::ZERO - lvl);
             let notes = format!("flatten_lvl_bps={}", lvl.raw());
             let notes = format!("attempt={attempts_done}|{notes}");
 
@@ -518,6 +718,7 @@ async fn flatten_positions(
                 trade_log,
                 calibration_tx,
                 exec,
+                book,
                 OmsAction::Flatten,
                 -1,
                 &p.token_id,
@@ -530,11 +731,11 @@ async fn flatten_positions(
             .await
             {
                 Ok(r) => r,
-                Err(e) => return SignalOutcome::HardStop { reason: e },
+                Err(e) => return SignalOutcome::failed(e),
             };
 
-            let remaining = (p.qty - r.filled_qty).max(0.0);
-            if remaining > 1e-12 {
+            let remaining = p.qty - r.filled_qty;
+            if remaining.is_positive() {
                 still.push(PositionChunk {
                     token_id: p.token_id,
                     qty: remaining,
@@ -549,6 +750,7 @@ async fn flatten_positions(
     }
 
     SignalOutcome::HardStop {
+        code: ErrorCode::Exec,
         reason: "flatten_failed".to_string(),
     }
 }
@@ -557,21 +759,22 @@ async fn flatten_positions(
 async fn simulate_ioc_and_log(
     _cfg: &Config,
     signal: &Signal,
-    trade_log: &mut CsvAppender,
+    trade_log: &mut TradeLog,
     calibration_tx: &mpsc::Sender<CalibrationEvent>,
     exec: &ExecutionGateway,
+    book: &PositionBook,
     action: OmsAction,
     leg_index: i32,
-    token_id: &str,
+    token_id: &TokenId,
     side: Side,
-    limit_price: f64,
-    req_qty: f64,
+    limit_price: Price,
+    req_qty: Qty,
     notes: &str,
     top: TopOfBook,
-) -> Result<FillReport, String> {
-    let kind = action
-        .exec_kind()
-        .ok_or_else(|| "not an executable action".to_string())?;
+) -> Result<FillReport, RazorError> {
+    let kind = action.exec_kind().ok_or_else(|| {
+        RazorError::OrderRejected(format!("{} is not executable", action.as_str()))
+    })?;
 
     let exec_res = exec
         .place_ioc(PlaceIocRequest {
@@ -584,7 +787,12 @@ async fn simulate_ioc_and_log(
             top,
         })
         .await
-        .map_err(|e| format!("exec error: {e:#}"))?;
+        .map_err(|e| {
+            into_razor(e, |source| RazorError::Exec {
+                what: "exec error".to_string(),
+                source,
+            })
+        })?;
 
     let report = exec_res.fill;
     let full_notes = format!(
@@ -608,25 +816,25 @@ async fn simulate_ioc_and_log(
         report.filled_qty,
         report.status,
         &full_notes,
-    )
-    .map_err(|e| format!("trade_log write failed: {e:#}"))?;
+    )?;
+    book.apply_fill(&signal.market_id, token_id, side, &report);
 
     let ev = CalibrationEvent {
-        ts_ms: now_ms(),
+        ts_ms: trade_log.clock.now_ms(),
         bucket: signal.bucket,
         market_id: signal.market_id.clone(),
-        token_id: token_id.to_string(),
+        token_id: token_id.clone(),
         side,
-        req_qty,
-        filled_qty: report.filled_qty,
-        market_ask_size_best: exec_res.top.best_ask_size_best,
-        market_bid_size_best: exec_res.top.best_bid_size_best,
+        req_qty: req_qty.to_f64(),
+        filled_qty: report.filled_qty.to_f64(),
+        market_ask_size_best: exec_res.top.best_ask_size_best.to_f64(),
+        market_bid_size_best: exec_res.top.best_bid_size_best.to_f64(),
         sim_fill_share_used: exec_res.sim_fill_share_used,
-        mode: "SIM".to_string(),
+        mode: trade_log.phase.to_string(),
     };
     if calibration_tx.try_send(ev).is_err() {
-        warn!(
-            signal_id = signal.signal_id,
+        warn_throttled!(
+            signal_id = signal.signal_id.get(),
             "calibration channel full/closed; dropped event"
         );
     }
@@ -642,50 +850,62 @@ fn max_chase_bps(cfg: &Config, expected_net_bps: Bps) -> Bps {
 
 #[allow(clippy::too_many_arguments)]
 fn write_trade_row(
-    out: &mut CsvAppender,
+    trade_log: &mut TradeLog,
     signal: &Signal,
     action: OmsAction,
     leg_index: i32,
     token_id: &str,
     side: Side,
-    limit_price: f64,
-    req_qty: f64,
-    fill_qty: f64,
+    limit_price: Price,
+    req_qty: Qty,
+    fill_qty: Qty,
     fill_status: FillStatus,
     notes: &str,
-) -> anyhow::Result<()> {
-    out.write_record([
-        now_ms().to_string(),
-        signal.signal_id.to_string(),
-        signal.market_id.clone(),
-        signal.strategy.as_str().to_string(),
-        signal.bucket.as_str().to_string(),
-        "SIM".to_string(),
-        action.as_str().to_string(),
-        leg_index.to_string(),
-        token_id.to_string(),
-        side.as_str().to_string(),
-        limit_price.to_string(),
-        req_qty.to_string(),
-        fill_qty.to_string(),
-        fill_status.as_str().to_string(),
-        signal.expected_net_bps.raw().to_string(),
-        notes.to_string(),
-    ])
+) -> Result<(), RazorError> {
+    trace_signal!(
+        signal.signal_id,
+        "sniper_action",
+        action = action.as_str(),
+        leg_index,
+        fill_qty = fill_qty.to_f64(),
+        fill_status = fill_status.as_str(),
+        notes
+    );
+    trade_log
+        .out
+        .write_row(&TradeLogRow {
+            ts_ms: trade_log.clock.now_ms(),
+            signal_id: signal.signal_id.get(),
+            market_id: signal.market_id.to_string(),
+            strategy: signal.strategy.as_str().to_string(),
+            bucket: signal.bucket.as_str().to_string(),
+            phase: trade_log.phase.to_string(),
+            action: action.as_str().to_string(),
+            leg_index,
+            token_id: token_id.to_string(),
+            side: side.as_str().to_string(),
+            limit_price: limit_price.to_f64(),
+            req_qty: req_qty.to_f64(),
+            fill_qty: fill_qty.to_f64(),
+            fill_status: fill_status.as_str().to_string(),
+            expected_net_bps: signal.expected_net_bps.raw(),
+            notes: notes.to_string(),
+        })
+        .io_context("write trade_log.csv")
 }
 
 async fn latest_market_snapshot(
-    snapshots: &Arc<Mutex<HashMap<String, MarketSnapshot>>>,
+    snapshots: &Arc<Mutex<HashMap<MarketId, MarketSnapshot>>>,
     market_id: &str,
 ) -> Option<MarketSnapshot> {
     let map = snapshots.lock().await;
     map.get(market_id).cloned()
 }
 
-fn depth3_for_token(snap: &MarketSnapshot, token_id: &str) -> f64 {
+fn depth3_for_token(snap: &MarketSnapshot, token_id: &TokenId) -> f64 {
     snap.legs
         .iter()
-        .find(|l| l.token_id == token_id)
+        .find(|l| &l.token_id == token_id)
         .map(|l| l.ask_depth3_usdc)
         .filter(|d| d.is_finite() && *d >= 0.0)
         .unwrap_or(f64::INFINITY)
@@ -693,10 +913,10 @@ fn depth3_for_token(snap: &MarketSnapshot, token_id: &str) -> f64 {
 
 fn spawn_snapshot_ingest(
     snap_rx: &mut watch::Receiver<Option<MarketSnapshot>>,
-    snapshots: Arc<Mutex<HashMap<String, MarketSnapshot>>>,
+    snapshots: Arc<Mutex<HashMap<MarketId, MarketSnapshot>>>,
 ) {
     let mut snap_rx = snap_rx.clone();
-    tokio::spawn(async move {
+    crate::crash::spawn_supervised("sniper_snapshot_ingest", async move {
         loop {
             if snap_rx.changed().await.is_err() {
                 break;
@@ -723,6 +943,23 @@ fn env_flag(name: &str) -> bool {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::clock::{Clock as _, SimClock};
+
+    #[test]
+    fn cooldown_expires_on_the_injected_clock() {
+        let clock = SimClock::starting_at_ms(1_000_000);
+        let mut cooldowns = Cooldowns::default();
+        let mkt = MarketId::from("mkt");
+
+        let until_ms = cooldowns.start(&mkt, clock.now_ms(), 1_000);
+        assert_eq!(until_ms, 1_001_000);
+        clock.advance_ms(999);
+        assert_eq!(cooldowns.active(&mkt, clock.now_ms()), Some(until_ms));
+        assert_eq!(cooldowns.active(&"other".into(), clock.now_ms()), None);
+        clock.advance_ms(1);
+        assert_eq!(cooldowns.active(&mkt, clock.now_ms()), None);
+        assert!(cooldowns.until_by_market.is_empty());
+    }
 
     #[test]
     fn max_chase_is_half_capped_by_config() {
@@ -733,6 +970,12 @@ mod tests {
                 market_ids: vec![],
                 snapshot_log_interval_ms: 1_000,
                 raw_ws_rotate_keep: 0,
+                raw_ws_rotate_interval_ms: 0,
+                raw_ws_max_total_mb: 0,
+                partition_hourly: false,
+                shutdown_deadline_ms: 10_000,
+                schedule: Default::default(),
+                labels: Default::default(),
             },
             schema_version: crate::schema::SCHEMA_VERSION.to_string(),
             brain: crate::config::BrainConfig::default(),
@@ -752,9 +995,28 @@ mod tests {
                 flatten_lvl3_bps: 1000,
                 flatten_max_attempts: 3,
                 cooldown_ms: 1000,
+                ..Default::default()
             },
             calibration: crate::config::CalibrationConfig::default(),
             sim: crate::config::SimConfig::default(),
+            health: crate::config::HealthConfig::default(),
+            disk: crate::config::DiskConfig::default(),
+            risk: crate::config::RiskConfig::default(),
+            api: crate::config::ApiConfig::default(),
+            email: crate::config::EmailConfig::default(),
+            escalation: crate::config::EscalationConfig::default(),
+            upload: crate::config::UploadConfig::default(),
+            recorder: crate::config::RecorderConfig::default(),
+            backpressure: Default::default(),
+            bucket_classifier: Default::default(),
+            conflation: Default::default(),
+            demo: Default::default(),
+            reconcile: Default::default(),
+            fees: Default::default(),
+            gas: Default::default(),
+            metrics: Default::default(),
+            markets: Default::default(),
+            secrets: Default::default(),
         };
 
         assert_eq!(max_chase_bps(&cfg, Bps::new(10)).raw(), 5);
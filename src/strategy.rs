@@ -0,0 +1,158 @@
+//! Signal strategies: what brain prices a market snapshot with. A strategy only proposes legs
+//! and an edge; brain keeps the shared gates (staleness, min net edge, cooldown, depth cap,
+//! sizing) and the bucket classification, so a new strategy is one impl registered in
+//! [`from_name`] and selected with `brain.strategy`.
+
+use crate::fees::FeeRates;
+use crate::types::{self, Bps, Leg, MarketSnapshot, Price, Qty, Side};
+
+/// What a strategy prices against besides the snapshot itself.
+#[derive(Debug, Clone, Copy)]
+pub struct StrategyContext {
+    /// Fee model in force at the snapshot time.
+    pub fees: FeeRates,
+    /// `brain.risk_premium_bps`.
+    pub risk_premium_bps: Bps,
+}
+
+/// A candidate signal before brain's gates. `legs` carry limit prices and the book at signal
+/// time; their `qty` is left at zero for brain to size.
+#[derive(Debug, Clone)]
+pub struct SignalDraft {
+    /// Label recorded in the frozen `strategy` column.
+    pub strategy: types::Strategy,
+    pub raw_cost_bps: Bps,
+    pub raw_edge_bps: Bps,
+    pub hard_fees_bps: Bps,
+    pub risk_premium_bps: Bps,
+    pub expected_net_bps: Bps,
+    pub legs: Vec<Leg>,
+}
+
+pub trait Strategy: Send + Sync {
+    /// The `brain.strategy` name that selects it.
+    fn name(&self) -> &'static str;
+
+    /// The draft for `snap`, or `None` when the strategy has nothing to price (brain then
+    /// skips the snapshot; a negative edge is still a draft, filtered by `min_net_edge_bps`).
+    fn evaluate(&self, snap: &MarketSnapshot, ctx: &StrategyContext) -> Option<SignalDraft>;
+}
+
+/// The Frozen Spec complete-set edge: buy every outcome at its best ask and merge the set for
+/// 1 USDC. Two legs are `binary`, three `triangle`.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct CompleteSetStrategy;
+
+impl CompleteSetStrategy {
+    pub const NAME: &'static str = "complete_set";
+}
+
+impl Strategy for CompleteSetStrategy {
+    fn name(&self) -> &'static str {
+        Self::NAME
+    }
+
+    fn evaluate(&self, snap: &MarketSnapshot, ctx: &StrategyContext) -> Option<SignalDraft> {
+        let strategy = match snap.legs.len() {
+            2 => types::Strategy::Binary,
+            3 => types::Strategy::Triangle,
+            _ => return None,
+        };
+
+        let sum_ask: Price = snap.legs.iter().map(|l| l.best_ask).sum();
+        if sum_ask < Price::ZERO {
+            return None;
+        }
+
+        // Cost/gating conversion uses ceil to avoid overstating edge near thresholds.
+        let raw_cost_bps = Bps::from_price_cost(sum_ask.to_f64());
+        let raw_edge_bps = Bps::ONE_HUNDRED_PERCENT - raw_cost_bps;
+        let hard_fees_bps = ctx.fees.hard_fees_bps();
+        let expected_net_bps = raw_edge_bps - hard_fees_bps - ctx.risk_premium_bps;
+
+        let legs = snap
+            .legs
+            .iter()
+            .enumerate()
+            .map(|(idx, l)| Leg {
+                leg_index: idx,
+                token_id: l.token_id.clone(),
+                side: Side::Buy,
+                limit_price: l.best_ask,
+                qty: Qty::ZERO,
+                best_bid_at_signal: l.best_bid,
+                best_ask_at_signal: l.best_ask,
+            })
+            .collect();
+
+        Some(SignalDraft {
+            strategy,
+            raw_cost_bps,
+            raw_edge_bps,
+            hard_fees_bps,
+            risk_premium_bps: ctx.risk_premium_bps,
+            expected_net_bps,
+            legs,
+        })
+    }
+}
+
+/// Every name `brain.strategy` accepts.
+pub const STRATEGY_NAMES: [&str; 1] = [CompleteSetStrategy::NAME];
+
+/// The strategy registered under `name`.
+pub fn from_name(name: &str) -> Option<Box<dyn Strategy>> {
+    match name {
+        CompleteSetStrategy::NAME => Some(Box::new(CompleteSetStrategy)),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::LegSnapshot;
+
+    #[test]
+    fn every_registered_name_resolves_to_itself() {
+        for name in STRATEGY_NAMES {
+            assert_eq!(from_name(name).expect("registered").name(), name);
+        }
+        assert!(from_name("mispriced_pair").is_none());
+    }
+
+    #[test]
+    fn complete_set_prices_the_sum_of_asks() {
+        let leg = |token_id: &str, ask: f64| LegSnapshot {
+            token_id: token_id.into(),
+            best_ask: Price::from_f64(ask).unwrap(),
+            best_bid: Price::from_f64(ask - 0.01).unwrap(),
+            best_ask_size_best: Qty::ZERO,
+            best_bid_size_best: Qty::ZERO,
+            ask_depth3_usdc: 1000.0,
+            asks: Default::default(),
+            ts_recv_us: 1,
+        };
+        let ctx = StrategyContext {
+            fees: FeeRates::FROZEN,
+            risk_premium_bps: Bps::new(80),
+        };
+        let snap = MarketSnapshot {
+            market_id: "m".into(),
+            legs: vec![leg("a", 0.30), leg("b", 0.33), leg("c", 0.34)],
+        };
+        let draft = CompleteSetStrategy.evaluate(&snap, &ctx).expect("draft");
+        assert_eq!(draft.strategy, types::Strategy::Triangle);
+        assert_eq!(draft.raw_cost_bps.raw(), 9700);
+        // 300 - 210 - 80
+        assert_eq!(draft.expected_net_bps.raw(), 10);
+        assert_eq!(draft.legs.len(), 3);
+        assert_eq!(draft.legs[1].limit_price, Price::from_f64(0.33).unwrap());
+
+        let single = MarketSnapshot {
+            market_id: "m".into(),
+            legs: vec![leg("a", 0.30)],
+        };
+        assert!(CompleteSetStrategy.evaluate(&single, &ctx).is_none());
+    }
+}
@@ -0,0 +1,492 @@
+use std::collections::{BTreeMap, HashMap, VecDeque};
+use std::io::{Read as _, Seek as _, SeekFrom};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use anyhow::Context as _;
+use ratatui::crossterm::event::{self, Event, KeyCode, KeyEventKind};
+use ratatui::layout::{Constraint, Layout};
+use ratatui::style::{Color, Modifier, Style};
+use ratatui::text::Line;
+use ratatui::widgets::{Block, Borders, Paragraph, Row, Table};
+use ratatui::Frame;
+
+use crate::schema::{FILE_HEALTH_JSONL, FILE_SHADOW_LOG, FILE_SNAPSHOTS};
+use crate::types::now_ms;
+
+const ROLLING_PNL_WINDOW_MS: u64 = 60 * 60 * 1000;
+/// A heartbeat older than this (written every 10s by default) means the run is most likely gone.
+const HEARTBEAT_DEAD_MS: u64 = 30_000;
+
+/// Follows an append-only file by byte offset; starts over if the file is truncated or replaced.
+pub struct Tail {
+    path: PathBuf,
+    offset: u64,
+    partial: String,
+    lines_seen: u64,
+}
+
+impl Tail {
+    pub fn new(path: PathBuf) -> Self {
+        Self {
+            path,
+            offset: 0,
+            partial: String::new(),
+            lines_seen: 0,
+        }
+    }
+
+    /// Returns complete lines appended since the last call. A missing file yields no lines.
+    pub fn read_new_lines(&mut self) -> std::io::Result<Vec<String>> {
+        let mut f = match std::fs::File::open(&self.path) {
+            Ok(f) => f,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(e) => return Err(e),
+        };
+        let len = f.metadata()?.len();
+        if len < self.offset {
+            self.offset = 0;
+            self.partial.clear();
+            self.lines_seen = 0;
+        }
+        f.seek(SeekFrom::Start(self.offset))?;
+        let mut buf = Vec::new();
+        f.read_to_end(&mut buf)?;
+        self.offset += buf.len() as u64;
+        self.partial.push_str(&String::from_utf8_lossy(&buf));
+
+        let mut lines = Vec::new();
+        while let Some(pos) = self.partial.find('\n') {
+            let line: String = self.partial.drain(..=pos).collect();
+            let line = line.trim_end_matches(['\n', '\r']);
+            if !line.is_empty() {
+                lines.push(line.to_string());
+            }
+        }
+        self.lines_seen += lines.len() as u64;
+        Ok(lines)
+    }
+
+    /// Like `read_new_lines`, but splits off the first line of the file as the CSV header.
+    pub fn read_new_csv_lines(&mut self) -> std::io::Result<(Option<String>, Vec<String>)> {
+        let mut lines = self.read_new_lines()?;
+        let fresh = self.lines_seen == lines.len() as u64;
+        if fresh && !lines.is_empty() {
+            let header = lines.remove(0);
+            return Ok((Some(header), lines));
+        }
+        Ok((None, lines))
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct LegQuote {
+    pub best_bid: f64,
+    pub best_ask: f64,
+    pub depth3_usdc: f64,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct MarketView {
+    pub snapshot_ts_ms: u64,
+    pub legs: Vec<LegQuote>,
+    pub signals: u64,
+    pub total_pnl: f64,
+    pub stale: bool,
+}
+
+/// Everything the dashboard shows, built purely from lines of the run's output files.
+#[derive(Debug, Default)]
+pub struct TopState {
+    pub heartbeat: Option<serde_json::Value>,
+    pub last_feed_stale: Option<(u64, String, u64)>,
+    pub markets: BTreeMap<String, MarketView>,
+    pub signals: u64,
+    pub total_pnl: f64,
+    set_ratio_sum: f64,
+    recent_pnl: VecDeque<(u64, f64)>,
+    shadow_cols: Option<HashMap<String, usize>>,
+    snapshot_cols: Option<HashMap<String, usize>>,
+}
+
+impl TopState {
+    pub fn ingest_health_line(&mut self, line: &str) {
+        let Ok(v) = serde_json::from_str::<serde_json::Value>(line) else {
+            return;
+        };
+        match v.get("type").and_then(|t| t.as_str()) {
+            Some("heartbeat") => self.heartbeat = Some(v),
+            Some("feed_stale") => {
+                // Edge-triggered: a `stale: false` line is the recovery.
+                if v.get("stale").and_then(|s| s.as_bool()) == Some(false) {
+                    self.last_feed_stale = None;
+                    return;
+                }
+                self.last_feed_stale = Some((
+                    u64_field(&v, "ts_ms"),
+                    str_field(&v, "feed"),
+                    u64_field(&v, "age_ms"),
+                ));
+            }
+            Some("market_stale") => {
+                let market_id = str_field(&v, "market_id");
+                let stale = v.get("stale").and_then(|s| s.as_bool()).unwrap_or(false);
+                self.markets.entry(market_id).or_default().stale = stale;
+            }
+            _ => {}
+        }
+    }
+
+    pub fn set_shadow_header(&mut self, line: &str) {
+        self.shadow_cols = Some(header_index(line));
+    }
+
+    pub fn ingest_shadow_row(&mut self, line: &str) {
+        let Some(cols) = self.shadow_cols.as_ref() else {
+            return;
+        };
+        let Some(rec) = parse_csv_line(line) else {
+            return;
+        };
+        let get = |name: &str| cols.get(name).and_then(|&i| rec.get(i)).unwrap_or("");
+        let ts_ms: u64 = get("signal_ts_unix_ms").parse().unwrap_or(0);
+        let market_id = get("market_id").to_string();
+        let pnl: f64 = get("total_pnl").parse().unwrap_or(0.0);
+        let set_ratio: f64 = get("set_ratio").parse().unwrap_or(0.0);
+
+        self.signals += 1;
+        self.total_pnl += pnl;
+        self.set_ratio_sum += set_ratio;
+        self.recent_pnl.push_back((ts_ms, pnl));
+        let m = self.markets.entry(market_id).or_default();
+        m.signals += 1;
+        m.total_pnl += pnl;
+    }
+
+    pub fn set_snapshot_header(&mut self, line: &str) {
+        self.snapshot_cols = Some(header_index(line));
+    }
+
+    pub fn ingest_snapshot_row(&mut self, line: &str) {
+        let Some(cols) = self.snapshot_cols.as_ref() else {
+            return;
+        };
+        let Some(rec) = parse_csv_line(line) else {
+            return;
+        };
+        let get = |name: &str| cols.get(name).and_then(|&i| rec.get(i)).unwrap_or("");
+        let legs_n: usize = get("legs_n").parse().unwrap_or(0);
+        let legs = (0..legs_n.min(3))
+            .map(|i| LegQuote {
+                best_bid: get(&format!("leg{i}_best_bid")).parse().unwrap_or(0.0),
+                best_ask: get(&format!("leg{i}_best_ask")).parse().unwrap_or(0.0),
+                depth3_usdc: get(&format!("leg{i}_depth3_usdc")).parse().unwrap_or(0.0),
+            })
+            .collect();
+        let ts_ms = get("ts_ms").parse().unwrap_or(0);
+        let m = self
+            .markets
+            .entry(get("market_id").to_string())
+            .or_default();
+        m.snapshot_ts_ms = ts_ms;
+        m.legs = legs;
+    }
+
+    /// Sum of shadow PnL for signals within the trailing hour of `now`.
+    pub fn rolling_pnl(&mut self, now: u64) -> f64 {
+        let cutoff = now.saturating_sub(ROLLING_PNL_WINDOW_MS);
+        while self.recent_pnl.front().is_some_and(|(ts, _)| *ts < cutoff) {
+            self.recent_pnl.pop_front();
+        }
+        self.recent_pnl.iter().map(|(_, p)| p).sum()
+    }
+
+    pub fn avg_set_ratio(&self) -> f64 {
+        if self.signals == 0 {
+            0.0
+        } else {
+            self.set_ratio_sum / self.signals as f64
+        }
+    }
+
+    fn hb(&self, key: &str) -> u64 {
+        self.heartbeat
+            .as_ref()
+            .map(|v| u64_field(v, key))
+            .unwrap_or(0)
+    }
+}
+
+struct Sources {
+    health: Tail,
+    shadow: Tail,
+    snapshots: Tail,
+}
+
+impl Sources {
+    fn open(run_dir: &Path) -> Self {
+        Self {
+            health: Tail::new(run_dir.join(FILE_HEALTH_JSONL)),
+            shadow: Tail::new(run_dir.join(FILE_SHADOW_LOG)),
+            snapshots: Tail::new(run_dir.join(FILE_SNAPSHOTS)),
+        }
+    }
+
+    fn poll(&mut self, state: &mut TopState) -> std::io::Result<()> {
+        for line in self.health.read_new_lines()? {
+            state.ingest_health_line(&line);
+        }
+
+        let (header, rows) = self.shadow.read_new_csv_lines()?;
+        if let Some(h) = header {
+            state.set_shadow_header(&h);
+        }
+        for line in rows {
+            state.ingest_shadow_row(&line);
+        }
+
+        let (header, rows) = self.snapshots.read_new_csv_lines()?;
+        if let Some(h) = header {
+            state.set_snapshot_header(&h);
+        }
+        for line in rows {
+            state.ingest_snapshot_row(&line);
+        }
+        Ok(())
+    }
+}
+
+/// Blocking entry point for `razor top`: tails the run dir and redraws every `refresh_ms` until
+/// `q`/`Esc` is pressed.
+pub fn run(run_dir: PathBuf, refresh_ms: u64) -> anyhow::Result<()> {
+    anyhow::ensure!(run_dir.is_dir(), "run dir not found: {}", run_dir.display());
+    let mut sources = Sources::open(&run_dir);
+    let mut state = TopState::default();
+    sources.poll(&mut state).context("read run dir")?;
+
+    let mut terminal = ratatui::init();
+    let res = (|| -> anyhow::Result<()> {
+        loop {
+            sources.poll(&mut state).context("read run dir")?;
+            terminal.draw(|f| draw(f, &run_dir, &mut state))?;
+            if event::poll(Duration::from_millis(refresh_ms))? {
+                if let Event::Key(k) = event::read()? {
+                    if k.kind == KeyEventKind::Press
+                        && matches!(k.code, KeyCode::Char('q') | KeyCode::Esc)
+                    {
+                        return Ok(());
+                    }
+                }
+            }
+        }
+    })();
+    ratatui::restore();
+    res
+}
+
+fn draw(f: &mut Frame, run_dir: &Path, state: &mut TopState) {
+    let now = now_ms();
+    let [header, counters, markets] = Layout::vertical([
+        Constraint::Length(4),
+        Constraint::Length(6),
+        Constraint::Min(3),
+    ])
+    .areas(f.area());
+
+    let hb_ts = state.hb("ts_ms");
+    let hb_age = now.saturating_sub(hb_ts);
+    let tick_age = hb_ts.saturating_sub(state.hb("last_tick_ingest_ms"));
+    let (ws_status, ws_color) = if state.heartbeat.is_none() {
+        ("waiting for heartbeat".to_string(), Color::Yellow)
+    } else if hb_age > HEARTBEAT_DEAD_MS {
+        (format!("NO HEARTBEAT ({}s)", hb_age / 1000), Color::Red)
+    } else if let Some((_, feed, age)) = state
+        .last_feed_stale
+        .as_ref()
+        .filter(|(ts, _, _)| *ts > state.hb("last_tick_ingest_ms"))
+    {
+        (format!("STALE {feed} ({age}ms)"), Color::Red)
+    } else {
+        (format!("OK (last tick {tick_age}ms)"), Color::Green)
+    };
+    let header_text = vec![
+        Line::from(format!("run_dir: {}", run_dir.display())),
+        Line::styled(format!("ws: {ws_status}"), Style::default().fg(ws_color)),
+        Line::from(format!(
+            "heartbeat age: {}s   disk: {}   (q to quit)",
+            hb_age / 1000,
+            state
+                .heartbeat
+                .as_ref()
+                .map(|v| str_field(v, "disk_level"))
+                .unwrap_or_default()
+        )),
+    ];
+    f.render_widget(
+        Paragraph::new(header_text)
+            .block(Block::default().borders(Borders::ALL).title("razor top")),
+        header,
+    );
+
+    let rolling = state.rolling_pnl(now);
+    let counters_text = vec![
+        Line::from(format!(
+            "signals: {} (emitted {} / suppressed {} / dropped {})",
+            state.signals,
+            state.hb("signals_emitted"),
+            state.hb("signals_suppressed"),
+            state.hb("signals_dropped"),
+        )),
+        Line::from(format!(
+            "pnl: total {:.4}   last 1h {:.4}   avg set_ratio {:.3}",
+            state.total_pnl,
+            rolling,
+            state.avg_set_ratio()
+        )),
+        Line::from(format!(
+            "channels: trade_store {} (evicted {})   trades dropped {}   shadow processed {}",
+            state.hb("trade_store_size"),
+            state.hb("trade_store_evicted"),
+            state.hb("trades_dropped"),
+            state.hb("shadow_processed"),
+        )),
+        Line::from(format!(
+            "ticks {}   trades {}   trade_poll {}ms (limit hits {})",
+            state.hb("ticks_processed"),
+            state.hb("trades_written"),
+            state.hb("trade_poll_interval_ms"),
+            state.hb("trade_poll_hit_limit"),
+        )),
+    ];
+    f.render_widget(
+        Paragraph::new(counters_text).block(Block::default().borders(Borders::ALL).title("totals")),
+        counters,
+    );
+
+    let rows = state.markets.iter().map(|(id, m)| {
+        let book = m
+            .legs
+            .iter()
+            .map(|l| format!("{:.3}/{:.3} ${:.0}", l.best_bid, l.best_ask, l.depth3_usdc))
+            .collect::<Vec<_>>()
+            .join("  ");
+        let age = if m.snapshot_ts_ms == 0 {
+            "-".to_string()
+        } else {
+            format!("{}s", now.saturating_sub(m.snapshot_ts_ms) / 1000)
+        };
+        let style = if m.stale {
+            Style::default().fg(Color::Red)
+        } else {
+            Style::default()
+        };
+        Row::new(vec![
+            id.clone(),
+            book,
+            age,
+            m.signals.to_string(),
+            format!("{:.4}", m.total_pnl),
+        ])
+        .style(style)
+    });
+    let table = Table::new(
+        rows,
+        [
+            Constraint::Length(14),
+            Constraint::Min(30),
+            Constraint::Length(6),
+            Constraint::Length(8),
+            Constraint::Length(10),
+        ],
+    )
+    .header(
+        Row::new(vec![
+            "market",
+            "bid/ask depth3 per leg",
+            "age",
+            "signals",
+            "pnl",
+        ])
+        .style(Style::default().add_modifier(Modifier::BOLD)),
+    )
+    .block(Block::default().borders(Borders::ALL).title("markets"));
+    f.render_widget(table, markets);
+}
+
+fn header_index(line: &str) -> HashMap<String, usize> {
+    parse_csv_line(line)
+        .map(|rec| {
+            rec.iter()
+                .enumerate()
+                .map(|(i, name)| (name.trim().to_string(), i))
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+fn parse_csv_line(line: &str) -> Option<csv::StringRecord> {
+    csv::ReaderBuilder::new()
+        .has_headers(false)
+        .from_reader(line.as_bytes())
+        .records()
+        .next()?
+        .ok()
+}
+
+fn u64_field(v: &serde_json::Value, key: &str) -> u64 {
+    v.get(key).and_then(|x| x.as_u64()).unwrap_or(0)
+}
+
+fn str_field(v: &serde_json::Value, key: &str) -> String {
+    v.get(key)
+        .and_then(|x| x.as_str())
+        .unwrap_or_default()
+        .to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::schema::SHADOW_HEADER;
+
+    #[test]
+    fn shadow_rows_accumulate_per_market_and_rolling_pnl() {
+        let mut s = TopState::default();
+        s.set_shadow_header(&SHADOW_HEADER.join(","));
+        let row = |ts: u64, market: &str, pnl: f64| {
+            SHADOW_HEADER
+                .iter()
+                .map(|c| match *c {
+                    "signal_ts_unix_ms" => ts.to_string(),
+                    "market_id" => market.to_string(),
+                    "total_pnl" => pnl.to_string(),
+                    "set_ratio" => "0.5".to_string(),
+                    "notes" => "\"a,b\"".to_string(),
+                    _ => String::new(),
+                })
+                .collect::<Vec<_>>()
+                .join(",")
+        };
+        s.ingest_shadow_row(&row(1_000, "m1", 1.0));
+        s.ingest_shadow_row(&row(ROLLING_PNL_WINDOW_MS + 2_000, "m1", 0.25));
+        s.ingest_shadow_row(&row(ROLLING_PNL_WINDOW_MS + 3_000, "m2", -0.5));
+
+        assert_eq!(s.signals, 3);
+        assert!((s.total_pnl - 0.75).abs() < 1e-9);
+        assert_eq!(s.markets["m1"].signals, 2);
+        assert!((s.rolling_pnl(ROLLING_PNL_WINDOW_MS + 3_000) + 0.25).abs() < 1e-9);
+        assert!((s.avg_set_ratio() - 0.5).abs() < 1e-9);
+    }
+
+    #[test]
+    fn health_lines_update_heartbeat_and_market_staleness() {
+        let mut s = TopState::default();
+        s.ingest_health_line(r#"{"type":"heartbeat","ts_ms":5,"trade_store_size":7}"#);
+        s.ingest_health_line(
+            r#"{"type":"market_stale","ts_ms":6,"market_id":"m1","feed":"tick","age_ms":1,"threshold_ms":1,"stale":true}"#,
+        );
+        s.ingest_health_line("not json");
+        assert_eq!(s.hb("trade_store_size"), 7);
+        assert!(s.markets["m1"].stale);
+    }
+}
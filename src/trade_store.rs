@@ -1,20 +1,27 @@
-use std::collections::{HashSet, VecDeque};
+use std::collections::{BTreeMap, HashMap, HashSet, VecDeque};
 
-use crate::types::{now_ms, TradeTick};
+use crate::clock::{self, SharedClock};
+use crate::types::{MarketId, Price, Qty, Side, TokenId, TradeTick};
 use tracing::warn;
 
 /// In-memory ring buffer for Shadow volume queries (Phase 1).
 ///
-/// Correctness first: O(n) scans are acceptable at Phase 1 scale.
+/// Trades are kept in arrival order for retention and the cap, and indexed per market and per
+/// `(market, token)` by ingest timestamp, so window queries cost O(log n + k) for the k trades in
+/// the window instead of a scan of the whole buffer on every settle tick.
 pub struct TradeStore {
     retention_ms: u64,
     max_trades: usize,
-    trades: VecDeque<TradeTick>,
+    /// `(seq, trade)` in arrival order; `seq` keys the trade in `index`.
+    trades: VecDeque<(u64, TradeTick)>,
+    index: TimeIndex,
+    next_seq: u64,
     recent_ids: HashSet<String>,
     dedup_events: VecDeque<DedupEvent>,
     last_seen_ts_ms: u64,
     needs_full_trim: bool,
     last_out_of_order_warn_ms: u64,
+    clock: SharedClock,
 }
 
 #[derive(Clone, Copy, Debug, Default)]
@@ -28,7 +35,7 @@ pub struct WindowStats {
 #[derive(Clone, Debug)]
 #[allow(dead_code)]
 struct DedupEvent {
-    market_id: String,
+    market_id: MarketId,
     ts_ms: u64,
 }
 
@@ -38,14 +45,23 @@ impl TradeStore {
             retention_ms,
             max_trades,
             trades: VecDeque::new(),
+            index: TimeIndex::default(),
+            next_seq: 0,
             recent_ids: HashSet::new(),
             dedup_events: VecDeque::new(),
             last_seen_ts_ms: 0,
             needs_full_trim: false,
             last_out_of_order_warn_ms: 0,
+            clock: clock::system(),
         }
     }
 
+    /// Retention is measured against `clock` instead of the wall clock.
+    pub fn with_clock(mut self, clock: SharedClock) -> Self {
+        self.clock = clock;
+        self
+    }
+
     pub fn len(&self) -> usize {
         self.trades.len()
     }
@@ -54,12 +70,17 @@ impl TradeStore {
         self.trades.is_empty()
     }
 
+    /// Newest ingest timestamp pushed so far (0 before the first trade).
+    pub fn latest_ingest_ts_ms(&self) -> u64 {
+        self.last_seen_ts_ms
+    }
+
     pub fn push(&mut self, t: TradeTick) -> PushResult {
         let mut t = t;
         if t.token_id.is_empty() || t.market_id.is_empty() {
             return PushResult::dropped();
         }
-        if !t.price.is_finite() || !t.size.is_finite() || t.price < 0.0 || t.size <= 0.0 {
+        if t.price < Price::ZERO || !t.size.is_positive() {
             return PushResult::dropped();
         }
         if effective_ingest_ts_ms(&t) == 0 {
@@ -69,7 +90,7 @@ impl TradeStore {
             t.trade_id = fallback_trade_id(&t);
         }
 
-        let now = now_ms();
+        let now = self.clock.now_ms();
         self.trim(now);
 
         let ts = effective_ingest_ts_ms(&t);
@@ -95,7 +116,10 @@ impl TradeStore {
         }
 
         self.recent_ids.insert(t.trade_id.clone());
-        self.trades.push_back(t);
+        let seq = self.next_seq;
+        self.next_seq += 1;
+        self.index.insert(seq, &t);
+        self.trades.push_back((seq, t));
 
         let evicted = self.enforce_cap();
         PushResult {
@@ -106,77 +130,101 @@ impl TradeStore {
     }
 
     #[allow(dead_code)]
-    pub fn dedup_hits_in_window(&self, market_id: &str, start_ms: u64, end_ms: u64) -> usize {
+    pub fn dedup_hits_in_window(&self, market_id: &MarketId, start_ms: u64, end_ms: u64) -> usize {
         if market_id.trim().is_empty() || start_ms > end_ms {
             return 0;
         }
         self.dedup_events
             .iter()
-            .filter(|e| e.market_id == market_id)
+            .filter(|e| &e.market_id == market_id)
             .filter(|e| e.ts_ms >= start_ms && e.ts_ms <= end_ms)
             .count()
     }
 
     pub fn volume_at_or_better_price(
         &self,
-        market_id: &str,
-        token_id: &str,
+        market_id: &MarketId,
+        token_id: &TokenId,
+        start_ms: u64,
+        end_ms: u64,
+        price_limit: Price,
+    ) -> Qty {
+        self.volume_filtered(market_id, token_id, start_ms, end_ms, price_limit, None)
+    }
+
+    /// Like [`Self::volume_at_or_better_price`], counting only trades whose aggressor was
+    /// `aggressor`. Trades with an unknown side (older feeds, pre-v4 trades.csv) still count, so a
+    /// run without side data degrades to the unsided volume.
+    pub fn sided_volume_at_or_better_price(
+        &self,
+        market_id: &MarketId,
+        token_id: &TokenId,
+        start_ms: u64,
+        end_ms: u64,
+        price_limit: Price,
+        aggressor: Side,
+    ) -> Qty {
+        self.volume_filtered(
+            market_id,
+            token_id,
+            start_ms,
+            end_ms,
+            price_limit,
+            Some(aggressor),
+        )
+    }
+
+    fn volume_filtered(
+        &self,
+        market_id: &MarketId,
+        token_id: &TokenId,
         start_ms: u64,
         end_ms: u64,
-        price_limit: f64,
-    ) -> f64 {
+        price_limit: Price,
+        aggressor: Option<Side>,
+    ) -> Qty {
         if token_id.is_empty() || market_id.is_empty() {
-            return 0.0;
+            return Qty::ZERO;
         }
         if self.is_empty() {
-            return 0.0;
+            return Qty::ZERO;
         }
         if start_ms > end_ms {
-            return 0.0;
-        }
-        if !price_limit.is_finite() {
-            return 0.0;
+            return Qty::ZERO;
         }
 
-        self.trades
-            .iter()
-            .filter(|t| t.market_id == market_id)
-            .filter(|t| t.token_id == token_id)
-            .filter(|t| {
-                let ts = effective_ingest_ts_ms(t);
-                ts >= start_ms && ts <= end_ms
-            })
-            .filter(|t| t.price.is_finite() && t.size.is_finite())
-            .filter(|t| t.price <= price_limit)
-            .map(|t| t.size)
+        self.index
+            .token_range(market_id, token_id, start_ms, end_ms)
+            .filter(|e| e.price <= price_limit)
+            .filter(|e| side_matches(e.side, aggressor))
+            .map(|e| e.size)
             .sum()
     }
 
-    pub fn window_stats(&self, market_id: &str, start_ms: u64, end_ms: u64) -> WindowStats {
+    pub fn window_stats(&self, market_id: &MarketId, start_ms: u64, end_ms: u64) -> WindowStats {
         if market_id.trim().is_empty() || start_ms > end_ms || self.is_empty() {
             return WindowStats::default();
         }
 
         let mut trades_in_window: usize = 0;
-        let mut ts_samples: Vec<u64> = Vec::new();
+        let mut max_gap_ms: u64 = 0;
         let mut max_trade_size: f64 = 0.0;
         let mut max_trade_notional: f64 = 0.0;
+        let mut prev_ts: Option<u64> = None;
 
-        for t in self.trades.iter() {
-            if t.market_id != market_id {
-                continue;
-            }
-            let ts = effective_ingest_ts_ms(t);
-            if ts < start_ms || ts > end_ms {
-                continue;
-            }
+        // The index yields trades in timestamp order, so gaps are measured in timestamp order
+        // even when trades arrived out-of-order.
+        for (ts, e) in self.index.market_range(market_id, start_ms, end_ms) {
             trades_in_window += 1;
-            ts_samples.push(ts);
+            if let Some(prev) = prev_ts {
+                max_gap_ms = max_gap_ms.max(ts.saturating_sub(prev));
+            }
+            prev_ts = Some(ts);
 
-            if t.size.is_finite() && t.size > max_trade_size {
-                max_trade_size = t.size;
+            if e.size.to_f64() > max_trade_size {
+                max_trade_size = e.size.to_f64();
             }
-            let notional = t.price * t.size;
+            let notional = e.price.notional(e.size);
             if notional.is_finite() && notional > max_trade_notional {
                 max_trade_notional = notional;
             }
@@ -186,16 +234,6 @@ impl TradeStore {
             return WindowStats::default();
         }
 
-        // Compute max gap in **timestamp order**, not insertion order, to avoid
-        // under-estimating gaps when trades arrive out-of-order.
-        let mut max_gap_ms: u64 = 0;
-        ts_samples.sort_unstable();
-        for pair in ts_samples.windows(2) {
-            let a = pair[0];
-            let b = pair[1];
-            max_gap_ms = max_gap_ms.max(b.saturating_sub(a));
-        }
-
         WindowStats {
             trades_in_window,
             max_gap_ms,
@@ -207,37 +245,31 @@ impl TradeStore {
     #[allow(dead_code)]
     pub fn volume_in_window(
         &self,
-        market_id: &str,
-        token_id: &str,
+        market_id: &MarketId,
+        token_id: &TokenId,
         start_ms: u64,
         end_ms: u64,
-    ) -> f64 {
+    ) -> Qty {
         if token_id.is_empty() || market_id.is_empty() {
-            return 0.0;
+            return Qty::ZERO;
         }
         if self.is_empty() {
-            return 0.0;
+            return Qty::ZERO;
         }
         if start_ms > end_ms {
-            return 0.0;
+            return Qty::ZERO;
         }
 
-        self.trades
-            .iter()
-            .filter(|t| t.market_id == market_id)
-            .filter(|t| t.token_id == token_id)
-            .filter(|t| {
-                let ts = effective_ingest_ts_ms(t);
-                ts >= start_ms && ts <= end_ms
-            })
-            .filter(|t| t.size.is_finite())
-            .map(|t| t.size)
+        self.index
+            .token_range(market_id, token_id, start_ms, end_ms)
+            .map(|e| e.size)
             .sum()
     }
 
     fn trim(&mut self, now_ms: u64) {
         if self.retention_ms == 0 {
             self.trades.clear();
+            self.index.clear();
             self.recent_ids.clear();
             self.dedup_events.clear();
             self.needs_full_trim = false;
@@ -251,9 +283,10 @@ impl TradeStore {
         while self
             .trades
             .front()
-            .is_some_and(|t| effective_ingest_ts_ms(t) < cutoff)
+            .is_some_and(|(_, t)| effective_ingest_ts_ms(t) < cutoff)
         {
-            if let Some(old) = self.trades.pop_front() {
+            if let Some((seq, old)) = self.trades.pop_front() {
+                self.index.remove(seq, &old);
                 if !old.trade_id.trim().is_empty() {
                     self.recent_ids.remove(old.trade_id.trim());
                 }
@@ -268,16 +301,17 @@ impl TradeStore {
 
     fn full_trim(&mut self, cutoff: u64) {
         // Fallback path for out-of-order inserts: we cannot rely on popping from the front.
-        let mut new_trades: VecDeque<TradeTick> = VecDeque::with_capacity(self.trades.len());
+        let mut new_trades: VecDeque<(u64, TradeTick)> = VecDeque::with_capacity(self.trades.len());
         let mut new_ids: HashSet<String> = HashSet::with_capacity(self.recent_ids.len());
-        for t in self.trades.drain(..) {
+        for (seq, t) in std::mem::take(&mut self.trades) {
             if effective_ingest_ts_ms(&t) < cutoff {
+                self.index.remove(seq, &t);
                 continue;
             }
             if !t.trade_id.trim().is_empty() {
                 new_ids.insert(t.trade_id.clone());
             }
-            new_trades.push_back(t);
+            new_trades.push_back((seq, t));
         }
         self.trades = new_trades;
         self.recent_ids = new_ids;
@@ -296,6 +330,7 @@ impl TradeStore {
         if self.max_trades == 0 {
             let evicted = self.trades.len();
             self.trades.clear();
+            self.index.clear();
             self.recent_ids.clear();
             self.dedup_events.clear();
             return evicted;
@@ -303,7 +338,8 @@ impl TradeStore {
 
         let mut evicted = 0usize;
         while self.trades.len() > self.max_trades {
-            if let Some(old) = self.trades.pop_front() {
+            if let Some((seq, old)) = self.trades.pop_front() {
+                self.index.remove(seq, &old);
                 if !old.trade_id.trim().is_empty() {
                     self.recent_ids.remove(old.trade_id.trim());
                 }
@@ -316,6 +352,105 @@ impl TradeStore {
     }
 }
 
+/// What the window queries need of a trade.
+#[derive(Clone, Copy, Debug)]
+struct IndexedTrade {
+    price: Price,
+    size: Qty,
+    side: Option<Side>,
+}
+
+/// Trades ordered by `(ingest ts, seq)`; `seq` keeps same-millisecond trades apart.
+type Series = BTreeMap<(u64, u64), IndexedTrade>;
+
+#[derive(Default)]
+struct MarketSeries {
+    all: Series,
+    by_token: HashMap<TokenId, Series>,
+}
+
+/// Per-market and per-`(market, token)` time index over the trades in the store.
+#[derive(Default)]
+struct TimeIndex {
+    markets: HashMap<MarketId, MarketSeries>,
+}
+
+impl TimeIndex {
+    fn insert(&mut self, seq: u64, t: &TradeTick) {
+        let key = (effective_ingest_ts_ms(t), seq);
+        let e = IndexedTrade {
+            price: t.price,
+            size: t.size,
+            side: t.side,
+        };
+        let m = self.markets.entry(t.market_id.clone()).or_default();
+        m.all.insert(key, e);
+        m.by_token
+            .entry(t.token_id.clone())
+            .or_default()
+            .insert(key, e);
+    }
+
+    fn remove(&mut self, seq: u64, t: &TradeTick) {
+        let key = (effective_ingest_ts_ms(t), seq);
+        let Some(m) = self.markets.get_mut(&t.market_id) else {
+            return;
+        };
+        m.all.remove(&key);
+        if let Some(series) = m.by_token.get_mut(&t.token_id) {
+            series.remove(&key);
+            if series.is_empty() {
+                m.by_token.remove(&t.token_id);
+            }
+        }
+        if m.all.is_empty() {
+            self.markets.remove(&t.market_id);
+        }
+    }
+
+    fn clear(&mut self) {
+        self.markets.clear();
+    }
+
+    /// Trades of `market_id` with ingest ts in `[start_ms, end_ms]`, in timestamp order.
+    fn market_range(
+        &self,
+        market_id: &MarketId,
+        start_ms: u64,
+        end_ms: u64,
+    ) -> impl Iterator<Item = (u64, &IndexedTrade)> {
+        self.markets
+            .get(market_id)
+            .into_iter()
+            .flat_map(move |m| series_range(&m.all, start_ms, end_ms))
+    }
+
+    /// Trades of `(market_id, token_id)` with ingest ts in `[start_ms, end_ms]`.
+    fn token_range(
+        &self,
+        market_id: &MarketId,
+        token_id: &TokenId,
+        start_ms: u64,
+        end_ms: u64,
+    ) -> impl Iterator<Item = &IndexedTrade> {
+        self.markets
+            .get(market_id)
+            .and_then(|m| m.by_token.get(token_id))
+            .into_iter()
+            .flat_map(move |series| series_range(series, start_ms, end_ms).map(|(_, e)| e))
+    }
+}
+
+fn series_range(
+    series: &Series,
+    start_ms: u64,
+    end_ms: u64,
+) -> impl Iterator<Item = (u64, &IndexedTrade)> {
+    series
+        .range((start_ms, 0)..=(end_ms, u64::MAX))
+        .map(|(&(ts, _), e)| (ts, e))
+}
+
 #[derive(Clone, Copy, Debug, PartialEq, Eq)]
 pub struct PushResult {
     pub inserted: bool,
@@ -347,8 +482,8 @@ fn fallback_trade_id(t: &TradeTick) -> String {
         t.market_id,
         t.token_id,
         t.ts_ms,
-        t.price.to_bits(),
-        t.size.to_bits()
+        t.price.to_f64().to_bits(),
+        t.size.to_f64().to_bits()
     )
 }
 
@@ -360,9 +495,58 @@ fn effective_ingest_ts_ms(t: &TradeTick) -> u64 {
     }
 }
 
+/// A trade of unknown side matches any aggressor filter.
+pub fn side_matches(trade: Option<Side>, aggressor: Option<Side>) -> bool {
+    match (trade, aggressor) {
+        (Some(t), Some(a)) => t == a,
+        _ => true,
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::clock::SimClock;
+    use crate::types::now_ms;
+
+    fn tick(ts_ms: u64, trade_id: &str) -> TradeTick {
+        TradeTick {
+            ts_ms,
+            ingest_ts_ms: ts_ms,
+            exchange_ts_ms: Some(ts_ms),
+            market_id: "m".into(),
+            token_id: "A".into(),
+            price: Price::from_f64(0.5).unwrap(),
+            size: Qty::from_f64(1.0).unwrap(),
+            trade_id: trade_id.to_string(),
+            side: None,
+        }
+    }
+
+    #[test]
+    fn retention_follows_the_injected_clock() {
+        let clock = SimClock::starting_at_ms(1_000_000);
+        let mut store = TradeStore::new_with_cap(5_000, usize::MAX).with_clock(clock.clone());
+        let _ = store.push(tick(1_000_000, "t1"));
+        clock.advance_ms(4_000);
+        let _ = store.push(tick(1_004_000, "t2"));
+        assert_eq!(store.len(), 2);
+
+        // The first trade falls out exactly when the clock passes its retention.
+        clock.set_ms(1_005_001);
+        let _ = store.push(tick(1_005_001, "t3"));
+        assert_eq!(store.len(), 2);
+        assert_eq!(
+            store.volume_at_or_better_price(
+                &"m".into(),
+                &"A".into(),
+                1_000_000,
+                1_000_000,
+                Price::from_f64(0.6).unwrap()
+            ),
+            Qty::ZERO
+        );
+    }
 
     #[test]
     fn token_filter_is_strict() {
@@ -373,36 +557,45 @@ mod tests {
             ts_ms: base,
             ingest_ts_ms: base,
             exchange_ts_ms: Some(base),
-            market_id: "m".to_string(),
-            token_id: "A".to_string(),
-            price: 0.5,
-            size: 1.0,
+            market_id: "m".into(),
+            token_id: "A".into(),
+            price: Price::from_f64(0.5).unwrap(),
+            size: Qty::from_f64(1.0).unwrap(),
             trade_id: "t1".to_string(),
+            side: None,
         });
         assert!(!store.is_empty());
         let _ = store.push(TradeTick {
             ts_ms: base + 10,
             ingest_ts_ms: base + 10,
             exchange_ts_ms: Some(base + 10),
-            market_id: "m".to_string(),
-            token_id: "A".to_string(),
-            price: 0.5,
-            size: 2.0,
+            market_id: "m".into(),
+            token_id: "A".into(),
+            price: Price::from_f64(0.5).unwrap(),
+            size: Qty::from_f64(2.0).unwrap(),
             trade_id: "t2".to_string(),
+            side: None,
         });
         let _ = store.push(TradeTick {
             ts_ms: base + 20,
             ingest_ts_ms: base + 20,
             exchange_ts_ms: Some(base + 20),
-            market_id: "m".to_string(),
-            token_id: "B".to_string(),
-            price: 0.5,
-            size: 10.0,
+            market_id: "m".into(),
+            token_id: "B".into(),
+            price: Price::from_f64(0.5).unwrap(),
+            size: Qty::from_f64(10.0).unwrap(),
             trade_id: "t3".to_string(),
+            side: None,
         });
 
-        let v = store.volume_at_or_better_price("m", "A", base, base + 100, 0.6);
-        assert_eq!(v, 3.0);
+        let v = store.volume_at_or_better_price(
+            &"m".into(),
+            &"A".into(),
+            base,
+            base + 100,
+            Price::from_f64(0.6).unwrap(),
+        );
+        assert_eq!(v, Qty::from_f64(3.0).unwrap());
     }
 
     #[test]
@@ -414,48 +607,84 @@ mod tests {
             ts_ms: base,
             ingest_ts_ms: base,
             exchange_ts_ms: Some(base),
-            market_id: "m".to_string(),
-            token_id: "A".to_string(),
-            price: 0.49,
-            size: 1.0,
+            market_id: "m".into(),
+            token_id: "A".into(),
+            price: Price::from_f64(0.49).unwrap(),
+            size: Qty::from_f64(1.0).unwrap(),
             trade_id: "t1".to_string(),
+            side: None,
         });
         // In window, price <= limit
         let _ = store.push(TradeTick {
             ts_ms: base + 100,
             ingest_ts_ms: base + 100,
             exchange_ts_ms: Some(base + 100),
-            market_id: "m".to_string(),
-            token_id: "A".to_string(),
-            price: 0.50,
-            size: 2.0,
+            market_id: "m".into(),
+            token_id: "A".into(),
+            price: Price::from_f64(0.50).unwrap(),
+            size: Qty::from_f64(2.0).unwrap(),
             trade_id: "t2".to_string(),
+            side: None,
         });
         // In window, price > limit
         let _ = store.push(TradeTick {
             ts_ms: base + 50,
             ingest_ts_ms: base + 50,
             exchange_ts_ms: Some(base + 50),
-            market_id: "m".to_string(),
-            token_id: "A".to_string(),
-            price: 0.51,
-            size: 100.0,
+            market_id: "m".into(),
+            token_id: "A".into(),
+            price: Price::from_f64(0.51).unwrap(),
+            size: Qty::from_f64(100.0).unwrap(),
             trade_id: "t3".to_string(),
+            side: None,
         });
         // Out of window, price <= limit
         let _ = store.push(TradeTick {
             ts_ms: base.saturating_sub(1),
             ingest_ts_ms: base.saturating_sub(1),
             exchange_ts_ms: Some(base.saturating_sub(1)),
-            market_id: "m".to_string(),
-            token_id: "A".to_string(),
-            price: 0.49,
-            size: 100.0,
+            market_id: "m".into(),
+            token_id: "A".into(),
+            price: Price::from_f64(0.49).unwrap(),
+            size: Qty::from_f64(100.0).unwrap(),
             trade_id: "t4".to_string(),
+            side: None,
         });
 
-        let v = store.volume_at_or_better_price("m", "A", base, base + 100, 0.50);
-        assert_eq!(v, 3.0);
+        let v = store.volume_at_or_better_price(
+            &"m".into(),
+            &"A".into(),
+            base,
+            base + 100,
+            Price::from_f64(0.50).unwrap(),
+        );
+        assert_eq!(v, Qty::from_f64(3.0).unwrap());
+    }
+
+    #[test]
+    fn sided_volume_keeps_matching_and_unknown_sides() {
+        let base = now_ms();
+        let mut store = TradeStore::new_with_cap(60_000, usize::MAX);
+        for (i, (side, size)) in [
+            (Some(Side::Sell), 1.0),
+            (Some(Side::Buy), 10.0),
+            (None, 100.0),
+        ]
+        .into_iter()
+        .enumerate()
+        {
+            let mut t = tick(base + i as u64, &format!("t{i}"));
+            t.side = side;
+            t.size = Qty::from_f64(size).unwrap();
+            let _ = store.push(t);
+        }
+
+        let (m, a, limit) = ("m".into(), "A".into(), Price::from_f64(0.5).unwrap());
+        let all = store.volume_at_or_better_price(&m, &a, base, base + 10, limit);
+        let sells =
+            store.sided_volume_at_or_better_price(&m, &a, base, base + 10, limit, Side::Sell);
+        assert_eq!(all, Qty::from_f64(111.0).unwrap());
+        assert_eq!(sells, Qty::from_f64(101.0).unwrap());
     }
 
     #[test]
@@ -468,36 +697,73 @@ mod tests {
             ts_ms: base + 4_000,
             ingest_ts_ms: base + 4_000,
             exchange_ts_ms: Some(base + 4_000),
-            market_id: "m".to_string(),
-            token_id: "A".to_string(),
-            price: 0.5,
-            size: 1.0,
+            market_id: "m".into(),
+            token_id: "A".into(),
+            price: Price::from_f64(0.5).unwrap(),
+            size: Qty::from_f64(1.0).unwrap(),
             trade_id: "t1".to_string(),
+            side: None,
         });
         let _ = store.push(TradeTick {
             ts_ms: base + 1_000,
             ingest_ts_ms: base + 1_000,
             exchange_ts_ms: Some(base + 1_000),
-            market_id: "m".to_string(),
-            token_id: "A".to_string(),
-            price: 0.5,
-            size: 1.0,
+            market_id: "m".into(),
+            token_id: "A".into(),
+            price: Price::from_f64(0.5).unwrap(),
+            size: Qty::from_f64(1.0).unwrap(),
             trade_id: "t2".to_string(),
+            side: None,
         });
         let _ = store.push(TradeTick {
             ts_ms: base + 2_000,
             ingest_ts_ms: base + 2_000,
             exchange_ts_ms: Some(base + 2_000),
-            market_id: "m".to_string(),
-            token_id: "A".to_string(),
-            price: 0.5,
-            size: 1.0,
+            market_id: "m".into(),
+            token_id: "A".into(),
+            price: Price::from_f64(0.5).unwrap(),
+            size: Qty::from_f64(1.0).unwrap(),
             trade_id: "t3".to_string(),
+            side: None,
         });
 
-        let stats = store.window_stats("m", base, base + 5_000);
+        let stats = store.window_stats(&"m".into(), base, base + 5_000);
         assert_eq!(stats.trades_in_window, 3);
         // Sorted ts: +1000, +2000, +4000 -> max gap = 2000.
         assert_eq!(stats.max_gap_ms, 2_000);
     }
+
+    #[test]
+    fn index_follows_cap_eviction_and_out_of_order_trim() {
+        let clock = SimClock::starting_at_ms(1_000_000);
+        let mut store = TradeStore::new_with_cap(5_000, 3).with_clock(clock.clone());
+        for (i, ts) in [1_000_000, 1_001_000, 1_002_000, 1_003_000]
+            .into_iter()
+            .enumerate()
+        {
+            let _ = store.push(tick(ts, &format!("t{i}")));
+        }
+        // The cap evicted the oldest trade from the index as well.
+        let all = |s: &TradeStore| {
+            s.volume_in_window(&"m".into(), &"A".into(), 0, u64::MAX)
+                .to_f64()
+        };
+        assert_eq!(all(&store), 3.0);
+        assert_eq!(
+            store
+                .window_stats(&"m".into(), 1_000_000, 1_003_000)
+                .trades_in_window,
+            3
+        );
+
+        // An out-of-order push forces the full trim; the index drops what retention drops.
+        clock.set_ms(1_006_500);
+        let _ = store.push(tick(1_002_500, "late"));
+        let _ = store.push(tick(1_006_500, "t5"));
+        assert_eq!(store.len(), 3);
+        assert_eq!(all(&store), 3.0);
+        let stats = store.window_stats(&"m".into(), 0, u64::MAX);
+        assert_eq!(stats.trades_in_window, 3);
+        assert_eq!(stats.max_gap_ms, 3_500);
+    }
 }
@@ -5,9 +5,13 @@
 //!   - For **cost / gating** (avoid false-positive edge): use `from_cost_ratio` (ceil).
 //!   - For **proceeds / display**: use `from_proceeds_ratio` (floor).
 //! - Do **not** introduce float fee constants like `0.02` outside this module.
+//! - Prices and quantities in the core types are fixed-point micro units (`Price`, `Qty`), so
+//!   comparisons are exact; the frozen accounting formulas take `f64` via `to_f64()`.
 
+use std::borrow::Borrow;
 use std::fmt;
-use std::ops::{Add, AddAssign, Sub, SubAssign};
+use std::ops::{Add, AddAssign, Deref, Sub, SubAssign};
+use std::sync::Arc;
 use std::time::{SystemTime, UNIX_EPOCH};
 
 use crate::reasons::ShadowNoteReason;
@@ -176,6 +180,332 @@ impl SubAssign for Bps {
     }
 }
 
+/// Fixed-point value in micro units (1e-6). Polymarket prices (0.01/0.001 ticks) and share
+/// sizes (6 decimals) are exact at this scale. Arithmetic saturates instead of overflowing.
+/// Serialized as a plain JSON/CSV number.
+macro_rules! micro_fixed {
+    ($(#[$meta:meta])* $name:ident) => {
+        $(#[$meta])*
+        #[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+        pub struct $name(i64);
+
+        impl $name {
+            pub const ZERO: $name = $name(0);
+            pub const SCALE: i64 = 1_000_000;
+
+            #[allow(dead_code)]
+            pub const fn from_micros(micros: i64) -> Self {
+                Self(micros)
+            }
+
+            pub const fn micros(self) -> i64 {
+                self.0
+            }
+
+            /// Rounds to the nearest micro unit; `None` for NaN/infinite or out-of-range input.
+            pub fn from_f64(x: f64) -> Option<Self> {
+                if !x.is_finite() {
+                    return None;
+                }
+                let scaled = (x * Self::SCALE as f64).round();
+                if scaled < i64::MIN as f64 || scaled > i64::MAX as f64 {
+                    return None;
+                }
+                Some(Self(scaled as i64))
+            }
+
+            pub fn to_f64(self) -> f64 {
+                self.0 as f64 / Self::SCALE as f64
+            }
+
+            pub const fn is_positive(self) -> bool {
+                self.0 > 0
+            }
+
+            pub const fn saturating_add(self, rhs: Self) -> Self {
+                Self(self.0.saturating_add(rhs.0))
+            }
+
+            pub const fn saturating_sub(self, rhs: Self) -> Self {
+                Self(self.0.saturating_sub(rhs.0))
+            }
+        }
+
+        impl Add for $name {
+            type Output = $name;
+
+            fn add(self, rhs: $name) -> $name {
+                self.saturating_add(rhs)
+            }
+        }
+
+        impl Sub for $name {
+            type Output = $name;
+
+            fn sub(self, rhs: $name) -> $name {
+                self.saturating_sub(rhs)
+            }
+        }
+
+        impl AddAssign for $name {
+            fn add_assign(&mut self, rhs: $name) {
+                *self = self.saturating_add(rhs);
+            }
+        }
+
+        impl SubAssign for $name {
+            fn sub_assign(&mut self, rhs: $name) {
+                *self = self.saturating_sub(rhs);
+            }
+        }
+
+        impl std::iter::Sum for $name {
+            fn sum<I: Iterator<Item = $name>>(iter: I) -> $name {
+                iter.fold($name::ZERO, |a, b| a + b)
+            }
+        }
+
+        /// Shortest decimal form, e.g. `0.49`, `12`, `0.000001`.
+        impl fmt::Display for $name {
+            fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                let sign = if self.0 < 0 { "-" } else { "" };
+                let abs = self.0.unsigned_abs();
+                let scale = $name::SCALE as u64;
+                let (int, frac) = (abs / scale, abs % scale);
+                if frac == 0 {
+                    return write!(f, "{sign}{int}");
+                }
+                let frac = format!("{frac:06}");
+                write!(f, "{sign}{int}.{}", frac.trim_end_matches('0'))
+            }
+        }
+
+        impl std::str::FromStr for $name {
+            type Err = String;
+
+            fn from_str(s: &str) -> Result<Self, Self::Err> {
+                let x: f64 = s
+                    .trim()
+                    .parse()
+                    .map_err(|_| format!("invalid {}: {s:?}", stringify!($name)))?;
+                Self::from_f64(x).ok_or_else(|| format!("invalid {}: {s:?}", stringify!($name)))
+            }
+        }
+
+        impl serde::Serialize for $name {
+            fn serialize<S: serde::Serializer>(&self, s: S) -> Result<S::Ok, S::Error> {
+                s.serialize_f64(self.to_f64())
+            }
+        }
+
+        impl<'de> serde::Deserialize<'de> for $name {
+            fn deserialize<D: serde::Deserializer<'de>>(d: D) -> Result<Self, D::Error> {
+                let x = f64::deserialize(d)?;
+                Self::from_f64(x)
+                    .ok_or_else(|| serde::de::Error::custom(format!("invalid {}: {x}", stringify!($name))))
+            }
+        }
+    };
+}
+
+micro_fixed!(
+    /// Outcome-token price in micro-probability units; `1.0` is a full 1.0 USDC payout.
+    Price
+);
+micro_fixed!(
+    /// Share quantity in micro-shares.
+    Qty
+);
+
+impl Price {
+    /// A missing best bid (one-sided book): zero, which every spread/edge check rejects.
+    pub const MISSING_BID: Price = Price(0);
+    /// A missing best ask (one-sided book): a full 1.0 payout, so no false-positive edge.
+    pub const MISSING_ASK: Price = Price(Price::SCALE);
+
+    /// USDC notional of `qty` at this price.
+    pub fn notional(self, qty: Qty) -> f64 {
+        self.to_f64() * qty.to_f64()
+    }
+
+    /// `self` moved by `bps` (above for positive, below for negative), rounded to the nearest
+    /// micro (ladder/flatten limit prices). Exact integer math, saturating at the `i64` range.
+    pub fn offset_bps(self, bps: Bps) -> Price {
+        let basis = i128::from(Bps::ONE_HUNDRED_PERCENT.raw());
+        let scaled = i128::from(self.0) * (basis + i128::from(bps.raw()));
+        let rounded = (scaled + basis / 2).div_euclid(basis);
+        Price(rounded.clamp(i128::from(i64::MIN), i128::from(i64::MAX)) as i64)
+    }
+}
+
+impl Qty {
+    /// `self * share` rounded **down** (a fill capacity must not be overstated).
+    pub fn scale_down(self, share: f64) -> Qty {
+        if !share.is_finite() || share <= 0.0 {
+            return Qty::ZERO;
+        }
+        Qty((self.0 as f64 * share).floor() as i64)
+    }
+}
+
+/// String identifiers are distinct types so a market id can't be passed where a token id is
+/// expected. Backed by `Arc<str>`: cloning one into every signal/leg/tick is a refcount bump.
+/// On disk (CSV/JSON) they are plain strings.
+macro_rules! string_id {
+    ($(#[$meta:meta])* $name:ident) => {
+        $(#[$meta])*
+        #[derive(Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+        pub struct $name(Arc<str>);
+
+        impl $name {
+            #[allow(dead_code)]
+            pub fn as_str(&self) -> &str {
+                &self.0
+            }
+        }
+
+        impl Deref for $name {
+            type Target = str;
+
+            fn deref(&self) -> &str {
+                &self.0
+            }
+        }
+
+        impl Default for $name {
+            fn default() -> Self {
+                Self("".into())
+            }
+        }
+
+        impl Borrow<str> for $name {
+            fn borrow(&self) -> &str {
+                &self.0
+            }
+        }
+
+        impl AsRef<str> for $name {
+            fn as_ref(&self) -> &str {
+                &self.0
+            }
+        }
+
+        impl fmt::Display for $name {
+            fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                f.write_str(&self.0)
+            }
+        }
+
+        impl fmt::Debug for $name {
+            fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                fmt::Debug::fmt(&*self.0, f)
+            }
+        }
+
+        impl From<String> for $name {
+            fn from(id: String) -> Self {
+                Self(id.into())
+            }
+        }
+
+        impl From<&str> for $name {
+            fn from(id: &str) -> Self {
+                Self(id.into())
+            }
+        }
+
+        impl From<&String> for $name {
+            fn from(id: &String) -> Self {
+                Self(id.as_str().into())
+            }
+        }
+
+        impl PartialEq<str> for $name {
+            fn eq(&self, other: &str) -> bool {
+                &*self.0 == other
+            }
+        }
+
+        impl PartialEq<&str> for $name {
+            fn eq(&self, other: &&str) -> bool {
+                &*self.0 == *other
+            }
+        }
+
+        impl PartialEq<String> for $name {
+            fn eq(&self, other: &String) -> bool {
+                *self.0 == **other
+            }
+        }
+
+        impl serde::Serialize for $name {
+            fn serialize<S: serde::Serializer>(&self, s: S) -> Result<S::Ok, S::Error> {
+                s.serialize_str(&self.0)
+            }
+        }
+
+        impl<'de> serde::Deserialize<'de> for $name {
+            fn deserialize<D: serde::Deserializer<'de>>(d: D) -> Result<Self, D::Error> {
+                String::deserialize(d).map(Self::from)
+            }
+        }
+    };
+}
+
+string_id!(
+    /// Market id (the Gamma `conditionId`).
+    MarketId
+);
+string_id!(
+    /// CLOB outcome token id (asset id).
+    TokenId
+);
+
+/// Per-run monotonically increasing signal number.
+#[derive(
+    Clone,
+    Copy,
+    Debug,
+    Default,
+    PartialEq,
+    Eq,
+    PartialOrd,
+    Ord,
+    Hash,
+    serde::Serialize,
+    serde::Deserialize,
+)]
+#[serde(transparent)]
+pub struct SignalId(pub u64);
+
+impl SignalId {
+    pub const fn get(self) -> u64 {
+        self.0
+    }
+
+    pub const fn next(self) -> SignalId {
+        SignalId(self.0 + 1)
+    }
+}
+
+impl fmt::Display for SignalId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+impl From<u64> for SignalId {
+    fn from(id: u64) -> Self {
+        SignalId(id)
+    }
+}
+
+impl From<SignalId> for u64 {
+    fn from(id: SignalId) -> Self {
+        id.0
+    }
+}
+
 #[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
 pub enum Strategy {
     Binary,
@@ -189,6 +519,15 @@ impl Strategy {
             Strategy::Triangle => "triangle",
         }
     }
+
+    /// `binary` / `triangle`, any case.
+    pub fn parse(s: &str) -> Option<Self> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "binary" => Some(Strategy::Binary),
+            "triangle" => Some(Strategy::Triangle),
+            _ => None,
+        }
+    }
 }
 
 #[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
@@ -204,32 +543,76 @@ impl LiquidityBucket {
             LiquidityBucket::Thin => "Thin",
         }
     }
+
+    /// `liquid` / `thin`, any case.
+    pub fn parse(s: &str) -> Option<Self> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "liquid" => Some(LiquidityBucket::Liquid),
+            "thin" => Some(LiquidityBucket::Thin),
+            _ => None,
+        }
+    }
 }
 
 pub type Bucket = LiquidityBucket;
 
+/// Which bucket classifier decided a signal's bucket (`[bucket_classifier] version`).
+#[derive(
+    Clone, Copy, Debug, Default, PartialEq, Eq, Hash, serde::Serialize, serde::Deserialize,
+)]
+#[serde(rename_all = "lowercase")]
+pub enum BucketClassifierVersion {
+    /// Fixed worst-leg depth/spread rules.
+    #[default]
+    V1,
+    /// Weighted score over trade rate, rolling spread percentile, depth and depth stability.
+    V2,
+}
+
+impl BucketClassifierVersion {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            BucketClassifierVersion::V1 => "v1",
+            BucketClassifierVersion::V2 => "v2",
+        }
+    }
+
+    /// `v1` / `v2`, any case.
+    pub fn parse(s: &str) -> Option<Self> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "v1" => Some(BucketClassifierVersion::V1),
+            "v2" => Some(BucketClassifierVersion::V2),
+            _ => None,
+        }
+    }
+}
+
 #[derive(Clone, Debug)]
 pub struct LegSnapshot {
-    pub token_id: String,
-    pub best_ask: f64,
+    pub token_id: TokenId,
+    pub best_ask: Price,
     #[allow(dead_code)]
-    pub best_ask_size_best: f64,
-    pub best_bid: f64,
+    pub best_ask_size_best: Qty,
+    pub best_bid: Price,
     #[allow(dead_code)]
-    pub best_bid_size_best: f64,
+    pub best_bid_size_best: Qty,
+    /// USDC notional of the top-3 ask levels (NaN when unknown).
     pub ask_depth3_usdc: f64,
+    /// Full ask side from the feed's L2 book (see `order_book`); empty without one.
+    pub asks: crate::order_book::AskLadder,
     #[allow(dead_code)]
     pub ts_recv_us: u64,
 }
 
 #[derive(Clone, Debug)]
 pub struct MarketSnapshot {
-    pub market_id: String,
+    pub market_id: MarketId,
     pub legs: Vec<LegSnapshot>,
 }
 
 #[allow(dead_code)]
-#[derive(Clone, Copy, Debug)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "UPPERCASE")]
 pub enum Side {
     Buy,
     Sell,
@@ -243,19 +626,35 @@ impl Side {
             Side::Sell => "SELL",
         }
     }
+
+    /// `BUY` / `SELL`, any case; anything else is unknown.
+    pub fn parse(s: &str) -> Option<Self> {
+        match s.trim().to_ascii_uppercase().as_str() {
+            "BUY" => Some(Side::Buy),
+            "SELL" => Some(Side::Sell),
+            _ => None,
+        }
+    }
+
+    pub fn opposite(self) -> Self {
+        match self {
+            Side::Buy => Side::Sell,
+            Side::Sell => Side::Buy,
+        }
+    }
 }
 
 #[derive(Clone, Debug)]
 pub struct SignalLeg {
     pub leg_index: usize,
-    pub token_id: String,
+    pub token_id: TokenId,
     #[allow(dead_code)]
     pub side: Side,
-    pub limit_price: f64,
-    pub qty: f64,
-    pub best_bid_at_signal: f64,
+    pub limit_price: Price,
+    pub qty: Qty,
+    pub best_bid_at_signal: Price,
     #[allow(dead_code)]
-    pub best_ask_at_signal: f64,
+    pub best_ask_at_signal: Price,
 }
 
 pub type Leg = SignalLeg;
@@ -263,13 +662,13 @@ pub type Leg = SignalLeg;
 #[derive(Clone, Debug)]
 pub struct Signal {
     pub run_id: String,
-    pub signal_id: u64,
+    pub signal_id: SignalId,
     pub signal_ts_ms: u64,
-    pub market_id: String,
+    pub market_id: MarketId,
     pub strategy: Strategy,
     pub bucket: Bucket,
     pub reasons: Vec<ShadowNoteReason>,
-    pub q_req: f64,
+    pub q_req: Qty,
     pub raw_cost_bps: Bps,
     pub raw_edge_bps: Bps,
     pub hard_fees_bps: Bps,
@@ -279,6 +678,17 @@ pub struct Signal {
     pub legs: Vec<SignalLeg>,
 }
 
+impl Signal {
+    /// Bucket whose fill share applies to leg `leg_index`.
+    pub fn leg_bucket(&self, leg_index: usize) -> Bucket {
+        self.bucket_metrics
+            .leg_buckets
+            .get(leg_index)
+            .copied()
+            .unwrap_or(self.bucket)
+    }
+}
+
 #[allow(dead_code)]
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum FillStatus {
@@ -301,9 +711,9 @@ impl FillStatus {
 #[allow(dead_code)]
 #[derive(Debug, Clone)]
 pub struct FillReport {
-    pub requested_qty: f64,
-    pub filled_qty: f64,
-    pub avg_price: f64,
+    pub requested_qty: Qty,
+    pub filled_qty: Qty,
+    pub avg_price: Price,
     pub status: FillStatus,
     pub order_id: String,
     pub latency_ms: u64,
@@ -321,11 +731,14 @@ pub struct TradeTick {
     /// Exchange timestamp (unix ms) if available; None when missing/unknown.
     #[serde(default)]
     pub exchange_ts_ms: Option<u64>,
-    pub market_id: String,
-    pub token_id: String,
-    pub price: f64,
-    pub size: f64,
+    pub market_id: MarketId,
+    pub token_id: TokenId,
+    pub price: Price,
+    pub size: Qty,
     pub trade_id: String,
+    /// Taker (aggressor) side from the data-api; `None` when the source did not say.
+    #[serde(default)]
+    pub side: Option<Side>,
 }
 
 #[derive(Clone, Debug)]
@@ -333,16 +746,25 @@ pub struct BucketMetrics {
     pub worst_leg_index: usize,
     #[allow(dead_code)]
     pub worst_spread_bps: i32,
-    #[allow(dead_code)]
+    /// Frozen top-3 ask depth of the thinnest leg by that measure, whatever
+    /// `buckets.depth_levels` is; NaN when degraded.
     pub worst_depth3_usdc: f64,
-    #[allow(dead_code)]
     pub is_depth3_degraded: bool,
+    /// Ask levels the bucket was decided on (`buckets.depth_levels`).
+    pub depth_levels: usize,
+    /// Depth over `depth_levels` asks of the leg that decided the bucket; NaN when degraded.
+    /// Equals `worst_depth3_usdc` at the frozen 3.
+    pub worst_depth_usdc: f64,
+    pub classifier: BucketClassifierVersion,
+    /// Per-leg buckets in leg order when `bucket_classifier.per_leg` is on; empty means every
+    /// leg takes the market bucket (the Frozen Spec worst-leg rule).
+    pub leg_buckets: Vec<Bucket>,
 }
 
 #[derive(Clone, Debug)]
 pub struct MarketDef {
-    pub market_id: String,
-    pub token_ids: Vec<String>,
+    pub market_id: MarketId,
+    pub token_ids: Vec<TokenId>,
 }
 
 impl MarketDef {
@@ -369,11 +791,194 @@ pub fn now_ms() -> u64 {
     d.as_millis() as u64
 }
 
+pub fn normalize_ts_ms(ts: u64) -> u64 {
+    // Normalize unix timestamps to milliseconds.
+    //
+    // Supported input units:
+    // - seconds:      ~1.7e9  (10 digits)
+    // - milliseconds: ~1.7e12 (13 digits)
+    // - microseconds: ~1.7e15 (16 digits)
+    // - nanoseconds:  ~1.7e18 (19 digits)
+    //
+    // Note: Phase 1 uses local ingest time (`ingest_ts_ms`) as the canonical timestamp domain for
+    // shadow windows; this normalization is used for diagnostics + exchange_ts_ms only.
+    match ts {
+        // seconds
+        0..=99_999_999_999 => ts.saturating_mul(1_000),
+        // milliseconds
+        100_000_000_000..=99_999_999_999_999 => ts,
+        // microseconds
+        100_000_000_000_000..=99_999_999_999_999_999 => ts / 1_000,
+        // nanoseconds (and anything larger) -> ms
+        _ => ts / 1_000_000,
+    }
+}
+
+/// `trade_id` of a data-api trade; `weak:` when the trade carries no transaction hash.
+pub fn trade_dedup_key(
+    market_id: &str,
+    token_id: &str,
+    ts_ms: u64,
+    price: f64,
+    size: f64,
+    transaction_hash: &str,
+) -> String {
+    let tx = transaction_hash.trim();
+    if !tx.is_empty() {
+        // Do not use bare `transactionHash` as a unique trade ID: a single tx can contain
+        // multiple fills. Include token+ts+price+size to avoid false de-duplication.
+        format!(
+            "tx:{market_id}:{token_id}:{ts_ms}:{tx}:{:016x}:{:016x}",
+            price.to_bits(),
+            size.to_bits()
+        )
+    } else {
+        format!(
+            "weak:{market_id}:{token_id}:{ts_ms}:{:016x}:{:016x}",
+            price.to_bits(),
+            size.to_bits()
+        )
+    }
+}
+
+// UTC date conversion (Howard Hinnant's algorithm).
+// Input: days since 1970-01-01.
+pub fn civil_from_days(days_since_epoch: i64) -> (i32, u32, u32) {
+    let z = days_since_epoch + 719_468;
+    let era = if z >= 0 { z } else { z - 146_096 }.div_euclid(146_097);
+    let doe = z - era * 146_097;
+    let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096).div_euclid(365);
+    let y = yoe + era * 400;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2).div_euclid(153);
+    let d = doy - (153 * mp + 2).div_euclid(5) + 1;
+    let m = mp + if mp < 10 { 3 } else { -9 };
+    let year = y + i64::from(m <= 2);
+    (year as i32, m as u32, d as u32)
+}
+
+/// Whole seconds of a UTC (`Z`) RFC 3339 timestamp; fractional seconds are dropped.
+pub fn parse_rfc3339_to_unix_secs(s: &str) -> Option<u64> {
+    // Accept formats like:
+    // - "2026-02-28T12:00:00Z"
+    // - "2025-01-08T01:33:54.924Z"
+    let (date, time) = s.split_once('T')?;
+    let (y, m, d) = parse_ymd(date)?;
+
+    let time = time.strip_suffix('Z')?;
+    let (hms, _frac) = time.split_once('.').unwrap_or((time, ""));
+    let mut it = hms.split(':');
+    let hh = it.next()?.parse::<u32>().ok()?;
+    let mm = it.next()?.parse::<u32>().ok()?;
+    let ss = it.next()?.parse::<u32>().ok()?;
+
+    let days = days_from_civil(y, m, d)?;
+    let secs = days
+        .checked_mul(86_400)?
+        .checked_add((hh as u64).checked_mul(3600)?)?
+        .checked_add((mm as u64).checked_mul(60)?)?
+        .checked_add(ss as u64)?;
+    Some(secs)
+}
+
+fn parse_ymd(s: &str) -> Option<(i32, u32, u32)> {
+    let mut it = s.split('-');
+    let y = it.next()?.parse::<i32>().ok()?;
+    let m = it.next()?.parse::<u32>().ok()?;
+    let d = it.next()?.parse::<u32>().ok()?;
+    Some((y, m, d))
+}
+
+/// Whole seconds of an HTTP `Date` header (IMF-fixdate, `Sun, 06 Nov 1994 08:49:37 GMT`).
+pub fn parse_http_date_secs(s: &str) -> Option<u64> {
+    const MONTHS: [&str; 12] = [
+        "Jan", "Feb", "Mar", "Apr", "May", "Jun", "Jul", "Aug", "Sep", "Oct", "Nov", "Dec",
+    ];
+    let (_weekday, rest) = s.trim().split_once(", ")?;
+    let mut it = rest.split(' ');
+    let d = it.next()?.parse::<u32>().ok()?;
+    let month = it.next()?;
+    let m = MONTHS.iter().position(|&name| name == month)? as u32 + 1;
+    let y = it.next()?.parse::<i32>().ok()?;
+    let mut hms = it.next()?.split(':');
+    if it.next()? != "GMT" {
+        return None;
+    }
+    let hh = hms.next()?.parse::<u64>().ok()?;
+    let mm = hms.next()?.parse::<u64>().ok()?;
+    let ss = hms.next()?.parse::<u64>().ok()?;
+    Some(days_from_civil(y, m, d)? * 86_400 + hh * 3600 + mm * 60 + ss)
+}
+
+// Inverse of civil_from_days (Howard Hinnant's algorithm).
+// Returns days since 1970-01-01.
+fn days_from_civil(year: i32, month: u32, day: u32) -> Option<u64> {
+    if month == 0 || month > 12 || day == 0 || day > 31 {
+        return None;
+    }
+    let y = i64::from(year) - i64::from(month <= 2);
+    let m = i64::from(month) + if month <= 2 { 9 } else { -3 };
+    let d = i64::from(day);
+    let era = if y >= 0 { y } else { y - 399 }.div_euclid(400);
+    let yoe = y - era * 400;
+    let doy = (153 * m + 2).div_euclid(5) + d - 1;
+    let doe = yoe * 365 + yoe.div_euclid(4) - yoe.div_euclid(100) + doy;
+    let days = era * 146_097 + doe - 719_468;
+    if days < 0 {
+        None
+    } else {
+        Some(days as u64)
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use assert_approx_eq::assert_approx_eq;
 
-    use super::Bps;
+    use super::{
+        normalize_ts_ms, parse_http_date_secs, parse_rfc3339_to_unix_secs, Bps, MarketId, Price,
+        Qty, SignalId, TokenId, TradeTick,
+    };
+
+    #[test]
+    fn normalize_ts_ms_handles_s_ms_us_ns() {
+        // seconds -> ms
+        assert_eq!(normalize_ts_ms(1_700_000_000), 1_700_000_000_000);
+        // ms -> ms
+        assert_eq!(normalize_ts_ms(1_700_000_000_000), 1_700_000_000_000);
+        // us -> ms
+        assert_eq!(normalize_ts_ms(1_700_000_000_000_000), 1_700_000_000_000);
+        // ns -> ms
+        assert_eq!(
+            normalize_ts_ms(1_700_000_000_000_000_000),
+            1_700_000_000_000
+        );
+    }
+
+    #[test]
+    fn parses_http_dates() {
+        assert_eq!(
+            parse_http_date_secs("Sun, 06 Nov 1994 08:49:37 GMT"),
+            parse_rfc3339_to_unix_secs("1994-11-06T08:49:37Z")
+        );
+        assert_eq!(parse_http_date_secs("Sun, 06 Nov 1994 08:49:37 PST"), None);
+        assert_eq!(parse_http_date_secs("1994-11-06T08:49:37Z"), None);
+    }
+
+    #[test]
+    fn parses_rfc3339_with_fractional_seconds() {
+        let secs = parse_rfc3339_to_unix_secs("2025-01-08T01:33:54.924Z").unwrap();
+        let secs2 = parse_rfc3339_to_unix_secs("2025-01-08T01:33:54Z").unwrap();
+        assert_eq!(secs, secs2);
+        assert_eq!(
+            parse_rfc3339_to_unix_secs("1970-01-02T00:00:01Z"),
+            Some(86_401)
+        );
+        assert_eq!(
+            parse_rfc3339_to_unix_secs("2025-01-08T01:33:54+08:00"),
+            None
+        );
+    }
 
     #[test]
     fn bps_apply_cost_and_proceeds() {
@@ -453,4 +1058,81 @@ mod tests {
         assert_eq!(Bps::FEE_MERGE.raw(), 10);
         assert_approx_eq!(Bps::BASIS, 10_000.0);
     }
+
+    #[test]
+    fn price_and_qty_are_exact_micro_units() {
+        // 0.1 + 0.2 != 0.3 in f64, but is exact in micros.
+        let sum = Price::from_f64(0.1).unwrap() + Price::from_f64(0.2).unwrap();
+        assert_eq!(sum, Price::from_f64(0.3).unwrap());
+        assert_eq!(sum.micros(), 300_000);
+        assert_eq!(
+            Price::from_f64(0.4999999).unwrap(),
+            Price::from_f64(0.5).unwrap()
+        );
+        assert!(Price::from_f64(f64::NAN).is_none());
+        assert!(Qty::from_f64(f64::INFINITY).is_none());
+        assert!(Price::from_f64(1e300).is_none());
+        assert!("NaN".parse::<Price>().is_err());
+        assert!(serde_json::from_str::<Qty>("1e300").is_err());
+
+        let ask = Price::from_f64(0.5).unwrap();
+        assert_eq!(
+            ask.offset_bps(Bps::new(20)),
+            Price::from_f64(0.501).unwrap()
+        );
+        assert_eq!(
+            ask.offset_bps(Bps::new(-15)),
+            Price::from_f64(0.49925).unwrap()
+        );
+
+        assert_eq!(Price::from_f64(0.49).unwrap().to_string(), "0.49");
+        assert_eq!(Qty::from_f64(12.0).unwrap().to_string(), "12");
+        assert_eq!(
+            "0.505".parse::<Price>().unwrap(),
+            Price::from_micros(505_000)
+        );
+
+        assert_eq!(
+            Qty::from_micros(i64::MAX) + Qty::from_micros(1),
+            Qty::from_micros(i64::MAX)
+        );
+        assert_eq!(
+            Qty::from_f64(10.0).unwrap().scale_down(0.333_333_3),
+            Qty::from_micros(3_333_333)
+        );
+        assert_eq!(Qty::from_f64(10.0).unwrap().scale_down(f64::NAN), Qty::ZERO);
+
+        assert_eq!(
+            serde_json::to_value(Price::from_f64(0.49).unwrap()).unwrap(),
+            0.49
+        );
+        let q: Qty = serde_json::from_str("2.5").unwrap();
+        assert_eq!(q, Qty::from_micros(2_500_000));
+    }
+
+    #[test]
+    fn ids_are_plain_strings_on_the_wire_and_look_up_by_str() {
+        let tick = TradeTick {
+            ts_ms: 1,
+            ingest_ts_ms: 1,
+            exchange_ts_ms: None,
+            market_id: MarketId::from("0xabc"),
+            token_id: TokenId::from("123"),
+            price: Price::from_f64(0.5).unwrap(),
+            size: Qty::from_f64(1.0).unwrap(),
+            trade_id: "t".to_string(),
+            side: None,
+        };
+        let v = serde_json::to_value(&tick).expect("serialize");
+        assert_eq!(v["market_id"], "0xabc");
+        assert_eq!(v["token_id"], "123");
+        let back: TradeTick = serde_json::from_value(v).expect("deserialize");
+        assert_eq!(back.market_id, "0xabc");
+
+        let mut by_market = std::collections::HashMap::new();
+        by_market.insert(back.market_id.clone(), 1);
+        assert_eq!(by_market.get("0xabc"), Some(&1));
+        assert_eq!(serde_json::to_value(SignalId(7)).expect("serialize"), 7);
+        assert_eq!(SignalId(7).next(), SignalId(8));
+    }
 }
@@ -0,0 +1,72 @@
+//! ULIDs (26 Crockford base32 chars: 48-bit unix ms + 80 random bits) for run ids. They sort
+//! lexicographically by creation time and do not collide across hosts the way `ts + pid` did.
+
+use std::collections::hash_map::RandomState;
+use std::hash::{BuildHasher as _, Hasher as _};
+use std::sync::atomic::{AtomicU64, Ordering};
+
+const ALPHABET: &[u8; 32] = b"0123456789ABCDEFGHJKMNPQRSTVWXYZ";
+pub const ULID_LEN: usize = 26;
+
+/// A new ULID stamped `ts_ms`.
+pub fn new_ulid(ts_ms: u64) -> String {
+    let rand80 = ((random_u64() as u128) << 16 | (random_u64() & 0xffff) as u128) & ((1 << 80) - 1);
+    encode(((ts_ms as u128 & ((1 << 48) - 1)) << 80) | rand80)
+}
+
+/// The unix ms a ULID was stamped with; `None` when `s` is not a ULID.
+pub fn timestamp_ms(s: &str) -> Option<u64> {
+    if s.len() != ULID_LEN {
+        return None;
+    }
+    let mut v: u128 = 0;
+    for b in s.bytes() {
+        let d = ALPHABET.iter().position(|&a| a == b.to_ascii_uppercase())?;
+        v = (v << 5) | d as u128;
+    }
+    Some((v >> 80) as u64)
+}
+
+fn encode(v: u128) -> String {
+    (0..ULID_LEN)
+        .map(|i| ALPHABET[((v >> (125 - 5 * i)) & 0x1f) as usize] as char)
+        .collect()
+}
+
+/// 64 bits from std's per-process random SipHash keys; no RNG dependency.
+fn random_u64() -> u64 {
+    static COUNTER: AtomicU64 = AtomicU64::new(0);
+    let mut h = RandomState::new().build_hasher();
+    h.write_u64(COUNTER.fetch_add(1, Ordering::Relaxed));
+    h.write_u32(std::process::id());
+    h.write_u128(
+        std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .map(|d| d.as_nanos())
+            .unwrap_or_default(),
+    );
+    h.finish()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn ulids_round_trip_their_timestamp_sort_by_time_and_differ() {
+        let a = new_ulid(1_700_000_000_000);
+        let b = new_ulid(1_700_000_000_000);
+        let c = new_ulid(1_700_000_000_001);
+        assert_eq!(a.len(), ULID_LEN);
+        assert!(a.bytes().all(|ch| ALPHABET.contains(&ch)));
+        assert_ne!(a, b);
+        assert!(a[..10] == b[..10] && a < c && b < c);
+        assert_eq!(timestamp_ms(&a), Some(1_700_000_000_000));
+        assert_eq!(
+            timestamp_ms(&c.to_ascii_lowercase()),
+            Some(1_700_000_000_001)
+        );
+        assert_eq!(encode(0), "0".repeat(ULID_LEN));
+        assert_eq!(timestamp_ms("20231114_221320_000000"), None);
+    }
+}
@@ -0,0 +1,252 @@
+//! Human-friendly durations (`"500ms"`, `"2s"`, `"1h30m"`) and sizes (`"512MiB"`, `"2GiB"`) for
+//! config fields. Raw integers keep their old meaning (milliseconds for `*_ms`, MiB for `*_mb`);
+//! the structs still hold and serialize plain integers.
+
+use serde::Deserializer;
+
+const MS_UNITS: [(&str, u64); 6] = [
+    ("ms", 1),
+    ("s", 1_000),
+    ("m", 60_000),
+    ("min", 60_000),
+    ("h", 3_600_000),
+    ("d", 86_400_000),
+];
+
+const BYTE_UNITS: [(&str, u64); 9] = [
+    ("B", 1),
+    ("KB", 1_000),
+    ("MB", 1_000_000),
+    ("GB", 1_000_000_000),
+    ("TB", 1_000_000_000_000),
+    ("KiB", 1 << 10),
+    ("MiB", 1 << 20),
+    ("GiB", 1 << 30),
+    ("TiB", 1 << 40),
+];
+
+const MIB: u64 = 1 << 20;
+
+/// Parses `"1500"`, `"1.5s"`, `"2m"` or `"1h30m"` into milliseconds. A bare number is ms.
+pub fn parse_duration_ms(s: &str) -> Result<u64, String> {
+    parse_with_units(s, &MS_UNITS, "ms")
+        .map_err(|e| format!("invalid duration {s:?}: {e} (e.g. \"500ms\", \"2s\", \"1h30m\")"))
+}
+
+/// Parses `"512MiB"`, `"1.5GiB"` or `"100MB"` into bytes. A bare number is bytes.
+pub fn parse_size_bytes(s: &str) -> Result<u64, String> {
+    parse_with_units(s, &BYTE_UNITS, "B")
+        .map_err(|e| format!("invalid size {s:?}: {e} (e.g. \"512MiB\", \"2GiB\")"))
+}
+
+/// `<number><unit>` segments summed; unit names are matched exactly (`m` is minutes, `MB` is
+/// 10^6 bytes). The total must be a whole number of the base unit. Decimals are kept as an
+/// integer mantissa and a power of ten, so `0.07m` or `1.001s` are exact rather than rounded.
+fn parse_with_units(s: &str, units: &[(&str, u64)], bare: &str) -> Result<u64, String> {
+    let s = s.trim();
+    if s.is_empty() {
+        return Err("empty".to_string());
+    }
+    let out_of_range = || "out of range".to_string();
+    let mut rest = s;
+    let mut total: u128 = 0;
+    while !rest.is_empty() {
+        let num_len = rest
+            .find(|c: char| !(c.is_ascii_digit() || c == '.' || c == '_'))
+            .unwrap_or(rest.len());
+        let (num, tail) = rest.split_at(num_len);
+        let tail = tail.trim_start();
+        let (mantissa, exp) = parse_decimal(&num.replace('_', ""))
+            .ok_or_else(|| format!("expected a number at {rest:?}"))?;
+        let unit_len = tail
+            .find(|c: char| !c.is_ascii_alphabetic())
+            .unwrap_or(tail.len());
+        let (unit, tail) = tail.split_at(unit_len);
+        let unit = if unit.is_empty() { bare } else { unit };
+        let (_, scale) = units
+            .iter()
+            .find(|(name, _)| *name == unit)
+            .ok_or_else(|| format!("unknown unit {unit:?}"))?;
+        let scaled = mantissa
+            .checked_mul(u128::from(*scale))
+            .ok_or_else(out_of_range)?;
+        let divisor = 10u128.checked_pow(exp).ok_or_else(out_of_range)?;
+        if scaled % divisor != 0 {
+            return Err(format!("not a whole number of {bare}"));
+        }
+        total = total
+            .checked_add(scaled / divisor)
+            .ok_or_else(out_of_range)?;
+        rest = tail.trim_start();
+    }
+    u64::try_from(total).map_err(|_| out_of_range())
+}
+
+/// `"1.250"` -> `(125, 2)`: the digits without the point and the count of significant decimals.
+fn parse_decimal(num: &str) -> Option<(u128, u32)> {
+    let (int, frac) = num.split_once('.').unwrap_or((num, ""));
+    let frac = frac.trim_end_matches('0');
+    if !num.bytes().any(|b| b.is_ascii_digit())
+        || !int.bytes().chain(frac.bytes()).all(|b| b.is_ascii_digit())
+    {
+        return None;
+    }
+    let mut mantissa: u128 = 0;
+    for b in int.bytes().chain(frac.bytes()) {
+        mantissa = mantissa
+            .checked_mul(10)?
+            .checked_add(u128::from(b - b'0'))?;
+    }
+    Some((mantissa, u32::try_from(frac.len()).ok()?))
+}
+
+/// `ms` in the largest unit that divides it exactly: `1100ms`, `2s`, `90m`, `0ms`.
+pub fn fmt_ms(ms: u64) -> String {
+    for (name, scale) in [
+        ("d", 86_400_000),
+        ("h", 3_600_000),
+        ("m", 60_000),
+        ("s", 1_000),
+    ] {
+        if ms != 0 && ms.is_multiple_of(scale) {
+            return format!("{}{name}", ms / scale);
+        }
+    }
+    format!("{ms}ms")
+}
+
+/// An integer in the field's base unit, or a string for `parse`.
+struct IntOrUnits {
+    what: &'static str,
+    parse: fn(&str) -> Result<u64, String>,
+}
+
+impl serde::de::Visitor<'_> for IntOrUnits {
+    type Value = u64;
+
+    fn expecting(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "a non-negative integer or a {} string", self.what)
+    }
+
+    fn visit_u64<E: serde::de::Error>(self, v: u64) -> Result<u64, E> {
+        Ok(v)
+    }
+
+    fn visit_i64<E: serde::de::Error>(self, v: i64) -> Result<u64, E> {
+        u64::try_from(v).map_err(|_| E::invalid_value(serde::de::Unexpected::Signed(v), &self))
+    }
+
+    fn visit_str<E: serde::de::Error>(self, v: &str) -> Result<u64, E> {
+        (self.parse)(v).map_err(E::custom)
+    }
+}
+
+/// `deserialize_with` for `*_ms` fields: an integer (ms) or a duration string.
+pub fn de_duration_ms<'de, D: Deserializer<'de>>(d: D) -> Result<u64, D::Error> {
+    d.deserialize_any(IntOrUnits {
+        what: "duration",
+        parse: parse_duration_ms,
+    })
+}
+
+/// [`de_duration_ms`] for optional fields.
+pub fn de_opt_duration_ms<'de, D: Deserializer<'de>>(d: D) -> Result<Option<u64>, D::Error> {
+    de_duration_ms(d).map(Some)
+}
+
+/// `deserialize_with` for `*_mb` fields: an integer (MiB) or a size string that is a whole
+/// number of MiB.
+pub fn de_size_mib<'de, D: Deserializer<'de>>(d: D) -> Result<u64, D::Error> {
+    d.deserialize_any(IntOrUnits {
+        what: "size",
+        parse: |s| {
+            let bytes = parse_size_bytes(s)?;
+            if !bytes.is_multiple_of(MIB) {
+                return Err(format!("size {s:?} is not a whole number of MiB"));
+            }
+            Ok(bytes / MIB)
+        },
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use serde::Deserialize;
+
+    use super::*;
+
+    #[test]
+    fn durations_parse_to_ms() {
+        assert_eq!(parse_duration_ms("1500"), Ok(1500));
+        assert_eq!(parse_duration_ms("500ms"), Ok(500));
+        assert_eq!(parse_duration_ms("2s"), Ok(2_000));
+        assert_eq!(parse_duration_ms("1.5s"), Ok(1_500));
+        assert_eq!(parse_duration_ms("1h30m"), Ok(5_400_000));
+        assert_eq!(parse_duration_ms("14d"), Ok(14 * 86_400_000));
+        assert_eq!(parse_duration_ms(" 2 min "), Ok(120_000));
+        assert!(parse_duration_ms("").is_err());
+        assert!(parse_duration_ms("2 sec").is_err());
+        assert!(parse_duration_ms("0.5ms").is_err());
+        assert!(parse_duration_ms("-1s").is_err());
+        assert!(parse_duration_ms("1.2.3s").is_err());
+        assert!(parse_duration_ms(".s").is_err());
+    }
+
+    #[test]
+    fn decimal_durations_are_exact() {
+        assert_eq!(parse_duration_ms("0.07m"), Ok(4_200));
+        assert_eq!(parse_duration_ms("0.29h"), Ok(1_044_000));
+        assert_eq!(parse_duration_ms("1.001s"), Ok(1_001));
+        assert_eq!(parse_duration_ms("2.50s"), Ok(2_500));
+        assert_eq!(parse_duration_ms(".5s"), Ok(500));
+        for ms in 1..10_000u64 {
+            let s = format!("{}.{:03}s", ms / 1000, ms % 1000);
+            assert_eq!(parse_duration_ms(&s), Ok(ms), "{s}");
+        }
+        assert!(parse_duration_ms("0.0001s").is_err_and(|e| e.contains("not a whole number")));
+        assert!(
+            parse_duration_ms("99999999999999999999d").is_err_and(|e| e.contains("out of range"))
+        );
+    }
+
+    #[test]
+    fn sizes_parse_to_bytes() {
+        assert_eq!(parse_size_bytes("512MiB"), Ok(512 << 20));
+        assert_eq!(parse_size_bytes("1.5GiB"), Ok(3 << 29));
+        assert_eq!(parse_size_bytes("100MB"), Ok(100_000_000));
+        assert_eq!(parse_size_bytes("4096"), Ok(4096));
+        assert!(parse_size_bytes("1mib").is_err());
+    }
+
+    #[test]
+    fn formats_pick_the_largest_exact_unit() {
+        assert_eq!(fmt_ms(0), "0ms");
+        assert_eq!(fmt_ms(1_100), "1100ms");
+        assert_eq!(fmt_ms(2_000), "2s");
+        assert_eq!(fmt_ms(5_400_000), "90m");
+    }
+
+    #[test]
+    fn config_fields_take_integers_or_strings() {
+        #[derive(Deserialize)]
+        struct T {
+            #[serde(deserialize_with = "de_duration_ms")]
+            a_ms: u64,
+            #[serde(default, deserialize_with = "de_opt_duration_ms")]
+            b_ms: Option<u64>,
+            #[serde(deserialize_with = "de_size_mib")]
+            c_mb: u64,
+        }
+        let t: T = toml::from_str("a_ms = 250\nb_ms = \"2s\"\nc_mb = \"1GiB\"\n").unwrap();
+        assert_eq!((t.a_ms, t.b_ms, t.c_mb), (250, Some(2_000), 1024));
+        let t: T = toml::from_str("a_ms = \"1m\"\nc_mb = 300\n").unwrap();
+        assert_eq!((t.a_ms, t.b_ms, t.c_mb), (60_000, None, 300));
+
+        let err = toml::from_str::<T>("a_ms = \"2x\"\nc_mb = 1\n")
+            .err()
+            .unwrap()
+            .to_string();
+        assert!(err.contains("unknown unit"), "{err}");
+        assert!(toml::from_str::<T>("a_ms = 1\nc_mb = \"100MB\"\n").is_err());
+    }
+}
@@ -0,0 +1,345 @@
+//! Authenticated user-channel WS (`<ws_base>/ws/user`): our own order and trade events. Every
+//! frame is kept verbatim in `user_events.jsonl`; trade events are decoded into [`UserFill`]s
+//! for the sniper, which reconciles them against what the gateway reported. The channel is a
+//! read-only subscription, opened only behind the live gateway with its API creds.
+
+use std::collections::HashMap;
+
+use serde::Deserialize;
+
+use crate::types::{Side, TokenId};
+use crate::ws_msg::lenient_f64;
+
+/// One of our orders matched in a trade, as the user channel reports it. A trade is re-sent as
+/// its `status` moves on (`MATCHED` → `MINED` → `CONFIRMED`, or `RETRYING` / `FAILED`).
+#[derive(Debug, Clone, PartialEq)]
+pub struct UserFill {
+    pub ts_recv_ms: u64,
+    pub trade_id: String,
+    pub order_id: String,
+    pub asset_id: TokenId,
+    pub side: Option<Side>,
+    pub price: f64,
+    pub size: f64,
+    pub status: String,
+    /// Our order was resting (maker) rather than taking.
+    pub maker: bool,
+}
+
+impl UserFill {
+    pub fn failed(&self) -> bool {
+        self.status.eq_ignore_ascii_case("FAILED")
+    }
+}
+
+#[derive(Deserialize)]
+struct TradeEvent {
+    #[serde(default)]
+    event_type: String,
+    #[serde(default)]
+    id: String,
+    #[serde(default)]
+    taker_order_id: String,
+    #[serde(default)]
+    asset_id: String,
+    #[serde(default)]
+    side: String,
+    #[serde(default, deserialize_with = "lenient_f64")]
+    price: Option<f64>,
+    #[serde(default, deserialize_with = "lenient_f64")]
+    size: Option<f64>,
+    #[serde(default)]
+    status: String,
+    /// API key of the taker.
+    #[serde(default)]
+    owner: String,
+    #[serde(default)]
+    maker_orders: Vec<MakerOrder>,
+}
+
+#[derive(Deserialize)]
+struct MakerOrder {
+    #[serde(default)]
+    order_id: String,
+    #[serde(default)]
+    asset_id: String,
+    #[serde(default, deserialize_with = "lenient_f64")]
+    matched_amount: Option<f64>,
+    #[serde(default, deserialize_with = "lenient_f64")]
+    price: Option<f64>,
+    #[serde(default)]
+    owner: String,
+}
+
+/// Our fills in a user-channel frame (one event or an array of them). The taker side counts
+/// when `owner` is `api_key` (or unset); maker orders only when theirs is. Non-trade events and
+/// undecodable ones yield nothing.
+#[cfg_attr(not(feature = "live"), allow(dead_code))]
+pub fn parse_user_fills(txt: &str, api_key: &str, ts_recv_ms: u64) -> Vec<UserFill> {
+    let Ok(v) = serde_json::from_str::<serde_json::Value>(txt) else {
+        return Vec::new();
+    };
+    let events = match v {
+        serde_json::Value::Array(a) => a,
+        other => vec![other],
+    };
+    let mut out = Vec::new();
+    for ev in events {
+        let Ok(t) = serde_json::from_value::<TradeEvent>(ev) else {
+            continue;
+        };
+        if t.event_type != "trade" {
+            continue;
+        }
+        let side = Side::parse(&t.side);
+        if !t.taker_order_id.is_empty() && (t.owner.is_empty() || t.owner == api_key) {
+            out.push(UserFill {
+                ts_recv_ms,
+                trade_id: t.id.clone(),
+                order_id: t.taker_order_id.clone(),
+                asset_id: TokenId::from(t.asset_id.clone()),
+                side,
+                price: t.price.unwrap_or(0.0),
+                size: t.size.unwrap_or(0.0),
+                status: t.status.clone(),
+                maker: false,
+            });
+        }
+        for m in t.maker_orders.iter().filter(|m| m.owner == api_key) {
+            out.push(UserFill {
+                ts_recv_ms,
+                trade_id: t.id.clone(),
+                order_id: m.order_id.clone(),
+                asset_id: TokenId::from(m.asset_id.clone()),
+                // The maker sits on the other side of the taker's trade.
+                side: side.map(Side::opposite),
+                price: m.price.unwrap_or(0.0),
+                size: m.matched_amount.unwrap_or(0.0),
+                status: t.status.clone(),
+                maker: true,
+            });
+        }
+    }
+    out
+}
+
+/// Matched size per order from the user channel; each (trade, order) counts once however many
+/// status updates it gets, and drops out again if the trade `FAILED`.
+#[derive(Debug, Default)]
+pub struct FillLedger {
+    by_trade: HashMap<(String, String), f64>,
+}
+
+impl FillLedger {
+    /// Applies `fill`; true when it changed the order's matched size.
+    pub fn apply(&mut self, fill: &UserFill) -> bool {
+        let key = (fill.trade_id.clone(), fill.order_id.clone());
+        if fill.failed() {
+            return self.by_trade.remove(&key).is_some();
+        }
+        self.by_trade.insert(key, fill.size) != Some(fill.size)
+    }
+
+    pub fn filled(&self, order_id: &str) -> f64 {
+        self.by_trade
+            .iter()
+            .filter(|((_, o), _)| o == order_id)
+            .map(|(_, size)| size)
+            .sum()
+    }
+
+    pub fn orders(&self) -> usize {
+        let mut ids: Vec<&str> = self.by_trade.keys().map(|(_, o)| o.as_str()).collect();
+        ids.sort_unstable();
+        ids.dedup();
+        ids.len()
+    }
+}
+
+#[cfg(feature = "live")]
+pub use ws::run_user_ws;
+
+#[cfg(feature = "live")]
+mod ws {
+    use std::path::PathBuf;
+    use std::time::Duration;
+
+    use anyhow::Context as _;
+    use futures_util::{SinkExt as _, StreamExt as _};
+    use tokio::sync::{mpsc, watch};
+    use tokio_tungstenite::tungstenite::Message;
+    use tracing::{info, warn};
+
+    use super::{parse_user_fills, UserFill};
+    use crate::clob::ApiCreds;
+    use crate::config::Config;
+    use crate::errors::{code_of, BoxError, ErrorContext as _, RazorError};
+    use crate::recorder::JsonlAppender;
+    use crate::types::now_ms;
+
+    /// Keeps the user channel subscribed until shutdown, reconnecting with backoff: frames go
+    /// to `user_events_path`, our fills to `fill_tx`.
+    pub async fn run_user_ws(
+        cfg: Config,
+        creds: ApiCreds,
+        user_events_path: PathBuf,
+        fill_tx: mpsc::Sender<UserFill>,
+        shutdown: watch::Receiver<bool>,
+    ) -> anyhow::Result<()> {
+        let mut out =
+            JsonlAppender::open(&user_events_path).io_context("open user_events.jsonl")?;
+        let ws_url = format!("{}/ws/user", cfg.polymarket.ws_base.trim_end_matches('/'));
+
+        let mut backoff = Duration::from_secs(1);
+        loop {
+            if *shutdown.borrow() {
+                break;
+            }
+            match run_once(&cfg, &ws_url, &creds, &mut out, &fill_tx, shutdown.clone()).await {
+                Ok(()) => backoff = Duration::from_secs(1),
+                Err(e) => {
+                    warn!(code = %code_of(&e), error = %format!("{e:#}"), "user ws error; reconnecting");
+                    tokio::time::sleep(backoff).await;
+                    backoff = (backoff * 2).min(Duration::from_secs(60));
+                }
+            }
+        }
+        out.flush_and_sync().io_context("flush user_events.jsonl")?;
+        Ok(())
+    }
+
+    async fn run_once(
+        cfg: &Config,
+        ws_url: &str,
+        creds: &ApiCreds,
+        out: &mut JsonlAppender,
+        fill_tx: &mpsc::Sender<UserFill>,
+        mut shutdown: watch::Receiver<bool>,
+    ) -> anyhow::Result<()> {
+        info!(%ws_url, "connecting user ws");
+        let connect_err = |source: BoxError| RazorError::WsConnect {
+            url: ws_url.to_string(),
+            source,
+        };
+        let (ws, _) = tokio::time::timeout(
+            Duration::from_millis(cfg.polymarket.ws_connect_timeout_ms),
+            tokio_tungstenite::connect_async(ws_url),
+        )
+        .await
+        .map_err(|e| connect_err(e.into()))?
+        .map_err(|e| connect_err(e.into()))?;
+        let (mut sink, mut stream) = ws.split();
+        let write_timeout = Duration::from_millis(cfg.polymarket.ws_write_timeout_ms);
+
+        // No `markets`: every market the key trades in.
+        let subscribe_msg = serde_json::json!({
+            "auth": {
+                "apiKey": creds.api_key,
+                "secret": creds.api_secret,
+                "passphrase": creds.api_passphrase,
+            },
+            "type": "user",
+        });
+        send(&mut sink, subscribe_msg.to_string(), write_timeout)
+            .await
+            .context("send user subscribe")?;
+
+        let mut ping = tokio::time::interval(Duration::from_secs(10));
+        ping.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+        loop {
+            tokio::select! {
+                _ = shutdown.changed() => {
+                    if *shutdown.borrow() {
+                        return Ok(());
+                    }
+                }
+                _ = ping.tick() => {
+                    send(&mut sink, "PING".to_string(), write_timeout)
+                        .await
+                        .context("send ping")?;
+                }
+                msg = stream.next() => {
+                    let Some(msg) = msg else {
+                        return Err(RazorError::ws_stream("user ws stream ended").into());
+                    };
+                    let txt = match msg.ws_context("user ws read")? {
+                        Message::Text(t) => t.to_string(),
+                        Message::Binary(b) => String::from_utf8_lossy(&b).to_string(),
+                        Message::Close(frame) => {
+                            return Err(RazorError::ws_stream(format!("user ws close: {frame:?}")).into());
+                        }
+                        _ => continue,
+                    };
+                    if txt.trim() == "PONG" {
+                        continue;
+                    }
+                    let ts_recv_ms = now_ms();
+                    let line = serde_json::json!({
+                        "ts_recv_ms": ts_recv_ms,
+                        "event": serde_json::from_str::<serde_json::Value>(&txt)
+                            .unwrap_or(serde_json::Value::String(txt.clone())),
+                    });
+                    if let Err(e) = out.write_line(&line.to_string()) {
+                        warn!(error = %e, "user_events write failed");
+                    }
+                    for fill in parse_user_fills(&txt, &creds.api_key, ts_recv_ms) {
+                        if fill_tx.send(fill).await.is_err() {
+                            return Ok(());
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    async fn send<S>(sink: &mut S, txt: String, timeout: Duration) -> Result<(), RazorError>
+    where
+        S: futures_util::Sink<Message> + Unpin,
+        S::Error: std::error::Error + Send + Sync + 'static,
+    {
+        tokio::time::timeout(timeout, sink.send(Message::Text(txt.into())))
+            .await
+            .ws_context("ws send timeout")?
+            .ws_context("ws send error")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_taker_and_own_maker_fills_and_ledgers_them_once() {
+        let frame = r#"[{"event_type":"trade","id":"t1","taker_order_id":"0xtaker","asset_id":"A",
+            "side":"BUY","price":"0.57","size":"10","status":"MATCHED","owner":"me",
+            "maker_orders":[{"order_id":"0xmine","asset_id":"A","matched_amount":"4","price":"0.57","owner":"me"},
+                            {"order_id":"0xother","asset_id":"A","matched_amount":"6","price":"0.57","owner":"them"}]},
+            {"event_type":"order","id":"0xtaker","type":"PLACEMENT"}]"#;
+        let fills = parse_user_fills(frame, "me", 7);
+        assert_eq!(fills.len(), 2);
+        assert_eq!(fills[0].order_id, "0xtaker");
+        assert_eq!(fills[0].side, Some(Side::Buy));
+        assert!(!fills[0].maker && (fills[0].size - 10.0).abs() < 1e-12);
+        assert_eq!(fills[1].order_id, "0xmine");
+        assert_eq!(fills[1].side, Some(Side::Sell));
+        assert!(fills[1].maker && (fills[1].size - 4.0).abs() < 1e-12);
+        assert!(parse_user_fills(frame, "nobody", 7).is_empty());
+        assert!(parse_user_fills("PONG", "me", 7).is_empty());
+
+        let mut ledger = FillLedger::default();
+        assert!(ledger.apply(&fills[0]));
+        let confirmed = UserFill {
+            status: "CONFIRMED".to_string(),
+            ..fills[0].clone()
+        };
+        assert!(!ledger.apply(&confirmed));
+        assert!((ledger.filled("0xtaker") - 10.0).abs() < 1e-12);
+        let failed = UserFill {
+            status: "FAILED".to_string(),
+            ..fills[0].clone()
+        };
+        assert!(ledger.apply(&failed));
+        assert_eq!(ledger.filled("0xtaker"), 0.0);
+        assert_eq!(ledger.orders(), 0);
+    }
+}
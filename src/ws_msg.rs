@@ -0,0 +1,434 @@
+//! Typed market-channel WS messages. Frames are decoded straight into [`WsEvent`]s that borrow
+//! their strings from the frame text, instead of building a `serde_json::Value` tree per message.
+//! Prices and sizes arrive either as numbers or as numeric strings; both are accepted.
+//!
+//! A frame that fails the typed decode (a malformed event in a batch, an unexpected field type)
+//! takes the raw path: it is parsed as a `serde_json::Value` and each event is decoded on its own,
+//! so one bad event does not drop the rest of the frame. Unknown `event_type`s decode to
+//! [`WsEvent::Other`]; they are still kept verbatim in `raw_ws.jsonl`.
+
+use std::borrow::Cow;
+
+use serde::de::{self, Deserializer, Unexpected, Visitor};
+use serde::Deserialize;
+
+#[derive(Debug)]
+pub enum WsEvent<'a> {
+    Book(BookEvent<'a>),
+    PriceChange(PriceChangeEvent<'a>),
+    LastTrade(LastTradeEvent<'a>),
+    /// `tick_size_change`, ...: not used by the pipeline.
+    Other,
+}
+
+/// Every field any handled event carries, decoded in one pass. An internally tagged enum would
+/// buffer the whole object before looking at `event_type`, which costs most of the gain over
+/// `serde_json::Value`.
+#[derive(Deserialize)]
+struct AnyEvent<'a> {
+    #[serde(borrow)]
+    event_type: Cow<'a, str>,
+    // Plain `Cow` fields (empty when absent): serde only borrows `Cow<str>` when it is not
+    // wrapped in an `Option`.
+    #[serde(default, borrow)]
+    asset_id: Cow<'a, str>,
+    #[serde(default, borrow)]
+    market: Cow<'a, str>,
+    #[serde(default)]
+    bids: Vec<Level>,
+    #[serde(default)]
+    asks: Vec<Level>,
+    #[serde(default, borrow)]
+    price_changes: Vec<PriceChange<'a>>,
+    #[serde(default, deserialize_with = "lenient_f64")]
+    price: Option<f64>,
+    #[serde(default, deserialize_with = "lenient_f64")]
+    size: Option<f64>,
+    #[serde(default, borrow)]
+    side: Cow<'a, str>,
+    #[serde(default, deserialize_with = "lenient_f64")]
+    timestamp: Option<f64>,
+    #[serde(default, borrow)]
+    transaction_hash: Cow<'a, str>,
+}
+
+impl<'de> Deserialize<'de> for WsEvent<'de> {
+    fn deserialize<D: Deserializer<'de>>(d: D) -> Result<Self, D::Error> {
+        let ev = AnyEvent::deserialize(d)?;
+        Ok(match ev.event_type.as_ref() {
+            "book" if ev.asset_id.is_empty() => return Err(de::Error::missing_field("asset_id")),
+            "book" => WsEvent::Book(BookEvent {
+                asset_id: ev.asset_id,
+                market: (!ev.market.is_empty()).then_some(ev.market),
+                bids: ev.bids,
+                asks: ev.asks,
+            }),
+            "price_change" => WsEvent::PriceChange(PriceChangeEvent {
+                price_changes: ev.price_changes,
+            }),
+            "last_trade_price" if ev.asset_id.is_empty() => {
+                return Err(de::Error::missing_field("asset_id"))
+            }
+            "last_trade_price" => WsEvent::LastTrade(LastTradeEvent {
+                asset_id: ev.asset_id,
+                price: ev.price,
+                size: ev.size,
+                side: ev.side,
+                timestamp: ev
+                    .timestamp
+                    .filter(|t| t.is_finite() && *t > 0.0)
+                    .map(|t| t as u64),
+                transaction_hash: ev.transaction_hash,
+            }),
+            _ => WsEvent::Other,
+        })
+    }
+}
+
+/// Full L2 snapshot of one token's book.
+#[derive(Debug, Deserialize)]
+pub struct BookEvent<'a> {
+    #[serde(borrow)]
+    pub asset_id: Cow<'a, str>,
+    /// Condition id as sent by the WS; token->market mapping stays the authority.
+    #[serde(default, borrow)]
+    pub market: Option<Cow<'a, str>>,
+    #[serde(default)]
+    pub bids: Vec<Level>,
+    #[serde(default)]
+    pub asks: Vec<Level>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct PriceChangeEvent<'a> {
+    #[serde(default, borrow)]
+    pub price_changes: Vec<PriceChange<'a>>,
+}
+
+/// One changed level of a token's book and the top of book after it.
+#[derive(Debug, Deserialize)]
+pub struct PriceChange<'a> {
+    #[serde(borrow)]
+    pub asset_id: Cow<'a, str>,
+    /// The level's new aggregate size (0 = removed) on `side`: `BUY` bids, `SELL` asks.
+    #[serde(default, deserialize_with = "lenient_f64")]
+    pub price: Option<f64>,
+    #[serde(default, deserialize_with = "lenient_f64")]
+    pub size: Option<f64>,
+    #[serde(default, borrow)]
+    pub side: Cow<'a, str>,
+    #[serde(default, deserialize_with = "lenient_f64")]
+    pub best_bid: Option<f64>,
+    #[serde(default, deserialize_with = "lenient_f64")]
+    pub best_ask: Option<f64>,
+}
+
+/// One fill on a subscribed token (the market channel's trade stream).
+#[derive(Debug)]
+pub struct LastTradeEvent<'a> {
+    pub asset_id: Cow<'a, str>,
+    pub price: Option<f64>,
+    pub size: Option<f64>,
+    /// Taker side, `BUY` / `SELL`.
+    pub side: Cow<'a, str>,
+    /// Exchange time as sent (ms); normalized by the consumer.
+    pub timestamp: Option<u64>,
+    /// Empty when the event does not carry one.
+    pub transaction_hash: Cow<'a, str>,
+}
+
+#[derive(Debug, Clone, Copy, Deserialize)]
+pub struct Level {
+    #[serde(default, deserialize_with = "lenient_f64")]
+    pub price: Option<f64>,
+    #[serde(default, deserialize_with = "lenient_f64")]
+    pub size: Option<f64>,
+}
+
+/// A number, a numeric string or null.
+pub(crate) fn lenient_f64<'de, D: Deserializer<'de>>(d: D) -> Result<Option<f64>, D::Error> {
+    struct LenientF64;
+
+    impl<'de> Visitor<'de> for LenientF64 {
+        type Value = Option<f64>;
+
+        fn expecting(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+            f.write_str("a number, a numeric string or null")
+        }
+
+        fn visit_f64<E: de::Error>(self, v: f64) -> Result<Self::Value, E> {
+            Ok(Some(v))
+        }
+
+        fn visit_u64<E: de::Error>(self, v: u64) -> Result<Self::Value, E> {
+            Ok(Some(v as f64))
+        }
+
+        fn visit_i64<E: de::Error>(self, v: i64) -> Result<Self::Value, E> {
+            Ok(Some(v as f64))
+        }
+
+        fn visit_str<E: de::Error>(self, v: &str) -> Result<Self::Value, E> {
+            v.parse::<f64>()
+                .map(Some)
+                .map_err(|_| E::invalid_value(Unexpected::Str(v), &self))
+        }
+
+        fn visit_unit<E: de::Error>(self) -> Result<Self::Value, E> {
+            Ok(None)
+        }
+
+        fn visit_none<E: de::Error>(self) -> Result<Self::Value, E> {
+            Ok(None)
+        }
+
+        fn visit_some<D: Deserializer<'de>>(self, d: D) -> Result<Self::Value, D::Error> {
+            d.deserialize_any(self)
+        }
+    }
+
+    d.deserialize_any(LenientF64)
+}
+
+/// Decodes one WS text frame (a single event or a batch) and hands each event to `on_event`.
+/// Returns the decode errors of the events the raw path had to skip (a non-JSON frame is one
+/// error); an error from `on_event` stops the frame.
+pub fn for_each_event<E>(
+    txt: &str,
+    mut on_event: impl FnMut(WsEvent<'_>) -> Result<(), E>,
+) -> Result<Vec<serde_json::Error>, E> {
+    let typed = if txt.trim_start().starts_with('[') {
+        serde_json::from_str::<Vec<WsEvent<'_>>>(txt)
+    } else {
+        serde_json::from_str::<WsEvent<'_>>(txt).map(|ev| vec![ev])
+    };
+    if let Ok(events) = typed {
+        for ev in events {
+            on_event(ev)?;
+        }
+        return Ok(Vec::new());
+    }
+
+    let raw: serde_json::Value = match serde_json::from_str(txt) {
+        Ok(v) => v,
+        Err(e) => return Ok(vec![e]),
+    };
+    let items = match &raw {
+        serde_json::Value::Array(items) => items.as_slice(),
+        v => std::slice::from_ref(v),
+    };
+    let mut skipped = Vec::new();
+    for item in items {
+        // Objects without an event_type (subscription acks, ...) carry nothing for us.
+        if item.get("event_type").is_none() {
+            continue;
+        }
+        match WsEvent::deserialize(item) {
+            Ok(ev) => on_event(ev)?,
+            Err(e) => skipped.push(e),
+        }
+    }
+    Ok(skipped)
+}
+
+/// Latest exchange `timestamp` (normalized to ms) among the events of a frame; `None` for frames
+/// without one (acks, `PONG`, undecodable text). `raw_ws.jsonl` keeps frames verbatim, so this
+/// is the only clock a replay of it has.
+#[allow(dead_code)]
+pub fn frame_ts_ms(txt: &str) -> Option<u64> {
+    let raw: serde_json::Value = serde_json::from_str(txt).ok()?;
+    let items = match &raw {
+        serde_json::Value::Array(items) => items.as_slice(),
+        v => std::slice::from_ref(v),
+    };
+    items
+        .iter()
+        .filter_map(|item| match item.get("timestamp")? {
+            serde_json::Value::String(s) => s.parse::<u64>().ok(),
+            v => v.as_u64(),
+        })
+        .map(crate::types::normalize_ts_ms)
+        .max()
+}
+
+#[derive(Debug, Clone, Copy)]
+pub enum PriceSide {
+    Bid,
+    Ask,
+}
+
+/// Best (highest bid / lowest ask) level with a positive price; size is 0 when missing.
+pub fn best_level(levels: &[Level], side: PriceSide) -> Option<(f64, f64)> {
+    let mut best: Option<(f64, f64)> = None;
+    for lvl in levels {
+        let Some(px) = lvl.price.filter(|v| v.is_finite() && *v > 0.0) else {
+            continue;
+        };
+
+        let sz = lvl
+            .size
+            .filter(|s| s.is_finite() && *s > 0.0)
+            .unwrap_or(0.0);
+
+        best = match (best, side) {
+            (None, _) => Some((px, sz)),
+            (Some((cur_px, cur_sz)), PriceSide::Bid) => {
+                if px > cur_px {
+                    Some((px, sz))
+                } else {
+                    Some((cur_px, cur_sz))
+                }
+            }
+            (Some((cur_px, cur_sz)), PriceSide::Ask) => {
+                if px < cur_px {
+                    Some((px, sz))
+                } else {
+                    Some((cur_px, cur_sz))
+                }
+            }
+        };
+    }
+    best
+}
+
+/// USDC notional of the 3 lowest-priced asks.
+pub fn ask_depth3_usdc(levels: &[Level]) -> f64 {
+    let mut best = [(f64::INFINITY, 0.0f64); 3];
+    for lvl in levels {
+        let Some(px) = lvl.price.filter(|v| v.is_finite() && *v > 0.0) else {
+            continue;
+        };
+        let Some(sz) = lvl.size.filter(|v| v.is_finite() && *v > 0.0) else {
+            continue;
+        };
+
+        if px >= best[2].0 {
+            continue;
+        }
+
+        best[2] = (px, sz);
+        best.sort_by(|a, b| a.0.partial_cmp(&b.0).unwrap_or(std::cmp::Ordering::Equal));
+    }
+
+    best.iter()
+        .filter(|(px, _)| px.is_finite())
+        .map(|(px, sz)| px * sz)
+        .sum()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use assert_approx_eq::assert_approx_eq;
+
+    fn levels(json: &str) -> Vec<Level> {
+        serde_json::from_str(json).expect("levels")
+    }
+
+    #[test]
+    fn ws_book_level_parses_numeric_and_string_fields() {
+        let bids = levels(r#"[{"price": 0.49, "size": 1.0}, {"price": "0.5", "size": "2"}]"#);
+        let (px, sz) = best_level(&bids, PriceSide::Bid).expect("best bid");
+        assert_approx_eq!(px, 0.5);
+        assert_approx_eq!(sz, 2.0);
+
+        let asks = levels(r#"[{"price": 0.6, "size": 1.0}, {"price": "0.55", "size": "2"}]"#);
+        let (px, sz) = best_level(&asks, PriceSide::Ask).expect("best ask");
+        assert_approx_eq!(px, 0.55);
+        assert_approx_eq!(sz, 2.0);
+    }
+
+    #[test]
+    fn ws_book_depth3_parses_numeric_and_sums_top3() {
+        let asks = levels(
+            r#"[
+                {"price": 0.6, "size": 10.0},
+                {"price": "0.55", "size": 20.0},
+                {"price": 0.50, "size": "30"},
+                {"price": 0.65, "size": 40.0}
+            ]"#,
+        );
+        // 6 + 11 + 15; the 0.65 level is not in the top 3.
+        assert_approx_eq!(ask_depth3_usdc(&asks), 32.0);
+    }
+
+    #[test]
+    fn frames_decode_typed_borrowed_and_fall_back_per_event() {
+        let batch = r#"[
+            {"event_type":"book","asset_id":"t1","market":"m1","bids":[{"price":"0.49","size":"5"}],"asks":[],"hash":"0x1"},
+            {"event_type":"price_change","market":"m1","price_changes":[{"asset_id":"t2","price":"0.5","best_bid":"0.5","best_ask":null}]},
+            {"event_type":"last_trade_price","asset_id":"t1","market":"m1","price":"0.5","size":"12.5","side":"SELL","timestamp":"1760000000123"},
+            {"event_type":"tick_size_change","asset_id":"t1"}
+        ]"#;
+        let mut seen = Vec::new();
+        let skipped = for_each_event(batch, |ev| {
+            match ev {
+                WsEvent::Book(b) => {
+                    assert!(matches!(b.asset_id, Cow::Borrowed("t1")));
+                    assert_eq!(b.market.as_deref(), Some("m1"));
+                    assert_eq!(best_level(&b.bids, PriceSide::Bid), Some((0.49, 5.0)));
+                    seen.push("book");
+                }
+                WsEvent::PriceChange(p) => {
+                    assert_eq!(p.price_changes[0].asset_id, "t2");
+                    assert_eq!(p.price_changes[0].best_bid, Some(0.5));
+                    assert_eq!(p.price_changes[0].best_ask, None);
+                    seen.push("price_change");
+                }
+                WsEvent::LastTrade(t) => {
+                    assert_eq!(t.asset_id, "t1");
+                    assert_eq!((t.price, t.size), (Some(0.5), Some(12.5)));
+                    assert_eq!(t.side, "SELL");
+                    assert_eq!(t.timestamp, Some(1_760_000_000_123));
+                    assert!(t.transaction_hash.is_empty());
+                    seen.push("last_trade");
+                }
+                WsEvent::Other => seen.push("other"),
+            }
+            Ok::<_, ()>(())
+        })
+        .unwrap();
+        assert!(skipped.is_empty());
+        assert_eq!(seen, ["book", "price_change", "last_trade", "other"]);
+
+        // A bad price in one event only drops that event.
+        let mixed = r#"[
+            {"event_type":"book","asset_id":"t1","bids":[{"price":"abc","size":"1"}]},
+            {"event_type":"book","asset_id":"t2","asks":[{"price":"0.6","size":"1"}]},
+            {"type":"subscribed"}
+        ]"#;
+        let mut books = Vec::new();
+        let skipped = for_each_event(mixed, |ev| {
+            if let WsEvent::Book(b) = ev {
+                books.push(b.asset_id.into_owned());
+            }
+            Ok::<_, ()>(())
+        })
+        .unwrap();
+        assert_eq!(books, ["t2"]);
+        assert_eq!(skipped.len(), 1);
+
+        let skipped = for_each_event("not json", |_| Ok::<_, ()>(())).unwrap();
+        assert_eq!(skipped.len(), 1);
+    }
+
+    #[test]
+    fn recorded_raw_ws_fixture_decodes_without_the_raw_path() {
+        let fixture = include_str!("../tests/fixtures/raw_ws_small/raw_ws.jsonl");
+        let mut books = 0usize;
+        for line in fixture.lines() {
+            let skipped = for_each_event(line, |ev| {
+                if matches!(ev, WsEvent::Book(_)) {
+                    books += 1;
+                }
+                Ok::<_, ()>(())
+            })
+            .unwrap();
+            assert!(skipped.is_empty(), "{line}: {skipped:?}");
+        }
+        assert!(books > 0);
+        let first = fixture.lines().next().unwrap();
+        assert_eq!(frame_ts_ms(first), Some(1_760_000_000_000));
+        assert_eq!(frame_ts_ms("PONG"), None);
+    }
+}
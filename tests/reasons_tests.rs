@@ -33,6 +33,10 @@ fn reason_code_display_is_strict() {
     assert_eq!(ShadowNoteReason::MissingBid.to_string(), "MISSING_BID");
     assert_eq!(ShadowNoteReason::InvalidPrice.to_string(), "INVALID_PRICE");
     assert_eq!(ShadowNoteReason::InvalidQty.to_string(), "INVALID_QTY");
+    assert_eq!(
+        ShadowNoteReason::TradeChannelDrop.to_string(),
+        "TRADE_CHANNEL_DROP"
+    );
 }
 
 #[test]
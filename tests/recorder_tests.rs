@@ -0,0 +1,366 @@
+use std::path::PathBuf;
+
+use razor::config::{CommitPolicy, FsyncPolicy, RecorderConfig, RecorderErrorPolicy};
+use razor::recorder::{
+    configure_group_commit, csv_input_paths, csv_sidecar_path, hour_partition, io_metrics_snapshot,
+    repair_torn_tail, CsvAppender, JsonlAppender, RotationPolicy,
+};
+
+/// `configure_group_commit` replaces the process-wide policies; tests that install their own
+/// hold this until their appenders are open.
+static RECORDER_CONFIG_LOCK: std::sync::Mutex<()> = std::sync::Mutex::new(());
+
+fn tmp_path(name: &str, ext: &str) -> PathBuf {
+    let mut p = std::env::temp_dir();
+    p.push(format!(
+        "razor_recorder_{name}_{}_{}.{ext}",
+        std::process::id(),
+        std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_nanos()
+    ));
+    p
+}
+
+fn file_key(p: &std::path::Path) -> String {
+    p.file_name().unwrap().to_string_lossy().to_string()
+}
+
+#[test]
+fn appenders_report_rows_bytes_and_fsyncs() {
+    let csv_path = tmp_path("io_csv", "csv");
+    let mut csv = CsvAppender::open(&csv_path, &["a", "b"]).expect("open csv");
+    csv.write_record(["1", "2"]).expect("write");
+    csv.write_record(["3", "4"]).expect("write");
+    csv.flush_and_sync().expect("sync");
+
+    let jsonl_path = tmp_path("io_jsonl", "jsonl");
+    let mut jsonl = JsonlAppender::open(&jsonl_path).expect("open jsonl");
+    jsonl.write_line("{}").expect("write");
+    jsonl.flush_and_sync().expect("sync");
+
+    let snap = io_metrics_snapshot();
+    let csv_m = snap
+        .iter()
+        .find(|m| m.file == file_key(&csv_path))
+        .expect("csv metrics");
+    assert_eq!(csv_m.rows, 2);
+    assert_eq!(csv_m.bytes, "a,b\n1,2\n3,4\n".len() as u64);
+    assert!(csv_m.fsyncs >= 1);
+    assert!(csv_m.fsync_p95_us >= 100);
+    assert_eq!(csv_m.write_errors, 0);
+
+    let jsonl_m = snap
+        .iter()
+        .find(|m| m.file == file_key(&jsonl_path))
+        .expect("jsonl metrics");
+    assert_eq!(jsonl_m.rows, 1);
+    assert_eq!(jsonl_m.bytes, 3);
+    assert_eq!(jsonl_m.fsyncs, 1);
+
+    let _ = std::fs::remove_file(csv_path);
+    let _ = std::fs::remove_file(jsonl_path);
+}
+
+#[test]
+fn partitioned_csv_writes_hour_dir_manifest_and_is_globbed() {
+    let run_dir = tmp_path("partitioned", "d");
+    std::fs::create_dir_all(&run_dir).unwrap();
+    assert_eq!(hour_partition(1_700_000_000_000), "2023-11-14/22");
+
+    let mut csv = CsvAppender::open_partitioned(run_dir.join("ticks.csv"), &["a", "b"])
+        .expect("open partitioned");
+    csv.write_record(["1", "2"]).expect("write");
+    csv.flush_and_sync().expect("sync");
+
+    // A flat file from an unpartitioned run segment is read first.
+    std::fs::write(run_dir.join("ticks.csv"), "a,b\n0,0\n").unwrap();
+    let paths = csv_input_paths(&run_dir, "ticks.csv");
+    assert_eq!(paths.len(), 2);
+    assert_eq!(paths[0], run_dir.join("ticks.csv"));
+    let part = paths[1].strip_prefix(&run_dir).unwrap();
+    assert_eq!(part.components().count(), 3);
+    assert_eq!(std::fs::read_to_string(&paths[1]).unwrap(), "a,b\n1,2\n");
+
+    let manifest = std::fs::read_to_string(run_dir.join("partitions.jsonl")).unwrap();
+    let line: serde_json::Value = serde_json::from_str(manifest.lines().next().unwrap()).unwrap();
+    assert_eq!(line["file"], "ticks.csv");
+    assert_eq!(
+        run_dir.join(line["path"].as_str().unwrap()),
+        paths[1],
+        "manifest path is relative to the run dir"
+    );
+    let _ = std::fs::remove_dir_all(&run_dir);
+}
+
+#[test]
+fn group_commit_bounds_loss_to_whole_uncommitted_rows() {
+    let csv_path = tmp_path("group_csv", "csv");
+    let jsonl_path = tmp_path("group_jsonl", "jsonl");
+    let mut cfg = RecorderConfig::default();
+    cfg.files.insert(
+        file_key(&csv_path),
+        CommitPolicy {
+            max_rows: 2,
+            flush_interval_ms: 60_000,
+            fsync_policy: FsyncPolicy::OnClose,
+        },
+    );
+    cfg.files.insert(
+        file_key(&jsonl_path),
+        CommitPolicy {
+            max_rows: 1_000,
+            flush_interval_ms: 50,
+            fsync_policy: FsyncPolicy::Interval,
+        },
+    );
+    let config_lock = RECORDER_CONFIG_LOCK
+        .lock()
+        .unwrap_or_else(|e| e.into_inner());
+    configure_group_commit(&cfg);
+
+    // Row-driven: nothing past the header reaches the file until max_rows are pending.
+    let mut csv = CsvAppender::open(&csv_path, &["a", "b"]).expect("open csv");
+    csv.write_record(["1", "2"]).expect("write");
+    assert_eq!(std::fs::read_to_string(&csv_path).unwrap(), "a,b\n");
+    csv.write_record(["3", "4"]).expect("write");
+    csv.write_record(["5", "6"]).expect("write");
+    // Simulated crash: the appender vanishes without flushing. Only the uncommitted third row
+    // is lost, and no partial line is left behind.
+    std::mem::forget(csv);
+    assert_eq!(
+        std::fs::read_to_string(&csv_path).unwrap(),
+        "a,b\n1,2\n3,4\n"
+    );
+
+    // Latency-driven: the background committer writes (and fsyncs) without further calls.
+    let mut jsonl = JsonlAppender::open(&jsonl_path).expect("open jsonl");
+    drop(config_lock);
+    jsonl.write_line("{\"n\":1}").expect("write");
+    assert_eq!(std::fs::read_to_string(&jsonl_path).unwrap(), "");
+    let fsyncs = || {
+        io_metrics_snapshot()
+            .into_iter()
+            .find(|m| m.file == file_key(&jsonl_path))
+            .map_or(0, |m| m.fsyncs)
+    };
+    let deadline = std::time::Instant::now() + std::time::Duration::from_secs(5);
+    while fsyncs() == 0 {
+        assert!(
+            std::time::Instant::now() < deadline,
+            "latency commit never ran"
+        );
+        std::thread::sleep(std::time::Duration::from_millis(10));
+    }
+    assert_eq!(std::fs::read_to_string(&jsonl_path).unwrap(), "{\"n\":1}\n");
+    drop(jsonl);
+    assert_eq!(fsyncs(), 1);
+
+    let _ = std::fs::remove_file(csv_path);
+    let _ = std::fs::remove_file(jsonl_path);
+}
+
+#[test]
+fn per_row_fsync_policy_syncs_every_row_and_legacy_keys_still_parse() {
+    let path = tmp_path("per_row", "jsonl");
+    let cfg = RecorderConfig {
+        flush_interval_ms: 60_000,
+        fsync_policy: FsyncPolicy::PerRow,
+        ..RecorderConfig::default()
+    };
+    let mut jsonl = {
+        let _lock = RECORDER_CONFIG_LOCK
+            .lock()
+            .unwrap_or_else(|e| e.into_inner());
+        configure_group_commit(&cfg);
+        let jsonl = JsonlAppender::open(&path).expect("open jsonl");
+        configure_group_commit(&RecorderConfig::default());
+        jsonl
+    };
+    jsonl.write_line("{\"n\":1}").expect("write");
+    jsonl.write_line("{\"n\":2}").expect("write");
+    assert_eq!(
+        std::fs::read_to_string(&path).unwrap(),
+        "{\"n\":1}\n{\"n\":2}\n"
+    );
+    let fsyncs = io_metrics_snapshot()
+        .into_iter()
+        .find(|m| m.file == file_key(&path))
+        .map_or(0, |m| m.fsyncs);
+    assert_eq!(fsyncs, 2);
+    drop(jsonl);
+    let _ = std::fs::remove_file(path);
+
+    let legacy: RecorderConfig = toml::from_str(
+        "commit_max_latency_ms = 250\ncommit_fsync = true\n\
+         [files.\"raw_ws.jsonl\"]\nmax_latency_ms = 2000\nfsync = false\n",
+    )
+    .unwrap();
+    assert_eq!(legacy.flush_interval_ms, 250);
+    assert_eq!(legacy.fsync_policy, FsyncPolicy::Interval);
+    let raw = legacy.files["raw_ws.jsonl"];
+    assert_eq!(raw.flush_interval_ms, 2000);
+    assert_eq!(raw.fsync_policy, FsyncPolicy::OnClose);
+    let err = toml::from_str::<RecorderConfig>("fsync_policy = \"sometimes\"\n").unwrap_err();
+    assert!(err.to_string().contains("per_row"), "{err}");
+}
+
+#[test]
+fn reopen_backs_up_and_truncates_torn_final_line() {
+    let csv_path = tmp_path("torn_csv", "csv");
+    std::fs::write(&csv_path, "a,b\n1,2\n3,").unwrap();
+    let mut csv = CsvAppender::open(&csv_path, &["a", "b"]).expect("open csv");
+    csv.write_record(["5", "6"]).expect("write");
+    csv.flush_and_sync().expect("sync");
+    assert_eq!(
+        std::fs::read_to_string(&csv_path).unwrap(),
+        "a,b\n1,2\n5,6\n"
+    );
+    let dir = csv_path.parent().unwrap();
+    let backup_prefix = format!("{}.torn_tail_", file_key(&csv_path));
+    let backup = std::fs::read_dir(dir)
+        .unwrap()
+        .flatten()
+        .find(|e| e.file_name().to_string_lossy().starts_with(&backup_prefix))
+        .expect("torn tail backup")
+        .path();
+    assert_eq!(std::fs::read_to_string(&backup).unwrap(), "3,");
+
+    // A file that is a single partial line is emptied; intact files are left alone.
+    let jsonl_path = tmp_path("torn_jsonl", "jsonl");
+    std::fs::write(&jsonl_path, "{\"n\":").unwrap();
+    let jsonl_backup = repair_torn_tail(&jsonl_path)
+        .expect("repair")
+        .expect("repaired");
+    assert_eq!(std::fs::read_to_string(&jsonl_path).unwrap(), "");
+    assert_eq!(std::fs::read_to_string(&jsonl_backup).unwrap(), "{\"n\":");
+    assert!(repair_torn_tail(&csv_path).unwrap().is_none());
+
+    for p in [csv_path, backup, jsonl_path, jsonl_backup] {
+        let _ = std::fs::remove_file(p);
+    }
+}
+
+#[test]
+fn csv_open_writes_self_describing_sidecar() {
+    let run_dir = tmp_path("sidecar", "d");
+    std::fs::create_dir_all(&run_dir).unwrap();
+    let path = run_dir.join("trades.csv");
+    let header = razor::schema::TRADES_HEADER;
+    let mut csv = CsvAppender::open(&path, &header).expect("open csv");
+    csv.flush_and_sync().expect("sync");
+
+    let sidecar = csv_sidecar_path(&path);
+    assert_eq!(file_key(&sidecar), "trades.csv.meta.json");
+    let meta: serde_json::Value =
+        serde_json::from_str(&std::fs::read_to_string(&sidecar).unwrap()).unwrap();
+    assert_eq!(meta["schema_version"], razor::schema::SCHEMA_VERSION);
+    assert_eq!(meta["file_version"], "v4");
+    assert_eq!(meta["producer"], "feed");
+    let cols = meta["columns"].as_array().unwrap();
+    assert_eq!(cols.len(), header.len());
+    assert_eq!(cols[0]["name"], "ts_ms");
+    assert_eq!(cols[0]["unit"], "unix_ms");
+    assert_eq!(cols[3]["unit"], "USDC per share");
+    assert_eq!(cols[5]["type"], "string");
+    assert!(cols[5]["unit"].is_null());
+
+    let _ = std::fs::remove_dir_all(&run_dir);
+}
+
+#[test]
+fn jsonl_rotates_on_interval_and_caps_total_bytes() {
+    let dir = tmp_path("rotate", "d");
+    std::fs::create_dir_all(&dir).unwrap();
+    let path = dir.join("raw_ws.jsonl");
+    let mut jsonl = JsonlAppender::open_with_policy(
+        &path,
+        RotationPolicy {
+            max_age_ms: Some(30),
+            max_total_bytes: Some(25),
+            ..RotationPolicy::default()
+        },
+    )
+    .expect("open jsonl");
+    let events = std::sync::Arc::new(std::sync::Mutex::new(Vec::new()));
+    let sink = events.clone();
+    jsonl.set_on_rotate(move |r| sink.lock().unwrap().push(r));
+
+    // Each line is 10 bytes; every write after the interval rotates the previous segment out.
+    for i in 0..4 {
+        jsonl
+            .write_line(&format!("{{\"n\":{i:03}}}"))
+            .expect("write");
+        jsonl.flush_and_sync().expect("sync");
+        std::thread::sleep(std::time::Duration::from_millis(40));
+    }
+    jsonl.write_line("{\"n\":999}").expect("write");
+
+    let events = events.lock().unwrap();
+    assert_eq!(events.len(), 4);
+    assert!(events
+        .iter()
+        .all(|e| e.reason == "interval" && e.segment_bytes == 10));
+    // live (0 bytes when rotating) + segments stay within 25 bytes: two segments survive.
+    let removed: usize = events.iter().map(|e| e.removed.len()).sum();
+    assert_eq!(removed, 2);
+    let segments = std::fs::read_dir(&dir)
+        .unwrap()
+        .flatten()
+        .filter(|e| e.file_name().to_string_lossy().contains(".rotated_"))
+        .count();
+    assert_eq!(segments, 2);
+
+    let _ = std::fs::remove_dir_all(&dir);
+}
+
+#[cfg(target_os = "linux")]
+#[test]
+fn degrade_policy_stops_writing_a_failing_file_without_erroring() {
+    // Every write to /dev/full fails with ENOSPC.
+    let full = std::path::Path::new("/dev/full");
+    let mut cfg = RecorderConfig::default();
+    cfg.files.insert(
+        "full".to_string(),
+        CommitPolicy {
+            max_rows: 1,
+            flush_interval_ms: 60_000,
+            fsync_policy: FsyncPolicy::OnClose,
+        },
+    );
+    cfg.on_write_error_files
+        .insert("full".to_string(), RecorderErrorPolicy::Degrade);
+    let mut jsonl = {
+        let _lock = RECORDER_CONFIG_LOCK
+            .lock()
+            .unwrap_or_else(|e| e.into_inner());
+        configure_group_commit(&cfg);
+        let jsonl = JsonlAppender::open(full).expect("open /dev/full");
+        configure_group_commit(&RecorderConfig::default());
+        jsonl
+    };
+
+    jsonl.write_line("{}").expect("degraded, not failed");
+    jsonl.write_line("{}").expect("dropped");
+    jsonl
+        .flush_and_sync()
+        .expect("degraded files skip the final sync");
+
+    let snap = io_metrics_snapshot()
+        .into_iter()
+        .find(|m| m.file == "full")
+        .expect("metrics for /dev/full");
+    assert!(snap.degraded_ts_ms.is_some());
+    assert_eq!(
+        snap.write_errors, 1,
+        "only the first write reached the file"
+    );
+    let degraded = razor::recorder::degraded_files();
+    assert_eq!(degraded.len(), 1);
+    assert!(
+        degraded[0].error.contains("group commit"),
+        "{}",
+        degraded[0].error
+    );
+}
@@ -20,6 +20,8 @@ fn replay_fixture_produces_expected_totals() -> anyhow::Result<()> {
         razor::replay::ReplayOptions {
             out_dir: out_dir.clone(),
             replay_run_id: replay_run_id.clone(),
+            source: razor::replay::ReplaySource::SnapshotsCsv,
+            speed: 0.0,
         },
     )?;
 
@@ -66,3 +68,52 @@ fn replay_fixture_produces_expected_totals() -> anyhow::Result<()> {
     let _ = std::fs::remove_dir_all(&out_dir);
     Ok(())
 }
+
+#[test]
+fn replay_speed_paces_wall_time_without_changing_output() -> anyhow::Result<()> {
+    let run_dir = PathBuf::from("tests/fixtures/replay_small");
+    let base = std::env::temp_dir().join(format!(
+        "razor_replay_speed_test_{}_{}",
+        std::process::id(),
+        razor::types::now_ms()
+    ));
+
+    let mut outputs = Vec::new();
+    for speed in [0.0, 1.0] {
+        let out_dir = base.join(format!("speed_{speed}"));
+        let started = std::time::Instant::now();
+        let res = razor::replay::run_replay(
+            &run_dir,
+            razor::replay::ReplayOptions {
+                out_dir: out_dir.clone(),
+                replay_run_id: "replay_speed".to_string(),
+                source: razor::replay::ReplaySource::SnapshotsCsv,
+                speed,
+            },
+        )?;
+        let elapsed = started.elapsed();
+        assert_eq!(res.shadow_rows, 1);
+        if speed > 0.0 {
+            // Snapshot at 1000 ms, trade at 1200 ms: 200 ms of virtual time at realtime.
+            assert!(elapsed >= std::time::Duration::from_millis(200));
+        }
+        outputs.push(std::fs::read_to_string(
+            out_dir.join(razor::replay::FILE_REPLAY_SHADOW_LOG),
+        )?);
+    }
+    assert_eq!(outputs[0], outputs[1]);
+
+    let bad = razor::replay::run_replay(
+        &run_dir,
+        razor::replay::ReplayOptions {
+            out_dir: base.join("bad"),
+            replay_run_id: "replay_speed".to_string(),
+            source: razor::replay::ReplaySource::SnapshotsCsv,
+            speed: -1.0,
+        },
+    );
+    assert!(bad.is_err());
+
+    let _ = std::fs::remove_dir_all(&base);
+    Ok(())
+}
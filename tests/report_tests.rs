@@ -149,3 +149,48 @@ fn bad_row_is_counted_and_ignored() {
     assert_eq!(report.totals.signals, 2);
     assert!((report.totals.total_shadow_pnl - 1.5).abs() < 1e-12);
 }
+
+#[test]
+fn execution_pnl_merges_complete_sets_per_phase() {
+    use razor::report::summarize_trade_log;
+    use razor::schema::TRADE_LOG_HEADER;
+
+    let line = |signal_id: u64, phase: &str, leg: i32, side: &str, price: f64, fill: f64| {
+        format!(
+            "1,{signal_id},m1,binary,liquid,{phase},FIRE_LEG1,{leg},t{leg},{side},{price},{fill},{fill},FULL,100,\n"
+        )
+    };
+    let csv = format!(
+        "{}\n{}{}{}{}{}{}",
+        TRADE_LOG_HEADER.join(","),
+        // Signal 1: both legs filled, one complete set, merge gas recorded by the sniper.
+        line(1, "LIVE", 0, "BUY", 0.40, 1.0),
+        line(1, "LIVE", 1, "BUY", 0.50, 1.0),
+        "1,1,m1,binary,liquid,LIVE,MERGE,-1,,SELL,0,1,0,NONE,100,merge_sets=1|gas_usdc=0.005000|gas_price_gwei=62.500|gas_source=oracle\n",
+        // Signal 2: leg 1 never filled, leg 0 flattened at a loss.
+        line(2, "LIVE", 0, "BUY", 0.40, 1.0),
+        line(2, "LIVE", 0, "SELL", 0.30, 1.0),
+        line(3, "SIM", 0, "BUY", 0.40, 2.0),
+    );
+    let path = tmp_csv("trade_log", &csv);
+
+    let by_phase = summarize_trade_log(&path).expect("summary");
+    let live = &by_phase["LIVE"];
+    assert_eq!((live.signals, live.fills), (2, 4));
+    assert!((live.merged_sets - 1.0).abs() < 1e-12);
+    let buys = (0.40 + 0.50 + 0.40) * 1.02;
+    let sells = 0.30 * 0.98;
+    let merge = 0.999;
+    assert!((live.pnl - (merge + sells - buys)).abs() < 1e-9, "{live:?}");
+    assert!(live.open_qty.abs() < 1e-12);
+    assert_eq!((live.merge_txs, live.redeem_txs), (1, 0));
+    assert!((live.gas_cost - 0.005).abs() < 1e-12, "{live:?}");
+    assert!((live.net_pnl - (live.pnl - 0.005)).abs() < 1e-12);
+
+    // No MERGE row: the open position is redeemed at the static [gas] estimate.
+    let sim = &by_phase["SIM"];
+    assert_eq!(sim.merged_sets, 0.0);
+    assert!((sim.open_qty - 2.0).abs() < 1e-12);
+    assert_eq!((sim.merge_txs, sim.redeem_txs), (0, 1));
+    assert!((sim.gas_cost - 0.003).abs() < 1e-12, "{sim:?}");
+}
@@ -1,7 +1,5 @@
-use assert_approx_eq::assert_approx_eq;
-
 use razor::trade_store::TradeStore;
-use razor::types::TradeTick;
+use razor::types::{Price, Qty, TradeTick};
 
 #[test]
 fn token_filter_is_strict() {
@@ -11,35 +9,44 @@ fn token_filter_is_strict() {
         ts_ms: 1_000,
         ingest_ts_ms: 1_000,
         exchange_ts_ms: Some(1_000),
-        market_id: "m".to_string(),
-        token_id: "A".to_string(),
-        price: 0.5,
-        size: 1.0,
+        market_id: "m".into(),
+        token_id: "A".into(),
+        price: Price::from_f64(0.5).unwrap(),
+        size: Qty::from_f64(1.0).unwrap(),
         trade_id: "t1".to_string(),
+        side: None,
     });
     store.push(TradeTick {
         ts_ms: 1_010,
         ingest_ts_ms: 1_010,
         exchange_ts_ms: Some(1_010),
-        market_id: "m".to_string(),
-        token_id: "A".to_string(),
-        price: 0.5,
-        size: 2.0,
+        market_id: "m".into(),
+        token_id: "A".into(),
+        price: Price::from_f64(0.5).unwrap(),
+        size: Qty::from_f64(2.0).unwrap(),
         trade_id: "t2".to_string(),
+        side: None,
     });
     store.push(TradeTick {
         ts_ms: 1_020,
         ingest_ts_ms: 1_020,
         exchange_ts_ms: Some(1_020),
-        market_id: "m".to_string(),
-        token_id: "B".to_string(),
-        price: 0.5,
-        size: 10.0,
+        market_id: "m".into(),
+        token_id: "B".into(),
+        price: Price::from_f64(0.5).unwrap(),
+        size: Qty::from_f64(10.0).unwrap(),
         trade_id: "t3".to_string(),
+        side: None,
     });
 
-    let v = store.volume_at_or_better_price("m", "A", 1_000, 1_100, 0.6);
-    assert_approx_eq!(v, 3.0, 1e-12);
+    let v = store.volume_at_or_better_price(
+        &"m".into(),
+        &"A".into(),
+        1_000,
+        1_100,
+        Price::from_f64(0.6).unwrap(),
+    );
+    assert_eq!(v, Qty::from_f64(3.0).unwrap());
 }
 
 #[test]
@@ -51,46 +58,56 @@ fn price_limit_and_window_filters_apply() {
         ts_ms: 1_000,
         ingest_ts_ms: 1_000,
         exchange_ts_ms: Some(1_000),
-        market_id: "m".to_string(),
-        token_id: "A".to_string(),
-        price: 0.49,
-        size: 1.0,
+        market_id: "m".into(),
+        token_id: "A".into(),
+        price: Price::from_f64(0.49).unwrap(),
+        size: Qty::from_f64(1.0).unwrap(),
         trade_id: "t1".to_string(),
+        side: None,
     });
     // In window, price <= limit
     store.push(TradeTick {
         ts_ms: 1_100,
         ingest_ts_ms: 1_100,
         exchange_ts_ms: Some(1_100),
-        market_id: "m".to_string(),
-        token_id: "A".to_string(),
-        price: 0.50,
-        size: 2.0,
+        market_id: "m".into(),
+        token_id: "A".into(),
+        price: Price::from_f64(0.50).unwrap(),
+        size: Qty::from_f64(2.0).unwrap(),
         trade_id: "t2".to_string(),
+        side: None,
     });
     // In window, price > limit
     store.push(TradeTick {
         ts_ms: 1_050,
         ingest_ts_ms: 1_050,
         exchange_ts_ms: Some(1_050),
-        market_id: "m".to_string(),
-        token_id: "A".to_string(),
-        price: 0.51,
-        size: 100.0,
+        market_id: "m".into(),
+        token_id: "A".into(),
+        price: Price::from_f64(0.51).unwrap(),
+        size: Qty::from_f64(100.0).unwrap(),
         trade_id: "t3".to_string(),
+        side: None,
     });
     // Out of window, price <= limit
     store.push(TradeTick {
         ts_ms: 999,
         ingest_ts_ms: 999,
         exchange_ts_ms: Some(999),
-        market_id: "m".to_string(),
-        token_id: "A".to_string(),
-        price: 0.49,
-        size: 100.0,
+        market_id: "m".into(),
+        token_id: "A".into(),
+        price: Price::from_f64(0.49).unwrap(),
+        size: Qty::from_f64(100.0).unwrap(),
         trade_id: "t4".to_string(),
+        side: None,
     });
 
-    let v = store.volume_at_or_better_price("m", "A", 1_000, 1_100, 0.50);
-    assert_approx_eq!(v, 3.0, 1e-12);
+    let v = store.volume_at_or_better_price(
+        &"m".into(),
+        &"A".into(),
+        1_000,
+        1_100,
+        Price::from_f64(0.50).unwrap(),
+    );
+    assert_eq!(v, Qty::from_f64(3.0).unwrap());
 }